@@ -26,8 +26,13 @@ pub enum Command {
     /// configuration. You just specify a ROM, and Z2L will run this in an emulated RISC-V system,
     /// like so: `z2l run-quick my_rom.bin`
     ///
-    /// The ROM will be loaded at address `0x00000000` of the address space, and execution will also
-    /// start at this point. By default, 32KiB of RAM will be accessible from address `0x80000000`,
-    /// but the size of this RAM is customisable.
+    /// A raw binary ROM will be loaded at address `0x00000000` of the address space, and execution
+    /// will also start at this point. If the ROM is an ELF file, each of its segments is loaded at
+    /// the address it specifies, and execution starts at its entry point. By default, 32KiB of RAM
+    /// will be accessible from address `0x80000000`, but the size of this RAM is customisable. A
+    /// serial port can optionally be attached to the host terminal, accessible from address
+    /// `0x10000000`. Rather than using the TUI, the ROM can be debugged with GDB by specifying
+    /// `--gdb <port>`. ELF64 files are run on an RV64I hart, and everything else on RV32I, unless
+    /// `--xlen` or `--isa` is given.
     RunQuick(RunQuickArgs),
 }
@@ -4,17 +4,32 @@ use crate::tui;
 use bus::{Bus, BusReader};
 use clap::Args;
 use cursive::CursiveExt;
-use std::fs::File;
+use std::io::Cursor;
+use std::net::TcpListener;
+#[cfg(unix)]
+use std::os::unix::net::UnixListener;
 use std::path::PathBuf;
 use std::time::Duration;
 use z2l_core::clock::{Clock, FixedClock, FreeClock, ManualClock};
-use z2l_core::{Config, ControlMessage, ExecutionEnvironment};
-use z2l_isa::rv32i::RV32I;
+use z2l_core::elf::Elf;
+use z2l_core::entropy::{EntropySource, HostEntropySource, SeededEntropySource};
+use z2l_core::extension::Extension;
+use z2l_core::gdb::GdbStub;
+use z2l_core::processor::Xlen;
+use z2l_core::uart::{SerialBackend, StdioBackend};
+use z2l_core::{Config, ControlMessage, ExecutionEnvironment, InstructionLog};
+use z2l_isa::registry::Registry;
+use z2l_isa::zicbom::Zicbom;
+use z2l_isa::zicboz::Zicboz;
+use z2l_isa::zkr::Zkr;
+use z2l_isa::zve32x::Zve32x;
 
 /// Arguments for the `run-quick` command.
 #[derive(Args, Clone, Debug, Hash)]
 pub struct RunQuickArgs {
     /// Path to RISC-V binary to execute.
+    ///
+    /// This may be either an ELF file, or a raw binary. ELF files are detected automatically.
     rom: PathBuf,
 
     /// Amount of memory to allocate for RAM.
@@ -33,6 +48,58 @@ pub struct RunQuickArgs {
     /// clock in HZ; or run as fast as possible, by specifying this value as "free".
     #[arg(short, long, default_value_t = String::from("manual"))]
     clock: String,
+
+    /// Serial port connection.
+    ///
+    /// By default, no serial port is attached. If this is "stdio", an NS16550A-compatible UART is
+    /// accessible from memory address 0x10000000, connected to the host terminal's stdin & stdout.
+    /// The TUI is not shown in this mode, so a clock other than "manual" must be selected.
+    #[arg(short, long, default_value_t = String::from("none"))]
+    serial: String,
+
+    /// Serve the GDB remote serial protocol.
+    ///
+    /// If this is a port number, Z2L waits for a debugger to connect to that port on localhost;
+    /// otherwise, it is interpreted as the path of a Unix socket to listen on. The TUI is not shown
+    /// in this mode: The processor is stepped & continued by the debugger instead, so unless
+    /// another clock is selected, the processor runs as fast as possible when continued.
+    #[arg(short, long)]
+    gdb: Option<String>,
+
+    /// Width of the integer registers.
+    ///
+    /// This may be "32" to use the RV32I base instruction set, or "64" for RV64I. By default, it is
+    /// taken from the class of an ELF file, and raw binaries are run as RV32I.
+    #[arg(short, long, default_value_t = String::from("auto"))]
+    xlen: String,
+
+    /// ISA string selecting the base instruction set & extensions to implement.
+    ///
+    /// For example, "rv64gc_zba" selects RV64I with the M, A, F, D, C, Zicsr, Zifencei, and Zba
+    /// extensions. Extensions must be listed in the canonical order given by the RISC-V spec. By
+    /// default, no extensions are enabled, and the base instruction set is chosen by `--xlen`.
+    #[arg(short, long)]
+    isa: Option<String>,
+
+    /// Entropy source for the Zkr `seed` CSR.
+    ///
+    /// By default, entropy is drawn from the host's random number generator. Alternatively, a
+    /// number can be specified to seed a deterministic pseudorandom generator, so that the guest
+    /// sees the same "entropy" on every run.
+    #[arg(short, long, default_value_t = String::from("host"))]
+    entropy: String,
+
+    /// Width of the vector registers, in bits (VLEN).
+    ///
+    /// This must be a power of 2 between 32 and 65536.
+    #[arg(long, default_value_t = 128)]
+    vlen: usize,
+
+    /// Size of the cache blocks operated on by the Zicbom & Zicboz instructions, in bytes.
+    ///
+    /// This must be a power of 2 between 8 and 4096.
+    #[arg(long, default_value_t = 64)]
+    cache_block_size: usize,
 }
 
 /// Parse memory size.
@@ -75,20 +142,99 @@ pub fn parse_clock(clock: &str, control_rx: BusReader<ControlMessage>) -> Box<dy
     }
 }
 
+/// Parse a serial port selection.
+///
+/// The user may specify "none", for no serial port, or "stdio", to connect the serial port to the
+/// host terminal.
+pub fn parse_serial(serial: &str) -> Option<Box<dyn SerialBackend>> {
+    match serial {
+        "none" => None,
+        "stdio" => Some(Box::new(StdioBackend::new())),
+        _ => panic!("Invalid serial specification"),
+    }
+}
+
+/// Parse an entropy source selection.
+///
+/// The user may specify "host", to use the host's random number generator, or a number to seed a
+/// deterministic source.
+pub fn parse_entropy(entropy: &str) -> Box<dyn EntropySource> {
+    if entropy == "host" {
+        Box::new(HostEntropySource::new())
+    } else {
+        let seed = entropy.parse().expect("Invalid entropy specification");
+        Box::new(SeededEntropySource::new(seed))
+    }
+}
+
+/// Parse a register width selection.
+///
+/// The user may specify "32", "64", or "auto" to detect it from the ROM.
+pub fn parse_xlen(xlen: &str, rom: &[u8]) -> Xlen {
+    match xlen {
+        "32" => Xlen::Rv32,
+        "64" => Xlen::Rv64,
+        "auto" if Elf::parse(rom).is_ok_and(|elf| elf.is_64) => Xlen::Rv64,
+        "auto" => Xlen::Rv32,
+        _ => panic!("Invalid XLEN specification"),
+    }
+}
+
+/// Parse an ISA string, picking the base instruction set & extensions to use.
+///
+/// If no ISA string is specified, only the base instruction set selected by [`parse_xlen`] is used.
+/// Otherwise, the XLEN must be "auto", or agree with the ISA string.
+pub fn parse_isa(
+    isa: Option<&str>,
+    xlen: &str,
+    rom: &[u8],
+    registry: &Registry,
+) -> Vec<Box<dyn Extension>> {
+    let isa = match isa {
+        Some(isa) => {
+            let prefix = format!("rv{}", xlen);
+            if xlen != "auto" && !isa.to_lowercase().starts_with(&prefix) {
+                panic!("Invalid ISA specification: ISA string conflicts with the selected XLEN");
+            }
+            isa.to_owned()
+        }
+        None => format!("rv{}i", parse_xlen(xlen, rom).bits()),
+    };
+    registry
+        .parse(&isa)
+        .unwrap_or_else(|err| panic!("Invalid ISA specification: {}", err))
+}
+
 /// Create the [`ExecutionEnvironment`] to run the ROM.
 pub fn create_execution_env(
     args: &RunQuickArgs,
     control_bus: &mut Bus<ControlMessage>,
 ) -> ExecutionEnvironment<Box<dyn Clock>> {
-    let rom = File::open(&args.rom).expect("Failed to open ROM file");
+    let rom = std::fs::read(&args.rom).expect("Failed to open ROM file");
+    let mut registry = Registry::default();
+    let block_size = args.cache_block_size;
+    registry.register("Zicbom", move || Box::new(Zicbom::new(block_size)));
+    registry.register("Zicboz", move || Box::new(Zicboz::new(block_size)));
+    let entropy = args.entropy.clone();
+    registry.register("Zkr", move || Box::new(Zkr::new(parse_entropy(&entropy))));
+    let vlen = args.vlen;
+    registry.register("Zve32x", move || Box::new(Zve32x::new(vlen)));
+    let extensions = parse_isa(args.isa.as_deref(), &args.xlen, &rom, &registry);
+
     let ram_size = parse_memory(&args.memory);
-    let clock = parse_clock(&args.clock, control_bus.add_rx());
+    let clock = if args.gdb.is_some() && args.clock == "manual" {
+        // The debugger replaces the manual clock
+        Box::new(FreeClock::new())
+    } else {
+        parse_clock(&args.clock, control_bus.add_rx())
+    };
 
     let config = Config {
         harts: 1,
-        extensions: vec![Box::new(RV32I)],
-        rom,
+        extensions,
+        rom: Cursor::new(rom),
         ram_size,
+        serial: parse_serial(&args.serial),
         clock,
         control_rx: control_bus.add_rx(),
     };
@@ -96,16 +242,79 @@ pub fn create_execution_env(
     ExecutionEnvironment::new(config).unwrap()
 }
 
+/// Wait for a debugger to connect, then serve it until it disconnects.
+///
+/// The debugger may connect over TCP, if `gdb` is a port number, or otherwise a Unix socket.
+pub fn serve_gdb<C: Clock>(gdb: &str, env: &mut ExecutionEnvironment<C>) {
+    if let Ok(port) = gdb.parse::<u16>() {
+        let listener =
+            TcpListener::bind(("127.0.0.1", port)).expect("Failed to listen for GDB connection");
+        eprintln!("Waiting for GDB connection on port {}...", port);
+        let (stream, _) = listener.accept().expect("Failed to accept GDB connection");
+        stream.set_nodelay(true).unwrap();
+        GdbStub::new(env, stream)
+            .serve()
+            .expect("GDB connection failed");
+    } else {
+        serve_gdb_unix(gdb, env);
+    }
+}
+
+/// Wait for a debugger to connect to a Unix socket, then serve it until it disconnects.
+#[cfg(unix)]
+fn serve_gdb_unix<C: Clock>(path: &str, env: &mut ExecutionEnvironment<C>) {
+    let listener = UnixListener::bind(path).expect("Failed to listen for GDB connection");
+    eprintln!("Waiting for GDB connection on {}...", path);
+    let (stream, _) = listener.accept().expect("Failed to accept GDB connection");
+    let result = GdbStub::new(env, stream).serve();
+    let _ = std::fs::remove_file(path);
+    result.expect("GDB connection failed");
+}
+
+/// Unix sockets are unavailable on this platform.
+#[cfg(not(unix))]
+fn serve_gdb_unix<C: Clock>(_path: &str, _env: &mut ExecutionEnvironment<C>) {
+    panic!("Invalid GDB specification: Unix sockets are not supported on this platform");
+}
+
 /// Execute the `run-quick` command.
+///
+/// If the serial port is connected to the host terminal, the TUI cannot also be shown, so the
+/// processor runs headless until it encounters an unhandled exception. If a debugger is used, the
+/// TUI is also not shown, and the processor is controlled by the debugger.
 pub fn execute(args: RunQuickArgs) {
+    if let Some(gdb) = &args.gdb {
+        let mut control_bus = bus::Bus::new(0xffff);
+        let mut env = create_execution_env(&args, &mut control_bus);
+        serve_gdb(gdb, &mut env);
+        return;
+    }
+
+    let headless = args.serial == "stdio";
+    if headless && args.clock == "manual" {
+        panic!(
+            "The manual clock requires the TUI: Select another clock to use the stdio serial port"
+        );
+    }
+
     let mut control_bus = bus::Bus::new(0xffff);
 
     let mut env = create_execution_env(&args, &mut control_bus);
-    let log_rx = env.add_rx();
+    let mut log_rx = env.add_rx();
 
     let env_handle = std::thread::spawn(move || {
         env.run();
     });
+
+    if headless {
+        while let Ok(log) = log_rx.recv() {
+            if let InstructionLog::Exception { exception, pc, .. } = log {
+                eprintln!("Encountered exception at {:x}: {:?}", pc, exception);
+            }
+        }
+        env_handle.join().unwrap();
+        return;
+    }
     let tui_handle = std::thread::spawn(move || {
         let mut tui = tui::create(control_bus, log_rx);
         tui.run();
@@ -71,21 +71,21 @@ impl Z2LView {
     }
 
     /// Update the registers to show the current values.
-    fn update_registers(&mut self, registers: &[i32]) {
+    fn update_registers(&mut self, registers: &[i64]) {
         for (i, value) in registers.iter().enumerate() {
             self.call_on_any(
                 &Selector::Name(&format!("reg{}", i)),
                 &mut |reg: &mut dyn View| {
-                    Self::update_dyn_textview(reg, &format!("{:08x}", value));
+                    Self::update_dyn_textview(reg, &format!("{:016x}", value));
                 },
             )
         }
     }
 
     /// Update the program counter to show the current value.
-    fn update_pc(&mut self, value: u32) {
+    fn update_pc(&mut self, value: u64) {
         self.call_on_any(&Selector::Name("pc"), &mut |pc: &mut dyn View| {
-            Self::update_dyn_textview(pc, &format!("{:08x}", value));
+            Self::update_dyn_textview(pc, &format!("{:016x}", value));
         });
     }
 
@@ -219,11 +219,11 @@ fn registers() -> Panel<NamedView<ScrollView<LinearLayout>>> {
     let mut layout = LinearLayout::horizontal();
 
     layout =
-        layout.child(Panel::new(TextView::new(format!("{:08x}", 0)).with_name("pc")).title("PC"));
+        layout.child(Panel::new(TextView::new(format!("{:016x}", 0)).with_name("pc")).title("PC"));
 
     for i in 0..32 {
         layout = layout.child(
-            Panel::new(TextView::new(format!("{:08x}", 0)).with_name(format!("reg{}", i)))
+            Panel::new(TextView::new(format!("{:016x}", 0)).with_name(format!("reg{}", i)))
                 .title(format!("x{}", i)),
         );
     }
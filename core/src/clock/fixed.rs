@@ -57,7 +57,7 @@ impl Clock for FixedClock {
 
         trace!("Ticking {:?} after last tick", elapsed);
 
-        self.prev_tick = self.prev_tick + wait_period;
+        self.prev_tick += wait_period;
 
         if missed == 0 {
             trace!("Tick");
@@ -176,4 +176,3 @@ mod tests {
         assert!(reset.elapsed() < PERIOD_END);
     }
 }
-
@@ -18,6 +18,12 @@ impl FreeClock {
     }
 }
 
+impl Default for FreeClock {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl Clock for FreeClock {
     #[inline(always)]
     fn next_tick(&mut self) -> ClockStatus {
@@ -43,4 +49,3 @@ mod tests {
         assert!(start.elapsed() < Duration::from_millis(1));
     }
 }
-
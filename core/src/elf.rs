@@ -0,0 +1,520 @@
+//! Executable and Linkable Format (ELF) loader.
+//!
+//! Toolchains produce ELF files, which describe where each part of a program should be placed in
+//! memory, rather than raw memory images. This module parses 32-bit and 64-bit little-endian RISC-V
+//! ELF files, so they can be run without first being converted to a raw binary with `objcopy`.
+//!
+//! Each loadable (`PT_LOAD`) segment is placed at its physical address, in whichever device is
+//! mapped there by the [`MMU`]: Code in the ROM, initialised data in RAM, etc. Any portion of a
+//! segment which is not present in the file (e.g: `.bss`) is zeroed. The entry point and symbol
+//! table are also retained, so the processor can start at the entry point, and other tooling (e.g.
+//! a debugger) can look up symbols.
+
+use crate::error::ElfError;
+use crate::mmu::MMU;
+
+/// ELF magic number, found at the start of every ELF file.
+const MAGIC: &[u8; 4] = b"\x7fELF";
+
+/// `EI_CLASS` value for 32-bit files.
+const CLASS_32: u8 = 1;
+
+/// `EI_CLASS` value for 64-bit files.
+const CLASS_64: u8 = 2;
+
+/// `EI_DATA` value for little-endian files.
+const DATA_LSB: u8 = 1;
+
+/// `e_machine` value for RISC-V.
+const EM_RISCV: u16 = 243;
+
+/// Program header type of a loadable segment.
+const PT_LOAD: u32 = 1;
+
+/// Section header type of a symbol table.
+const SHT_SYMTAB: u32 = 2;
+
+/// A loadable segment of an ELF file.
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+pub struct Segment {
+    /// Physical address at which the segment should be loaded.
+    pub addr: usize,
+
+    /// Contents of the segment present in the file.
+    pub data: Vec<u8>,
+
+    /// Size of the segment in memory, in bytes.
+    ///
+    /// If this is larger than `data`, the remainder of the segment is zeroed.
+    pub size: usize,
+}
+
+impl Segment {
+    /// Address immediately following the end of this segment.
+    pub fn end(&self) -> usize {
+        self.addr + self.size
+    }
+}
+
+/// An entry in the symbol table of an ELF file.
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+pub struct Symbol {
+    /// Name of the symbol.
+    pub name: String,
+
+    /// Value of the symbol: For functions & data, this is its address.
+    pub value: u64,
+
+    /// Size of the object the symbol refers to, in bytes.
+    pub size: u64,
+}
+
+/// A parsed ELF file.
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+pub struct Elf {
+    /// Whether this is a 64-bit ELF file.
+    pub is_64: bool,
+
+    /// Address at which execution should begin.
+    pub entry: usize,
+
+    /// Loadable segments, in the order they appear in the program header table.
+    pub segments: Vec<Segment>,
+
+    /// Named symbols from the symbol table, if the file has one.
+    pub symbols: Vec<Symbol>,
+}
+
+/// Little-endian reader for the fields of an ELF file.
+struct Reader<'a> {
+    bytes: &'a [u8],
+    is_64: bool,
+    /// Offset in the file which fields are read relative to.
+    base: u64,
+}
+
+impl<'a> Reader<'a> {
+    /// Get a reader for the structure at `offset`, whose fields are read relative to its start.
+    fn at(&self, offset: u64) -> Result<Self, ElfError> {
+        Ok(Self {
+            base: self.base.checked_add(offset).ok_or(ElfError::Truncated)?,
+            ..*self
+        })
+    }
+
+    /// Get a reader for entry `index` of the table at `offset`, with entries of `entsize` bytes.
+    fn entry(&self, offset: u64, index: u64, entsize: u64) -> Result<Self, ElfError> {
+        let entry = index.checked_mul(entsize).ok_or(ElfError::Truncated)?;
+        self.at(offset)?.at(entry)
+    }
+
+    /// Get `len` bytes starting at `offset`.
+    fn bytes(&self, offset: u64, len: u64) -> Result<&'a [u8], ElfError> {
+        let offset = self.base.checked_add(offset).ok_or(ElfError::Truncated)?;
+        let start = usize::try_from(offset).map_err(|_| ElfError::Truncated)?;
+        let len = usize::try_from(len).map_err(|_| ElfError::Truncated)?;
+        let end = start.checked_add(len).ok_or(ElfError::Truncated)?;
+        self.bytes.get(start..end).ok_or(ElfError::Truncated)
+    }
+
+    fn u8(&self, offset: u64) -> Result<u8, ElfError> {
+        Ok(self.bytes(offset, 1)?[0])
+    }
+
+    fn u16(&self, offset: u64) -> Result<u16, ElfError> {
+        Ok(u16::from_le_bytes(
+            self.bytes(offset, 2)?.try_into().unwrap(),
+        ))
+    }
+
+    fn u32(&self, offset: u64) -> Result<u32, ElfError> {
+        Ok(u32::from_le_bytes(
+            self.bytes(offset, 4)?.try_into().unwrap(),
+        ))
+    }
+
+    fn u64(&self, offset: u64) -> Result<u64, ElfError> {
+        Ok(u64::from_le_bytes(
+            self.bytes(offset, 8)?.try_into().unwrap(),
+        ))
+    }
+
+    /// Read an address/offset-sized field: 32 bits for 32-bit files, or 64 bits for 64-bit files.
+    fn word(&self, offset: u64) -> Result<u64, ElfError> {
+        if self.is_64 {
+            self.u64(offset)
+        } else {
+            self.u32(offset).map(u64::from)
+        }
+    }
+
+    /// Read a null-terminated string starting at `offset`.
+    fn string(&self, offset: u64) -> Result<String, ElfError> {
+        let offset = self.base.checked_add(offset).ok_or(ElfError::Truncated)?;
+        let start = usize::try_from(offset).map_err(|_| ElfError::Truncated)?;
+        let bytes = self.bytes.get(start..).ok_or(ElfError::Truncated)?;
+        let len = bytes
+            .iter()
+            .position(|b| *b == 0)
+            .ok_or(ElfError::Truncated)?;
+        Ok(String::from_utf8_lossy(&bytes[..len]).into_owned())
+    }
+}
+
+/// Convert an address from an ELF file to an address in the address space.
+fn address(value: u64) -> Result<usize, ElfError> {
+    u32::try_from(value)
+        .map(|addr| addr as usize)
+        .map_err(|_| ElfError::AddressOverflow)
+}
+
+impl Elf {
+    /// Returns true if `bytes` starts with the ELF magic number.
+    pub fn is_elf(bytes: &[u8]) -> bool {
+        bytes.starts_with(MAGIC)
+    }
+
+    /// Parse an ELF file.
+    pub fn parse(bytes: &[u8]) -> Result<Self, ElfError> {
+        if !Self::is_elf(bytes) {
+            return Err(ElfError::NotElf);
+        }
+
+        let mut reader = Reader {
+            bytes,
+            is_64: false,
+            base: 0,
+        };
+        reader.is_64 = match reader.u8(4)? {
+            CLASS_32 => false,
+            CLASS_64 => true,
+            _ => return Err(ElfError::UnsupportedClass),
+        };
+        if reader.u8(5)? != DATA_LSB {
+            return Err(ElfError::UnsupportedEncoding);
+        }
+        if reader.u16(18)? != EM_RISCV {
+            return Err(ElfError::UnsupportedMachine);
+        }
+
+        // Offsets of the remaining ELF header fields depend on the size of an address
+        let (phoff, shoff, tables) = if reader.is_64 {
+            (32, 40, 54)
+        } else {
+            (28, 32, 42)
+        };
+        let entry = address(reader.word(24)?)?;
+        let phoff = reader.word(phoff)?;
+        let shoff = reader.word(shoff)?;
+        let phentsize = reader.u16(tables)? as u64;
+        let phnum = reader.u16(tables + 2)? as u64;
+        let shentsize = reader.u16(tables + 4)? as u64;
+        let shnum = reader.u16(tables + 6)? as u64;
+
+        let segments = (0..phnum)
+            .map(|i| Self::parse_segment(&reader, &reader.entry(phoff, i, phentsize)?))
+            .filter_map(Result::transpose)
+            .collect::<Result<_, _>>()?;
+
+        let mut symbols = Vec::new();
+        for i in 0..shnum {
+            let header = reader.entry(shoff, i, shentsize)?;
+            if header.u32(4)? == SHT_SYMTAB {
+                symbols.extend(Self::parse_symbols(&reader, shoff, shentsize, &header)?);
+            }
+        }
+
+        Ok(Self {
+            is_64: reader.is_64,
+            entry,
+            segments,
+            symbols,
+        })
+    }
+
+    /// Parse the program header read by `header`, returning the segment it describes if it is
+    /// loadable.
+    fn parse_segment(reader: &Reader, header: &Reader) -> Result<Option<Segment>, ElfError> {
+        if header.u32(0)? != PT_LOAD {
+            return Ok(None);
+        }
+
+        let (offset, paddr, filesz, memsz) = if reader.is_64 {
+            (
+                header.u64(8)?,
+                header.u64(24)?,
+                header.u64(32)?,
+                header.u64(40)?,
+            )
+        } else {
+            (
+                header.word(4)?,
+                header.word(12)?,
+                header.word(16)?,
+                header.word(20)?,
+            )
+        };
+
+        let addr = address(paddr)?;
+        let size = address(memsz.max(filesz))?;
+        addr.checked_add(size)
+            .filter(|end| *end <= 1 << 32)
+            .ok_or(ElfError::AddressOverflow)?;
+
+        Ok(Some(Segment {
+            addr,
+            data: reader.bytes(offset, filesz)?.to_vec(),
+            size,
+        }))
+    }
+
+    /// Parse the symbol table described by the section header read by `header`.
+    ///
+    /// Unnamed symbols are skipped.
+    fn parse_symbols(
+        reader: &Reader,
+        shoff: u64,
+        shentsize: u64,
+        header: &Reader,
+    ) -> Result<Vec<Symbol>, ElfError> {
+        let (offset, size, link, entsize) = if reader.is_64 {
+            (
+                header.u64(24)?,
+                header.u64(32)?,
+                header.u32(40)?,
+                header.u64(56)?,
+            )
+        } else {
+            (
+                header.word(16)?,
+                header.word(20)?,
+                header.u32(24)?,
+                header.word(36)?,
+            )
+        };
+
+        // The linked section is the string table holding the symbol names
+        let strtab_header = reader.entry(shoff, link as u64, shentsize)?;
+        let strtab = if reader.is_64 {
+            strtab_header.u64(24)?
+        } else {
+            strtab_header.word(16)?
+        };
+        let strtab = reader.at(strtab)?;
+
+        let mut symbols = Vec::new();
+        for i in 0..size.checked_div(entsize).unwrap_or(0) {
+            let entry = reader.entry(offset, i, entsize)?;
+            let name = entry.u32(0)?;
+            if name == 0 {
+                continue;
+            }
+
+            let (value, size) = if reader.is_64 {
+                (entry.u64(8)?, entry.u64(16)?)
+            } else {
+                (entry.word(4)?, entry.word(8)?)
+            };
+
+            symbols.push(Symbol {
+                name: strtab.string(name as u64)?,
+                value,
+                size,
+            });
+        }
+
+        Ok(symbols)
+    }
+
+    /// Find the symbol with the provided name.
+    pub fn symbol(&self, name: &str) -> Option<&Symbol> {
+        self.symbols.iter().find(|symbol| symbol.name == name)
+    }
+
+    /// Load each segment of this file into the devices mapped by the provided MMU.
+    ///
+    /// Returns an error if any part of a segment is not mapped to a device which can be
+    /// initialised.
+    pub fn load(&self, mmu: &mut MMU) -> Result<(), ElfError> {
+        for segment in &self.segments {
+            mmu.preload(segment.addr, &segment.data)
+                .map_err(|_| ElfError::Unloadable(segment.addr))?;
+
+            let bss = segment.addr + segment.data.len();
+            mmu.preload(bss, &vec![0; segment.size - segment.data.len()])
+                .map_err(|_| ElfError::Unloadable(bss))?;
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Elf, Segment};
+    use crate::error::ElfError;
+    use crate::mmu::MMU;
+    use crate::ram::RAM;
+    use crate::rom::ROM;
+
+    /// Build a 32-bit RISC-V ELF file with a text segment at 0x0, a data segment at 0x80000000
+    /// with 4 bytes of .bss, and a symbol table containing `_start` and `counter`.
+    fn elf32() -> Vec<u8> {
+        let mut elf = vec![0u8; 0x180];
+
+        // ELF header
+        elf[..4].copy_from_slice(b"\x7fELF");
+        elf[4] = 1;
+        elf[5] = 1;
+        elf[6] = 1;
+        elf[16..18].copy_from_slice(&2u16.to_le_bytes());
+        elf[18..20].copy_from_slice(&243u16.to_le_bytes());
+        elf[24..28].copy_from_slice(&4u32.to_le_bytes());
+        elf[28..32].copy_from_slice(&0x34u32.to_le_bytes());
+        elf[32..36].copy_from_slice(&0x100u32.to_le_bytes());
+        elf[42..44].copy_from_slice(&32u16.to_le_bytes());
+        elf[44..46].copy_from_slice(&2u16.to_le_bytes());
+        elf[46..48].copy_from_slice(&40u16.to_le_bytes());
+        elf[48..50].copy_from_slice(&3u16.to_le_bytes());
+
+        // Program headers: (offset, paddr, filesz, memsz)
+        for (i, (offset, paddr, filesz, memsz)) in
+            [(0xe0u32, 0x0u32, 8u32, 8u32), (0xe8, 0x8000_0000, 4, 8)]
+                .into_iter()
+                .enumerate()
+        {
+            let header = 0x34 + i * 32;
+            elf[header..header + 4].copy_from_slice(&1u32.to_le_bytes());
+            elf[header + 4..header + 8].copy_from_slice(&offset.to_le_bytes());
+            elf[header + 8..header + 12].copy_from_slice(&paddr.to_le_bytes());
+            elf[header + 12..header + 16].copy_from_slice(&paddr.to_le_bytes());
+            elf[header + 16..header + 20].copy_from_slice(&filesz.to_le_bytes());
+            elf[header + 20..header + 24].copy_from_slice(&memsz.to_le_bytes());
+        }
+
+        // Section headers: null, .symtab (linked to section 2), .strtab
+        let symtab = 0x100 + 40;
+        elf[symtab + 4..symtab + 8].copy_from_slice(&2u32.to_le_bytes());
+        elf[symtab + 16..symtab + 20].copy_from_slice(&0xc0u32.to_le_bytes());
+        elf[symtab + 20..symtab + 24].copy_from_slice(&32u32.to_le_bytes());
+        elf[symtab + 24..symtab + 28].copy_from_slice(&2u32.to_le_bytes());
+        elf[symtab + 36..symtab + 40].copy_from_slice(&16u32.to_le_bytes());
+        let strtab = 0x100 + 80;
+        elf[strtab + 4..strtab + 8].copy_from_slice(&3u32.to_le_bytes());
+        elf[strtab + 16..strtab + 20].copy_from_slice(&0xf0u32.to_le_bytes());
+        elf[strtab + 20..strtab + 24].copy_from_slice(&16u32.to_le_bytes());
+
+        // Symbols: (name, value, size)
+        for (i, (name, value, size)) in [(1u32, 4u32, 0u32), (8, 0x8000_0004, 4)]
+            .into_iter()
+            .enumerate()
+        {
+            let entry = 0xc0 + i * 16;
+            elf[entry..entry + 4].copy_from_slice(&name.to_le_bytes());
+            elf[entry + 4..entry + 8].copy_from_slice(&value.to_le_bytes());
+            elf[entry + 8..entry + 12].copy_from_slice(&size.to_le_bytes());
+        }
+
+        // Segment contents & string table
+        elf[0xe0..0xec].copy_from_slice(&[1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12]);
+        elf[0xf0..0x100].copy_from_slice(b"\0_start\0counter\0");
+
+        elf
+    }
+
+    #[test]
+    fn parse() {
+        let elf = Elf::parse(&elf32()).unwrap();
+        assert!(!elf.is_64);
+        assert_eq!(elf.entry, 4);
+        assert_eq!(
+            elf.segments,
+            vec![
+                Segment {
+                    addr: 0,
+                    data: vec![1, 2, 3, 4, 5, 6, 7, 8],
+                    size: 8,
+                },
+                Segment {
+                    addr: 0x8000_0000,
+                    data: vec![9, 10, 11, 12],
+                    size: 8,
+                },
+            ]
+        );
+        assert_eq!(elf.symbol("_start").unwrap().value, 4);
+        assert_eq!(elf.symbol("counter").unwrap().value, 0x8000_0004);
+        assert_eq!(elf.symbol("counter").unwrap().size, 4);
+    }
+
+    #[test]
+    fn invalid() {
+        assert_eq!(Elf::parse(b"\x13\x00\x00\x00"), Err(ElfError::NotElf));
+
+        let mut elf = elf32();
+        elf[18] = 62;
+        assert_eq!(Elf::parse(&elf), Err(ElfError::UnsupportedMachine));
+
+        let mut elf = elf32();
+        elf.truncate(0xe4);
+        assert_eq!(Elf::parse(&elf), Err(ElfError::Truncated));
+    }
+
+    #[test]
+    fn overflowing_offsets() {
+        // 64-bit ELF header, with no program or section headers
+        let mut header = vec![0u8; 64];
+        header[..4].copy_from_slice(b"\x7fELF");
+        header[4] = 2;
+        header[5] = 1;
+        header[18..20].copy_from_slice(&243u16.to_le_bytes());
+        header[54..56].copy_from_slice(&56u16.to_le_bytes());
+        header[58..60].copy_from_slice(&64u16.to_le_bytes());
+        assert!(Elf::parse(&header).is_ok());
+
+        // Section header table at the top of the file offsets
+        let mut elf = header.clone();
+        elf[40..48].copy_from_slice(&0xffff_ffff_ffff_fffeu64.to_le_bytes());
+        elf[60..62].copy_from_slice(&1u16.to_le_bytes());
+        assert_eq!(Elf::parse(&elf), Err(ElfError::Truncated));
+
+        // Symbol table whose string table is at the top of the file offsets: The string table's
+        // section header overlaps the symbol, with its offset field after the symbol's fields
+        let mut elf = header;
+        elf[40..48].copy_from_slice(&64u64.to_le_bytes());
+        elf[60..62].copy_from_slice(&1u16.to_le_bytes());
+        let mut symtab = vec![0u8; 64];
+        symtab[4..8].copy_from_slice(&2u32.to_le_bytes());
+        symtab[24..32].copy_from_slice(&128u64.to_le_bytes());
+        symtab[32..40].copy_from_slice(&24u64.to_le_bytes());
+        symtab[40..44].copy_from_slice(&1u32.to_le_bytes());
+        symtab[56..64].copy_from_slice(&24u64.to_le_bytes());
+        elf.extend(symtab);
+        let mut symbol = vec![0u8; 32];
+        symbol[..4].copy_from_slice(&1u32.to_le_bytes());
+        symbol[24..32].copy_from_slice(&u64::MAX.to_le_bytes());
+        elf.extend(symbol);
+        assert_eq!(Elf::parse(&elf), Err(ElfError::Truncated));
+    }
+
+    #[test]
+    fn load() {
+        let elf = Elf::parse(&elf32()).unwrap();
+        let mut mmu = MMU::new();
+        mmu.map(0, 8, Box::new(ROM::new(vec![0u8; 8]))).unwrap();
+        mmu.map(0x8000_0000, 16, Box::new(RAM::new(16))).unwrap();
+        mmu.store_word(0x8000_0004, -1).unwrap();
+
+        elf.load(&mut mmu).unwrap();
+        assert_eq!(mmu.load_word(4).unwrap(), 0x0807_0605);
+        assert_eq!(mmu.load_word(0x8000_0000).unwrap(), 0x0c0b_0a09);
+
+        // .bss is zeroed
+        assert_eq!(mmu.load_word(0x8000_0004).unwrap(), 0);
+
+        // Segments must be loaded into mapped memory
+        let mut mmu = MMU::new();
+        mmu.map(0, 8, Box::new(ROM::new(vec![0u8; 8]))).unwrap();
+        assert_eq!(elf.load(&mut mmu), Err(ElfError::Unloadable(0x8000_0000)));
+    }
+}
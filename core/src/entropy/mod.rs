@@ -0,0 +1,53 @@
+//! Entropy sources for the `seed` CSR of the Zkr extension.
+//!
+//! The Zkr extension exposes a physical entropy source to software via the `seed` CSR. Each access
+//! to `seed` polls the entropy source, which reports its status, and if it is healthy, returns 16
+//! bits of entropy. Guests (typically a bootloader or kernel) use this to seed a deterministic
+//! random bit generator (DRBG), rather than using it directly as a random number generator.
+//!
+//! The [`EntropySource`] trait defined in this module represents an abstract entropy source, which
+//! the Zkr extension polls each time the guest accesses `seed`. The status it reports is mapped
+//! onto the `OPST` field of the CSR, as described by [`EntropyStatus`].
+//!
+//! # Available Sources
+//! Two [`EntropySource`] structs are provided:
+//! * The [`SeededEntropySource`] source produces a deterministic pseudorandom sequence from a seed.
+//!   This is intended for reproducible tests, and can also simulate a source which is running its
+//!   self-test, or can't keep up with demand.
+//! * The [`HostEntropySource`] source draws entropy from the host operating system's random number
+//!   generator.
+
+mod host;
+mod seeded;
+
+pub use host::HostEntropySource;
+pub use seeded::SeededEntropySource;
+
+use std::fmt;
+
+/// Result of polling an [`EntropySource`].
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum EntropyStatus {
+    /// The source is running its built-in self test (BIST).
+    ///
+    /// This is not an error: Entropy will become available once the self test completes.
+    Bist,
+
+    /// The source is healthy, but no entropy is available yet.
+    Wait,
+
+    /// 16 bits of entropy are available.
+    Es16(u16),
+
+    /// The source has failed unrecoverably, and will not produce any more entropy.
+    Dead,
+}
+
+/// Trait for types which can produce entropy for the `seed` CSR.
+pub trait EntropySource: fmt::Debug + Send + Sync + 'static {
+    /// Poll the entropy source.
+    ///
+    /// This is called each time the guest accesses the `seed` CSR. Any entropy returned is consumed
+    /// by this call: Subsequent calls should never return the same bits again.
+    fn poll(&mut self) -> EntropyStatus;
+}
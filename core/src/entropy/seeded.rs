@@ -0,0 +1,117 @@
+//! Deterministic entropy source.
+
+use crate::entropy::{EntropySource, EntropyStatus};
+
+/// An [`EntropySource`] producing a deterministic pseudorandom sequence from a seed.
+///
+/// Each 16 bits of "entropy" is taken from the output of a SplitMix64 generator, so the same seed
+/// always produces the same sequence of `seed` CSR values. This makes guest behaviour reproducible,
+/// but of course provides no actual entropy, so must never be used where real randomness matters.
+///
+/// By default, entropy is available on every poll. [`with_self_test`](Self::with_self_test) and
+/// [`with_wait`](Self::with_wait) can be used to exercise how the guest handles the `BIST` and
+/// `WAIT` states.
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+pub struct SeededEntropySource {
+    /// State of the SplitMix64 generator.
+    state: u64,
+
+    /// Number of polls remaining before the self test completes.
+    self_test: usize,
+
+    /// Number of polls which report `WAIT` before each 16 bits of entropy.
+    wait: usize,
+
+    /// Number of polls which have reported `WAIT` since entropy was last returned.
+    waited: usize,
+}
+
+impl SeededEntropySource {
+    /// Create a new SeededEntropySource from the provided seed.
+    pub fn new(seed: u64) -> Self {
+        Self {
+            state: seed,
+            self_test: 0,
+            wait: 0,
+            waited: 0,
+        }
+    }
+
+    /// Report `BIST` for the provided number of polls, before producing any entropy.
+    pub fn with_self_test(mut self, polls: usize) -> Self {
+        self.self_test = polls;
+        self
+    }
+
+    /// Report `WAIT` for the provided number of polls before each 16 bits of entropy.
+    pub fn with_wait(mut self, polls: usize) -> Self {
+        self.wait = polls;
+        self
+    }
+
+    /// Get the next output of the SplitMix64 generator.
+    fn next(&mut self) -> u64 {
+        self.state = self.state.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut z = self.state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^ (z >> 31)
+    }
+}
+
+impl EntropySource for SeededEntropySource {
+    fn poll(&mut self) -> EntropyStatus {
+        if self.self_test > 0 {
+            self.self_test -= 1;
+            return EntropyStatus::Bist;
+        }
+
+        if self.waited < self.wait {
+            self.waited += 1;
+            return EntropyStatus::Wait;
+        }
+
+        self.waited = 0;
+        EntropyStatus::Es16((self.next() >> 48) as u16)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::entropy::{EntropySource, EntropyStatus, SeededEntropySource};
+
+    #[test]
+    fn deterministic() {
+        let mut a = SeededEntropySource::new(42);
+        let mut b = SeededEntropySource::new(42);
+        let mut c = SeededEntropySource::new(43);
+
+        let a = (0..16).map(|_| a.poll()).collect::<Vec<_>>();
+        let b = (0..16).map(|_| b.poll()).collect::<Vec<_>>();
+        let c = (0..16).map(|_| c.poll()).collect::<Vec<_>>();
+        assert_eq!(a, b);
+        assert_ne!(a, c);
+        assert!(a.iter().all(|s| matches!(s, EntropyStatus::Es16(_))));
+
+        // First output of SplitMix64 seeded with 42 is 0xbdd732262feb6e95
+        assert_eq!(a[0], EntropyStatus::Es16(0xbdd7));
+    }
+
+    #[test]
+    fn self_test_and_wait() {
+        let mut source = SeededEntropySource::new(0).with_self_test(2).with_wait(1);
+        let statuses = (0..6).map(|_| source.poll()).collect::<Vec<_>>();
+
+        assert_eq!(
+            statuses[..3],
+            [
+                EntropyStatus::Bist,
+                EntropyStatus::Bist,
+                EntropyStatus::Wait
+            ]
+        );
+        assert!(matches!(statuses[3], EntropyStatus::Es16(_)));
+        assert_eq!(statuses[4], EntropyStatus::Wait);
+        assert!(matches!(statuses[5], EntropyStatus::Es16(_)));
+    }
+}
@@ -1,5 +1,7 @@
 //! Error types.
 
+use std::fmt;
+
 /// An exception encountered by a hart during execution.
 #[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
 pub enum ProcessorException {
@@ -34,33 +36,102 @@ pub enum MemoryAccessError {
     /// mapped to a memory device.
     OutOfBounds,
 
+    /// Tried to load/store from a range which starts in one mapped region, but ends outside it.
+    ///
+    /// Each access must be handled by a single device, so accesses which span the boundary between
+    /// two regions (or between a region and unmapped memory) are not supported.
+    CrossesBoundary,
+
+    /// Tried to load/store a value of a width which the device does not support.
+    ///
+    /// For example, a device with byte-wide registers may not support word-sized accesses.
+    UnsupportedWidth,
+
     /// Tried to store to a range which is read-only.
     ///
     /// This could happen if you try to overwrite a portion of the ROM, for example.
     ReadOnly,
 
-    /// Mismatch in the length of the value to store and the range to which the value should be
-    /// stored.
+    /// Tried to perform an access which must be naturally aligned at a misaligned address.
     ///
-    /// This indicates an error in the implementation of the processor/MMU, *not* an error on the
-    /// part of the emulated program. In theory, this should never occur.
-    LengthMismatch,
+    /// Most loads/stores may be misaligned, but some accesses, such as atomic memory operations,
+    /// require their address to be a multiple of the access width.
+    Misaligned,
 }
 
-impl From<MemoryAccessError> for ProcessorException {
-    fn from(value: MemoryAccessError) -> Self {
-        ProcessorException::InvalidMemoryAccess(value)
+/// An error encountered when mapping a device to the address space.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum MemoryMapError {
+    /// Tried to map a device to a zero-sized region.
+    Empty,
+
+    /// Tried to map a device to a region extending beyond the end of the address space.
+    Overflow,
+
+    /// Tried to map a device to a region which overlaps a region which is already mapped.
+    Overlap,
+}
+
+impl fmt::Display for MemoryMapError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MemoryMapError::Empty => f.write_str("cannot map a device to an empty region"),
+            MemoryMapError::Overflow => f.write_str("region extends beyond the address space"),
+            MemoryMapError::Overlap => f.write_str("region overlaps an existing region"),
+        }
     }
 }
 
-/// Helper trait for adding a program counter value to an error.
-pub trait WithPC<T, E> {
-    /// Add the program counter to the error value of this Result.
-    fn with_pc(self, pc: u32) -> Result<T, (E, u32)>;
+impl std::error::Error for MemoryMapError {}
+
+/// An error encountered when loading an ELF file.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum ElfError {
+    /// The file does not start with the ELF magic number.
+    NotElf,
+
+    /// The file is neither a 32-bit nor 64-bit ELF file.
+    UnsupportedClass,
+
+    /// The file is not little-endian.
+    UnsupportedEncoding,
+
+    /// The file is not for the RISC-V architecture.
+    UnsupportedMachine,
+
+    /// A header, table, or segment extends beyond the end of the file.
+    Truncated,
+
+    /// An address in the file does not fit in the address space.
+    AddressOverflow,
+
+    /// A loadable segment could not be placed into memory.
+    ///
+    /// The associated value is the address at which loading failed: This address is either not
+    /// mapped to any device, or is mapped to a device which cannot be initialised.
+    Unloadable(usize),
 }
 
-impl<T, E> WithPC<T, E> for Result<T, E> {
-    fn with_pc(self, pc: u32) -> Result<T, (E, u32)> {
-        self.map_err(|e| (e, pc))
+impl fmt::Display for ElfError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ElfError::NotElf => f.write_str("not an ELF file"),
+            ElfError::UnsupportedClass => f.write_str("unsupported ELF class"),
+            ElfError::UnsupportedEncoding => f.write_str("ELF file is not little-endian"),
+            ElfError::UnsupportedMachine => f.write_str("ELF file is not for RISC-V"),
+            ElfError::Truncated => f.write_str("ELF file is truncated"),
+            ElfError::AddressOverflow => f.write_str("ELF address exceeds the address space"),
+            ElfError::Unloadable(addr) => {
+                write!(f, "cannot load ELF segment to address {:#010x}", addr)
+            }
+        }
+    }
+}
+
+impl std::error::Error for ElfError {}
+
+impl From<MemoryAccessError> for ProcessorException {
+    fn from(value: MemoryAccessError) -> Self {
+        ProcessorException::InvalidMemoryAccess(value)
     }
 }
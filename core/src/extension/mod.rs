@@ -11,14 +11,17 @@
 //! of [`OpcodeHandler`]s, which define how a processor should decode instructions with a given
 //! opcode. The key method of an [`Extension`] is [`register`](Extension::register), which is passed
 //! a mutable reference to a [`Hart`], and updates the hart to implement the extension's opcode
-//! handlers.
+//! handlers. Extensions adding instructions to an opcode already claimed by another extension can
+//! instead register a [`SubDecoder`], which decodes only its own instructions.
 //!
 //! Note that the [`Extension`] trait is also used to implement the base integer instruction sets,
 //! since these are functionally equivalent to extensions for this application.
 
 mod opcode_handler;
+mod sub_decoder;
 
 pub use opcode_handler::OpcodeHandler;
+pub use sub_decoder::{SubDecoder, SubDecoderHandler};
 
 use crate::processor::hart::Hart;
 
@@ -45,7 +48,7 @@ pub trait Extension: Send + Sync + 'static {
     ///
     /// This function should update the provided [`Hart`] to support this extension: The usual
     /// way to do this would be to add a set of [`OpcodeHandler`]s to the [`Hart::opcodes`]
-    /// hashmap.
+    /// hashmap, and to register any CSRs the extension defines in [`Hart::csrs`].
     ///
     /// This is deliberately given a lot of freedom in what it can do, and how it does it, so as to
     /// support extensions which may fundamentally change properties of the processor (e.g: The
@@ -2,6 +2,7 @@
 
 use crate::error::ProcessorException;
 use crate::instruction::{Instruction, InstructionParts};
+use crate::processor::Xlen;
 
 /// A decoder for instructions with a given opcode.
 pub trait OpcodeHandler: Send + Sync + 'static {
@@ -11,9 +12,24 @@ pub trait OpcodeHandler: Send + Sync + 'static {
     /// associated with this handler. The OpcodeHandler should decode the instruction to return an
     /// [`Instruction`], which may later be executed by the processor, or return an error if the
     /// instruction is invalid for the associated opcode.
+    ///
+    /// `xlen` is the hart's current XLEN. This is passed at decode time, rather than captured when
+    /// the handler is registered, since the base integer instruction set which selects it may be
+    /// registered after the extension.
     fn decode(
         &self,
         instruction: InstructionParts,
-        pc: u32,
+        pc: u64,
+        xlen: Xlen,
     ) -> Result<Box<dyn Instruction>, ProcessorException>;
+
+    /// Get the slot holding the handler this handler wraps, if it only decodes some of the opcode's
+    /// instructions and passes the rest on to another handler.
+    ///
+    /// This is used by [`Hart::register_opcode`](crate::processor::hart::Hart::register_opcode) to
+    /// register an opcode's handler beneath any handlers already wrapping it. Handlers which decode
+    /// every instruction with their opcode return `None`, which is the default.
+    fn inner_mut(&mut self) -> Option<&mut Option<Box<dyn OpcodeHandler>>> {
+        None
+    }
 }
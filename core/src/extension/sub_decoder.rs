@@ -0,0 +1,68 @@
+//! The SubDecoder trait.
+
+use crate::error::ProcessorException;
+use crate::extension::OpcodeHandler;
+use crate::instruction::{Instruction, InstructionParts, InstructionWordParts};
+use crate::processor::Xlen;
+
+/// A decoder for a subset of the instructions with a given opcode.
+///
+/// Some extensions add instructions to an opcode which is already claimed by another extension's
+/// [`OpcodeHandler`], distinguished by fields such as funct7 and funct3: For example, the
+/// bit-manipulation extensions add instructions to the OP and OP-IMM opcodes of the base integer
+/// instruction set. Rather than replacing the opcode's handler, such extensions register a
+/// SubDecoder with
+/// [`Hart::register_sub_decoder`](crate::processor::hart::Hart::register_sub_decoder), which is
+/// offered each instruction with the opcode before the existing handler.
+pub trait SubDecoder: Send + Sync + 'static {
+    /// Decode the provided instruction, if it is one of this decoder's instructions.
+    ///
+    /// Returns `Ok(None)` if the instruction isn't recognised, in which case it is passed on to the
+    /// opcode's existing handler. Otherwise, returns the decoded [`Instruction`], or an error if
+    /// the instruction is invalid. As for [`OpcodeHandler::decode`], `xlen` is the hart's current
+    /// XLEN.
+    fn decode(
+        &self,
+        instruction: &InstructionWordParts,
+        pc: u64,
+        xlen: Xlen,
+    ) -> Result<Option<Box<dyn Instruction>>, ProcessorException>;
+}
+
+/// An [`OpcodeHandler`] offering instructions to a [`SubDecoder`], before passing any it doesn't
+/// recognise on to the wrapped handler, if there is one.
+pub struct SubDecoderHandler {
+    decoder: Box<dyn SubDecoder>,
+    inner: Option<Box<dyn OpcodeHandler>>,
+}
+
+impl SubDecoderHandler {
+    /// Create a new SubDecoderHandler, wrapping the provided opcode handler.
+    pub fn new(decoder: Box<dyn SubDecoder>, inner: Option<Box<dyn OpcodeHandler>>) -> Self {
+        Self { decoder, inner }
+    }
+}
+
+impl OpcodeHandler for SubDecoderHandler {
+    fn decode(
+        &self,
+        instruction: InstructionParts,
+        pc: u64,
+        xlen: Xlen,
+    ) -> Result<Box<dyn Instruction>, ProcessorException> {
+        if let Ok(parts) = instruction.word() {
+            if let Some(decoded) = self.decoder.decode(parts, pc, xlen)? {
+                return Ok(decoded);
+            }
+        }
+
+        match &self.inner {
+            Some(inner) => inner.decode(instruction, pc, xlen),
+            None => Err(ProcessorException::IllegalInstruction),
+        }
+    }
+
+    fn inner_mut(&mut self) -> Option<&mut Option<Box<dyn OpcodeHandler>>> {
+        Some(&mut self.inner)
+    }
+}
@@ -0,0 +1,734 @@
+//! GDB remote serial protocol (RSP) stub.
+//!
+//! Rather than stepping through a program in the TUI, guest code can be debugged with a standard
+//! RISC-V GDB (e.g: `riscv64-unknown-elf-gdb`), which connects to the emulator over TCP or a Unix
+//! socket and controls it using the remote serial protocol. The [`GdbStub`] defined in this module
+//! serves a single such connection, driving an [`ExecutionEnvironment`] directly: While a debugger
+//! is connected, the processor only runs when GDB requests a step or continue, replacing the
+//! [`ManualClock`](crate::clock::ManualClock).
+//!
+//! The following features are supported:
+//! * Reading & writing the general-purpose registers & program counter (`g`/`G`/`p`/`P`), as
+//!   described by a target description (`qXfer:features:read`)
+//! * Reading & writing memory through the [`MMU`](crate::mmu::MMU) (`m`/`M`)
+//! * Single-step (`s`) & continue (`c`), including interrupting a running target
+//! * Software breakpoints (`Z0`) and write/read/access watchpoints (`Z2`/`Z3`/`Z4`)
+//!
+//! Exceptions which cannot be handled by the guest are reported to GDB as signals: e.g. an illegal
+//! instruction is reported as `SIGILL`, and an invalid memory access as `SIGSEGV`.
+
+mod packet;
+
+use crate::clock::Clock;
+use crate::error::{MemoryAccessError, ProcessorException};
+use crate::processor::Xlen;
+use crate::ExecutionEnvironment;
+use packet::Message;
+use std::collections::BTreeSet;
+use std::io::{self, Read, Write};
+use std::net::TcpStream;
+
+/// Signal reported when execution is interrupted by the debugger.
+const SIGINT: u8 = 2;
+
+/// Signal reported for illegal instructions.
+const SIGILL: u8 = 4;
+
+/// Signal reported for breakpoints, watchpoints, and completed steps.
+const SIGTRAP: u8 = 5;
+
+/// Signal reported for misaligned accesses.
+const SIGBUS: u8 = 7;
+
+/// Signal reported for invalid memory accesses.
+const SIGSEGV: u8 = 11;
+
+/// Number of instructions executed between checks for an interrupt from the debugger.
+const POLL_INTERVAL: usize = 1024;
+
+/// Register number of the program counter, following the 32 general-purpose registers.
+const PC_REGNUM: u64 = 32;
+
+/// ABI names of the general-purpose registers.
+const REGISTER_NAMES: [&str; 32] = [
+    "zero", "ra", "sp", "gp", "tp", "t0", "t1", "t2", "fp", "s1", "a0", "a1", "a2", "a3", "a4",
+    "a5", "a6", "a7", "s2", "s3", "s4", "s5", "s6", "s7", "s8", "s9", "s10", "s11", "t3", "t4",
+    "t5", "t6",
+];
+
+/// A connection to a debugger.
+pub trait Connection: Read + Write {
+    /// Switch the connection into or out of non-blocking mode.
+    ///
+    /// While execution continues, the connection is polled in non-blocking mode, to check whether
+    /// the debugger has requested an interrupt.
+    fn set_nonblocking(&self, nonblocking: bool) -> io::Result<()>;
+}
+
+impl Connection for TcpStream {
+    fn set_nonblocking(&self, nonblocking: bool) -> io::Result<()> {
+        TcpStream::set_nonblocking(self, nonblocking)
+    }
+}
+
+#[cfg(unix)]
+impl Connection for std::os::unix::net::UnixStream {
+    fn set_nonblocking(&self, nonblocking: bool) -> io::Result<()> {
+        std::os::unix::net::UnixStream::set_nonblocking(self, nonblocking)
+    }
+}
+
+/// Type of memory access which triggers a watchpoint.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+enum WatchKind {
+    /// Stores (`Z2`).
+    Write,
+
+    /// Loads (`Z3`).
+    Read,
+
+    /// Loads & stores (`Z4`).
+    Access,
+}
+
+/// A watchpoint on a range of memory.
+///
+/// The range never extends beyond the top of the address space: Such watchpoints are rejected when
+/// inserted.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+struct Watchpoint {
+    kind: WatchKind,
+    addr: u64,
+    len: u64,
+}
+
+impl Watchpoint {
+    /// Returns true if an access to `len` bytes at `addr` triggers this watchpoint.
+    fn hit(&self, addr: usize, len: usize, read: bool, write: bool) -> bool {
+        let kind = match self.kind {
+            WatchKind::Write => write,
+            WatchKind::Read => read,
+            WatchKind::Access => read || write,
+        };
+        let start = self.addr as usize;
+        kind && addr < start.saturating_add(self.len as usize) && start < addr.saturating_add(len)
+    }
+}
+
+/// Reason execution stopped, as reported to GDB.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+enum StopReason {
+    /// Stopped due to a signal: A completed step, an interrupt, or an unhandled exception.
+    Signal(u8),
+
+    /// Reached a software breakpoint.
+    Breakpoint,
+
+    /// Triggered a watchpoint.
+    Watchpoint(Watchpoint),
+}
+
+impl StopReason {
+    /// Format this stop reason as a stop reply packet.
+    fn reply(&self) -> Vec<u8> {
+        match self {
+            StopReason::Signal(signal) => format!("S{:02x}", signal),
+            StopReason::Breakpoint => format!("T{:02x}swbreak:;", SIGTRAP),
+            StopReason::Watchpoint(watchpoint) => {
+                let kind = match watchpoint.kind {
+                    WatchKind::Write => "watch",
+                    WatchKind::Read => "rwatch",
+                    WatchKind::Access => "awatch",
+                };
+                format!("T{:02x}{}:{:x};", SIGTRAP, kind, watchpoint.addr)
+            }
+        }
+        .into_bytes()
+    }
+}
+
+/// Determine the signal to report for an exception which could not be handled by the guest.
+fn signal(exception: ProcessorException) -> u8 {
+    match exception {
+        ProcessorException::IllegalInstruction => SIGILL,
+        ProcessorException::InstructionAddressMisaligned
+        | ProcessorException::InvalidMemoryAccess(MemoryAccessError::Misaligned) => SIGBUS,
+        ProcessorException::InvalidMemoryAccess(_) => SIGSEGV,
+        ProcessorException::EnvironmentCall | ProcessorException::EnvironmentBreak => SIGTRAP,
+    }
+}
+
+/// Generate the target description XML, describing the registers of a processor with the provided
+/// XLEN.
+fn target_xml(xlen: Xlen) -> String {
+    let bits = xlen.bits();
+    let mut xml = String::from(concat!(
+        r#"<?xml version="1.0"?>"#,
+        r#"<!DOCTYPE target SYSTEM "gdb-target.dtd">"#,
+        r#"<target version="1.0">"#,
+    ));
+    xml.push_str(&format!(
+        concat!(
+            "<architecture>riscv:rv{}</architecture>",
+            r#"<feature name="org.gnu.gdb.riscv.cpu">"#,
+        ),
+        bits
+    ));
+    for (i, name) in REGISTER_NAMES.iter().enumerate() {
+        let ty = match *name {
+            "ra" => "code_ptr",
+            "sp" | "gp" | "tp" | "fp" => "data_ptr",
+            _ => "int",
+        };
+        xml.push_str(&format!(
+            r#"<reg name="{}" bitsize="{}" type="{}" regnum="{}"/>"#,
+            name, bits, ty, i
+        ));
+    }
+    xml.push_str(&format!(
+        r#"<reg name="pc" bitsize="{}" type="code_ptr" regnum="{}"/>"#,
+        bits, PC_REGNUM
+    ));
+    xml.push_str("</feature></target>");
+    xml
+}
+
+/// Parse a comma-separated list of hex numbers.
+fn parse_args(args: &[u8]) -> Option<Vec<u64>> {
+    args.split(|b| *b == b',').map(packet::parse_hex).collect()
+}
+
+/// Response to a packet.
+enum Response {
+    /// Send a reply.
+    Reply(Vec<u8>),
+
+    /// Send a reply, then end the session.
+    ReplyAndClose(Vec<u8>),
+
+    /// End the session without replying.
+    Close,
+}
+
+/// A GDB remote serial protocol stub, serving a single debugger connection.
+pub struct GdbStub<'a, C, S> {
+    /// The system being debugged.
+    env: &'a mut ExecutionEnvironment<C>,
+
+    /// Connection to the debugger.
+    stream: S,
+
+    /// Whether packets must be acknowledged.
+    ///
+    /// This is initially true, but GDB will usually negotiate no-ack mode.
+    ack: bool,
+
+    /// Addresses of software breakpoints.
+    breakpoints: BTreeSet<u64>,
+
+    /// Active watchpoints.
+    watchpoints: Vec<Watchpoint>,
+
+    /// Reason execution most recently stopped.
+    stop: StopReason,
+}
+
+impl<'a, C, S> GdbStub<'a, C, S>
+where
+    C: Clock,
+    S: Connection,
+{
+    /// Create a new GdbStub, to debug `env` via the provided connection.
+    pub fn new(env: &'a mut ExecutionEnvironment<C>, stream: S) -> Self {
+        Self {
+            env,
+            stream,
+            ack: true,
+            breakpoints: BTreeSet::new(),
+            watchpoints: Vec::new(),
+            stop: StopReason::Signal(SIGTRAP),
+        }
+    }
+
+    /// Serve the connection.
+    ///
+    /// Returns once the debugger detaches, kills the target, or disconnects.
+    pub fn serve(mut self) -> io::Result<()> {
+        match self.serve_packets() {
+            Err(e) if e.kind() == io::ErrorKind::UnexpectedEof => Ok(()),
+            result => result,
+        }
+    }
+
+    /// Handle packets until the connection should be closed.
+    fn serve_packets(&mut self) -> io::Result<()> {
+        loop {
+            let packet = match packet::read_message(&mut self.stream, self.ack)? {
+                Message::Packet(packet) => packet,
+                // Execution is already stopped
+                Message::Interrupt => continue,
+            };
+
+            match self.handle(&packet)? {
+                Response::Reply(reply) => packet::write_packet(&mut self.stream, &reply, self.ack)?,
+                Response::ReplyAndClose(reply) => {
+                    return packet::write_packet(&mut self.stream, &reply, self.ack)
+                }
+                Response::Close => return Ok(()),
+            }
+
+            // No-ack mode starts after the reply to the request is acknowledged
+            if packet == b"QStartNoAckMode" {
+                self.ack = false;
+            }
+        }
+    }
+
+    /// Handle a single packet.
+    fn handle(&mut self, packet: &[u8]) -> io::Result<Response> {
+        let (command, args) = match packet.split_first() {
+            Some((command, args)) => (*command, args),
+            None => return Ok(Response::Reply(Vec::new())),
+        };
+
+        let reply = match command {
+            b'?' => self.stop.reply(),
+            b'q' => self.query(packet),
+            b'Q' if packet == b"QStartNoAckMode" => b"OK".to_vec(),
+            b'H' | b'T' => b"OK".to_vec(),
+            b'g' => self.read_registers(),
+            b'G' => self.write_registers(args),
+            b'p' => self.read_register(args),
+            b'P' => self.write_register(args),
+            b'm' => self.read_memory(args),
+            b'M' => self.write_memory(args),
+            b's' | b'c' => {
+                if let Some(addr) = packet::parse_hex(args) {
+                    self.env.processor.redirect(addr);
+                }
+                self.stop = if command == b's' {
+                    self.step().unwrap_or(StopReason::Signal(SIGTRAP))
+                } else {
+                    self.resume()?
+                };
+                self.stop.reply()
+            }
+            b'Z' | b'z' => self.breakpoint(command == b'Z', args),
+            b'D' => return Ok(Response::ReplyAndClose(b"OK".to_vec())),
+            b'k' => return Ok(Response::Close),
+            _ => Vec::new(),
+        };
+
+        Ok(Response::Reply(reply))
+    }
+
+    /// Handle a general query packet.
+    fn query(&self, packet: &[u8]) -> Vec<u8> {
+        const FEATURES: &[u8] = b"qXfer:features:read:target.xml:";
+
+        if packet.starts_with(b"qSupported") {
+            b"PacketSize=4000;qXfer:features:read+;QStartNoAckMode+;swbreak+".to_vec()
+        } else if let Some(range) = packet.strip_prefix(FEATURES) {
+            let xml = target_xml(self.env.processor.hart.xlen);
+            match parse_args(range).as_deref() {
+                Some([offset, len]) => {
+                    let start = (*offset as usize).min(xml.len());
+                    let end = start.saturating_add(*len as usize).min(xml.len());
+                    let prefix = if end == xml.len() { "l" } else { "m" };
+                    format!("{}{}", prefix, &xml[start..end]).into_bytes()
+                }
+                _ => b"E01".to_vec(),
+            }
+        } else if packet == b"qAttached" {
+            b"1".to_vec()
+        } else if packet == b"qC" {
+            b"QC1".to_vec()
+        } else if packet == b"qfThreadInfo" {
+            b"m1".to_vec()
+        } else if packet == b"qsThreadInfo" {
+            b"l".to_vec()
+        } else {
+            Vec::new()
+        }
+    }
+
+    /// Width of a register, in bytes.
+    fn register_size(&self) -> usize {
+        self.env.processor.hart.xlen.bytes()
+    }
+
+    /// Get the value of a register, as numbered by GDB.
+    fn register(&self, regnum: u64) -> Option<u64> {
+        let hart = &self.env.processor.hart;
+        if regnum == PC_REGNUM {
+            return Some(hart.next_pc());
+        }
+
+        let reg = hart.registers.get(&u8::try_from(regnum).ok()?)?;
+        Some(reg.load().unwrap_or(0) as u64)
+    }
+
+    /// Encode the value of a register, as a target-endian value of the register's width.
+    fn encode_register(&self, value: u64) -> String {
+        packet::encode_hex(&value.to_le_bytes()[..self.register_size()])
+    }
+
+    /// Decode the value of a register, from a target-endian value of the register's width.
+    ///
+    /// Returns `None` if the value has the wrong width.
+    fn decode_register(&self, bytes: &[u8]) -> Option<u64> {
+        if bytes.len() != self.register_size() {
+            return None;
+        }
+        let mut value = [0; 8];
+        value[..bytes.len()].copy_from_slice(bytes);
+        Some(u64::from_le_bytes(value))
+    }
+
+    /// Set the value of a register, as numbered by GDB.
+    ///
+    /// The processor is redirected to the next instruction, so that it is decoded again & any
+    /// memory access it requires uses the new register values.
+    fn set_register(&mut self, regnum: u64, value: u64) -> Option<()> {
+        let processor = &mut self.env.processor;
+        let pc = if regnum == PC_REGNUM {
+            value
+        } else {
+            let reg = processor
+                .hart
+                .registers
+                .get_mut(&u8::try_from(regnum).ok()?)?;
+            reg.store(processor.hart.xlen.sign_extend(value as i64))
+                .ok()?;
+            processor.hart.next_pc()
+        };
+
+        processor.redirect(pc);
+        Some(())
+    }
+
+    fn read_registers(&self) -> Vec<u8> {
+        (0..=PC_REGNUM)
+            .map(|regnum| self.encode_register(self.register(regnum).unwrap_or(0)))
+            .collect::<String>()
+            .into_bytes()
+    }
+
+    fn write_registers(&mut self, args: &[u8]) -> Vec<u8> {
+        let Some(bytes) = packet::decode_hex(args) else {
+            return b"E01".to_vec();
+        };
+
+        for (regnum, value) in bytes.chunks_exact(self.register_size()).enumerate() {
+            let value = self
+                .decode_register(value)
+                .expect("Chunk has register width");
+            // x0 is hard-wired to zero, so writes are discarded
+            self.set_register(regnum as u64, value);
+        }
+        b"OK".to_vec()
+    }
+
+    fn read_register(&self, args: &[u8]) -> Vec<u8> {
+        match packet::parse_hex(args).and_then(|regnum| self.register(regnum)) {
+            Some(value) => self.encode_register(value).into_bytes(),
+            None => b"E01".to_vec(),
+        }
+    }
+
+    fn write_register(&mut self, args: &[u8]) -> Vec<u8> {
+        let mut parts = args.splitn(2, |b| *b == b'=');
+        let regnum = parts.next().and_then(packet::parse_hex);
+        let value = parts
+            .next()
+            .and_then(packet::decode_hex)
+            .and_then(|value| self.decode_register(&value));
+
+        let result = match (regnum, value) {
+            (Some(regnum), Some(value)) => self.set_register(regnum, value),
+            _ => None,
+        };
+        match result {
+            Some(()) => b"OK".to_vec(),
+            None => b"E01".to_vec(),
+        }
+    }
+
+    fn read_memory(&mut self, args: &[u8]) -> Vec<u8> {
+        let Some(&[addr, len]) = parse_args(args).as_deref() else {
+            return b"E01".to_vec();
+        };
+
+        let mut mmu = self.env.processor.mmu.lock().unwrap();
+        match mmu.load_bytes(addr as usize, len as usize) {
+            Ok(bytes) => packet::encode_hex(&bytes).into_bytes(),
+            Err(_) => b"E14".to_vec(),
+        }
+    }
+
+    /// Write memory.
+    ///
+    /// Memory is written as a program loader would, so that e.g. the ROM can be patched. The
+    /// processor is redirected to the next instruction, in case it was modified.
+    fn write_memory(&mut self, args: &[u8]) -> Vec<u8> {
+        let mut parts = args.splitn(2, |b| *b == b':');
+        let range = parts.next().and_then(parse_args);
+        let bytes = parts.next().and_then(packet::decode_hex);
+        let (addr, bytes) = match (range.as_deref(), bytes) {
+            (Some([addr, len]), Some(bytes)) if bytes.len() == *len as usize => (*addr, bytes),
+            _ => return b"E01".to_vec(),
+        };
+
+        let processor = &mut self.env.processor;
+        let result = processor.mmu.lock().unwrap().preload(addr as usize, &bytes);
+        processor.redirect(processor.hart.next_pc());
+        match result {
+            Ok(()) => b"OK".to_vec(),
+            Err(_) => b"E14".to_vec(),
+        }
+    }
+
+    /// Insert (`Z`) or remove (`z`) a breakpoint or watchpoint.
+    fn breakpoint(&mut self, insert: bool, args: &[u8]) -> Vec<u8> {
+        let Some(&[ty, addr, len]) = parse_args(args).as_deref() else {
+            return b"E01".to_vec();
+        };
+
+        let kind = match ty {
+            0 => {
+                if insert {
+                    self.breakpoints.insert(addr);
+                } else {
+                    self.breakpoints.remove(&addr);
+                }
+                return b"OK".to_vec();
+            }
+            2 => WatchKind::Write,
+            3 => WatchKind::Read,
+            4 => WatchKind::Access,
+            // Hardware breakpoints are not supported
+            _ => return Vec::new(),
+        };
+
+        if addr.checked_add(len).is_none() {
+            return b"E01".to_vec();
+        }
+
+        let watchpoint = Watchpoint { kind, addr, len };
+        if insert {
+            self.watchpoints.push(watchpoint);
+        } else {
+            self.watchpoints.retain(|w| *w != watchpoint);
+        }
+        b"OK".to_vec()
+    }
+
+    /// Run a single processor cycle.
+    ///
+    /// Returns a stop reason if the processor encountered an unhandled exception, or the cycle
+    /// triggered a watchpoint. Following an unhandled exception, the processor is redirected to the
+    /// instruction which raised it.
+    fn cycle(&mut self) -> Option<StopReason> {
+        self.env.tick();
+
+        let processor = &mut self.env.processor;
+        if let Err((exception, pc)) = processor.cycle() {
+            processor.redirect(pc);
+            return Some(StopReason::Signal(signal(exception)));
+        }
+
+        let access = &processor.last_access;
+        let loads = access.load.iter().chain(&access.loads);
+        let stores = access.store.iter().chain(&access.stores);
+        let accesses = loads
+            .map(|l| (l.addr, l.access_type.bytes(), true, false))
+            .chain(
+                access
+                    .atomic
+                    .map(|a| (a.addr, a.access_type.bytes(), true, true)),
+            )
+            .chain(stores.map(|s| (s.addr, s.access_type.bytes(), false, true)))
+            .collect::<Vec<_>>();
+        self.watchpoints
+            .iter()
+            .find(|watchpoint| {
+                accesses
+                    .iter()
+                    .any(|(addr, len, read, write)| watchpoint.hit(*addr, *len, *read, *write))
+            })
+            .map(|watchpoint| StopReason::Watchpoint(*watchpoint))
+    }
+
+    /// Execute a single instruction.
+    ///
+    /// If no instruction has been decoded yet, an additional cycle is run first to decode it.
+    /// Returns a stop reason if execution should stop before the next instruction.
+    fn step(&mut self) -> Option<StopReason> {
+        if !self.env.processor.hart.has_decoded_instruction() {
+            if let Some(stop) = self.cycle() {
+                return Some(stop);
+            }
+        }
+        self.cycle()
+    }
+
+    /// Execute instructions until a breakpoint is reached, or some other reason to stop occurs.
+    ///
+    /// Any breakpoint at the current instruction is ignored, so that execution can continue from a
+    /// breakpoint.
+    fn resume(&mut self) -> io::Result<StopReason> {
+        for executed in 1.. {
+            if let Some(stop) = self.step() {
+                return Ok(stop);
+            }
+
+            let pc = self.env.processor.hart.next_pc();
+            if self.breakpoints.contains(&pc) {
+                return Ok(StopReason::Breakpoint);
+            }
+
+            if executed % POLL_INTERVAL == 0 && self.interrupted()? {
+                return Ok(StopReason::Signal(SIGINT));
+            }
+        }
+        unreachable!("Loops until stopped")
+    }
+
+    /// Check whether the debugger has requested an interrupt.
+    fn interrupted(&mut self) -> io::Result<bool> {
+        self.stream.set_nonblocking(true)?;
+        let mut byte = [0];
+        let result = self.stream.read(&mut byte);
+        self.stream.set_nonblocking(false)?;
+
+        match result {
+            Ok(0) => Err(io::ErrorKind::UnexpectedEof.into()),
+            Ok(_) => Ok(byte[0] == packet::INTERRUPT),
+            Err(e) if e.kind() == io::ErrorKind::WouldBlock => Ok(false),
+            Err(e) => Err(e),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Connection, GdbStub};
+    use crate::clock::FreeClock;
+    use crate::{Config, ExecutionEnvironment};
+    use bus::Bus;
+    use std::io::{self, Cursor, Read, Write};
+
+    /// An in-memory connection, reading from a fixed input & recording output.
+    struct Stream {
+        input: Cursor<Vec<u8>>,
+        output: Vec<u8>,
+    }
+
+    impl Read for Stream {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            self.input.read(buf)
+        }
+    }
+
+    impl Write for Stream {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.output.write(buf)
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    /// Run a session consisting of the provided packets, in no-ack mode, returning the replies.
+    ///
+    /// No extensions are registered, so every instruction is illegal.
+    fn session(packets: &[&str]) -> Vec<String> {
+        let mut control_bus = Bus::new(1);
+        let config = Config {
+            harts: 1,
+            extensions: Vec::new(),
+            rom: Cursor::new(vec![0x13, 0x00, 0x00, 0x00, 0x78, 0x56, 0x34, 0x12]),
+            ram_size: 16,
+            serial: None,
+            clock: FreeClock::new(),
+            control_rx: control_bus.add_rx(),
+        };
+        let mut env = ExecutionEnvironment::new(config).unwrap();
+
+        // The reply to QStartNoAckMode must be acknowledged: No other packets are
+        let mut input = String::new();
+        for (i, packet) in ["QStartNoAckMode"].iter().chain(packets).enumerate() {
+            let checksum = packet.bytes().fold(0u8, |sum, b| sum.wrapping_add(b));
+            input.push_str(&format!("${}#{:02x}", packet, checksum));
+            if i == 0 {
+                input.push('+');
+            }
+        }
+
+        let mut stream = Stream {
+            input: Cursor::new(input.into_bytes()),
+            output: Vec::new(),
+        };
+        GdbStub::new(&mut env, &mut stream).serve().unwrap();
+
+        let output = String::from_utf8(stream.output).unwrap();
+        output
+            .split('$')
+            .skip(2)
+            .map(|reply| reply[..reply.len() - 3].to_owned())
+            .collect()
+    }
+
+    impl Connection for &mut Stream {
+        fn set_nonblocking(&self, _nonblocking: bool) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn registers() {
+        let replies = session(&["P5=78563412", "p5", "P20=04000000", "g"]);
+        assert_eq!(replies[..3], ["OK", "78563412", "OK"]);
+        assert_eq!(&replies[3][..8], "00000000");
+        assert_eq!(&replies[3][40..48], "78563412");
+        assert_eq!(&replies[3][256..], "04000000");
+    }
+
+    #[test]
+    fn memory() {
+        let replies = session(&[
+            "m4,4",
+            "M80000000,2:abcd",
+            "m80000000,4",
+            "m40000000,1",
+            "mffffffffffffffff,2",
+        ]);
+        assert_eq!(replies, ["78563412", "OK", "abcd0000", "E14", "E14"]);
+    }
+
+    #[test]
+    fn stop_reasons() {
+        let replies = session(&[
+            "?",
+            "Z0,4,4",
+            "s",
+            "p20",
+            "Z2,80000000,4",
+            "z2,80000000,4",
+            "Z2,ffffffffffffffff,4",
+        ]);
+        assert_eq!(replies, ["S05", "OK", "S04", "00000000", "OK", "OK", "E01"]);
+    }
+
+    #[test]
+    fn target_description() {
+        let replies = session(&[
+            "qXfer:features:read:target.xml:0,b",
+            "qXfer:features:read:target.xml:10000,b",
+            "qXfer:features:read:target.xml:1,ffffffffffffffff",
+        ]);
+        assert_eq!(replies[..2], ["m<?xml versi", "l"]);
+        assert!(replies[2].starts_with("l?xml versi"));
+    }
+}
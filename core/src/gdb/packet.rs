@@ -0,0 +1,217 @@
+//! Framing & encoding of GDB remote serial protocol packets.
+//!
+//! Packets take the form `$<data>#<checksum>`, where the checksum is the sum of the bytes of
+//! `<data>` modulo 256, as two hex digits. Unless no-ack mode has been negotiated, each packet is
+//! acknowledged by the receiver with `+`, or `-` to request retransmission. Outside of a packet,
+//! the byte `0x03` is sent by GDB to interrupt the running target.
+
+use std::io::{self, Read, Write};
+
+/// Byte sent by GDB to interrupt the target.
+pub const INTERRUPT: u8 = 0x03;
+
+/// A message received from GDB.
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+pub enum Message {
+    /// A packet, with its framing removed.
+    Packet(Vec<u8>),
+
+    /// A request to interrupt the target.
+    Interrupt,
+}
+
+/// Compute the checksum of packet data.
+fn checksum(data: &[u8]) -> u8 {
+    data.iter().fold(0u8, |sum, b| sum.wrapping_add(*b))
+}
+
+/// Read a single byte, returning an `UnexpectedEof` error if the connection was closed.
+fn read_byte<R: Read>(reader: &mut R) -> io::Result<u8> {
+    let mut byte = [0];
+    reader.read_exact(&mut byte)?;
+    Ok(byte[0])
+}
+
+/// Read the next message from GDB.
+///
+/// Acknowledgements from GDB are skipped. If `ack` is true, received packets are acknowledged, and
+/// a retransmission is requested for packets with an invalid checksum.
+pub fn read_message<S: Read + Write>(stream: &mut S, ack: bool) -> io::Result<Message> {
+    loop {
+        match read_byte(stream)? {
+            INTERRUPT => return Ok(Message::Interrupt),
+            b'$' => {}
+            _ => continue,
+        }
+
+        // The checksum covers the data as transmitted, before escape sequences are decoded
+        let mut raw = Vec::new();
+        let mut data = Vec::new();
+        loop {
+            match read_byte(stream)? {
+                b'#' => break,
+                b'}' => {
+                    let escaped = read_byte(stream)?;
+                    raw.extend([b'}', escaped]);
+                    data.push(escaped ^ 0x20);
+                }
+                byte => {
+                    raw.push(byte);
+                    data.push(byte);
+                }
+            }
+        }
+
+        let digits = [read_byte(stream)?, read_byte(stream)?];
+        let valid = std::str::from_utf8(&digits)
+            .ok()
+            .and_then(|digits| u8::from_str_radix(digits, 16).ok())
+            == Some(checksum(&raw));
+
+        if ack {
+            stream.write_all(if valid { b"+" } else { b"-" })?;
+            stream.flush()?;
+        }
+        if valid || !ack {
+            return Ok(Message::Packet(data));
+        }
+    }
+}
+
+/// Send a packet to GDB.
+///
+/// If `ack` is true, the packet is retransmitted until GDB acknowledges it.
+pub fn write_packet<S: Read + Write>(stream: &mut S, data: &[u8], ack: bool) -> io::Result<()> {
+    let mut packet = Vec::with_capacity(data.len() + 4);
+    packet.push(b'$');
+    for byte in data {
+        // Escape bytes which have a special meaning in packets
+        if matches!(byte, b'$' | b'#' | b'}' | b'*') {
+            packet.extend([b'}', byte ^ 0x20]);
+        } else {
+            packet.push(*byte);
+        }
+    }
+    packet.extend(format!("#{:02x}", checksum(&packet[1..])).bytes());
+
+    loop {
+        stream.write_all(&packet)?;
+        stream.flush()?;
+        if !ack {
+            return Ok(());
+        }
+
+        loop {
+            match read_byte(stream)? {
+                b'+' => return Ok(()),
+                b'-' => break,
+                _ => continue,
+            }
+        }
+    }
+}
+
+/// Encode bytes as a string of hex digits.
+pub fn encode_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+/// Decode a string of hex digits to bytes.
+///
+/// Returns `None` if the string has an odd length, or contains characters which are not hex
+/// digits.
+pub fn decode_hex(hex: &[u8]) -> Option<Vec<u8>> {
+    if !hex.len().is_multiple_of(2) {
+        return None;
+    }
+
+    hex.chunks(2)
+        .map(|digits| {
+            let digits = std::str::from_utf8(digits).ok()?;
+            u8::from_str_radix(digits, 16).ok()
+        })
+        .collect()
+}
+
+/// Parse a hex number, as used for addresses, lengths, and register numbers in packets.
+pub fn parse_hex(hex: &[u8]) -> Option<u64> {
+    u64::from_str_radix(std::str::from_utf8(hex).ok()?, 16).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{decode_hex, encode_hex, read_message, write_packet, Message};
+    use std::io::{self, Cursor, Read, Write};
+
+    /// An in-memory stream, reading from a fixed input & recording output.
+    struct Stream {
+        input: Cursor<Vec<u8>>,
+        output: Vec<u8>,
+    }
+
+    impl Stream {
+        fn new(input: &[u8]) -> Self {
+            Self {
+                input: Cursor::new(input.to_vec()),
+                output: Vec::new(),
+            }
+        }
+    }
+
+    impl Read for Stream {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            self.input.read(buf)
+        }
+    }
+
+    impl Write for Stream {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.output.write(buf)
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn read() {
+        let mut stream = Stream::new(b"+$g#67$m0,4#fd\x03");
+        assert_eq!(
+            read_message(&mut stream, true).unwrap(),
+            Message::Packet(b"g".to_vec())
+        );
+        assert_eq!(stream.output, b"+");
+
+        // Bad checksum requests retransmission
+        let mut stream = Stream::new(b"$g#00$g#67");
+        assert_eq!(
+            read_message(&mut stream, true).unwrap(),
+            Message::Packet(b"g".to_vec())
+        );
+        assert_eq!(stream.output, b"-+");
+
+        let mut stream = Stream::new(b"\x03");
+        assert_eq!(read_message(&mut stream, true).unwrap(), Message::Interrupt);
+    }
+
+    #[test]
+    fn write() {
+        let mut stream = Stream::new(b"-+");
+        write_packet(&mut stream, b"OK", true).unwrap();
+        assert_eq!(stream.output, b"$OK#9a$OK#9a");
+
+        // Special characters are escaped
+        let mut stream = Stream::new(b"");
+        write_packet(&mut stream, b"a#b", false).unwrap();
+        assert_eq!(stream.output, b"$a}\x03b#43");
+    }
+
+    #[test]
+    fn hex() {
+        assert_eq!(encode_hex(&[0x01, 0xab]), "01ab");
+        assert_eq!(decode_hex(b"01aB"), Some(vec![0x01, 0xab]));
+        assert_eq!(decode_hex(b"0"), None);
+        assert_eq!(decode_hex(b"zz"), None);
+    }
+}
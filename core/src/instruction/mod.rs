@@ -11,17 +11,18 @@
 mod parts;
 
 use crate::error::ProcessorException;
-pub use parts::{InstructionParts, InstructionWordParts};
+pub use parts::{InstructionHalfWordParts, InstructionParts, InstructionWordParts};
 
-use crate::mmu::{LoadSpec, StoreSpec};
+use crate::mmu::{AtomicSpec, LoadSpec, StoreSpec};
+use crate::processor::csr::CsrSpec;
 use crate::processor::register::RegisterFile;
+use crate::processor::PrivilegeLevel;
 
 /// Length of a RISC-V instruction.
 ///
-/// Only the [`Word`](Self::Word) (standard) format is supported by this implementation. n.b. This
-/// does not inherently imply lack of support for the RV64I/RV128I instruction sets, as these still
-/// use 32-bit instructions, although for the time being these are not supported by this
-/// implementation.
+/// Only the [`HalfWord`](Self::HalfWord) (compressed) and [`Word`](Self::Word) (standard) formats
+/// are supported by this implementation. n.b. This does not imply lack of support for the RV64I
+/// instruction set, as it still uses 32-bit instructions.
 #[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
 pub enum InstructionLength {
     /// The compressed 16-bit instruction length.
@@ -45,33 +46,146 @@ pub enum InstructionLength {
     Reserved,
 }
 
+impl InstructionLength {
+    /// Get the length of an instruction of this type, in bytes.
+    ///
+    /// For [`Reserved`](Self::Reserved) instructions, this returns the minimum length of 24 bytes.
+    pub fn bytes(&self) -> u32 {
+        match self {
+            InstructionLength::HalfWord => 2,
+            InstructionLength::Word => 4,
+            InstructionLength::WordAndHalf => 6,
+            InstructionLength::DoubleWord => 8,
+            InstructionLength::Custom(bits) => *bits as u32 / 8,
+            InstructionLength::Reserved => 24,
+        }
+    }
+}
+
+/// Cache-block management operation performed by an instruction.
+///
+/// The hart has no caches, so only [`Zero`](Self::Zero) has any effect on memory, through the
+/// stores requested alongside it. Each operation must still be enabled for the current privilege
+/// level.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum CacheBlockOperation {
+    /// Write the cache block back to memory, if it has been modified.
+    Clean,
+
+    /// Write the cache block back to memory, if it has been modified, then invalidate it.
+    Flush,
+
+    /// Invalidate the cache block, discarding any modifications.
+    Invalidate,
+
+    /// Zero the cache block.
+    Zero,
+}
+
 /// Result of executing an instruction.
 ///
-/// This is used to communicate to the hart whether it needs to jump or store a value in memory.
-#[derive(Clone, Copy, Debug, Default, Eq, Hash, PartialEq)]
+/// This is used to communicate to the hart whether it needs to jump, store values in memory, access
+/// a CSR, return from a trap handler, discard the instruction it has already decoded, or check a
+/// cache-block management operation is permitted.
+#[derive(Clone, Debug, Default, Eq, Hash, PartialEq)]
 pub struct InstructionResult {
     /// If set to `Some(addr)`, the hart will jump to `addr` following the instruction execution.
-    pub jump: Option<u32>,
+    pub jump: Option<u64>,
 
     /// If set to `Some(store_spec)`, the hart will write a value to memory according to the
     /// provided [`StoreSpec`].
     pub store: Option<StoreSpec>,
+
+    /// Values the hart will write to memory, in order, according to the provided [`StoreSpec`]s.
+    ///
+    /// This is used by instructions which store multiple values (e.g. vector stores), and is
+    /// performed after [`store`](Self::store). If a store fails, the stores preceding it have still
+    /// been performed.
+    pub stores: Vec<StoreSpec>,
+
+    /// If set to `Some(csr_spec)`, the hart will access a CSR according to the provided
+    /// [`CsrSpec`].
+    pub csr: Option<CsrSpec>,
+
+    /// If set to `Some(privilege)`, the hart will return from a trap handler running at the
+    /// provided privilege level.
+    pub trap_return: Option<PrivilegeLevel>,
+
+    /// If set, the hart will discard the next instruction, which it has already decoded, and fetch
+    /// it again from memory.
+    ///
+    /// This synchronises the instruction stream with any stores which have been performed.
+    pub flush: bool,
+
+    /// If set to `Some(op)`, the hart will check the provided cache-block management operation is
+    /// enabled at its current privilege level by `menvcfg` & `senvcfg`, raising an
+    /// IllegalInstruction exception if not. Any stores are only performed if it is enabled.
+    pub cache_block: Option<CacheBlockOperation>,
 }
 
 impl InstructionResult {
     /// Create an InstructionResult which will instruct the hart to jump to the provided address.
-    pub fn set_jump(addr: u32) -> Self {
-        let mut result = Self::default();
-        result.jump = Some(addr);
-        result
+    pub fn set_jump(addr: u64) -> Self {
+        Self {
+            jump: Some(addr),
+            ..Self::default()
+        }
     }
 
     /// Create an InstructionResult which will instruct the hart to store a value to memory
     /// according to the provided [`StoreSpec`].
     pub fn set_store(store: StoreSpec) -> Self {
-        let mut result = Self::default();
-        result.store = Some(store);
-        result
+        Self {
+            store: Some(store),
+            ..Self::default()
+        }
+    }
+
+    /// Create an InstructionResult which will instruct the hart to store multiple values to memory,
+    /// in order, according to the provided [`StoreSpec`]s.
+    pub fn set_stores(stores: Vec<StoreSpec>) -> Self {
+        Self {
+            stores,
+            ..Self::default()
+        }
+    }
+
+    /// Create an InstructionResult which will instruct the hart to access a CSR according to the
+    /// provided [`CsrSpec`].
+    pub fn set_csr(csr: CsrSpec) -> Self {
+        Self {
+            csr: Some(csr),
+            ..Self::default()
+        }
+    }
+
+    /// Create an InstructionResult which will instruct the hart to return from a trap handler
+    /// running at the provided privilege level.
+    pub fn set_trap_return(privilege: PrivilegeLevel) -> Self {
+        Self {
+            trap_return: Some(privilege),
+            ..Self::default()
+        }
+    }
+
+    /// Create an InstructionResult which will instruct the hart to fetch the next instruction again
+    /// from memory.
+    pub fn set_flush() -> Self {
+        Self {
+            flush: true,
+            ..Self::default()
+        }
+    }
+
+    /// Create an InstructionResult which will instruct the hart to check the provided cache-block
+    /// management operation is enabled, then store multiple values to memory, in order, according
+    /// to the provided [`StoreSpec`]s.
+    pub fn set_cache_block(op: CacheBlockOperation, stores: Vec<StoreSpec>) -> Self {
+        Self {
+            cache_block: Some(op),
+            stores,
+            ..Self::default()
+        }
     }
 }
 
@@ -87,6 +201,32 @@ pub trait Instruction: Send + Sync + 'static {
         Ok(None)
     }
 
+    /// Returns an [`AtomicSpec`] indicating an atomic memory operation the instruction requires.
+    ///
+    /// If this instruction needs to perform an indivisible read-modify-write access to memory, this
+    /// should return `Some(spec)`, where `spec` is an [`AtomicSpec`] describing the operation to
+    /// perform. The operation will be performed immediately before the instruction is executed,
+    /// and its result will be provided to the [`execute`](Self::execute) function as the `mem`
+    /// argument.
+    ///
+    /// If this returns `Some`, any [`LoadSpec`] returned by [`load`](Self::load) or
+    /// [`loads`](Self::loads) is ignored.
+    fn atomic(&self, _registers: &RegisterFile) -> Result<Option<AtomicSpec>, ProcessorException> {
+        Ok(None)
+    }
+
+    /// Returns a [`LoadSpec`] for each of the memory values the instruction requires, if it
+    /// requires more than one (e.g. a vector load).
+    ///
+    /// The values will be loaded from memory in order, immediately before the instruction is
+    /// executed, then provided to the [`execute_loads`](Self::execute_loads) function instead of
+    /// [`execute`](Self::execute). If any value cannot be loaded, the instruction is not executed.
+    ///
+    /// If this returns any `LoadSpec`s, any `LoadSpec` returned by [`load`](Self::load) is ignored.
+    fn loads(&self, _registers: &RegisterFile) -> Result<Vec<LoadSpec>, ProcessorException> {
+        Ok(Vec::new())
+    }
+
     /// Execute this instruction.
     ///
     /// `registers` is a reference to the [`RegisterFile`] of the hart on which this instruction is
@@ -94,17 +234,31 @@ pub trait Instruction: Send + Sync + 'static {
     ///
     /// If the [`load`](Self::load) function of this instruction returns a [`LoadSpec`], then a
     /// value will be retrieved from memory according to this spec, and supplied as the `mem`
-    /// argument for this function. If the load function returned `None`, the value of `mem` is
-    /// unspecified.
+    /// argument for this function. Similarly, if the [`atomic`](Self::atomic) function returns an
+    /// [`AtomicSpec`], the result of the atomic operation is supplied as `mem`. If neither function
+    /// requested a memory access, the value of `mem` is unspecified.
     ///
-    /// Returns an [`InstructionResult`], which can be used to perform a jump or store a value to
-    /// memory, if required.
+    /// Returns an [`InstructionResult`], which can be used to perform a jump, store values to
+    /// memory, access a CSR, or return from a trap handler, if required.
     fn execute(
         &self,
         registers: &mut RegisterFile,
-        mem: i32,
+        mem: i64,
     ) -> Result<InstructionResult, ProcessorException>;
 
+    /// Execute this instruction, having loaded the values requested by [`loads`](Self::loads).
+    ///
+    /// `mem` holds the loaded values, in the order their [`LoadSpec`]s were returned. This is only
+    /// called if `loads` returned at least one `LoadSpec`: By default, it calls
+    /// [`execute`](Self::execute), discarding the values.
+    fn execute_loads(
+        &self,
+        registers: &mut RegisterFile,
+        _mem: &[i64],
+    ) -> Result<InstructionResult, ProcessorException> {
+        self.execute(registers, 0)
+    }
+
     /// Provide a human-readable decoding of this instruction.
     ///
     /// This should correspond loosely to the assembly a human would type for this instruction. For
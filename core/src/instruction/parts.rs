@@ -1,7 +1,8 @@
 //! Basic initial processing for the RISC-V instruction formats.
 //!
 //! This module contains the logic for extracting opcodes, register numbers, and immediate values
-//! from raw binary instructions.
+//! from raw binary instructions, for both the standard 32-bit instruction formats and the 16-bit
+//! compressed instruction formats.
 
 use crate::error::ProcessorException;
 use crate::instruction::InstructionLength;
@@ -9,6 +10,9 @@ use crate::instruction::InstructionLength;
 /// Represents the component parts of an instruction of any length.
 #[derive(Clone, Debug, Eq, Hash, PartialEq)]
 pub enum InstructionParts {
+    /// This instruction is of the compressed 16-bit length.
+    HalfWord(InstructionHalfWordParts),
+
     /// This instruction is of the standard 32-bit length.
     Word(InstructionWordParts),
 }
@@ -19,6 +23,9 @@ impl InstructionParts {
     /// Uses [`Self::identify_instruction_length`] to determine the length of the instruction.
     pub fn new(raw: u32) -> Result<Self, ProcessorException> {
         match Self::identify_instruction_length(raw) {
+            InstructionLength::HalfWord => {
+                Ok(Self::HalfWord(InstructionHalfWordParts::new(raw as u16)))
+            }
             InstructionLength::Word => Ok(Self::Word(InstructionWordParts::new(raw))),
             _ => Err(ProcessorException::IllegalInstruction),
         }
@@ -49,18 +56,43 @@ impl InstructionParts {
     }
 
     /// Return the opcode of this instruction.
+    ///
+    /// For compressed instructions, this is the quadrant (the lowest two bits of the instruction),
+    /// which never overlaps with the opcode of a 32-bit instruction.
     pub fn opcode(&self) -> u8 {
         match self {
+            Self::HalfWord(parts) => parts.opcode,
             Self::Word(parts) => parts.opcode,
         }
     }
 
+    /// Get a reference to the underlying [`InstructionHalfWordParts`].
+    ///
+    /// If this instruction is of the compressed 16-bit length, returns a reference to the
+    /// underlying parts of the instruction. Otherwise returns an error.
+    pub fn halfword(&self) -> Result<&InstructionHalfWordParts, ProcessorException> {
+        match self {
+            Self::HalfWord(parts) => Ok(parts),
+            _ => Err(ProcessorException::IllegalInstruction),
+        }
+    }
+
+    /// Convert to [`InstructionHalfWordParts`].
+    ///
+    /// If this instruction is of the compressed 16-bit length, returns the underlying parts of the
+    /// instruction. Otherwise returns an error.
+    pub fn into_halfword(self) -> Result<InstructionHalfWordParts, ProcessorException> {
+        match self {
+            Self::HalfWord(parts) => Ok(parts),
+            _ => Err(ProcessorException::IllegalInstruction),
+        }
+    }
+
     /// Get a reference to the underlying [`InstructionWordParts`].
     ///
     /// If this instruction is of the standard 32-bit length, returns a reference to the underlying
     /// parts of the instruction. Otherwise returns an error.
     pub fn word(&self) -> Result<&InstructionWordParts, ProcessorException> {
-        #[allow(unreachable_patterns)]
         match self {
             Self::Word(parts) => Ok(parts),
             _ => Err(ProcessorException::IllegalInstruction),
@@ -72,7 +104,6 @@ impl InstructionParts {
     /// If this instruction is of the standard 32-bit length, returns the underlying parts of the
     /// instruction. Otherwise returns an error.
     pub fn into_word(self) -> Result<InstructionWordParts, ProcessorException> {
-        #[allow(unreachable_patterns)]
         match self {
             Self::Word(parts) => Ok(parts),
             _ => Err(ProcessorException::IllegalInstruction),
@@ -171,18 +202,176 @@ impl InstructionWordParts {
             rs2: rs2 as u8,
             funct3: funct3 as u8,
             funct7: funct7 as u8,
-            imm_i: imm_i as i32,
-            imm_s: imm_s as i32,
-            imm_b: imm_b as i32,
-            imm_u: imm_u as i32,
-            imm_j: imm_j as i32,
+            imm_i,
+            imm_s,
+            imm_b,
+            imm_u,
+            imm_j,
+        }
+    }
+}
+
+/// Represents the component parts of an instruction of the compressed 16-bit length.
+///
+/// The compressed instruction formats are far less regular than the standard formats: Register
+/// fields may address all 32 registers, or only the 8 most popular registers (`x8` to `x15`), and
+/// immediate bits are heavily shuffled. The fields here are extracted according to each of the
+/// formats listed in the RISC-V spec, Section 16.2 (Compressed Instruction Formats). The immediate
+/// values for the CSS, CL, and CS formats are decoded as for word-width loads/stores: Other
+/// instructions which scale their immediates differently (e.g. C.LWSP, C.ADDI16SP, C.LUI) must
+/// decode them from [`raw`](Self::raw).
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+pub struct InstructionHalfWordParts {
+    /// The raw instruction represented by these parts.
+    pub raw: u16,
+
+    /// Opcode (quadrant) for this instruction.
+    pub opcode: u8,
+
+    /// Two-bit minor opcode.
+    ///
+    /// Used by the CA instruction format.
+    pub funct2: u8,
+
+    /// Three-bit minor opcode.
+    ///
+    /// Used by the CI, CSS, CIW, CL, CS, CB, CJ instruction formats.
+    pub funct3: u8,
+
+    /// Four-bit minor opcode.
+    ///
+    /// Used by the CR instruction format.
+    pub funct4: u8,
+
+    /// Six-bit minor opcode.
+    ///
+    /// Used by the CA instruction format.
+    pub funct6: u8,
+
+    /// Destination register for this instruction, which is also the first source register.
+    ///
+    /// Used by the CR, CI instruction formats.
+    pub rd: u8,
+
+    /// Second source register for this instruction.
+    ///
+    /// Used by the CR, CSS instruction formats.
+    pub rs2: u8,
+
+    /// Destination register for this instruction, in the range `x8` to `x15`.
+    ///
+    /// Used by the CIW, CL instruction formats.
+    pub rd_prime: u8,
+
+    /// First source register for this instruction, in the range `x8` to `x15`.
+    ///
+    /// Used by the CL, CS, CA, CB instruction formats. For the CA format, and some CB format
+    /// instructions, this is also the destination register.
+    pub rs1_prime: u8,
+
+    /// Second source register for this instruction, in the range `x8` to `x15`.
+    ///
+    /// Used by the CS, CA instruction formats.
+    pub rs2_prime: u8,
+
+    /// Sign-extended 6-bit immediate value encoded by this instruction when interpreted in CI
+    /// format.
+    ///
+    /// CB format arithmetic instructions (C.SRLI, C.SRAI, C.ANDI) also use this encoding.
+    pub imm_ci: i32,
+
+    /// Zero-extended word offset encoded by this instruction when interpreted in CSS format.
+    pub imm_css: i32,
+
+    /// Zero-extended immediate value encoded by this instruction when interpreted in CIW format.
+    pub imm_ciw: i32,
+
+    /// Zero-extended word offset encoded by this instruction when interpreted in CL format.
+    pub imm_cl: i32,
+
+    /// Zero-extended word offset encoded by this instruction when interpreted in CS format.
+    pub imm_cs: i32,
+
+    /// Sign-extended branch offset encoded by this instruction when interpreted in CB format.
+    pub imm_cb: i32,
+
+    /// Sign-extended jump offset encoded by this instruction when interpreted in CJ format.
+    pub imm_cj: i32,
+}
+
+impl InstructionHalfWordParts {
+    /// Extract the raw component parts of this 16-bit instruction.
+    pub fn new(raw: u16) -> Self {
+        let raw32 = raw as u32;
+        let bit = |n: u32| (raw32 >> n) & 1;
+
+        let opcode = raw & 0b00000000_00000011;
+
+        // Minor opcode
+        let funct2 = (raw & 0b00000000_01100000) >> 5;
+        let funct3 = (raw & 0b11100000_00000000) >> 13;
+        let funct4 = (raw & 0b11110000_00000000) >> 12;
+        let funct6 = (raw & 0b11111100_00000000) >> 10;
+
+        // Registers
+        let rd = (raw & 0b00001111_10000000) >> 7;
+        let rs2 = (raw & 0b00000000_01111100) >> 2;
+        let rd_prime = ((raw & 0b00000000_00011100) >> 2) + 8;
+        let rs1_prime = ((raw & 0b00000011_10000000) >> 7) + 8;
+        let rs2_prime = rd_prime;
+
+        // Immediate data
+        let imm_ci = (((bit(12) << 5) | ((raw32 >> 2) & 0b11111)) as i32) << 26 >> 26;
+        let imm_css = (((raw32 >> 9) & 0b1111) << 2) | (((raw32 >> 7) & 0b11) << 6);
+        let imm_ciw = (((raw32 >> 11) & 0b11) << 4)
+            | (((raw32 >> 7) & 0b1111) << 6)
+            | (bit(6) << 2)
+            | (bit(5) << 3);
+        let imm_cl = (((raw32 >> 10) & 0b111) << 3) | (bit(6) << 2) | (bit(5) << 6);
+        let imm_cb = (((bit(12) << 8)
+            | (((raw32 >> 10) & 0b11) << 3)
+            | (((raw32 >> 5) & 0b11) << 6)
+            | (((raw32 >> 3) & 0b11) << 1)
+            | (bit(2) << 5)) as i32)
+            << 23
+            >> 23;
+        let imm_cj = (((bit(12) << 11)
+            | (bit(11) << 4)
+            | (((raw32 >> 9) & 0b11) << 8)
+            | (bit(8) << 10)
+            | (bit(7) << 6)
+            | (bit(6) << 7)
+            | (((raw32 >> 3) & 0b111) << 1)
+            | (bit(2) << 5)) as i32)
+            << 20
+            >> 20;
+
+        Self {
+            raw,
+            opcode: opcode as u8,
+            funct2: funct2 as u8,
+            funct3: funct3 as u8,
+            funct4: funct4 as u8,
+            funct6: funct6 as u8,
+            rd: rd as u8,
+            rs2: rs2 as u8,
+            rd_prime: rd_prime as u8,
+            rs1_prime: rs1_prime as u8,
+            rs2_prime: rs2_prime as u8,
+            imm_ci,
+            imm_css: imm_css as i32,
+            imm_ciw: imm_ciw as i32,
+            imm_cl: imm_cl as i32,
+            imm_cs: imm_cl as i32,
+            imm_cb,
+            imm_cj,
         }
     }
 }
 
 #[cfg(test)]
 mod tests {
-    use super::{InstructionParts, InstructionWordParts};
+    use super::{InstructionHalfWordParts, InstructionParts, InstructionWordParts};
     use crate::instruction::InstructionLength;
 
     #[test]
@@ -210,13 +399,10 @@ mod tests {
             InstructionParts::identify_instruction_length(0x2423_12a9),
             InstructionLength::HalfWord,
         );
-        /*
-        // TODO: Uncomment when supported
         assert!(matches!(
-            InstructionParts::new(0x0000_12a9),
+            InstructionParts::new(0x0000_12a9).unwrap(),
             InstructionParts::HalfWord(_)
         ));
-         */
     }
 
     // From https://inst.eecs.berkeley.edu/~cs61c/resources/su18_lec/Lecture7.pdf
@@ -291,4 +477,109 @@ mod tests {
         assert_eq!(instruction.rd, 5);
         assert_eq!(instruction.imm_j, 164);
     }
+
+    #[test]
+    fn split_cr_format() {
+        let instruction = InstructionHalfWordParts::new(0x94aa); // c.add x9, x10
+        assert_eq!(instruction.opcode, 0b10);
+        assert_eq!(instruction.funct4, 0b1001);
+        assert_eq!(instruction.rd, 9);
+        assert_eq!(instruction.rs2, 10);
+    }
+
+    #[test]
+    fn split_ci_format() {
+        let instruction = InstructionHalfWordParts::new(0x1281); // c.addi x5, -32
+        assert_eq!(instruction.opcode, 0b01);
+        assert_eq!(instruction.funct3, 0b000);
+        assert_eq!(instruction.rd, 5);
+        assert_eq!(instruction.imm_ci, -32);
+
+        let instruction = InstructionHalfWordParts::new(0x437d); // c.li x6, 31
+        assert_eq!(instruction.opcode, 0b01);
+        assert_eq!(instruction.funct3, 0b010);
+        assert_eq!(instruction.rd, 6);
+        assert_eq!(instruction.imm_ci, 31);
+    }
+
+    #[test]
+    fn split_css_format() {
+        let instruction = InstructionHalfWordParts::new(0xdfae); // c.swsp x11, 252(x2)
+        assert_eq!(instruction.opcode, 0b10);
+        assert_eq!(instruction.funct3, 0b110);
+        assert_eq!(instruction.rs2, 11);
+        assert_eq!(instruction.imm_css, 252);
+    }
+
+    #[test]
+    fn split_ciw_format() {
+        let instruction = InstructionHalfWordParts::new(0x1fe0); // c.addi4spn x8, x2, 1020
+        assert_eq!(instruction.opcode, 0b00);
+        assert_eq!(instruction.funct3, 0b000);
+        assert_eq!(instruction.rd_prime, 8);
+        assert_eq!(instruction.imm_ciw, 1020);
+    }
+
+    #[test]
+    fn split_cl_format() {
+        let instruction = InstructionHalfWordParts::new(0x5fe4); // c.lw x9, 124(x15)
+        assert_eq!(instruction.opcode, 0b00);
+        assert_eq!(instruction.funct3, 0b010);
+        assert_eq!(instruction.rd_prime, 9);
+        assert_eq!(instruction.rs1_prime, 15);
+        assert_eq!(instruction.imm_cl, 124);
+    }
+
+    #[test]
+    fn split_cs_format() {
+        let instruction = InstructionHalfWordParts::new(0xc028); // c.sw x10, 64(x8)
+        assert_eq!(instruction.opcode, 0b00);
+        assert_eq!(instruction.funct3, 0b110);
+        assert_eq!(instruction.rs2_prime, 10);
+        assert_eq!(instruction.rs1_prime, 8);
+        assert_eq!(instruction.imm_cs, 64);
+    }
+
+    #[test]
+    fn split_ca_format() {
+        let instruction = InstructionHalfWordParts::new(0x8c05); // c.sub x8, x9
+        assert_eq!(instruction.opcode, 0b01);
+        assert_eq!(instruction.funct6, 0b100011);
+        assert_eq!(instruction.funct2, 0b00);
+        assert_eq!(instruction.rs1_prime, 8);
+        assert_eq!(instruction.rs2_prime, 9);
+    }
+
+    #[test]
+    fn split_cb_format() {
+        let instruction = InstructionHalfWordParts::new(0xd001); // c.beqz x8, offset = -256 bytes
+        assert_eq!(instruction.opcode, 0b01);
+        assert_eq!(instruction.funct3, 0b110);
+        assert_eq!(instruction.rs1_prime, 8);
+        assert_eq!(instruction.imm_cb, -256);
+
+        let instruction = InstructionHalfWordParts::new(0xeffd); // c.bnez x15, offset = 254 bytes
+        assert_eq!(instruction.funct3, 0b111);
+        assert_eq!(instruction.rs1_prime, 15);
+        assert_eq!(instruction.imm_cb, 254);
+
+        let instruction = InstructionHalfWordParts::new(0x997d); // c.andi x10, -1
+        assert_eq!(instruction.rs1_prime, 10);
+        assert_eq!(instruction.imm_ci, -1);
+    }
+
+    #[test]
+    fn split_cj_format() {
+        let instruction = InstructionHalfWordParts::new(0x3001); // c.jal offset = -2048 bytes
+        assert_eq!(instruction.opcode, 0b01);
+        assert_eq!(instruction.funct3, 0b001);
+        assert_eq!(instruction.imm_cj, -2048);
+
+        let instruction = InstructionHalfWordParts::new(0x2ffd); // c.jal offset = 2046 bytes
+        assert_eq!(instruction.imm_cj, 2046);
+
+        let instruction = InstructionHalfWordParts::new(0xa9c9); // c.j offset = 1234 bytes
+        assert_eq!(instruction.funct3, 0b101);
+        assert_eq!(instruction.imm_cj, 1234);
+    }
 }
@@ -5,20 +5,34 @@
 //! of these emulated hardware components to provide a RISC-V bare-metal EEI.
 
 pub mod clock;
+pub mod elf;
+pub mod entropy;
 pub mod error;
 pub mod extension;
+pub mod gdb;
 pub mod instruction;
 pub mod mmu;
 pub mod processor;
 pub mod ram;
 pub mod rom;
+pub mod uart;
 
 use crate::error::ProcessorException;
+use crate::mmu::Addressable;
 use bus::{Bus, BusReader};
 use log::info;
-use std::io::Read;
+use std::io::{self, Read};
 use std::sync::mpsc::TryRecvError;
-use std::sync::{Arc, RwLock};
+use std::sync::{Arc, Mutex};
+
+/// Address at which the ROM is mapped.
+pub const ROM_BASE: usize = 0x0000_0000;
+
+/// Address at which the RAM is mapped.
+pub const RAM_BASE: usize = 0x8000_0000;
+
+/// Address at which the UART is mapped, if one is attached.
+pub const UART_BASE: usize = 0x1000_0000;
 
 /// A control message sent to the processor.
 ///
@@ -59,9 +73,15 @@ pub struct Config<R, C> {
 
     /// Rom from which execution should begin.
     ///
-    /// The processor will start execution at address `0x00000000` of this ROM. This could be used
-    /// to define a bootloader, or just a small RISC-V program which does not need to dynamically
-    /// load any program code.
+    /// This may either be a raw binary, or an ELF file. A raw binary is mapped at [`ROM_BASE`],
+    /// and the processor will start execution at its first byte. This could be used to define a
+    /// bootloader, or just a small RISC-V program which does not need to dynamically load any
+    /// program code.
+    ///
+    /// If this is an ELF file, each of its segments is loaded at the address it specifies: Segments
+    /// below [`RAM_BASE`] are placed in a ROM mapped at [`ROM_BASE`], sized to fit them, while
+    /// segments above are placed in RAM. The processor will start execution at the entry point of
+    /// the file.
     pub rom: R,
 
     /// Size for the RAM, in bytes.
@@ -69,6 +89,12 @@ pub struct Config<R, C> {
     /// This will all be allocated upfront.
     pub ram_size: usize,
 
+    /// Backend for the serial port.
+    ///
+    /// If set, an NS16550A-compatible [`UART`](uart::UART) is mapped at [`UART_BASE`], connected to
+    /// the host via this backend.
+    pub serial: Option<Box<dyn uart::SerialBackend>>,
+
     /// [`Clock`](clock::Clock) to use to run the processor.
     pub clock: C,
 
@@ -86,11 +112,11 @@ pub enum InstructionLog {
         /// If None, no instruction was executed on the most recent cycle, only decoded.
         instr: Option<String>,
 
-        /// Current values of all registers.
-        registers: Vec<i32>,
+        /// Current values of the integer registers, `x0` to `x31`.
+        registers: Vec<i64>,
 
         /// Current value of the program counter.
-        pc: u32,
+        pc: u64,
     },
 
     /// An exception was encountered.
@@ -98,11 +124,11 @@ pub enum InstructionLog {
         /// The exception which occurred.
         exception: ProcessorException,
 
-        /// Current values of all registers.
-        registers: Vec<i32>,
+        /// Current values of the integer registers, `x0` to `x31`.
+        registers: Vec<i64>,
 
         /// Current value of the program counter.
-        pc: u32,
+        pc: u64,
     },
 }
 
@@ -123,6 +149,9 @@ pub struct ExecutionEnvironment<C> {
     ///
     /// This is used to report what the processor is doing to the UI.
     log_bus: Bus<InstructionLog>,
+
+    /// Symbols from the ELF file the ROM was loaded from, if any.
+    symbols: Vec<elf::Symbol>,
 }
 
 impl<C> ExecutionEnvironment<C>
@@ -130,15 +159,64 @@ where
     C: clock::Clock,
 {
     /// Create a new RISC-V system.
-    pub fn new<R: Read>(config: Config<R, C>) -> Result<Self, std::io::Error> {
-        let rom = rom::ROM::from(config.rom)?;
+    ///
+    /// The ROM is mapped at [`ROM_BASE`], the RAM at [`RAM_BASE`], and the UART (if any) at
+    /// [`UART_BASE`]. Returns an error if the ROM could not be read, or the devices do not fit in
+    /// the address space.
+    pub fn new<R: Read>(mut config: Config<R, C>) -> Result<Self, io::Error> {
+        let mut contents = Vec::new();
+        config.rom.read_to_end(&mut contents)?;
+        let elf = if elf::Elf::is_elf(&contents) {
+            let elf = elf::Elf::parse(&contents)
+                .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+            Some(elf)
+        } else {
+            None
+        };
+
+        // An ELF file's ROM segments are loaded once the memory map is in place. If it has no such
+        // segments, no ROM is mapped.
+        let rom = match &elf {
+            Some(elf) => elf
+                .segments
+                .iter()
+                .filter(|segment| segment.addr < RAM_BASE)
+                .map(|segment| segment.end() - ROM_BASE)
+                .max()
+                .map(|size| rom::ROM::new(vec![0u8; size])),
+            None => Some(rom::ROM::new(contents)),
+        };
         let ram = ram::RAM::new(config.ram_size);
-        let mmu = Arc::new(RwLock::new(mmu::MMU::new(rom, ram)));
+
+        let mut mmu = mmu::MMU::new();
+        let map_err = |e| io::Error::new(io::ErrorKind::InvalidInput, e);
+        if let Some(rom) = rom {
+            mmu.map(ROM_BASE, rom.reserve(), Box::new(rom))
+                .map_err(map_err)?;
+        }
+        mmu.map(RAM_BASE, config.ram_size, Box::new(ram))
+            .map_err(map_err)?;
+        if let Some(backend) = config.serial {
+            let uart = uart::UART::new(backend);
+            mmu.map(UART_BASE, uart.reserve(), Box::new(uart))
+                .map_err(map_err)?;
+        }
+
+        let (reset_vector, symbols) = match elf {
+            Some(elf) => {
+                elf.load(&mut mmu)
+                    .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+                (elf.entry as u64, elf.symbols)
+            }
+            None => (ROM_BASE as u64, Vec::new()),
+        };
+        let mmu = Arc::new(Mutex::new(mmu));
 
         let processor_config = processor::ProcessorConfig {
             harts: config.harts,
             mmu,
             extensions: config.extensions,
+            reset_vector,
         };
         let processor = processor::Processor::new(processor_config);
 
@@ -147,24 +225,44 @@ where
             clock: config.clock,
             control_rx: config.control_rx,
             log_bus: Bus::new(0xffff),
+            symbols,
         })
     }
 
+    /// Symbols from the ELF file the ROM was loaded from.
+    ///
+    /// This is empty if the ROM was a raw binary.
+    pub fn symbols(&self) -> &[elf::Symbol] {
+        &self.symbols
+    }
+
     /// Add a log message receiver.
     pub fn add_rx(&mut self) -> BusReader<InstructionLog> {
         self.log_bus.add_rx()
     }
 
-    /// Get the current values of all registers.
-    fn get_registers(&self) -> Vec<i32> {
-        self.processor
-            .hart
-            .registers
-            .iter()
-            .map(|(_, reg)| reg.load().unwrap_or(0))
+    /// Get the current values of the integer registers.
+    ///
+    /// Registers the hart doesn't have (e.g. `x16` to `x31` on RV32E) read as zero.
+    fn get_registers(&self) -> Vec<i64> {
+        let registers = &self.processor.hart.registers;
+        (0..32)
+            .map(|i| registers.get(&i).map_or(0, |reg| reg.load().unwrap_or(0)))
             .collect()
     }
 
+    /// Block until the next clock tick, advancing the processor's `time` timebase.
+    ///
+    /// The timebase counts every tick of the clock, including those missed while the processor
+    /// was still running the previous cycle.
+    fn tick(&mut self) {
+        let ticks = match self.clock.next_tick() {
+            clock::ClockStatus::Ok => 1,
+            clock::ClockStatus::MissedTicks(missed) => missed as u64 + 1,
+        };
+        self.processor.hart.counters.advance_time(ticks);
+    }
+
     /// Run the processor.
     ///
     /// This will block indefinitely, until the processor halts or encounters an unhandled
@@ -186,7 +284,7 @@ where
                 }
             }
 
-            self.clock.next_tick();
+            self.tick();
 
             match self.processor.cycle() {
                 Ok(()) => self.log_bus.broadcast(InstructionLog::Ok {
@@ -3,45 +3,67 @@
 //! Computers consist of many memory-mapped devices. Our simple RISC-V system, for example, has both
 //! a RAM and ROM, and instructions or values may be loaded from either. More complicated systems
 //! will have many more memory-mapped peripherals. All of these are accessible from the processor
-//! via addresses in the same 32-bit address space (`0x00000000` to `0xffffffff`). The MMU maps
-//! processor memory accesses to specific devices, by dividing up the 32-bit address space into
-//! portions, each of which corresponds to a specific device, then translating the processor's
+//! via addresses in the same address space (`0x00000000` to `0xffffffff` for an RV32 hart). The MMU
+//! maps processor memory accesses to specific devices, by dividing up the address space into
+//! regions, each of which corresponds to a specific device, then translating the processor's
 //! addresses into addresses relative to each device.
+//!
+//! Regions are registered using [`MMU::map`], and may not overlap. Accesses to addresses which are
+//! not mapped to any device, or which cross the boundary of a region, raise an exception.
 
-use crate::error::ProcessorException;
-use crate::ram::RAM;
-use crate::rom::ROM;
+use crate::error::{MemoryAccessError, MemoryMapError, ProcessorException};
+use crate::instruction::{InstructionLength, InstructionParts};
 use std::fmt;
-use std::ops::Range;
+use std::ops::{self, Range};
 
 /// Type of value to retrieve from memory.
 ///
-/// Memory loads via [`MMU::load`] always result in an `i32` to store in a register, however this
-/// may be the result of loading a smaller value and extending it to fill a 32-bit space, either
+/// Memory loads via [`MMU::load`] always result in an `i64` to store in a register, however this
+/// may be the result of loading a smaller value and extending it to fill a 64-bit space, either
 /// through sign-extension, in the case of signed values, or zero-extension, in the case of unsigned
 /// values.
 #[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
 pub enum MemoryAccessType {
-    /// Load a word from memory.
+    /// Load a double-word from memory.
+    DoubleWord,
+
+    /// Load a word from memory, then sign-extend it to 64 bits.
     Word,
 
-    /// Load a half-word from memory, then sign-extend it to 32 bits.
+    /// Load a word from memory, then zero-extend it to 64 bits.
+    UnsignedWord,
+
+    /// Load a half-word from memory, then sign-extend it to 64 bits.
     SignedHalfWord,
 
-    /// Load a half-word from memory, then zero-extend it to 32 bits.
+    /// Load a half-word from memory, then zero-extend it to 64 bits.
     UnsignedHalfWord,
 
-    /// Load a byte from memory, then sign-extend it to 32 bits.
+    /// Load a byte from memory, then sign-extend it to 64 bits.
     SignedByte,
 
-    /// Load a byte from memory, then zero-extend it to 32 bits.
+    /// Load a byte from memory, then zero-extend it to 64 bits.
     UnsignedByte,
 }
 
+impl MemoryAccessType {
+    /// Number of bytes accessed by an access of this type.
+    pub fn bytes(&self) -> usize {
+        match self {
+            MemoryAccessType::DoubleWord => 8,
+            MemoryAccessType::Word | MemoryAccessType::UnsignedWord => 4,
+            MemoryAccessType::SignedHalfWord | MemoryAccessType::UnsignedHalfWord => 2,
+            MemoryAccessType::SignedByte | MemoryAccessType::UnsignedByte => 1,
+        }
+    }
+}
+
 impl fmt::Display for MemoryAccessType {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
+            MemoryAccessType::DoubleWord => f.write_str("d"),
             MemoryAccessType::Word => f.write_str("w"),
+            MemoryAccessType::UnsignedWord => f.write_str("wu"),
             MemoryAccessType::SignedHalfWord => f.write_str("h"),
             MemoryAccessType::UnsignedHalfWord => f.write_str("hu"),
             MemoryAccessType::SignedByte => f.write_str("b"),
@@ -75,19 +97,19 @@ impl LoadSpec {
 pub struct StoreSpec {
     /// Width of the value to store.
     ///
-    /// If this is less than 32 bits, the most significant bits are discarded.
+    /// If this is less than 64 bits, the most significant bits are discarded.
     pub access_type: MemoryAccessType,
 
     /// Address at which the value should be stored.
     pub addr: usize,
 
     /// Value to store.
-    pub value: i32,
+    pub value: i64,
 }
 
 impl StoreSpec {
     /// Create a new StoreSpec.
-    pub fn new(width: MemoryAccessType, addr: usize, value: i32) -> Self {
+    pub fn new(width: MemoryAccessType, addr: usize, value: i64) -> Self {
         Self {
             access_type: width,
             addr,
@@ -96,138 +118,972 @@ impl StoreSpec {
     }
 }
 
+/// Operation to perform as part of an atomic memory access.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum AtomicOperation {
+    /// Load a value from memory, and register a reservation on that value.
+    LoadReserved,
+
+    /// Store a value to memory, only if a valid reservation exists for that value.
+    StoreConditional,
+
+    /// Swap the value in memory with the provided value.
+    Swap,
+
+    /// Add the provided value to the value in memory.
+    Add,
+
+    /// Bitwise XOR the value in memory with the provided value.
+    Xor,
+
+    /// Bitwise AND the value in memory with the provided value.
+    And,
+
+    /// Bitwise OR the value in memory with the provided value.
+    Or,
+
+    /// Store the minimum of the provided value & the value in memory, as signed values.
+    Min,
+
+    /// Store the maximum of the provided value & the value in memory, as signed values.
+    Max,
+
+    /// Store the minimum of the provided value & the value in memory, as unsigned values.
+    MinU,
+
+    /// Store the maximum of the provided value & the value in memory, as unsigned values.
+    MaxU,
+
+    /// Swap the value in memory with the provided value, only if the value in memory is equal to
+    /// this expected value.
+    CompareAndSwap(i64),
+}
+
+/// Specification for an atomic read-modify-write memory access.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub struct AtomicSpec {
+    /// Operation to perform.
+    pub op: AtomicOperation,
+
+    /// Width of the value to operate on.
+    ///
+    /// This must be one of the signed types: [`MemoryAccessType::DoubleWord`],
+    /// [`MemoryAccessType::Word`], [`MemoryAccessType::SignedHalfWord`], or
+    /// [`MemoryAccessType::SignedByte`]. Narrower values are sign-extended to 64 bits when loaded.
+    pub access_type: MemoryAccessType,
+
+    /// Address of the value to operate on.
+    pub addr: usize,
+
+    /// Value to combine with the value in memory.
+    ///
+    /// Unused for [`AtomicOperation::LoadReserved`].
+    pub value: i64,
+}
+
+impl AtomicSpec {
+    /// Create a new AtomicSpec.
+    pub fn new(op: AtomicOperation, width: MemoryAccessType, addr: usize, value: i64) -> Self {
+        Self {
+            op,
+            access_type: width,
+            addr,
+            value,
+        }
+    }
+}
+
+/// Width of a single access to a memory-mapped device.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum AccessWidth {
+    /// An 8-bit access.
+    Byte,
+
+    /// A 16-bit access.
+    HalfWord,
+
+    /// A 32-bit access.
+    Word,
+
+    /// A 64-bit access.
+    DoubleWord,
+}
+
+impl AccessWidth {
+    /// Number of bytes accessed by an access of this width.
+    pub fn bytes(&self) -> usize {
+        match self {
+            AccessWidth::Byte => 1,
+            AccessWidth::HalfWord => 2,
+            AccessWidth::Word => 4,
+            AccessWidth::DoubleWord => 8,
+        }
+    }
+}
+
+/// A set of [`AccessWidth`]s supported by a device.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub struct AccessWidths(u8);
+
+impl AccessWidths {
+    /// No access widths.
+    pub const NONE: Self = Self(0);
+
+    /// 8-bit accesses.
+    pub const BYTE: Self = Self(1 << 0);
+
+    /// 16-bit accesses.
+    pub const HALFWORD: Self = Self(1 << 1);
+
+    /// 32-bit accesses.
+    pub const WORD: Self = Self(1 << 2);
+
+    /// 64-bit accesses.
+    pub const DOUBLEWORD: Self = Self(1 << 3);
+
+    /// All access widths.
+    pub const ALL: Self = Self(0b1111);
+
+    /// Returns true if this set contains the provided width.
+    pub fn contains(&self, width: AccessWidth) -> bool {
+        self.0 & Self::from(width).0 != 0
+    }
+}
+
+impl From<AccessWidth> for AccessWidths {
+    fn from(value: AccessWidth) -> Self {
+        match value {
+            AccessWidth::Byte => Self::BYTE,
+            AccessWidth::HalfWord => Self::HALFWORD,
+            AccessWidth::Word => Self::WORD,
+            AccessWidth::DoubleWord => Self::DOUBLEWORD,
+        }
+    }
+}
+
+impl ops::BitOr for AccessWidths {
+    type Output = Self;
+
+    fn bitor(self, rhs: Self) -> Self {
+        Self(self.0 | rhs.0)
+    }
+}
+
+/// A value loaded from/stored to a memory-mapped device, tagged with its width.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum MemoryValue {
+    /// An 8-bit value.
+    Byte(u8),
+
+    /// A 16-bit value.
+    HalfWord(u16),
+
+    /// A 32-bit value.
+    Word(u32),
+
+    /// A 64-bit value.
+    DoubleWord(u64),
+}
+
+impl MemoryValue {
+    /// Create a value of the provided width from the low bits of `value`.
+    pub fn new(width: AccessWidth, value: u64) -> Self {
+        match width {
+            AccessWidth::Byte => MemoryValue::Byte(value as u8),
+            AccessWidth::HalfWord => MemoryValue::HalfWord(value as u16),
+            AccessWidth::Word => MemoryValue::Word(value as u32),
+            AccessWidth::DoubleWord => MemoryValue::DoubleWord(value),
+        }
+    }
+
+    /// Decode a value of the provided width from its little-endian representation.
+    ///
+    /// `bytes` must be exactly as long as the width.
+    pub fn from_le_bytes(width: AccessWidth, bytes: &[u8]) -> Self {
+        match width {
+            AccessWidth::Byte => MemoryValue::Byte(bytes[0]),
+            AccessWidth::HalfWord => MemoryValue::HalfWord(u16::from_le_bytes(
+                bytes.try_into().expect("Slice length matches width"),
+            )),
+            AccessWidth::Word => MemoryValue::Word(u32::from_le_bytes(
+                bytes.try_into().expect("Slice length matches width"),
+            )),
+            AccessWidth::DoubleWord => MemoryValue::DoubleWord(u64::from_le_bytes(
+                bytes.try_into().expect("Slice length matches width"),
+            )),
+        }
+    }
+
+    /// Write the little-endian representation of this value to `bytes`.
+    ///
+    /// `bytes` must be exactly as long as the width of this value.
+    pub fn write_le_bytes(&self, bytes: &mut [u8]) {
+        match self {
+            MemoryValue::Byte(value) => bytes.copy_from_slice(&value.to_le_bytes()),
+            MemoryValue::HalfWord(value) => bytes.copy_from_slice(&value.to_le_bytes()),
+            MemoryValue::Word(value) => bytes.copy_from_slice(&value.to_le_bytes()),
+            MemoryValue::DoubleWord(value) => bytes.copy_from_slice(&value.to_le_bytes()),
+        }
+    }
+
+    /// Width of this value.
+    pub fn width(&self) -> AccessWidth {
+        match self {
+            MemoryValue::Byte(_) => AccessWidth::Byte,
+            MemoryValue::HalfWord(_) => AccessWidth::HalfWord,
+            MemoryValue::Word(_) => AccessWidth::Word,
+            MemoryValue::DoubleWord(_) => AccessWidth::DoubleWord,
+        }
+    }
+
+    /// This value, zero-extended to 64 bits.
+    pub fn as_u64(&self) -> u64 {
+        match self {
+            MemoryValue::Byte(value) => *value as u64,
+            MemoryValue::HalfWord(value) => *value as u64,
+            MemoryValue::Word(value) => *value as u64,
+            MemoryValue::DoubleWord(value) => *value,
+        }
+    }
+}
+
 /// Trait for devices which can be mapped to memory.
-pub trait Addressable {
-    /// Portion of the 32-bit address space to reserve for this device.
+///
+/// Loads take `&mut self`, so that devices can implement registers with read side-effects (e.g: a
+/// receive register which pops a value from a FIFO).
+pub trait Addressable: fmt::Debug + Send + Sync + 'static {
+    /// Portion of the 32-bit address space to reserve for this device by default.
     ///
-    /// This should be a power of 2.
+    /// This should be a power of 2. It is used as the size of the device's region when the device
+    /// is mapped without an explicit size.
     fn reserve(&self) -> usize;
 
-    /// Load a value from this device.
+    /// Access widths supported by this device.
+    ///
+    /// Accesses of any other width raise an exception without reaching the device. By default,
+    /// all widths are supported.
+    fn access_widths(&self) -> AccessWidths {
+        AccessWidths::ALL
+    }
+
+    /// Load a value of the provided width from this device.
     ///
-    /// `range` should be the range of addresses to load.
+    /// `addr` is the address to load from, relative to the start of the device's region.
     ///
-    /// If `range` is invalid for this device, or loads are not supported for this range, this
+    /// If `addr` is invalid for this device, or loads are not supported at this address, this
     /// should return an exception.
-    fn load_raw(&self, range: Range<usize>) -> Result<&[u8], ProcessorException>;
+    fn load(&mut self, addr: usize, width: AccessWidth) -> Result<MemoryValue, ProcessorException>;
 
     /// Store a value to this device.
     ///
-    /// `range` should be the range of addresses where the provided value should be stored.
+    /// `addr` is the address to store to, relative to the start of the device's region.
+    ///
+    /// If `addr` is invalid for this device, or stores are not supported at this address, this
+    /// should return an exception.
+    fn store(&mut self, addr: usize, value: MemoryValue) -> Result<(), ProcessorException>;
+
+    /// Initialise the contents of this device, as a program loader would.
     ///
-    /// If `values` is not the same size as `range`, `range` is invalid for this device, or stores
-    /// are not supported for this range, this should return an exception.
-    fn store_raw(&mut self, range: Range<usize>, values: &[u8]) -> Result<(), ProcessorException>;
+    /// `addr` is the address of the first byte to initialise, relative to the start of the
+    /// device's region. By default, this stores each byte in turn: Devices which cannot be written
+    /// by the processor (e.g: a ROM) should override this to allow their contents to be loaded.
+    fn preload(&mut self, addr: usize, bytes: &[u8]) -> Result<(), ProcessorException> {
+        for (i, byte) in bytes.iter().enumerate() {
+            self.store(addr + i, MemoryValue::Byte(*byte))?;
+        }
+        Ok(())
+    }
 }
 
-/// Memory-management unit.
+/// A region of the address space mapped to a device.
 #[derive(Debug)]
+struct Region {
+    /// Address of the start of the region.
+    base: usize,
+
+    /// Size of the region, in bytes.
+    size: usize,
+
+    /// The device to which accesses in this region are forwarded.
+    device: Box<dyn Addressable>,
+}
+
+impl Region {
+    /// Address immediately following the end of this region.
+    fn end(&self) -> usize {
+        self.base + self.size
+    }
+}
+
+/// Memory-management unit.
+#[derive(Debug, Default)]
 pub struct MMU {
-    rom: ROM,
-    ram: RAM,
+    /// Mapped regions, sorted by base address.
+    regions: Vec<Region>,
+
+    /// Address & length of the value reserved by the most recent load-reserved operation.
+    ///
+    /// Any store which overlaps this value invalidates the reservation. Currently there is only a
+    /// single hart, so only a single reservation is tracked.
+    reservation: Option<(usize, usize)>,
 }
 
 impl MMU {
-    /// Create a new MMU.
-    pub fn new(rom: ROM, ram: RAM) -> Self {
-        Self { rom, ram }
+    /// Create a new MMU, with no devices mapped.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Map a device to the region of `size` bytes starting at `base`.
+    ///
+    /// Returns an error if the region is empty, or overlaps a region which is already mapped.
+    pub fn map(
+        &mut self,
+        base: usize,
+        size: usize,
+        device: Box<dyn Addressable>,
+    ) -> Result<(), MemoryMapError> {
+        if size == 0 {
+            return Err(MemoryMapError::Empty);
+        }
+        let end = base.checked_add(size).ok_or(MemoryMapError::Overflow)?;
+
+        // Only the regions immediately before & after the new region can overlap it
+        let index = self.regions.partition_point(|region| region.base < base);
+        let overlaps_prev = index > 0 && self.regions[index - 1].end() > base;
+        let overlaps_next = index < self.regions.len() && self.regions[index].base < end;
+        if overlaps_prev || overlaps_next {
+            return Err(MemoryMapError::Overlap);
+        }
+
+        self.regions.insert(index, Region { base, size, device });
+        Ok(())
+    }
+
+    /// Iterate over the address ranges of all mapped regions, in ascending order.
+    pub fn regions(&self) -> impl Iterator<Item = Range<usize>> + '_ {
+        self.regions.iter().map(|region| region.base..region.end())
     }
 
-    /// Load a raw value from memory.
+    /// Find the region which should handle an access of the provided width at `addr`.
     ///
-    /// Returns an error if the provided range is not mapped to a single device.
-    pub fn load_raw(&self, range: Range<usize>) -> Result<&[u8], ProcessorException> {
-        if range.start & 0x80000000 == 0 {
-            self.rom.load_raw(range).into()
+    /// Returns the region, together with the address relative to the start of the region. Returns
+    /// an error if `addr` is not mapped, the access extends beyond the end of the region containing
+    /// `addr`, or the device does not support accesses of this width.
+    fn region(
+        &mut self,
+        addr: usize,
+        width: AccessWidth,
+    ) -> Result<(&mut Region, usize), ProcessorException> {
+        let region = self.containing_region(addr)?;
+        if width.bytes() > region.end() - addr {
+            Err(MemoryAccessError::CrossesBoundary.into())
+        } else if !region.device.access_widths().contains(width) {
+            Err(MemoryAccessError::UnsupportedWidth.into())
         } else {
-            self.ram
-                .load_raw(range.start & 0x7fffffff..range.end & 0x7fffffff)
-                .into()
+            let offset = addr - region.base;
+            Ok((region, offset))
         }
     }
 
+    /// Find the region containing `addr`, or return an error if `addr` is not mapped.
+    fn containing_region(&mut self, addr: usize) -> Result<&mut Region, ProcessorException> {
+        let index = self
+            .regions
+            .partition_point(|region| region.base <= addr)
+            .checked_sub(1)
+            .ok_or(MemoryAccessError::OutOfBounds)?;
+
+        let region = &mut self.regions[index];
+        if addr >= region.end() {
+            return Err(MemoryAccessError::OutOfBounds.into());
+        }
+        Ok(region)
+    }
+
+    /// Invalidate any reservation registered by a load-reserved operation.
+    pub fn clear_reservation(&mut self) {
+        self.reservation = None;
+    }
+
+    /// Load a value of the provided width from memory.
+    ///
+    /// Returns an error if the access is not mapped to a single device which supports accesses of
+    /// this width.
+    pub fn load_value(
+        &mut self,
+        addr: usize,
+        width: AccessWidth,
+    ) -> Result<MemoryValue, ProcessorException> {
+        let (region, offset) = self.region(addr, width)?;
+        region.device.load(offset, width)
+    }
+
+    /// Store a value to memory.
+    ///
+    /// Returns an error if the access is not mapped to a single device which supports accesses of
+    /// this width.
+    pub fn store_value(
+        &mut self,
+        addr: usize,
+        value: MemoryValue,
+    ) -> Result<(), ProcessorException> {
+        let end = addr
+            .checked_add(value.width().bytes())
+            .ok_or(MemoryAccessError::OutOfBounds)?;
+        if let Some((reserved, len)) = self.reservation {
+            if addr < reserved + len && reserved < end {
+                self.reservation = None;
+            }
+        }
+
+        let (region, offset) = self.region(addr, value.width())?;
+        region.device.store(offset, value)
+    }
+
+    /// Load a sequence of bytes from memory.
+    ///
+    /// This performs a separate byte-wide load for each byte, so is intended for use by loaders &
+    /// debuggers, rather than the processor.
+    pub fn load_bytes(&mut self, addr: usize, len: usize) -> Result<Vec<u8>, ProcessorException> {
+        let end = addr
+            .checked_add(len)
+            .ok_or(MemoryAccessError::OutOfBounds)?;
+        (addr..end)
+            .map(|addr| Ok(self.load_unsigned_byte(addr)? as u8))
+            .collect()
+    }
+
+    /// Store a sequence of bytes to memory.
+    ///
+    /// This performs a separate byte-wide store for each byte, so is intended for use by loaders &
+    /// debuggers, rather than the processor.
+    pub fn store_bytes(&mut self, addr: usize, values: &[u8]) -> Result<(), ProcessorException> {
+        if addr.checked_add(values.len()).is_none() {
+            return Err(MemoryAccessError::OutOfBounds.into());
+        }
+        for (i, value) in values.iter().enumerate() {
+            self.store_value(addr + i, MemoryValue::Byte(*value))?;
+        }
+        Ok(())
+    }
+
+    /// Initialise the contents of memory, as a program loader would.
+    ///
+    /// Unlike [`store_bytes`](Self::store_bytes), this can initialise devices which cannot be
+    /// written by the processor, such as the ROM, and may span multiple adjacent regions. Returns
+    /// an error if any of the bytes are not mapped, or the device cannot be initialised.
+    pub fn preload(&mut self, addr: usize, bytes: &[u8]) -> Result<(), ProcessorException> {
+        let mut addr = addr;
+        let mut bytes = bytes;
+        while !bytes.is_empty() {
+            let region = self.containing_region(addr)?;
+            let len = bytes.len().min(region.end() - addr);
+            region.device.preload(addr - region.base, &bytes[..len])?;
+
+            addr += len;
+            bytes = &bytes[len..];
+        }
+        Ok(())
+    }
+
+    /// Load an instruction from memory.
+    ///
+    /// Instructions are fetched in 16-bit parcels, as described in the RISC-V spec, Section 1.5
+    /// (Base Instruction-Length Encoding): The first parcel is loaded, and the second parcel is
+    /// only loaded if the first indicates the instruction is longer than 16 bits. This means that a
+    /// compressed instruction at the very end of a device can be fetched, and that a 32-bit
+    /// instruction which straddles the end of a device is fetched from the devices on either side
+    /// of the boundary, or produces an error if nothing is mapped beyond the boundary.
+    ///
+    /// Instructions longer than 32 bits are not supported: Only their first 32 bits are returned.
+    pub fn load_instruction(&mut self, addr: usize) -> Result<u32, ProcessorException> {
+        let low = self.load_unsigned_halfword(addr)?;
+        if InstructionParts::identify_instruction_length(low) == InstructionLength::HalfWord {
+            return Ok(low);
+        }
+
+        let high = self.load_unsigned_halfword(addr + 2)?;
+        Ok(low | (high << 16))
+    }
+
+    /// Load a double-word from memory.
+    pub fn load_doubleword(&mut self, addr: usize) -> Result<i64, ProcessorException> {
+        Ok(self.load_value(addr, AccessWidth::DoubleWord)?.as_u64() as i64)
+    }
+
     /// Load a word from memory.
-    pub fn load_word(&self, addr: usize) -> Result<i32, ProcessorException> {
-        Ok(i32::from_le_bytes(
-            self.load_raw(addr..addr + 4)?.try_into().unwrap(),
-        ))
+    pub fn load_word(&mut self, addr: usize) -> Result<i32, ProcessorException> {
+        Ok(self.load_unsigned_word(addr)? as i32)
+    }
+
+    /// Load a word from memory, as an unsigned value.
+    pub fn load_unsigned_word(&mut self, addr: usize) -> Result<u32, ProcessorException> {
+        Ok(self.load_value(addr, AccessWidth::Word)?.as_u64() as u32)
     }
 
     /// Load a half-word from memory, then sign-extend to a full word.
-    pub fn load_signed_halfword(&self, addr: usize) -> Result<i32, ProcessorException> {
+    pub fn load_signed_halfword(&mut self, addr: usize) -> Result<i32, ProcessorException> {
         Ok(((self.load_unsigned_halfword(addr)? as i32) << 16) >> 16)
     }
 
     /// Load a half-word from memory, then zero-extend to a full word.
-    pub fn load_unsigned_halfword(&self, addr: usize) -> Result<u32, ProcessorException> {
-        let value = u16::from_le_bytes(self.load_raw(addr..addr + 2)?.try_into().unwrap());
-        Ok(value as u32)
+    pub fn load_unsigned_halfword(&mut self, addr: usize) -> Result<u32, ProcessorException> {
+        Ok(self.load_value(addr, AccessWidth::HalfWord)?.as_u64() as u32)
     }
 
     /// Load a byte from memory, then sign-extend to a full word.
-    pub fn load_signed_byte(&self, addr: usize) -> Result<i32, ProcessorException> {
+    pub fn load_signed_byte(&mut self, addr: usize) -> Result<i32, ProcessorException> {
         Ok(((self.load_unsigned_byte(addr)? as i32) << 24) >> 24)
     }
 
     /// Load a byte from memory, then zero-extend to a full word.
-    pub fn load_unsigned_byte(&self, addr: usize) -> Result<u32, ProcessorException> {
-        let value = u8::from_le_bytes(self.load_raw(addr..addr + 1)?.try_into().unwrap());
-        Ok(value as u32)
+    pub fn load_unsigned_byte(&mut self, addr: usize) -> Result<u32, ProcessorException> {
+        Ok(self.load_value(addr, AccessWidth::Byte)?.as_u64() as u32)
     }
 
     /// Load a value from memory, according to the provided [`LoadSpec`].
-    pub fn load(&self, load: LoadSpec) -> Result<i32, ProcessorException> {
+    pub fn load(&mut self, load: LoadSpec) -> Result<i64, ProcessorException> {
         Ok(match load.access_type {
-            MemoryAccessType::Word => self.load_word(load.addr)?,
-            MemoryAccessType::SignedHalfWord => self.load_signed_halfword(load.addr)?,
-            MemoryAccessType::UnsignedHalfWord => self.load_unsigned_halfword(load.addr)? as i32,
-            MemoryAccessType::SignedByte => self.load_signed_byte(load.addr)?,
-            MemoryAccessType::UnsignedByte => self.load_unsigned_byte(load.addr)? as i32,
+            MemoryAccessType::DoubleWord => self.load_doubleword(load.addr)?,
+            MemoryAccessType::Word => self.load_word(load.addr)? as i64,
+            MemoryAccessType::UnsignedWord => self.load_unsigned_word(load.addr)? as i64,
+            MemoryAccessType::SignedHalfWord => self.load_signed_halfword(load.addr)? as i64,
+            MemoryAccessType::UnsignedHalfWord => self.load_unsigned_halfword(load.addr)? as i64,
+            MemoryAccessType::SignedByte => self.load_signed_byte(load.addr)? as i64,
+            MemoryAccessType::UnsignedByte => self.load_unsigned_byte(load.addr)? as i64,
         })
     }
 
-    /// Store a raw value to memory.
-    ///
-    /// Returns an error if the provided range is not mapped to a single device.
-    pub fn store_raw(
-        &mut self,
-        range: Range<usize>,
-        values: &[u8],
-    ) -> Result<(), ProcessorException> {
-        if range.start & 0x80000000 == 0 {
-            self.rom.store_raw(range, values).into()
-        } else {
-            self.ram
-                .store_raw(range.start & 0x7fffffff..range.end & 0x7fffffff, values)
-                .into()
-        }
+    /// Store a double-word to memory.
+    pub fn store_doubleword(&mut self, addr: usize, value: i64) -> Result<(), ProcessorException> {
+        self.store_value(addr, MemoryValue::DoubleWord(value as u64))
     }
 
     /// Store a word to memory.
     pub fn store_word(&mut self, addr: usize, value: i32) -> Result<(), ProcessorException> {
-        self.store_raw(addr..addr + 4, &value.to_le_bytes())
+        self.store_value(addr, MemoryValue::Word(value as u32))
     }
 
     /// Store the low 16 bits of the provided value to memory.
     pub fn store_halfword(&mut self, addr: usize, value: i32) -> Result<(), ProcessorException> {
-        self.store_raw(addr..addr + 2, &(value as u16).to_le_bytes())
+        self.store_value(addr, MemoryValue::HalfWord(value as u16))
     }
 
     /// Store the low 8 bits of the provided value to memory.
     pub fn store_byte(&mut self, addr: usize, value: i32) -> Result<(), ProcessorException> {
-        self.store_raw(addr..addr + 1, &(value as u8).to_le_bytes())
+        self.store_value(addr, MemoryValue::Byte(value as u8))
     }
 
     /// Store a value to memory, according to the provided [`StoreSpec`].
     pub fn store(&mut self, store: StoreSpec) -> Result<(), ProcessorException> {
-        Ok(match store.access_type {
-            MemoryAccessType::Word => self.store_word(store.addr, store.value)?,
+        match store.access_type {
+            MemoryAccessType::DoubleWord => self.store_doubleword(store.addr, store.value),
+            MemoryAccessType::Word | MemoryAccessType::UnsignedWord => {
+                self.store_word(store.addr, store.value as i32)
+            }
             MemoryAccessType::SignedHalfWord | MemoryAccessType::UnsignedHalfWord => {
-                self.store_halfword(store.addr, store.value)?
+                self.store_halfword(store.addr, store.value as i32)
             }
             MemoryAccessType::SignedByte | MemoryAccessType::UnsignedByte => {
-                self.store_halfword(store.addr, store.value)?
+                self.store_byte(store.addr, store.value as i32)
             }
-        })
+        }
+    }
+
+    /// Perform an atomic memory operation, according to the provided [`AtomicSpec`].
+    ///
+    /// The load and store making up the operation are both performed within this call, so as long
+    /// as the caller holds the MMU exclusively, no other access can be interleaved between them.
+    ///
+    /// Returns the value which should be written to the destination register: For
+    /// [`AtomicOperation::StoreConditional`], this is zero if the store succeeded, or one
+    /// otherwise. For all other operations, this is the value originally loaded from memory.
+    pub fn atomic(&mut self, atomic: AtomicSpec) -> Result<i64, ProcessorException> {
+        let len = atomic.access_type.bytes();
+        if !atomic.addr.is_multiple_of(len) {
+            return Err(MemoryAccessError::Misaligned.into());
+        }
+
+        // Narrower operations are performed on sign-extended values: Sign-extension preserves both
+        // signed & unsigned ordering, so the comparisons are correct for any width
+        let extend = |value: i64| {
+            let shift = 64 - len as u32 * 8;
+            (value << shift) >> shift
+        };
+        let operand = extend(atomic.value);
+        let load = LoadSpec::new(atomic.access_type, atomic.addr);
+        let store = |value| StoreSpec::new(atomic.access_type, atomic.addr, value);
+        match atomic.op {
+            AtomicOperation::LoadReserved => {
+                let value = self.load(load)?;
+                self.reservation = Some((atomic.addr, len));
+                Ok(value)
+            }
+            AtomicOperation::StoreConditional => {
+                // The reservation is always invalidated, whether or not the store succeeds
+                if self.reservation.take() == Some((atomic.addr, len)) {
+                    self.store(store(operand))?;
+                    Ok(0)
+                } else {
+                    Ok(1)
+                }
+            }
+            op => {
+                let prev = self.load(load)?;
+                let value = match op {
+                    AtomicOperation::Swap => operand,
+                    AtomicOperation::Add => prev.wrapping_add(operand),
+                    AtomicOperation::Xor => prev ^ operand,
+                    AtomicOperation::And => prev & operand,
+                    AtomicOperation::Or => prev | operand,
+                    AtomicOperation::Min => prev.min(operand),
+                    AtomicOperation::Max => prev.max(operand),
+                    AtomicOperation::MinU => (prev as u64).min(operand as u64) as i64,
+                    AtomicOperation::MaxU => (prev as u64).max(operand as u64) as i64,
+                    AtomicOperation::CompareAndSwap(expected) => {
+                        // Memory is left untouched if the comparison fails
+                        if prev != extend(expected) {
+                            return Ok(prev);
+                        }
+                        operand
+                    }
+                    AtomicOperation::LoadReserved | AtomicOperation::StoreConditional => {
+                        unreachable!("Handled above")
+                    }
+                };
+                self.store(store(value))?;
+                Ok(prev)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        AccessWidth, AccessWidths, Addressable, AtomicOperation, AtomicSpec, MemoryAccessType,
+        MemoryValue, StoreSpec, MMU,
+    };
+    use crate::error::{MemoryAccessError, MemoryMapError, ProcessorException};
+    use crate::mmu::AtomicOperation::CompareAndSwap;
+    use crate::mmu::MemoryAccessType::{DoubleWord, SignedByte, SignedHalfWord, Word};
+    use crate::ram::RAM;
+    use crate::rom::ROM;
+    use std::collections::VecDeque;
+
+    /// A device with a single byte-wide register, which pops from a FIFO on read, and pushes to it
+    /// on write.
+    #[derive(Debug, Default)]
+    struct Fifo(VecDeque<u8>);
+
+    impl Addressable for Fifo {
+        fn reserve(&self) -> usize {
+            1
+        }
+
+        fn access_widths(&self) -> AccessWidths {
+            AccessWidths::BYTE
+        }
+
+        fn load(
+            &mut self,
+            _addr: usize,
+            width: AccessWidth,
+        ) -> Result<MemoryValue, ProcessorException> {
+            Ok(MemoryValue::new(
+                width,
+                self.0.pop_front().unwrap_or(0) as u64,
+            ))
+        }
+
+        fn store(&mut self, _addr: usize, value: MemoryValue) -> Result<(), ProcessorException> {
+            self.0.push_back(value.as_u64() as u8);
+            Ok(())
+        }
+    }
+
+    /// Create an MMU with the provided ROM mapped at 0, and 16 bytes of RAM at 0x80000000.
+    fn mmu_with_rom(rom: ROM) -> MMU {
+        let mut mmu = MMU::new();
+        mmu.map(0, 16, Box::new(rom)).unwrap();
+        mmu.map(0x8000_0000, 16, Box::new(RAM::new(16))).unwrap();
+        mmu
+    }
+
+    fn mmu() -> MMU {
+        mmu_with_rom(ROM::new(vec![0u8; 16]))
+    }
+
+    #[test]
+    fn store_byte() {
+        let mut mmu = mmu();
+        let store = StoreSpec::new(MemoryAccessType::UnsignedByte, 0x8000_0001, 0x1234);
+        mmu.store(store).unwrap();
+
+        // Only the addressed byte is written
+        assert_eq!(mmu.load_unsigned_halfword(0x8000_0000).unwrap(), 0x3400);
+        assert_eq!(mmu.load_unsigned_halfword(0x8000_0002).unwrap(), 0);
+    }
+
+    #[test]
+    fn overlapping_regions() {
+        let mut mmu = mmu();
+        let ram = || Box::new(RAM::new(16));
+
+        assert_eq!(mmu.map(0x8, 16, ram()), Err(MemoryMapError::Overlap));
+        assert_eq!(
+            mmu.map(0x7fff_fff8, 16, ram()),
+            Err(MemoryMapError::Overlap)
+        );
+        assert_eq!(mmu.map(0x8000_0004, 4, ram()), Err(MemoryMapError::Overlap));
+        assert_eq!(mmu.map(0x100, 0, ram()), Err(MemoryMapError::Empty));
+
+        // Adjacent regions are fine
+        mmu.map(0x10, 16, ram()).unwrap();
+        mmu.map(0x7fff_fff0, 16, ram()).unwrap();
+        assert_eq!(
+            mmu.regions().collect::<Vec<_>>(),
+            vec![
+                0x0..0x10,
+                0x10..0x20,
+                0x7fff_fff0..0x8000_0000,
+                0x8000_0000..0x8000_0010
+            ]
+        );
+    }
+
+    #[test]
+    fn side_effecting_device() {
+        let mut mmu = mmu();
+        mmu.map(0x1000, 4, Box::new(Fifo::default())).unwrap();
+
+        mmu.store_byte(0x1000, 1).unwrap();
+        mmu.store_byte(0x1000, 2).unwrap();
+        assert_eq!(mmu.load_unsigned_byte(0x1000).unwrap(), 1);
+        assert_eq!(mmu.load_unsigned_byte(0x1000).unwrap(), 2);
+        assert_eq!(mmu.load_unsigned_byte(0x1000).unwrap(), 0);
+
+        // Only byte-wide accesses are supported
+        let unsupported =
+            ProcessorException::InvalidMemoryAccess(MemoryAccessError::UnsupportedWidth);
+        assert_eq!(mmu.load_word(0x1000), Err(unsupported));
+        assert_eq!(mmu.store_halfword(0x1000, 0), Err(unsupported));
+    }
+
+    #[test]
+    fn region_lookup() {
+        let mut mmu = mmu();
+        mmu.map(0x1000, 8, Box::new(RAM::new(8))).unwrap();
+
+        // Addresses are translated relative to the start of each region
+        mmu.store_word(0x1004, 0x1234_5678).unwrap();
+        mmu.store_word(0x8000_0004, 0x0bad_cafe).unwrap();
+        assert_eq!(mmu.load_word(0x1004).unwrap(), 0x1234_5678);
+        assert_eq!(mmu.load_word(0x8000_0004).unwrap(), 0x0bad_cafe);
+        assert_eq!(mmu.load_word(0x1000).unwrap(), 0);
+
+        // Gaps between regions
+        let out_of_bounds = ProcessorException::InvalidMemoryAccess(MemoryAccessError::OutOfBounds);
+        assert_eq!(mmu.load_word(0x800), Err(out_of_bounds));
+        assert_eq!(mmu.load_word(0x1008), Err(out_of_bounds));
+        assert_eq!(mmu.store_byte(0xffff_ffff, 0), Err(out_of_bounds));
+
+        // Accesses spanning the end of a region
+        let crosses = ProcessorException::InvalidMemoryAccess(MemoryAccessError::CrossesBoundary);
+        assert_eq!(mmu.load_word(0x1006), Err(crosses));
+        assert_eq!(mmu.store_halfword(0x8000_000f, 0), Err(crosses));
+    }
+
+    #[test]
+    fn top_of_address_space() {
+        let mut mmu = mmu();
+        let out_of_bounds = ProcessorException::InvalidMemoryAccess(MemoryAccessError::OutOfBounds);
+        let store = StoreSpec::new(MemoryAccessType::DoubleWord, usize::MAX, 0);
+        assert_eq!(mmu.store(store), Err(out_of_bounds));
+        assert_eq!(mmu.load_doubleword(usize::MAX), Err(out_of_bounds));
+        assert_eq!(mmu.load_bytes(usize::MAX, 2), Err(out_of_bounds));
+        assert_eq!(mmu.store_bytes(usize::MAX, &[0, 0]), Err(out_of_bounds));
+
+        // Accesses spanning the end of a region at the top of the address space
+        mmu.map(usize::MAX - 8, 8, Box::new(RAM::new(8))).unwrap();
+        let crosses = ProcessorException::InvalidMemoryAccess(MemoryAccessError::CrossesBoundary);
+        mmu.store_word(usize::MAX - 4, 0x1234_5678).unwrap();
+        assert_eq!(mmu.load_word(usize::MAX - 4).unwrap(), 0x1234_5678);
+        assert_eq!(mmu.load_doubleword(usize::MAX - 4), Err(crosses));
+        assert_eq!(mmu.load_bytes(usize::MAX - 1, 2), Err(out_of_bounds));
+
+        // Stores past the top of the address space are out of bounds, rather than wrapping
+        assert_eq!(mmu.store_doubleword(usize::MAX - 4, 0), Err(out_of_bounds));
+        assert_eq!(mmu.load_word(usize::MAX - 4).unwrap(), 0x1234_5678);
+    }
+
+    #[test]
+    fn atomic_read_modify_write() {
+        let mut mmu = mmu();
+        mmu.store_word(0x8000_0004, 5).unwrap();
+
+        let add = AtomicSpec::new(AtomicOperation::Add, Word, 0x8000_0004, 3);
+        assert_eq!(mmu.atomic(add).unwrap(), 5);
+        assert_eq!(mmu.load_word(0x8000_0004).unwrap(), 8);
+
+        let swap = AtomicSpec::new(AtomicOperation::Swap, Word, 0x8000_0004, -1);
+        assert_eq!(mmu.atomic(swap).unwrap(), 8);
+        assert_eq!(mmu.load_word(0x8000_0004).unwrap(), -1);
+
+        let min = AtomicSpec::new(AtomicOperation::Min, Word, 0x8000_0004, 2);
+        assert_eq!(mmu.atomic(min).unwrap(), -1);
+        assert_eq!(mmu.load_word(0x8000_0004).unwrap(), -1);
+
+        let min_u = AtomicSpec::new(AtomicOperation::MinU, Word, 0x8000_0004, 2);
+        assert_eq!(mmu.atomic(min_u).unwrap(), -1);
+        assert_eq!(mmu.load_word(0x8000_0004).unwrap(), 2);
+    }
+
+    #[test]
+    fn atomic_doubleword() {
+        let mut mmu = mmu();
+        mmu.store_doubleword(0x8000_0008, 0x1_0000_0000).unwrap();
+
+        let add = AtomicSpec::new(AtomicOperation::Add, DoubleWord, 0x8000_0008, -1);
+        assert_eq!(mmu.atomic(add).unwrap(), 0x1_0000_0000);
+        assert_eq!(mmu.load_doubleword(0x8000_0008).unwrap(), 0xffff_ffff);
+
+        // Word operations only see the low 32 bits of each operand, sign-extended
+        let max = AtomicSpec::new(AtomicOperation::Max, Word, 0x8000_0008, 0x1_0000_0000);
+        assert_eq!(mmu.atomic(max).unwrap(), -1);
+        assert_eq!(mmu.load_doubleword(0x8000_0008).unwrap(), 0);
+
+        // Double-word reservations cover all 8 bytes
+        let lr = AtomicSpec::new(AtomicOperation::LoadReserved, DoubleWord, 0x8000_0008, 0);
+        let sc = AtomicSpec::new(
+            AtomicOperation::StoreConditional,
+            DoubleWord,
+            0x8000_0008,
+            7,
+        );
+        mmu.atomic(lr).unwrap();
+        mmu.store_byte(0x8000_000f, 1).unwrap();
+        assert_eq!(mmu.atomic(sc).unwrap(), 1);
+    }
+
+    #[test]
+    fn atomic_compare_and_swap() {
+        let mut mmu = mmu();
+        mmu.store_doubleword(0x8000_0008, 0x1_0000_0005).unwrap();
+
+        // Fails, leaving memory unchanged
+        let cas = AtomicSpec::new(CompareAndSwap(5), DoubleWord, 0x8000_0008, 7);
+        assert_eq!(mmu.atomic(cas).unwrap(), 0x1_0000_0005);
+        assert_eq!(mmu.load_doubleword(0x8000_0008).unwrap(), 0x1_0000_0005);
+
+        // Word operations only compare the low 32 bits of the expected value
+        let cas = AtomicSpec::new(CompareAndSwap(0x7_0000_0005), Word, 0x8000_0008, -1);
+        assert_eq!(mmu.atomic(cas).unwrap(), 5);
+        assert_eq!(mmu.load_doubleword(0x8000_0008).unwrap(), 0x1_ffff_ffff);
+
+        // A failed comparison doesn't invalidate a reservation
+        let lr = AtomicSpec::new(AtomicOperation::LoadReserved, Word, 0x8000_0008, 0);
+        let sc = AtomicSpec::new(AtomicOperation::StoreConditional, Word, 0x8000_0008, 3);
+        mmu.atomic(lr).unwrap();
+        let cas = AtomicSpec::new(CompareAndSwap(0), Word, 0x8000_0008, 1);
+        assert_eq!(mmu.atomic(cas).unwrap(), -1);
+        assert_eq!(mmu.atomic(sc).unwrap(), 0);
+    }
+
+    #[test]
+    fn atomic_byte_halfword() {
+        let mut mmu = mmu();
+        mmu.store_word(0x8000_0004, 0x1234_80ff).unwrap();
+
+        // Narrow values are sign-extended, and their neighbours are unaffected
+        let add = AtomicSpec::new(AtomicOperation::Add, SignedByte, 0x8000_0004, 2);
+        assert_eq!(mmu.atomic(add).unwrap(), -1);
+        assert_eq!(mmu.load_word(0x8000_0004).unwrap(), 0x1234_8001);
+
+        let max_u = AtomicSpec::new(AtomicOperation::MaxU, SignedHalfWord, 0x8000_0006, 0xffff);
+        assert_eq!(mmu.atomic(max_u).unwrap(), 0x1234);
+        assert_eq!(mmu.load_word(0x8000_0004).unwrap(), 0xffff_8001_u32 as i32);
+
+        let cas = AtomicSpec::new(CompareAndSwap(0x8001), SignedHalfWord, 0x8000_0004, 9);
+        assert_eq!(mmu.atomic(cas).unwrap(), -0x7fff);
+        assert_eq!(mmu.load_word(0x8000_0004).unwrap(), 0xffff_0009_u32 as i32);
+
+        let swap = AtomicSpec::new(AtomicOperation::Swap, SignedHalfWord, 0x8000_0005, 1);
+        assert_eq!(
+            mmu.atomic(swap),
+            Err(ProcessorException::InvalidMemoryAccess(
+                MemoryAccessError::Misaligned
+            ))
+        );
+    }
+
+    #[test]
+    fn atomic_requires_alignment() {
+        let mut mmu = mmu();
+        let misaligned = Err(ProcessorException::InvalidMemoryAccess(
+            MemoryAccessError::Misaligned,
+        ));
+        let swap = AtomicSpec::new(AtomicOperation::Swap, Word, 0x8000_0002, 1);
+        assert_eq!(mmu.atomic(swap), misaligned);
+        let swap = AtomicSpec::new(AtomicOperation::Swap, DoubleWord, 0x8000_0004, 1);
+        assert_eq!(mmu.atomic(swap), misaligned);
+    }
+
+    #[test]
+    fn store_conditional() {
+        let mut mmu = mmu();
+        let lr = AtomicSpec::new(AtomicOperation::LoadReserved, Word, 0x8000_0008, 0);
+        let sc = AtomicSpec::new(AtomicOperation::StoreConditional, Word, 0x8000_0008, 7);
+
+        // Fails without a reservation
+        assert_eq!(mmu.atomic(sc).unwrap(), 1);
+        assert_eq!(mmu.load_word(0x8000_0008).unwrap(), 0);
+
+        // Succeeds with a reservation, which is then consumed
+        assert_eq!(mmu.atomic(lr).unwrap(), 0);
+        assert_eq!(mmu.atomic(sc).unwrap(), 0);
+        assert_eq!(mmu.load_word(0x8000_0008).unwrap(), 7);
+        assert_eq!(mmu.atomic(sc).unwrap(), 1);
+
+        // Fails if the reservation is for a different address
+        let other_sc = AtomicSpec::new(AtomicOperation::StoreConditional, Word, 0x8000_000c, 7);
+        mmu.atomic(lr).unwrap();
+        assert_eq!(mmu.atomic(other_sc).unwrap(), 1);
+
+        // Fails if an overlapping store is made between LR & SC
+        mmu.atomic(lr).unwrap();
+        mmu.store_byte(0x8000_000b, 1).unwrap();
+        assert_eq!(mmu.atomic(sc).unwrap(), 1);
+
+        // Succeeds if a non-overlapping store is made between LR & SC
+        mmu.atomic(lr).unwrap();
+        mmu.store_word(0x8000_000c, 1).unwrap();
+        assert_eq!(mmu.atomic(sc).unwrap(), 0);
+    }
+
+    #[test]
+    fn load_instruction_parcels() {
+        // A compressed instruction in the final two bytes of the ROM, preceded by a 32-bit
+        // instruction
+        let mut mmu = MMU::new();
+        let rom = ROM::new(vec![0x93, 0x87, 0xe0, 0xfc, 0x81, 0x12]);
+        mmu.map(0, 6, Box::new(rom)).unwrap();
+        assert_eq!(mmu.load_instruction(0).unwrap(), 0xfce0_8793);
+        assert_eq!(mmu.load_instruction(4).unwrap(), 0x1281);
+
+        // A 32-bit instruction which straddles the end of the ROM
+        let mut mmu = MMU::new();
+        mmu.map(0, 2, Box::new(ROM::new(vec![0x93, 0x87]))).unwrap();
+        assert_eq!(
+            mmu.load_instruction(0),
+            Err(ProcessorException::InvalidMemoryAccess(
+                MemoryAccessError::OutOfBounds
+            ))
+        );
+
+        // Or is fetched from both regions, if the following region is mapped
+        mmu.map(2, 2, Box::new(ROM::new(vec![0xe0, 0xfc]))).unwrap();
+        assert_eq!(mmu.load_instruction(0).unwrap(), 0xfce0_8793);
     }
 }
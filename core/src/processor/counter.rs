@@ -0,0 +1,287 @@
+//! Hardware performance counters.
+//!
+//! RISC-V defines 32 64-bit counters for each hart, numbered to match the CSRs which expose them:
+//! Counter 0 counts cycles, counter 1 the `time` timebase, counter 2 instructions retired, and
+//! counters 3 to 31 are the hardware performance monitoring (HPM) counters, each of which counts
+//! the [`Event`] selected by its `mhpmevent` CSR. The hart updates its [`Counters`] as it executes,
+//! while the counter CSRs themselves are registered by the Zicntr and Zihpm extensions.
+//!
+//! `time` is not derived from host wall time: It counts ticks of the [`Clock`] driving the
+//! processor, including any ticks the processor missed, so that it is reproducible with the
+//! free-running or manual clocks. The [`ExecutionEnvironment`] advances it before each cycle.
+//!
+//! [`Clock`]: crate::clock::Clock
+//! [`ExecutionEnvironment`]: crate::ExecutionEnvironment
+
+use std::array;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+
+/// Number of counters, including the fixed counters.
+pub const COUNTER_COUNT: usize = 32;
+
+/// Index of the cycle counter.
+pub const CYCLE: usize = 0;
+
+/// Index of the `time` timebase.
+pub const TIME: usize = 1;
+
+/// Index of the instructions-retired counter.
+pub const INSTRET: usize = 2;
+
+/// Index of the first HPM counter.
+pub const HPM_BASE: usize = 3;
+
+/// An event which can be counted by an HPM counter.
+///
+/// Each event is selected by writing its code to an `mhpmevent` CSR. These are the events a hart
+/// can observe while running its decode-execute pipeline.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum Event {
+    /// An instruction which loads from memory retired.
+    ///
+    /// Load-reserved instructions are counted as [`Atomic`](Self::Atomic) instead.
+    Load,
+
+    /// An instruction which stores to memory retired.
+    ///
+    /// Store-conditional instructions are counted as [`Atomic`](Self::Atomic) instead.
+    Store,
+
+    /// An atomic memory operation retired.
+    Atomic,
+
+    /// A branch was taken, or a jump performed.
+    BranchTaken,
+
+    /// An instruction which had already been decoded was discarded, due to a jump, trap return,
+    /// trap, or instruction-fetch fence.
+    ///
+    /// The hart executes nothing on the following cycle, while it decodes the instruction at the
+    /// new address, or decodes the discarded instruction again.
+    Flush,
+
+    /// A trap was taken.
+    Trap,
+}
+
+impl Event {
+    /// Determine the event selected by the provided `mhpmevent` value.
+    ///
+    /// Returns `None` if the value does not select a supported event.
+    pub fn from_code(code: u64) -> Option<Self> {
+        match code {
+            1 => Some(Event::Load),
+            2 => Some(Event::Store),
+            3 => Some(Event::Atomic),
+            4 => Some(Event::BranchTaken),
+            5 => Some(Event::Flush),
+            6 => Some(Event::Trap),
+            _ => None,
+        }
+    }
+
+    /// Get the `mhpmevent` value which selects this event.
+    pub fn code(&self) -> u64 {
+        match self {
+            Event::Load => 1,
+            Event::Store => 2,
+            Event::Atomic => 3,
+            Event::BranchTaken => 4,
+            Event::Flush => 5,
+            Event::Trap => 6,
+        }
+    }
+}
+
+/// State shared by each handle to a hart's counters.
+#[derive(Debug)]
+struct State {
+    /// Current value of each counter.
+    counters: [AtomicU64; COUNTER_COUNT],
+
+    /// Event code selected for each counter.
+    ///
+    /// Only the entries for HPM counters are used.
+    events: [AtomicU64; COUNTER_COUNT],
+
+    /// Counters which should not increment, as in `mcountinhibit`.
+    inhibit: AtomicU64,
+
+    /// Counters which have been written since the last cycle.
+    written: AtomicU64,
+}
+
+/// The performance counters of a hart.
+///
+/// This is a handle to state shared with the counter CSRs: Cloning it produces another handle to
+/// the same counters.
+#[derive(Clone, Debug)]
+pub struct Counters {
+    state: Arc<State>,
+}
+
+impl Counters {
+    /// Create a new set of counters, all zero.
+    pub fn new() -> Self {
+        Self {
+            state: Arc::new(State {
+                counters: array::from_fn(|_| AtomicU64::new(0)),
+                events: array::from_fn(|_| AtomicU64::new(0)),
+                inhibit: AtomicU64::new(0),
+                written: AtomicU64::new(0),
+            }),
+        }
+    }
+
+    /// Get the value of the counter with the provided index.
+    pub fn read(&self, counter: usize) -> u64 {
+        self.state.counters[counter].load(Ordering::Relaxed)
+    }
+
+    /// Set the value of the counter with the provided index.
+    ///
+    /// If this is done while an instruction executes (i.e: by a CSR write), the write takes the
+    /// place of any increment the counter would otherwise receive in the same cycle.
+    pub fn write(&self, counter: usize, value: u64) {
+        self.state.counters[counter].store(value, Ordering::Relaxed);
+        self.state.written.fetch_or(1 << counter, Ordering::Relaxed);
+    }
+
+    /// Get the event code selected for the HPM counter with the provided index.
+    pub fn event(&self, counter: usize) -> u64 {
+        self.state.events[counter].load(Ordering::Relaxed)
+    }
+
+    /// Select the event the HPM counter with the provided index counts.
+    ///
+    /// Codes which do not correspond to an [`Event`] are stored, but the counter never increments.
+    pub fn set_event(&self, counter: usize, code: u64) {
+        self.state.events[counter].store(code, Ordering::Relaxed);
+    }
+
+    /// Get the mask of counters which are inhibited from incrementing.
+    pub fn inhibit(&self) -> u64 {
+        self.state.inhibit.load(Ordering::Relaxed)
+    }
+
+    /// Set the mask of counters which are inhibited from incrementing.
+    ///
+    /// Bit `n` inhibits counter `n`. The `time` timebase cannot be inhibited.
+    pub fn set_inhibit(&self, mask: u64) {
+        let mask = mask & !(1 << TIME) & 0xffff_ffff;
+        self.state.inhibit.store(mask, Ordering::Relaxed);
+    }
+
+    /// Advance the `time` timebase by the provided number of clock ticks.
+    pub fn advance_time(&self, ticks: u64) {
+        self.state.counters[TIME].fetch_add(ticks, Ordering::Relaxed);
+    }
+
+    /// Record that an instruction retired.
+    pub fn retire(&self) {
+        self.increment(INSTRET);
+    }
+
+    /// Record that an event occurred, incrementing each HPM counter which counts it.
+    pub fn record(&self, event: Event) {
+        for counter in HPM_BASE..COUNTER_COUNT {
+            if self.event(counter) == event.code() {
+                self.increment(counter);
+            }
+        }
+    }
+
+    /// Record the end of a cycle.
+    ///
+    /// Increments the cycle counter. Counters written during the cycle will increment again from
+    /// the next cycle.
+    pub fn tick(&self) {
+        self.increment(CYCLE);
+        self.state.written.store(0, Ordering::Relaxed);
+    }
+
+    /// Reset all counters & event selections to zero.
+    pub fn reset(&self) {
+        for i in 0..COUNTER_COUNT {
+            self.state.counters[i].store(0, Ordering::Relaxed);
+            self.state.events[i].store(0, Ordering::Relaxed);
+        }
+        self.state.inhibit.store(0, Ordering::Relaxed);
+        self.state.written.store(0, Ordering::Relaxed);
+    }
+
+    /// Increment the counter with the provided index, unless it is inhibited or has been written
+    /// this cycle.
+    fn increment(&self, counter: usize) {
+        let skip = self.inhibit() | self.state.written.load(Ordering::Relaxed);
+        if skip & (1 << counter) == 0 {
+            self.state.counters[counter].fetch_add(1, Ordering::Relaxed);
+        }
+    }
+}
+
+impl Default for Counters {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Counters, Event, CYCLE, INSTRET, TIME};
+
+    #[test]
+    fn fixed_counters() {
+        let counters = Counters::new();
+        counters.retire();
+        counters.tick();
+        counters.tick();
+        counters.advance_time(5);
+        assert_eq!(counters.read(CYCLE), 2);
+        assert_eq!(counters.read(INSTRET), 1);
+        assert_eq!(counters.read(TIME), 5);
+
+        // A write replaces the increment for the same cycle
+        counters.write(INSTRET, 10);
+        counters.retire();
+        counters.tick();
+        counters.retire();
+        assert_eq!(counters.read(INSTRET), 11);
+
+        // time cannot be inhibited
+        counters.set_inhibit(u64::MAX);
+        assert_eq!(counters.inhibit(), 0xffff_fffd);
+        counters.tick();
+        counters.advance_time(1);
+        assert_eq!(counters.read(CYCLE), 3);
+        assert_eq!(counters.read(TIME), 6);
+    }
+
+    #[test]
+    fn events() {
+        let counters = Counters::new();
+        counters.set_event(3, Event::Load.code());
+        counters.set_event(4, Event::Load.code());
+        counters.set_event(5, Event::Trap.code());
+        counters.set_event(6, 0xff);
+
+        counters.record(Event::Load);
+        counters.record(Event::Store);
+        counters.record(Event::Load);
+        assert_eq!(counters.read(3), 2);
+        assert_eq!(counters.read(4), 2);
+        assert_eq!(counters.read(5), 0);
+        assert_eq!(counters.read(6), 0);
+
+        counters.set_inhibit(1 << 4);
+        counters.record(Event::Load);
+        assert_eq!(counters.read(3), 3);
+        assert_eq!(counters.read(4), 2);
+
+        for code in 1..=6 {
+            assert_eq!(Event::from_code(code).unwrap().code(), code);
+        }
+        assert_eq!(Event::from_code(0), None);
+    }
+}
@@ -0,0 +1,559 @@
+//! Control and Status Registers (CSRs).
+//!
+//! RISC-V defines a separate 12-bit address space of up to 4096 CSRs for each hart, which are
+//! accessed using the instructions of the Zicsr extension. Much like the [`RegisterFile`], no CSRs
+//! are hard-coded here: Instead, each [`Extension`] registers the CSRs it requires in the hart's
+//! [`CsrFile`], specifying which bits are readable/writable, which privilege level is required to
+//! access the CSR, and any side-effects reading/writing the CSR should have.
+//!
+//! [`RegisterFile`]: crate::processor::register::RegisterFile
+//! [`Extension`]: crate::extension::Extension
+
+use crate::error::ProcessorException;
+use crate::processor::{PrivilegeLevel, Xlen};
+use std::fmt;
+
+/// Number of addressable CSRs.
+pub const CSR_COUNT: usize = 4096;
+
+/// Supervisor environment configuration register.
+pub const SENVCFG: u16 = 0x10a;
+
+/// Machine status register.
+pub const MSTATUS: u16 = 0x300;
+
+/// Machine ISA register.
+pub const MISA: u16 = 0x301;
+
+/// Machine trap-handler base address.
+pub const MTVEC: u16 = 0x305;
+
+/// Machine environment configuration register.
+pub const MENVCFG: u16 = 0x30a;
+
+/// Additional machine status register (upper 32 bits of `mstatus`).
+pub const MSTATUSH: u16 = 0x310;
+
+/// Additional machine environment configuration register (upper 32 bits of `menvcfg`).
+pub const MENVCFGH: u16 = 0x31a;
+
+/// Scratch register for machine trap handlers.
+pub const MSCRATCH: u16 = 0x340;
+
+/// Machine exception program counter.
+pub const MEPC: u16 = 0x341;
+
+/// Machine trap cause.
+pub const MCAUSE: u16 = 0x342;
+
+/// Machine bad address or instruction.
+pub const MTVAL: u16 = 0x343;
+
+/// Machine security configuration register.
+pub const MSECCFG: u16 = 0x747;
+
+/// Upper 32 bits of `mseccfg`.
+pub const MSECCFGH: u16 = 0x757;
+
+/// Vendor ID.
+pub const MVENDORID: u16 = 0xf11;
+
+/// Architecture ID.
+pub const MARCHID: u16 = 0xf12;
+
+/// Implementation ID.
+pub const MIMPID: u16 = 0xf13;
+
+/// Hardware thread ID.
+pub const MHARTID: u16 = 0xf14;
+
+/// Field of `menvcfg` & `senvcfg` which enables `cbo.inval` for less-privileged modes.
+///
+/// `01` performs a flush in place of the invalidation, and `11` performs the invalidation: `10` is
+/// reserved, and treated as `00`.
+pub const ENVCFG_CBIE: u64 = 0b11 << 4;
+
+/// Low bit of the CBIE field of `menvcfg` & `senvcfg`, which is set by both of its enabled values.
+pub const ENVCFG_CBIE_ENABLED: u64 = 1 << 4;
+
+/// Bit of `menvcfg` & `senvcfg` which enables `cbo.clean` & `cbo.flush` for less-privileged modes.
+pub const ENVCFG_CBCFE: u64 = 1 << 6;
+
+/// Bit of `menvcfg` & `senvcfg` which enables `cbo.zero` for less-privileged modes.
+pub const ENVCFG_CBZE: u64 = 1 << 7;
+
+/// Callback run when a CSR is read.
+///
+/// This is passed the value currently stored in the CSR and the hart's XLEN, and returns the value
+/// to read. This can be used to implement CSRs with read side-effects, or CSRs whose value is
+/// computed on demand.
+pub type CsrReadCallback = Box<dyn FnMut(u64, Xlen) -> u64 + Send + Sync>;
+
+/// Callback run when a CSR is written.
+///
+/// This is passed the value currently stored in the CSR, the new value (after applying the write
+/// mask), and the hart's XLEN, and returns the value which should actually be stored. This can be
+/// used to implement CSRs with write side-effects, or to enforce legal values for WARL fields.
+pub type CsrWriteCallback = Box<dyn FnMut(u64, u64, Xlen) -> u64 + Send + Sync>;
+
+/// Callback run to check whether a CSR can be accessed.
+///
+/// This is passed the hart's CSR file, the current privilege level, and whether the CSR will be
+/// written, and returns whether the access is permitted. It is only run once the privilege level
+/// encoded in the address of the CSR has been checked. This can be used to implement CSRs whose
+/// accessibility is controlled by the value of another CSR.
+pub type CsrAccessCallback = Box<dyn Fn(&CsrFile, PrivilegeLevel, bool) -> bool + Send + Sync>;
+
+/// A control and status register.
+pub struct Csr {
+    /// Value currently stored in this CSR.
+    ///
+    /// Modifying this field directly bypasses the masks and callbacks of the CSR: This is intended
+    /// for use by the hart/extensions to update the CSR as a result of some other event.
+    pub value: u64,
+
+    /// Bits of the CSR which can be read.
+    ///
+    /// All other bits read as zero.
+    pub read_mask: u64,
+
+    /// Bits of the CSR which can be written by CSR instructions.
+    ///
+    /// All other bits are left unchanged on write.
+    pub write_mask: u64,
+
+    /// Minimum privilege level required to access this CSR.
+    ///
+    /// The privilege level encoded in the address of the CSR is always enforced: This can be used
+    /// to require a higher privilege level.
+    pub privilege: PrivilegeLevel,
+
+    /// Whether this CSR only exists on RV32, such as the CSRs holding the upper 32 bits of another
+    /// CSR.
+    ///
+    /// These CSRs are removed if the hart's XLEN is set to RV64.
+    pub rv32_only: bool,
+
+    /// Callback run when the CSR is read.
+    on_read: Option<CsrReadCallback>,
+
+    /// Callback run when the CSR is written.
+    on_write: Option<CsrWriteCallback>,
+
+    /// Callback run to check whether the CSR can be accessed.
+    on_access: Option<CsrAccessCallback>,
+}
+
+impl Csr {
+    /// Create a new CSR with the provided initial value.
+    ///
+    /// By default, all bits can be read and written, and no additional privilege is required
+    /// beyond that encoded in the address of the CSR.
+    pub fn new(value: u64) -> Self {
+        Self {
+            value,
+            read_mask: u64::MAX,
+            write_mask: u64::MAX,
+            privilege: PrivilegeLevel::User,
+            rv32_only: false,
+            on_read: None,
+            on_write: None,
+            on_access: None,
+        }
+    }
+
+    /// Set the bits of this CSR which can be read.
+    pub fn with_read_mask(mut self, mask: u64) -> Self {
+        self.read_mask = mask;
+        self
+    }
+
+    /// Set the bits of this CSR which can be written by CSR instructions.
+    pub fn with_write_mask(mut self, mask: u64) -> Self {
+        self.write_mask = mask;
+        self
+    }
+
+    /// Set the minimum privilege level required to access this CSR.
+    pub fn with_privilege(mut self, privilege: PrivilegeLevel) -> Self {
+        self.privilege = privilege;
+        self
+    }
+
+    /// Mark this CSR as only existing on RV32.
+    pub fn rv32_only(mut self) -> Self {
+        self.rv32_only = true;
+        self
+    }
+
+    /// Set a callback to run when this CSR is read.
+    pub fn on_read<F>(mut self, callback: F) -> Self
+    where
+        F: FnMut(u64, Xlen) -> u64 + Send + Sync + 'static,
+    {
+        self.on_read = Some(Box::new(callback));
+        self
+    }
+
+    /// Set a callback to run when this CSR is written.
+    pub fn on_write<F>(mut self, callback: F) -> Self
+    where
+        F: FnMut(u64, u64, Xlen) -> u64 + Send + Sync + 'static,
+    {
+        self.on_write = Some(Box::new(callback));
+        self
+    }
+
+    /// Set a callback to check whether this CSR can be accessed.
+    pub fn on_access<F>(mut self, callback: F) -> Self
+    where
+        F: Fn(&CsrFile, PrivilegeLevel, bool) -> bool + Send + Sync + 'static,
+    {
+        self.on_access = Some(Box::new(callback));
+        self
+    }
+}
+
+impl fmt::Debug for Csr {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Csr")
+            .field("value", &self.value)
+            .field("read_mask", &self.read_mask)
+            .field("write_mask", &self.write_mask)
+            .field("privilege", &self.privilege)
+            .field("rv32_only", &self.rv32_only)
+            .finish_non_exhaustive()
+    }
+}
+
+/// The CSR address space of a hart.
+///
+/// CSR values are stored as 64-bit values. On an RV32 hart, the hart ensures that only 32-bit
+/// values are written by CSR instructions.
+///
+/// Accesses to CSR addresses which have not been registered raise an illegal instruction
+/// exception.
+pub struct CsrFile {
+    csrs: Vec<Option<Csr>>,
+    xlen: Xlen,
+}
+
+impl CsrFile {
+    /// Create a new, empty CsrFile, for an RV32 hart.
+    pub fn new() -> Self {
+        let mut csrs = Vec::with_capacity(CSR_COUNT);
+        csrs.resize_with(CSR_COUNT, || None);
+        Self {
+            csrs,
+            xlen: Xlen::Rv32,
+        }
+    }
+
+    /// Get the XLEN of the hart, as passed to the read & write callbacks of the CSRs.
+    pub fn xlen(&self) -> Xlen {
+        self.xlen
+    }
+
+    /// Set the XLEN of the hart.
+    ///
+    /// This is called by [`Hart::set_xlen`](crate::processor::hart::Hart::set_xlen). On RV64, the
+    /// [`rv32_only`](field@Csr::rv32_only) CSRs are removed. CSRs whose layout depends on the XLEN
+    /// use the value passed to their callbacks, so they behave the same whether they were
+    /// registered before or after the base instruction set.
+    pub fn set_xlen(&mut self, xlen: Xlen) {
+        self.xlen = xlen;
+        if xlen == Xlen::Rv64 {
+            for slot in &mut self.csrs {
+                if slot.as_ref().is_some_and(|csr| csr.rv32_only) {
+                    *slot = None;
+                }
+            }
+        }
+    }
+
+    /// Register a CSR at the provided address.
+    ///
+    /// Returns the CSR previously registered at this address, if any.
+    pub fn register(&mut self, addr: u16, csr: Csr) -> Option<Csr> {
+        self.csrs[addr as usize & 0xfff].replace(csr)
+    }
+
+    /// Remove the CSR registered at the provided address.
+    pub fn unregister(&mut self, addr: u16) -> Option<Csr> {
+        self.csrs[addr as usize & 0xfff].take()
+    }
+
+    /// Get a reference to the CSR registered at the provided address.
+    pub fn get(&self, addr: u16) -> Option<&Csr> {
+        self.csrs[addr as usize & 0xfff].as_ref()
+    }
+
+    /// Get a mutable reference to the CSR registered at the provided address.
+    pub fn get_mut(&mut self, addr: u16) -> Option<&mut Csr> {
+        self.csrs[addr as usize & 0xfff].as_mut()
+    }
+
+    /// Check whether a CSR can be accessed at the provided privilege level.
+    ///
+    /// Returns an illegal instruction exception if there is no CSR at this address, the privilege
+    /// level is insufficient, `write` is true and the CSR is read-only, or the access callback of
+    /// the CSR rejects the access.
+    pub fn check(
+        &self,
+        addr: u16,
+        privilege: PrivilegeLevel,
+        write: bool,
+    ) -> Result<(), ProcessorException> {
+        self.check_privilege(addr, privilege, write)?;
+
+        match &self.get(addr).unwrap().on_access {
+            Some(callback) if !callback(self, privilege, write) => {
+                Err(ProcessorException::IllegalInstruction)
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// Check whether a CSR can be accessed at the provided privilege level, without running its
+    /// access callback.
+    fn check_privilege(
+        &self,
+        addr: u16,
+        privilege: PrivilegeLevel,
+        write: bool,
+    ) -> Result<(), ProcessorException> {
+        let csr = self
+            .get(addr)
+            .ok_or(ProcessorException::IllegalInstruction)?;
+
+        // Bits [9:8] of the address encode the lowest privilege level which can access the CSR,
+        // and bits [11:10] encode whether the CSR is read-only (0b11) or read/write
+        let required = PrivilegeLevel::from_bits((addr >> 8) as u8).max(csr.privilege);
+        let read_only = addr & 0xc00 == 0xc00;
+
+        if privilege < required || (write && read_only) {
+            return Err(ProcessorException::IllegalInstruction);
+        }
+
+        Ok(())
+    }
+
+    /// Read a CSR, as a CSR instruction would.
+    ///
+    /// Applies the read mask and read callback of the CSR. The access callback of the CSR is not
+    /// run, as a single instruction may both read & write the CSR: Use [`check`](Self::check)
+    /// first.
+    pub fn read(
+        &mut self,
+        addr: u16,
+        privilege: PrivilegeLevel,
+    ) -> Result<u64, ProcessorException> {
+        self.check_privilege(addr, privilege, false)?;
+        let xlen = self.xlen;
+        let csr = self.get_mut(addr).unwrap();
+
+        let value = match &mut csr.on_read {
+            Some(callback) => callback(csr.value, xlen),
+            None => csr.value,
+        };
+
+        Ok(value & csr.read_mask)
+    }
+
+    /// Write a CSR, as a CSR instruction would.
+    ///
+    /// Applies the write mask and write callback of the CSR. As for [`read`](Self::read), the
+    /// access callback of the CSR is not run.
+    pub fn write(
+        &mut self,
+        addr: u16,
+        value: u64,
+        privilege: PrivilegeLevel,
+    ) -> Result<(), ProcessorException> {
+        self.check_privilege(addr, privilege, true)?;
+        let xlen = self.xlen;
+        let csr = self.get_mut(addr).unwrap();
+
+        let new = (csr.value & !csr.write_mask) | (value & csr.write_mask);
+        csr.value = match &mut csr.on_write {
+            Some(callback) => callback(csr.value, new, xlen),
+            None => new,
+        };
+
+        Ok(())
+    }
+}
+
+impl Default for CsrFile {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Read-modify-write operation to perform on a CSR.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum CsrOperation {
+    /// Overwrite the CSR with the provided value.
+    Write,
+
+    /// Set the bits of the CSR which are set in the provided value.
+    Set,
+
+    /// Clear the bits of the CSR which are set in the provided value.
+    Clear,
+}
+
+/// Specification for accessing a CSR.
+///
+/// This is returned by CSR instructions, to request that the hart atomically read a CSR into a
+/// register, then update the CSR.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub struct CsrSpec {
+    /// Address of the CSR to access.
+    pub addr: u16,
+
+    /// Operation to perform on the CSR.
+    pub op: CsrOperation,
+
+    /// Operand for the operation.
+    pub value: u64,
+
+    /// Register to which the previous value of the CSR should be written.
+    pub dest: u8,
+
+    /// Whether the CSR should be read.
+    ///
+    /// If false, the CSR is not read, and no read side-effects occur.
+    pub read: bool,
+
+    /// Whether the CSR should be written.
+    ///
+    /// If false, the CSR is not written, and no write side-effects occur.
+    pub write: bool,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Csr, CsrFile};
+    use crate::error::ProcessorException;
+    use crate::processor::PrivilegeLevel;
+    use std::sync::atomic::{AtomicU64, Ordering};
+    use std::sync::Arc;
+
+    #[test]
+    fn unregistered_csr() {
+        let mut csrs = CsrFile::new();
+        assert_eq!(
+            csrs.read(0x340, PrivilegeLevel::Machine),
+            Err(ProcessorException::IllegalInstruction)
+        );
+        assert_eq!(
+            csrs.write(0x340, 1, PrivilegeLevel::Machine),
+            Err(ProcessorException::IllegalInstruction)
+        );
+    }
+
+    #[test]
+    fn masks() {
+        let mut csrs = CsrFile::new();
+        csrs.register(
+            0x340,
+            Csr::new(0xf0f0_0000)
+                .with_read_mask(0xffff_00ff)
+                .with_write_mask(0x0000_ffff),
+        );
+
+        csrs.write(0x340, 0xffff_ffff, PrivilegeLevel::Machine)
+            .unwrap();
+        assert_eq!(csrs.get(0x340).unwrap().value, 0xf0f0_ffff);
+        assert_eq!(
+            csrs.read(0x340, PrivilegeLevel::Machine).unwrap(),
+            0xf0f0_00ff
+        );
+    }
+
+    #[test]
+    fn privilege() {
+        let mut csrs = CsrFile::new();
+        csrs.register(0x340, Csr::new(0));
+        csrs.register(0x001, Csr::new(0));
+        csrs.register(
+            0x002,
+            Csr::new(0).with_privilege(PrivilegeLevel::Supervisor),
+        );
+        csrs.register(0xc00, Csr::new(0));
+
+        // Privilege encoded in address
+        assert_eq!(
+            csrs.read(0x340, PrivilegeLevel::Supervisor),
+            Err(ProcessorException::IllegalInstruction)
+        );
+        assert!(csrs.read(0x340, PrivilegeLevel::Machine).is_ok());
+        assert!(csrs.read(0x001, PrivilegeLevel::User).is_ok());
+
+        // Privilege specified on registration
+        assert_eq!(
+            csrs.read(0x002, PrivilegeLevel::User),
+            Err(ProcessorException::IllegalInstruction)
+        );
+        assert!(csrs.read(0x002, PrivilegeLevel::Supervisor).is_ok());
+
+        // Read-only
+        assert!(csrs.read(0xc00, PrivilegeLevel::User).is_ok());
+        assert_eq!(
+            csrs.write(0xc00, 0, PrivilegeLevel::Machine),
+            Err(ProcessorException::IllegalInstruction)
+        );
+    }
+
+    #[test]
+    fn callbacks() {
+        let shared = Arc::new(AtomicU64::new(7));
+        let read_shared = shared.clone();
+        let write_shared = shared.clone();
+
+        let mut csrs = CsrFile::new();
+        csrs.register(
+            0x001,
+            Csr::new(0)
+                .on_read(move |_, _| read_shared.fetch_add(1, Ordering::Relaxed))
+                .on_write(move |_, new, _| {
+                    write_shared.store(new, Ordering::Relaxed);
+                    0
+                }),
+        );
+
+        assert_eq!(csrs.read(0x001, PrivilegeLevel::User).unwrap(), 7);
+        assert_eq!(csrs.read(0x001, PrivilegeLevel::User).unwrap(), 8);
+
+        csrs.write(0x001, 42, PrivilegeLevel::User).unwrap();
+        assert_eq!(shared.load(Ordering::Relaxed), 42);
+        assert_eq!(csrs.get(0x001).unwrap().value, 0);
+    }
+
+    #[test]
+    fn access_callback() {
+        let mut csrs = CsrFile::new();
+        csrs.register(0x340, Csr::new(0));
+        csrs.register(
+            0x015,
+            Csr::new(0).on_access(|csrs, privilege, write| {
+                write
+                    && (privilege == PrivilegeLevel::Machine || csrs.get(0x340).unwrap().value != 0)
+            }),
+        );
+
+        assert!(csrs.check(0x015, PrivilegeLevel::Machine, true).is_ok());
+        assert_eq!(
+            csrs.check(0x015, PrivilegeLevel::Machine, false),
+            Err(ProcessorException::IllegalInstruction)
+        );
+        assert_eq!(
+            csrs.check(0x015, PrivilegeLevel::User, true),
+            Err(ProcessorException::IllegalInstruction)
+        );
+
+        csrs.get_mut(0x340).unwrap().value = 1;
+        assert!(csrs.check(0x015, PrivilegeLevel::User, true).is_ok());
+    }
+}
@@ -3,26 +3,76 @@
 //! This module defines the [`Hart`] struct, which represents a single hardware thread, which runs
 //! instructions in sequence. A processor can consist of multiple such harts, running in parallel.
 
-use crate::error::{ProcessorException, WithPC};
-use crate::extension::OpcodeHandler;
-use crate::instruction::{Instruction, InstructionParts};
-use crate::mmu::{LoadSpec, StoreSpec};
-use crate::processor::register::{GeneralPurposeRegister, RegisterFile, ZeroRegister};
+use crate::error::ProcessorException;
+use crate::extension::{OpcodeHandler, SubDecoder, SubDecoderHandler};
+use crate::instruction::{CacheBlockOperation, Instruction, InstructionLength, InstructionParts};
+use crate::mmu::{AtomicSpec, LoadSpec, StoreSpec};
+use crate::processor::counter::{Counters, Event};
+use crate::processor::csr::{
+    Csr, CsrFile, CsrOperation, CsrSpec, ENVCFG_CBCFE, ENVCFG_CBIE_ENABLED, ENVCFG_CBZE, MARCHID,
+    MCAUSE, MENVCFG, MEPC, MHARTID, MIMPID, MISA, MSCRATCH, MSTATUS, MSTATUSH, MTVAL, MTVEC,
+    MVENDORID, SENVCFG,
+};
+use crate::processor::register::{
+    GeneralPurposeRegister, RegisterFile, ZeroRegister, FLOAT_REGISTER_BASE,
+};
+use crate::processor::trap::{
+    trap_vector, AccessType, Trap, MSTATUS_MIE, MSTATUS_MPIE, MSTATUS_MPP, MSTATUS_UXL, MTVEC_MODE,
+    MTVEC_MODE_VECTORED,
+};
+use crate::processor::{PrivilegeLevel, Xlen};
 use std::collections::{BTreeMap, HashMap};
 
 /// Memory accesses required by the hart after a cycle.
 ///
-/// Each instruction may require a value to be loaded from memory before it can be executed, or may
-/// require a value to be stored to memory following its execution. This struct, the return value of
+/// Each instruction may require values to be loaded from memory before it can be executed, or may
+/// require values to be stored to memory following its execution. This struct, the return value of
 /// [`Hart::cycle`], informs the processor of such accesses, so that they can be performed before
 /// the next cycle.
-#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+#[derive(Clone, Debug, Default, Eq, Hash, PartialEq)]
 pub struct MemoryAccess {
     /// Value which must be loaded from memory before the next instruction can execute.
     pub load: Option<LoadSpec>,
 
     /// Value which must be stored to memory, having executed an instruction.
     pub store: Option<StoreSpec>,
+
+    /// Atomic memory operation which must be performed before the next instruction can execute.
+    ///
+    /// If this is set, `load` will be `None`, and `loads` will be empty.
+    pub atomic: Option<AtomicSpec>,
+
+    /// Values which must be loaded from memory, in order, before the next instruction can execute.
+    ///
+    /// This is used by instructions which load multiple values (e.g. vector loads). If it is not
+    /// empty, `load` will be `None`.
+    pub loads: Vec<LoadSpec>,
+
+    /// Values which must be stored to memory, in order, having executed an instruction.
+    ///
+    /// These are stored after `store`, if it is also set.
+    pub stores: Vec<StoreSpec>,
+}
+
+/// How a hart handles stores to an instruction it has already decoded.
+///
+/// Each cycle, the hart decodes the instruction following the one it executes, so a store by the
+/// executing instruction to the address of the next instruction would otherwise leave a stale
+/// decoding to execute. Instructions further ahead are always fetched after any such store.
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, PartialEq)]
+pub enum CodeCoherence {
+    /// Stores to code only become visible to instruction fetches after a FENCE.I instruction.
+    ///
+    /// A store to the next instruction leaves the stale decoding in place, as hardware with an
+    /// instruction cache may do. This is the behaviour the ISA specifies programs should expect.
+    #[default]
+    FenceI,
+
+    /// Stores to code become visible to instruction fetches immediately.
+    ///
+    /// A store which overlaps the next instruction discards its decoding, so it is fetched again
+    /// from memory on the next cycle. This makes self-modifying code work without FENCE.I.
+    Immediate,
 }
 
 /// A hardware thread.
@@ -30,13 +80,37 @@ pub struct Hart {
     /// Registers of this hart.
     pub registers: RegisterFile,
 
+    /// Control and status registers of this hart.
+    ///
+    /// Extensions register any CSRs they define in this CSR file.
+    pub csrs: CsrFile,
+
+    /// Privilege level at which the hart is currently executing.
+    pub privilege: PrivilegeLevel,
+
+    /// Performance counters of this hart.
+    ///
+    /// The hart updates these as it executes: The Zicntr & Zihpm extensions expose them as CSRs.
+    pub counters: Counters,
+
+    /// Width of the hart's integer registers.
+    ///
+    /// This is RV32 by default: The base integer instruction set changes it using
+    /// [`set_xlen`](Self::set_xlen) when it is registered.
+    pub xlen: Xlen,
+
     /// The program counter.
     ///
     /// This stores the memory address of the instruction to execute next.
-    pub pc: u32,
+    pub pc: u64,
 
     /// Stores the memory adress of the instruction executed most recently.
-    pub prev_pc: u32,
+    pub prev_pc: u64,
+
+    /// Address at which the hart starts executing on reset.
+    ///
+    /// This is `0x00000000` by default, but may be set to e.g. the entry point of an ELF file.
+    pub reset_vector: u64,
 
     /// Opcode handlers used to decode instructions.
     ///
@@ -44,8 +118,25 @@ pub struct Hart {
     /// called if the hart encounters an instruction with the relevant opcode. It is the
     /// responsibility of the opcode handler to decode the provided instruction to produce an
     /// [`Instruction`], which may be executed on the next cycle.
+    ///
+    /// Handlers should be added with [`register_opcode`](Self::register_opcode) or
+    /// [`register_sub_decoder`](Self::register_sub_decoder) rather than inserted directly, so that
+    /// extensions sharing an opcode don't replace each other's handlers.
     pub opcodes: HashMap<u8, Box<dyn OpcodeHandler>>,
 
+    /// Required alignment of instruction addresses, in bytes.
+    ///
+    /// If an instruction jumps/branches to an address which is not a multiple of this value, an
+    /// [`InstructionAddressMisaligned`](ProcessorException::InstructionAddressMisaligned) exception
+    /// is raised. This is 4 for the base instruction sets, but extensions which add shorter
+    /// instructions (e.g. the compressed instruction extension) may relax this requirement.
+    pub instruction_alignment: u64,
+
+    /// How the hart handles stores to an instruction it has already decoded.
+    ///
+    /// This is [`CodeCoherence::FenceI`] by default.
+    pub code_coherence: CodeCoherence,
+
     /// The previous instruction executed by this hart.
     ///
     /// Used for UI/debugging purposes.
@@ -54,15 +145,26 @@ pub struct Hart {
     /// Instruction decoded on the previous cycle.
     ///
     /// If this is `None`, the execute portion of this cycle will not run: only the decode portion.
-    /// This happens when the processor jumps/branches.
-    next_instr: Option<Result<Box<dyn Instruction>, ProcessorException>>,
+    /// This happens when the processor jumps/branches. If this is an error, the trap is taken when
+    /// the instruction would have executed.
+    next_instr: Option<Result<Box<dyn Instruction>, Trap>>,
+
+    /// Memory access the instruction decoded on the previous cycle will perform, if any.
+    ///
+    /// This is counted as an [`Event`] if the instruction retires.
+    next_access: Option<Event>,
+
+    /// Whether the hart has entered a trap handler, and not yet executed any of its instructions.
+    ///
+    /// An exception raised in this state is a double fault, which cannot be handled by the guest.
+    trap_entry: bool,
 }
 
 impl Hart {
     /// Create a new Hart.
     ///
     /// This Hart will start executing instructions at address `0x00000000`. Change the value of
-    /// `Hart::pc` to change this.
+    /// [`Hart::reset_vector`] (and `Hart::pc`, if the hart has not yet been reset) to change this.
     pub fn new() -> Self {
         let mut registers: RegisterFile = BTreeMap::new();
         registers.insert(0, Box::new(ZeroRegister));
@@ -70,87 +172,552 @@ impl Hart {
             registers.insert(i, Box::new(GeneralPurposeRegister::new()));
         }
 
-        Self {
+        let mut hart = Self {
             registers,
+            csrs: CsrFile::new(),
+            counters: Counters::new(),
+            privilege: PrivilegeLevel::Machine,
+            xlen: Xlen::Rv32,
             pc: 0,
             prev_pc: 0,
+            reset_vector: 0,
             opcodes: HashMap::with_capacity(256),
+            instruction_alignment: 4,
+            code_coherence: CodeCoherence::default(),
             last_instr: None,
             next_instr: None,
+            next_access: None,
+            trap_entry: false,
+        };
+        hart.register_machine_csrs();
+        hart
+    }
+
+    /// Register the machine-level CSRs required for trap handling.
+    fn register_machine_csrs(&mut self) {
+        // MPP is WARL: Only machine and user mode are supported, so writes of any other mode are
+        // ignored
+        let mstatus = Csr::new(MSTATUS_MPP)
+            .with_write_mask(MSTATUS_MIE | MSTATUS_MPIE | MSTATUS_MPP)
+            .on_write(|old, new, _| {
+                let mpp = (new & MSTATUS_MPP) >> 11;
+                if mpp == 0b01 || mpp == 0b10 {
+                    (new & !MSTATUS_MPP) | (old & MSTATUS_MPP)
+                } else {
+                    new
+                }
+            });
+
+        // MODE is WARL: Only direct and vectored modes are supported
+        let mtvec = Csr::new(0).on_write(|old, new, _| {
+            if new & MTVEC_MODE > MTVEC_MODE_VECTORED {
+                (new & !MTVEC_MODE) | (old & MTVEC_MODE)
+            } else {
+                new
+            }
+        });
+
+        // MXL = 1 (32-bit), plus the U bit: Extension bits are set by the processor
+        self.csrs
+            .register(MISA, Csr::new(0x4010_0000).with_write_mask(0));
+        self.csrs.register(MSTATUS, mstatus);
+        self.csrs
+            .register(MSTATUSH, Csr::new(0).with_write_mask(0).rv32_only());
+        self.csrs.register(MTVEC, mtvec);
+        self.csrs.register(MSCRATCH, Csr::new(0));
+        self.csrs.register(MEPC, Csr::new(0).with_write_mask(!0b1));
+        self.csrs.register(MCAUSE, Csr::new(0));
+        self.csrs.register(MTVAL, Csr::new(0));
+        for addr in [MVENDORID, MARCHID, MIMPID, MHARTID] {
+            self.csrs.register(addr, Csr::new(0));
+        }
+    }
+
+    /// Set the width of the hart's integer registers.
+    ///
+    /// This should be called by the base integer instruction set when it is registered. As well as
+    /// setting [`Hart::xlen`] & the XLEN passed to CSR callbacks, this updates the machine-level
+    /// CSRs whose layout depends on the XLEN: `misa.MXL` moves to the top of the register, and on
+    /// RV64, `mstatus.UXL` reports that user mode is also 64-bit, and the
+    /// [`rv32_only`](field@Csr::rv32_only) CSRs (such as `mstatush`, and `menvcfgh` if an extension
+    /// registered it before the base instruction set) are removed.
+    pub fn set_xlen(&mut self, xlen: Xlen) {
+        self.xlen = xlen;
+        self.csrs.set_xlen(xlen);
+
+        if let Some(misa) = self.csrs.get_mut(MISA) {
+            let extensions = misa.value & ((1 << 26) - 1);
+            misa.value = (xlen.mxl() << (xlen.bits() - 2)) | extensions;
+        }
+
+        if xlen == Xlen::Rv32 {
+            self.csrs
+                .register(MSTATUSH, Csr::new(0).with_write_mask(0).rv32_only());
+        }
+        self.set_csr(MSTATUS, self.reset_mstatus());
+    }
+
+    /// Set the number of integer registers available to the hart.
+    ///
+    /// This should be called by base integer instruction sets which do not provide the usual 32
+    /// registers, such as RV32E, when they are registered. The register file is shaped to contain
+    /// `x0` through `x{count - 1}`, so instructions naming any other register raise an
+    /// IllegalInstruction exception.
+    pub fn set_register_count(&mut self, count: u8) {
+        self.registers
+            .retain(|&i, _| i < count || i >= FLOAT_REGISTER_BASE);
+        for i in 1..count {
+            self.registers
+                .entry(i)
+                .or_insert_with(|| Box::new(GeneralPurposeRegister::new()));
+        }
+    }
+
+    /// Register the handler for the provided opcode.
+    ///
+    /// Extensions may have already wrapped the opcode's handler with handlers which decode only
+    /// some of its instructions, such as [`SubDecoder`]s, before it is registered. In that case,
+    /// the handler is registered beneath them, and decodes only the instructions they don't, so
+    /// extensions sharing an opcode can be registered in any order. Returns the handler previously
+    /// registered for the opcode, if it is replaced.
+    pub fn register_opcode(
+        &mut self,
+        opcode: u8,
+        handler: Box<dyn OpcodeHandler>,
+    ) -> Option<Box<dyn OpcodeHandler>> {
+        let mut slot = self.opcodes.remove(&opcode);
+        let replaced = register_beneath(&mut slot, handler);
+        if let Some(handler) = slot {
+            self.opcodes.insert(opcode, handler);
+        }
+        replaced
+    }
+
+    /// Register a [`SubDecoder`] for the provided opcode.
+    ///
+    /// The decoder is offered each instruction with the opcode before the handler already
+    /// registered for it (if any), which decodes any instructions the decoder doesn't recognise.
+    /// Decoders registered later are offered instructions first.
+    pub fn register_sub_decoder(&mut self, opcode: u8, decoder: Box<dyn SubDecoder>) {
+        let inner = self.opcodes.remove(&opcode);
+        self.opcodes
+            .insert(opcode, Box::new(SubDecoderHandler::new(decoder, inner)));
+    }
+
+    /// Value of `mstatus` on reset.
+    fn reset_mstatus(&self) -> u64 {
+        match self.xlen {
+            Xlen::Rv32 => MSTATUS_MPP,
+            Xlen::Rv64 => MSTATUS_MPP | ((self.xlen.mxl() << 32) & MSTATUS_UXL),
         }
     }
 
     /// Reset the hart.
     ///
-    /// On the next cycle, the hart will resume execution at address 0, discarding any intermediate
-    /// instruction decodings to execute.
+    /// On the next cycle, the hart will resume execution at [`Hart::reset_vector`] in machine mode,
+    /// discarding any intermediate instruction decodings to execute.
     pub fn reset(&mut self) {
-        self.pc = 0;
-        self.prev_pc = 0;
+        self.pc = self.reset_vector;
+        self.prev_pc = self.reset_vector;
+        self.privilege = PrivilegeLevel::Machine;
         self.last_instr = None;
         self.next_instr = None;
+        self.next_access = None;
+        self.trap_entry = false;
+        self.counters.reset();
+
+        for (addr, value) in [
+            (MSTATUS, self.reset_mstatus()),
+            (MTVEC, 0),
+            (MSCRATCH, 0),
+            (MEPC, 0),
+            (MCAUSE, 0),
+            (MTVAL, 0),
+        ] {
+            self.set_csr(addr, value);
+        }
+    }
+
+    /// Address of the next instruction the hart will execute.
+    ///
+    /// If an instruction has been decoded, this is its address. Otherwise (e.g: following a jump),
+    /// the next cycle will only decode the instruction at [`Hart::pc`], which will then execute on
+    /// the cycle after.
+    pub fn next_pc(&self) -> u64 {
+        if self.has_decoded_instruction() {
+            self.prev_pc
+        } else {
+            self.pc
+        }
+    }
+
+    /// Whether an instruction has been decoded, and will execute on the next cycle.
+    pub fn has_decoded_instruction(&self) -> bool {
+        self.next_instr.is_some()
+    }
+
+    /// Redirect execution to the provided address.
+    ///
+    /// Any instruction which has been decoded is discarded, so on the next cycle, the hart will
+    /// start decoding at `pc`. This can be used by debuggers to change the program counter, or to
+    /// ensure an instruction is re-decoded after its memory or operands have been modified.
+    pub fn redirect(&mut self, pc: u64) {
+        self.pc = pc;
+        self.prev_pc = pc;
+        self.next_instr = None;
+        self.next_access = None;
     }
 
     /// Perform a single decode-execute cycle.
     ///
-    /// `raw_instr` should be the 32-bit memory value starting at address `self.pc`.
+    /// `raw_instr` should be the result of fetching the instruction starting at address `self.pc`.
+    /// If the fetch failed, the resulting exception will be raised if and when the hart attempts to
+    /// execute the instruction, rather than immediately.
     ///
     /// If the previous cycle's [`MemoryAccess`] return value specified a [`LoadSpec`], then `mem`
-    /// should be the result of loading from memory according ot this spec. Otherwise, the value of
-    /// `mem` is unspecified.
+    /// should be the result of loading from memory according ot this spec. Likewise, if it
+    /// specified an [`AtomicSpec`], `mem` should be the result of performing that operation.
+    /// Otherwise, the value of `mem` is unspecified. If it specified multiple `LoadSpec`s in
+    /// [`MemoryAccess::loads`], `values` should hold the result of each load, in order: Otherwise,
+    /// `values` should be empty.
     ///
     /// If the cycle was successful, returns a [`MemoryAccess`] value indicating whether data needs
     /// to be loaded from/stored to memory before the next cycle.
     ///
-    /// If an exception occurs, currently returns a [`ProcessorException`], together with the
-    /// address of the instruction which caused the exception. In the future exceptions will be
-    /// handled by higher-privileged trap handlers.
+    /// Exceptions are handled by trapping into the machine-mode trap handler (see
+    /// [`trap`](Self::trap)). If the exception cannot be handled, returns the
+    /// [`ProcessorException`], together with the address of the instruction which caused it.
+    ///
+    /// Each cycle increments the cycle counter in [`Hart::counters`], whether or not an instruction
+    /// retires.
     pub fn cycle(
         &mut self,
-        raw_instr: u32,
-        mem: i32,
-    ) -> Result<MemoryAccess, (ProcessorException, u32)> {
+        raw_instr: Result<u32, ProcessorException>,
+        mem: i64,
+        values: &[i64],
+    ) -> Result<MemoryAccess, (ProcessorException, u64)> {
+        let result = match self.step(raw_instr, mem, values) {
+            Ok(access) => Ok(access),
+            Err((trap, epc)) => self.trap(trap, epc).map(|_| MemoryAccess::default()),
+        };
+        self.counters.tick();
+        result
+    }
+
+    /// Take a trap into the machine-mode trap handler.
+    ///
+    /// `epc` should be the address of the instruction which caused the trap. On the next cycle, the
+    /// hart will start decoding the trap handler, discarding any intermediate instruction decodings
+    /// to execute.
+    ///
+    /// If no trap handler is installed (the base address in `mtvec` is zero), or if the trap
+    /// handler has not yet executed any instructions since the last trap (a double fault), the
+    /// exception cannot be handled by the guest, so it is returned together with `epc` instead.
+    pub fn trap(&mut self, trap: Trap, epc: u64) -> Result<(), (ProcessorException, u64)> {
+        let mtvec = self.csrs.get(MTVEC).map_or(0, |csr| csr.value);
+        if mtvec & !MTVEC_MODE == 0 || self.trap_entry {
+            return Err((trap.exception, epc));
+        }
+
+        let cause = trap.cause(self.privilege);
+        let mstatus = self.csrs.get(MSTATUS).map_or(0, |csr| csr.value);
+
+        // Save the previous privilege & interrupt-enable state, and disable interrupts
+        let mpie = if mstatus & MSTATUS_MIE != 0 {
+            MSTATUS_MPIE
+        } else {
+            0
+        };
+        let mpp = (self.privilege.bits() as u64) << 11;
+        let mstatus = (mstatus & !(MSTATUS_MIE | MSTATUS_MPIE | MSTATUS_MPP)) | mpie | mpp;
+
+        self.set_csr(MSTATUS, mstatus);
+        self.set_csr(MEPC, epc & !0b1);
+        self.set_csr(MCAUSE, cause);
+        self.set_csr(MTVAL, trap.tval);
+
+        self.privilege = PrivilegeLevel::Machine;
+        self.pc = trap_vector(mtvec, cause, self.xlen);
+        self.prev_pc = epc;
+        self.next_instr = None;
+        self.next_access = None;
+        self.trap_entry = true;
+
+        self.counters.record(Event::Trap);
+        self.counters.record(Event::Flush);
+
+        Ok(())
+    }
+
+    /// Perform a single decode-execute cycle, returning any exception to be handled.
+    fn step(
+        &mut self,
+        raw_instr: Result<u32, ProcessorException>,
+        mem: i64,
+        values: &[i64],
+    ) -> Result<MemoryAccess, (Trap, u64)> {
+        // Address of the instruction decoded on the previous cycle, which executes this cycle
+        let exec_pc = self.prev_pc;
         let cur_pc = self.pc;
-        let mut next_pc = self.pc + 4;
 
         // Decode the next instruction
-        let mut next_instr = Some(self.decode(raw_instr));
+        let (mut next_instr, length) = match raw_instr {
+            Ok(raw) => (
+                Some(
+                    self.decode(raw)
+                        .map_err(|e| Trap::new(e, AccessType::Fetch, raw as u64)),
+                ),
+                InstructionParts::identify_instruction_length(raw),
+            ),
+            Err(e) => (
+                Some(Err(Trap::new(e, AccessType::Fetch, cur_pc))),
+                InstructionLength::Word,
+            ),
+        };
+        let mut next_pc = self.address(cur_pc.wrapping_add(length.bytes() as u64));
 
         // Execute the current instruction
-        let store = match &self.next_instr {
+        let access = self.next_access.take();
+        let (store, stores) = match self.next_instr.take() {
             Some(Ok(instr)) => {
                 self.last_instr = Some(instr.format());
 
-                let result = instr.execute(&mut self.registers, mem).with_pc(cur_pc)?;
+                let result = if values.is_empty() {
+                    instr.execute(&mut self.registers, mem)
+                } else {
+                    instr.execute_loads(&mut self.registers, values)
+                };
+                let result = result.map_err(|e| {
+                    let tval = match e {
+                        ProcessorException::EnvironmentBreak => exec_pc,
+                        _ => 0,
+                    };
+                    (Trap::new(e, AccessType::Load, tval), exec_pc)
+                })?;
+
+                if let Some(op) = result.cache_block {
+                    self.check_cache_block(op)
+                        .map_err(|e| (Trap::new(e, AccessType::Load, 0), exec_pc))?;
+                }
+
+                if let Some(csr) = result.csr {
+                    self.access_csr(csr)
+                        .map_err(|e| (Trap::new(e, AccessType::Load, 0), exec_pc))?;
+                }
+
+                // If the instruction returns from a trap handler, resume execution at the address
+                // stored when the trap was taken.
+                if let Some(privilege) = result.trap_return {
+                    next_instr = None;
+                    next_pc = self
+                        .trap_return(privilege)
+                        .map_err(|e| (Trap::new(e, AccessType::Load, 0), exec_pc))?;
+                    self.counters.record(Event::Flush);
+                }
 
                 // If the instruction specifies a jump, invalidate the next instruction decoding and
                 // set the pc as required.
                 if let Some(pc) = result.jump {
+                    let pc = self.address(pc);
+                    if !pc.is_multiple_of(self.instruction_alignment) {
+                        let exception = ProcessorException::InstructionAddressMisaligned;
+                        return Err((Trap::new(exception, AccessType::Fetch, pc), exec_pc));
+                    }
+
                     next_instr = None;
                     next_pc = pc;
+                    self.counters.record(Event::BranchTaken);
+                    self.counters.record(Event::Flush);
+                }
+
+                // If the instruction flushes the instruction stream, or stores over the next
+                // instruction when stores to code are immediately visible, fetch the next
+                // instruction again once the store has been performed.
+                let overwrites_next = self.code_coherence == CodeCoherence::Immediate
+                    && result.store.iter().chain(&result.stores).any(|store| {
+                        let start = self.address(store.addr as u64);
+                        let end = start.saturating_add(store.access_type.bytes() as u64);
+                        start < cur_pc.wrapping_add(length.bytes() as u64) && cur_pc < end
+                    });
+                if (result.flush || overwrites_next) && next_instr.is_some() {
+                    next_instr = None;
+                    next_pc = cur_pc;
+                    self.counters.record(Event::Flush);
+                }
+
+                self.counters.retire();
+                if let Some(event) = access {
+                    self.counters.record(event);
+                }
+                if result.store.is_some() || !result.stores.is_empty() {
+                    self.counters.record(Event::Store);
                 }
 
-                result.store
+                self.trap_entry = false;
+                (result.store, result.stores)
             }
-            Some(Err(e)) => return Err((*e, cur_pc)),
+            Some(Err(trap)) => return Err((trap, exec_pc)),
             None => {
                 self.last_instr = None;
-                None
+                (None, Vec::new())
             }
         };
 
-        // Determine memory load spec for use by the next instruction
+        // Determine memory load/atomic spec for use by the next instruction. If this fails, the
+        // exception is raised when the next instruction would execute.
         let mut load = None;
+        let mut atomic = None;
+        let mut loads = Vec::new();
         if let Some(Ok(instr)) = &next_instr {
-            load = instr.load(&self.registers).with_pc(next_pc)?;
+            let spec = match instr.atomic(&self.registers) {
+                Ok(Some(spec)) => {
+                    atomic = Some(spec);
+                    Ok(())
+                }
+                Ok(None) => instr
+                    .loads(&self.registers)
+                    .and_then(|specs| {
+                        if specs.is_empty() {
+                            load = instr.load(&self.registers)?;
+                        }
+                        loads = specs;
+                        Ok(())
+                    })
+                    .map_err(|e| Trap::new(e, AccessType::Load, 0)),
+                // No memory has been accessed yet, so this is a fault executing the instruction
+                Err(e) => Err(Trap::new(e, AccessType::Load, 0)),
+            };
+
+            if let Err(trap) = spec {
+                next_instr = Some(Err(trap));
+            }
         }
 
         // Update state for next instruction.
         self.pc = next_pc;
         self.prev_pc = cur_pc;
         self.next_instr = next_instr;
+        self.next_access = if atomic.is_some() {
+            Some(Event::Atomic)
+        } else if load.is_some() || !loads.is_empty() {
+            Some(Event::Load)
+        } else {
+            None
+        };
+
+        Ok(MemoryAccess {
+            load,
+            store,
+            atomic,
+            loads,
+            stores,
+        })
+    }
+
+    /// Return from a trap handler running at the provided privilege level.
+    ///
+    /// Restores the privilege & interrupt-enable state saved when the trap was taken, returning the
+    /// address at which execution should resume.
+    fn trap_return(&mut self, privilege: PrivilegeLevel) -> Result<u64, ProcessorException> {
+        if privilege != PrivilegeLevel::Machine || self.privilege < privilege {
+            return Err(ProcessorException::IllegalInstruction);
+        }
+
+        let mstatus = self.csrs.get(MSTATUS).map_or(0, |csr| csr.value);
+        let mepc = self.csrs.get(MEPC).map_or(0, |csr| csr.value);
+
+        // Restore the interrupt-enable bit, and set MPP to the least-privileged supported mode
+        let mie = if mstatus & MSTATUS_MPIE != 0 {
+            MSTATUS_MIE
+        } else {
+            0
+        };
+        let mpp = (mstatus & MSTATUS_MPP) >> 11;
 
-        Ok(MemoryAccess { load, store })
+        self.set_csr(
+            MSTATUS,
+            (mstatus & !(MSTATUS_MIE | MSTATUS_MPP)) | mie | MSTATUS_MPIE,
+        );
+        self.privilege = PrivilegeLevel::from_bits(mpp as u8);
+
+        Ok(self.address(mepc) & !(self.instruction_alignment - 1))
+    }
+
+    /// Check the provided cache-block management operation is enabled at the current privilege
+    /// level.
+    ///
+    /// Machine mode may always perform these operations. Supervisor mode may only perform them if
+    /// they are enabled by `menvcfg`, and user mode only if they are enabled by both `menvcfg` and
+    /// `senvcfg`. If these CSRs are not registered, the operations are disabled.
+    fn check_cache_block(&self, op: CacheBlockOperation) -> Result<(), ProcessorException> {
+        let mask = match op {
+            CacheBlockOperation::Clean | CacheBlockOperation::Flush => ENVCFG_CBCFE,
+            CacheBlockOperation::Invalidate => ENVCFG_CBIE_ENABLED,
+            CacheBlockOperation::Zero => ENVCFG_CBZE,
+        };
+        let enabled = |addr| self.csrs.get(addr).is_some_and(|csr| csr.value & mask != 0);
+
+        let permitted = match self.privilege {
+            PrivilegeLevel::Machine => true,
+            PrivilegeLevel::Supervisor => enabled(MENVCFG),
+            PrivilegeLevel::User => enabled(MENVCFG) && enabled(SENVCFG),
+            PrivilegeLevel::Hypervisor => false,
+        };
+        if !permitted {
+            return Err(ProcessorException::IllegalInstruction);
+        }
+        Ok(())
+    }
+
+    /// Truncate an address to XLEN bits, wrapping it around the address space.
+    fn address(&self, addr: u64) -> u64 {
+        self.xlen.zero_extend(addr as i64)
+    }
+
+    /// Set the value of a CSR directly, bypassing its masks & callbacks.
+    fn set_csr(&mut self, addr: u16, value: u64) {
+        if let Some(csr) = self.csrs.get_mut(addr) {
+            csr.value = value;
+        }
+    }
+
+    /// Perform the CSR access requested by an instruction.
+    ///
+    /// The previous value of the CSR is written to the destination register, and the CSR is updated
+    /// according to the requested operation. Only the lowest XLEN bits of the operand are used.
+    fn access_csr(&mut self, spec: CsrSpec) -> Result<(), ProcessorException> {
+        let operand = self.xlen.zero_extend(spec.value as i64);
+
+        // Check the destination register exists & the CSR can be accessed before any side-effects
+        // occur
+        if spec.read && !self.registers.contains_key(&spec.dest) {
+            return Err(ProcessorException::IllegalInstruction);
+        }
+        self.csrs.check(spec.addr, self.privilege, spec.write)?;
+
+        let prev = if spec.read {
+            self.csrs.read(spec.addr, self.privilege)?
+        } else {
+            0
+        };
+
+        if spec.write {
+            let value = match spec.op {
+                CsrOperation::Write => operand,
+                CsrOperation::Set => prev | operand,
+                CsrOperation::Clear => prev & !operand,
+            };
+            self.csrs.write(spec.addr, value, self.privilege)?;
+        }
+
+        if spec.read {
+            self.registers
+                .get_mut(&spec.dest)
+                .ok_or(ProcessorException::IllegalInstruction)?
+                .store(self.xlen.sign_extend(prev as i64))?;
+        }
+
+        Ok(())
     }
 
     /// Decode the provided raw instruction.
@@ -160,6 +727,28 @@ impl Hart {
             .opcodes
             .get(&parts.opcode())
             .ok_or(ProcessorException::IllegalInstruction)?;
-        handler.decode(parts, self.pc)
+        handler.decode(parts, self.pc, self.xlen)
+    }
+}
+
+impl Default for Hart {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Register an opcode handler in the provided slot, beneath any handlers already wrapping it.
+///
+/// Returns the handler which was replaced, if any.
+fn register_beneath(
+    slot: &mut Option<Box<dyn OpcodeHandler>>,
+    handler: Box<dyn OpcodeHandler>,
+) -> Option<Box<dyn OpcodeHandler>> {
+    match slot {
+        Some(current) => match current.inner_mut() {
+            Some(inner) => register_beneath(inner, handler),
+            None => slot.replace(handler),
+        },
+        None => slot.replace(handler),
     }
 }
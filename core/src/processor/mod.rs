@@ -5,31 +5,147 @@
 //! following occurs:
 //! * The processor retrieves the instructions at the memory addresses specified by each hart's
 //!   [`Hart::pc`] value
-//! * The processor retrieves the value at the memory locations specified by each hart in its
-//!   [`hart::MemoryAccess`] return value last cycle
-//! * The processor calls [`Hart::cycle`] with the fetched instruction & value
-//!     * The hart decodes the fetched instruction, and determines if it requires a memory load
-//!         * If a memory load is required, this is indicated in the function return value
+//! * The processor retrieves the values at the memory locations specified by each hart in its
+//!   [`hart::MemoryAccess`] return value last cycle, or performs the atomic memory operation
+//!   specified there
+//! * The processor calls [`Hart::cycle`] with the fetched instruction & values
+//!     * The hart decodes the fetched instruction, and determines if it requires memory loads
+//!         * If memory loads are required, this is indicated in the function return value
 //!         * This decoded instruction is stored in the struct, to be executed next time
 //!           [`Hart::cycle`] is called
 //!     * The hart executes the instruction decoded in the previous cycle, supplying the memory
-//!       value retrieved by the processor
-//!         * The instruction indicates whether if a memory store is required: If so, this is
+//!       values retrieved by the processor
+//!         * The instruction indicates whether if memory stores are required: If so, this is
 //!           indicated in the function return value
-//! * If the return value of `Hart::cycle` indicates a store is required, the processor stores the
-//!   provided value to memory at the provided address.
+//! * If the return value of `Hart::cycle` indicates stores are required, the processor stores the
+//!   provided values to memory at the provided addresses.
+//!
+//! If an exception occurs at any point, the hart traps into the machine-mode trap handler, as
+//! described in the [`trap`] module.
 //!
 //! Actual instruction behaviour is specified separately, in [`Extension`]s.
 
+pub mod counter;
+pub mod csr;
 pub mod hart;
 pub mod register;
+pub mod trap;
 
-use crate::error::{ProcessorException, WithPC};
+use crate::error::ProcessorException;
 use crate::extension::Extension;
-use crate::mmu::{LoadSpec, MMU};
-use hart::Hart;
+use crate::mmu::{AtomicOperation, AtomicSpec, LoadSpec, MMU};
+use hart::{Hart, MemoryAccess};
 use std::fmt;
-use std::sync::{Arc, RwLock};
+use std::sync::{Arc, Mutex};
+use trap::{AccessType, Trap};
+
+/// Privilege level at which a hart is executing.
+///
+/// Privilege levels are ordered, so that a more-privileged level compares greater than a
+/// less-privileged level.
+#[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub enum PrivilegeLevel {
+    /// User/application mode.
+    User,
+
+    /// Supervisor mode.
+    Supervisor,
+
+    /// Hypervisor mode (reserved).
+    Hypervisor,
+
+    /// Machine mode.
+    Machine,
+}
+
+impl PrivilegeLevel {
+    /// Determine the privilege level encoded by the provided 2-bit value.
+    ///
+    /// Only the lowest 2 bits of `bits` are considered.
+    pub fn from_bits(bits: u8) -> Self {
+        match bits & 0b11 {
+            0b00 => PrivilegeLevel::User,
+            0b01 => PrivilegeLevel::Supervisor,
+            0b10 => PrivilegeLevel::Hypervisor,
+            _ => PrivilegeLevel::Machine,
+        }
+    }
+
+    /// Get the 2-bit encoding of this privilege level.
+    pub fn bits(&self) -> u8 {
+        match self {
+            PrivilegeLevel::User => 0b00,
+            PrivilegeLevel::Supervisor => 0b01,
+            PrivilegeLevel::Hypervisor => 0b10,
+            PrivilegeLevel::Machine => 0b11,
+        }
+    }
+}
+
+/// Width of the integer registers of a hart (XLEN), in bits.
+///
+/// This is selected by the base integer instruction set. Registers always store 64 bits: On an
+/// RV32 hart, values are kept sign-extended from 32 bits, which is also how RV64 represents the
+/// 32-bit results of its "W" instructions. The helpers here convert values between the register
+/// representation & the representation used for a given XLEN, so that instructions can be shared
+/// between RV32 & RV64.
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, PartialEq)]
+pub enum Xlen {
+    /// 32-bit registers.
+    #[default]
+    Rv32,
+
+    /// 64-bit registers.
+    Rv64,
+}
+
+impl Xlen {
+    /// Number of bits in a register.
+    pub fn bits(&self) -> u32 {
+        match self {
+            Xlen::Rv32 => 32,
+            Xlen::Rv64 => 64,
+        }
+    }
+
+    /// Number of bytes in a register.
+    pub fn bytes(&self) -> usize {
+        self.bits() as usize / 8
+    }
+
+    /// Mask of the bits of a register which specify a shift amount.
+    pub fn shift_mask(&self) -> u32 {
+        self.bits() - 1
+    }
+
+    /// Sign-extend the lowest XLEN bits of `value` to 64 bits.
+    ///
+    /// Instructions should apply this to each result written to a register.
+    pub fn sign_extend(&self, value: i64) -> i64 {
+        match self {
+            Xlen::Rv32 => value as i32 as i64,
+            Xlen::Rv64 => value,
+        }
+    }
+
+    /// Zero-extend the lowest XLEN bits of `value` to 64 bits.
+    ///
+    /// This gives the unsigned interpretation of a register value, e.g: to use it as an address.
+    pub fn zero_extend(&self, value: i64) -> u64 {
+        match self {
+            Xlen::Rv32 => value as u32 as u64,
+            Xlen::Rv64 => value as u64,
+        }
+    }
+
+    /// Value of the `misa.MXL` field for this XLEN.
+    pub fn mxl(&self) -> u64 {
+        match self {
+            Xlen::Rv32 => 1,
+            Xlen::Rv64 => 2,
+        }
+    }
+}
 
 /// Configuration to instantiate a processor.
 pub struct ProcessorConfig {
@@ -41,22 +157,26 @@ pub struct ProcessorConfig {
     pub harts: usize,
 
     /// MMU for the system.
-    pub mmu: Arc<RwLock<MMU>>,
+    pub mmu: Arc<Mutex<MMU>>,
 
     /// Extensions to support.
     ///
     /// This includes the base integer instruction set to use, plus any extensions.
     pub extensions: Vec<Box<dyn Extension>>,
+
+    /// Address at which harts start executing, on creation & reset.
+    pub reset_vector: u64,
 }
 
 impl fmt::Debug for ProcessorConfig {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         let extensions: Vec<&str> = self.extensions.iter().map(|e| e.code()).collect();
         f.write_fmt(format_args!(
-            "ProcessorConfig {{ harts: {:?}, mmu: {:?}, extensions: {} }}",
+            "ProcessorConfig {{ harts: {:?}, mmu: {:?}, extensions: {}, reset_vector: {:#010x} }}",
             self.harts,
             self.mmu,
-            extensions.join("")
+            extensions.join(""),
+            self.reset_vector
         ))
     }
 }
@@ -70,7 +190,7 @@ pub struct Processor {
     pub hart: Hart,
 
     /// MMU for the system.
-    pub mmu: Arc<RwLock<MMU>>,
+    pub mmu: Arc<Mutex<MMU>>,
 
     /// Memory load request from the previous cycle.
     ///
@@ -79,73 +199,191 @@ pub struct Processor {
     /// according to the provided specification, and supply this to the hart.
     load: Option<LoadSpec>,
 
+    /// Atomic memory operation request from the previous cycle.
+    ///
+    /// If set, the processor should perform this operation with exclusive access to the MMU, and
+    /// supply the result to the hart.
+    atomic: Option<AtomicSpec>,
+
+    /// Memory load requests for multiple values from the previous cycle.
+    ///
+    /// If not empty, the processor should load each value in order, and supply them all to the
+    /// hart.
+    loads: Vec<LoadSpec>,
+
     /// Program counter value of the hart at the previous cycle.
-    prev_pc: u32,
+    prev_pc: u64,
+
+    /// Memory accesses performed during the most recent cycle.
+    ///
+    /// `load`, `atomic` and `loads` are the accesses performed before the current instruction
+    /// executed, and `store` and `stores` are the accesses performed after. If one of `stores`
+    /// failed, only those preceding it are included. This is used by debuggers to implement
+    /// watchpoints.
+    pub last_access: MemoryAccess,
 }
 
 impl Processor {
     /// Create a new [`Processor`].
     pub fn new(config: ProcessorConfig) -> Self {
         let mut hart = Hart::new();
+        hart.reset_vector = config.reset_vector;
+        hart.pc = config.reset_vector;
+        hart.prev_pc = config.reset_vector;
 
+        let mut misa = 0;
         for extension in config.extensions {
             extension.register(&mut hart);
+
+            // Single-letter extensions, and the base integer instruction set, are reported in misa
+            let code = extension.code();
+            let letter = match code.strip_prefix("RV32").or(code.strip_prefix("RV64")) {
+                Some(base) => base.chars().next(),
+                None if code.len() == 1 => code.chars().next(),
+                None => None,
+            };
+            if let Some(letter @ 'A'..='Z') = letter {
+                misa |= 1 << (letter as u32 - 'A' as u32);
+            }
+        }
+        if let Some(csr) = hart.csrs.get_mut(csr::MISA) {
+            csr.value |= misa;
         }
 
         Self {
             hart,
             mmu: config.mmu,
             load: None,
-            prev_pc: 0,
+            atomic: None,
+            loads: Vec::new(),
+            prev_pc: config.reset_vector,
+            last_access: MemoryAccess::default(),
         }
     }
 
     /// Reset the processor.
     ///
-    /// Resets each hart of the processor, so at the next cycle, instruction will start executing
-    /// instructions at address 0.
+    /// Resets each hart of the processor, so at the next cycle, each hart will start executing
+    /// instructions at its reset vector.
     pub fn reset(&mut self) {
         self.hart.reset();
+        self.mmu.lock().unwrap().clear_reservation();
         self.load = None;
-        self.prev_pc = 0;
+        self.atomic = None;
+        self.loads.clear();
+        self.prev_pc = self.hart.reset_vector;
+        self.last_access = MemoryAccess::default();
+    }
+
+    /// Redirect execution to the provided address.
+    ///
+    /// Any instruction which has been decoded is discarded, along with its memory access request.
+    /// See [`Hart::redirect`].
+    pub fn redirect(&mut self, pc: u64) {
+        self.hart.redirect(pc);
+        self.load = None;
+        self.atomic = None;
+        self.loads.clear();
+        self.prev_pc = pc;
     }
 
     /// Execute a processor cycle.
     ///
-    /// Currently this returns a [`ProcessorException`] with the program counter indicating the
-    /// location of the instruction which caused the exception if there is any exception, since
-    /// M-mode is not yet implemented, so software exception-handling is not possible. In the
-    /// future, the processor will only return an error (or some other indicator value) if a reset
-    /// or halt is requested.
-    pub fn cycle(&mut self) -> Result<(), (ProcessorException, u32)> {
+    /// Exceptions are handled by the hart's machine-mode trap handler. If an exception cannot be
+    /// handled by the guest (e.g: No trap handler has been installed), returns the
+    /// [`ProcessorException`], with the program counter indicating the location of the
+    /// instruction which caused the exception. In the future, the processor will also return an
+    /// error (or some other indicator value) if a reset or halt is requested.
+    pub fn cycle(&mut self) -> Result<(), (ProcessorException, u64)> {
         let prev_pc = self.prev_pc;
         let cur_pc = self.hart.pc;
+        self.last_access = MemoryAccess::default();
 
-        // Fetch the next instruction
-        let mmu = self.mmu.read().unwrap();
-        let instr = mmu.load_word(cur_pc as usize).with_pc(cur_pc)? as u32;
-
-        // Fetch the memory value requested by the current instruction
-        let mem = if let Some(access) = self.load {
-            mmu.load(access).with_pc(prev_pc)?
-        } else {
-            0
+        // Perform the memory loads/atomic memory operation requested by the current instruction,
+        // and fetch the next instruction. Loads may have side-effects on memory-mapped devices, so
+        // the MMU is held exclusively for all of these accesses, which also ensures the atomic
+        // memory operation is indivisible. The fetch comes last, so that it observes any value
+        // written by the atomic memory operation.
+        let mut mmu = self.mmu.lock().unwrap();
+        let mem = match (self.load, self.atomic) {
+            (_, Some(access)) => mmu.atomic(access).map_err(|e| {
+                // Load-reserved only reads memory, so faults are reported as load faults
+                let access_type = match access.op {
+                    AtomicOperation::LoadReserved => AccessType::Load,
+                    _ => AccessType::Store,
+                };
+                (e, access_type, access.addr)
+            }),
+            (Some(access), None) => mmu
+                .load(access)
+                .map_err(|e| (e, AccessType::Load, access.addr)),
+            (None, None) => Ok(0),
         };
+        let values = self
+            .loads
+            .iter()
+            .map(|&access| {
+                mmu.load(access)
+                    .map_err(|e| (e, AccessType::Load, access.addr))
+            })
+            .collect::<Result<Vec<_>, _>>();
+        let instr = mmu.load_instruction(cur_pc as usize);
         drop(mmu);
 
+        let (mem, values) = match mem.and_then(|mem| Ok((mem, values?))) {
+            Ok(loaded) => {
+                self.last_access.load = self.load;
+                self.last_access.atomic = self.atomic;
+                self.last_access.loads = self.loads.clone();
+                loaded
+            }
+            Err((e, access, addr)) => {
+                self.hart.counters.tick();
+                return self.trap(Trap::new(e, access, addr as u64), prev_pc);
+            }
+        };
+
         // Execute the current instruction & decode the next instruction
-        let result = self.hart.cycle(instr, mem)?;
+        let result = self.hart.cycle(instr, mem, &values)?;
+
+        // Save PC & memory load requests for next instruction
+        self.prev_pc = self.hart.prev_pc;
+        self.load = result.load;
+        self.atomic = result.atomic;
+        self.loads = result.loads;
 
         // Store to memory if required by the current instruction
         if let Some(store) = result.store {
-            let mut mmu = self.mmu.write().unwrap();
-            mmu.store(store).with_pc(prev_pc)?;
+            let mut mmu = self.mmu.lock().unwrap();
+            if let Err(e) = mmu.store(store) {
+                drop(mmu);
+                let trap = Trap::new(e, AccessType::Store, store.addr as u64);
+                return self.trap(trap, prev_pc);
+            }
+            self.last_access.store = Some(store);
+        }
+        for store in result.stores {
+            let mut mmu = self.mmu.lock().unwrap();
+            if let Err(e) = mmu.store(store) {
+                drop(mmu);
+                let trap = Trap::new(e, AccessType::Store, store.addr as u64);
+                return self.trap(trap, prev_pc);
+            }
+            self.last_access.stores.push(store);
         }
 
-        // Save PC & memory load requests for next instruction
-        self.prev_pc = cur_pc;
-        self.load = result.load;
+        Ok(())
+    }
 
+    /// Trap into the hart's trap handler due to an exception raised by a memory access.
+    ///
+    /// Any memory accesses requested for the next instruction are discarded.
+    fn trap(&mut self, trap: Trap, epc: u64) -> Result<(), (ProcessorException, u64)> {
+        self.load = None;
+        self.atomic = None;
+        self.loads.clear();
+        self.hart.trap(trap, epc)?;
+        self.prev_pc = self.hart.prev_pc;
         Ok(())
     }
 }
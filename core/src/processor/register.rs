@@ -5,8 +5,14 @@
 //! extensions to add new registers, which can then be accessed by other extensions, without
 //! affecting the base ISA.
 //!
-//! The [`GeneralPurposeRegister`] is, as the name implies, a general-purpose 32-bit register
-//! capable of stores and loads. The RISC-V base ISA requires 31 such general-purpose registers.
+//! The [`GeneralPurposeRegister`] is, as the name implies, a general-purpose register capable of
+//! stores and loads. The RISC-V base ISA requires 31 such general-purpose registers (or 15, for the
+//! embedded RV32E base ISA).
+//!
+//! Registers are 64 bits wide, which is enough to hold the state of both RV32 and RV64 harts. On an
+//! RV32 hart, instructions keep each 32-bit value sign-extended to 64 bits (as RV64 does for the
+//! results of its 32-bit "W" instructions), so that the two can share instruction implementations:
+//! See [`Xlen`](crate::processor::Xlen).
 //!
 //! The [`ZeroRegister`] allows stores, but discards them, always returning zero on load. The RISC-V
 //! ISA requires one such "register".
@@ -14,29 +20,38 @@
 use crate::error::ProcessorException;
 use std::collections::BTreeMap;
 
-/// A RV32I register file.
+/// A RISC-V integer register file.
 ///
 /// By default, `RegisterFile[1]` through `RegisterFile[31]` are populated with general-purpose
-/// registers, and `RegisterFile[0]` is a zero register.
+/// registers, and `RegisterFile[0]` is a zero register. Base instruction sets with fewer registers
+/// (i.e. RV32E) remove the rest, and instructions must raise an IllegalInstruction exception if
+/// they name a register which is not present.
 pub type RegisterFile = BTreeMap<u8, Box<dyn Register>>;
 
-/// A 32-bit register.
+/// Key of the floating-point register `f0` in a [`RegisterFile`].
+///
+/// Floating-point extensions add their registers `f0` to `f31` to the register file at keys
+/// `FLOAT_REGISTER_BASE` to `FLOAT_REGISTER_BASE + 31`, keeping them separate from the integer
+/// registers, which are always numbered below this.
+pub const FLOAT_REGISTER_BASE: u8 = 32;
+
+/// A 64-bit register.
 pub trait Register: std::fmt::Debug + Send + Sync + 'static {
     /// Get the current value stored in this register.
-    fn load(&self) -> Result<i32, ProcessorException>;
+    fn load(&self) -> Result<i64, ProcessorException>;
 
     /// Store a value in this register.
     ///
     /// Returns the value of the register prior to this store.
-    fn store(&mut self, val: i32) -> Result<i32, ProcessorException>;
+    fn store(&mut self, val: i64) -> Result<i64, ProcessorException>;
 }
 
 /// A general-purpose register, which behaves "as you would expect".
 ///
-/// Stores a single 32-bit value. When a value is stored, this change is reflected in later loads.
+/// Stores a single 64-bit value. When a value is stored, this change is reflected in later loads.
 #[derive(Clone, Debug, Eq, Hash, PartialEq)]
 pub struct GeneralPurposeRegister {
-    value: i32,
+    value: i64,
 }
 
 impl GeneralPurposeRegister {
@@ -46,12 +61,18 @@ impl GeneralPurposeRegister {
     }
 }
 
+impl Default for GeneralPurposeRegister {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl Register for GeneralPurposeRegister {
-    fn load(&self) -> Result<i32, ProcessorException> {
+    fn load(&self) -> Result<i64, ProcessorException> {
         Ok(self.value)
     }
 
-    fn store(&mut self, val: i32) -> Result<i32, ProcessorException> {
+    fn store(&mut self, val: i64) -> Result<i64, ProcessorException> {
         let prev = self.value;
         self.value = val;
         Ok(prev)
@@ -67,11 +88,11 @@ impl Register for GeneralPurposeRegister {
 pub struct ZeroRegister;
 
 impl Register for ZeroRegister {
-    fn load(&self) -> Result<i32, ProcessorException> {
+    fn load(&self) -> Result<i64, ProcessorException> {
         Ok(0)
     }
 
-    fn store(&mut self, _val: i32) -> Result<i32, ProcessorException> {
+    fn store(&mut self, _val: i64) -> Result<i64, ProcessorException> {
         Ok(0)
     }
 }
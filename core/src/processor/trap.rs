@@ -0,0 +1,179 @@
+//! Machine-mode trap handling.
+//!
+//! When a hart encounters an exception, rather than halting, it transfers control to the trap
+//! handler whose address is specified by the `mtvec` CSR, recording the cause of the trap and the
+//! address of the offending instruction in `mcause` and `mepc`. The trap handler can then return to
+//! the interrupted program with the MRET instruction.
+//!
+//! This module defines the [`Trap`] struct, which describes an exception which should be handled in
+//! this manner, plus the `mstatus`/`mtvec` fields used by the trap flow.
+
+use crate::error::{MemoryAccessError, ProcessorException};
+use crate::processor::{PrivilegeLevel, Xlen};
+
+/// `mstatus` machine interrupt-enable bit.
+pub const MSTATUS_MIE: u64 = 1 << 3;
+
+/// `mstatus` machine prior interrupt-enable bit.
+pub const MSTATUS_MPIE: u64 = 1 << 7;
+
+/// `mstatus` machine previous privilege mode field.
+pub const MSTATUS_MPP: u64 = 0b11 << 11;
+
+/// `mstatus` user-mode XLEN field.
+///
+/// This only exists for RV64, where it is read-only.
+pub const MSTATUS_UXL: u64 = 0b11 << 32;
+
+/// `mtvec` mode field.
+pub const MTVEC_MODE: u64 = 0b11;
+
+/// `mtvec` mode in which all traps set the pc to BASE.
+pub const MTVEC_MODE_DIRECT: u64 = 0b00;
+
+/// `mtvec` mode in which interrupts set the pc to BASE + 4 × cause.
+pub const MTVEC_MODE_VECTORED: u64 = 0b01;
+
+/// Get the `mcause` bit indicating the trap was caused by an interrupt.
+///
+/// This is the most significant bit of the register, so depends on the XLEN.
+pub fn mcause_interrupt(xlen: Xlen) -> u64 {
+    1 << (xlen.bits() - 1)
+}
+
+/// Type of access being performed when an exception was raised.
+///
+/// This is used to distinguish between e.g. instruction, load, and store access faults, which are
+/// reported to the trap handler using different exception codes.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum AccessType {
+    /// Fetching or decoding an instruction.
+    Fetch,
+
+    /// Loading a value from memory, or executing an instruction.
+    Load,
+
+    /// Storing a value to memory, or performing an atomic memory operation.
+    Store,
+}
+
+/// An exception to be handled by a trap handler.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub struct Trap {
+    /// The exception which occurred.
+    pub exception: ProcessorException,
+
+    /// Type of access being performed when the exception occurred.
+    pub access: AccessType,
+
+    /// Exception-specific information to write to `mtval`.
+    ///
+    /// For memory exceptions this is the faulting address, for illegal instruction exceptions this
+    /// is the instruction itself, and for breakpoints this is the address of the breakpoint.
+    /// Otherwise, this is zero.
+    pub tval: u64,
+}
+
+impl Trap {
+    /// Create a new Trap.
+    pub fn new(exception: ProcessorException, access: AccessType, tval: u64) -> Self {
+        Self {
+            exception,
+            access,
+            tval,
+        }
+    }
+
+    /// Determine the value to write to `mcause` for this trap.
+    ///
+    /// `privilege` should be the privilege level at which the hart was executing when the trap
+    /// occurred, which determines the exception code for environment calls.
+    pub fn cause(&self, privilege: PrivilegeLevel) -> u64 {
+        match (self.exception, self.access) {
+            (ProcessorException::InstructionAddressMisaligned, _) => 0,
+            (ProcessorException::IllegalInstruction, _) => 2,
+            (ProcessorException::EnvironmentBreak, _) => 3,
+            (ProcessorException::EnvironmentCall, _) => 8 + privilege.bits() as u64,
+            (ProcessorException::InvalidMemoryAccess(e), access) => {
+                let misaligned = e == MemoryAccessError::Misaligned;
+                match (access, misaligned) {
+                    (AccessType::Fetch, true) => 0,
+                    (AccessType::Fetch, false) => 1,
+                    (AccessType::Load, true) => 4,
+                    (AccessType::Load, false) => 5,
+                    (AccessType::Store, true) => 6,
+                    (AccessType::Store, false) => 7,
+                }
+            }
+        }
+    }
+}
+
+/// Determine the address of the trap handler for a trap with the provided `mcause` value.
+pub fn trap_vector(mtvec: u64, cause: u64, xlen: Xlen) -> u64 {
+    let base = mtvec & !MTVEC_MODE;
+    let interrupt = mcause_interrupt(xlen);
+    if mtvec & MTVEC_MODE == MTVEC_MODE_VECTORED && cause & interrupt != 0 {
+        base.wrapping_add(4 * (cause & !interrupt))
+    } else {
+        base
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{mcause_interrupt, trap_vector, AccessType, Trap};
+    use crate::error::{MemoryAccessError, ProcessorException};
+    use crate::processor::{PrivilegeLevel, Xlen};
+
+    #[test]
+    fn cause() {
+        let cause = |exception, access| Trap::new(exception, access, 0).cause(PrivilegeLevel::User);
+        let fault = ProcessorException::InvalidMemoryAccess(MemoryAccessError::OutOfBounds);
+        let misaligned = ProcessorException::InvalidMemoryAccess(MemoryAccessError::Misaligned);
+
+        assert_eq!(cause(fault, AccessType::Fetch), 1);
+        assert_eq!(cause(fault, AccessType::Load), 5);
+        assert_eq!(cause(misaligned, AccessType::Store), 6);
+        assert_eq!(
+            cause(ProcessorException::IllegalInstruction, AccessType::Fetch),
+            2
+        );
+        assert_eq!(
+            cause(ProcessorException::EnvironmentCall, AccessType::Load),
+            8
+        );
+        assert_eq!(
+            Trap::new(ProcessorException::EnvironmentCall, AccessType::Load, 0)
+                .cause(PrivilegeLevel::Machine),
+            11
+        );
+    }
+
+    #[test]
+    fn vector() {
+        let interrupt = mcause_interrupt(Xlen::Rv32);
+        assert_eq!(trap_vector(0x8000_0100, 2, Xlen::Rv32), 0x8000_0100);
+        assert_eq!(trap_vector(0x8000_0101, 2, Xlen::Rv32), 0x8000_0100);
+        assert_eq!(
+            trap_vector(0x8000_0100, interrupt | 7, Xlen::Rv32),
+            0x8000_0100
+        );
+        assert_eq!(
+            trap_vector(0x8000_0101, interrupt | 7, Xlen::Rv32),
+            0x8000_011c
+        );
+
+        // The interrupt bit is the most significant bit of mcause
+        let interrupt = mcause_interrupt(Xlen::Rv64);
+        assert_eq!(interrupt, 1 << 63);
+        assert_eq!(
+            trap_vector(0x8000_0101, interrupt | 7, Xlen::Rv64),
+            0x8000_011c
+        );
+        assert_eq!(
+            trap_vector(0x8000_0101, (1 << 31) | 7, Xlen::Rv64),
+            0x8000_0100
+        );
+    }
+}
@@ -3,8 +3,7 @@
 //! Volatile, mutable storage region.
 
 use crate::error::{MemoryAccessError, ProcessorException};
-use crate::mmu::Addressable;
-use std::ops::Range;
+use crate::mmu::{AccessWidth, Addressable, MemoryValue};
 
 /// RAM device.
 #[derive(Debug)]
@@ -31,22 +30,33 @@ impl Addressable for RAM {
         self.contents.len().next_power_of_two()
     }
 
-    fn load_raw(&self, range: Range<usize>) -> Result<&[u8], ProcessorException> {
+    fn load(&mut self, addr: usize, width: AccessWidth) -> Result<MemoryValue, ProcessorException> {
+        let range = addr..addr + width.bytes();
         if range.end > self.contents.len() {
             return Err(MemoryAccessError::OutOfBounds.into());
         }
 
-        Ok(&self.contents[range])
+        Ok(MemoryValue::from_le_bytes(width, &self.contents[range]))
     }
 
-    fn store_raw(&mut self, range: Range<usize>, values: &[u8]) -> Result<(), ProcessorException> {
+    fn store(&mut self, addr: usize, value: MemoryValue) -> Result<(), ProcessorException> {
+        let range = addr..addr + value.width().bytes();
         if range.end > self.contents.len() {
             return Err(MemoryAccessError::OutOfBounds.into());
-        } else if values.len() != range.len() {
-            return Err(MemoryAccessError::LengthMismatch.into());
         }
 
-        self.contents[range].copy_from_slice(values);
+        value.write_le_bytes(&mut self.contents[range]);
+
+        Ok(())
+    }
+
+    fn preload(&mut self, addr: usize, bytes: &[u8]) -> Result<(), ProcessorException> {
+        let range = addr..addr + bytes.len();
+        if range.end > self.contents.len() {
+            return Err(MemoryAccessError::OutOfBounds.into());
+        }
+
+        self.contents[range].copy_from_slice(bytes);
 
         Ok(())
     }
@@ -6,9 +6,8 @@
 //! The processor will start executing code at address `0x00000000` of the ROM.
 
 use crate::error::{MemoryAccessError, ProcessorException};
-use crate::mmu::Addressable;
+use crate::mmu::{AccessWidth, Addressable, MemoryValue};
 use std::io::{self, Read};
-use std::ops::Range;
 
 /// ROM device.
 #[derive(Debug)]
@@ -43,19 +42,27 @@ impl Addressable for ROM {
         self.contents.len().next_power_of_two()
     }
 
-    fn load_raw(&self, range: Range<usize>) -> Result<&[u8], ProcessorException> {
+    fn load(&mut self, addr: usize, width: AccessWidth) -> Result<MemoryValue, ProcessorException> {
+        let range = addr..addr + width.bytes();
         if range.end > self.contents.len() {
             return Err(MemoryAccessError::OutOfBounds.into());
         }
 
-        Ok(&self.contents[range])
+        Ok(MemoryValue::from_le_bytes(width, &self.contents[range]))
     }
 
-    fn store_raw(
-        &mut self,
-        _range: Range<usize>,
-        _values: &[u8],
-    ) -> Result<(), ProcessorException> {
+    fn store(&mut self, _addr: usize, _value: MemoryValue) -> Result<(), ProcessorException> {
         Err(MemoryAccessError::ReadOnly.into())
     }
+
+    fn preload(&mut self, addr: usize, bytes: &[u8]) -> Result<(), ProcessorException> {
+        let range = addr..addr + bytes.len();
+        if range.end > self.contents.len() {
+            return Err(MemoryAccessError::OutOfBounds.into());
+        }
+
+        self.contents[range].copy_from_slice(bytes);
+
+        Ok(())
+    }
 }
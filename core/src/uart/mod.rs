@@ -0,0 +1,572 @@
+//! NS16550A-compatible Universal Asynchronous Receiver/Transmitter (UART).
+//!
+//! The 16550 is the de-facto standard serial port: It is the device Linux, U-Boot, and OpenSBI
+//! expect to find on most simple RISC-V platforms, and is very simple to drive from bare-metal
+//! code. This module models the programmer-visible behaviour of the NS16550A, with byte-wide
+//! registers at consecutive addresses:
+//!
+//! | Offset | DLAB | Read                            | Write                         |
+//! |--------|------|---------------------------------|-------------------------------|
+//! | 0      | 0    | Receiver Buffer (RBR)           | Transmitter Holding (THR)     |
+//! | 0      | 1    | Divisor Latch, low byte (DLL)   | Divisor Latch, low byte (DLL) |
+//! | 1      | 0    | Interrupt Enable (IER)          | Interrupt Enable (IER)        |
+//! | 1      | 1    | Divisor Latch, high byte (DLM)  | Divisor Latch, high byte (DLM)|
+//! | 2      | -    | Interrupt Identification (IIR)  | FIFO Control (FCR)            |
+//! | 3      | -    | Line Control (LCR)              | Line Control (LCR)            |
+//! | 4      | -    | Modem Control (MCR)             | Modem Control (MCR)           |
+//! | 5      | -    | Line Status (LSR)               | -                             |
+//! | 6      | -    | Modem Status (MSR)              | -                             |
+//! | 7      | -    | Scratch (SCR)                   | Scratch (SCR)                 |
+//!
+//! The serial line itself is not emulated: Characters are exchanged with the host via a
+//! [`SerialBackend`] instantly, so the baud rate, word length, parity & stop bits configured by the
+//! guest have no effect, and the transmitter is always ready for more data. Received characters are
+//! only pulled from the backend when the guest accesses the UART, so the receive FIFO never
+//! overruns unless loopback mode is in use.
+//!
+//! There is not yet an interrupt controller to deliver UART interrupts to the processor, but the
+//! interrupt identification register is fully modelled, so guests which poll IIR still work, and
+//! [`UART::interrupt_pending`] reports the state of the interrupt line.
+
+mod stdio;
+
+pub use stdio::StdioBackend;
+
+use crate::error::{MemoryAccessError, ProcessorException};
+use crate::mmu::{AccessWidth, AccessWidths, Addressable, MemoryValue};
+use std::collections::VecDeque;
+use std::fmt;
+
+/// Receiver buffer/transmitter holding register, or divisor latch low byte if DLAB is set.
+const RBR_THR_DLL: usize = 0;
+
+/// Interrupt enable register, or divisor latch high byte if DLAB is set.
+const IER_DLM: usize = 1;
+
+/// Interrupt identification register (read), or FIFO control register (write).
+const IIR_FCR: usize = 2;
+
+/// Line control register.
+const LCR: usize = 3;
+
+/// Modem control register.
+const MCR: usize = 4;
+
+/// Line status register.
+const LSR: usize = 5;
+
+/// Modem status register.
+const MSR: usize = 6;
+
+/// Scratch register.
+const SCR: usize = 7;
+
+/// IER: Enable received data available interrupt.
+const IER_ERBFI: u8 = 1 << 0;
+
+/// IER: Enable transmitter holding register empty interrupt.
+const IER_ETBEI: u8 = 1 << 1;
+
+/// IER: Enable receiver line status interrupt.
+const IER_ELSI: u8 = 1 << 2;
+
+/// IER: Enable modem status interrupt.
+const IER_EDSSI: u8 = 1 << 3;
+
+/// IIR: No interrupt pending.
+const IIR_NONE: u8 = 0b0001;
+
+/// IIR: Modem status interrupt.
+const IIR_MODEM_STATUS: u8 = 0b0000;
+
+/// IIR: Transmitter holding register empty interrupt.
+const IIR_THR_EMPTY: u8 = 0b0010;
+
+/// IIR: Received data available interrupt.
+const IIR_RX_DATA: u8 = 0b0100;
+
+/// IIR: Receiver line status interrupt.
+const IIR_RX_LINE_STATUS: u8 = 0b0110;
+
+/// IIR: FIFOs enabled.
+const IIR_FIFO_ENABLED: u8 = 0b1100_0000;
+
+/// FCR: Enable FIFOs.
+const FCR_ENABLE: u8 = 1 << 0;
+
+/// FCR: Clear the receive FIFO.
+const FCR_CLEAR_RX: u8 = 1 << 1;
+
+/// FCR: Clear the transmit FIFO.
+const FCR_CLEAR_TX: u8 = 1 << 2;
+
+/// LCR: Divisor latch access bit.
+const LCR_DLAB: u8 = 1 << 7;
+
+/// MCR: Writable bits (DTR, RTS, OUT1, OUT2, LOOP).
+const MCR_MASK: u8 = 0b0001_1111;
+
+/// MCR: Loopback mode.
+const MCR_LOOP: u8 = 1 << 4;
+
+/// LSR: Data ready.
+const LSR_DR: u8 = 1 << 0;
+
+/// LSR: Overrun error.
+const LSR_OE: u8 = 1 << 1;
+
+/// LSR: Transmitter holding register empty.
+const LSR_THRE: u8 = 1 << 5;
+
+/// LSR: Transmitter empty.
+const LSR_TEMT: u8 = 1 << 6;
+
+/// MSR: Delta bits, set when the corresponding status bit changes.
+const MSR_DELTA: u8 = 0b0000_1111;
+
+/// MSR: Clear to send, data set ready & data carrier detect, as reported when connected to a host
+/// which is always ready.
+const MSR_HOST_READY: u8 = 0b1011_0000;
+
+/// Capacity of the receive & transmit FIFOs, when enabled.
+pub const FIFO_SIZE: usize = 16;
+
+/// Size of the region reserved for the UART.
+///
+/// Only the first 8 bytes are used: The rest of the region is reserved so that the UART occupies a
+/// conveniently aligned page of the address space.
+pub const UART_SIZE: usize = 0x100;
+
+/// A connection between a UART and the host.
+///
+/// The UART exchanges characters with the outside world through a backend: This could be the host
+/// terminal (see [`StdioBackend`]), a file, a socket, a test harness, etc.
+pub trait SerialBackend: fmt::Debug + Send + Sync + 'static {
+    /// Retrieve the next character received from the host, if one is available.
+    ///
+    /// This must not block.
+    fn receive(&mut self) -> Option<u8>;
+
+    /// Transmit a character to the host.
+    fn transmit(&mut self, byte: u8);
+}
+
+/// NS16550A-compatible UART device.
+#[derive(Debug)]
+pub struct UART {
+    /// Host connection.
+    backend: Box<dyn SerialBackend>,
+
+    /// Characters received, but not yet read by the guest.
+    rx_fifo: VecDeque<u8>,
+
+    /// Characters written by the guest, but not yet transmitted.
+    tx_fifo: VecDeque<u8>,
+
+    /// Interrupt enable register.
+    ier: u8,
+
+    /// FIFO control register.
+    fcr: u8,
+
+    /// Line control register.
+    lcr: u8,
+
+    /// Modem control register.
+    mcr: u8,
+
+    /// Line status bits which are latched until LSR is read (i.e: error bits).
+    lsr_errors: u8,
+
+    /// Modem status register.
+    msr: u8,
+
+    /// Scratch register.
+    scr: u8,
+
+    /// Divisor latch.
+    divisor: u16,
+
+    /// Whether a transmitter holding register empty interrupt is pending.
+    ///
+    /// This is raised whenever the transmitter becomes empty, and cleared by reading IIR while it
+    /// is the highest priority interrupt, or writing THR.
+    thr_empty_pending: bool,
+}
+
+impl UART {
+    /// Create a new UART, exchanging characters with the host via the provided backend.
+    pub fn new(backend: Box<dyn SerialBackend>) -> Self {
+        Self {
+            backend,
+            rx_fifo: VecDeque::with_capacity(FIFO_SIZE),
+            tx_fifo: VecDeque::with_capacity(FIFO_SIZE),
+            ier: 0,
+            fcr: 0,
+            lcr: 0,
+            mcr: 0,
+            lsr_errors: 0,
+            msr: MSR_HOST_READY,
+            scr: 0,
+            divisor: 0,
+            thr_empty_pending: false,
+        }
+    }
+
+    /// Returns true if the UART is asserting its interrupt line.
+    pub fn interrupt_pending(&self) -> bool {
+        self.interrupt_id() != IIR_NONE
+    }
+
+    /// Whether the FIFOs are enabled.
+    fn fifo_enabled(&self) -> bool {
+        self.fcr & FCR_ENABLE != 0
+    }
+
+    /// Number of characters which can be held by the receive & transmit buffers.
+    fn capacity(&self) -> usize {
+        if self.fifo_enabled() {
+            FIFO_SIZE
+        } else {
+            1
+        }
+    }
+
+    /// Whether the divisor latch is selected.
+    fn dlab(&self) -> bool {
+        self.lcr & LCR_DLAB != 0
+    }
+
+    /// Whether loopback mode is enabled.
+    fn loopback(&self) -> bool {
+        self.mcr & MCR_LOOP != 0
+    }
+
+    /// Move characters from the backend into the receive FIFO, while there is room for them.
+    ///
+    /// In loopback mode, the receiver is disconnected from the backend.
+    fn poll_backend(&mut self) {
+        if self.loopback() {
+            return;
+        }
+
+        while self.rx_fifo.len() < self.capacity() {
+            match self.backend.receive() {
+                Some(byte) => self.rx_fifo.push_back(byte),
+                None => break,
+            }
+        }
+    }
+
+    /// Add a character to the receive FIFO, raising an overrun error if it is full.
+    fn receive(&mut self, byte: u8) {
+        if self.rx_fifo.len() < self.capacity() {
+            self.rx_fifo.push_back(byte);
+        } else {
+            self.lsr_errors |= LSR_OE;
+        }
+    }
+
+    /// Transmit all characters in the transmit FIFO.
+    ///
+    /// In loopback mode, transmitted characters are received by the UART itself rather than being
+    /// sent to the backend.
+    fn flush(&mut self) {
+        while let Some(byte) = self.tx_fifo.pop_front() {
+            if self.loopback() {
+                self.receive(byte);
+            } else {
+                self.backend.transmit(byte);
+            }
+        }
+        self.thr_empty_pending = true;
+    }
+
+    /// Current value of the line status register.
+    fn lsr(&self) -> u8 {
+        let mut lsr = self.lsr_errors;
+        if !self.rx_fifo.is_empty() {
+            lsr |= LSR_DR;
+        }
+        if self.tx_fifo.is_empty() {
+            lsr |= LSR_THRE | LSR_TEMT;
+        }
+        lsr
+    }
+
+    /// Update the modem status register.
+    ///
+    /// In loopback mode, the modem control outputs are connected to the modem status inputs: DTR to
+    /// DSR, RTS to CTS, OUT1 to RI, and OUT2 to DCD. Otherwise, the host is always ready.
+    fn update_msr(&mut self) {
+        let status = if self.loopback() {
+            let mcr = self.mcr;
+            ((mcr & 0b0010) << 3)
+                | ((mcr & 0b0001) << 5)
+                | ((mcr & 0b0100) << 4)
+                | ((mcr & 0b1000) << 4)
+        } else {
+            MSR_HOST_READY
+        };
+
+        let changed = (self.msr ^ status) >> 4;
+        // TERI is only set on the trailing edge of RI
+        let teri = changed & 0b0100 & (self.msr >> 4);
+        let delta = (changed & 0b1011) | teri;
+        self.msr = status | (self.msr & MSR_DELTA) | delta;
+    }
+
+    /// Identify the highest-priority pending interrupt, as reported in bits [3:0] of IIR.
+    fn interrupt_id(&self) -> u8 {
+        if self.ier & IER_ELSI != 0 && self.lsr_errors != 0 {
+            IIR_RX_LINE_STATUS
+        } else if self.ier & IER_ERBFI != 0 && !self.rx_fifo.is_empty() {
+            IIR_RX_DATA
+        } else if self.ier & IER_ETBEI != 0 && self.thr_empty_pending {
+            IIR_THR_EMPTY
+        } else if self.ier & IER_EDSSI != 0 && self.msr & MSR_DELTA != 0 {
+            IIR_MODEM_STATUS
+        } else {
+            IIR_NONE
+        }
+    }
+
+    /// Read the register at the provided offset.
+    fn read(&mut self, offset: usize) -> Result<u8, ProcessorException> {
+        self.poll_backend();
+
+        Ok(match offset {
+            RBR_THR_DLL if self.dlab() => self.divisor as u8,
+            RBR_THR_DLL => self.rx_fifo.pop_front().unwrap_or(0),
+            IER_DLM if self.dlab() => (self.divisor >> 8) as u8,
+            IER_DLM => self.ier,
+            IIR_FCR => {
+                let id = self.interrupt_id();
+                if id == IIR_THR_EMPTY {
+                    self.thr_empty_pending = false;
+                }
+                let fifo = if self.fifo_enabled() {
+                    IIR_FIFO_ENABLED
+                } else {
+                    0
+                };
+                fifo | id
+            }
+            LCR => self.lcr,
+            MCR => self.mcr,
+            LSR => {
+                let lsr = self.lsr();
+                self.lsr_errors = 0;
+                lsr
+            }
+            MSR => {
+                let msr = self.msr;
+                self.msr &= !MSR_DELTA;
+                msr
+            }
+            SCR => self.scr,
+            _ => return Err(MemoryAccessError::OutOfBounds.into()),
+        })
+    }
+
+    /// Write the register at the provided offset.
+    fn write(&mut self, offset: usize, value: u8) -> Result<(), ProcessorException> {
+        match offset {
+            RBR_THR_DLL if self.dlab() => self.divisor = (self.divisor & 0xff00) | value as u16,
+            RBR_THR_DLL => {
+                self.thr_empty_pending = false;
+                if self.tx_fifo.len() < self.capacity() {
+                    self.tx_fifo.push_back(value);
+                }
+                self.flush();
+            }
+            IER_DLM if self.dlab() => {
+                self.divisor = (self.divisor & 0x00ff) | ((value as u16) << 8)
+            }
+            IER_DLM => {
+                // Enabling the THRE interrupt while the transmitter is empty raises it immediately
+                if value & IER_ETBEI != 0 && self.ier & IER_ETBEI == 0 && self.tx_fifo.is_empty() {
+                    self.thr_empty_pending = true;
+                }
+                self.ier = value & 0x0f;
+            }
+            IIR_FCR => {
+                // Changing the FIFO enable bit clears both FIFOs
+                if (value ^ self.fcr) & FCR_ENABLE != 0 || value & FCR_CLEAR_RX != 0 {
+                    self.rx_fifo.clear();
+                }
+                if (value ^ self.fcr) & FCR_ENABLE != 0 || value & FCR_CLEAR_TX != 0 {
+                    self.tx_fifo.clear();
+                }
+                // The clear bits are self-clearing
+                self.fcr = value & !(FCR_CLEAR_RX | FCR_CLEAR_TX);
+            }
+            LCR => self.lcr = value,
+            MCR => {
+                self.mcr = value & MCR_MASK;
+                self.update_msr();
+            }
+            LSR | MSR => {}
+            SCR => self.scr = value,
+            _ => return Err(MemoryAccessError::OutOfBounds.into()),
+        }
+
+        Ok(())
+    }
+}
+
+impl Addressable for UART {
+    fn reserve(&self) -> usize {
+        UART_SIZE
+    }
+
+    fn access_widths(&self) -> AccessWidths {
+        AccessWidths::BYTE
+    }
+
+    fn load(&mut self, addr: usize, width: AccessWidth) -> Result<MemoryValue, ProcessorException> {
+        Ok(MemoryValue::new(width, self.read(addr)? as u64))
+    }
+
+    fn store(&mut self, addr: usize, value: MemoryValue) -> Result<(), ProcessorException> {
+        self.write(addr, value.as_u64() as u8)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{SerialBackend, UART};
+    use crate::mmu::{AccessWidth, Addressable, MemoryValue};
+    use std::collections::VecDeque;
+    use std::sync::{Arc, Mutex};
+
+    /// A backend which receives from a queue, and transmits to a shared buffer.
+    #[derive(Debug, Default)]
+    struct TestBackend {
+        rx: VecDeque<u8>,
+        tx: Arc<Mutex<Vec<u8>>>,
+    }
+
+    impl SerialBackend for TestBackend {
+        fn receive(&mut self) -> Option<u8> {
+            self.rx.pop_front()
+        }
+
+        fn transmit(&mut self, byte: u8) {
+            self.tx.lock().unwrap().push(byte);
+        }
+    }
+
+    /// Create a UART with the provided pending input, returning it with its transmit buffer.
+    fn uart(input: &[u8]) -> (UART, Arc<Mutex<Vec<u8>>>) {
+        let backend = TestBackend {
+            rx: input.iter().copied().collect(),
+            tx: Arc::default(),
+        };
+        let tx = backend.tx.clone();
+        (UART::new(Box::new(backend)), tx)
+    }
+
+    fn read(uart: &mut UART, offset: usize) -> u8 {
+        uart.load(offset, AccessWidth::Byte).unwrap().as_u64() as u8
+    }
+
+    fn write(uart: &mut UART, offset: usize, value: u8) {
+        uart.store(offset, MemoryValue::Byte(value)).unwrap();
+    }
+
+    #[test]
+    fn transmit() {
+        let (mut uart, tx) = uart(&[]);
+        assert_eq!(read(&mut uart, 5) & 0x60, 0x60);
+
+        for byte in b"hi\n" {
+            write(&mut uart, 0, *byte);
+        }
+        assert_eq!(*tx.lock().unwrap(), b"hi\n");
+        assert_eq!(read(&mut uart, 5) & 0x60, 0x60);
+    }
+
+    #[test]
+    fn receive() {
+        let (mut uart, _) = uart(b"abc");
+
+        // Without FIFOs, only a single character is buffered at a time
+        assert_eq!(read(&mut uart, 5) & 0x01, 0x01);
+        assert_eq!(read(&mut uart, 0), b'a');
+        assert_eq!(read(&mut uart, 0), b'b');
+
+        // Enable & clear FIFOs
+        write(&mut uart, 2, 0x07);
+        assert_eq!(read(&mut uart, 2) & 0xc0, 0xc0);
+        assert_eq!(read(&mut uart, 0), b'c');
+        assert_eq!(read(&mut uart, 5) & 0x01, 0x00);
+        assert_eq!(read(&mut uart, 0), 0);
+    }
+
+    #[test]
+    fn divisor_latch() {
+        let (mut uart, tx) = uart(&[]);
+        write(&mut uart, 1, 0x05);
+
+        // With DLAB set, offsets 0 & 1 access the divisor latch
+        write(&mut uart, 3, 0x83);
+        write(&mut uart, 0, 0x01);
+        write(&mut uart, 1, 0x02);
+        assert_eq!(read(&mut uart, 0), 0x01);
+        assert_eq!(read(&mut uart, 1), 0x02);
+        assert!(tx.lock().unwrap().is_empty());
+
+        write(&mut uart, 3, 0x03);
+        assert_eq!(read(&mut uart, 1), 0x05);
+        assert_eq!(read(&mut uart, 3), 0x03);
+    }
+
+    #[test]
+    fn interrupts() {
+        let (mut uart, _) = uart(b"x");
+        assert_eq!(read(&mut uart, 2), 0x01);
+
+        // THRE interrupt is raised when enabled, and cleared by reading IIR
+        write(&mut uart, 1, 0x02);
+        assert_eq!(read(&mut uart, 2), 0x02);
+        assert_eq!(read(&mut uart, 2), 0x01);
+
+        // Received data takes priority over THRE
+        write(&mut uart, 1, 0x03);
+        write(&mut uart, 0, b'y');
+        assert_eq!(read(&mut uart, 2), 0x04);
+        assert!(uart.interrupt_pending());
+        assert_eq!(read(&mut uart, 0), b'x');
+        assert_eq!(read(&mut uart, 2), 0x02);
+        assert!(!uart.interrupt_pending());
+    }
+
+    #[test]
+    fn loopback() {
+        let (mut uart, tx) = uart(b"z");
+        write(&mut uart, 4, 0x10 | 0x03);
+
+        // Modem control outputs are looped back to modem status inputs
+        assert_eq!(read(&mut uart, 6) & 0xf0, 0x30);
+
+        write(&mut uart, 0, b'a');
+        assert_eq!(read(&mut uart, 0), b'a');
+        assert!(tx.lock().unwrap().is_empty());
+
+        // Overrun without FIFOs
+        write(&mut uart, 0, b'b');
+        write(&mut uart, 0, b'c');
+        assert_eq!(read(&mut uart, 5) & 0x03, 0x03);
+        assert_eq!(read(&mut uart, 5) & 0x02, 0x00);
+        assert_eq!(read(&mut uart, 0), b'b');
+    }
+
+    #[test]
+    fn scratch() {
+        let (mut uart, _) = uart(&[]);
+        write(&mut uart, 7, 0xa5);
+        assert_eq!(read(&mut uart, 7), 0xa5);
+        assert!(uart.load(8, AccessWidth::Byte).is_err());
+    }
+}
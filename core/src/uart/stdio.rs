@@ -0,0 +1,64 @@
+//! Serial backend connected to the host's standard input & output.
+
+use crate::uart::SerialBackend;
+use log::warn;
+use std::io::{self, Read, Write};
+use std::sync::mpsc::{self, Receiver};
+use std::sync::Mutex;
+use std::thread;
+
+/// A [`SerialBackend`] which reads from the host's stdin, and writes to the host's stdout.
+///
+/// Reading from stdin blocks, so a background thread is spawned to read from stdin & forward each
+/// byte to the UART. Note that unless the host terminal is in raw mode, input is line-buffered by
+/// the terminal, so the guest will only receive characters once the user presses Enter.
+#[derive(Debug)]
+pub struct StdioBackend {
+    /// Bytes read from stdin by the background thread.
+    ///
+    /// Wrapped in a mutex only so that the backend is `Sync`: It is only ever accessed by the UART.
+    rx: Mutex<Receiver<u8>>,
+}
+
+impl StdioBackend {
+    /// Create a new StdioBackend, spawning a thread to read from stdin.
+    pub fn new() -> Self {
+        let (tx, rx) = mpsc::channel();
+        thread::spawn(move || {
+            for byte in io::stdin().lock().bytes() {
+                match byte {
+                    Ok(byte) => {
+                        if tx.send(byte).is_err() {
+                            break;
+                        }
+                    }
+                    Err(e) => {
+                        warn!("Failed to read from stdin: {}", e);
+                        break;
+                    }
+                }
+            }
+        });
+
+        Self { rx: Mutex::new(rx) }
+    }
+}
+
+impl Default for StdioBackend {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl SerialBackend for StdioBackend {
+    fn receive(&mut self) -> Option<u8> {
+        self.rx.get_mut().unwrap().try_recv().ok()
+    }
+
+    fn transmit(&mut self, byte: u8) {
+        let mut stdout = io::stdout().lock();
+        if let Err(e) = stdout.write_all(&[byte]).and_then(|_| stdout.flush()) {
+            warn!("Failed to write to stdout: {}", e);
+        }
+    }
+}
@@ -0,0 +1,103 @@
+//! Atomic memory operation instructions (AMOSWAP.W, AMOADD.W, AMOXOR.W, AMOAND.W, AMOOR.W,
+//! AMOMIN.W, AMOMAX.W, AMOMINU.W, AMOMAXU.W), their RV64 double-word variants (AMO*.D), and the
+//! byte & half-word variants from the Zabha extension (AMO*.B, AMO*.H).
+//!
+//! These instructions atomically load the value at the address in rs1 into rd, apply a binary
+//! operation to the loaded value and the value in rs2, then store the result back to the address in
+//! rs1.
+
+use crate::a::Ordering;
+use z2l_core::error::ProcessorException;
+use z2l_core::instruction::{Instruction, InstructionResult, InstructionWordParts};
+use z2l_core::mmu::{AtomicOperation, AtomicSpec, MemoryAccessType};
+use z2l_core::processor::register::RegisterFile;
+use z2l_core::processor::Xlen;
+
+/// An AMO*.W or AMO*.D instruction, or a byte or half-word AMO*.B or AMO*.H instruction from the
+/// Zabha extension.
+pub struct AmoInstruction {
+    addr: u8,
+    src: u8,
+    dest: u8,
+    op: AtomicOperation,
+    width: MemoryAccessType,
+    ordering: Ordering,
+    xlen: Xlen,
+}
+
+impl AmoInstruction {
+    /// Create a new AmoInstruction, which will perform the provided operation on a value of the
+    /// provided width.
+    pub fn new(
+        instruction: &InstructionWordParts,
+        op: AtomicOperation,
+        width: MemoryAccessType,
+        xlen: Xlen,
+    ) -> Self {
+        Self {
+            addr: instruction.rs1,
+            src: instruction.rs2,
+            dest: instruction.rd,
+            op,
+            width,
+            ordering: Ordering::new(instruction),
+            xlen,
+        }
+    }
+}
+
+impl Instruction for AmoInstruction {
+    fn atomic(&self, registers: &RegisterFile) -> Result<Option<AtomicSpec>, ProcessorException> {
+        // Check the destination exists before the memory access, which may have side effects
+        if !registers.contains_key(&self.dest) {
+            return Err(ProcessorException::IllegalInstruction);
+        }
+        let addr = registers
+            .get(&self.addr)
+            .ok_or(ProcessorException::IllegalInstruction)?
+            .load()?;
+        let addr = self.xlen.zero_extend(addr) as usize;
+        let src = registers
+            .get(&self.src)
+            .ok_or(ProcessorException::IllegalInstruction)?
+            .load()?;
+
+        Ok(Some(AtomicSpec::new(self.op, self.width, addr, src)))
+    }
+
+    fn execute(
+        &self,
+        registers: &mut RegisterFile,
+        mem: i64,
+    ) -> Result<InstructionResult, ProcessorException> {
+        let dest = registers
+            .get_mut(&self.dest)
+            .ok_or(ProcessorException::IllegalInstruction)?;
+        dest.store(mem)?;
+
+        Ok(InstructionResult::default())
+    }
+
+    fn format(&self) -> String {
+        let op = match self.op {
+            AtomicOperation::Swap => "swap",
+            AtomicOperation::Add => "add",
+            AtomicOperation::Xor => "xor",
+            AtomicOperation::And => "and",
+            AtomicOperation::Or => "or",
+            AtomicOperation::Min => "min",
+            AtomicOperation::Max => "max",
+            AtomicOperation::MinU => "minu",
+            AtomicOperation::MaxU => "maxu",
+            AtomicOperation::LoadReserved | AtomicOperation::StoreConditional => {
+                unreachable!("Decoded as LR/SC instructions")
+            }
+            AtomicOperation::CompareAndSwap(_) => unreachable!("Decoded as AMOCAS instructions"),
+        };
+
+        format!(
+            "amo{}.{}{} x{}, x{}, (x{})",
+            op, self.width, self.ordering, self.dest, self.src, self.addr
+        )
+    }
+}
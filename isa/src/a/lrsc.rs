@@ -0,0 +1,152 @@
+//! Load-reserved/store-conditional instructions (LR.W, SC.W, and on RV64, LR.D, SC.D).
+//!
+//! LR loads a value from the address in rs1 into rd, and registers a reservation on that value. SC
+//! writes the value in rs2 to the address in rs1, but only if a valid reservation exists on that
+//! value: It writes zero to rd on success, or one on failure.
+
+use crate::a::Ordering;
+use z2l_core::error::ProcessorException;
+use z2l_core::instruction::{Instruction, InstructionResult, InstructionWordParts};
+use z2l_core::mmu::{AtomicOperation, AtomicSpec, MemoryAccessType};
+use z2l_core::processor::register::RegisterFile;
+use z2l_core::processor::Xlen;
+
+/// LR.W or LR.D instruction.
+pub struct LoadReservedInstruction {
+    addr: u8,
+    dest: u8,
+    width: MemoryAccessType,
+    ordering: Ordering,
+    xlen: Xlen,
+}
+
+impl LoadReservedInstruction {
+    /// Create a new LoadReservedInstruction, loading a value of the provided width.
+    pub fn new(
+        instruction: &InstructionWordParts,
+        width: MemoryAccessType,
+        xlen: Xlen,
+    ) -> Result<Self, ProcessorException> {
+        if instruction.rs2 != 0 {
+            return Err(ProcessorException::IllegalInstruction);
+        }
+
+        Ok(Self {
+            addr: instruction.rs1,
+            dest: instruction.rd,
+            width,
+            ordering: Ordering::new(instruction),
+            xlen,
+        })
+    }
+}
+
+impl Instruction for LoadReservedInstruction {
+    fn atomic(&self, registers: &RegisterFile) -> Result<Option<AtomicSpec>, ProcessorException> {
+        // Check the destination exists before the memory access, which may have side effects
+        if !registers.contains_key(&self.dest) {
+            return Err(ProcessorException::IllegalInstruction);
+        }
+        let addr = registers
+            .get(&self.addr)
+            .ok_or(ProcessorException::IllegalInstruction)?
+            .load()?;
+        let addr = self.xlen.zero_extend(addr) as usize;
+
+        Ok(Some(AtomicSpec::new(
+            AtomicOperation::LoadReserved,
+            self.width,
+            addr,
+            0,
+        )))
+    }
+
+    fn execute(
+        &self,
+        registers: &mut RegisterFile,
+        mem: i64,
+    ) -> Result<InstructionResult, ProcessorException> {
+        let dest = registers
+            .get_mut(&self.dest)
+            .ok_or(ProcessorException::IllegalInstruction)?;
+        dest.store(mem)?;
+
+        Ok(InstructionResult::default())
+    }
+
+    fn format(&self) -> String {
+        format!(
+            "lr.{}{} x{}, (x{})",
+            self.width, self.ordering, self.dest, self.addr
+        )
+    }
+}
+
+/// SC.W or SC.D instruction.
+pub struct StoreConditionalInstruction {
+    addr: u8,
+    src: u8,
+    dest: u8,
+    width: MemoryAccessType,
+    ordering: Ordering,
+    xlen: Xlen,
+}
+
+impl StoreConditionalInstruction {
+    /// Create a new StoreConditionalInstruction, storing a value of the provided width.
+    pub fn new(instruction: &InstructionWordParts, width: MemoryAccessType, xlen: Xlen) -> Self {
+        Self {
+            addr: instruction.rs1,
+            src: instruction.rs2,
+            dest: instruction.rd,
+            width,
+            ordering: Ordering::new(instruction),
+            xlen,
+        }
+    }
+}
+
+impl Instruction for StoreConditionalInstruction {
+    fn atomic(&self, registers: &RegisterFile) -> Result<Option<AtomicSpec>, ProcessorException> {
+        // Check the destination exists before the memory access, which may have side effects
+        if !registers.contains_key(&self.dest) {
+            return Err(ProcessorException::IllegalInstruction);
+        }
+        let addr = registers
+            .get(&self.addr)
+            .ok_or(ProcessorException::IllegalInstruction)?
+            .load()?;
+        let addr = self.xlen.zero_extend(addr) as usize;
+        let src = registers
+            .get(&self.src)
+            .ok_or(ProcessorException::IllegalInstruction)?
+            .load()?;
+
+        Ok(Some(AtomicSpec::new(
+            AtomicOperation::StoreConditional,
+            self.width,
+            addr,
+            src,
+        )))
+    }
+
+    fn execute(
+        &self,
+        registers: &mut RegisterFile,
+        mem: i64,
+    ) -> Result<InstructionResult, ProcessorException> {
+        let dest = registers
+            .get_mut(&self.dest)
+            .ok_or(ProcessorException::IllegalInstruction)?;
+        dest.store(mem)?;
+
+        Ok(InstructionResult::default())
+    }
+
+    fn format(&self) -> String {
+        format!(
+            "sc.{}{} x{}, x{}, (x{})",
+            self.width, self.ordering, self.dest, self.src, self.addr
+        )
+    }
+}
@@ -0,0 +1,312 @@
+//! The "A" standard extension for atomic instructions.
+//!
+//! This extension defines the load-reserved/store-conditional instructions (LR.W, SC.W), and the
+//! atomic memory operation instructions (AMO*.W), all of which use the AMO opcode. On RV64, each
+//! also has a double-word variant (LR.D, SC.D, AMO*.D).
+//!
+//! Our processor never reorders memory accesses, so the acquire/release ordering bits of these
+//! instructions have no effect beyond how the instructions are formatted.
+
+mod amo;
+mod lrsc;
+
+pub use amo::AmoInstruction;
+pub use lrsc::{LoadReservedInstruction, StoreConditionalInstruction};
+
+use std::fmt;
+use z2l_core::error::ProcessorException;
+use z2l_core::extension::{Extension, OpcodeHandler};
+use z2l_core::instruction::{Instruction, InstructionParts, InstructionWordParts};
+use z2l_core::mmu::{AtomicOperation, MemoryAccessType};
+use z2l_core::processor::hart::Hart;
+use z2l_core::processor::Xlen;
+
+/// An [`Extension`] defining the A standard extension.
+pub struct A;
+
+impl Extension for A {
+    fn code(&self) -> &'static str {
+        "A"
+    }
+
+    fn name(&self) -> &'static str {
+        "Standard Extension for Atomic Instructions"
+    }
+
+    fn register(&self, hart: &mut Hart) {
+        hart.register_opcode(0x2f, Box::new(AmoHandler));
+    }
+}
+
+/// AMO opcode handler.
+pub struct AmoHandler;
+
+impl OpcodeHandler for AmoHandler {
+    fn decode(
+        &self,
+        instruction: InstructionParts,
+        _pc: u64,
+        xlen: Xlen,
+    ) -> Result<Box<dyn Instruction>, ProcessorException> {
+        let instruction = instruction.into_word()?;
+
+        // Double-word atomics are only defined for RV64
+        let width = match (instruction.funct3, xlen) {
+            (0b010, _) => MemoryAccessType::Word,
+            (0b011, Xlen::Rv64) => MemoryAccessType::DoubleWord,
+            _ => return Err(ProcessorException::IllegalInstruction),
+        };
+
+        let op = match instruction.funct7 >> 2 {
+            0b00010 => {
+                let instruction = LoadReservedInstruction::new(&instruction, width, xlen)?;
+                return Ok(Box::new(instruction));
+            }
+            0b00011 => {
+                let instruction = StoreConditionalInstruction::new(&instruction, width, xlen);
+                return Ok(Box::new(instruction));
+            }
+            funct5 => operation(funct5).ok_or(ProcessorException::IllegalInstruction)?,
+        };
+
+        Ok(Box::new(AmoInstruction::new(&instruction, op, width, xlen)))
+    }
+}
+
+/// Get the read-modify-write operation performed by an AMO instruction with the provided funct5
+/// field, if it is one of the AMO*.W/AMO*.D operations.
+pub(crate) fn operation(funct5: u8) -> Option<AtomicOperation> {
+    Some(match funct5 {
+        0b00001 => AtomicOperation::Swap,
+        0b00000 => AtomicOperation::Add,
+        0b00100 => AtomicOperation::Xor,
+        0b01100 => AtomicOperation::And,
+        0b01000 => AtomicOperation::Or,
+        0b10000 => AtomicOperation::Min,
+        0b10100 => AtomicOperation::Max,
+        0b11000 => AtomicOperation::MinU,
+        0b11100 => AtomicOperation::MaxU,
+        _ => return None,
+    })
+}
+
+/// Determine whether the handlers already registered for the AMO opcode on the provided hart decode
+/// the provided instruction.
+///
+/// Extensions which add instructions when another extension is also present use this to detect it
+/// from whichever is registered last, so the extensions may be registered in any order.
+pub(crate) fn decodes(hart: &Hart, raw: u32) -> bool {
+    match (hart.opcodes.get(&0x2f), InstructionParts::new(raw)) {
+        (Some(handler), Ok(parts)) => handler.decode(parts, 0, hart.xlen).is_ok(),
+        _ => false,
+    }
+}
+
+/// Memory ordering constraints of an atomic instruction.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub(crate) struct Ordering {
+    /// Acquire bit.
+    acquire: bool,
+
+    /// Release bit.
+    release: bool,
+}
+
+impl Ordering {
+    /// Extract the ordering bits from the provided instruction.
+    pub(crate) fn new(instruction: &InstructionWordParts) -> Self {
+        Self {
+            acquire: instruction.funct7 & 0b10 != 0,
+            release: instruction.funct7 & 0b01 != 0,
+        }
+    }
+}
+
+impl fmt::Display for Ordering {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match (self.acquire, self.release) {
+            (false, false) => Ok(()),
+            (true, false) => f.write_str(".aq"),
+            (false, true) => f.write_str(".rl"),
+            (true, true) => f.write_str(".aqrl"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::A;
+    use crate::rv32i::RV32I;
+    use crate::rv64i::RV64I;
+    use crate::test_utils::{self, reg};
+    use z2l_core::error::ProcessorException;
+    use z2l_core::extension::Extension;
+    use z2l_core::instruction::InstructionParts;
+    use z2l_core::processor::hart::Hart;
+    use z2l_core::processor::Processor;
+
+    /// Run the provided program to completion on RV32I & RV64I, returning the processors.
+    fn run(program: &[u32]) -> [Processor; 2] {
+        let mut program = program.to_vec();
+        program.push(0x0000_006f); // jal x0, 0
+
+        [Box::new(RV32I) as Box<dyn Extension>, Box::new(RV64I)].map(|base| {
+            let mut processor = test_utils::processor(vec![base, Box::new(A)], &program);
+            for _ in 0..program.len() {
+                processor.cycle().unwrap();
+            }
+            processor
+        })
+    }
+
+    /// Set up x1 = 0x80000000 (on either base), & store -3 there.
+    const SETUP: [u32; 4] = [
+        0x0010_0093, // addi x1, x0, 1
+        0x01f0_9093, // slli x1, x1, 31
+        0xffd0_0113, // addi x2, x0, -3
+        0x0020_a023, // sw x2, 0(x1)
+    ];
+
+    #[test]
+    fn load_reserved_store_conditional() {
+        let mut program = SETUP.to_vec();
+        program.extend([
+            0x1000_a1af, // lr.w x3, (x1)
+            0x0070_0213, // addi x4, x0, 7
+            0x1840_a2af, // sc.w x5, x4, (x1)
+            0x1800_a32f, // sc.w x6, x0, (x1)
+        ]);
+
+        for processor in run(&program) {
+            // The first SC succeeds, consuming the reservation, so the second fails
+            assert_eq!(reg(&processor, 3), -3);
+            assert_eq!(reg(&processor, 5), 0);
+            assert_eq!(reg(&processor, 6), 1);
+            let mut mmu = processor.mmu.lock().unwrap();
+            assert_eq!(mmu.load_word(0x8000_0000).unwrap(), 7);
+        }
+    }
+
+    #[test]
+    fn store_clears_reservation() {
+        let mut program = SETUP.to_vec();
+        program.extend([
+            0x1000_a1af, // lr.w x3, (x1)
+            0x0000_9123, // sh x0, 2(x1)
+            0x0070_0213, // addi x4, x0, 7
+            0x1840_a2af, // sc.w x5, x4, (x1)
+        ]);
+
+        for processor in run(&program) {
+            // The store to part of the reserved word invalidates the reservation
+            assert_eq!(reg(&processor, 5), 1);
+            let mut mmu = processor.mmu.lock().unwrap();
+            assert_eq!(mmu.load_word(0x8000_0000).unwrap(), 0xfffd);
+        }
+    }
+
+    #[test]
+    fn memory_operations() {
+        // (funct5, value stored) for memory holding -3, & rs2 = 5
+        let cases: &[(u32, i64)] = &[
+            (0b00001, 5),  // amoswap.w
+            (0b00000, 2),  // amoadd.w
+            (0b00100, -8), // amoxor.w
+            (0b01100, 5),  // amoand.w
+            (0b01000, -3), // amoor.w
+            (0b10000, -3), // amomin.w
+            (0b10100, 5),  // amomax.w
+            (0b11000, 5),  // amominu.w
+            (0b11100, -3), // amomaxu.w
+        ];
+
+        for (funct5, expected) in cases {
+            let mut program = SETUP.to_vec();
+            program.extend([
+                0x0050_0213,                  // addi x4, x0, 5
+                (funct5 << 27) | 0x0040_a1af, // amo*.w x3, x4, (x1)
+                0x0000_a283,                  // lw x5, 0(x1)
+            ]);
+
+            for processor in run(&program) {
+                // rd receives the original value, sign-extended on RV64
+                assert_eq!(reg(&processor, 3), -3, "{:05b}", funct5);
+                assert_eq!(reg(&processor, 5), *expected, "{:05b}", funct5);
+            }
+        }
+    }
+
+    #[test]
+    fn access_fault() {
+        // (instruction, mcause) for an access to unmapped memory at 0x40000000
+        let cases: &[(u32, u64)] = &[
+            (0x1000_a1af, 5), // lr.w x3, (x1)
+            (0x0000_a1af, 7), // amoadd.w x3, x0, (x1)
+        ];
+
+        for (raw, cause) in cases {
+            let program = [
+                0x4000_00b7, // lui x1, 0x40000
+                *raw,
+                0x0000_006f, // jal x0, 0
+            ];
+            let mut processor = test_utils::processor(vec![Box::new(RV32I), Box::new(A)], &program);
+            processor.hart.csrs.get_mut(0x305).unwrap().value = 0x8; // mtvec
+            for _ in 0..5 {
+                processor.cycle().unwrap();
+            }
+
+            let csr = |addr| processor.hart.csrs.get(addr).unwrap().value;
+            assert_eq!(csr(0x342), *cause, "{:08x}", raw); // mcause
+            assert_eq!(csr(0x341), 0x4, "{:08x}", raw); // mepc
+            assert_eq!(csr(0x343), 0x4000_0000, "{:08x}", raw); // mtval
+        }
+    }
+
+    #[test]
+    fn decoding() {
+        let decode = |base: &dyn Extension, raw| {
+            let mut hart = Hart::new();
+            base.register(&mut hart);
+            A.register(&mut hart);
+            let parts = InstructionParts::new(raw).unwrap();
+            let handler = hart.opcodes.get(&parts.opcode()).unwrap();
+            handler.decode(parts, 0, hart.xlen).map(|i| i.format())
+        };
+
+        let cases: &[(u32, &str)] = &[
+            (0x1000_a1af, "lr.w x3, (x1)"),
+            (0x1400_a1af, "lr.w.aq x3, (x1)"),
+            (0x1a40_a2af, "sc.w.rl x5, x4, (x1)"),
+            (0x1e40_a2af, "sc.w.aqrl x5, x4, (x1)"),
+            (0x0e40_a1af, "amoswap.w.aqrl x3, x4, (x1)"),
+            (0x0440_a1af, "amoadd.w.aq x3, x4, (x1)"),
+            (0xe240_a1af, "amomaxu.w.rl x3, x4, (x1)"),
+        ];
+        for (raw, expected) in cases {
+            assert_eq!(decode(&RV32I, *raw), Ok(expected.to_string()));
+        }
+
+        // The double-word variants are only available on RV64
+        assert_eq!(
+            decode(&RV64I, 0x0040_b1af),
+            Ok("amoadd.d x3, x4, (x1)".to_owned())
+        );
+        assert_eq!(decode(&RV64I, 0x1000_b1af), Ok("lr.d x3, (x1)".to_owned()));
+
+        let illegal: &[u32] = &[
+            0x0040_b1af, // amoadd.d on RV32
+            0x1040_a1af, // lr.w with rs2 != 0
+            0x2840_a1af, // Reserved funct5
+            0x0040_c1af, // Reserved funct3
+        ];
+        for raw in illegal {
+            assert_eq!(
+                decode(&RV32I, *raw),
+                Err(ProcessorException::IllegalInstruction),
+                "{:08x}",
+                raw
+            );
+        }
+    }
+}
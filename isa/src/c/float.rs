@@ -0,0 +1,137 @@
+//! Compressed floating-point load & store instructions (C.FLD, C.FSD, C.FLDSP, C.FSDSP, and on
+//! RV32, C.FLW, C.FSW, C.FLWSP, C.FSWSP).
+//!
+//! These instructions are only available when the F or D extensions' floating-point registers are
+//! also present, so they are decoded by a separate handler, registered for quadrants 0 & 2 beneath
+//! the quadrant handlers by whichever of the extensions is registered last.
+
+use crate::c::{i_type, s_type};
+use crate::f::softfloat::Format;
+use crate::f::{FloatLoadInstruction, FloatStoreInstruction};
+use z2l_core::error::ProcessorException;
+use z2l_core::extension::OpcodeHandler;
+use z2l_core::instruction::{Instruction, InstructionParts};
+use z2l_core::processor::Xlen;
+
+/// LOAD-FP opcode.
+const LOAD_FP: u32 = 0x07;
+
+/// STORE-FP opcode.
+const STORE_FP: u32 = 0x27;
+
+/// Quadrant 0 & 2 opcode handler for the compressed floating-point loads & stores.
+pub struct FloatHandler {
+    flen: Format,
+}
+
+impl FloatHandler {
+    /// Create a new FloatHandler, supporting formats up to the provided width.
+    pub fn new(flen: Format) -> Self {
+        Self { flen }
+    }
+}
+
+impl OpcodeHandler for FloatHandler {
+    fn decode(
+        &self,
+        instruction: InstructionParts,
+        _pc: u64,
+        xlen: Xlen,
+    ) -> Result<Box<dyn Instruction>, ProcessorException> {
+        let instruction = instruction.into_halfword()?;
+        let raw = instruction.raw as i32;
+
+        // The single-precision encodings are used by C.LD, C.SD, C.LDSP & C.SDSP on RV64
+        let (funct3, format) = match (instruction.funct3, xlen) {
+            (0b001 | 0b101, _) => (0b011, Format::Double),
+            (0b011 | 0b111, Xlen::Rv32) => (0b010, Format::Single),
+            _ => return Err(ProcessorException::IllegalInstruction),
+        };
+        let load = instruction.funct3 & 0b100 == 0;
+
+        let expanded = match (instruction.opcode, load, format) {
+            // C.FLD: fld rd', offset(rs1')
+            (0b00, true, Format::Double) => {
+                // offset[5:3] = inst[12:10], offset[7:6] = inst[6:5]
+                let offset = (((raw >> 10) & 0b111) << 3) | (((raw >> 5) & 0b11) << 6);
+                i_type(
+                    LOAD_FP,
+                    instruction.rd_prime,
+                    funct3,
+                    instruction.rs1_prime,
+                    offset,
+                )
+            }
+
+            // C.FLW: flw rd', offset(rs1')
+            (0b00, true, Format::Single) => i_type(
+                LOAD_FP,
+                instruction.rd_prime,
+                funct3,
+                instruction.rs1_prime,
+                instruction.imm_cl,
+            ),
+
+            // C.FSD: fsd rs2', offset(rs1')
+            (0b00, false, Format::Double) => {
+                let offset = (((raw >> 10) & 0b111) << 3) | (((raw >> 5) & 0b11) << 6);
+                s_type(
+                    STORE_FP,
+                    funct3,
+                    instruction.rs1_prime,
+                    instruction.rs2_prime,
+                    offset,
+                )
+            }
+
+            // C.FSW: fsw rs2', offset(rs1')
+            (0b00, false, Format::Single) => s_type(
+                STORE_FP,
+                funct3,
+                instruction.rs1_prime,
+                instruction.rs2_prime,
+                instruction.imm_cs,
+            ),
+
+            // C.FLDSP: fld rd, offset(x2)
+            (0b10, true, Format::Double) => {
+                // Unlike C.LDSP, rd may be f0
+                let offset = (((raw >> 12) & 0b1) << 5)
+                    | (((raw >> 5) & 0b11) << 3)
+                    | (((raw >> 2) & 0b111) << 6);
+                i_type(LOAD_FP, instruction.rd, funct3, 2, offset)
+            }
+
+            // C.FLWSP: flw rd, offset(x2)
+            (0b10, true, Format::Single) => {
+                let offset = (((raw >> 12) & 0b1) << 5)
+                    | (((raw >> 4) & 0b111) << 2)
+                    | (((raw >> 2) & 0b11) << 6);
+                i_type(LOAD_FP, instruction.rd, funct3, 2, offset)
+            }
+
+            // C.FSDSP: fsd rs2, offset(x2)
+            (0b10, false, Format::Double) => {
+                let offset = (((raw >> 10) & 0b111) << 3) | (((raw >> 7) & 0b111) << 6);
+                s_type(STORE_FP, funct3, 2, instruction.rs2, offset)
+            }
+
+            // C.FSWSP: fsw rs2, offset(x2)
+            (0b10, false, Format::Single) => {
+                s_type(STORE_FP, funct3, 2, instruction.rs2, instruction.imm_css)
+            }
+
+            _ => return Err(ProcessorException::IllegalInstruction),
+        };
+
+        if load {
+            Ok(Box::new(FloatLoadInstruction::new(
+                &expanded, self.flen, xlen,
+            )?))
+        } else {
+            Ok(Box::new(FloatStoreInstruction::new(
+                &expanded, self.flen, xlen,
+            )?))
+        }
+    }
+}
@@ -0,0 +1,424 @@
+//! The "C" standard extension for compressed instructions.
+//!
+//! This extension defines 16-bit encodings for the most common instructions of the base integer
+//! instruction set. Each compressed instruction is equivalent to a standard 32-bit instruction, so
+//! we decode compressed instructions by expanding them to their 32-bit equivalents, then reusing
+//! the instruction implementations from the base instruction set. As a result, compressed
+//! instructions are formatted as the instructions they expand to.
+//!
+//! Compressed instructions are divided into three quadrants, identified by the lowest two bits of
+//! the instruction (the fourth "quadrant" identifies instructions of 32 bits or longer). Each
+//! quadrant has its own [`OpcodeHandler`](z2l_core::extension::OpcodeHandler).
+//!
+//! On RV64, the encodings used by the RV32 compressed single-precision floating-point load/store
+//! instructions and C.JAL instead encode the RV64-only C.LD, C.SD, C.LDSP, C.SDSP and C.ADDIW
+//! instructions.
+//!
+//! The compressed floating-point load/store instructions are only available with the F or D
+//! extensions, and are decoded by a [`FloatHandler`] wrapped by the quadrant 0 & 2 handlers. This
+//! is registered by whichever of the extensions is registered last, so they may be registered in
+//! any order.
+
+mod float;
+mod quadrant0;
+mod quadrant1;
+mod quadrant2;
+
+pub use float::FloatHandler;
+pub use quadrant0::Quadrant0Handler;
+pub use quadrant1::Quadrant1Handler;
+pub use quadrant2::Quadrant2Handler;
+
+use crate::f::softfloat::Format;
+use z2l_core::extension::{Extension, OpcodeHandler};
+use z2l_core::instruction::{InstructionParts, InstructionWordParts};
+use z2l_core::processor::hart::Hart;
+
+/// An [`Extension`] defining the C standard extension.
+pub struct C;
+
+impl Extension for C {
+    fn code(&self) -> &'static str {
+        "C"
+    }
+
+    fn name(&self) -> &'static str {
+        "Standard Extension for Compressed Instructions"
+    }
+
+    fn register(&self, hart: &mut Hart) {
+        let flen = float_flen(hart);
+        let float = || flen.map(|flen| Box::new(FloatHandler::new(flen)) as Box<dyn OpcodeHandler>);
+
+        hart.register_opcode(0b00, Box::new(Quadrant0Handler::new(float())));
+        hart.register_opcode(0b01, Box::new(Quadrant1Handler));
+        hart.register_opcode(0b10, Box::new(Quadrant2Handler::new(float())));
+
+        // Instructions may now be placed on 2-byte boundaries
+        hart.instruction_alignment = 2;
+    }
+}
+
+/// Register the compressed floating-point load/store instructions, supporting formats up to the
+/// provided width, if the C extension has been registered.
+///
+/// This is called when the F or D extension is registered: If the C extension is registered later,
+/// it instead detects the floating-point loads using [`float_flen`].
+pub(crate) fn register_float(hart: &mut Hart, flen: Format) {
+    for quadrant in [0b00, 0b10] {
+        if hart.opcodes.contains_key(&quadrant) {
+            hart.register_opcode(quadrant, Box::new(FloatHandler::new(flen)));
+        }
+    }
+}
+
+/// Get the widest floating-point format loaded by the LOAD-FP handler already registered on the
+/// provided hart, if any.
+fn float_flen(hart: &Hart) -> Option<Format> {
+    let handler = hart.opcodes.get(&0x07)?;
+    let loads = |raw| {
+        InstructionParts::new(raw).is_ok_and(|parts| handler.decode(parts, 0, hart.xlen).is_ok())
+    };
+
+    if loads(0x0000_3007) {
+        // fld f0, 0(x0)
+        Some(Format::Double)
+    } else if loads(0x0000_2007) {
+        // flw f0, 0(x0)
+        Some(Format::Single)
+    } else {
+        None
+    }
+}
+
+/// LOAD opcode.
+const LOAD: u32 = 0x03;
+
+/// OP-IMM opcode.
+const OP_IMM: u32 = 0x13;
+
+/// OP-IMM-32 opcode.
+const OP_IMM_32: u32 = 0x1b;
+
+/// STORE opcode.
+const STORE: u32 = 0x23;
+
+/// OP opcode.
+const OP: u32 = 0x33;
+
+/// OP-32 opcode.
+const OP_32: u32 = 0x3b;
+
+/// LUI opcode.
+const LUI: u32 = 0x37;
+
+/// BRANCH opcode.
+const BRANCH: u32 = 0x63;
+
+/// JALR opcode.
+const JALR: u32 = 0x67;
+
+/// JAL opcode.
+const JAL: u32 = 0x6f;
+
+/// SYSTEM opcode.
+const SYSTEM: u32 = 0x73;
+
+/// Build the parts of an R-format 32-bit instruction.
+fn r_type(opcode: u32, rd: u8, funct3: u32, rs1: u8, rs2: u8, funct7: u32) -> InstructionWordParts {
+    InstructionWordParts::new(
+        opcode
+            | ((rd as u32) << 7)
+            | (funct3 << 12)
+            | ((rs1 as u32) << 15)
+            | ((rs2 as u32) << 20)
+            | (funct7 << 25),
+    )
+}
+
+/// Build the parts of an I-format 32-bit instruction.
+fn i_type(opcode: u32, rd: u8, funct3: u32, rs1: u8, imm: i32) -> InstructionWordParts {
+    InstructionWordParts::new(
+        opcode | ((rd as u32) << 7) | (funct3 << 12) | ((rs1 as u32) << 15) | ((imm as u32) << 20),
+    )
+}
+
+/// Build the parts of an S-format 32-bit instruction.
+fn s_type(opcode: u32, funct3: u32, rs1: u8, rs2: u8, imm: i32) -> InstructionWordParts {
+    let imm = imm as u32;
+    InstructionWordParts::new(
+        opcode
+            | ((imm & 0b11111) << 7)
+            | (funct3 << 12)
+            | ((rs1 as u32) << 15)
+            | ((rs2 as u32) << 20)
+            | (((imm >> 5) & 0b1111111) << 25),
+    )
+}
+
+/// Build the parts of a B-format 32-bit instruction.
+fn b_type(opcode: u32, funct3: u32, rs1: u8, rs2: u8, imm: i32) -> InstructionWordParts {
+    let imm = imm as u32;
+    InstructionWordParts::new(
+        opcode
+            | (((imm >> 11) & 0b1) << 7)
+            | (((imm >> 1) & 0b1111) << 8)
+            | (funct3 << 12)
+            | ((rs1 as u32) << 15)
+            | ((rs2 as u32) << 20)
+            | (((imm >> 5) & 0b111111) << 25)
+            | (((imm >> 12) & 0b1) << 31),
+    )
+}
+
+/// Build the parts of a U-format 32-bit instruction.
+fn u_type(opcode: u32, rd: u8, imm: i32) -> InstructionWordParts {
+    InstructionWordParts::new(opcode | ((rd as u32) << 7) | (imm as u32 & 0xfffff000))
+}
+
+/// Build the parts of a J-format 32-bit instruction.
+fn j_type(opcode: u32, rd: u8, imm: i32) -> InstructionWordParts {
+    let imm = imm as u32;
+    InstructionWordParts::new(
+        opcode
+            | ((rd as u32) << 7)
+            | (imm & 0x000f_f000)
+            | (((imm >> 11) & 0b1) << 20)
+            | (((imm >> 1) & 0b1111111111) << 21)
+            | (((imm >> 20) & 0b1) << 31),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::C;
+    use crate::d::D;
+    use crate::f::F;
+    use crate::rv32i::RV32I;
+    use crate::rv64i::RV64I;
+    use std::collections::HashMap;
+    use z2l_core::error::ProcessorException;
+    use z2l_core::extension::Extension;
+    use z2l_core::instruction::InstructionParts;
+    use z2l_core::processor::hart::Hart;
+
+    fn hart() -> Hart {
+        let mut hart = Hart::new();
+        RV32I.register(&mut hart);
+        C.register(&mut hart);
+        hart
+    }
+
+    /// Decode the provided compressed instruction, returning its human-readable format.
+    fn decode(hart: &Hart, raw: u16) -> Result<String, ProcessorException> {
+        let parts = InstructionParts::new(raw as u32)?;
+        let handler = hart.opcodes.get(&parts.opcode()).unwrap();
+        Ok(handler.decode(parts, 0, hart.xlen)?.format())
+    }
+
+    #[test]
+    fn expand() {
+        let hart = hart();
+        let cases: &[(u16, &str)] = &[
+            (0x1fe0, "addi x8, x2, 0x000003fc"),   // c.addi4spn x8, x2, 1020
+            (0x5fe4, "lw x9, 0x0000007c(x15)"),    // c.lw x9, 124(x15)
+            (0xc028, "sw x10, 0x00000040(x8)"),    // c.sw x10, 64(x8)
+            (0x0001, "addi x0, x0, 0x00000000"),   // c.nop
+            (0x1281, "addi x5, x5, 0xffffffe0"),   // c.addi x5, -32
+            (0x3001, "jal x1, 0xfffff800"),        // c.jal -2048
+            (0x437d, "addi x6, x0, 0x0000001f"),   // c.li x6, 31
+            (0x7101, "addi x2, x2, 0xfffffe00"),   // c.addi16sp x2, -512
+            (0x7385, "lui x7, 0xfffe1000"),        // c.lui x7, 0xfffe1
+            (0x807d, "srli x8, x8, 31"),           // c.srli x8, 31
+            (0x8485, "srai x9, x9, 1"),            // c.srai x9, 1
+            (0x997d, "andi x10, x10, 0xffffffff"), // c.andi x10, -1
+            (0x8c05, "sub x8, x8, x9"),            // c.sub x8, x9
+            (0x8c25, "xor x8, x8, x9"),            // c.xor x8, x9
+            (0x8c45, "or x8, x8, x9"),             // c.or x8, x9
+            (0x8c65, "and x8, x8, x9"),            // c.and x8, x9
+            (0xa9c9, "jal x0, 0x000004d2"),        // c.j 1234
+            (0xd001, "beq x8, x0, 0xffffff00"),    // c.beqz x8, -256
+            (0xeffd, "bne x15, x0, 0x000000fe"),   // c.bnez x15, 254
+            (0x01c6, "slli x3, x3, 17"),           // c.slli x3, 17
+            (0x527e, "lw x4, 0x000000fc(x2)"),     // c.lwsp x4, 252(x2)
+            (0x8282, "jalr x0, 0x00000000(x5)"),   // c.jr x5
+            (0x831e, "add x6, x0, x7"),            // c.mv x6, x7
+            (0x9002, "ebreak"),                    // c.ebreak
+            (0x9402, "jalr x1, 0x00000000(x8)"),   // c.jalr x8
+            (0x94aa, "add x9, x9, x10"),           // c.add x9, x10
+            (0xdfae, "sw x11, 0x000000fc(x2)"),    // c.swsp x11, 252(x2)
+        ];
+
+        for (raw, expected) in cases {
+            assert_eq!(decode(&hart, *raw).unwrap(), *expected);
+        }
+    }
+
+    #[test]
+    fn expand_rv64() {
+        let mut hart = Hart::new();
+        RV64I.register(&mut hart);
+        C.register(&mut hart);
+
+        let cases: &[(u16, &str)] = &[
+            (0x6784, "ld x9, 0x00000008(x15)"),   // c.ld x9, 8(x15)
+            (0xfc68, "sd x10, 0x000000f8(x8)"),   // c.sd x10, 248(x8)
+            (0x32fd, "addiw x5, x5, 0xffffffff"), // c.addiw x5, -1
+            (0x9001, "srli x8, x8, 32"),          // c.srli x8, 32
+            (0x9c05, "subw x8, x8, x9"),          // c.subw x8, x9
+            (0x9c25, "addw x8, x8, x9"),          // c.addw x8, x9
+            (0x11fe, "slli x3, x3, 63"),          // c.slli x3, 63
+            (0x727e, "ld x4, 0x000001f8(x2)"),    // c.ldsp x4, 504(x2)
+            (0xffae, "sd x11, 0x000001f8(x2)"),   // c.sdsp x11, 504(x2)
+        ];
+        for (raw, expected) in cases {
+            assert_eq!(decode(&hart, *raw).unwrap(), *expected);
+        }
+
+        for raw in [0x2001, 0x6002, 0x2000] {
+            // c.addiw with rd = x0, c.ldsp with rd = x0, c.fld without D
+            assert_eq!(
+                decode(&hart, raw),
+                Err(ProcessorException::IllegalInstruction)
+            );
+        }
+    }
+
+    #[test]
+    fn reserved_encodings() {
+        let hart = hart();
+        let cases: &[u16] = &[
+            0x0000, // All-zero instruction
+            0x0010, // c.addi4spn with zero immediate
+            0x6101, // c.addi16sp with zero immediate
+            0x6381, // c.lui with zero immediate
+            0x5002, // c.lwsp with rd = x0
+            0x8002, // c.jr with rs1 = x0
+            0x9001, // c.srli with shamt[5] set
+            0x9c05, // c.subw (RV64 only)
+            0x2000, // c.fld (requires D)
+            0x6000, // c.flw (requires F)
+        ];
+
+        for raw in cases {
+            assert_eq!(
+                decode(&hart, *raw),
+                Err(ProcessorException::IllegalInstruction)
+            );
+        }
+    }
+
+    #[test]
+    fn expand_float() {
+        let double: &[(u16, &str)] = &[
+            (0x2784, "fld f9, 0x00000008(x15)"), // c.fld f9, 8(x15)
+            (0xbc68, "fsd f10, 0x000000f8(x8)"), // c.fsd f10, 248(x8)
+            (0x327e, "fld f4, 0x000001f8(x2)"),  // c.fldsp f4, 504(x2)
+            (0x2002, "fld f0, 0x00000000(x2)"),  // c.fldsp f0, 0(x2)
+            (0xbfae, "fsd f11, 0x000001f8(x2)"), // c.fsdsp f11, 504(x2)
+        ];
+        let single: &[(u16, &str)] = &[
+            (0x7fe4, "flw f9, 0x0000007c(x15)"), // c.flw f9, 124(x15)
+            (0xe028, "fsw f10, 0x00000040(x8)"), // c.fsw f10, 64(x8)
+            (0x727e, "flw f4, 0x000000fc(x2)"),  // c.flwsp f4, 252(x2)
+            (0x6002, "flw f0, 0x00000000(x2)"),  // c.flwsp f0, 0(x2)
+            (0xffae, "fsw f11, 0x000000fc(x2)"), // c.fswsp f11, 252(x2)
+        ];
+
+        // The floating-point extensions may be registered before or after C
+        let orders: &[&[&dyn Extension]] = &[
+            &[&RV32I, &C, &F, &D],
+            &[&RV32I, &F, &D, &C],
+            &[&F, &C, &RV32I, &D],
+        ];
+        for extensions in orders {
+            let mut hart = Hart::new();
+            for extension in extensions.iter() {
+                extension.register(&mut hart);
+            }
+            for (raw, expected) in double.iter().chain(single) {
+                assert_eq!(decode(&hart, *raw).unwrap(), *expected);
+            }
+        }
+
+        // The double-precision instructions require D, & none are available without F
+        let mut single_only = Hart::new();
+        for extension in [&F as &dyn Extension, &C, &RV32I] {
+            extension.register(&mut single_only);
+        }
+        for (raw, expected) in single {
+            assert_eq!(decode(&single_only, *raw).unwrap(), *expected);
+        }
+        for (raw, _) in double {
+            assert_eq!(
+                decode(&single_only, *raw),
+                Err(ProcessorException::IllegalInstruction)
+            );
+        }
+        for (raw, _) in double.iter().chain(single) {
+            assert_eq!(
+                decode(&hart(), *raw),
+                Err(ProcessorException::IllegalInstruction)
+            );
+        }
+
+        // On RV64, the single-precision encodings are C.LD, C.SD, C.LDSP & C.SDSP
+        let mut hart = Hart::new();
+        for extension in [&RV64I as &dyn Extension, &D, &C] {
+            extension.register(&mut hart);
+        }
+        for (raw, expected) in double {
+            assert_eq!(decode(&hart, *raw).unwrap(), *expected);
+        }
+        assert_eq!(decode(&hart, 0x727e).unwrap(), "ld x4, 0x000001f8(x2)");
+    }
+
+    /// Run the provided program on the hart for the given number of cycles.
+    fn run(hart: &mut Hart, program: &HashMap<u64, u32>, cycles: usize) {
+        for _ in 0..cycles {
+            let raw = program.get(&hart.pc).copied().unwrap_or(0x0001);
+            hart.cycle(Ok(raw), 0, &[]).unwrap();
+        }
+    }
+
+    #[test]
+    fn execute_mixed_lengths() {
+        let mut hart = hart();
+        let program = HashMap::from([
+            (0x0, 0x437d),      // c.li x6, 31
+            (0x2, 0x2019),      // c.jal 6
+            (0x4, 0xfce0_8793), // addi x15, x1, -50 (skipped)
+            (0x8, 0x839a),      // c.mv x7, x6
+            (0xa, 0xfce0_8793), // addi x15, x1, -50
+        ]);
+
+        run(&mut hart, &program, 7);
+
+        let reg = |n: u8| hart.registers.get(&n).unwrap().load().unwrap();
+        assert_eq!(reg(1), 4);
+        assert_eq!(reg(6), 31);
+        assert_eq!(reg(7), 31);
+        assert_eq!(reg(15), -46);
+        assert_eq!(hart.pc, 0x12);
+    }
+
+    #[test]
+    fn relaxed_alignment() {
+        let jal = 0x0060_00ef; // jal x1, 6
+
+        // Jumping to a 2-byte aligned address is fine with compressed instructions enabled
+        let mut hart = hart();
+        hart.cycle(Ok(jal), 0, &[]).unwrap();
+        hart.cycle(Ok(0x0001), 0, &[]).unwrap();
+        assert_eq!(hart.pc, 6);
+
+        // But raises an exception in the base ISA
+        let mut hart = Hart::new();
+        RV32I.register(&mut hart);
+        hart.cycle(Ok(jal), 0, &[]).unwrap();
+        assert_eq!(
+            hart.cycle(Ok(0x0000_0013), 0, &[]).unwrap_err(),
+            (ProcessorException::InstructionAddressMisaligned, 0)
+        );
+    }
+}
@@ -0,0 +1,111 @@
+//! Quadrant 0 compressed instructions (C.ADDI4SPN, C.LW, C.SW, and on RV64, C.LD, C.SD).
+
+use crate::c::{i_type, s_type, LOAD, OP_IMM, STORE};
+use crate::rv32i::load::LoadInstruction;
+use crate::rv32i::op_imm::AddIInstruction;
+use crate::rv32i::store::StoreInstruction;
+use z2l_core::error::ProcessorException;
+use z2l_core::extension::OpcodeHandler;
+use z2l_core::instruction::{Instruction, InstructionParts};
+use z2l_core::processor::Xlen;
+
+/// Quadrant 0 opcode handler.
+///
+/// Any instruction this handler doesn't decode is passed on to the wrapped handler, if there is
+/// one, which decodes the compressed floating-point loads & stores.
+pub struct Quadrant0Handler {
+    inner: Option<Box<dyn OpcodeHandler>>,
+}
+
+impl Quadrant0Handler {
+    /// Create a new Quadrant0Handler, wrapping the provided handler.
+    pub fn new(inner: Option<Box<dyn OpcodeHandler>>) -> Self {
+        Self { inner }
+    }
+}
+
+impl OpcodeHandler for Quadrant0Handler {
+    fn decode(
+        &self,
+        parts: InstructionParts,
+        pc: u64,
+        xlen: Xlen,
+    ) -> Result<Box<dyn Instruction>, ProcessorException> {
+        let instruction = parts.halfword()?;
+
+        // Offset of C.LD/C.SD: offset[5:3] = inst[12:10], offset[7:6] = inst[6:5]
+        let raw = instruction.raw as i32;
+        let offset_d = (((raw >> 10) & 0b111) << 3) | (((raw >> 5) & 0b11) << 6);
+
+        match (instruction.funct3, xlen) {
+            // C.ADDI4SPN: addi rd', x2, nzuimm
+            (0b000, _) => {
+                // A zero immediate is reserved: This also covers the all-zero instruction, which is
+                // defined to be illegal
+                if instruction.imm_ciw == 0 {
+                    return Err(ProcessorException::IllegalInstruction);
+                }
+
+                let expanded = i_type(OP_IMM, instruction.rd_prime, 0b000, 2, instruction.imm_ciw);
+                Ok(Box::new(AddIInstruction::new(&expanded, xlen)))
+            }
+
+            // C.LW: lw rd', offset(rs1')
+            (0b010, _) => {
+                let expanded = i_type(
+                    LOAD,
+                    instruction.rd_prime,
+                    0b010,
+                    instruction.rs1_prime,
+                    instruction.imm_cl,
+                );
+                Ok(Box::new(LoadInstruction::new(&expanded, xlen)?))
+            }
+
+            // C.LD: ld rd', offset(rs1')
+            (0b011, Xlen::Rv64) => {
+                let expanded = i_type(
+                    LOAD,
+                    instruction.rd_prime,
+                    0b011,
+                    instruction.rs1_prime,
+                    offset_d,
+                );
+                Ok(Box::new(LoadInstruction::new(&expanded, xlen)?))
+            }
+
+            // C.SW: sw rs2', offset(rs1')
+            (0b110, _) => {
+                let expanded = s_type(
+                    STORE,
+                    0b010,
+                    instruction.rs1_prime,
+                    instruction.rs2_prime,
+                    instruction.imm_cs,
+                );
+                Ok(Box::new(StoreInstruction::new(&expanded, xlen)?))
+            }
+
+            // C.SD: sd rs2', offset(rs1')
+            (0b111, Xlen::Rv64) => {
+                let expanded = s_type(
+                    STORE,
+                    0b011,
+                    instruction.rs1_prime,
+                    instruction.rs2_prime,
+                    offset_d,
+                );
+                Ok(Box::new(StoreInstruction::new(&expanded, xlen)?))
+            }
+
+            _ => match &self.inner {
+                Some(inner) => inner.decode(parts, pc, xlen),
+                None => Err(ProcessorException::IllegalInstruction),
+            },
+        }
+    }
+
+    fn inner_mut(&mut self) -> Option<&mut Option<Box<dyn OpcodeHandler>>> {
+        Some(&mut self.inner)
+    }
+}
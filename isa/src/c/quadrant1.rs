@@ -0,0 +1,186 @@
+//! Quadrant 1 compressed instructions (C.NOP, C.ADDI, C.JAL, C.LI, C.ADDI16SP, C.LUI, C.SRLI,
+//! C.SRAI, C.ANDI, C.SUB, C.XOR, C.OR, C.AND, C.J, C.BEQZ, C.BNEZ, and on RV64, C.ADDIW, C.SUBW,
+//! C.ADDW).
+
+use crate::c::{
+    b_type, i_type, j_type, r_type, u_type, BRANCH, JAL, LUI, OP, OP_32, OP_IMM, OP_IMM_32,
+};
+use crate::rv32i::branch::BranchInstruction;
+use crate::rv32i::jal::JalInstruction;
+use crate::rv32i::lui::LuiInstruction;
+use crate::rv32i::op::{AndInstruction, ArithmeticInstruction, OrInstruction, XorInstruction};
+use crate::rv32i::op_imm::{AddIInstruction, AndIInstruction, SrIInstruction};
+use z2l_core::error::ProcessorException;
+use z2l_core::extension::OpcodeHandler;
+use z2l_core::instruction::{Instruction, InstructionHalfWordParts, InstructionParts};
+use z2l_core::processor::Xlen;
+
+/// Quadrant 1 opcode handler.
+pub struct Quadrant1Handler;
+
+impl OpcodeHandler for Quadrant1Handler {
+    fn decode(
+        &self,
+        instruction: InstructionParts,
+        pc: u64,
+        xlen: Xlen,
+    ) -> Result<Box<dyn Instruction>, ProcessorException> {
+        let instruction = instruction.into_halfword()?;
+        let rd = instruction.rd;
+
+        match instruction.funct3 {
+            // C.NOP/C.ADDI: addi rd, rd, nzimm
+            0b000 => {
+                let expanded = i_type(OP_IMM, rd, 0b000, rd, instruction.imm_ci);
+                Ok(Box::new(AddIInstruction::new(&expanded, xlen)))
+            }
+
+            // C.ADDIW: addiw rd, rd, imm
+            0b001 if xlen == Xlen::Rv64 => {
+                if rd == 0 {
+                    return Err(ProcessorException::IllegalInstruction);
+                }
+
+                let expanded = i_type(OP_IMM_32, rd, 0b000, rd, instruction.imm_ci);
+                Ok(Box::new(AddIInstruction::new_word(&expanded)))
+            }
+
+            // C.JAL: jal x1, offset
+            0b001 => {
+                let expanded = j_type(JAL, 1, instruction.imm_cj);
+                Ok(Box::new(JalInstruction::new_compressed(
+                    &expanded, pc, xlen,
+                )))
+            }
+
+            // C.LI: addi rd, x0, imm
+            0b010 => {
+                let expanded = i_type(OP_IMM, rd, 0b000, 0, instruction.imm_ci);
+                Ok(Box::new(AddIInstruction::new(&expanded, xlen)))
+            }
+
+            // C.ADDI16SP: addi x2, x2, nzimm
+            0b011 if rd == 2 => {
+                let raw = instruction.raw as u32;
+                let bit = |n: u32| (raw >> n) & 1;
+                let imm = ((bit(12) << 9)
+                    | (bit(6) << 4)
+                    | (bit(5) << 6)
+                    | (((raw >> 3) & 0b11) << 7)
+                    | (bit(2) << 5)) as i32;
+                let imm = (imm << 22) >> 22;
+                if imm == 0 {
+                    return Err(ProcessorException::IllegalInstruction);
+                }
+
+                let expanded = i_type(OP_IMM, 2, 0b000, 2, imm);
+                Ok(Box::new(AddIInstruction::new(&expanded, xlen)))
+            }
+
+            // C.LUI: lui rd, nzimm
+            0b011 => {
+                if instruction.imm_ci == 0 {
+                    return Err(ProcessorException::IllegalInstruction);
+                }
+
+                let expanded = u_type(LUI, rd, instruction.imm_ci << 12);
+                Ok(Box::new(LuiInstruction::new(&expanded)))
+            }
+
+            0b100 => decode_misc_alu(&instruction, xlen),
+
+            // C.J: jal x0, offset
+            0b101 => {
+                let expanded = j_type(JAL, 0, instruction.imm_cj);
+                Ok(Box::new(JalInstruction::new_compressed(
+                    &expanded, pc, xlen,
+                )))
+            }
+
+            // C.BEQZ: beq rs1', x0, offset
+            0b110 => {
+                let expanded = b_type(BRANCH, 0b000, instruction.rs1_prime, 0, instruction.imm_cb);
+                Ok(Box::new(BranchInstruction::new(&expanded, pc)?))
+            }
+
+            // C.BNEZ: bne rs1', x0, offset
+            0b111 => {
+                let expanded = b_type(BRANCH, 0b001, instruction.rs1_prime, 0, instruction.imm_cb);
+                Ok(Box::new(BranchInstruction::new(&expanded, pc)?))
+            }
+
+            _ => unreachable!("funct3 is 3 bits"),
+        }
+    }
+}
+
+/// Decode the arithmetic instructions sharing the `0b100` funct3 value.
+fn decode_misc_alu(
+    instruction: &InstructionHalfWordParts,
+    xlen: Xlen,
+) -> Result<Box<dyn Instruction>, ProcessorException> {
+    let rd = instruction.rs1_prime;
+    let rs2 = instruction.rs2_prime;
+
+    // Bit 12 is shamt[5] for shifts, which must be zero for RV32 (this is checked when the expanded
+    // instruction is decoded); and selects the RV64-only SUBW/ADDW instructions for
+    // register-register operations.
+    let bit_12 = instruction.funct6 & 0b100 != 0;
+    let shamt = instruction.imm_ci & 0b111111;
+
+    match (instruction.funct6 & 0b11, bit_12) {
+        // C.SRLI: srli rd', rd', shamt
+        (0b00, _) => {
+            let expanded = i_type(OP_IMM, rd, 0b101, rd, shamt);
+            Ok(Box::new(SrIInstruction::new(&expanded, xlen)?))
+        }
+
+        // C.SRAI: srai rd', rd', shamt
+        (0b01, _) => {
+            let expanded = i_type(OP_IMM, rd, 0b101, rd, shamt | 0b010000000000);
+            Ok(Box::new(SrIInstruction::new(&expanded, xlen)?))
+        }
+
+        // C.ANDI: andi rd', rd', imm
+        (0b10, _) => {
+            let expanded = i_type(OP_IMM, rd, 0b111, rd, instruction.imm_ci);
+            Ok(Box::new(AndIInstruction::new(&expanded)))
+        }
+
+        // C.SUB, C.XOR, C.OR, C.AND
+        (0b11, false) => match instruction.funct2 {
+            0b00 => {
+                let expanded = r_type(OP, rd, 0b000, rd, rs2, 0b0100000);
+                Ok(Box::new(ArithmeticInstruction::new(&expanded, xlen)?))
+            }
+            0b01 => {
+                let expanded = r_type(OP, rd, 0b100, rd, rs2, 0b0000000);
+                Ok(Box::new(XorInstruction::new(&expanded)))
+            }
+            0b10 => {
+                let expanded = r_type(OP, rd, 0b110, rd, rs2, 0b0000000);
+                Ok(Box::new(OrInstruction::new(&expanded)))
+            }
+            0b11 => {
+                let expanded = r_type(OP, rd, 0b111, rd, rs2, 0b0000000);
+                Ok(Box::new(AndInstruction::new(&expanded)))
+            }
+            _ => unreachable!("funct2 is 2 bits"),
+        },
+
+        // C.SUBW, C.ADDW
+        (0b11, true) if xlen == Xlen::Rv64 => match instruction.funct2 {
+            0b00 => {
+                let expanded = r_type(OP_32, rd, 0b000, rd, rs2, 0b0100000);
+                Ok(Box::new(ArithmeticInstruction::new_word(&expanded)?))
+            }
+            0b01 => {
+                let expanded = r_type(OP_32, rd, 0b000, rd, rs2, 0b0000000);
+                Ok(Box::new(ArithmeticInstruction::new_word(&expanded)?))
+            }
+            _ => Err(ProcessorException::IllegalInstruction),
+        },
+
+        _ => Err(ProcessorException::IllegalInstruction),
+    }
+}
@@ -0,0 +1,140 @@
+//! Quadrant 2 compressed instructions (C.SLLI, C.LWSP, C.JR, C.MV, C.EBREAK, C.JALR, C.ADD,
+//! C.SWSP, and on RV64, C.LDSP, C.SDSP).
+
+use crate::c::{i_type, r_type, s_type, JALR, LOAD, OP, OP_IMM, STORE, SYSTEM};
+use crate::rv32i::jalr::JalrInstruction;
+use crate::rv32i::load::LoadInstruction;
+use crate::rv32i::op::ArithmeticInstruction;
+use crate::rv32i::op_imm::SllIInstruction;
+use crate::rv32i::store::StoreInstruction;
+use crate::rv32i::system::EBreakInstruction;
+use z2l_core::error::ProcessorException;
+use z2l_core::extension::OpcodeHandler;
+use z2l_core::instruction::{Instruction, InstructionParts};
+use z2l_core::processor::Xlen;
+
+/// Quadrant 2 opcode handler.
+///
+/// Any instruction this handler doesn't decode is passed on to the wrapped handler, if there is
+/// one, which decodes the compressed floating-point loads & stores.
+pub struct Quadrant2Handler {
+    inner: Option<Box<dyn OpcodeHandler>>,
+}
+
+impl Quadrant2Handler {
+    /// Create a new Quadrant2Handler, wrapping the provided handler.
+    pub fn new(inner: Option<Box<dyn OpcodeHandler>>) -> Self {
+        Self { inner }
+    }
+}
+
+impl OpcodeHandler for Quadrant2Handler {
+    fn decode(
+        &self,
+        parts: InstructionParts,
+        pc: u64,
+        xlen: Xlen,
+    ) -> Result<Box<dyn Instruction>, ProcessorException> {
+        let instruction = parts.halfword()?;
+        let rd = instruction.rd;
+        let rs2 = instruction.rs2;
+        let raw = instruction.raw as i32;
+
+        match (instruction.funct3, xlen) {
+            // C.SLLI: slli rd, rd, shamt
+            (0b000, _) => {
+                // shamt[5] must be zero for RV32, which is checked when the expansion is decoded
+                let expanded = i_type(OP_IMM, rd, 0b001, rd, instruction.imm_ci & 0b111111);
+                Ok(Box::new(SllIInstruction::new(&expanded, xlen)?))
+            }
+
+            // C.LWSP: lw rd, offset(x2)
+            (0b010, _) => {
+                if rd == 0 {
+                    return Err(ProcessorException::IllegalInstruction);
+                }
+
+                let offset = (((raw >> 12) & 0b1) << 5)
+                    | (((raw >> 4) & 0b111) << 2)
+                    | (((raw >> 2) & 0b11) << 6);
+
+                let expanded = i_type(LOAD, rd, 0b010, 2, offset);
+                Ok(Box::new(LoadInstruction::new(&expanded, xlen)?))
+            }
+
+            // C.LDSP: ld rd, offset(x2)
+            (0b011, Xlen::Rv64) => {
+                if rd == 0 {
+                    return Err(ProcessorException::IllegalInstruction);
+                }
+
+                let offset = (((raw >> 12) & 0b1) << 5)
+                    | (((raw >> 5) & 0b11) << 3)
+                    | (((raw >> 2) & 0b111) << 6);
+
+                let expanded = i_type(LOAD, rd, 0b011, 2, offset);
+                Ok(Box::new(LoadInstruction::new(&expanded, xlen)?))
+            }
+
+            (0b100, _) => match (instruction.funct4 & 0b1 != 0, rd, rs2) {
+                // C.JR: jalr x0, 0(rs1)
+                (false, 0, 0) => Err(ProcessorException::IllegalInstruction),
+                (false, rs1, 0) => {
+                    let expanded = i_type(JALR, 0, 0b000, rs1, 0);
+                    Ok(Box::new(JalrInstruction::new_compressed(
+                        &expanded, pc, xlen,
+                    )))
+                }
+
+                // C.MV: add rd, x0, rs2
+                (false, rd, rs2) => {
+                    let expanded = r_type(OP, rd, 0b000, 0, rs2, 0b0000000);
+                    Ok(Box::new(ArithmeticInstruction::new(&expanded, xlen)?))
+                }
+
+                // C.EBREAK: ebreak
+                (true, 0, 0) => {
+                    let expanded = i_type(SYSTEM, 0, 0b000, 0, 1);
+                    Ok(Box::new(EBreakInstruction::new(&expanded)?))
+                }
+
+                // C.JALR: jalr x1, 0(rs1)
+                (true, rs1, 0) => {
+                    let expanded = i_type(JALR, 1, 0b000, rs1, 0);
+                    Ok(Box::new(JalrInstruction::new_compressed(
+                        &expanded, pc, xlen,
+                    )))
+                }
+
+                // C.ADD: add rd, rd, rs2
+                (true, rd, rs2) => {
+                    let expanded = r_type(OP, rd, 0b000, rd, rs2, 0b0000000);
+                    Ok(Box::new(ArithmeticInstruction::new(&expanded, xlen)?))
+                }
+            },
+
+            // C.SWSP: sw rs2, offset(x2)
+            (0b110, _) => {
+                let expanded = s_type(STORE, 0b010, 2, rs2, instruction.imm_css);
+                Ok(Box::new(StoreInstruction::new(&expanded, xlen)?))
+            }
+
+            // C.SDSP: sd rs2, offset(x2)
+            (0b111, Xlen::Rv64) => {
+                let offset = (((raw >> 10) & 0b111) << 3) | (((raw >> 7) & 0b111) << 6);
+
+                let expanded = s_type(STORE, 0b011, 2, rs2, offset);
+                Ok(Box::new(StoreInstruction::new(&expanded, xlen)?))
+            }
+
+            _ => match &self.inner {
+                Some(inner) => inner.decode(parts, pc, xlen),
+                None => Err(ProcessorException::IllegalInstruction),
+            },
+        }
+    }
+
+    fn inner_mut(&mut self) -> Option<&mut Option<Box<dyn OpcodeHandler>>> {
+        Some(&mut self.inner)
+    }
+}
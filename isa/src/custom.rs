@@ -0,0 +1,466 @@
+//! Support for user-defined instructions in the custom opcodes.
+//!
+//! The RISC-V spec reserves four major opcodes, custom-0 to custom-3, for non-standard extensions.
+//! Rather than implementing an [`OpcodeHandler`](z2l_core::extension::OpcodeHandler) and
+//! [`Instruction`] for each custom instruction, a [`CustomExtension`] can be built declaratively
+//! from the format, opcode & minor opcodes of each instruction, and a closure implementing it:
+//!
+//! ```
+//! use z2l_isa::custom::{CustomExtension, Format, CUSTOM_0};
+//!
+//! // rd = rd + (rs1 * rs2)
+//! let xacc = CustomExtension::new("Xacc", "Multiply-Accumulate Extension").instruction(
+//!     "mac",
+//!     CUSTOM_0,
+//!     Format::R(0b0000000),
+//!     0b000,
+//!     |registers, operands| {
+//!         let acc = operands.dest(registers)?;
+//!         let product = operands.src1(registers)?.wrapping_mul(operands.src2(registers)?);
+//!         operands.set_dest(registers, acc.wrapping_add(product))
+//!     },
+//! );
+//! ```
+//!
+//! Custom instructions only operate on the register file. Each opcode used by the extension is
+//! registered as a [`SubDecoder`], so several custom extensions may share an opcode, provided their
+//! instructions' encodings don't overlap.
+
+use std::collections::BTreeMap;
+use std::sync::Arc;
+use z2l_core::error::ProcessorException;
+use z2l_core::extension::{Extension, SubDecoder};
+use z2l_core::instruction::{Instruction, InstructionResult, InstructionWordParts};
+use z2l_core::processor::hart::Hart;
+use z2l_core::processor::register::RegisterFile;
+use z2l_core::processor::Xlen;
+
+/// The custom-0 major opcode.
+pub const CUSTOM_0: u8 = 0x0b;
+
+/// The custom-1 major opcode.
+pub const CUSTOM_1: u8 = 0x2b;
+
+/// The custom-2 major opcode.
+pub const CUSTOM_2: u8 = 0x5b;
+
+/// The custom-3 major opcode.
+pub const CUSTOM_3: u8 = 0x7b;
+
+/// Format of a custom instruction, which determines how its operands are decoded.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum Format {
+    /// Register-register format, with the provided funct7 field: Operands are rd, rs1 & rs2.
+    R(u8),
+
+    /// Immediate format: Operands are rd, rs1 & a 12-bit sign-extended immediate.
+    I,
+
+    /// Store format: Operands are rs1, rs2 & a 12-bit sign-extended immediate.
+    S,
+}
+
+/// The operands of a decoded custom instruction.
+///
+/// Fields which aren't used by the instruction's [`Format`] are zero.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub struct Operands {
+    /// Destination register.
+    pub rd: u8,
+
+    /// First source register.
+    pub rs1: u8,
+
+    /// Second source register.
+    pub rs2: u8,
+
+    /// Sign-extended immediate.
+    pub imm: i64,
+
+    /// XLEN of the hart executing the instruction.
+    pub xlen: Xlen,
+}
+
+impl Operands {
+    /// Load the value of the provided register.
+    fn load(registers: &RegisterFile, reg: u8) -> Result<i64, ProcessorException> {
+        registers
+            .get(&reg)
+            .ok_or(ProcessorException::IllegalInstruction)?
+            .load()
+    }
+
+    /// Load the value of rs1.
+    pub fn src1(&self, registers: &RegisterFile) -> Result<i64, ProcessorException> {
+        Self::load(registers, self.rs1)
+    }
+
+    /// Load the value of rs2.
+    pub fn src2(&self, registers: &RegisterFile) -> Result<i64, ProcessorException> {
+        Self::load(registers, self.rs2)
+    }
+
+    /// Load the current value of rd.
+    pub fn dest(&self, registers: &RegisterFile) -> Result<i64, ProcessorException> {
+        Self::load(registers, self.rd)
+    }
+
+    /// Store a value in rd, sign-extended from XLEN bits.
+    pub fn set_dest(
+        &self,
+        registers: &mut RegisterFile,
+        value: i64,
+    ) -> Result<(), ProcessorException> {
+        registers
+            .get_mut(&self.rd)
+            .ok_or(ProcessorException::IllegalInstruction)?
+            .store(self.xlen.sign_extend(value))?;
+        Ok(())
+    }
+}
+
+/// Closure implementing a custom instruction.
+type Behaviour =
+    dyn Fn(&mut RegisterFile, &Operands) -> Result<(), ProcessorException> + Send + Sync;
+
+/// Definition of a single custom instruction.
+struct CustomSpec {
+    mnemonic: &'static str,
+    opcode: u8,
+    format: Format,
+    funct3: u8,
+    behaviour: Box<Behaviour>,
+}
+
+impl CustomSpec {
+    /// Determine whether the provided instruction is encoded as this instruction.
+    fn matches(&self, instruction: &InstructionWordParts) -> bool {
+        let funct7 = match self.format {
+            Format::R(funct7) => funct7 == instruction.funct7,
+            Format::I | Format::S => true,
+        };
+        instruction.opcode == self.opcode && instruction.funct3 == self.funct3 && funct7
+    }
+
+    /// Determine whether some instruction could be decoded as both this and the other instruction.
+    fn overlaps(&self, other: &CustomSpec) -> bool {
+        let funct7 = match (self.format, other.format) {
+            (Format::R(a), Format::R(b)) => a == b,
+            _ => true,
+        };
+        self.opcode == other.opcode && self.funct3 == other.funct3 && funct7
+    }
+}
+
+/// An [`Extension`] consisting of user-defined instructions in the custom opcodes.
+pub struct CustomExtension {
+    code: &'static str,
+    name: &'static str,
+    instructions: Vec<Arc<CustomSpec>>,
+}
+
+impl CustomExtension {
+    /// Create a new CustomExtension with no instructions, and the provided code & name.
+    ///
+    /// By convention, the codes of non-standard extensions begin with "X".
+    pub fn new(code: &'static str, name: &'static str) -> Self {
+        Self {
+            code,
+            name,
+            instructions: Vec::new(),
+        }
+    }
+
+    /// Add an instruction to this extension.
+    ///
+    /// The instruction is matched by its opcode, the provided funct3 field, and for the R format,
+    /// its funct7 field. When executed, `behaviour` is called with the hart's register file and the
+    /// instruction's decoded operands: Any error it returns is raised as an exception.
+    ///
+    /// Programs may place any value in the registers, so `behaviour` must not panic for any operand
+    /// values: For example, arithmetic should use the `wrapping_*` methods, which match the
+    /// behaviour of the standard instructions on overflow, rather than the operators, which panic
+    /// on overflow in debug builds.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `opcode` is not one of the custom opcodes, if funct3 or funct7 don't fit in their
+    /// fields, or if the instruction's encoding overlaps with another instruction in this
+    /// extension.
+    pub fn instruction<F>(
+        mut self,
+        mnemonic: &'static str,
+        opcode: u8,
+        format: Format,
+        funct3: u8,
+        behaviour: F,
+    ) -> Self
+    where
+        F: Fn(&mut RegisterFile, &Operands) -> Result<(), ProcessorException>
+            + Send
+            + Sync
+            + 'static,
+    {
+        assert!(
+            [CUSTOM_0, CUSTOM_1, CUSTOM_2, CUSTOM_3].contains(&opcode),
+            "Custom instructions must use one of the custom-0 to custom-3 opcodes"
+        );
+        let funct7 = match format {
+            Format::R(funct7) => funct7,
+            Format::I | Format::S => 0,
+        };
+        assert!(
+            funct3 <= 0b111 && funct7 <= 0b111_1111,
+            "funct3 must be 3 bits wide, and funct7 7 bits wide"
+        );
+
+        let spec = CustomSpec {
+            mnemonic,
+            opcode,
+            format,
+            funct3,
+            behaviour: Box::new(behaviour),
+        };
+        if let Some(other) = self.instructions.iter().find(|other| other.overlaps(&spec)) {
+            panic!(
+                "Custom instruction {} overlaps with {}",
+                mnemonic, other.mnemonic
+            );
+        }
+        self.instructions.push(Arc::new(spec));
+        self
+    }
+}
+
+impl Extension for CustomExtension {
+    fn code(&self) -> &'static str {
+        self.code
+    }
+
+    fn name(&self) -> &'static str {
+        self.name
+    }
+
+    fn register(&self, hart: &mut Hart) {
+        let mut opcodes: BTreeMap<u8, Vec<Arc<CustomSpec>>> = BTreeMap::new();
+        for spec in &self.instructions {
+            opcodes.entry(spec.opcode).or_default().push(spec.clone());
+        }
+
+        for (opcode, instructions) in opcodes {
+            hart.register_sub_decoder(opcode, Box::new(CustomDecoder { instructions }));
+        }
+    }
+}
+
+/// Decoder for a custom extension's instructions, in one of the custom opcodes.
+struct CustomDecoder {
+    instructions: Vec<Arc<CustomSpec>>,
+}
+
+impl SubDecoder for CustomDecoder {
+    fn decode(
+        &self,
+        instruction: &InstructionWordParts,
+        _pc: u64,
+        xlen: Xlen,
+    ) -> Result<Option<Box<dyn Instruction>>, ProcessorException> {
+        let Some(spec) = self
+            .instructions
+            .iter()
+            .find(|spec| spec.matches(instruction))
+        else {
+            return Ok(None);
+        };
+
+        let operands = match spec.format {
+            Format::R(_) => Operands {
+                rd: instruction.rd,
+                rs1: instruction.rs1,
+                rs2: instruction.rs2,
+                imm: 0,
+                xlen,
+            },
+            Format::I => Operands {
+                rd: instruction.rd,
+                rs1: instruction.rs1,
+                rs2: 0,
+                imm: instruction.imm_i as i64,
+                xlen,
+            },
+            Format::S => Operands {
+                rd: 0,
+                rs1: instruction.rs1,
+                rs2: instruction.rs2,
+                imm: instruction.imm_s as i64,
+                xlen,
+            },
+        };
+
+        Ok(Some(Box::new(CustomInstruction {
+            spec: spec.clone(),
+            operands,
+        })))
+    }
+}
+
+/// A decoded custom instruction.
+struct CustomInstruction {
+    spec: Arc<CustomSpec>,
+    operands: Operands,
+}
+
+impl Instruction for CustomInstruction {
+    fn execute(
+        &self,
+        registers: &mut RegisterFile,
+        _mem: i64,
+    ) -> Result<InstructionResult, ProcessorException> {
+        (self.spec.behaviour)(registers, &self.operands)?;
+        Ok(InstructionResult::default())
+    }
+
+    fn format(&self) -> String {
+        let Operands {
+            rd, rs1, rs2, imm, ..
+        } = self.operands;
+        match self.spec.format {
+            Format::R(_) => format!("{} x{}, x{}, x{}", self.spec.mnemonic, rd, rs1, rs2),
+            Format::I => format!(
+                "{} x{}, x{}, 0x{:08x}",
+                self.spec.mnemonic, rd, rs1, imm as i32
+            ),
+            Format::S => format!(
+                "{} x{}, 0x{:08x}(x{})",
+                self.spec.mnemonic, rs2, imm as i32, rs1
+            ),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{CustomExtension, Format, CUSTOM_0, CUSTOM_1};
+    use crate::rv32i::RV32I;
+    use crate::rv64i::RV64I;
+    use crate::test_utils;
+    use z2l_core::error::ProcessorException;
+    use z2l_core::extension::Extension;
+    use z2l_core::processor::hart::Hart;
+
+    const MAC: u32 = 0x0020_818b; // mac x3, x1, x2
+    const ROTI: u32 = 0x0040_a1ab; // roti x3, x1, 4
+    const CHECK: u32 = 0xfe20_bfab; // check x2, -1(x1)
+    const SUB: u32 = 0x4020_818b; // funct7 of 0b0100000 in custom-0
+
+    /// An extension with one instruction of each format.
+    fn xtest() -> CustomExtension {
+        CustomExtension::new("Xtest", "Test Extension")
+            .instruction("mac", CUSTOM_0, Format::R(0), 0b000, |registers, ops| {
+                let acc = ops.dest(registers)?;
+                let product = ops.src1(registers)?.wrapping_mul(ops.src2(registers)?);
+                ops.set_dest(registers, acc.wrapping_add(product))
+            })
+            .instruction("roti", CUSTOM_1, Format::I, 0b010, |registers, ops| {
+                let src = ops.src1(registers)? as u32;
+                ops.set_dest(registers, src.rotate_left(ops.imm as u32) as i64)
+            })
+            .instruction("check", CUSTOM_1, Format::S, 0b011, |registers, ops| {
+                if ops.src1(registers)?.wrapping_add(ops.imm) != ops.src2(registers)? {
+                    return Err(ProcessorException::IllegalInstruction);
+                }
+                Ok(())
+            })
+    }
+
+    /// Execute a single instruction, returning its formatting.
+    fn run(hart: &mut Hart, raw: u32) -> Result<String, (ProcessorException, u64)> {
+        hart.cycle(Ok(raw), 0, &[])?;
+        hart.cycle(Ok(0x0000_0013), 0, &[])?;
+        Ok(hart.last_instr.clone().unwrap())
+    }
+
+    #[test]
+    fn execution() {
+        let xtest = xtest();
+        let mut hart = test_utils::hart(&[&RV32I, &xtest]);
+        assert_eq!(run(&mut hart, MAC).as_deref(), Ok("mac x3, x1, x2"));
+        assert_eq!(test_utils::execute(&mut hart, MAC, 6, 7), 42);
+        assert_eq!(test_utils::execute(&mut hart, MAC, 2, 4), 50);
+
+        // Results are sign-extended on RV32
+        assert_eq!(
+            run(&mut hart, ROTI).as_deref(),
+            Ok("roti x3, x1, 0x00000004")
+        );
+        assert_eq!(test_utils::execute(&mut hart, ROTI, 0x1000_0000, 0), 1);
+        assert_eq!(
+            test_utils::execute(&mut hart, ROTI, 0x0800_0000, 0),
+            i32::MIN as i64
+        );
+
+        // Errors returned by the closure are raised, so the check fails once x2 is cleared
+        test_utils::execute(&mut hart, CHECK, 0x0800_0000, 0x07ff_ffff);
+        assert_eq!(
+            run(&mut hart, CHECK).as_deref(),
+            Ok("check x2, 0xffffffff(x1)")
+        );
+        hart.registers.get_mut(&2).unwrap().store(0).unwrap();
+        assert!(matches!(
+            run(&mut hart, CHECK),
+            Err((ProcessorException::IllegalInstruction, _))
+        ));
+    }
+
+    #[test]
+    fn decoding() {
+        let xtest = xtest();
+        let mut single = test_utils::hart(&[&RV64I, &xtest]);
+        assert!(run(&mut single, SUB).is_err());
+
+        // Further extensions may share an opcode, regardless of registration order
+        let xsub = CustomExtension::new("Xsub", "Subtract Extension").instruction(
+            "sub",
+            CUSTOM_0,
+            Format::R(0b0100000),
+            0b000,
+            |registers, ops| {
+                let difference = ops.src1(registers)?.wrapping_sub(ops.src2(registers)?);
+                ops.set_dest(registers, difference)
+            },
+        );
+        for extensions in [[&xtest as &dyn Extension, &xsub], [&xsub, &xtest]] {
+            let mut hart = test_utils::hart(&[&RV64I, extensions[0], extensions[1]]);
+            assert_eq!(run(&mut hart, SUB).as_deref(), Ok("sub x3, x1, x2"));
+            assert_eq!(run(&mut hart, MAC).as_deref(), Ok("mac x3, x1, x2"));
+            assert_eq!(test_utils::execute(&mut hart, SUB, i64::MIN, 1), i64::MAX);
+        }
+    }
+
+    #[test]
+    fn overflow() {
+        // Overflow wraps, as in the standard instructions, rather than panicking
+        let xtest = xtest();
+        let mut hart = test_utils::hart(&[&RV64I, &xtest]);
+        assert_eq!(test_utils::execute(&mut hart, MAC, i64::MIN, -1), i64::MIN);
+        assert_eq!(test_utils::execute(&mut hart, MAC, i64::MIN, 1), 0);
+    }
+
+    #[test]
+    #[should_panic(expected = "Custom instruction bad overlaps with roti")]
+    fn overlapping() {
+        xtest().instruction("bad", CUSTOM_1, Format::R(0), 0b010, |_, _| Ok(()));
+    }
+
+    #[test]
+    #[should_panic(expected = "must use one of the custom-0 to custom-3 opcodes")]
+    fn standard_opcode() {
+        CustomExtension::new("Xbad", "Bad Extension").instruction(
+            "bad",
+            0x33,
+            Format::I,
+            0b000,
+            |_, _| Ok(()),
+        );
+    }
+}
@@ -0,0 +1,28 @@
+//! The "D" standard extension for double-precision floating-point.
+//!
+//! The D extension widens the floating-point registers of the F extension to 64 bits, and adds
+//! double-precision variants of each of its instructions, selected by an `fmt` field of `0b01`. D
+//! depends on F, so registering this extension also registers the F extension's registers and
+//! instructions.
+
+use crate::f::softfloat::Format;
+use crate::f::Operands;
+use z2l_core::extension::Extension;
+use z2l_core::processor::hart::Hart;
+
+/// An [`Extension`] defining the D standard extension.
+pub struct D;
+
+impl Extension for D {
+    fn code(&self) -> &'static str {
+        "D"
+    }
+
+    fn name(&self) -> &'static str {
+        "Standard Extension for Double-Precision Floating-Point"
+    }
+
+    fn register(&self, hart: &mut Hart) {
+        crate::f::register(hart, Format::Double, Operands::Float);
+    }
+}
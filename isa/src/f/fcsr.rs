@@ -0,0 +1,122 @@
+//! The floating-point control and status register, `fcsr`.
+//!
+//! `fcsr` holds the dynamic rounding mode (`frm`, bits 7:5) and the accrued exception flags
+//! (`fflags`, bits 4:0), each of which can also be accessed through its own CSR. Floating-point
+//! instructions must read `frm` and accrue exception flags as they execute, but only have access to
+//! the hart's [`RegisterFile`]: So the value of `fcsr` is also exposed as a register, at key
+//! [`FCSR_REGISTER`], sharing its state with the CSRs.
+
+use super::softfloat::{FloatEnv, RoundingMode};
+use super::Rounding;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use z2l_core::error::ProcessorException;
+use z2l_core::processor::csr::Csr;
+use z2l_core::processor::hart::Hart;
+use z2l_core::processor::register::{Register, RegisterFile};
+
+/// Floating-point accrued exceptions.
+pub const FFLAGS: u16 = 0x001;
+
+/// Floating-point dynamic rounding mode.
+pub const FRM: u16 = 0x002;
+
+/// Floating-point control and status register (`frm` + `fflags`).
+pub const FCSR: u16 = 0x003;
+
+/// Key of the register holding the value of `fcsr` in the [`RegisterFile`].
+pub const FCSR_REGISTER: u8 = 64;
+
+/// Mask of the `fflags` field of `fcsr`.
+const FFLAGS_MASK: u64 = 0x1f;
+
+/// Offset of the `frm` field of `fcsr`.
+const FRM_SHIFT: u32 = 5;
+
+/// Mask of `fcsr`.
+const FCSR_MASK: u64 = 0xff;
+
+/// Register exposing the value of `fcsr` to floating-point instructions.
+#[derive(Clone, Debug, Default)]
+pub struct FcsrRegister {
+    value: Arc<AtomicU64>,
+}
+
+impl Register for FcsrRegister {
+    fn load(&self) -> Result<i64, ProcessorException> {
+        Ok(self.value.load(Ordering::Relaxed) as i64)
+    }
+
+    fn store(&mut self, val: i64) -> Result<i64, ProcessorException> {
+        let prev = self.value.swap(val as u64 & FCSR_MASK, Ordering::Relaxed);
+        Ok(prev as i64)
+    }
+}
+
+/// Register the `fcsr` register, and the `fflags`, `frm` & `fcsr` CSRs.
+pub(crate) fn register(hart: &mut Hart) {
+    let value = Arc::new(AtomicU64::new(0));
+    hart.registers.insert(
+        FCSR_REGISTER,
+        Box::new(FcsrRegister {
+            value: value.clone(),
+        }),
+    );
+
+    // Each CSR updates its field of the shared value, & reads it back on demand
+    let field = |mask: u64, shift: u32| {
+        let (read, write) = (value.clone(), value.clone());
+        Csr::new(0)
+            .with_write_mask(mask)
+            .on_read(move |_, _| (read.load(Ordering::Relaxed) >> shift) & mask)
+            .on_write(move |_, new, _| {
+                let update = |fcsr: u64| Some((fcsr & !(mask << shift)) | ((new & mask) << shift));
+                let _ = write.fetch_update(Ordering::Relaxed, Ordering::Relaxed, update);
+                new & mask
+            })
+    };
+    hart.csrs.register(FFLAGS, field(FFLAGS_MASK, 0));
+    hart.csrs.register(FRM, field(0b111, FRM_SHIFT));
+    hart.csrs.register(FCSR, field(FCSR_MASK, 0));
+}
+
+/// Create the [`FloatEnv`] in which an instruction with the provided rounding mode executes.
+///
+/// Raises an IllegalInstruction exception if the dynamic rounding mode is selected, and `frm` holds
+/// an invalid rounding mode.
+pub(crate) fn env(
+    registers: &RegisterFile,
+    rounding: Rounding,
+) -> Result<FloatEnv, ProcessorException> {
+    let mode = match rounding {
+        Rounding::Static(mode) => mode,
+        Rounding::Dynamic => {
+            let frm = (load(registers)? >> FRM_SHIFT) & 0b111;
+            RoundingMode::from_bits(frm as u8).ok_or(ProcessorException::IllegalInstruction)?
+        }
+    };
+    Ok(FloatEnv::new(mode))
+}
+
+/// Accrue the exception flags raised in a [`FloatEnv`] into `fflags`.
+pub(crate) fn accrue(
+    registers: &mut RegisterFile,
+    env: &FloatEnv,
+) -> Result<(), ProcessorException> {
+    if env.flags != 0 {
+        let fcsr = load(registers)?;
+        registers
+            .get_mut(&FCSR_REGISTER)
+            .ok_or(ProcessorException::IllegalInstruction)?
+            .store((fcsr | env.flags as u64) as i64)?;
+    }
+    Ok(())
+}
+
+/// Read the value of `fcsr`.
+fn load(registers: &RegisterFile) -> Result<u64, ProcessorException> {
+    Ok(registers
+        .get(&FCSR_REGISTER)
+        .ok_or(ProcessorException::IllegalInstruction)?
+        .load()? as u64)
+}
@@ -0,0 +1,152 @@
+//! Fused multiply-add instructions (FMADD, FMSUB, FNMSUB, FNMADD).
+//!
+//! These instructions multiply rs1 by rs2, then add or subtract rs3, rounding only once. Each has
+//! its own major opcode, and names a third source register in the upper 5 bits of the instruction
+//! (the "R4" instruction format).
+
+use super::softfloat::Format;
+use super::{fcsr, format, Operands, RegisterBank, Rounding};
+use std::fmt;
+use z2l_core::error::ProcessorException;
+use z2l_core::extension::OpcodeHandler;
+use z2l_core::instruction::{
+    Instruction, InstructionParts, InstructionResult, InstructionWordParts,
+};
+use z2l_core::processor::register::RegisterFile;
+use z2l_core::processor::Xlen;
+
+/// Operation to perform.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+enum Operation {
+    /// `(rs1 * rs2) + rs3`
+    MulAdd,
+
+    /// `(rs1 * rs2) - rs3`
+    MulSub,
+
+    /// `-(rs1 * rs2) + rs3`
+    NegMulSub,
+
+    /// `-(rs1 * rs2) - rs3`
+    NegMulAdd,
+}
+
+impl fmt::Display for Operation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Operation::MulAdd => f.write_str("fmadd"),
+            Operation::MulSub => f.write_str("fmsub"),
+            Operation::NegMulSub => f.write_str("fnmsub"),
+            Operation::NegMulAdd => f.write_str("fnmadd"),
+        }
+    }
+}
+
+/// MADD, MSUB, NMSUB, and NMADD opcode handler.
+pub struct FusedMultiplyAddHandler {
+    flen: Format,
+    operands: Operands,
+}
+
+impl FusedMultiplyAddHandler {
+    /// Create a new FusedMultiplyAddHandler, supporting formats up to the provided width, with
+    /// operands in the provided registers.
+    pub fn new(flen: Format, operands: Operands) -> Self {
+        Self { flen, operands }
+    }
+}
+
+impl OpcodeHandler for FusedMultiplyAddHandler {
+    fn decode(
+        &self,
+        instruction: InstructionParts,
+        _pc: u64,
+        xlen: Xlen,
+    ) -> Result<Box<dyn Instruction>, ProcessorException> {
+        let instruction = instruction.into_word()?;
+        Ok(Box::new(FusedMultiplyAddInstruction::new(
+            &instruction,
+            self.flen,
+            self.operands.bank(xlen),
+        )?))
+    }
+}
+
+/// FMADD, FMSUB, FNMSUB, or FNMADD instruction.
+pub struct FusedMultiplyAddInstruction {
+    src1: u8,
+    src2: u8,
+    src3: u8,
+    dest: u8,
+    op: Operation,
+    format: Format,
+    rounding: Rounding,
+    bank: RegisterBank,
+}
+
+impl FusedMultiplyAddInstruction {
+    /// Create a new FusedMultiplyAddInstruction, supporting formats up to the provided width, with
+    /// operands in the provided registers.
+    pub fn new(
+        instruction: &InstructionWordParts,
+        flen: Format,
+        bank: RegisterBank,
+    ) -> Result<Self, ProcessorException> {
+        let op = match instruction.opcode {
+            0x43 => Operation::MulAdd,
+            0x47 => Operation::MulSub,
+            0x4b => Operation::NegMulSub,
+            0x4f => Operation::NegMulAdd,
+            _ => return Err(ProcessorException::IllegalInstruction),
+        };
+
+        Ok(Self {
+            src1: instruction.rs1,
+            src2: instruction.rs2,
+            src3: instruction.funct7 >> 2,
+            dest: instruction.rd,
+            op,
+            format: format(instruction.funct7 & 0b11, flen)?,
+            rounding: Rounding::new(instruction.funct3)?,
+            bank,
+        })
+    }
+}
+
+impl Instruction for FusedMultiplyAddInstruction {
+    fn execute(
+        &self,
+        registers: &mut RegisterFile,
+        _mem: i64,
+    ) -> Result<InstructionResult, ProcessorException> {
+        let mut env = fcsr::env(registers, self.rounding)?;
+        let src1 = self.bank.read(registers, self.src1, self.format)?;
+        let src2 = self.bank.read(registers, self.src2, self.format)?;
+        let src3 = self.bank.read(registers, self.src3, self.format)?;
+
+        let (negate_product, negate_addend) = match self.op {
+            Operation::MulAdd => (false, false),
+            Operation::MulSub => (false, true),
+            Operation::NegMulSub => (true, false),
+            Operation::NegMulAdd => (true, true),
+        };
+        let result = env.mul_add(self.format, src1, src2, src3, negate_product, negate_addend);
+
+        self.bank.write(registers, self.dest, self.format, result)?;
+        fcsr::accrue(registers, &env)?;
+        Ok(InstructionResult::default())
+    }
+
+    fn format(&self) -> String {
+        format!(
+            "{}.{} {}, {}, {}, {}{}",
+            self.op,
+            self.format,
+            self.bank.name(self.dest),
+            self.bank.name(self.src1),
+            self.bank.name(self.src2),
+            self.bank.name(self.src3),
+            self.rounding
+        )
+    }
+}
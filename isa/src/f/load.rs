@@ -0,0 +1,106 @@
+//! LOAD-FP opcode instructions (FLW, FLD).
+//!
+//! These instructions load a floating-point value from memory into a floating-point register. The
+//! value is not interpreted, so loading a signaling NaN doesn't raise an exception.
+
+use super::softfloat::Format;
+use super::RegisterBank;
+use z2l_core::error::ProcessorException;
+use z2l_core::extension::OpcodeHandler;
+use z2l_core::instruction::{
+    Instruction, InstructionParts, InstructionResult, InstructionWordParts,
+};
+use z2l_core::mmu::{LoadSpec, MemoryAccessType};
+use z2l_core::processor::register::RegisterFile;
+use z2l_core::processor::Xlen;
+
+/// LOAD-FP opcode handler.
+pub struct FloatLoadHandler {
+    flen: Format,
+}
+
+impl FloatLoadHandler {
+    /// Create a new FloatLoadHandler, supporting formats up to the provided width.
+    pub fn new(flen: Format) -> Self {
+        Self { flen }
+    }
+}
+
+impl OpcodeHandler for FloatLoadHandler {
+    fn decode(
+        &self,
+        instruction: InstructionParts,
+        _pc: u64,
+        xlen: Xlen,
+    ) -> Result<Box<dyn Instruction>, ProcessorException> {
+        let instruction = instruction.into_word()?;
+        Ok(Box::new(FloatLoadInstruction::new(
+            &instruction,
+            self.flen,
+            xlen,
+        )?))
+    }
+}
+
+/// FLW or FLD instruction.
+pub struct FloatLoadInstruction {
+    base: u8,
+    offset: i32,
+    dest: u8,
+    format: Format,
+    width: MemoryAccessType,
+    xlen: Xlen,
+}
+
+impl FloatLoadInstruction {
+    /// Create a new FloatLoadInstruction, supporting formats up to the provided width, for a hart
+    /// with the provided XLEN.
+    pub fn new(
+        instruction: &InstructionWordParts,
+        flen: Format,
+        xlen: Xlen,
+    ) -> Result<Self, ProcessorException> {
+        let (format, width) = match (instruction.funct3, flen) {
+            (0b010, _) => (Format::Single, MemoryAccessType::Word),
+            (0b011, Format::Double) => (Format::Double, MemoryAccessType::DoubleWord),
+            _ => return Err(ProcessorException::IllegalInstruction),
+        };
+
+        Ok(Self {
+            base: instruction.rs1,
+            offset: instruction.imm_i,
+            dest: instruction.rd,
+            format,
+            width,
+            xlen,
+        })
+    }
+}
+
+impl Instruction for FloatLoadInstruction {
+    fn load(&self, registers: &RegisterFile) -> Result<Option<LoadSpec>, ProcessorException> {
+        let base = registers
+            .get(&self.base)
+            .ok_or(ProcessorException::IllegalInstruction)?
+            .load()?;
+        let addr = self.xlen.zero_extend(base.wrapping_add(self.offset as i64)) as usize;
+
+        Ok(Some(LoadSpec::new(self.width, addr)))
+    }
+
+    fn execute(
+        &self,
+        registers: &mut RegisterFile,
+        mem: i64,
+    ) -> Result<InstructionResult, ProcessorException> {
+        RegisterBank::Float.write(registers, self.dest, self.format, mem as u64)?;
+        Ok(InstructionResult::default())
+    }
+
+    fn format(&self) -> String {
+        format!(
+            "fl{} f{}, 0x{:08x}(x{})",
+            self.width, self.dest, self.offset, self.base
+        )
+    }
+}
@@ -0,0 +1,519 @@
+//! The "F" standard extension for single-precision floating-point.
+//!
+//! This extension adds 32 floating-point registers, `f0` to `f31`, which are stored in the hart's
+//! [`RegisterFile`] alongside the integer registers (see [`FLOAT_REGISTER_BASE`]), and the `fcsr`
+//! CSR, which holds the dynamic rounding mode (`frm`) and the accrued exception flags (`fflags`).
+//! The registers are 64 bits wide: Single-precision values are NaN-boxed, so that the registers can
+//! also hold the double-precision values of the D extension.
+//!
+//! Floating-point arithmetic is implemented in software (see [`softfloat`]), so results are
+//! bit-exact regardless of the host. The instructions are shared with the Zfinx and Zdinx
+//! extensions, which operate on the integer registers instead (see [`RegisterBank`]).
+//!
+//! With the C extension, the compressed floating-point load/store instructions are also available
+//! (see [`crate::c::FloatHandler`]).
+
+pub mod fcsr;
+mod fma;
+mod load;
+mod op;
+pub mod softfloat;
+mod store;
+
+pub use fma::{FusedMultiplyAddHandler, FusedMultiplyAddInstruction};
+pub use load::{FloatLoadHandler, FloatLoadInstruction};
+pub use op::{
+    ArithmeticInstruction, ClassifyInstruction, CompareInstruction, ConvertFormatInstruction,
+    ConvertFromIntInstruction, ConvertToIntInstruction, MoveFromIntInstruction,
+    MoveToIntInstruction, OpFpHandler, SignInjectionInstruction, SqrtInstruction,
+};
+pub use store::{FloatStoreHandler, FloatStoreInstruction};
+
+use softfloat::{Format, RoundingMode};
+use std::fmt;
+use z2l_core::error::ProcessorException;
+use z2l_core::extension::Extension;
+use z2l_core::processor::hart::Hart;
+use z2l_core::processor::register::{GeneralPurposeRegister, RegisterFile, FLOAT_REGISTER_BASE};
+use z2l_core::processor::Xlen;
+
+/// An [`Extension`] defining the F standard extension.
+pub struct F;
+
+impl Extension for F {
+    fn code(&self) -> &'static str {
+        "F"
+    }
+
+    fn name(&self) -> &'static str {
+        "Standard Extension for Single-Precision Floating-Point"
+    }
+
+    fn register(&self, hart: &mut Hart) {
+        // The D extension also registers the F extension's instructions, which must not be replaced
+        // by the single-precision-only handlers
+        if hart.opcodes.contains_key(&OP_FP) {
+            return;
+        }
+        register(hart, Format::Single, Operands::Float);
+    }
+}
+
+/// LOAD-FP opcode.
+const LOAD_FP: u8 = 0x07;
+
+/// STORE-FP opcode.
+const STORE_FP: u8 = 0x27;
+
+/// MADD opcode.
+const MADD: u8 = 0x43;
+
+/// MSUB opcode.
+const MSUB: u8 = 0x47;
+
+/// NMSUB opcode.
+const NMSUB: u8 = 0x4b;
+
+/// NMADD opcode.
+const NMADD: u8 = 0x4f;
+
+/// OP-FP opcode.
+pub(crate) const OP_FP: u8 = 0x53;
+
+/// Register `fcsr` and the floating-point opcode handlers, with operands in the provided registers.
+///
+/// `flen` is the widest format supported: Instructions operating on wider formats are illegal. If
+/// the floating-point registers are used, they are also registered, along with the LOAD-FP &
+/// STORE-FP opcodes and their compressed forms.
+pub(crate) fn register(hart: &mut Hart, flen: Format, operands: Operands) {
+    if operands == Operands::Float {
+        for i in 0..32 {
+            hart.registers.insert(
+                FLOAT_REGISTER_BASE + i,
+                Box::new(GeneralPurposeRegister::new()),
+            );
+        }
+        hart.register_opcode(LOAD_FP, Box::new(FloatLoadHandler::new(flen)));
+        hart.register_opcode(STORE_FP, Box::new(FloatStoreHandler::new(flen)));
+        crate::c::register_float(hart, flen);
+    }
+    fcsr::register(hart);
+
+    for opcode in [MADD, MSUB, NMSUB, NMADD] {
+        hart.register_opcode(
+            opcode,
+            Box::new(FusedMultiplyAddHandler::new(flen, operands)),
+        );
+    }
+    hart.register_opcode(OP_FP, Box::new(OpFpHandler::new(flen, operands)));
+}
+
+/// Get the format identified by the `fmt` field of an instruction.
+///
+/// Raises an IllegalInstruction exception if the format is not supported.
+fn format(fmt: u8, flen: Format) -> Result<Format, ProcessorException> {
+    match (fmt, flen) {
+        (0b00, _) => Ok(Format::Single),
+        (0b01, Format::Double) => Ok(Format::Double),
+        _ => Err(ProcessorException::IllegalInstruction),
+    }
+}
+
+/// Rounding mode selected by the `rm` field of an instruction.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum Rounding {
+    /// Use the provided rounding mode.
+    Static(RoundingMode),
+
+    /// Use the rounding mode in the `frm` CSR.
+    Dynamic,
+}
+
+impl Rounding {
+    /// Decode the `rm` field of an instruction.
+    ///
+    /// Raises an IllegalInstruction exception for the reserved encodings.
+    pub fn new(rm: u8) -> Result<Self, ProcessorException> {
+        match rm {
+            0b111 => Ok(Rounding::Dynamic),
+            _ => RoundingMode::from_bits(rm)
+                .map(Rounding::Static)
+                .ok_or(ProcessorException::IllegalInstruction),
+        }
+    }
+}
+
+impl fmt::Display for Rounding {
+    /// Formats as an operand to append to an instruction: Nothing for the dynamic rounding mode.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Rounding::Static(mode) => write!(f, ", {}", mode),
+            Rounding::Dynamic => Ok(()),
+        }
+    }
+}
+
+/// Registers holding the operands & results of the floating-point instructions of an extension.
+///
+/// Unlike a [`RegisterBank`], this doesn't depend on the hart's XLEN, so can be chosen when the
+/// extension is registered, which may be before the base integer instruction set.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum Operands {
+    /// The floating-point registers, as used by the F & D extensions.
+    Float,
+
+    /// The integer registers, as used by the Zfinx & Zdinx extensions.
+    Integer,
+}
+
+impl Operands {
+    /// Get the bank of registers holding the operands, on a hart with the provided XLEN.
+    pub fn bank(self, xlen: Xlen) -> RegisterBank {
+        match self {
+            Operands::Float => RegisterBank::Float,
+            Operands::Integer => RegisterBank::Integer(xlen),
+        }
+    }
+}
+
+/// Registers holding the operands & results of floating-point instructions.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum RegisterBank {
+    /// The floating-point registers, `f0` to `f31`, added by the F extension.
+    ///
+    /// Single-precision values are NaN-boxed (the upper 32 bits of the register are all ones).
+    Float,
+
+    /// The integer registers of a hart with the provided XLEN, as used by the Zfinx & Zdinx
+    /// extensions.
+    ///
+    /// Single-precision values are sign-extended. On RV32, double-precision values are held in an
+    /// even/odd pair of registers, with the lower 32 bits in the even register: Odd registers are
+    /// reserved, and the pair starting at `x0` reads as zero & ignores writes.
+    Integer(Xlen),
+}
+
+impl RegisterBank {
+    /// Read a register as a value of the provided format.
+    ///
+    /// In the floating-point registers, single-precision values must be NaN-boxed: Otherwise, the
+    /// register is read as the canonical NaN.
+    pub(crate) fn read(
+        &self,
+        registers: &RegisterFile,
+        reg: u8,
+        format: Format,
+    ) -> Result<u64, ProcessorException> {
+        let load = |reg: u8| {
+            registers
+                .get(&reg)
+                .ok_or(ProcessorException::IllegalInstruction)?
+                .load()
+        };
+
+        match (self, format) {
+            (RegisterBank::Float, _) => {
+                let value = read_raw(registers, reg)?;
+                match format {
+                    Format::Single if value >> 32 != 0xffff_ffff => Ok(format.canonical_nan()),
+                    _ => Ok(value & format.mask()),
+                }
+            }
+            (RegisterBank::Integer(_), Format::Single) => Ok(load(reg)? as u32 as u64),
+            (RegisterBank::Integer(Xlen::Rv64), Format::Double) => Ok(load(reg)? as u64),
+            (RegisterBank::Integer(Xlen::Rv32), Format::Double) => {
+                Self::check_pair(reg)?;
+                if reg == 0 {
+                    return Ok(0);
+                }
+                let low = load(reg)? as u32 as u64;
+                let high = load(reg + 1)? as u32 as u64;
+                Ok((high << 32) | low)
+            }
+        }
+    }
+
+    /// Write a value of the provided format to a register.
+    pub(crate) fn write(
+        &self,
+        registers: &mut RegisterFile,
+        reg: u8,
+        format: Format,
+        value: u64,
+    ) -> Result<(), ProcessorException> {
+        let mut store = |reg: u8, value: i64| -> Result<(), ProcessorException> {
+            registers
+                .get_mut(&reg)
+                .ok_or(ProcessorException::IllegalInstruction)?
+                .store(value)?;
+            Ok(())
+        };
+
+        match (self, format) {
+            (RegisterBank::Float, _) => {
+                let boxed = (value & format.mask()) | !format.mask();
+                store(FLOAT_REGISTER_BASE + reg, boxed as i64)
+            }
+            (RegisterBank::Integer(_), Format::Single) => store(reg, value as u32 as i32 as i64),
+            (RegisterBank::Integer(Xlen::Rv64), Format::Double) => store(reg, value as i64),
+            (RegisterBank::Integer(Xlen::Rv32), Format::Double) => {
+                Self::check_pair(reg)?;
+                if reg == 0 {
+                    return Ok(());
+                }
+                // Write the odd register first, so a missing register (e.g. on RV32E) leaves both
+                // registers unchanged
+                store(reg + 1, (value >> 32) as u32 as i32 as i64)?;
+                store(reg, value as u32 as i32 as i64)
+            }
+        }
+    }
+
+    /// Get the assembly name of a register.
+    pub(crate) fn name(&self, reg: u8) -> String {
+        match self {
+            RegisterBank::Float => format!("f{}", reg),
+            RegisterBank::Integer(_) => format!("x{}", reg),
+        }
+    }
+
+    /// Check that a register can hold the lower half of a register pair.
+    fn check_pair(reg: u8) -> Result<(), ProcessorException> {
+        match reg % 2 {
+            0 => Ok(()),
+            _ => Err(ProcessorException::IllegalInstruction),
+        }
+    }
+}
+
+/// Read the raw 64-bit value of a floating-point register.
+pub(crate) fn read_raw(registers: &RegisterFile, reg: u8) -> Result<u64, ProcessorException> {
+    Ok(registers
+        .get(&(FLOAT_REGISTER_BASE + reg))
+        .ok_or(ProcessorException::IllegalInstruction)?
+        .load()? as u64)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::d::D;
+    use crate::f::F;
+    use crate::rv32i::RV32I;
+    use crate::rv64i::RV64I;
+    use crate::test_utils::{processor, reg};
+    use crate::zicsr::Zicsr;
+    use z2l_core::error::ProcessorException;
+    use z2l_core::extension::Extension;
+    use z2l_core::instruction::InstructionParts;
+    use z2l_core::processor::hart::Hart;
+    use z2l_core::processor::register::FLOAT_REGISTER_BASE;
+    use z2l_core::processor::Processor;
+
+    /// Run the provided program to completion on an RV32IFD processor, returning the processor.
+    fn run(program: &[u32]) -> Processor {
+        let extensions: Vec<Box<dyn Extension>> =
+            vec![Box::new(RV32I), Box::new(F), Box::new(D), Box::new(Zicsr)];
+        let mut program = program.to_vec();
+        program.push(0x0000_006f); // jal x0, 0
+
+        let mut processor = processor(extensions, &program);
+        for _ in 0..program.len() {
+            processor.cycle().unwrap();
+        }
+        processor
+    }
+
+    /// Decode the provided instruction on a hart with the provided extensions.
+    fn decode(extensions: &[&dyn Extension], raw: u32) -> Result<String, ProcessorException> {
+        let mut hart = Hart::new();
+        for extension in extensions {
+            extension.register(&mut hart);
+        }
+        let parts = InstructionParts::new(raw)?;
+        let handler = hart.opcodes.get(&parts.opcode()).unwrap();
+        Ok(handler.decode(parts, 0, hart.xlen)?.format())
+    }
+
+    fn freg(processor: &Processor, n: u8) -> u64 {
+        reg(processor, FLOAT_REGISTER_BASE + n) as u64
+    }
+
+    #[test]
+    fn decode_instructions() {
+        let cases: &[(u32, &str)] = &[
+            (0x0041_2087, "flw f1, 0x00000004(x2)"),
+            (0x0011_3427, "fsd f1, 0x00000008(x2)"),
+            (0x0031_00d3, "fadd.s f1, f2, f3, rne"),
+            (0x0a31_10d3, "fsub.d f1, f2, f3, rtz"),
+            (0x1831_70d3, "fdiv.s f1, f2, f3"),
+            (0x5a01_00d3, "fsqrt.d f1, f2, rne"),
+            (0x2031_10d3, "fsgnjn.s f1, f2, f3"),
+            (0x2a31_10d3, "fmax.d f1, f2, f3"),
+            (0x2031_8243, "fmadd.s f4, f3, f3, f4, rne"),
+            (0x1031_f24f, "fnmadd.s f4, f3, f3, f2"),
+            (0x4011_70d3, "fcvt.s.d f1, f2"),
+            (0xa031_20d3, "feq.s x1, f2, f3"),
+            (0xc011_70d3, "fcvt.wu.s x1, f2"),
+            (0xd201_70d3, "fcvt.d.w f1, x2"),
+            (0xe001_00d3, "fmv.x.w x1, f2"),
+            (0xe201_10d3, "fclass.d x1, f2"),
+            (0xf001_00d3, "fmv.w.x f1, x2"),
+        ];
+        for (raw, expected) in cases {
+            assert_eq!(decode(&[&RV32I, &F, &D], *raw).unwrap(), *expected);
+        }
+
+        let illegal = [
+            0x0011_3427, // fsd f1, 8(x2) without D
+            0x0231_00d3, // fadd.d f1, f2, f3 without D
+            0x0031_50d3, // fadd.s f1, f2, f3 with rm = 0b101
+            0x5811_00d3, // fsqrt.s f1, f2 with rs2 != 0
+            0xc021_70d3, // fcvt.l.s x1, f2 on RV32
+        ];
+        for raw in illegal {
+            assert_eq!(
+                decode(&[&RV32I, &F], raw),
+                Err(ProcessorException::IllegalInstruction),
+                "{:08x}",
+                raw
+            );
+        }
+
+        // F doesn't replace the double-precision handlers if registered after D
+        assert_eq!(
+            decode(&[&RV32I, &D, &F], 0x0231_00d3).unwrap(),
+            "fadd.d f1, f2, f3, rne"
+        );
+
+        // FMV.X.D & the 64-bit integer conversions are only available on RV64
+        assert!(decode(&[&RV32I, &F, &D], 0xe201_00d3).is_err());
+        assert_eq!(
+            decode(&[&RV64I, &F, &D], 0xe201_00d3).unwrap(),
+            "fmv.x.d x1, f2"
+        );
+        assert_eq!(
+            decode(&[&RV64I, &F], 0xd031_70d3).unwrap(),
+            "fcvt.s.lu f1, x2"
+        );
+    }
+
+    #[test]
+    fn nan_boxing() {
+        let processor = run(&[
+            0x0010_0093, // addi x1, x0, 1
+            0xd000_f0d3, // fcvt.s.w f1, x1
+            0xd200_f153, // fcvt.d.w f2, x1
+            0x2021_01d3, // fsgnj.s f3, f2, f2
+            0x0010_8253, // fadd.s f4, f1, f1
+            0xe000_0153, // fmv.x.w x2, f0
+        ]);
+
+        assert_eq!(freg(&processor, 1), 0xffff_ffff_3f80_0000);
+        assert_eq!(freg(&processor, 2), 0x3ff0_0000_0000_0000);
+        // f2 isn't a NaN-boxed single, so is read as the canonical NaN
+        assert_eq!(freg(&processor, 3), 0xffff_ffff_7fc0_0000);
+        assert_eq!(freg(&processor, 4), 0xffff_ffff_4000_0000);
+        assert_eq!(reg(&processor, 2), 0);
+    }
+
+    #[test]
+    fn fcsr() {
+        let processor = run(&[
+            0x0010_0093, // addi x1, x0, 1
+            0x0030_0113, // addi x2, x0, 3
+            0xd000_f0d3, // fcvt.s.w f1, x1
+            0xd001_7153, // fcvt.s.w f2, x2
+            0x0020_21f3, // csrrs x3, frm, x0
+            0x0020_d073, // csrrwi x0, frm, 1
+            0x1820_f1d3, // fdiv.s f3, f1, f2
+            0x0030_2273, // csrrs x4, fcsr, x0
+            0x1820_8253, // fdiv.s f4, f1, f2, rne
+            0x0010_12f3, // csrrw x5, fflags, x0
+            0x0030_2373, // csrrs x6, fcsr, x0
+        ]);
+
+        assert_eq!(reg(&processor, 3), 0);
+        // 1/3 rounded towards zero (frm = 1) & to nearest differ in the last bit
+        assert_eq!(freg(&processor, 3), 0xffff_ffff_3eaa_aaaa);
+        assert_eq!(freg(&processor, 4), 0xffff_ffff_3eaa_aaab);
+        // frm = RTZ, NX accrued
+        assert_eq!(reg(&processor, 4), 0b001_00001);
+        assert_eq!(reg(&processor, 5), 0b00001);
+        assert_eq!(reg(&processor, 6), 0b001_00000);
+    }
+
+    #[test]
+    fn invalid_dynamic_rounding_mode() {
+        let extensions: Vec<Box<dyn Extension>> =
+            vec![Box::new(RV32I), Box::new(F), Box::new(Zicsr)];
+        let mut processor = processor(
+            extensions,
+            &[
+                0x0020_d073, // csrrwi x0, frm, 1
+                0x0022_d073, // csrrwi x0, frm, 5
+                0x0000_70d3, // fadd.s f1, f0, f0
+            ],
+        );
+        processor.cycle().unwrap();
+        processor.cycle().unwrap();
+        processor.cycle().unwrap();
+        assert_eq!(
+            processor.cycle(),
+            Err((ProcessorException::IllegalInstruction, 8))
+        );
+    }
+
+    #[test]
+    fn load_store() {
+        let processor = run(&[
+            0x0010_0093, // addi x1, x0, 1
+            0x01f0_9093, // slli x1, x1, 31
+            0xbf80_0137, // lui x2, 0xbf800
+            0x0020_a023, // sw x2, 0(x1)
+            0xfff0_0193, // addi x3, x0, -1
+            0x0030_a223, // sw x3, 4(x1)
+            0x0000_a087, // flw f1, 0(x1)
+            0x0000_b107, // fld f2, 0(x1)
+            0x0010_b427, // fsd f1, 8(x1)
+            0x0080_a203, // lw x4, 8(x1)
+            0x00c0_a283, // lw x5, 12(x1)
+            0x0020_a827, // fsw f2, 16(x1)
+            0x0100_a303, // lw x6, 16(x1)
+        ]);
+
+        assert_eq!(freg(&processor, 1), 0xffff_ffff_bf80_0000);
+        assert_eq!(freg(&processor, 2), 0xffff_ffff_bf80_0000);
+        assert_eq!(reg(&processor, 4) as u32, 0xbf80_0000);
+        assert_eq!(reg(&processor, 5), -1);
+        assert_eq!(reg(&processor, 6) as u32, 0xbf80_0000);
+    }
+
+    #[test]
+    fn conversions() {
+        let processor = run(&[
+            0xff90_0093, // addi x1, x0, -7
+            0xd010_f0d3, // fcvt.s.wu f1, x1
+            0xc000_f153, // fcvt.w.s x2, f1
+            0xc010_f1d3, // fcvt.wu.s x3, f1
+            0xd200_f253, // fcvt.d.w f4, x1
+            0xc202_1253, // fcvt.w.d x4, f4, rtz
+            0xc212_12d3, // fcvt.wu.d x5, f4, rtz
+            0x4202_02d3, // fcvt.d.s f5, f4
+            0xe002_9353, // fclass.s x6, f5
+            0x0030_2073, // csrrs x0, fcsr, x0
+            0x0010_13f3, // csrrw x7, fflags, x0
+        ]);
+
+        // -7 as an unsigned integer rounds to 2^32
+        assert_eq!(freg(&processor, 1), 0xffff_ffff_4f80_0000);
+        assert_eq!(reg(&processor, 2), i32::MAX as i64);
+        assert_eq!(reg(&processor, 3), -1);
+        assert_eq!(freg(&processor, 4), (-7.0f64).to_bits());
+        assert_eq!(reg(&processor, 4), -7);
+        assert_eq!(reg(&processor, 5), 0);
+        // f4 isn't NaN-boxed, so FCVT.D.S converts the canonical NaN
+        assert_eq!(freg(&processor, 5), 0x7ff8_0000_0000_0000);
+        assert_eq!(reg(&processor, 6), 1 << 9);
+        assert_eq!(reg(&processor, 7), 0b10001);
+    }
+}
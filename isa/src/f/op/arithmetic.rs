@@ -0,0 +1,177 @@
+//! Floating-point arithmetic instructions (FADD, FSUB, FMUL, FDIV, FMIN, FMAX, FSQRT).
+//!
+//! FADD, FSUB, FMUL, and FDIV round their result according to the instruction's rounding mode.
+//! FMIN and FMAX return the smaller or larger of their operands: If only one operand is a NaN, the
+//! other operand is returned, and -0.0 is considered smaller than +0.0.
+
+use crate::f::softfloat::{FloatEnv, Format, RoundingMode};
+use crate::f::{fcsr, RegisterBank, Rounding};
+use std::fmt;
+use z2l_core::error::ProcessorException;
+use z2l_core::instruction::{Instruction, InstructionResult, InstructionWordParts};
+use z2l_core::processor::register::RegisterFile;
+
+/// Operation to perform.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+enum Operation {
+    Add,
+    Sub,
+    Mul,
+    Div,
+    Min,
+    Max,
+}
+
+impl fmt::Display for Operation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Operation::Add => f.write_str("fadd"),
+            Operation::Sub => f.write_str("fsub"),
+            Operation::Mul => f.write_str("fmul"),
+            Operation::Div => f.write_str("fdiv"),
+            Operation::Min => f.write_str("fmin"),
+            Operation::Max => f.write_str("fmax"),
+        }
+    }
+}
+
+/// FADD, FSUB, FMUL, FDIV, FMIN, or FMAX instruction.
+pub struct ArithmeticInstruction {
+    src1: u8,
+    src2: u8,
+    dest: u8,
+    op: Operation,
+    format: Format,
+    rounding: Option<Rounding>,
+    bank: RegisterBank,
+}
+
+impl ArithmeticInstruction {
+    /// Create a new ArithmeticInstruction, operating on the provided format in the provided
+    /// registers.
+    pub fn new(
+        instruction: &InstructionWordParts,
+        format: Format,
+        bank: RegisterBank,
+    ) -> Result<Self, ProcessorException> {
+        let (op, rounding) = match (instruction.funct7 >> 2, instruction.funct3) {
+            (0b00000, rm) => (Operation::Add, Some(Rounding::new(rm)?)),
+            (0b00001, rm) => (Operation::Sub, Some(Rounding::new(rm)?)),
+            (0b00010, rm) => (Operation::Mul, Some(Rounding::new(rm)?)),
+            (0b00011, rm) => (Operation::Div, Some(Rounding::new(rm)?)),
+            (0b00101, 0b000) => (Operation::Min, None),
+            (0b00101, 0b001) => (Operation::Max, None),
+            _ => return Err(ProcessorException::IllegalInstruction),
+        };
+
+        Ok(Self {
+            src1: instruction.rs1,
+            src2: instruction.rs2,
+            dest: instruction.rd,
+            op,
+            format,
+            rounding,
+            bank,
+        })
+    }
+}
+
+impl Instruction for ArithmeticInstruction {
+    fn execute(
+        &self,
+        registers: &mut RegisterFile,
+        _mem: i64,
+    ) -> Result<InstructionResult, ProcessorException> {
+        let src1 = self.bank.read(registers, self.src1, self.format)?;
+        let src2 = self.bank.read(registers, self.src2, self.format)?;
+
+        // FMIN & FMAX don't round, so don't depend on `frm`
+        let mut env = match self.rounding {
+            Some(rounding) => fcsr::env(registers, rounding)?,
+            None => FloatEnv::new(RoundingMode::NearestEven),
+        };
+        let format = self.format;
+        let result = match self.op {
+            Operation::Add => env.add(format, src1, src2),
+            Operation::Sub => env.sub(format, src1, src2),
+            Operation::Mul => env.mul(format, src1, src2),
+            Operation::Div => env.div(format, src1, src2),
+            Operation::Min => env.min_max(format, src1, src2, false),
+            Operation::Max => env.min_max(format, src1, src2, true),
+        };
+
+        self.bank.write(registers, self.dest, format, result)?;
+        fcsr::accrue(registers, &env)?;
+        Ok(InstructionResult::default())
+    }
+
+    fn format(&self) -> String {
+        let rounding = self.rounding.map(|r| r.to_string()).unwrap_or_default();
+        format!(
+            "{}.{} {}, {}, {}{}",
+            self.op,
+            self.format,
+            self.bank.name(self.dest),
+            self.bank.name(self.src1),
+            self.bank.name(self.src2),
+            rounding
+        )
+    }
+}
+
+/// FSQRT instruction.
+pub struct SqrtInstruction {
+    src: u8,
+    dest: u8,
+    format: Format,
+    rounding: Rounding,
+    bank: RegisterBank,
+}
+
+impl SqrtInstruction {
+    /// Create a new SqrtInstruction, operating on the provided format in the provided
+    /// registers.
+    pub fn new(
+        instruction: &InstructionWordParts,
+        format: Format,
+        bank: RegisterBank,
+    ) -> Result<Self, ProcessorException> {
+        if instruction.rs2 != 0 {
+            return Err(ProcessorException::IllegalInstruction);
+        }
+
+        Ok(Self {
+            src: instruction.rs1,
+            dest: instruction.rd,
+            format,
+            rounding: Rounding::new(instruction.funct3)?,
+            bank,
+        })
+    }
+}
+
+impl Instruction for SqrtInstruction {
+    fn execute(
+        &self,
+        registers: &mut RegisterFile,
+        _mem: i64,
+    ) -> Result<InstructionResult, ProcessorException> {
+        let mut env = fcsr::env(registers, self.rounding)?;
+        let src = self.bank.read(registers, self.src, self.format)?;
+        let result = env.sqrt(self.format, src);
+
+        self.bank.write(registers, self.dest, self.format, result)?;
+        fcsr::accrue(registers, &env)?;
+        Ok(InstructionResult::default())
+    }
+
+    fn format(&self) -> String {
+        format!(
+            "fsqrt.{} {}, {}{}",
+            self.format,
+            self.bank.name(self.dest),
+            self.bank.name(self.src),
+            self.rounding
+        )
+    }
+}
@@ -0,0 +1,161 @@
+//! Comparison and classification instructions (FEQ, FLT, FLE, FCLASS).
+//!
+//! These instructions write their result to an integer register. FEQ is a quiet comparison, only
+//! raising the invalid operation exception for signaling NaNs, while FLT and FLE are signaling
+//! comparisons, raising it for any NaN. All three write 0 if either operand is a NaN.
+//!
+//! FCLASS writes a mask with a single bit set, identifying the class of its operand, and never
+//! raises exceptions.
+
+use crate::f::softfloat::{self, FloatEnv, Format, RoundingMode};
+use crate::f::{fcsr, RegisterBank};
+use std::fmt;
+use z2l_core::error::ProcessorException;
+use z2l_core::instruction::{Instruction, InstructionResult, InstructionWordParts};
+use z2l_core::processor::register::RegisterFile;
+
+/// Comparison to perform.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+enum Comparison {
+    Equal,
+    LessThan,
+    LessThanOrEqual,
+}
+
+impl fmt::Display for Comparison {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Comparison::Equal => f.write_str("feq"),
+            Comparison::LessThan => f.write_str("flt"),
+            Comparison::LessThanOrEqual => f.write_str("fle"),
+        }
+    }
+}
+
+/// FEQ, FLT, or FLE instruction.
+pub struct CompareInstruction {
+    src1: u8,
+    src2: u8,
+    dest: u8,
+    comparison: Comparison,
+    format: Format,
+    bank: RegisterBank,
+}
+
+impl CompareInstruction {
+    /// Create a new CompareInstruction, operating on the provided format in the provided
+    /// registers.
+    pub fn new(
+        instruction: &InstructionWordParts,
+        format: Format,
+        bank: RegisterBank,
+    ) -> Result<Self, ProcessorException> {
+        let comparison = match instruction.funct3 {
+            0b000 => Comparison::LessThanOrEqual,
+            0b001 => Comparison::LessThan,
+            0b010 => Comparison::Equal,
+            _ => return Err(ProcessorException::IllegalInstruction),
+        };
+
+        Ok(Self {
+            src1: instruction.rs1,
+            src2: instruction.rs2,
+            dest: instruction.rd,
+            comparison,
+            format,
+            bank,
+        })
+    }
+}
+
+impl Instruction for CompareInstruction {
+    fn execute(
+        &self,
+        registers: &mut RegisterFile,
+        _mem: i64,
+    ) -> Result<InstructionResult, ProcessorException> {
+        let src1 = self.bank.read(registers, self.src1, self.format)?;
+        let src2 = self.bank.read(registers, self.src2, self.format)?;
+
+        // Comparisons don't round, so don't depend on `frm`
+        let mut env = FloatEnv::new(RoundingMode::NearestEven);
+        let result = match self.comparison {
+            Comparison::Equal => env.equal(self.format, src1, src2),
+            Comparison::LessThan => env.lt(self.format, src1, src2, false),
+            Comparison::LessThanOrEqual => env.lt(self.format, src1, src2, true),
+        };
+
+        registers
+            .get_mut(&self.dest)
+            .ok_or(ProcessorException::IllegalInstruction)?
+            .store(result as i64)?;
+        fcsr::accrue(registers, &env)?;
+        Ok(InstructionResult::default())
+    }
+
+    fn format(&self) -> String {
+        format!(
+            "{}.{} x{}, {}, {}",
+            self.comparison,
+            self.format,
+            self.dest,
+            self.bank.name(self.src1),
+            self.bank.name(self.src2)
+        )
+    }
+}
+
+/// FCLASS instruction.
+pub struct ClassifyInstruction {
+    src: u8,
+    dest: u8,
+    format: Format,
+    bank: RegisterBank,
+}
+
+impl ClassifyInstruction {
+    /// Create a new ClassifyInstruction, operating on the provided format in the provided
+    /// registers.
+    pub fn new(
+        instruction: &InstructionWordParts,
+        format: Format,
+        bank: RegisterBank,
+    ) -> Result<Self, ProcessorException> {
+        if instruction.rs2 != 0 || instruction.funct3 != 0b001 {
+            return Err(ProcessorException::IllegalInstruction);
+        }
+
+        Ok(Self {
+            src: instruction.rs1,
+            dest: instruction.rd,
+            format,
+            bank,
+        })
+    }
+}
+
+impl Instruction for ClassifyInstruction {
+    fn execute(
+        &self,
+        registers: &mut RegisterFile,
+        _mem: i64,
+    ) -> Result<InstructionResult, ProcessorException> {
+        let src = self.bank.read(registers, self.src, self.format)?;
+        let result = softfloat::classify(self.format, src);
+
+        registers
+            .get_mut(&self.dest)
+            .ok_or(ProcessorException::IllegalInstruction)?
+            .store(result as i64)?;
+        Ok(InstructionResult::default())
+    }
+
+    fn format(&self) -> String {
+        format!(
+            "fclass.{} x{}, {}",
+            self.format,
+            self.dest,
+            self.bank.name(self.src)
+        )
+    }
+}
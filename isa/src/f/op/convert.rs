@@ -0,0 +1,269 @@
+//! Conversion instructions (FCVT).
+//!
+//! FCVT.int.fmt converts a floating-point value to a signed or unsigned 32-bit (W, WU) or 64-bit
+//! (L, LU, RV64 only) integer, writing the result to an integer register. Out of range values and
+//! NaNs raise the invalid operation exception, and saturate to the nearest representable integer
+//! (NaNs to the largest). 32-bit results are sign-extended, even for FCVT.WU.
+//!
+//! FCVT.fmt.int converts an integer to a floating-point value, and FCVT.fmt.fmt converts between
+//! floating-point formats. All conversions round according to the instruction's rounding mode.
+
+use crate::f::softfloat::Format;
+use crate::f::{fcsr, format, RegisterBank, Rounding};
+use std::fmt;
+use z2l_core::error::ProcessorException;
+use z2l_core::instruction::{Instruction, InstructionResult, InstructionWordParts};
+use z2l_core::processor::register::RegisterFile;
+use z2l_core::processor::Xlen;
+
+/// Integer type being converted to or from.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+enum IntType {
+    Word,
+    UnsignedWord,
+    Long,
+    UnsignedLong,
+}
+
+impl IntType {
+    /// Decode the integer type from the rs2 field of an instruction.
+    ///
+    /// Raises an IllegalInstruction exception for 64-bit types on RV32.
+    fn new(rs2: u8, xlen: Xlen) -> Result<Self, ProcessorException> {
+        match (rs2, xlen) {
+            (0b00000, _) => Ok(IntType::Word),
+            (0b00001, _) => Ok(IntType::UnsignedWord),
+            (0b00010, Xlen::Rv64) => Ok(IntType::Long),
+            (0b00011, Xlen::Rv64) => Ok(IntType::UnsignedLong),
+            _ => Err(ProcessorException::IllegalInstruction),
+        }
+    }
+
+    /// Width of the type, in bits.
+    fn bits(&self) -> u32 {
+        match self {
+            IntType::Word | IntType::UnsignedWord => 32,
+            IntType::Long | IntType::UnsignedLong => 64,
+        }
+    }
+
+    /// Whether the type is signed.
+    fn signed(&self) -> bool {
+        matches!(self, IntType::Word | IntType::Long)
+    }
+
+    /// Interpret the value of an integer register as this type.
+    fn read(&self, value: i64) -> i128 {
+        match self {
+            IntType::Word => value as i32 as i128,
+            IntType::UnsignedWord => value as u32 as i128,
+            IntType::Long => value as i128,
+            IntType::UnsignedLong => value as u64 as i128,
+        }
+    }
+
+    /// Convert a value of this type to the value of an integer register.
+    ///
+    /// 32-bit values are sign-extended, regardless of the signedness of the type.
+    fn write(&self, value: i128) -> i64 {
+        match self {
+            IntType::Word | IntType::UnsignedWord => value as i32 as i64,
+            IntType::Long | IntType::UnsignedLong => value as i64,
+        }
+    }
+}
+
+impl fmt::Display for IntType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            IntType::Word => f.write_str("w"),
+            IntType::UnsignedWord => f.write_str("wu"),
+            IntType::Long => f.write_str("l"),
+            IntType::UnsignedLong => f.write_str("lu"),
+        }
+    }
+}
+
+/// FCVT.W, FCVT.WU, FCVT.L, or FCVT.LU instruction.
+pub struct ConvertToIntInstruction {
+    src: u8,
+    dest: u8,
+    int_type: IntType,
+    format: Format,
+    rounding: Rounding,
+    bank: RegisterBank,
+}
+
+impl ConvertToIntInstruction {
+    /// Create a new ConvertToIntInstruction, converting from the provided format in the provided
+    /// registers, for a hart with the provided XLEN.
+    pub fn new(
+        instruction: &InstructionWordParts,
+        format: Format,
+        xlen: Xlen,
+        bank: RegisterBank,
+    ) -> Result<Self, ProcessorException> {
+        Ok(Self {
+            src: instruction.rs1,
+            dest: instruction.rd,
+            int_type: IntType::new(instruction.rs2, xlen)?,
+            format,
+            rounding: Rounding::new(instruction.funct3)?,
+            bank,
+        })
+    }
+}
+
+impl Instruction for ConvertToIntInstruction {
+    fn execute(
+        &self,
+        registers: &mut RegisterFile,
+        _mem: i64,
+    ) -> Result<InstructionResult, ProcessorException> {
+        let mut env = fcsr::env(registers, self.rounding)?;
+        let src = self.bank.read(registers, self.src, self.format)?;
+        let int_type = self.int_type;
+        let result = env.to_int(self.format, src, int_type.bits(), int_type.signed());
+
+        registers
+            .get_mut(&self.dest)
+            .ok_or(ProcessorException::IllegalInstruction)?
+            .store(int_type.write(result))?;
+        fcsr::accrue(registers, &env)?;
+        Ok(InstructionResult::default())
+    }
+
+    fn format(&self) -> String {
+        format!(
+            "fcvt.{}.{} x{}, {}{}",
+            self.int_type,
+            self.format,
+            self.dest,
+            self.bank.name(self.src),
+            self.rounding
+        )
+    }
+}
+
+/// FCVT.fmt.W, FCVT.fmt.WU, FCVT.fmt.L, or FCVT.fmt.LU instruction.
+pub struct ConvertFromIntInstruction {
+    src: u8,
+    dest: u8,
+    int_type: IntType,
+    format: Format,
+    rounding: Rounding,
+    bank: RegisterBank,
+}
+
+impl ConvertFromIntInstruction {
+    /// Create a new ConvertFromIntInstruction, converting to the provided format in the provided
+    /// registers, for a hart with the provided XLEN.
+    pub fn new(
+        instruction: &InstructionWordParts,
+        format: Format,
+        xlen: Xlen,
+        bank: RegisterBank,
+    ) -> Result<Self, ProcessorException> {
+        Ok(Self {
+            src: instruction.rs1,
+            dest: instruction.rd,
+            int_type: IntType::new(instruction.rs2, xlen)?,
+            format,
+            rounding: Rounding::new(instruction.funct3)?,
+            bank,
+        })
+    }
+}
+
+impl Instruction for ConvertFromIntInstruction {
+    fn execute(
+        &self,
+        registers: &mut RegisterFile,
+        _mem: i64,
+    ) -> Result<InstructionResult, ProcessorException> {
+        let mut env = fcsr::env(registers, self.rounding)?;
+        let src = registers
+            .get(&self.src)
+            .ok_or(ProcessorException::IllegalInstruction)?
+            .load()?;
+        let result = env.from_int(self.format, self.int_type.read(src));
+
+        self.bank.write(registers, self.dest, self.format, result)?;
+        fcsr::accrue(registers, &env)?;
+        Ok(InstructionResult::default())
+    }
+
+    fn format(&self) -> String {
+        format!(
+            "fcvt.{}.{} {}, x{}{}",
+            self.format,
+            self.int_type,
+            self.bank.name(self.dest),
+            self.src,
+            self.rounding
+        )
+    }
+}
+
+/// FCVT.S.D or FCVT.D.S instruction.
+pub struct ConvertFormatInstruction {
+    src: u8,
+    dest: u8,
+    from: Format,
+    to: Format,
+    rounding: Rounding,
+    bank: RegisterBank,
+}
+
+impl ConvertFormatInstruction {
+    /// Create a new ConvertFormatInstruction, converting to the provided format, and supporting
+    /// formats up to the provided width in the provided registers.
+    pub fn new(
+        instruction: &InstructionWordParts,
+        to: Format,
+        flen: Format,
+        bank: RegisterBank,
+    ) -> Result<Self, ProcessorException> {
+        // The source format is encoded in rs2, & must differ from the destination format
+        let from = format(instruction.rs2, flen)?;
+        if from == to {
+            return Err(ProcessorException::IllegalInstruction);
+        }
+
+        Ok(Self {
+            src: instruction.rs1,
+            dest: instruction.rd,
+            from,
+            to,
+            rounding: Rounding::new(instruction.funct3)?,
+            bank,
+        })
+    }
+}
+
+impl Instruction for ConvertFormatInstruction {
+    fn execute(
+        &self,
+        registers: &mut RegisterFile,
+        _mem: i64,
+    ) -> Result<InstructionResult, ProcessorException> {
+        let mut env = fcsr::env(registers, self.rounding)?;
+        let src = self.bank.read(registers, self.src, self.from)?;
+        let result = env.convert(self.from, self.to, src);
+
+        self.bank.write(registers, self.dest, self.to, result)?;
+        fcsr::accrue(registers, &env)?;
+        Ok(InstructionResult::default())
+    }
+
+    fn format(&self) -> String {
+        format!(
+            "fcvt.{}.{} {}, {}{}",
+            self.to,
+            self.from,
+            self.bank.name(self.dest),
+            self.bank.name(self.src),
+            self.rounding
+        )
+    }
+}
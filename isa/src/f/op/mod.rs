@@ -0,0 +1,86 @@
+//! OP-FP opcode instructions.
+//!
+//! OP-FP instructions perform floating-point computations, comparisons, and conversions. The
+//! operation is selected by the upper 5 bits of funct7, and the format of the operands by the
+//! lowest 2 bits (`fmt`). Where an instruction rounds its result, funct3 selects the rounding mode;
+//! otherwise, it may select between related operations.
+
+mod arithmetic;
+mod compare;
+mod convert;
+mod mv;
+mod sign;
+
+pub use arithmetic::{ArithmeticInstruction, SqrtInstruction};
+pub use compare::{ClassifyInstruction, CompareInstruction};
+pub use convert::{ConvertFormatInstruction, ConvertFromIntInstruction, ConvertToIntInstruction};
+pub use mv::{MoveFromIntInstruction, MoveToIntInstruction};
+pub use sign::SignInjectionInstruction;
+
+use super::softfloat::Format;
+use super::{format, Operands, RegisterBank};
+use z2l_core::error::ProcessorException;
+use z2l_core::extension::OpcodeHandler;
+use z2l_core::instruction::{Instruction, InstructionParts};
+use z2l_core::processor::Xlen;
+
+/// OP-FP opcode handler.
+pub struct OpFpHandler {
+    flen: Format,
+    operands: Operands,
+}
+
+impl OpFpHandler {
+    /// Create a new OpFpHandler, supporting formats up to the provided width in the provided
+    /// registers.
+    pub fn new(flen: Format, operands: Operands) -> Self {
+        Self { flen, operands }
+    }
+}
+
+impl OpcodeHandler for OpFpHandler {
+    fn decode(
+        &self,
+        instruction: InstructionParts,
+        _pc: u64,
+        xlen: Xlen,
+    ) -> Result<Box<dyn Instruction>, ProcessorException> {
+        let instruction = instruction.into_word()?;
+        let fmt = format(instruction.funct7 & 0b11, self.flen)?;
+        let bank = self.operands.bank(xlen);
+
+        Ok(match instruction.funct7 >> 2 {
+            0b00000..=0b00011 | 0b00101 => {
+                Box::new(ArithmeticInstruction::new(&instruction, fmt, bank)?)
+            }
+            0b01011 => Box::new(SqrtInstruction::new(&instruction, fmt, bank)?),
+            0b00100 => Box::new(SignInjectionInstruction::new(&instruction, fmt, bank)?),
+            0b01000 => Box::new(ConvertFormatInstruction::new(
+                &instruction,
+                fmt,
+                self.flen,
+                bank,
+            )?),
+            0b10100 => Box::new(CompareInstruction::new(&instruction, fmt, bank)?),
+            0b11000 => Box::new(ConvertToIntInstruction::new(&instruction, fmt, xlen, bank)?),
+            0b11010 => Box::new(ConvertFromIntInstruction::new(
+                &instruction,
+                fmt,
+                xlen,
+                bank,
+            )?),
+            0b11100 if instruction.funct3 == 0b001 => {
+                Box::new(ClassifyInstruction::new(&instruction, fmt, bank)?)
+            }
+            // Values in integer registers don't need to be moved, so FMV is only available with the
+            // floating-point registers
+            0b11100 if bank == RegisterBank::Float => {
+                Box::new(MoveToIntInstruction::new(&instruction, fmt, xlen)?)
+            }
+            0b11110 if bank == RegisterBank::Float => {
+                Box::new(MoveFromIntInstruction::new(&instruction, fmt, xlen)?)
+            }
+            _ => return Err(ProcessorException::IllegalInstruction),
+        })
+    }
+}
@@ -0,0 +1,138 @@
+//! Move instructions (FMV.X.W, FMV.W.X, and on RV64, FMV.X.D, FMV.D.X).
+//!
+//! These instructions copy the bit pattern of a value between an integer register and a
+//! floating-point register, without interpreting it. FMV.X.W sign-extends the 32-bit value, and
+//! FMV.W.X NaN-boxes it. The double-precision moves are only available on RV64, where the value
+//! fits in an integer register.
+
+use crate::f::softfloat::Format;
+use crate::f::{read_raw, RegisterBank};
+use z2l_core::error::ProcessorException;
+use z2l_core::instruction::{Instruction, InstructionResult, InstructionWordParts};
+use z2l_core::processor::register::RegisterFile;
+use z2l_core::processor::Xlen;
+
+/// Get the suffix of a move instruction for the provided format.
+fn suffix(format: Format) -> &'static str {
+    match format {
+        Format::Single => "w",
+        Format::Double => "d",
+    }
+}
+
+/// Check the fields of a move instruction, which must have rs2 = 0 and funct3 = 0.
+///
+/// Raises an IllegalInstruction exception if not, or if the format doesn't fit in an integer
+/// register.
+fn check(
+    instruction: &InstructionWordParts,
+    format: Format,
+    xlen: Xlen,
+) -> Result<(), ProcessorException> {
+    match (instruction.rs2, instruction.funct3, format, xlen) {
+        (0, 0b000, Format::Single, _) | (0, 0b000, Format::Double, Xlen::Rv64) => Ok(()),
+        _ => Err(ProcessorException::IllegalInstruction),
+    }
+}
+
+/// FMV.X.W or FMV.X.D instruction.
+pub struct MoveToIntInstruction {
+    src: u8,
+    dest: u8,
+    format: Format,
+}
+
+impl MoveToIntInstruction {
+    /// Create a new MoveToIntInstruction, moving a value of the provided format, for a hart with
+    /// the provided XLEN.
+    pub fn new(
+        instruction: &InstructionWordParts,
+        format: Format,
+        xlen: Xlen,
+    ) -> Result<Self, ProcessorException> {
+        check(instruction, format, xlen)?;
+        Ok(Self {
+            src: instruction.rs1,
+            dest: instruction.rd,
+            format,
+        })
+    }
+}
+
+impl Instruction for MoveToIntInstruction {
+    fn execute(
+        &self,
+        registers: &mut RegisterFile,
+        _mem: i64,
+    ) -> Result<InstructionResult, ProcessorException> {
+        let src = read_raw(registers, self.src)?;
+        let result = match self.format {
+            Format::Single => src as i32 as i64,
+            Format::Double => src as i64,
+        };
+
+        registers
+            .get_mut(&self.dest)
+            .ok_or(ProcessorException::IllegalInstruction)?
+            .store(result)?;
+        Ok(InstructionResult::default())
+    }
+
+    fn format(&self) -> String {
+        format!(
+            "fmv.x.{} x{}, f{}",
+            suffix(self.format),
+            self.dest,
+            self.src
+        )
+    }
+}
+
+/// FMV.W.X or FMV.D.X instruction.
+pub struct MoveFromIntInstruction {
+    src: u8,
+    dest: u8,
+    format: Format,
+}
+
+impl MoveFromIntInstruction {
+    /// Create a new MoveFromIntInstruction, moving a value of the provided format, for a hart with
+    /// the provided XLEN.
+    pub fn new(
+        instruction: &InstructionWordParts,
+        format: Format,
+        xlen: Xlen,
+    ) -> Result<Self, ProcessorException> {
+        check(instruction, format, xlen)?;
+        Ok(Self {
+            src: instruction.rs1,
+            dest: instruction.rd,
+            format,
+        })
+    }
+}
+
+impl Instruction for MoveFromIntInstruction {
+    fn execute(
+        &self,
+        registers: &mut RegisterFile,
+        _mem: i64,
+    ) -> Result<InstructionResult, ProcessorException> {
+        let src = registers
+            .get(&self.src)
+            .ok_or(ProcessorException::IllegalInstruction)?
+            .load()?;
+
+        RegisterBank::Float.write(registers, self.dest, self.format, src as u64)?;
+        Ok(InstructionResult::default())
+    }
+
+    fn format(&self) -> String {
+        format!(
+            "fmv.{}.x f{}, x{}",
+            suffix(self.format),
+            self.dest,
+            self.src
+        )
+    }
+}
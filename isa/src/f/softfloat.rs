@@ -0,0 +1,1127 @@
+//! Software implementation of IEEE 754 binary floating-point arithmetic.
+//!
+//! The host's floating-point unit can't be used to implement the floating-point extensions: Rust
+//! provides no way to select a rounding mode or to read the accrued exception flags, and some hosts
+//! (e.g. x87) don't round to single/double precision correctly. Instead, values are handled as raw
+//! bit patterns. Each operation computes its exact result as an integer significand & exponent (or
+//! a result with enough extra precision that a "sticky" bit records whether any non-zero bits were
+//! discarded), which is then rounded to the destination format according to the rounding mode.
+//!
+//! NaN results are always the canonical NaN, as RISC-V does not propagate NaN payloads. Tininess is
+//! detected after rounding, as RISC-V requires.
+
+use std::fmt;
+
+/// Invalid operation exception flag.
+pub const INVALID: u8 = 0x10;
+
+/// Divide by zero exception flag.
+pub const DIVIDE_BY_ZERO: u8 = 0x08;
+
+/// Overflow exception flag.
+pub const OVERFLOW: u8 = 0x04;
+
+/// Underflow exception flag.
+pub const UNDERFLOW: u8 = 0x02;
+
+/// Inexact exception flag.
+pub const INEXACT: u8 = 0x01;
+
+/// A binary floating-point format.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum Format {
+    /// IEEE 754 binary32, used by the F extension.
+    Single,
+
+    /// IEEE 754 binary64, used by the D extension.
+    Double,
+}
+
+impl Format {
+    /// Width of a value in this format, in bits.
+    pub fn bits(&self) -> u32 {
+        match self {
+            Format::Single => 32,
+            Format::Double => 64,
+        }
+    }
+
+    /// Number of bits in the biased exponent field.
+    fn exponent_bits(&self) -> u32 {
+        match self {
+            Format::Single => 8,
+            Format::Double => 11,
+        }
+    }
+
+    /// Number of bits in the trailing significand field.
+    fn fraction_bits(&self) -> u32 {
+        self.bits() - self.exponent_bits() - 1
+    }
+
+    /// Exponent bias.
+    fn bias(&self) -> i32 {
+        (1 << (self.exponent_bits() - 1)) - 1
+    }
+
+    /// Biased exponent of infinities & NaNs.
+    fn max_exponent(&self) -> u64 {
+        (1 << self.exponent_bits()) - 1
+    }
+
+    /// Mask of the bits used by a value in this format.
+    pub fn mask(&self) -> u64 {
+        u64::MAX >> (64 - self.bits())
+    }
+
+    /// The sign bit.
+    fn sign_bit(&self) -> u64 {
+        1 << (self.bits() - 1)
+    }
+
+    /// The canonical NaN, which is returned by any operation producing a NaN.
+    pub fn canonical_nan(&self) -> u64 {
+        (self.max_exponent() << self.fraction_bits()) | (1 << (self.fraction_bits() - 1))
+    }
+
+    /// Infinity of the provided sign.
+    fn infinity(&self, sign: bool) -> u64 {
+        self.with_sign(sign, self.max_exponent() << self.fraction_bits())
+    }
+
+    /// Largest finite value of the provided sign.
+    fn max_finite(&self, sign: bool) -> u64 {
+        self.with_sign(sign, (self.max_exponent() << self.fraction_bits()) - 1)
+    }
+
+    /// Zero of the provided sign.
+    fn zero(&self, sign: bool) -> u64 {
+        self.with_sign(sign, 0)
+    }
+
+    /// Set the sign bit of a magnitude.
+    fn with_sign(&self, sign: bool, magnitude: u64) -> u64 {
+        if sign {
+            magnitude | self.sign_bit()
+        } else {
+            magnitude
+        }
+    }
+
+    /// Get the sign of a value.
+    fn sign(&self, value: u64) -> bool {
+        value & self.sign_bit() != 0
+    }
+
+    /// Classify a value.
+    pub fn classify(&self, value: u64) -> Class {
+        let exponent = (value >> self.fraction_bits()) & self.max_exponent();
+        let fraction = value & ((1 << self.fraction_bits()) - 1);
+        let quiet = 1 << (self.fraction_bits() - 1);
+
+        match (exponent, fraction) {
+            (0, 0) => Class::Zero,
+            (0, _) => Class::Subnormal,
+            (e, 0) if e == self.max_exponent() => Class::Infinite,
+            (e, f) if e == self.max_exponent() && f & quiet != 0 => Class::QuietNan,
+            (e, _) if e == self.max_exponent() => Class::SignalingNan,
+            _ => Class::Normal,
+        }
+    }
+
+    /// Decompose a finite value into its sign, exponent, and integer significand.
+    ///
+    /// The magnitude of the value is `significand * 2^exponent`.
+    fn unpack(&self, value: u64) -> (bool, i32, u128) {
+        let exponent = ((value >> self.fraction_bits()) & self.max_exponent()) as i32;
+        let fraction = (value & ((1 << self.fraction_bits()) - 1)) as u128;
+        let min_exponent = 1 - self.bias() - self.fraction_bits() as i32;
+
+        if exponent == 0 {
+            (self.sign(value), min_exponent, fraction)
+        } else {
+            let significand = fraction | (1 << self.fraction_bits());
+            (self.sign(value), min_exponent + exponent - 1, significand)
+        }
+    }
+}
+
+impl fmt::Display for Format {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Format::Single => f.write_str("s"),
+            Format::Double => f.write_str("d"),
+        }
+    }
+}
+
+/// Class of a floating-point value.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum Class {
+    /// Positive or negative zero.
+    Zero,
+
+    /// A subnormal (denormalised) number.
+    Subnormal,
+
+    /// A normal number.
+    Normal,
+
+    /// Positive or negative infinity.
+    Infinite,
+
+    /// A quiet NaN.
+    QuietNan,
+
+    /// A signaling NaN.
+    SignalingNan,
+}
+
+impl Class {
+    /// Whether this class is a NaN.
+    fn is_nan(&self) -> bool {
+        matches!(self, Class::QuietNan | Class::SignalingNan)
+    }
+}
+
+/// Rounding mode.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum RoundingMode {
+    /// Round to nearest, ties to even (`rne`).
+    NearestEven,
+
+    /// Round towards zero (`rtz`).
+    TowardsZero,
+
+    /// Round down, towards negative infinity (`rdn`).
+    Down,
+
+    /// Round up, towards positive infinity (`rup`).
+    Up,
+
+    /// Round to nearest, ties to max magnitude (`rmm`).
+    NearestMaxMagnitude,
+}
+
+impl RoundingMode {
+    /// Get the rounding mode encoded by the provided value of the `rm` instruction field or the
+    /// `frm` CSR.
+    ///
+    /// Returns `None` for the reserved encodings, and the dynamic rounding mode.
+    pub fn from_bits(bits: u8) -> Option<Self> {
+        match bits {
+            0b000 => Some(RoundingMode::NearestEven),
+            0b001 => Some(RoundingMode::TowardsZero),
+            0b010 => Some(RoundingMode::Down),
+            0b011 => Some(RoundingMode::Up),
+            0b100 => Some(RoundingMode::NearestMaxMagnitude),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for RoundingMode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RoundingMode::NearestEven => f.write_str("rne"),
+            RoundingMode::TowardsZero => f.write_str("rtz"),
+            RoundingMode::Down => f.write_str("rdn"),
+            RoundingMode::Up => f.write_str("rup"),
+            RoundingMode::NearestMaxMagnitude => f.write_str("rmm"),
+        }
+    }
+}
+
+/// Shift a significand right by `shift` bits, rounding the result to an integer.
+///
+/// Returns the rounded value, and whether it is inexact. A negative `shift` shifts left.
+fn round_shift(significand: u128, shift: i32, mode: RoundingMode, sign: bool) -> (u128, bool) {
+    if shift <= 0 {
+        return (significand << -shift, false);
+    }
+
+    let (kept, round, sticky) = match shift {
+        129.. => (0, false, significand != 0),
+        128 => (0, significand >> 127 != 0, significand << 1 != 0),
+        _ => (
+            significand >> shift,
+            (significand >> (shift - 1)) & 1 != 0,
+            significand & ((1 << (shift - 1)) - 1) != 0,
+        ),
+    };
+
+    let inexact = round || sticky;
+    let increment = match mode {
+        RoundingMode::NearestEven => round && (sticky || kept & 1 != 0),
+        RoundingMode::TowardsZero => false,
+        RoundingMode::Down => inexact && sign,
+        RoundingMode::Up => inexact && !sign,
+        RoundingMode::NearestMaxMagnitude => round,
+    };
+
+    (kept + increment as u128, inexact)
+}
+
+/// Shift a significand right by `shift` bits, setting the lowest bit if any bits shifted out were
+/// non-zero.
+fn shift_right_jam(significand: u128, shift: u32) -> u128 {
+    if shift >= 128 {
+        (significand != 0) as u128
+    } else {
+        (significand >> shift) | (significand & ((1 << shift) - 1) != 0) as u128
+    }
+}
+
+/// Number of significant bits in a significand.
+fn bit_length(significand: u128) -> i32 {
+    128 - significand.leading_zeros() as i32
+}
+
+/// An exact (or sticky) intermediate result: `(-1)^sign * significand * 2^exponent`.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+struct Term {
+    sign: bool,
+    exponent: i32,
+    significand: u128,
+}
+
+/// Floating-point environment: The rounding mode to use, and the exception flags accrued by the
+/// operations performed.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub struct FloatEnv {
+    /// Rounding mode used by operations which round their result.
+    pub rounding_mode: RoundingMode,
+
+    /// Exception flags raised by the operations performed, in the layout of the `fflags` CSR.
+    pub flags: u8,
+}
+
+impl FloatEnv {
+    /// Create a new FloatEnv using the provided rounding mode, with no exception flags raised.
+    pub fn new(rounding_mode: RoundingMode) -> Self {
+        Self {
+            rounding_mode,
+            flags: 0,
+        }
+    }
+
+    /// Round a non-zero intermediate result to the provided format.
+    fn round(&mut self, format: Format, term: Term) -> u64 {
+        let Term {
+            sign,
+            exponent,
+            significand,
+        } = term;
+        if significand == 0 {
+            return format.zero(sign);
+        }
+
+        let fraction_bits = format.fraction_bits() as i32;
+        let precision = fraction_bits + 1;
+        let min_exponent = 1 - format.bias();
+
+        // Exponent of the most significant bit of the result, and of the least significant bit
+        // which can be represented (accounting for subnormals)
+        let msb = exponent + bit_length(significand) - 1;
+        let mut quantum = msb.max(min_exponent) - fraction_bits;
+
+        let (mut rounded, inexact) =
+            round_shift(significand, quantum - exponent, self.rounding_mode, sign);
+        if rounded >> precision != 0 {
+            rounded >>= 1;
+            quantum += 1;
+        }
+
+        if inexact {
+            self.flags |= INEXACT;
+
+            // The result is tiny if, rounded with an unbounded exponent range, it would lie
+            // strictly between +/-2^min_exponent
+            if msb < min_exponent {
+                let (unbounded, _) = round_shift(
+                    significand,
+                    msb - fraction_bits - exponent,
+                    self.rounding_mode,
+                    sign,
+                );
+                if msb + 1 < min_exponent || unbounded >> precision == 0 {
+                    self.flags |= UNDERFLOW;
+                }
+            }
+        }
+
+        let biased = if rounded >> fraction_bits == 0 {
+            0
+        } else {
+            (quantum + fraction_bits + format.bias()) as u64
+        };
+        if biased >= format.max_exponent() {
+            self.flags |= OVERFLOW | INEXACT;
+            let infinite = match self.rounding_mode {
+                RoundingMode::NearestEven | RoundingMode::NearestMaxMagnitude => true,
+                RoundingMode::TowardsZero => false,
+                RoundingMode::Down => sign,
+                RoundingMode::Up => !sign,
+            };
+            return if infinite {
+                format.infinity(sign)
+            } else {
+                format.max_finite(sign)
+            };
+        }
+
+        let fraction = rounded as u64 & ((1 << fraction_bits) - 1);
+        format.with_sign(sign, (biased << fraction_bits) | fraction)
+    }
+
+    /// Check the operands of an arithmetic operation for NaNs.
+    ///
+    /// Raises the invalid operation flag if any operand is a signaling NaN. Returns the canonical
+    /// NaN if any operand is a NaN.
+    fn propagate_nan(&mut self, format: Format, operands: &[u64]) -> Option<u64> {
+        let classes = operands.iter().map(|v| format.classify(*v));
+        if classes.clone().any(|c| c == Class::SignalingNan) {
+            self.flags |= INVALID;
+        }
+        let mut classes = classes;
+        classes.any(|c| c.is_nan()).then(|| format.canonical_nan())
+    }
+
+    /// Raise the invalid operation flag, returning the canonical NaN.
+    fn invalid(&mut self, format: Format) -> u64 {
+        self.flags |= INVALID;
+        format.canonical_nan()
+    }
+
+    /// Decompose a finite value into a [`Term`].
+    fn term(format: Format, value: u64) -> Term {
+        let (sign, exponent, significand) = format.unpack(value);
+        Term {
+            sign,
+            exponent,
+            significand,
+        }
+    }
+
+    /// Add two finite terms, rounding the sum to the provided format.
+    fn sum(&mut self, format: Format, a: Term, b: Term) -> u64 {
+        if a.significand == 0 && b.significand == 0 {
+            // The sum of zeroes of opposite signs is +0, except when rounding down
+            let sign = if a.sign == b.sign {
+                a.sign
+            } else {
+                self.rounding_mode == RoundingMode::Down
+            };
+            return format.zero(sign);
+        }
+        if b.significand == 0 {
+            return self.round(format, a);
+        }
+        if a.significand == 0 {
+            return self.round(format, b);
+        }
+
+        // Align the term with the larger magnitude so its most significant bit is bit 125, leaving
+        // room for a carry. The smaller term can only lose bits if its most significant bit is at
+        // least 2 bits lower, in which case the discarded bits are recorded as a sticky bit well
+        // below the rounding position.
+        let msb = |t: &Term| t.exponent + bit_length(t.significand);
+        let (hi, lo) = if msb(&a) >= msb(&b) { (a, b) } else { (b, a) };
+        let hi_shift = 126 - bit_length(hi.significand);
+        let exponent = hi.exponent - hi_shift;
+        let hi_significand = hi.significand << hi_shift;
+        let lo_shift = exponent - lo.exponent;
+        let lo_significand = if lo_shift <= 0 {
+            lo.significand << -lo_shift
+        } else {
+            shift_right_jam(lo.significand, lo_shift as u32)
+        };
+
+        let (sign, significand) = if hi.sign == lo.sign {
+            (hi.sign, hi_significand + lo_significand)
+        } else if hi_significand >= lo_significand {
+            (hi.sign, hi_significand - lo_significand)
+        } else {
+            (lo.sign, lo_significand - hi_significand)
+        };
+
+        if significand == 0 {
+            // An exact zero sum of non-zero terms is +0, except when rounding down
+            return format.zero(self.rounding_mode == RoundingMode::Down);
+        }
+
+        self.round(
+            format,
+            Term {
+                sign,
+                exponent,
+                significand,
+            },
+        )
+    }
+
+    /// Add two values.
+    pub fn add(&mut self, format: Format, a: u64, b: u64) -> u64 {
+        if let Some(nan) = self.propagate_nan(format, &[a, b]) {
+            return nan;
+        }
+
+        match (format.classify(a), format.classify(b)) {
+            (Class::Infinite, Class::Infinite) if format.sign(a) != format.sign(b) => {
+                self.invalid(format)
+            }
+            (Class::Infinite, _) => a,
+            (_, Class::Infinite) => b,
+            _ => self.sum(format, Self::term(format, a), Self::term(format, b)),
+        }
+    }
+
+    /// Subtract `b` from `a`.
+    pub fn sub(&mut self, format: Format, a: u64, b: u64) -> u64 {
+        self.add(format, a, b ^ format.sign_bit())
+    }
+
+    /// Multiply two values.
+    pub fn mul(&mut self, format: Format, a: u64, b: u64) -> u64 {
+        if let Some(nan) = self.propagate_nan(format, &[a, b]) {
+            return nan;
+        }
+
+        let sign = format.sign(a) != format.sign(b);
+        match (format.classify(a), format.classify(b)) {
+            (Class::Infinite, Class::Zero) | (Class::Zero, Class::Infinite) => self.invalid(format),
+            (Class::Infinite, _) | (_, Class::Infinite) => format.infinity(sign),
+            _ => {
+                let a = Self::term(format, a);
+                let b = Self::term(format, b);
+                self.round(
+                    format,
+                    Term {
+                        sign,
+                        exponent: a.exponent + b.exponent,
+                        significand: a.significand * b.significand,
+                    },
+                )
+            }
+        }
+    }
+
+    /// Divide `a` by `b`.
+    pub fn div(&mut self, format: Format, a: u64, b: u64) -> u64 {
+        if let Some(nan) = self.propagate_nan(format, &[a, b]) {
+            return nan;
+        }
+
+        let sign = format.sign(a) != format.sign(b);
+        match (format.classify(a), format.classify(b)) {
+            (Class::Infinite, Class::Infinite) | (Class::Zero, Class::Zero) => self.invalid(format),
+            (Class::Infinite, _) => format.infinity(sign),
+            (_, Class::Infinite) | (Class::Zero, _) => format.zero(sign),
+            (_, Class::Zero) => {
+                self.flags |= DIVIDE_BY_ZERO;
+                format.infinity(sign)
+            }
+            _ => {
+                // Normalise both significands to the same length, then scale the dividend up so the
+                // quotient has plenty of bits beyond the rounding position
+                let a = Self::term(format, a);
+                let b = Self::term(format, b);
+                let precision = format.fraction_bits() as i32 + 1;
+                let a_shift = 126 - bit_length(a.significand);
+                let b_shift = precision - bit_length(b.significand);
+                let dividend = a.significand << a_shift;
+                let divisor = b.significand << b_shift;
+
+                let quotient = dividend / divisor;
+                let sticky = !dividend.is_multiple_of(divisor) as u128;
+                self.round(
+                    format,
+                    Term {
+                        sign,
+                        exponent: (a.exponent - a_shift) - (b.exponent - b_shift),
+                        significand: quotient | sticky,
+                    },
+                )
+            }
+        }
+    }
+
+    /// Compute the square root of a value.
+    pub fn sqrt(&mut self, format: Format, a: u64) -> u64 {
+        if let Some(nan) = self.propagate_nan(format, &[a]) {
+            return nan;
+        }
+
+        match format.classify(a) {
+            Class::Zero => a,
+            _ if format.sign(a) => self.invalid(format),
+            Class::Infinite => a,
+            _ => {
+                // Scale the significand up to make the exponent even, leaving plenty of bits in the
+                // root beyond the rounding position
+                let a = Self::term(format, a);
+                let mut shift = 125 - bit_length(a.significand);
+                if (a.exponent - shift) % 2 != 0 {
+                    shift += 1;
+                }
+                let radicand = a.significand << shift;
+
+                let root = radicand.isqrt();
+                let sticky = (root * root != radicand) as u128;
+                self.round(
+                    format,
+                    Term {
+                        sign: false,
+                        exponent: (a.exponent - shift) / 2,
+                        significand: root | sticky,
+                    },
+                )
+            }
+        }
+    }
+
+    /// Compute `(a * b) + c` with a single rounding, negating the product and/or addend as
+    /// requested.
+    pub fn mul_add(
+        &mut self,
+        format: Format,
+        a: u64,
+        b: u64,
+        c: u64,
+        negate_product: bool,
+        negate_addend: bool,
+    ) -> u64 {
+        let (class_a, class_b, class_c) =
+            (format.classify(a), format.classify(b), format.classify(c));
+
+        // Multiplying infinity by zero is invalid, even if the addend is a quiet NaN
+        let infinity_times_zero = matches!(
+            (class_a, class_b),
+            (Class::Infinite, Class::Zero) | (Class::Zero, Class::Infinite)
+        );
+        if let Some(nan) = self.propagate_nan(format, &[a, b, c]) {
+            if infinity_times_zero {
+                self.flags |= INVALID;
+            }
+            return nan;
+        }
+        if infinity_times_zero {
+            return self.invalid(format);
+        }
+
+        let product_sign = (format.sign(a) != format.sign(b)) != negate_product;
+        let addend_sign = format.sign(c) != negate_addend;
+        let product_infinite = class_a == Class::Infinite || class_b == Class::Infinite;
+
+        match (product_infinite, class_c) {
+            (true, Class::Infinite) if product_sign != addend_sign => self.invalid(format),
+            (true, _) => format.infinity(product_sign),
+            (false, Class::Infinite) => format.infinity(addend_sign),
+            _ => {
+                let a = Self::term(format, a);
+                let b = Self::term(format, b);
+                let c = Self::term(format, c);
+                let product = Term {
+                    sign: product_sign,
+                    exponent: a.exponent + b.exponent,
+                    significand: a.significand * b.significand,
+                };
+                let addend = Term {
+                    sign: addend_sign,
+                    ..c
+                };
+                self.sum(format, product, addend)
+            }
+        }
+    }
+
+    /// Return the smaller (or if `max`, the larger) of two values.
+    ///
+    /// -0 is considered less than +0. If only one value is a NaN, the other is returned.
+    pub fn min_max(&mut self, format: Format, a: u64, b: u64, max: bool) -> u64 {
+        let nan = self.propagate_nan(format, &[a, b]);
+        match (format.classify(a).is_nan(), format.classify(b).is_nan()) {
+            (true, true) => nan.unwrap(),
+            (true, false) => b,
+            (false, true) => a,
+            (false, false) => {
+                let a_less = Self::order(format, a) < Self::order(format, b)
+                    || (Self::order(format, a) == Self::order(format, b) && format.sign(a));
+                if a_less != max {
+                    a
+                } else {
+                    b
+                }
+            }
+        }
+    }
+
+    /// Map a non-NaN value to an integer with the same ordering.
+    ///
+    /// +0 and -0 map to the same value.
+    fn order(format: Format, value: u64) -> i128 {
+        let magnitude = (value & !format.sign_bit()) as i128;
+        if format.sign(value) {
+            -magnitude
+        } else {
+            magnitude
+        }
+    }
+
+    /// Whether `a` is equal to `b`.
+    ///
+    /// This is a quiet comparison: Only signaling NaNs raise the invalid operation flag.
+    pub fn equal(&mut self, format: Format, a: u64, b: u64) -> bool {
+        if self.propagate_nan(format, &[a, b]).is_some() {
+            return false;
+        }
+        Self::order(format, a) == Self::order(format, b)
+    }
+
+    /// Whether `a` is less than (or if `or_equal`, less than or equal to) `b`.
+    ///
+    /// This is a signaling comparison: Any NaN operand raises the invalid operation flag.
+    pub fn lt(&mut self, format: Format, a: u64, b: u64, or_equal: bool) -> bool {
+        if format.classify(a).is_nan() || format.classify(b).is_nan() {
+            self.flags |= INVALID;
+            return false;
+        }
+        let (a, b) = (Self::order(format, a), Self::order(format, b));
+        a < b || (or_equal && a == b)
+    }
+
+    /// Convert a value to an integer of the provided width, rounding according to the rounding
+    /// mode.
+    ///
+    /// Values which are out of range raise the invalid operation flag, and saturate to the nearest
+    /// representable value. NaNs are treated as positive infinity.
+    pub fn to_int(&mut self, format: Format, a: u64, bits: u32, signed: bool) -> i128 {
+        let (min, max) = if signed {
+            (-(1i128 << (bits - 1)), (1i128 << (bits - 1)) - 1)
+        } else {
+            (0, (1i128 << bits) - 1)
+        };
+
+        let (sign, magnitude, inexact) = match format.classify(a) {
+            Class::QuietNan | Class::SignalingNan => (false, None, false),
+            Class::Infinite => (format.sign(a), None, false),
+            _ => {
+                let a = Self::term(format, a);
+                if a.exponent > 64 {
+                    (a.sign, None, false)
+                } else {
+                    let (magnitude, inexact) =
+                        round_shift(a.significand, -a.exponent, self.rounding_mode, a.sign);
+                    (a.sign, Some(magnitude as i128), inexact)
+                }
+            }
+        };
+
+        let value = magnitude.map(|m| if sign { -m } else { m });
+        match value {
+            Some(value) if (min..=max).contains(&value) => {
+                if inexact {
+                    self.flags |= INEXACT;
+                }
+                value
+            }
+            _ => {
+                self.flags |= INVALID;
+                if sign {
+                    min
+                } else {
+                    max
+                }
+            }
+        }
+    }
+
+    /// Convert an integer to the provided format, rounding according to the rounding mode.
+    pub fn from_int(&mut self, format: Format, value: i128) -> u64 {
+        self.round(
+            format,
+            Term {
+                sign: value < 0,
+                exponent: 0,
+                significand: value.unsigned_abs(),
+            },
+        )
+    }
+
+    /// Convert a value from one format to another, rounding according to the rounding mode.
+    pub fn convert(&mut self, from: Format, to: Format, a: u64) -> u64 {
+        if self.propagate_nan(from, &[a]).is_some() {
+            return to.canonical_nan();
+        }
+
+        match from.classify(a) {
+            Class::Infinite => to.infinity(from.sign(a)),
+            Class::Zero => to.zero(from.sign(a)),
+            _ => self.round(to, Self::term(from, a)),
+        }
+    }
+}
+
+/// Classify a value as the FCLASS instructions do.
+///
+/// Returns a mask with exactly one bit set, identifying the class & sign of the value.
+pub fn classify(format: Format, value: u64) -> u64 {
+    let sign = format.sign(value);
+    let bit = match (format.classify(value), sign) {
+        (Class::Infinite, true) => 0,
+        (Class::Normal, true) => 1,
+        (Class::Subnormal, true) => 2,
+        (Class::Zero, true) => 3,
+        (Class::Zero, false) => 4,
+        (Class::Subnormal, false) => 5,
+        (Class::Normal, false) => 6,
+        (Class::Infinite, false) => 7,
+        (Class::SignalingNan, _) => 8,
+        (Class::QuietNan, _) => 9,
+    };
+    1 << bit
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ALL_MODES: [RoundingMode; 5] = [
+        RoundingMode::NearestEven,
+        RoundingMode::TowardsZero,
+        RoundingMode::Down,
+        RoundingMode::Up,
+        RoundingMode::NearestMaxMagnitude,
+    ];
+
+    fn s(value: f32) -> u64 {
+        value.to_bits() as u64
+    }
+
+    fn d(value: f64) -> u64 {
+        value.to_bits()
+    }
+
+    /// Deterministic pseudo-random values, covering all classes of value.
+    fn values(format: Format) -> Vec<u64> {
+        let mut state = 0x2545_f491_4f6c_dd1du64;
+        let mut values = vec![
+            0,
+            format.sign_bit(),
+            1,
+            format.infinity(false),
+            format.infinity(true),
+            format.max_finite(false),
+            format.canonical_nan(),
+        ];
+        for _ in 0..2000 {
+            state ^= state << 13;
+            state ^= state >> 7;
+            state ^= state << 17;
+            // Bias towards values near 1, so that sums & products are interesting
+            let value = match state % 4 {
+                0 => state,
+                _ => (state & !(0x3f << (format.bits() - 7))) ^ (0x1f << (format.bits() - 7)),
+            };
+            values.push(value & format.mask());
+        }
+        values
+    }
+
+    /// Check an operation matches the host, for round-to-nearest-even only.
+    ///
+    /// NaN results are only compared as NaNs, as the host may not produce the canonical NaN.
+    fn check_host(format: Format, result: u64, expected: u64) {
+        if format.classify(expected).is_nan() {
+            assert_eq!(result, format.canonical_nan());
+        } else {
+            assert_eq!(result, expected);
+        }
+    }
+
+    #[test]
+    fn matches_host_single() {
+        let values = values(Format::Single);
+        for pair in values.windows(2) {
+            let (a, b) = (pair[0], pair[1]);
+            let (fa, fb) = (f32::from_bits(a as u32), f32::from_bits(b as u32));
+            let mut env = FloatEnv::new(RoundingMode::NearestEven);
+            let f = Format::Single;
+
+            check_host(f, env.add(f, a, b), s(fa + fb));
+            check_host(f, env.sub(f, a, b), s(fa - fb));
+            check_host(f, env.mul(f, a, b), s(fa * fb));
+            check_host(f, env.div(f, a, b), s(fa / fb));
+            check_host(f, env.sqrt(f, a), s(fa.sqrt()));
+            check_host(
+                f,
+                env.mul_add(f, a, b, a, false, false),
+                s(fa.mul_add(fb, fa)),
+            );
+            check_host(
+                Format::Double,
+                env.convert(f, Format::Double, a),
+                d(fa as f64),
+            );
+        }
+    }
+
+    #[test]
+    fn matches_host_double() {
+        let values = values(Format::Double);
+        for pair in values.windows(3) {
+            let (a, b, c) = (pair[0], pair[1], pair[2]);
+            let (fa, fb, fc) = (f64::from_bits(a), f64::from_bits(b), f64::from_bits(c));
+            let mut env = FloatEnv::new(RoundingMode::NearestEven);
+            let f = Format::Double;
+
+            check_host(f, env.add(f, a, b), d(fa + fb));
+            check_host(f, env.sub(f, a, b), d(fa - fb));
+            check_host(f, env.mul(f, a, b), d(fa * fb));
+            check_host(f, env.div(f, a, b), d(fa / fb));
+            check_host(f, env.sqrt(f, a), d(fa.sqrt()));
+            check_host(
+                f,
+                env.mul_add(f, a, b, c, false, false),
+                d(fa.mul_add(fb, fc)),
+            );
+            check_host(
+                Format::Single,
+                env.convert(f, Format::Single, a),
+                s(fa as f32),
+            );
+        }
+    }
+
+    #[test]
+    fn rounding_modes() {
+        let f = Format::Single;
+        let one = s(1.0);
+        let tiny = s(f32::EPSILON / 4.0);
+        let up = s(1.0 + f32::EPSILON);
+
+        let expected = [
+            (RoundingMode::NearestEven, one, s(-1.0)),
+            (RoundingMode::TowardsZero, one, s(-1.0)),
+            (RoundingMode::Down, one, s(-1.0 - f32::EPSILON)),
+            (RoundingMode::Up, up, s(-1.0)),
+            (RoundingMode::NearestMaxMagnitude, one, s(-1.0)),
+        ];
+        for (mode, positive, negative) in expected {
+            let mut env = FloatEnv::new(mode);
+            assert_eq!(env.add(f, one, tiny), positive, "{}", mode);
+            assert_eq!(env.sub(f, s(-1.0), tiny), negative, "{}", mode);
+            assert_eq!(env.flags, INEXACT);
+        }
+
+        // Ties
+        let half = s(f32::EPSILON / 2.0);
+        let mut env = FloatEnv::new(RoundingMode::NearestEven);
+        assert_eq!(env.add(f, one, half), one);
+        let mut env = FloatEnv::new(RoundingMode::NearestMaxMagnitude);
+        assert_eq!(env.add(f, one, half), up);
+    }
+
+    #[test]
+    fn exception_flags() {
+        let f = Format::Double;
+        let mut env = FloatEnv::new(RoundingMode::NearestEven);
+        assert_eq!(env.add(f, d(1.0), d(2.0)), d(3.0));
+        assert_eq!(env.flags, 0);
+
+        assert_eq!(env.div(f, d(1.0), d(0.0)), d(f64::INFINITY));
+        assert_eq!(env.flags, DIVIDE_BY_ZERO);
+
+        let mut env = FloatEnv::new(RoundingMode::NearestEven);
+        assert_eq!(env.mul(f, d(f64::MAX), d(2.0)), d(f64::INFINITY));
+        assert_eq!(env.flags, OVERFLOW | INEXACT);
+
+        let mut env = FloatEnv::new(RoundingMode::TowardsZero);
+        assert_eq!(env.mul(f, d(f64::MAX), d(2.0)), d(f64::MAX));
+        assert_eq!(env.flags, OVERFLOW | INEXACT);
+
+        let mut env = FloatEnv::new(RoundingMode::NearestEven);
+        assert_eq!(
+            env.mul(f, d(f64::MIN_POSITIVE), d(0.75)),
+            0x000c_0000_0000_0000
+        );
+        assert_eq!(env.flags, 0);
+        assert_eq!(
+            env.mul(f, d(f64::MIN_POSITIVE), d(1.0 / 3.0)),
+            0x0005_5555_5555_5555
+        );
+        assert_eq!(env.flags, UNDERFLOW | INEXACT);
+
+        let mut env = FloatEnv::new(RoundingMode::NearestEven);
+        assert_eq!(env.sqrt(f, d(-1.0)), f.canonical_nan());
+        assert_eq!(
+            env.sub(f, d(f64::INFINITY), d(f64::INFINITY)),
+            f.canonical_nan()
+        );
+        assert_eq!(env.flags, INVALID);
+
+        // Signaling NaNs raise the invalid flag, quiet NaNs don't
+        let mut env = FloatEnv::new(RoundingMode::NearestEven);
+        assert_eq!(env.add(f, f.canonical_nan(), d(1.0)), f.canonical_nan());
+        assert_eq!(env.flags, 0);
+        assert_eq!(env.add(f, 0x7ff0_0000_0000_0001, d(1.0)), f.canonical_nan());
+        assert_eq!(env.flags, INVALID);
+    }
+
+    #[test]
+    fn tininess_after_rounding() {
+        // Just below the smallest normal, but rounds up to it with an unbounded exponent range, so
+        // isn't tiny
+        let below = d(f32::MIN_POSITIVE as f64 * (1.0 - 2f64.powi(-26)));
+        let mut env = FloatEnv::new(RoundingMode::NearestEven);
+        assert_eq!(
+            env.convert(Format::Double, Format::Single, below),
+            s(f32::MIN_POSITIVE)
+        );
+        assert_eq!(env.flags, INEXACT);
+
+        // Rounding towards zero leaves the result tiny
+        let mut env = FloatEnv::new(RoundingMode::TowardsZero);
+        assert_eq!(
+            env.convert(Format::Double, Format::Single, below),
+            s(f32::MIN_POSITIVE) - 1
+        );
+        assert_eq!(env.flags, UNDERFLOW | INEXACT);
+
+        // Rounds up to the smallest normal with the subnormal precision only, so is tiny
+        let f = Format::Single;
+        let mut env = FloatEnv::new(RoundingMode::NearestEven);
+        let x = s(1.0 - f32::EPSILON / 2.0);
+        assert_eq!(env.mul(f, s(f32::MIN_POSITIVE), x), s(f32::MIN_POSITIVE));
+        assert_eq!(env.flags, UNDERFLOW | INEXACT);
+    }
+
+    #[test]
+    fn zero_signs() {
+        let f = Format::Single;
+        for mode in ALL_MODES {
+            let mut env = FloatEnv::new(mode);
+            let expected = if mode == RoundingMode::Down {
+                s(-0.0)
+            } else {
+                s(0.0)
+            };
+            assert_eq!(env.sub(f, s(1.5), s(1.5)), expected);
+            assert_eq!(env.add(f, s(0.0), s(-0.0)), expected);
+            assert_eq!(env.add(f, s(-0.0), s(-0.0)), s(-0.0));
+            assert_eq!(
+                env.mul_add(f, s(0.0), s(-1.0), s(0.0), false, false),
+                expected
+            );
+        }
+    }
+
+    #[test]
+    fn fused_multiply_add() {
+        // (1 + 2^-23) * (1 - 2^-23) = 1 - 2^-46, which rounds to 1 if computed separately
+        let f = Format::Single;
+        let mut env = FloatEnv::new(RoundingMode::NearestEven);
+        let a = s(1.0 + f32::EPSILON);
+        let b = s(1.0 - f32::EPSILON);
+        assert_eq!(
+            env.mul_add(f, a, b, s(-1.0), false, false),
+            s(-(2f32.powi(-46)))
+        );
+        assert_eq!(env.mul_add(f, a, b, s(1.0), true, false), s(2f32.powi(-46)));
+        assert_eq!(env.flags, 0);
+
+        // Infinity * 0 is invalid, even with a quiet NaN addend
+        let nan = f.canonical_nan();
+        assert_eq!(env.mul_add(f, s(f32::INFINITY), 0, nan, false, false), nan);
+        assert_eq!(env.flags, INVALID);
+    }
+
+    #[test]
+    fn min_max() {
+        let f = Format::Single;
+        let mut env = FloatEnv::new(RoundingMode::NearestEven);
+        assert_eq!(env.min_max(f, s(-0.0), s(0.0), false), s(-0.0));
+        assert_eq!(env.min_max(f, s(0.0), s(-0.0), false), s(-0.0));
+        assert_eq!(env.min_max(f, s(-0.0), s(0.0), true), s(0.0));
+        assert_eq!(env.min_max(f, s(-2.0), s(1.0), true), s(1.0));
+        assert_eq!(env.min_max(f, f.canonical_nan(), s(1.0), false), s(1.0));
+        assert_eq!(env.flags, 0);
+        assert_eq!(
+            env.min_max(f, 0x7f80_0001, 0x7f80_0001, false),
+            f.canonical_nan()
+        );
+        assert_eq!(env.flags, INVALID);
+    }
+
+    #[test]
+    fn comparisons() {
+        let f = Format::Double;
+        let nan = f.canonical_nan();
+        let mut env = FloatEnv::new(RoundingMode::NearestEven);
+        assert!(env.equal(f, d(0.0), d(-0.0)));
+        assert!(!env.lt(f, d(-0.0), d(0.0), false));
+        assert!(env.lt(f, d(-0.0), d(0.0), true));
+        assert!(env.lt(f, d(-3.0), d(-2.0), false));
+        assert!(!env.equal(f, nan, nan));
+        assert_eq!(env.flags, 0);
+        assert!(!env.lt(f, nan, d(1.0), true));
+        assert_eq!(env.flags, INVALID);
+    }
+
+    #[test]
+    fn integer_conversions() {
+        let f = Format::Double;
+        let mut env = FloatEnv::new(RoundingMode::NearestEven);
+        assert_eq!(env.to_int(f, d(2.5), 32, true), 2);
+        assert_eq!(env.to_int(f, d(-3.5), 32, true), -4);
+        assert_eq!(env.flags, INEXACT);
+
+        let mut env = FloatEnv::new(RoundingMode::TowardsZero);
+        assert_eq!(env.to_int(f, d(-0.5), 32, false), 0);
+        assert_eq!(env.flags, INEXACT);
+
+        let mut env = FloatEnv::new(RoundingMode::NearestEven);
+        assert_eq!(env.to_int(f, d(-1.0), 32, false), 0);
+        assert_eq!(env.to_int(f, d(3e9), 32, true), i32::MAX as i128);
+        assert_eq!(env.to_int(f, d(-1e300), 64, true), i64::MIN as i128);
+        assert_eq!(
+            env.to_int(f, f.canonical_nan(), 64, false),
+            u64::MAX as i128
+        );
+        assert_eq!(
+            env.to_int(f, d(f64::NEG_INFINITY), 32, true),
+            i32::MIN as i128
+        );
+        assert_eq!(env.flags, INVALID);
+
+        let mut env = FloatEnv::new(RoundingMode::NearestEven);
+        assert_eq!(env.from_int(f, -7), d(-7.0));
+        assert_eq!(env.from_int(Format::Single, 0), 0);
+        assert_eq!(env.flags, 0);
+        assert_eq!(env.from_int(f, u64::MAX as i128), d(u64::MAX as f64));
+        assert_eq!(env.flags, INEXACT);
+
+        let mut env = FloatEnv::new(RoundingMode::TowardsZero);
+        assert_eq!(env.from_int(Format::Single, 16_777_217), s(16_777_216.0));
+    }
+
+    #[test]
+    fn classify_values() {
+        let f = Format::Single;
+        let cases = [
+            (s(f32::NEG_INFINITY), 0),
+            (s(-1.0), 1),
+            (s(-f32::MIN_POSITIVE / 2.0), 2),
+            (s(-0.0), 3),
+            (s(0.0), 4),
+            (1, 5),
+            (s(1.0), 6),
+            (s(f32::INFINITY), 7),
+            (0x7f80_0001, 8),
+            (f.canonical_nan(), 9),
+        ];
+        for (value, bit) in cases {
+            assert_eq!(classify(f, value), 1 << bit);
+        }
+    }
+}
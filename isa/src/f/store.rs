@@ -0,0 +1,103 @@
+//! STORE-FP opcode instructions (FSW, FSD).
+//!
+//! These instructions store the value of a floating-point register to memory. The value is not
+//! interpreted: FSW stores the lowest 32 bits of the register, whether or not it is NaN-boxed.
+
+use super::read_raw;
+use super::softfloat::Format;
+use z2l_core::error::ProcessorException;
+use z2l_core::extension::OpcodeHandler;
+use z2l_core::instruction::{
+    Instruction, InstructionParts, InstructionResult, InstructionWordParts,
+};
+use z2l_core::mmu::{MemoryAccessType, StoreSpec};
+use z2l_core::processor::register::RegisterFile;
+use z2l_core::processor::Xlen;
+
+/// STORE-FP opcode handler.
+pub struct FloatStoreHandler {
+    flen: Format,
+}
+
+impl FloatStoreHandler {
+    /// Create a new FloatStoreHandler, supporting formats up to the provided width.
+    pub fn new(flen: Format) -> Self {
+        Self { flen }
+    }
+}
+
+impl OpcodeHandler for FloatStoreHandler {
+    fn decode(
+        &self,
+        instruction: InstructionParts,
+        _pc: u64,
+        xlen: Xlen,
+    ) -> Result<Box<dyn Instruction>, ProcessorException> {
+        let instruction = instruction.into_word()?;
+        Ok(Box::new(FloatStoreInstruction::new(
+            &instruction,
+            self.flen,
+            xlen,
+        )?))
+    }
+}
+
+/// FSW or FSD instruction.
+pub struct FloatStoreInstruction {
+    src: u8,
+    base: u8,
+    offset: i32,
+    width: MemoryAccessType,
+    xlen: Xlen,
+}
+
+impl FloatStoreInstruction {
+    /// Create a new FloatStoreInstruction, supporting formats up to the provided width, for a hart
+    /// with the provided XLEN.
+    pub fn new(
+        instruction: &InstructionWordParts,
+        flen: Format,
+        xlen: Xlen,
+    ) -> Result<Self, ProcessorException> {
+        let width = match (instruction.funct3, flen) {
+            (0b010, _) => MemoryAccessType::Word,
+            (0b011, Format::Double) => MemoryAccessType::DoubleWord,
+            _ => return Err(ProcessorException::IllegalInstruction),
+        };
+
+        Ok(Self {
+            src: instruction.rs2,
+            base: instruction.rs1,
+            offset: instruction.imm_s,
+            width,
+            xlen,
+        })
+    }
+}
+
+impl Instruction for FloatStoreInstruction {
+    fn execute(
+        &self,
+        registers: &mut RegisterFile,
+        _mem: i64,
+    ) -> Result<InstructionResult, ProcessorException> {
+        let src = read_raw(registers, self.src)?;
+        let base = registers
+            .get(&self.base)
+            .ok_or(ProcessorException::IllegalInstruction)?
+            .load()?;
+
+        let addr = self.xlen.zero_extend(base.wrapping_add(self.offset as i64)) as usize;
+
+        Ok(InstructionResult::set_store(StoreSpec::new(
+            self.width, addr, src as i64,
+        )))
+    }
+
+    fn format(&self) -> String {
+        format!(
+            "fs{} f{}, 0x{:08x}(x{})",
+            self.width, self.src, self.offset, self.base
+        )
+    }
+}
@@ -2,4 +2,41 @@
 //!
 //! This crate defines the RISC-V base instruction set, plus ratified extensions.
 
+pub mod a;
+pub mod c;
+pub mod custom;
+pub mod d;
+pub mod f;
+pub mod m;
+pub mod registry;
+pub mod rv32e;
 pub mod rv32i;
+pub mod rv64i;
+pub mod v;
+pub mod zabha;
+pub mod zacas;
+pub mod zba;
+pub mod zbb;
+pub mod zbc;
+pub mod zbkb;
+pub mod zbkc;
+pub mod zbkx;
+pub mod zbs;
+pub mod zdinx;
+pub mod zfinx;
+pub mod zicbom;
+pub mod zicbop;
+pub mod zicboz;
+pub mod zicntr;
+pub mod zicond;
+pub mod zicsr;
+pub mod zifencei;
+pub mod zihpm;
+pub mod zknd;
+pub mod zkne;
+pub mod zknh;
+pub mod zkr;
+pub mod zve32x;
+
+#[cfg(test)]
+mod test_utils;
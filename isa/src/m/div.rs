@@ -0,0 +1,201 @@
+//! Division instructions (DIV, DIVU, REM, REMU, and on RV64, DIVW, DIVUW, REMW, REMUW).
+//!
+//! These instructions divide rs1 by rs2, storing either the quotient (DIV, DIVU) or the remainder
+//! (REM, REMU) in rd. DIV and REM perform signed division, rounding towards zero, while DIVU and
+//! REMU perform unsigned division. The word variants divide the lowest 32 bits of each operand,
+//! sign-extending the 32-bit result.
+//!
+//! Division by zero and signed overflow do not raise exceptions: Instead, the results specified in
+//! the RISC-V spec, Section 7.2 (Division Operations) are produced. Division by zero sets the
+//! quotient to all ones and the remainder to the dividend, while dividing the most negative integer
+//! by -1 sets the quotient to the dividend and the remainder to zero.
+
+use std::fmt;
+use z2l_core::error::ProcessorException;
+use z2l_core::instruction::{Instruction, InstructionResult, InstructionWordParts};
+use z2l_core::processor::register::RegisterFile;
+use z2l_core::processor::Xlen;
+
+/// Operation to perform.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+enum Operation {
+    Div,
+    DivU,
+    Rem,
+    RemU,
+}
+
+impl fmt::Display for Operation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Operation::Div => f.write_str("div"),
+            Operation::DivU => f.write_str("divu"),
+            Operation::Rem => f.write_str("rem"),
+            Operation::RemU => f.write_str("remu"),
+        }
+    }
+}
+
+/// A DIV, DIVU, REM, or REMU instruction, or one of their word variants.
+pub struct DivInstruction {
+    src1: u8,
+    src2: u8,
+    dest: u8,
+    op: Operation,
+    xlen: Xlen,
+    suffix: &'static str,
+}
+
+impl DivInstruction {
+    /// Create a new DivInstruction, for a hart with the provided XLEN.
+    pub fn new(instruction: &InstructionWordParts, xlen: Xlen) -> Self {
+        let op = match instruction.funct3 & 0b011 {
+            0b000 => Operation::Div,
+            0b001 => Operation::DivU,
+            0b010 => Operation::Rem,
+            0b011 => Operation::RemU,
+            _ => unreachable!("Masked to lowest 2 bits"),
+        };
+
+        Self {
+            src1: instruction.rs1,
+            src2: instruction.rs2,
+            dest: instruction.rd,
+            op,
+            xlen,
+            suffix: "",
+        }
+    }
+
+    /// Create a new DIVW, DIVUW, REMW, or REMUW instruction.
+    pub fn new_word(instruction: &InstructionWordParts) -> Self {
+        Self {
+            suffix: "w",
+            ..Self::new(instruction, Xlen::Rv32)
+        }
+    }
+}
+
+impl Instruction for DivInstruction {
+    fn execute(
+        &self,
+        registers: &mut RegisterFile,
+        _mem: i64,
+    ) -> Result<InstructionResult, ProcessorException> {
+        let xlen = self.xlen;
+        let src1 = xlen.sign_extend(
+            registers
+                .get(&self.src1)
+                .ok_or(ProcessorException::IllegalInstruction)?
+                .load()?,
+        );
+        let src2 = xlen.sign_extend(
+            registers
+                .get(&self.src2)
+                .ok_or(ProcessorException::IllegalInstruction)?
+                .load()?,
+        );
+
+        // `wrapping_div`/`wrapping_rem` already produce the required results on signed overflow,
+        // so we only need to special-case division by zero.
+        let result = match (self.op, src2) {
+            (Operation::Div | Operation::DivU, 0) => -1,
+            (Operation::Rem | Operation::RemU, 0) => src1,
+            (Operation::Div, _) => src1.wrapping_div(src2),
+            (Operation::DivU, _) => (xlen.zero_extend(src1) / xlen.zero_extend(src2)) as i64,
+            (Operation::Rem, _) => src1.wrapping_rem(src2),
+            (Operation::RemU, _) => (xlen.zero_extend(src1) % xlen.zero_extend(src2)) as i64,
+        };
+        let result = xlen.sign_extend(result);
+
+        let dest = registers
+            .get_mut(&self.dest)
+            .ok_or(ProcessorException::IllegalInstruction)?;
+        dest.store(result)?;
+
+        Ok(InstructionResult::default())
+    }
+
+    fn format(&self) -> String {
+        format!(
+            "{}{} x{}, x{}, x{}",
+            self.op, self.suffix, self.dest, self.src1, self.src2
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::m::M;
+    use crate::rv32i::RV32I;
+    use crate::rv64i::RV64I;
+    use crate::test_utils::{self, hart};
+
+    /// Execute the provided M instruction on an RV32 hart with x1 = `a` and x2 = `b`, returning the
+    /// value of x3.
+    fn execute(raw: u32, a: i32, b: i32) -> i32 {
+        let mut hart = hart(&[&RV32I, &M]);
+        let result = test_utils::execute(&mut hart, raw, a as i64, b as i64);
+        assert_eq!(result, result as i32 as i64, "Result is sign-extended");
+        result as i32
+    }
+
+    /// Execute the provided M instruction on an RV64 hart with x1 = `a` and x2 = `b`, returning the
+    /// value of x3.
+    fn execute64(raw: u32, a: i64, b: i64) -> i64 {
+        test_utils::execute(&mut hart(&[&RV64I, &M]), raw, a, b)
+    }
+
+    const DIV: u32 = 0x0220_c1b3; // div x3, x1, x2
+    const DIVU: u32 = 0x0220_d1b3; // divu x3, x1, x2
+    const REM: u32 = 0x0220_e1b3; // rem x3, x1, x2
+    const REMU: u32 = 0x0220_f1b3; // remu x3, x1, x2
+    const DIVW: u32 = 0x0220_c1bb; // divw x3, x1, x2
+    const DIVUW: u32 = 0x0220_d1bb; // divuw x3, x1, x2
+    const REMUW: u32 = 0x0220_f1bb; // remuw x3, x1, x2
+
+    #[test]
+    fn divide() {
+        assert_eq!(execute(DIV, 42, 6), 7);
+        assert_eq!(execute(DIV, -7, 2), -3);
+        assert_eq!(execute(DIV, 7, -2), -3);
+        assert_eq!(execute(DIVU, -1, 2), 0x7fff_ffff);
+        assert_eq!(execute(DIVU, 7, 2), 3);
+
+        assert_eq!(execute(REM, -7, 2), -1);
+        assert_eq!(execute(REM, 7, -2), 1);
+        assert_eq!(execute(REMU, -1, 16), 15);
+        assert_eq!(execute(REMU, 7, 2), 1);
+    }
+
+    #[test]
+    fn divide_by_zero() {
+        assert_eq!(execute(DIV, 42, 0), -1);
+        assert_eq!(execute(DIV, i32::MIN, 0), -1);
+        assert_eq!(execute(DIVU, 42, 0), -1);
+        assert_eq!(execute(REM, 42, 0), 42);
+        assert_eq!(execute(REM, -42, 0), -42);
+        assert_eq!(execute(REMU, -42, 0), -42);
+    }
+
+    #[test]
+    fn signed_overflow() {
+        assert_eq!(execute(DIV, i32::MIN, -1), i32::MIN);
+        assert_eq!(execute(REM, i32::MIN, -1), 0);
+
+        // Unsigned division has no overflow case
+        assert_eq!(execute(DIVU, i32::MIN, -1), 0);
+        assert_eq!(execute(REMU, i32::MIN, -1), i32::MIN);
+    }
+
+    #[test]
+    fn rv64() {
+        assert_eq!(execute64(DIVU, -1, 2), i64::MAX);
+        assert_eq!(execute64(DIV, i64::MIN, -1), i64::MIN);
+
+        // Word variants ignore the upper 32 bits, & sign-extend the result
+        assert_eq!(execute64(DIVUW, 0x1_ffff_fffe, 1), -2);
+        assert_eq!(execute64(DIVW, 0x1_0000_0006, 0x1_ffff_fffd), -2);
+        assert_eq!(execute64(REMUW, 0x1_0000_0000, 0), 0);
+    }
+}
@@ -0,0 +1,104 @@
+//! The "M" standard extension for integer multiplication and division.
+//!
+//! The M extension's instructions share the OP opcode with the base integer instruction set, and
+//! are distinguished by a funct7 value of `0b0000001`, so are registered as a [`SubDecoder`] on
+//! that opcode. On RV64, the word variants (MULW, DIVW, DIVUW, REMW, REMUW) are decoded from the
+//! OP-32 opcode in the same way.
+
+mod div;
+mod mul;
+
+pub use div::DivInstruction;
+pub use mul::MulInstruction;
+
+use z2l_core::error::ProcessorException;
+use z2l_core::extension::{Extension, SubDecoder};
+use z2l_core::instruction::{Instruction, InstructionWordParts};
+use z2l_core::processor::hart::Hart;
+use z2l_core::processor::Xlen;
+
+/// An [`Extension`] defining the M standard extension.
+pub struct M;
+
+impl Extension for M {
+    fn code(&self) -> &'static str {
+        "M"
+    }
+
+    fn name(&self) -> &'static str {
+        "Standard Extension for Integer Multiplication and Division"
+    }
+
+    fn register(&self, hart: &mut Hart) {
+        for opcode in [0x33, 0x3b] {
+            hart.register_sub_decoder(opcode, Box::new(MulDivDecoder));
+        }
+    }
+}
+
+/// Decoder for the M extension's instructions, in the OP and OP-32 opcodes.
+///
+/// Decodes instructions with funct7 = `0b0000001` as multiplication/division instructions. Any
+/// other instruction is passed on to the opcode's existing handler.
+pub struct MulDivDecoder;
+
+impl SubDecoder for MulDivDecoder {
+    fn decode(
+        &self,
+        instruction: &InstructionWordParts,
+        _pc: u64,
+        xlen: Xlen,
+    ) -> Result<Option<Box<dyn Instruction>>, ProcessorException> {
+        if instruction.funct7 != 0b0000001 {
+            return Ok(None);
+        }
+
+        Ok(Some(match (instruction.opcode, instruction.funct3, xlen) {
+            (0x33, 0b000..=0b011, _) => Box::new(MulInstruction::new(instruction, xlen)),
+            (0x33, 0b100..=0b111, _) => Box::new(DivInstruction::new(instruction, xlen)),
+            // The word variants are only defined for RV64, and there are none of the MULH
+            // instructions
+            (0x3b, 0b000, Xlen::Rv64) => Box::new(MulInstruction::new_word(instruction)),
+            (0x3b, 0b100..=0b111, Xlen::Rv64) => Box::new(DivInstruction::new_word(instruction)),
+            _ => return Ok(None),
+        }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::M;
+    use crate::rv32i::RV32I;
+    use crate::rv64i::RV64I;
+    use crate::test_utils::{decode, hart};
+    use z2l_core::instruction::InstructionParts;
+
+    #[test]
+    fn wraps_base_op_handler() {
+        let hart = hart(&[&RV32I, &M]);
+
+        assert_eq!(decode(&hart, 0x0073_02b3), "add x5, x6, x7");
+        assert_eq!(decode(&hart, 0x4073_02b3), "sub x5, x6, x7");
+        assert_eq!(decode(&hart, 0x0220_81b3), "mul x3, x1, x2");
+        assert_eq!(decode(&hart, 0x0220_91b3), "mulh x3, x1, x2");
+        assert_eq!(decode(&hart, 0x0220_f1b3), "remu x3, x1, x2");
+    }
+
+    #[test]
+    fn wraps_base_op_32_handler() {
+        let hart = hart(&[&RV64I, &M]);
+
+        assert_eq!(decode(&hart, 0x0073_02bb), "addw x5, x6, x7");
+        assert_eq!(decode(&hart, 0x0220_81bb), "mulw x3, x1, x2");
+        assert_eq!(decode(&hart, 0x0220_f1bb), "remuw x3, x1, x2");
+
+        // MULH has no word variant
+        let parts = InstructionParts::new(0x0220_91bb).unwrap();
+        assert!(hart
+            .opcodes
+            .get(&0x3b)
+            .unwrap()
+            .decode(parts, 0, hart.xlen)
+            .is_err());
+    }
+}
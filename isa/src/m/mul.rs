@@ -0,0 +1,184 @@
+//! Multiplication instructions (MUL, MULH, MULHSU, MULHU, and on RV64, MULW).
+//!
+//! These instructions multiply rs1 by rs2, storing either the low or high XLEN bits of the 2×XLEN
+//! bit product in rd. MUL stores the low bits, while MULH, MULHSU and MULHU store the high bits,
+//! treating the operands as signed×signed, signed×unsigned, and unsigned×unsigned respectively.
+//! MULW multiplies the lowest 32 bits of each operand, sign-extending the low 32 bits of the
+//! product.
+
+use std::fmt;
+use z2l_core::error::ProcessorException;
+use z2l_core::instruction::{Instruction, InstructionResult, InstructionWordParts};
+use z2l_core::processor::register::RegisterFile;
+use z2l_core::processor::Xlen;
+
+/// Operation to perform.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+enum Operation {
+    Mul,
+    MulH,
+    MulHSU,
+    MulHU,
+}
+
+impl fmt::Display for Operation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Operation::Mul => f.write_str("mul"),
+            Operation::MulH => f.write_str("mulh"),
+            Operation::MulHSU => f.write_str("mulhsu"),
+            Operation::MulHU => f.write_str("mulhu"),
+        }
+    }
+}
+
+/// A MUL, MULH, MULHSU, MULHU, or MULW instruction.
+pub struct MulInstruction {
+    src1: u8,
+    src2: u8,
+    dest: u8,
+    op: Operation,
+    xlen: Xlen,
+    suffix: &'static str,
+}
+
+impl MulInstruction {
+    /// Create a new MulInstruction, for a hart with the provided XLEN.
+    pub fn new(instruction: &InstructionWordParts, xlen: Xlen) -> Self {
+        let op = match instruction.funct3 & 0b011 {
+            0b000 => Operation::Mul,
+            0b001 => Operation::MulH,
+            0b010 => Operation::MulHSU,
+            0b011 => Operation::MulHU,
+            _ => unreachable!("Masked to lowest 2 bits"),
+        };
+
+        Self {
+            src1: instruction.rs1,
+            src2: instruction.rs2,
+            dest: instruction.rd,
+            op,
+            xlen,
+            suffix: "",
+        }
+    }
+
+    /// Create a new MULW instruction.
+    pub fn new_word(instruction: &InstructionWordParts) -> Self {
+        Self {
+            suffix: "w",
+            ..Self::new(instruction, Xlen::Rv32)
+        }
+    }
+}
+
+impl Instruction for MulInstruction {
+    fn execute(
+        &self,
+        registers: &mut RegisterFile,
+        _mem: i64,
+    ) -> Result<InstructionResult, ProcessorException> {
+        let xlen = self.xlen;
+        let src1 = xlen.sign_extend(
+            registers
+                .get(&self.src1)
+                .ok_or(ProcessorException::IllegalInstruction)?
+                .load()?,
+        );
+        let src2 = xlen.sign_extend(
+            registers
+                .get(&self.src2)
+                .ok_or(ProcessorException::IllegalInstruction)?
+                .load()?,
+        );
+
+        let bits = xlen.bits();
+        let result = match self.op {
+            Operation::Mul => src1.wrapping_mul(src2),
+            Operation::MulH => ((src1 as i128 * src2 as i128) >> bits) as i64,
+            Operation::MulHSU => ((src1 as i128 * xlen.zero_extend(src2) as i128) >> bits) as i64,
+            Operation::MulHU => {
+                ((xlen.zero_extend(src1) as u128 * xlen.zero_extend(src2) as u128) >> bits) as i64
+            }
+        };
+        let result = xlen.sign_extend(result);
+
+        let dest = registers
+            .get_mut(&self.dest)
+            .ok_or(ProcessorException::IllegalInstruction)?;
+        dest.store(result)?;
+
+        Ok(InstructionResult::default())
+    }
+
+    fn format(&self) -> String {
+        format!(
+            "{}{} x{}, x{}, x{}",
+            self.op, self.suffix, self.dest, self.src1, self.src2
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::m::M;
+    use crate::rv32i::RV32I;
+    use crate::rv64i::RV64I;
+    use crate::test_utils::{self, hart};
+
+    /// Execute the provided M instruction on an RV32 hart with x1 = `a` and x2 = `b`, returning the
+    /// value of x3.
+    fn execute(raw: u32, a: i32, b: i32) -> i32 {
+        let mut hart = hart(&[&RV32I, &M]);
+        let result = test_utils::execute(&mut hart, raw, a as i64, b as i64);
+        assert_eq!(result, result as i32 as i64, "Result is sign-extended");
+        result as i32
+    }
+
+    /// Execute the provided M instruction on an RV64 hart with x1 = `a` and x2 = `b`, returning the
+    /// value of x3.
+    fn execute64(raw: u32, a: i64, b: i64) -> i64 {
+        test_utils::execute(&mut hart(&[&RV64I, &M]), raw, a, b)
+    }
+
+    const MUL: u32 = 0x0220_81b3; // mul x3, x1, x2
+    const MULH: u32 = 0x0220_91b3; // mulh x3, x1, x2
+    const MULHSU: u32 = 0x0220_a1b3; // mulhsu x3, x1, x2
+    const MULHU: u32 = 0x0220_b1b3; // mulhu x3, x1, x2
+    const MULW: u32 = 0x0220_81bb; // mulw x3, x1, x2
+
+    #[test]
+    fn mul() {
+        assert_eq!(execute(MUL, 6, 7), 42);
+        assert_eq!(execute(MUL, -6, 7), -42);
+        assert_eq!(execute(MUL, 0x0001_0000, 0x0001_0000), 0);
+        assert_eq!(execute(MUL, i32::MIN, -1), i32::MIN);
+    }
+
+    #[test]
+    fn mul_high() {
+        assert_eq!(execute(MULH, 0x0001_0000, 0x0001_0000), 1);
+        assert_eq!(execute(MULH, -1, -1), 0);
+        assert_eq!(execute(MULH, -1, 1), -1);
+        assert_eq!(execute(MULH, i32::MIN, i32::MIN), 0x4000_0000);
+
+        assert_eq!(execute(MULHSU, -1, -1), -1);
+        assert_eq!(execute(MULHSU, 1, -1), 0);
+        assert_eq!(execute(MULHSU, i32::MIN, -1), i32::MIN);
+
+        assert_eq!(execute(MULHU, -1, -1), -2);
+        assert_eq!(execute(MULHU, -1, 1), 0);
+        assert_eq!(execute(MULHU, i32::MIN, 2), 1);
+    }
+
+    #[test]
+    fn rv64() {
+        assert_eq!(execute64(MUL, 0x1_0000_0000, 0x10), 0x10_0000_0000);
+        assert_eq!(execute64(MULH, i64::MIN, 2), -1);
+        assert_eq!(execute64(MULHSU, -1, -1), -1);
+        assert_eq!(execute64(MULHU, -1, -1), -2);
+
+        // MULW only multiplies the low 32 bits, & sign-extends the result
+        assert_eq!(execute64(MULW, 0x1_4000_0000, 2), i32::MIN as i64);
+    }
+}
@@ -0,0 +1,382 @@
+//! A registry of extensions, which can be selected by an ISA string.
+//!
+//! An ISA string names a base instruction set, followed by the extensions a hart implements, as
+//! described in the "ISA Extension Naming Conventions" chapter of the RISC-V spec: For example,
+//! `rv32imac_zicsr_zifencei_zba`. The string begins with `rv32` or `rv64`, and the base instruction
+//! set `i` or `e`, then lists single-letter extensions, then multi-letter extensions, each of which
+//! is separated from the previous extension by an underscore. The letter `g` may be used in place
+//! of the base instruction set, as shorthand for `imafd_zicsr_zifencei`.
+//!
+//! Extensions must be listed in the canonical order: Single-letter extensions in the order
+//! `MAFDQLCBKJTPVH`; then multi-letter "Z" extensions, ordered by the category given by their
+//! second letter (in the same order, with `I` first), then alphabetically; then "S" extensions,
+//! then "X" extensions, both ordered alphabetically. The extensions are registered in this order,
+//! too.
+//!
+//! Each extension may be followed by a version number, such as `2p1` for version 2.1. Only one
+//! version of each extension is implemented, so these are accepted, but ignored. ISA strings are
+//! case-insensitive.
+
+use crate::a::A;
+use crate::c::C;
+use crate::d::D;
+use crate::f::F;
+use crate::m::M;
+use crate::rv32e::RV32E;
+use crate::rv32i::RV32I;
+use crate::rv64i::RV64I;
+use crate::zabha::Zabha;
+use crate::zacas::Zacas;
+use crate::zba::Zba;
+use crate::zbb::Zbb;
+use crate::zbc::Zbc;
+use crate::zbkb::Zbkb;
+use crate::zbkc::Zbkc;
+use crate::zbkx::Zbkx;
+use crate::zbs::Zbs;
+use crate::zdinx::Zdinx;
+use crate::zfinx::Zfinx;
+use crate::zicbom::Zicbom;
+use crate::zicbop::Zicbop;
+use crate::zicboz::Zicboz;
+use crate::zicntr::Zicntr;
+use crate::zicond::Zicond;
+use crate::zicsr::Zicsr;
+use crate::zifencei::Zifencei;
+use crate::zihpm::Zihpm;
+use crate::zknd::Zknd;
+use crate::zkne::Zkne;
+use crate::zknh::Zknh;
+use crate::zkr::Zkr;
+use crate::zve32x::Zve32x;
+use std::collections::{BTreeSet, HashMap};
+use std::fmt;
+use z2l_core::entropy::HostEntropySource;
+use z2l_core::extension::Extension;
+
+/// Canonical order of the single-letter extensions.
+const CANONICAL_ORDER: &str = "mafdqlcbkjtpvh";
+
+/// Extensions implied by the `g` shorthand.
+const GENERAL: [&str; 6] = ["m", "a", "f", "d", "zicsr", "zifencei"];
+
+/// Error parsing an ISA string.
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+pub enum IsaError {
+    /// The ISA string doesn't begin with `rv32` or `rv64`, followed by a base instruction set.
+    InvalidBase,
+
+    /// The named extension isn't in the registry.
+    Unknown(String),
+
+    /// The first named extension must come before the second, to follow the canonical order.
+    Misordered(String, String),
+
+    /// The named extension is listed more than once.
+    Duplicate(String),
+}
+
+impl fmt::Display for IsaError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            IsaError::InvalidBase => {
+                f.write_str("ISA string must begin with rv32 or rv64, and a base instruction set")
+            }
+            IsaError::Unknown(code) => write!(f, "unknown extension \"{}\"", code),
+            IsaError::Misordered(code, before) => {
+                write!(f, "extension \"{}\" must come before \"{}\"", code, before)
+            }
+            IsaError::Duplicate(code) => write!(f, "extension \"{}\" is listed twice", code),
+        }
+    }
+}
+
+impl std::error::Error for IsaError {}
+
+/// A function constructing an extension.
+type Constructor = Box<dyn Fn() -> Box<dyn Extension>>;
+
+/// A registry mapping extension codes to functions constructing those extensions.
+///
+/// Codes are the [`Extension::code`] values of the extensions, and are case-insensitive. The base
+/// instruction sets are registered under their codes, too: e.g. `RV32I`.
+pub struct Registry {
+    constructors: HashMap<String, Constructor>,
+}
+
+impl Registry {
+    /// Create a new, empty registry.
+    pub fn new() -> Self {
+        Self {
+            constructors: HashMap::new(),
+        }
+    }
+
+    /// Register a function constructing the extension with the provided code, replacing any
+    /// function already registered for that code.
+    ///
+    /// This may be used to register extensions defined outside this crate, or to configure the
+    /// parameters of an extension.
+    pub fn register<F>(&mut self, code: &str, constructor: F)
+    where
+        F: Fn() -> Box<dyn Extension> + 'static,
+    {
+        self.constructors
+            .insert(code.to_lowercase(), Box::new(constructor));
+    }
+
+    /// Construct the extension with the provided code, if it is registered.
+    pub fn construct(&self, code: &str) -> Option<Box<dyn Extension>> {
+        self.constructors
+            .get(&code.to_lowercase())
+            .map(|constructor| constructor())
+    }
+
+    /// Parse an ISA string, constructing its base instruction set & extensions in the order they
+    /// should be registered with a hart.
+    pub fn parse(&self, isa: &str) -> Result<Vec<Box<dyn Extension>>, IsaError> {
+        let isa = isa.to_lowercase();
+        let rest = isa.strip_prefix("rv").ok_or(IsaError::InvalidBase)?;
+        let (xlen, rest) = match (rest.get(..2), rest.get(2..)) {
+            (Some(xlen @ ("32" | "64")), Some(rest)) => (xlen, rest),
+            _ => return Err(IsaError::InvalidBase),
+        };
+
+        // The base instruction set, and any extensions implied by the G shorthand
+        let mut chars = rest.chars().peekable();
+        let (base, implied) = match chars.next() {
+            Some(base @ ('i' | 'e')) => (format!("rv{}{}", xlen, base), &[][..]),
+            Some('g') => (format!("rv{}i", xlen), &GENERAL[..]),
+            _ => return Err(IsaError::InvalidBase),
+        };
+        skip_version(&mut chars);
+
+        let mut codes = vec![base];
+        let mut listed = BTreeSet::new();
+        let mut last: Option<String> = implied
+            .iter()
+            .rev()
+            .find(|c| c.len() == 1)
+            .map(|c| c.to_string());
+        while let Some(c) = chars.next() {
+            let code = match c {
+                '_' => continue,
+                'z' | 's' | 'x' => {
+                    let mut name = String::from(c);
+                    while let Some(&c) = chars.peek().filter(|&&c| c != '_') {
+                        name.push(c);
+                        chars.next();
+                    }
+                    strip_version(&name).to_owned()
+                }
+                _ => {
+                    skip_version(&mut chars);
+                    c.to_string()
+                }
+            };
+
+            if !self.constructors.contains_key(&code) {
+                return Err(IsaError::Unknown(code));
+            }
+            if !listed.insert(code.clone()) {
+                return Err(IsaError::Duplicate(code));
+            }
+            if let Some(last) = last.filter(|last| rank(last) >= rank(&code)) {
+                return Err(IsaError::Misordered(code, last));
+            }
+            last = Some(code.clone());
+            codes.push(code);
+        }
+
+        // Extensions implied by G may also be listed explicitly
+        codes.extend(
+            implied
+                .iter()
+                .filter(|code| !listed.contains(**code))
+                .map(|code| code.to_string()),
+        );
+        codes[1..].sort_by(|a, b| rank(a).cmp(&rank(b)));
+
+        codes
+            .iter()
+            .map(|code| self.construct(code).ok_or(IsaError::Unknown(code.clone())))
+            .collect()
+    }
+}
+
+impl Default for Registry {
+    /// Create a registry containing each extension defined by this crate, with its default
+    /// parameters.
+    fn default() -> Self {
+        let mut registry = Self::new();
+        registry.register("RV32I", || Box::new(RV32I));
+        registry.register("RV32E", || Box::new(RV32E));
+        registry.register("RV64I", || Box::new(RV64I));
+        registry.register("M", || Box::new(M));
+        registry.register("A", || Box::new(A));
+        registry.register("F", || Box::new(F));
+        registry.register("D", || Box::new(D));
+        registry.register("C", || Box::new(C));
+        registry.register("Zicbom", || Box::new(Zicbom::new(64)));
+        registry.register("Zicbop", || Box::new(Zicbop));
+        registry.register("Zicboz", || Box::new(Zicboz::new(64)));
+        registry.register("Zicntr", || Box::new(Zicntr));
+        registry.register("Zicond", || Box::new(Zicond));
+        registry.register("Zicsr", || Box::new(Zicsr));
+        registry.register("Zifencei", || Box::new(Zifencei));
+        registry.register("Zihpm", || Box::new(Zihpm));
+        registry.register("Zabha", || Box::new(Zabha));
+        registry.register("Zacas", || Box::new(Zacas));
+        registry.register("Zfinx", || Box::new(Zfinx));
+        registry.register("Zdinx", || Box::new(Zdinx));
+        registry.register("Zba", || Box::new(Zba));
+        registry.register("Zbb", || Box::new(Zbb));
+        registry.register("Zbc", || Box::new(Zbc));
+        registry.register("Zbkb", || Box::new(Zbkb));
+        registry.register("Zbkc", || Box::new(Zbkc));
+        registry.register("Zbkx", || Box::new(Zbkx));
+        registry.register("Zbs", || Box::new(Zbs));
+        registry.register("Zknd", || Box::new(Zknd));
+        registry.register("Zkne", || Box::new(Zkne));
+        registry.register("Zknh", || Box::new(Zknh));
+        registry.register("Zkr", || {
+            Box::new(Zkr::new(Box::new(HostEntropySource::new())))
+        });
+        registry.register("Zve32x", || Box::new(Zve32x::new(128)));
+        registry
+    }
+}
+
+/// Position of an extension in the canonical order.
+///
+/// Single-letter extensions are ordered first, then "Z", "S" & "X" extensions, each ordered by
+/// category (for "Z" extensions), then name.
+fn rank(code: &str) -> (usize, usize, &str) {
+    let letter = |c| CANONICAL_ORDER.find(c).unwrap_or(CANONICAL_ORDER.len());
+    let mut chars = code.chars();
+    match (chars.next(), chars.next()) {
+        (Some(c), None) => (0, letter(c), code),
+        (Some('z'), Some('i')) => (1, 0, code),
+        (Some('z'), Some(category)) => (1, letter(category) + 1, code),
+        (Some('s'), _) => (2, 0, code),
+        _ => (3, 0, code),
+    }
+}
+
+/// Remove the version number from the end of a multi-letter extension's name.
+fn strip_version(name: &str) -> &str {
+    let without_minor = match name.rsplit_once('p') {
+        Some((major, minor))
+            if !minor.is_empty()
+                && minor.chars().all(|c| c.is_ascii_digit())
+                && major.ends_with(|c: char| c.is_ascii_digit()) =>
+        {
+            major
+        }
+        _ => name,
+    };
+    without_minor.trim_end_matches(|c: char| c.is_ascii_digit())
+}
+
+/// Skip a version number following a single-letter extension.
+fn skip_version(chars: &mut std::iter::Peekable<std::str::Chars>) {
+    if !chars.peek().is_some_and(|c| c.is_ascii_digit()) {
+        return;
+    }
+    while chars.next_if(|c| c.is_ascii_digit()).is_some() {}
+
+    // The "p" is only part of the version if a minor version follows it: Otherwise, it's the P
+    // extension
+    let mut lookahead = chars.clone();
+    if lookahead.next() == Some('p') && lookahead.peek().is_some_and(|c| c.is_ascii_digit()) {
+        chars.next();
+        while chars.next_if(|c| c.is_ascii_digit()).is_some() {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{IsaError, Registry};
+
+    fn parse(isa: &str) -> Result<Vec<&'static str>, IsaError> {
+        let extensions = Registry::default().parse(isa)?;
+        Ok(extensions.iter().map(|e| e.code()).collect())
+    }
+
+    #[test]
+    fn canonical() {
+        assert_eq!(
+            parse("rv32imac_zicsr_zifencei_zba"),
+            Ok(vec!["RV32I", "M", "A", "C", "Zicsr", "Zifencei", "Zba"])
+        );
+        assert_eq!(parse("RV32E"), Ok(vec!["RV32E"]));
+        assert_eq!(
+            parse("rv64i_zicntr_zacas_zbkb_zknh_zve32x"),
+            Ok(vec!["RV64I", "Zicntr", "Zacas", "Zbkb", "Zknh", "Zve32x"])
+        );
+    }
+
+    #[test]
+    fn versions() {
+        assert_eq!(
+            parse("rv64i2p1m2a_zicsr2p0_zve32x1p0"),
+            Ok(vec!["RV64I", "M", "A", "Zicsr", "Zve32x"])
+        );
+    }
+
+    #[test]
+    fn general() {
+        let rv64gc = Ok(vec![
+            "RV64I", "M", "A", "F", "D", "C", "Zicsr", "Zifencei", "Zba",
+        ]);
+        assert_eq!(parse("rv64gc_zba"), rv64gc);
+        assert_eq!(parse("rv64gc_zicsr_zifencei_zba"), rv64gc);
+        assert_eq!(
+            parse("rv64gm"),
+            Err(IsaError::Misordered("m".to_owned(), "d".to_owned()))
+        );
+    }
+
+    #[test]
+    fn errors() {
+        assert_eq!(parse("rv128i"), Err(IsaError::InvalidBase));
+        assert_eq!(parse("rv32"), Err(IsaError::InvalidBase));
+        assert_eq!(parse("rv32m"), Err(IsaError::InvalidBase));
+        assert_eq!(parse("rv64e"), Err(IsaError::Unknown("rv64e".to_owned())));
+        assert_eq!(parse("rv32iq"), Err(IsaError::Unknown("q".to_owned())));
+        assert_eq!(
+            parse("rv32i_zfoo"),
+            Err(IsaError::Unknown("zfoo".to_owned()))
+        );
+        assert_eq!(
+            parse("rv32iam"),
+            Err(IsaError::Misordered("m".to_owned(), "a".to_owned()))
+        );
+        assert_eq!(
+            parse("rv32i_zba_zicsr"),
+            Err(IsaError::Misordered("zicsr".to_owned(), "zba".to_owned()))
+        );
+        assert_eq!(
+            parse("rv32i_zbs_zbb"),
+            Err(IsaError::Misordered("zbb".to_owned(), "zbs".to_owned()))
+        );
+        assert_eq!(
+            parse("rv32ic_zicsr_zicsr"),
+            Err(IsaError::Duplicate("zicsr".to_owned()))
+        );
+    }
+
+    #[test]
+    fn custom_extensions() {
+        let mut registry = Registry::default();
+        registry.register("Xtest", || {
+            Box::new(crate::custom::CustomExtension::new(
+                "Xtest",
+                "Test Extension",
+            ))
+        });
+        let extensions = registry.parse("rv32i_zicsr_xtest").unwrap();
+        assert_eq!(extensions[2].code(), "Xtest");
+        assert!(Registry::new().parse("rv32i").is_err());
+    }
+}
@@ -0,0 +1,120 @@
+//! The RV32E base instruction set.
+//!
+//! RV32E is a variant of RV32I for small embedded cores, which only provides the integer registers
+//! `x0` through `x15`. Its instructions are otherwise identical to RV32I, so it shares RV32I's
+//! decoders: Any instruction naming one of the missing registers raises an IllegalInstruction
+//! exception.
+
+use z2l_core::extension::Extension;
+use z2l_core::processor::hart::Hart;
+use z2l_core::processor::Xlen;
+
+/// Number of integer registers available in RV32E.
+const REGISTER_COUNT: u8 = 16;
+
+/// An [`Extension`] defining the RV32E base instruction set.
+pub struct RV32E;
+
+impl Extension for RV32E {
+    fn code(&self) -> &'static str {
+        "RV32E"
+    }
+
+    fn name(&self) -> &'static str {
+        "32-bit Base Integer Instruction Set (Embedded)"
+    }
+
+    fn register(&self, hart: &mut Hart) {
+        crate::rv32i::register(hart, Xlen::Rv32);
+        hart.set_register_count(REGISTER_COUNT);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::RV32E;
+    use crate::a::A;
+    use crate::m::M;
+    use crate::test_utils;
+    use crate::zicsr::Zicsr;
+    use z2l_core::error::ProcessorException;
+    use z2l_core::processor::csr::MSCRATCH;
+    use z2l_core::processor::Processor;
+
+    /// Create an RV32E processor with the M, A & Zicsr extensions, with a ROM at 0 containing the
+    /// provided program, and RAM at 0x80000000.
+    fn processor(program: &[u32]) -> Processor {
+        test_utils::processor(
+            vec![Box::new(RV32E), Box::new(M), Box::new(A), Box::new(Zicsr)],
+            program,
+        )
+    }
+
+    /// Run the provided program until its last instruction executes, returning the exception it
+    /// raised, if any.
+    fn run(program: &[u32]) -> Result<(), (ProcessorException, u64)> {
+        let mut processor = processor(program);
+        for _ in 0..=program.len() {
+            processor.cycle()?;
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn registers() {
+        let processor = processor(&[]);
+        let registers = processor.hart.registers.keys().copied().collect::<Vec<_>>();
+        assert_eq!(registers, (0..16).collect::<Vec<_>>());
+
+        // misa reports the E base ISA, rather than I
+        let misa = processor.hart.csrs.get(0x301).unwrap().value;
+        assert_ne!(misa & (1 << 4), 0);
+        assert_eq!(misa & (1 << 8), 0);
+    }
+
+    #[test]
+    fn missing_registers() {
+        let nop = 0x0000_0013; // addi x0, x0, 0
+        let illegal = [
+            0x0010_0813, // addi x16, x0, 1
+            0x0008_8793, // addi x15, x17, 0
+            0x01f0_07b3, // add x15, x0, x31
+            0x0011_0833, // add x16, x2, x1
+            0x0000_0f97, // auipc x31, 0
+            0x0000_08ef, // jal x17, 0
+            0x0004_2803, // lw x16, 0(x8)
+            0x0108_2023, // sw x16, 0(x16)
+            0x0208_0833, // mul x16, x16, x0
+            0x0800_a82f, // amoswap.w x16, x0, (x1)
+            0x1008_282f, // lr.w x16, (x16)
+            0x3410_2873, // csrrs x16, mepc, x0
+        ];
+        for raw in illegal {
+            assert_eq!(
+                run(&[nop, raw]),
+                Err((ProcessorException::IllegalInstruction, 4)),
+                "{:08x}",
+                raw
+            );
+        }
+
+        assert_eq!(run(&[nop, 0x0010_0793]), Ok(())); // addi x15, x0, 1
+    }
+
+    #[test]
+    fn csr_missing_destination() {
+        let mut processor = processor(&[
+            0x0050_0093, // addi x1, x0, 5
+            0x3400_9873, // csrrw x16, mscratch, x1
+        ]);
+        processor.cycle().unwrap();
+        processor.cycle().unwrap();
+        assert_eq!(
+            processor.cycle(),
+            Err((ProcessorException::IllegalInstruction, 4))
+        );
+
+        // The CSR isn't written by the illegal instruction
+        assert_eq!(processor.hart.csrs.get(MSCRATCH).unwrap().value, 0);
+    }
+}
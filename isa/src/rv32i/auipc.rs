@@ -9,6 +9,7 @@ use z2l_core::instruction::{
     Instruction, InstructionParts, InstructionResult, InstructionWordParts,
 };
 use z2l_core::processor::register::RegisterFile;
+use z2l_core::processor::Xlen;
 
 /// AUIPC opcode handler.
 pub struct AUIPCHandler;
@@ -17,27 +18,30 @@ impl OpcodeHandler for AUIPCHandler {
     fn decode(
         &self,
         instruction: InstructionParts,
-        pc: u32,
+        pc: u64,
+        xlen: Xlen,
     ) -> Result<Box<dyn Instruction>, ProcessorException> {
         let instruction = instruction.into_word()?;
-        Ok(Box::new(AUIPCInstruction::new(&instruction, pc)))
+        Ok(Box::new(AUIPCInstruction::new(&instruction, pc, xlen)))
     }
 }
 
 /// AUIPC instruction.
 pub struct AUIPCInstruction {
-    pc: u32,
+    pc: u64,
     imm: i32,
     dest: u8,
+    xlen: Xlen,
 }
 
 impl AUIPCInstruction {
     /// Create a new AUIPCInstruction.
-    pub fn new(instruction: &InstructionWordParts, pc: u32) -> Self {
+    pub fn new(instruction: &InstructionWordParts, pc: u64, xlen: Xlen) -> Self {
         Self {
             pc,
             imm: instruction.imm_u,
             dest: instruction.rd,
+            xlen,
         }
     }
 }
@@ -46,11 +50,15 @@ impl Instruction for AUIPCInstruction {
     fn execute(
         &self,
         registers: &mut RegisterFile,
-        _mem: i32,
+        _mem: i64,
     ) -> Result<InstructionResult, ProcessorException> {
-        let result = (self.pc as i32).wrapping_add(self.imm);
+        let result = self
+            .xlen
+            .sign_extend((self.pc as i64).wrapping_add(self.imm as i64));
 
-        let dest = registers.get_mut(&self.dest).unwrap();
+        let dest = registers
+            .get_mut(&self.dest)
+            .ok_or(ProcessorException::IllegalInstruction)?;
         dest.store(result)?;
 
         Ok(InstructionResult::default())
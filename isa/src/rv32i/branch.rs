@@ -9,6 +9,7 @@ use z2l_core::instruction::{
     Instruction, InstructionParts, InstructionResult, InstructionWordParts,
 };
 use z2l_core::processor::register::RegisterFile;
+use z2l_core::processor::Xlen;
 
 /// BRANCH opcode handler.
 pub struct BranchHandler;
@@ -17,7 +18,8 @@ impl OpcodeHandler for BranchHandler {
     fn decode(
         &self,
         instruction: InstructionParts,
-        pc: u32,
+        pc: u64,
+        _xlen: Xlen,
     ) -> Result<Box<dyn Instruction>, ProcessorException> {
         let instruction = instruction.into_word()?;
         Ok(Box::new(BranchInstruction::new(&instruction, pc)?))
@@ -63,7 +65,7 @@ impl fmt::Display for BranchCondition {
 
 /// BRANCH instruction.
 pub struct BranchInstruction {
-    pc: u32,
+    pc: u64,
     src1: u8,
     src2: u8,
     offset: i32,
@@ -72,7 +74,7 @@ pub struct BranchInstruction {
 
 impl BranchInstruction {
     /// Create a new BranchInstruction.
-    pub fn new(instruction: &InstructionWordParts, pc: u32) -> Result<Self, ProcessorException> {
+    pub fn new(instruction: &InstructionWordParts, pc: u64) -> Result<Self, ProcessorException> {
         let condition = match instruction.funct3 & 0b111 {
             0b000 => BranchCondition::Equal,
             0b001 => BranchCondition::NotEqual,
@@ -97,23 +99,26 @@ impl Instruction for BranchInstruction {
     fn execute(
         &self,
         registers: &mut RegisterFile,
-        _mem: i32,
+        _mem: i64,
     ) -> Result<InstructionResult, ProcessorException> {
-        let src1 = registers.get(&self.src1).unwrap().load()?;
-        let src2 = registers.get(&self.src2).unwrap().load()?;
+        let src1 = registers
+            .get(&self.src1)
+            .ok_or(ProcessorException::IllegalInstruction)?
+            .load()?;
+        let src2 = registers
+            .get(&self.src2)
+            .ok_or(ProcessorException::IllegalInstruction)?
+            .load()?;
 
-        let jump_addr = self.pc.wrapping_add(self.offset as u32);
-        if jump_addr % 4 != 0 {
-            return Err(ProcessorException::InstructionAddressMisaligned);
-        }
+        let jump_addr = self.pc.wrapping_add(self.offset as u64);
 
         let jump_cond = match self.condition {
             BranchCondition::Equal => src1 == src2,
             BranchCondition::NotEqual => src1 != src2,
             BranchCondition::Less => src1 < src2,
             BranchCondition::GreaterOrEqual => src1 >= src2,
-            BranchCondition::LessUnsigned => (src1 as u32) < (src2 as u32),
-            BranchCondition::GreaterOrEqualUnsigned => (src1 as u32) >= (src2 as u32),
+            BranchCondition::LessUnsigned => (src1 as u64) < (src2 as u64),
+            BranchCondition::GreaterOrEqualUnsigned => (src1 as u64) >= (src2 as u64),
         };
 
         if jump_cond {
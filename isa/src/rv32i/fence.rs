@@ -11,6 +11,7 @@ use z2l_core::instruction::{
     Instruction, InstructionParts, InstructionResult, InstructionWordParts,
 };
 use z2l_core::processor::register::RegisterFile;
+use z2l_core::processor::Xlen;
 
 /// FENCE opcode handler.
 pub struct FenceHandler;
@@ -19,9 +20,14 @@ impl OpcodeHandler for FenceHandler {
     fn decode(
         &self,
         instruction: InstructionParts,
-        _pc: u32,
+        _pc: u64,
+        _xlen: Xlen,
     ) -> Result<Box<dyn Instruction>, ProcessorException> {
         let instruction = instruction.into_word()?;
+        if instruction.funct3 != 0b000 {
+            // FENCE.I is provided by the Zifencei extension
+            return Err(ProcessorException::IllegalInstruction);
+        }
         Ok(Box::new(FenceInstruction::new(&instruction)?))
     }
 }
@@ -76,7 +82,7 @@ impl Instruction for FenceInstruction {
     fn execute(
         &self,
         _registers: &mut RegisterFile,
-        _mem: i32,
+        _mem: i64,
     ) -> Result<InstructionResult, ProcessorException> {
         // NOP: We always order device I/O and memory accesses exactly in the order they occur in
         // the actual program flow
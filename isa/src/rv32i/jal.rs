@@ -10,6 +10,7 @@ use z2l_core::instruction::{
 };
 
 use z2l_core::processor::register::RegisterFile;
+use z2l_core::processor::Xlen;
 
 /// JAL opcode handler.
 pub struct JalHandler;
@@ -18,27 +19,43 @@ impl OpcodeHandler for JalHandler {
     fn decode(
         &self,
         instruction: InstructionParts,
-        pc: u32,
+        pc: u64,
+        xlen: Xlen,
     ) -> Result<Box<dyn Instruction>, ProcessorException> {
         let instruction = instruction.into_word()?;
-        Ok(Box::new(JalInstruction::new(&instruction, pc)))
+        Ok(Box::new(JalInstruction::new(&instruction, pc, xlen)))
     }
 }
 
 /// JAL instruction.
 pub struct JalInstruction {
-    pc: u32,
+    pc: u64,
+    length: u64,
     offset: i32,
     dest: u8,
+    xlen: Xlen,
 }
 
 impl JalInstruction {
     /// Create a new JAL instruction.
-    pub fn new(instruction: &InstructionWordParts, pc: u32) -> Self {
+    pub fn new(instruction: &InstructionWordParts, pc: u64, xlen: Xlen) -> Self {
         Self {
             pc,
+            length: 4,
             offset: instruction.imm_j,
             dest: instruction.rd,
+            xlen,
+        }
+    }
+
+    /// Create a new JAL instruction, expanded from a 16-bit compressed instruction (C.J, C.JAL).
+    ///
+    /// The return address written to rd will be the address of the instruction following the
+    /// compressed instruction.
+    pub fn new_compressed(instruction: &InstructionWordParts, pc: u64, xlen: Xlen) -> Self {
+        Self {
+            length: 2,
+            ..Self::new(instruction, pc, xlen)
         }
     }
 }
@@ -47,16 +64,15 @@ impl Instruction for JalInstruction {
     fn execute(
         &self,
         registers: &mut RegisterFile,
-        _mem: i32,
+        _mem: i64,
     ) -> Result<InstructionResult, ProcessorException> {
-        let jump_addr = self.pc.wrapping_add(self.offset as u32);
-        if jump_addr % 4 != 0 {
-            return Err(ProcessorException::InstructionAddressMisaligned);
-        }
+        let jump_addr = self.pc.wrapping_add(self.offset as u64);
 
-        let dest = registers.get_mut(&self.dest).unwrap();
-        let ret_addr = self.pc + 4;
-        dest.store(ret_addr as i32)?;
+        let dest = registers
+            .get_mut(&self.dest)
+            .ok_or(ProcessorException::IllegalInstruction)?;
+        let ret_addr = self.pc.wrapping_add(self.length);
+        dest.store(self.xlen.sign_extend(ret_addr as i64))?;
 
         Ok(InstructionResult::set_jump(jump_addr))
     }
@@ -10,6 +10,7 @@ use z2l_core::instruction::{
 };
 
 use z2l_core::processor::register::RegisterFile;
+use z2l_core::processor::Xlen;
 
 /// JALR opcode handler.
 pub struct JalrHandler;
@@ -18,29 +19,45 @@ impl OpcodeHandler for JalrHandler {
     fn decode(
         &self,
         instruction: InstructionParts,
-        pc: u32,
+        pc: u64,
+        xlen: Xlen,
     ) -> Result<Box<dyn Instruction>, ProcessorException> {
         let instruction = instruction.into_word().unwrap();
-        Ok(Box::new(JalrInstruction::new(&instruction, pc)))
+        Ok(Box::new(JalrInstruction::new(&instruction, pc, xlen)))
     }
 }
 
 /// JALR instruction.
 pub struct JalrInstruction {
-    pc: u32,
+    pc: u64,
+    length: u64,
     base: u8,
     offset: i32,
     dest: u8,
+    xlen: Xlen,
 }
 
 impl JalrInstruction {
     /// Create a new JALR instruction.
-    pub fn new(instruction: &InstructionWordParts, pc: u32) -> Self {
+    pub fn new(instruction: &InstructionWordParts, pc: u64, xlen: Xlen) -> Self {
         Self {
             pc,
+            length: 4,
             base: instruction.rs1,
             offset: instruction.imm_i,
             dest: instruction.rd,
+            xlen,
+        }
+    }
+
+    /// Create a new JALR instruction, expanded from a 16-bit compressed instruction (C.JR, C.JALR).
+    ///
+    /// The return address written to rd will be the address of the instruction following the
+    /// compressed instruction.
+    pub fn new_compressed(instruction: &InstructionWordParts, pc: u64, xlen: Xlen) -> Self {
+        Self {
+            length: 2,
+            ..Self::new(instruction, pc, xlen)
         }
     }
 }
@@ -49,23 +66,25 @@ impl Instruction for JalrInstruction {
     fn execute(
         &self,
         registers: &mut RegisterFile,
-        _mem: i32,
+        _mem: i64,
     ) -> Result<InstructionResult, ProcessorException> {
-        let base = registers.get(&self.base).unwrap().load()? as u32;
+        let base = registers
+            .get(&self.base)
+            .ok_or(ProcessorException::IllegalInstruction)?
+            .load()?;
 
-        let jump_addr = base.wrapping_add(self.offset as u32) & 0xfffffffe;
-        if jump_addr % 4 != 0 {
-            return Err(ProcessorException::InstructionAddressMisaligned);
-        }
+        let jump_addr = base.wrapping_add(self.offset as i64) as u64 & !0b1;
 
-        let dest = registers.get_mut(&self.dest).unwrap();
-        let ret_addr = self.pc + 4;
-        dest.store(ret_addr as i32)?;
+        let dest = registers
+            .get_mut(&self.dest)
+            .ok_or(ProcessorException::IllegalInstruction)?;
+        let ret_addr = self.pc.wrapping_add(self.length);
+        dest.store(self.xlen.sign_extend(ret_addr as i64))?;
 
         Ok(InstructionResult::set_jump(jump_addr))
     }
 
     fn format(&self) -> String {
-        format!("jalr x{}, 0x{:08x}(x{})", self.dest, self.offset, self.dest)
+        format!("jalr x{}, 0x{:08x}(x{})", self.dest, self.offset, self.base)
     }
 }
@@ -1,6 +1,7 @@
 //! LOAD opcode instructions.
 //!
-//! LOAD instructions copy a value from memory into a register.
+//! LOAD instructions copy a value from memory into a register. The LD and LWU instructions are
+//! only available on RV64.
 
 use z2l_core::error::ProcessorException;
 use z2l_core::extension::OpcodeHandler;
@@ -9,6 +10,7 @@ use z2l_core::instruction::{
 };
 use z2l_core::mmu::{LoadSpec, MemoryAccessType};
 use z2l_core::processor::register::RegisterFile;
+use z2l_core::processor::Xlen;
 
 /// LOAD opcode handler.
 pub struct LoadHandler;
@@ -17,10 +19,11 @@ impl OpcodeHandler for LoadHandler {
     fn decode(
         &self,
         instruction: InstructionParts,
-        _pc: u32,
+        _pc: u64,
+        xlen: Xlen,
     ) -> Result<Box<dyn Instruction>, ProcessorException> {
         let instruction = instruction.into_word()?;
-        Ok(Box::new(LoadInstruction::new(&instruction)?))
+        Ok(Box::new(LoadInstruction::new(&instruction, xlen)?))
     }
 }
 
@@ -30,17 +33,20 @@ pub struct LoadInstruction {
     offset: i32,
     dest: u8,
     width: MemoryAccessType,
+    xlen: Xlen,
 }
 
 impl LoadInstruction {
-    /// Create a new LOAD instruction.
-    pub fn new(instruction: &InstructionWordParts) -> Result<Self, ProcessorException> {
-        let width = match instruction.funct3 {
-            0b000 => MemoryAccessType::SignedByte,
-            0b001 => MemoryAccessType::SignedHalfWord,
-            0b010 => MemoryAccessType::Word,
-            0b100 => MemoryAccessType::UnsignedByte,
-            0b101 => MemoryAccessType::UnsignedHalfWord,
+    /// Create a new LOAD instruction, for a hart with the provided XLEN.
+    pub fn new(instruction: &InstructionWordParts, xlen: Xlen) -> Result<Self, ProcessorException> {
+        let width = match (instruction.funct3, xlen) {
+            (0b000, _) => MemoryAccessType::SignedByte,
+            (0b001, _) => MemoryAccessType::SignedHalfWord,
+            (0b010, _) => MemoryAccessType::Word,
+            (0b011, Xlen::Rv64) => MemoryAccessType::DoubleWord,
+            (0b100, _) => MemoryAccessType::UnsignedByte,
+            (0b101, _) => MemoryAccessType::UnsignedHalfWord,
+            (0b110, Xlen::Rv64) => MemoryAccessType::UnsignedWord,
             _ => return Err(ProcessorException::IllegalInstruction),
         };
 
@@ -49,14 +55,22 @@ impl LoadInstruction {
             offset: instruction.imm_i,
             dest: instruction.rd,
             width,
+            xlen,
         })
     }
 }
 
 impl Instruction for LoadInstruction {
     fn load(&self, registers: &RegisterFile) -> Result<Option<LoadSpec>, ProcessorException> {
-        let base = registers.get(&self.base).unwrap().load()?;
-        let addr = base.wrapping_add(self.offset) as usize;
+        // Check the destination exists before the memory access, which may have side effects
+        if !registers.contains_key(&self.dest) {
+            return Err(ProcessorException::IllegalInstruction);
+        }
+        let base = registers
+            .get(&self.base)
+            .ok_or(ProcessorException::IllegalInstruction)?
+            .load()?;
+        let addr = self.xlen.zero_extend(base.wrapping_add(self.offset as i64)) as usize;
 
         Ok(Some(LoadSpec::new(self.width, addr)))
     }
@@ -64,9 +78,11 @@ impl Instruction for LoadInstruction {
     fn execute(
         &self,
         registers: &mut RegisterFile,
-        mem: i32,
+        mem: i64,
     ) -> Result<InstructionResult, ProcessorException> {
-        let dest = registers.get_mut(&self.dest).unwrap();
+        let dest = registers
+            .get_mut(&self.dest)
+            .ok_or(ProcessorException::IllegalInstruction)?;
         dest.store(mem)?;
 
         Ok(InstructionResult::default())
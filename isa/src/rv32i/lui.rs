@@ -9,6 +9,7 @@ use z2l_core::instruction::{
     Instruction, InstructionParts, InstructionResult, InstructionWordParts,
 };
 use z2l_core::processor::register::RegisterFile;
+use z2l_core::processor::Xlen;
 
 /// LUI opcode handler.
 pub struct LuiHandler;
@@ -17,7 +18,8 @@ impl OpcodeHandler for LuiHandler {
     fn decode(
         &self,
         instruction: InstructionParts,
-        _pc: u32,
+        _pc: u64,
+        _xlen: Xlen,
     ) -> Result<Box<dyn Instruction>, ProcessorException> {
         let instruction = instruction.into_word()?;
         Ok(Box::new(LuiInstruction::new(&instruction)))
@@ -44,10 +46,12 @@ impl Instruction for LuiInstruction {
     fn execute(
         &self,
         registers: &mut RegisterFile,
-        _mem: i32,
+        _mem: i64,
     ) -> Result<InstructionResult, ProcessorException> {
-        let dest = registers.get_mut(&self.dest).unwrap();
-        dest.store(self.imm)?;
+        let dest = registers
+            .get_mut(&self.dest)
+            .ok_or(ProcessorException::IllegalInstruction)?;
+        dest.store(self.imm as i64)?;
 
         Ok(InstructionResult::default())
     }
@@ -16,6 +16,7 @@ use std::fmt;
 use std::fmt::Write;
 use z2l_core::extension::Extension;
 use z2l_core::processor::hart::Hart;
+use z2l_core::processor::Xlen;
 
 /// An [`Extension`] defining the RV32I base instruction set.
 pub struct RV32I;
@@ -30,20 +31,26 @@ impl Extension for RV32I {
     }
 
     fn register(&self, hart: &mut Hart) {
-        hart.opcodes.insert(0x03, Box::new(load::LoadHandler));
-        hart.opcodes.insert(0x0f, Box::new(fence::FenceHandler));
-        hart.opcodes.insert(0x13, Box::new(op_imm::OpImmHandler));
-        hart.opcodes.insert(0x17, Box::new(auipc::AUIPCHandler));
-        hart.opcodes.insert(0x23, Box::new(store::StoreHandler));
-        hart.opcodes.insert(0x33, Box::new(op::OpHandler));
-        hart.opcodes.insert(0x37, Box::new(lui::LuiHandler));
-        hart.opcodes.insert(0x63, Box::new(branch::BranchHandler));
-        hart.opcodes.insert(0x67, Box::new(jalr::JalrHandler));
-        hart.opcodes.insert(0x6f, Box::new(jal::JalHandler));
-        hart.opcodes.insert(0x73, Box::new(system::SystemHandler));
+        register(hart, Xlen::Rv32);
     }
 }
 
+/// Register the opcode handlers shared by the RV32I & RV64I base instruction sets.
+pub(crate) fn register(hart: &mut Hart, xlen: Xlen) {
+    hart.set_xlen(xlen);
+    hart.register_opcode(0x03, Box::new(load::LoadHandler));
+    hart.register_opcode(0x0f, Box::new(fence::FenceHandler));
+    hart.register_opcode(0x13, Box::new(op_imm::OpImmHandler));
+    hart.register_opcode(0x17, Box::new(auipc::AUIPCHandler));
+    hart.register_opcode(0x23, Box::new(store::StoreHandler));
+    hart.register_opcode(0x33, Box::new(op::OpHandler));
+    hart.register_opcode(0x37, Box::new(lui::LuiHandler));
+    hart.register_opcode(0x63, Box::new(branch::BranchHandler));
+    hart.register_opcode(0x67, Box::new(jalr::JalrHandler));
+    hart.register_opcode(0x6f, Box::new(jal::JalHandler));
+    hart.register_opcode(0x73, Box::new(system::SystemHandler));
+}
+
 /// Behaviour of a right-shift instruction.
 #[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
 pub(crate) enum RightShiftBehaviour {
@@ -54,6 +61,17 @@ pub(crate) enum RightShiftBehaviour {
     Arithmetic,
 }
 
+impl RightShiftBehaviour {
+    /// Shift the lowest XLEN bits of `value` right by `shift` bits, sign-extending the result.
+    pub(crate) fn shift(&self, value: i64, shift: u32, xlen: Xlen) -> i64 {
+        let result = match self {
+            RightShiftBehaviour::Logical => (xlen.zero_extend(value) >> shift) as i64,
+            RightShiftBehaviour::Arithmetic => xlen.sign_extend(value) >> shift,
+        };
+        xlen.sign_extend(result)
+    }
+}
+
 impl fmt::Display for RightShiftBehaviour {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
@@ -1,11 +1,13 @@
 //! OP arithmetic instructions (ADD, SUB).
 //!
-//! These instructions add or subtract rs2 from rs1, storing the result in rd.
+//! These instructions add or subtract rs2 from rs1, storing the result in rd. The RV64I ADDW and
+//! SUBW instructions are the same operations, performed on the lowest 32 bits of each register.
 
 use std::fmt;
 use z2l_core::error::ProcessorException;
 use z2l_core::instruction::{Instruction, InstructionResult, InstructionWordParts};
 use z2l_core::processor::register::RegisterFile;
+use z2l_core::processor::Xlen;
 
 /// Operation to perform.
 #[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
@@ -23,17 +25,19 @@ impl fmt::Display for Operation {
     }
 }
 
-/// An ADD or SUB instruction.
+/// An ADD or SUB instruction, or their ADDW and SUBW variants.
 pub struct ArithmeticInstruction {
     src1: u8,
     src2: u8,
     dest: u8,
     op: Operation,
+    xlen: Xlen,
+    suffix: &'static str,
 }
 
 impl ArithmeticInstruction {
-    /// Create a new ArithmeticInstruction.
-    pub fn new(instruction: &InstructionWordParts) -> Result<Self, ProcessorException> {
+    /// Create a new ArithmeticInstruction, for a hart with the provided XLEN.
+    pub fn new(instruction: &InstructionWordParts, xlen: Xlen) -> Result<Self, ProcessorException> {
         let op = match instruction.funct7 {
             0b0000000 => Operation::Add,
             0b0100000 => Operation::Sub,
@@ -45,6 +49,16 @@ impl ArithmeticInstruction {
             src2: instruction.rs2,
             dest: instruction.rd,
             op,
+            xlen,
+            suffix: "",
+        })
+    }
+
+    /// Create a new ADDW or SUBW instruction.
+    pub fn new_word(instruction: &InstructionWordParts) -> Result<Self, ProcessorException> {
+        Ok(Self {
+            suffix: "w",
+            ..Self::new(instruction, Xlen::Rv32)?
         })
     }
 }
@@ -53,23 +67,35 @@ impl Instruction for ArithmeticInstruction {
     fn execute(
         &self,
         registers: &mut RegisterFile,
-        _mem: i32,
+        _mem: i64,
     ) -> Result<InstructionResult, ProcessorException> {
-        let src1 = registers.get(&self.src1).unwrap().load()?;
-        let src2 = registers.get(&self.src2).unwrap().load()?;
+        let src1 = registers
+            .get(&self.src1)
+            .ok_or(ProcessorException::IllegalInstruction)?
+            .load()?;
+        let src2 = registers
+            .get(&self.src2)
+            .ok_or(ProcessorException::IllegalInstruction)?
+            .load()?;
 
         let result = match self.op {
             Operation::Add => src1.wrapping_add(src2),
             Operation::Sub => src1.wrapping_sub(src2),
         };
+        let result = self.xlen.sign_extend(result);
 
-        let dest = registers.get_mut(&self.dest).unwrap();
+        let dest = registers
+            .get_mut(&self.dest)
+            .ok_or(ProcessorException::IllegalInstruction)?;
         dest.store(result)?;
 
         Ok(InstructionResult::default())
     }
 
     fn format(&self) -> String {
-        format!("{} x{}, x{}, x{}", self.op, self.dest, self.src1, self.src2)
+        format!(
+            "{}{} x{}, x{}, x{}",
+            self.op, self.suffix, self.dest, self.src1, self.src2
+        )
     }
 }
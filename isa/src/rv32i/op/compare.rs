@@ -29,14 +29,22 @@ impl Instruction for SltInstruction {
     fn execute(
         &self,
         registers: &mut RegisterFile,
-        _mem: i32,
+        _mem: i64,
     ) -> Result<InstructionResult, ProcessorException> {
-        let src1 = registers.get(&self.src1).unwrap().load()?;
-        let src2 = registers.get(&self.src2).unwrap().load()?;
+        let src1 = registers
+            .get(&self.src1)
+            .ok_or(ProcessorException::IllegalInstruction)?
+            .load()?;
+        let src2 = registers
+            .get(&self.src2)
+            .ok_or(ProcessorException::IllegalInstruction)?
+            .load()?;
 
         let result = if src1 < src2 { 1 } else { 0 };
 
-        let dest = registers.get_mut(&self.dest).unwrap();
+        let dest = registers
+            .get_mut(&self.dest)
+            .ok_or(ProcessorException::IllegalInstruction)?;
         dest.store(result)?;
 
         Ok(InstructionResult::default())
@@ -69,14 +77,22 @@ impl Instruction for SltUInstruction {
     fn execute(
         &self,
         registers: &mut RegisterFile,
-        _mem: i32,
+        _mem: i64,
     ) -> Result<InstructionResult, ProcessorException> {
-        let src1 = registers.get(&self.src1).unwrap().load()? as u32;
-        let src2 = registers.get(&self.src2).unwrap().load()? as u32;
+        let src1 = registers
+            .get(&self.src1)
+            .ok_or(ProcessorException::IllegalInstruction)?
+            .load()? as u64;
+        let src2 = registers
+            .get(&self.src2)
+            .ok_or(ProcessorException::IllegalInstruction)?
+            .load()? as u64;
 
         let result = if src1 < src2 { 1 } else { 0 };
 
-        let dest = registers.get_mut(&self.dest).unwrap();
+        let dest = registers
+            .get_mut(&self.dest)
+            .ok_or(ProcessorException::IllegalInstruction)?;
         dest.store(result)?;
 
         Ok(InstructionResult::default())
@@ -28,14 +28,22 @@ impl Instruction for AndInstruction {
     fn execute(
         &self,
         registers: &mut RegisterFile,
-        _mem: i32,
+        _mem: i64,
     ) -> Result<InstructionResult, ProcessorException> {
-        let src1 = registers.get(&self.src1).unwrap().load()?;
-        let src2 = registers.get(&self.src2).unwrap().load()?;
+        let src1 = registers
+            .get(&self.src1)
+            .ok_or(ProcessorException::IllegalInstruction)?
+            .load()?;
+        let src2 = registers
+            .get(&self.src2)
+            .ok_or(ProcessorException::IllegalInstruction)?
+            .load()?;
 
         let result = src1 & src2;
 
-        let dest = registers.get_mut(&self.dest).unwrap();
+        let dest = registers
+            .get_mut(&self.dest)
+            .ok_or(ProcessorException::IllegalInstruction)?;
         dest.store(result)?;
 
         Ok(InstructionResult::default())
@@ -68,14 +76,22 @@ impl Instruction for OrInstruction {
     fn execute(
         &self,
         registers: &mut RegisterFile,
-        _mem: i32,
+        _mem: i64,
     ) -> Result<InstructionResult, ProcessorException> {
-        let src1 = registers.get(&self.src1).unwrap().load()?;
-        let src2 = registers.get(&self.src2).unwrap().load()?;
+        let src1 = registers
+            .get(&self.src1)
+            .ok_or(ProcessorException::IllegalInstruction)?
+            .load()?;
+        let src2 = registers
+            .get(&self.src2)
+            .ok_or(ProcessorException::IllegalInstruction)?
+            .load()?;
 
         let result = src1 | src2;
 
-        let dest = registers.get_mut(&self.dest).unwrap();
+        let dest = registers
+            .get_mut(&self.dest)
+            .ok_or(ProcessorException::IllegalInstruction)?;
         dest.store(result)?;
 
         Ok(InstructionResult::default())
@@ -108,14 +124,22 @@ impl Instruction for XorInstruction {
     fn execute(
         &self,
         registers: &mut RegisterFile,
-        _mem: i32,
+        _mem: i64,
     ) -> Result<InstructionResult, ProcessorException> {
-        let src1 = registers.get(&self.src1).unwrap().load()?;
-        let src2 = registers.get(&self.src2).unwrap().load()?;
+        let src1 = registers
+            .get(&self.src1)
+            .ok_or(ProcessorException::IllegalInstruction)?
+            .load()?;
+        let src2 = registers
+            .get(&self.src2)
+            .ok_or(ProcessorException::IllegalInstruction)?
+            .load()?;
 
         let result = src1 ^ src2;
 
-        let dest = registers.get_mut(&self.dest).unwrap();
+        let dest = registers
+            .get_mut(&self.dest)
+            .ok_or(ProcessorException::IllegalInstruction)?;
         dest.store(result)?;
 
         Ok(InstructionResult::default())
@@ -15,6 +15,7 @@ pub use shift::{SllInstruction, SrInstruction};
 use z2l_core::error::ProcessorException;
 use z2l_core::extension::OpcodeHandler;
 use z2l_core::instruction::{Instruction, InstructionParts};
+use z2l_core::processor::Xlen;
 
 /// OP opcode handler.
 pub struct OpHandler;
@@ -23,17 +24,24 @@ impl OpcodeHandler for OpHandler {
     fn decode(
         &self,
         instruction: InstructionParts,
-        _pc: u32,
+        _pc: u64,
+        xlen: Xlen,
     ) -> Result<Box<dyn Instruction>, ProcessorException> {
         let instruction = instruction.into_word()?;
 
-        Ok(match instruction.funct3 & 0b111 {
-            0b000 => Box::new(ArithmeticInstruction::new(&instruction)?),
-            0b001 => Box::new(SllInstruction::new(&instruction)),
+        // Only ADD/SUB & SRL/SRA are distinguished by funct7, which is otherwise zero
+        let funct3 = instruction.funct3 & 0b111;
+        if instruction.funct7 != 0 && !matches!(funct3, 0b000 | 0b101) {
+            return Err(ProcessorException::IllegalInstruction);
+        }
+
+        Ok(match funct3 {
+            0b000 => Box::new(ArithmeticInstruction::new(&instruction, xlen)?),
+            0b001 => Box::new(SllInstruction::new(&instruction, xlen)),
             0b010 => Box::new(SltInstruction::new(&instruction)),
             0b011 => Box::new(SltUInstruction::new(&instruction)),
             0b100 => Box::new(XorInstruction::new(&instruction)),
-            0b101 => Box::new(SrInstruction::new(&instruction)?),
+            0b101 => Box::new(SrInstruction::new(&instruction, xlen)?),
             0b110 => Box::new(OrInstruction::new(&instruction)),
             0b111 => Box::new(AndInstruction::new(&instruction)),
             _ => unreachable!("Masked to lowest 3 bits"),
@@ -2,27 +2,42 @@
 //!
 //! These instructions shift the positions of bits in rs1 by the value of rs2, storing the result in
 //! rd. SLL performs a logical left-shift, SRL performs a logical right-shift (zero-extended), and
-//! SRA performs an arithmetic right-shift (sign-extended).
+//! SRA performs an arithmetic right-shift (sign-extended). Only the lowest log2(XLEN) bits of rs2
+//! are used as the shift amount. The RV64I SLLW, SRLW, and SRAW instructions are the same
+//! operations, performed on the lowest 32 bits of rs1.
 
 use crate::rv32i::RightShiftBehaviour;
 use z2l_core::error::ProcessorException;
 use z2l_core::instruction::{Instruction, InstructionResult, InstructionWordParts};
 use z2l_core::processor::register::RegisterFile;
+use z2l_core::processor::Xlen;
 
-/// SLL instruction.
+/// SLL or SLLW instruction.
 pub struct SllInstruction {
     src1: u8,
     src2: u8,
     dest: u8,
+    xlen: Xlen,
+    suffix: &'static str,
 }
 
 impl SllInstruction {
-    /// Create a new SllInstruction.
-    pub fn new(instruction: &InstructionWordParts) -> Self {
+    /// Create a new SllInstruction, for a hart with the provided XLEN.
+    pub fn new(instruction: &InstructionWordParts, xlen: Xlen) -> Self {
         Self {
             src1: instruction.rs1,
             src2: instruction.rs2,
             dest: instruction.rd,
+            xlen,
+            suffix: "",
+        }
+    }
+
+    /// Create a new SLLW instruction.
+    pub fn new_word(instruction: &InstructionWordParts) -> Self {
+        Self {
+            suffix: "w",
+            ..Self::new(instruction, Xlen::Rv32)
         }
     }
 }
@@ -31,35 +46,49 @@ impl Instruction for SllInstruction {
     fn execute(
         &self,
         registers: &mut RegisterFile,
-        _mem: i32,
+        _mem: i64,
     ) -> Result<InstructionResult, ProcessorException> {
-        let src1 = registers.get(&self.src1).unwrap().load()?;
-        let src2 = registers.get(&self.src2).unwrap().load()?;
-
-        let result = src1.wrapping_shl((src2 & 0b11111) as u32);
-
-        let dest = registers.get_mut(&self.dest).unwrap();
+        let src1 = registers
+            .get(&self.src1)
+            .ok_or(ProcessorException::IllegalInstruction)?
+            .load()?;
+        let src2 = registers
+            .get(&self.src2)
+            .ok_or(ProcessorException::IllegalInstruction)?
+            .load()?;
+
+        let shift = src2 as u32 & self.xlen.shift_mask();
+        let result = self.xlen.sign_extend(src1.wrapping_shl(shift));
+
+        let dest = registers
+            .get_mut(&self.dest)
+            .ok_or(ProcessorException::IllegalInstruction)?;
         dest.store(result)?;
 
         Ok(InstructionResult::default())
     }
 
     fn format(&self) -> String {
-        format!("sll x{}, x{}, x{}", self.dest, self.src1, self.src2)
+        format!(
+            "sll{} x{}, x{}, x{}",
+            self.suffix, self.dest, self.src1, self.src2
+        )
     }
 }
 
-/// SRL or SRA instruction.
+/// SRL or SRA instruction, or their SRLW and SRAW variants.
 pub struct SrInstruction {
     src1: u8,
     src2: u8,
     dest: u8,
     behaviour: RightShiftBehaviour,
+    xlen: Xlen,
+    suffix: &'static str,
 }
 
 impl SrInstruction {
-    /// Create a new SrInstruction.
-    pub fn new(instruction: &InstructionWordParts) -> Result<Self, ProcessorException> {
+    /// Create a new SrInstruction, for a hart with the provided XLEN.
+    pub fn new(instruction: &InstructionWordParts, xlen: Xlen) -> Result<Self, ProcessorException> {
         let behaviour = match instruction.funct7 {
             0b0000000 => RightShiftBehaviour::Logical,
             0b0100000 => RightShiftBehaviour::Arithmetic,
@@ -71,6 +100,16 @@ impl SrInstruction {
             src2: instruction.rs2,
             dest: instruction.rd,
             behaviour,
+            xlen,
+            suffix: "",
+        })
+    }
+
+    /// Create a new SRLW or SRAW instruction.
+    pub fn new_word(instruction: &InstructionWordParts) -> Result<Self, ProcessorException> {
+        Ok(Self {
+            suffix: "w",
+            ..Self::new(instruction, Xlen::Rv32)?
         })
     }
 }
@@ -79,19 +118,23 @@ impl Instruction for SrInstruction {
     fn execute(
         &self,
         registers: &mut RegisterFile,
-        _mem: i32,
+        _mem: i64,
     ) -> Result<InstructionResult, ProcessorException> {
-        let src1 = registers.get(&self.src1).unwrap().load()?;
-        let src2 = registers.get(&self.src2).unwrap().load()?;
-
-        let result = match self.behaviour {
-            RightShiftBehaviour::Logical => {
-                (src1 as u32).wrapping_shr((src2 & 0b11111) as u32) as i32
-            }
-            RightShiftBehaviour::Arithmetic => src1.wrapping_shr((src2 & 0b11111) as u32),
-        };
-
-        let dest = registers.get_mut(&self.dest).unwrap();
+        let src1 = registers
+            .get(&self.src1)
+            .ok_or(ProcessorException::IllegalInstruction)?
+            .load()?;
+        let src2 = registers
+            .get(&self.src2)
+            .ok_or(ProcessorException::IllegalInstruction)?
+            .load()?;
+
+        let shift = src2 as u32 & self.xlen.shift_mask();
+        let result = self.behaviour.shift(src1, shift, self.xlen);
+
+        let dest = registers
+            .get_mut(&self.dest)
+            .ok_or(ProcessorException::IllegalInstruction)?;
         dest.store(result)?;
 
         Ok(InstructionResult::default())
@@ -99,8 +142,8 @@ impl Instruction for SrInstruction {
 
     fn format(&self) -> String {
         format!(
-            "sr{} x{}, x{}, x{}",
-            self.behaviour, self.dest, self.src1, self.src2
+            "sr{}{} x{}, x{}, x{}",
+            self.behaviour, self.suffix, self.dest, self.src1, self.src2
         )
     }
 }
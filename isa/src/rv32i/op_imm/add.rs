@@ -1,25 +1,39 @@
 //! ADDI instruction.
 //!
-//! The ADDI instruction adds the value of rs1 to an immediate value, storing the result in rd.
+//! The ADDI instruction adds the value of rs1 to an immediate value, storing the result in rd. The
+//! RV64I ADDIW instruction is the same operation, performed on the lowest 32 bits of rs1.
 
 use z2l_core::error::ProcessorException;
 use z2l_core::instruction::{Instruction, InstructionResult, InstructionWordParts};
 use z2l_core::processor::register::RegisterFile;
+use z2l_core::processor::Xlen;
 
-/// ADDI instruction.
+/// ADDI or ADDIW instruction.
 pub struct AddIInstruction {
     src: u8,
     imm: i32,
     dest: u8,
+    xlen: Xlen,
+    suffix: &'static str,
 }
 
 impl AddIInstruction {
-    /// Create a new AddIInstruction.
-    pub fn new(instruction: &InstructionWordParts) -> Self {
+    /// Create a new AddIInstruction, for a hart with the provided XLEN.
+    pub fn new(instruction: &InstructionWordParts, xlen: Xlen) -> Self {
         Self {
             src: instruction.rs1,
             imm: instruction.imm_i,
             dest: instruction.rd,
+            xlen,
+            suffix: "",
+        }
+    }
+
+    /// Create a new ADDIW instruction.
+    pub fn new_word(instruction: &InstructionWordParts) -> Self {
+        Self {
+            suffix: "w",
+            ..Self::new(instruction, Xlen::Rv32)
         }
     }
 }
@@ -28,20 +42,28 @@ impl Instruction for AddIInstruction {
     fn execute(
         &self,
         registers: &mut RegisterFile,
-        _mem: i32,
+        _mem: i64,
     ) -> Result<InstructionResult, ProcessorException> {
         // TODO: Handle errors
-        let src = registers.get(&self.src).unwrap().load()?;
+        let src = registers
+            .get(&self.src)
+            .ok_or(ProcessorException::IllegalInstruction)?
+            .load()?;
 
-        let result = src.wrapping_add(self.imm);
+        let result = self.xlen.sign_extend(src.wrapping_add(self.imm as i64));
 
-        let dest = registers.get_mut(&self.dest).unwrap();
+        let dest = registers
+            .get_mut(&self.dest)
+            .ok_or(ProcessorException::IllegalInstruction)?;
         dest.store(result)?;
 
         Ok(InstructionResult::default())
     }
 
     fn format(&self) -> String {
-        format!("addi x{}, x{}, 0x{:08x}", self.dest, self.src, self.imm)
+        format!(
+            "addi{} x{}, x{}, 0x{:08x}",
+            self.suffix, self.dest, self.src, self.imm
+        )
     }
 }
@@ -30,13 +30,18 @@ impl Instruction for SltIInstruction {
     fn execute(
         &self,
         registers: &mut RegisterFile,
-        _mem: i32,
+        _mem: i64,
     ) -> Result<InstructionResult, ProcessorException> {
-        let src = registers.get(&self.src).unwrap().load()?;
+        let src = registers
+            .get(&self.src)
+            .ok_or(ProcessorException::IllegalInstruction)?
+            .load()?;
 
-        let result = if src < self.imm { 1 } else { 0 };
+        let result = if src < self.imm as i64 { 1 } else { 0 };
 
-        let dest = registers.get_mut(&self.dest).unwrap();
+        let dest = registers
+            .get_mut(&self.dest)
+            .ok_or(ProcessorException::IllegalInstruction)?;
         dest.store(result)?;
 
         Ok(InstructionResult::default())
@@ -69,13 +74,18 @@ impl Instruction for SltIUInstruction {
     fn execute(
         &self,
         registers: &mut RegisterFile,
-        _mem: i32,
+        _mem: i64,
     ) -> Result<InstructionResult, ProcessorException> {
-        let src = registers.get(&self.src).unwrap().load()? as u32;
+        let src = registers
+            .get(&self.src)
+            .ok_or(ProcessorException::IllegalInstruction)?
+            .load()? as u64;
 
-        let result = if src < self.imm as u32 { 1 } else { 0 };
+        let result = if src < self.imm as i64 as u64 { 1 } else { 0 };
 
-        let dest = registers.get_mut(&self.dest).unwrap();
+        let dest = registers
+            .get_mut(&self.dest)
+            .ok_or(ProcessorException::IllegalInstruction)?;
         dest.store(result)?;
 
         Ok(InstructionResult::default())
@@ -16,6 +16,7 @@ use z2l_core::error::ProcessorException;
 
 use z2l_core::extension::OpcodeHandler;
 use z2l_core::instruction::{Instruction, InstructionParts};
+use z2l_core::processor::Xlen;
 
 /// OP-IMM opcode handler.
 pub struct OpImmHandler;
@@ -24,17 +25,18 @@ impl OpcodeHandler for OpImmHandler {
     fn decode(
         &self,
         instruction: InstructionParts,
-        _pc: u32,
+        _pc: u64,
+        xlen: Xlen,
     ) -> Result<Box<dyn Instruction>, ProcessorException> {
         let instruction = instruction.into_word()?;
 
         Ok(match instruction.funct3 & 0b111 {
-            0b000 => Box::new(AddIInstruction::new(&instruction)),
-            0b001 => Box::new(SllIInstruction::new(&instruction)),
+            0b000 => Box::new(AddIInstruction::new(&instruction, xlen)),
+            0b001 => Box::new(SllIInstruction::new(&instruction, xlen)?),
             0b010 => Box::new(SltIInstruction::new(&instruction)),
             0b011 => Box::new(SltIUInstruction::new(&instruction)),
             0b100 => Box::new(XorIInstruction::new(&instruction)),
-            0b101 => Box::new(SrIInstruction::new(&instruction)?),
+            0b101 => Box::new(SrIInstruction::new(&instruction, xlen)?),
             0b110 => Box::new(OrIInstruction::new(&instruction)),
             0b111 => Box::new(AndIInstruction::new(&instruction)),
             _ => unreachable!("Masked to lowest 3 bits"),
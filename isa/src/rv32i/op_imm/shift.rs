@@ -2,28 +2,59 @@
 //!
 //! These instructions shift the positions of bits in rs1 by an immediate value, storing the result
 //! in rd. SLLI performs a logical left-shift, SRLI performs a logical right-shift (zero-extended),
-//! and SRAI performs an arithmetic right-shift (sign-extended).
+//! and SRAI performs an arithmetic right-shift (sign-extended). The shift amount is 5 bits wide on
+//! RV32, and 6 bits wide on RV64. The RV64I SLLIW, SRLIW, and SRAIW instructions are the same
+//! operations, performed on the lowest 32 bits of rs1, with a 5-bit shift amount.
 
 use crate::rv32i::RightShiftBehaviour;
 use z2l_core::error::ProcessorException;
 use z2l_core::instruction::{Instruction, InstructionResult, InstructionWordParts};
 use z2l_core::processor::register::RegisterFile;
+use z2l_core::processor::Xlen;
+
+/// Split the immediate of a shift instruction into its shift amount, and the function bits above
+/// the widest possible (6-bit) shift amount.
+///
+/// Returns an error if the shift amount is too wide for the provided XLEN.
+fn decode_shift(
+    instruction: &InstructionWordParts,
+    xlen: Xlen,
+) -> Result<(u32, i32), ProcessorException> {
+    let shift = (instruction.imm_i & 0b111111) as u32;
+    if shift & !xlen.shift_mask() != 0 {
+        return Err(ProcessorException::IllegalInstruction);
+    }
+    Ok((shift, (instruction.imm_i & 0b111111000000) >> 6))
+}
 
-/// SLLI instruction.
+/// SLLI or SLLIW instruction.
 pub struct SllIInstruction {
     src: u8,
     shift: u32,
     dest: u8,
+    xlen: Xlen,
+    suffix: &'static str,
 }
 
 impl SllIInstruction {
-    /// Create a new SllIInstruction.
-    pub fn new(instruction: &InstructionWordParts) -> Self {
-        Self {
+    /// Create a new SllIInstruction, for a hart with the provided XLEN.
+    pub fn new(instruction: &InstructionWordParts, xlen: Xlen) -> Result<Self, ProcessorException> {
+        let (shift, _) = decode_shift(instruction, xlen)?;
+        Ok(Self {
             src: instruction.rs1,
-            shift: (instruction.imm_i & 0b11111) as u32,
+            shift,
             dest: instruction.rd,
-        }
+            xlen,
+            suffix: "",
+        })
+    }
+
+    /// Create a new SLLIW instruction.
+    pub fn new_word(instruction: &InstructionWordParts) -> Result<Self, ProcessorException> {
+        Ok(Self {
+            suffix: "w",
+            ..Self::new(instruction, Xlen::Rv32)?
+        })
     }
 }
 
@@ -31,45 +62,66 @@ impl Instruction for SllIInstruction {
     fn execute(
         &self,
         registers: &mut RegisterFile,
-        _mem: i32,
+        _mem: i64,
     ) -> Result<InstructionResult, ProcessorException> {
-        let src = registers.get(&self.src).unwrap().load()?;
+        let src = registers
+            .get(&self.src)
+            .ok_or(ProcessorException::IllegalInstruction)?
+            .load()?;
 
-        let result = src.wrapping_shl(self.shift);
+        let result = self.xlen.sign_extend(src.wrapping_shl(self.shift));
 
-        let dest = registers.get_mut(&self.dest).unwrap();
+        let dest = registers
+            .get_mut(&self.dest)
+            .ok_or(ProcessorException::IllegalInstruction)?;
         dest.store(result)?;
 
         Ok(InstructionResult::default())
     }
 
     fn format(&self) -> String {
-        format!("slli x{}, x{}, {}", self.dest, self.src, self.shift)
+        format!(
+            "slli{} x{}, x{}, {}",
+            self.suffix, self.dest, self.src, self.shift
+        )
     }
 }
 
-/// SRLI or SRAI instruction.
+/// SRLI or SRAI instruction, or their SRLIW and SRAIW variants.
 pub struct SrIInstruction {
     src: u8,
     behaviour: RightShiftBehaviour,
     imm: u32,
     dest: u8,
+    xlen: Xlen,
+    suffix: &'static str,
 }
 
 impl SrIInstruction {
-    /// Create a new SrIInstruction.
-    pub fn new(instruction: &InstructionWordParts) -> Result<Self, ProcessorException> {
-        let behaviour = match instruction.imm_i & 0b111111100000 {
-            0b0000000 => RightShiftBehaviour::Logical,
-            0b0100000 => RightShiftBehaviour::Arithmetic,
+    /// Create a new SrIInstruction, for a hart with the provided XLEN.
+    pub fn new(instruction: &InstructionWordParts, xlen: Xlen) -> Result<Self, ProcessorException> {
+        let (imm, funct6) = decode_shift(instruction, xlen)?;
+        let behaviour = match funct6 {
+            0b000000 => RightShiftBehaviour::Logical,
+            0b010000 => RightShiftBehaviour::Arithmetic,
             _ => return Err(ProcessorException::IllegalInstruction),
         };
 
         Ok(Self {
             src: instruction.rs1,
             behaviour,
-            imm: (instruction.imm_i & 0b11111) as u32,
+            imm,
             dest: instruction.rd,
+            xlen,
+            suffix: "",
+        })
+    }
+
+    /// Create a new SRLIW or SRAIW instruction.
+    pub fn new_word(instruction: &InstructionWordParts) -> Result<Self, ProcessorException> {
+        Ok(Self {
+            suffix: "w",
+            ..Self::new(instruction, Xlen::Rv32)?
         })
     }
 }
@@ -78,16 +130,18 @@ impl Instruction for SrIInstruction {
     fn execute(
         &self,
         registers: &mut RegisterFile,
-        _mem: i32,
+        _mem: i64,
     ) -> Result<InstructionResult, ProcessorException> {
-        let src = registers.get(&self.src).unwrap().load()?;
+        let src = registers
+            .get(&self.src)
+            .ok_or(ProcessorException::IllegalInstruction)?
+            .load()?;
 
-        let result = match self.behaviour {
-            RightShiftBehaviour::Logical => (src as u32).wrapping_shr(self.imm) as i32,
-            RightShiftBehaviour::Arithmetic => src.wrapping_shr(self.imm),
-        };
+        let result = self.behaviour.shift(src, self.imm, self.xlen);
 
-        let dest = registers.get_mut(&self.dest).unwrap();
+        let dest = registers
+            .get_mut(&self.dest)
+            .ok_or(ProcessorException::IllegalInstruction)?;
         dest.store(result)?;
 
         Ok(InstructionResult::default())
@@ -95,8 +149,8 @@ impl Instruction for SrIInstruction {
 
     fn format(&self) -> String {
         format!(
-            "sr{}i x{}, x{}, {}",
-            self.behaviour, self.dest, self.src, self.imm
+            "sr{}i{} x{}, x{}, {}",
+            self.behaviour, self.suffix, self.dest, self.src, self.imm
         )
     }
 }
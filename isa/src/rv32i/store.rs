@@ -1,6 +1,7 @@
 //! STORE opcode instructions.
 //!
-//! STORE instructions copy a value from a register into memory.
+//! STORE instructions copy a value from a register into memory. The SD instruction is only
+//! available on RV64.
 
 use z2l_core::error::ProcessorException;
 use z2l_core::extension::OpcodeHandler;
@@ -9,6 +10,7 @@ use z2l_core::instruction::{
 };
 use z2l_core::mmu::{MemoryAccessType, StoreSpec};
 use z2l_core::processor::register::RegisterFile;
+use z2l_core::processor::Xlen;
 
 /// STORE opcode handler.
 pub struct StoreHandler;
@@ -17,10 +19,11 @@ impl OpcodeHandler for StoreHandler {
     fn decode(
         &self,
         instruction: InstructionParts,
-        _pc: u32,
+        _pc: u64,
+        xlen: Xlen,
     ) -> Result<Box<dyn Instruction>, ProcessorException> {
         let instruction = instruction.into_word()?;
-        Ok(Box::new(StoreInstruction::new(&instruction)?))
+        Ok(Box::new(StoreInstruction::new(&instruction, xlen)?))
     }
 }
 
@@ -30,15 +33,17 @@ pub struct StoreInstruction {
     base: u8,
     offset: i32,
     width: MemoryAccessType,
+    xlen: Xlen,
 }
 
 impl StoreInstruction {
-    /// Create a new StoreInstruction.
-    pub fn new(instruction: &InstructionWordParts) -> Result<Self, ProcessorException> {
-        let width = match instruction.funct3 {
-            0b000 => MemoryAccessType::SignedByte,
-            0b001 => MemoryAccessType::SignedHalfWord,
-            0b010 => MemoryAccessType::Word,
+    /// Create a new StoreInstruction, for a hart with the provided XLEN.
+    pub fn new(instruction: &InstructionWordParts, xlen: Xlen) -> Result<Self, ProcessorException> {
+        let width = match (instruction.funct3, xlen) {
+            (0b000, _) => MemoryAccessType::SignedByte,
+            (0b001, _) => MemoryAccessType::SignedHalfWord,
+            (0b010, _) => MemoryAccessType::Word,
+            (0b011, Xlen::Rv64) => MemoryAccessType::DoubleWord,
             _ => return Err(ProcessorException::IllegalInstruction),
         };
 
@@ -47,6 +52,7 @@ impl StoreInstruction {
             base: instruction.rs1,
             offset: instruction.imm_s,
             width,
+            xlen,
         })
     }
 }
@@ -55,12 +61,18 @@ impl Instruction for StoreInstruction {
     fn execute(
         &self,
         registers: &mut RegisterFile,
-        _mem: i32,
+        _mem: i64,
     ) -> Result<InstructionResult, ProcessorException> {
-        let src = registers.get(&self.src).unwrap().load()?;
-        let base = registers.get(&self.base).unwrap().load()?;
+        let src = registers
+            .get(&self.src)
+            .ok_or(ProcessorException::IllegalInstruction)?
+            .load()?;
+        let base = registers
+            .get(&self.base)
+            .ok_or(ProcessorException::IllegalInstruction)?
+            .load()?;
 
-        let addr = base.wrapping_add(self.offset) as usize;
+        let addr = self.xlen.zero_extend(base.wrapping_add(self.offset as i64)) as usize;
 
         Ok(InstructionResult::set_store(StoreSpec::new(
             self.width, addr, src,
@@ -2,6 +2,8 @@
 //!
 //! SYSTEM instructions are used to access system functionality which may require privileged access.
 //! In the base instruction set, the "SYSTEM" opcode is only used for the ECALL/EBREAK instructions.
+//! The machine-level ISA additionally defines the MRET instruction, used to return from a trap
+//! handler.
 
 use z2l_core::error::ProcessorException;
 use z2l_core::extension::OpcodeHandler;
@@ -9,6 +11,7 @@ use z2l_core::instruction::{
     Instruction, InstructionParts, InstructionResult, InstructionWordParts,
 };
 use z2l_core::processor::register::RegisterFile;
+use z2l_core::processor::{PrivilegeLevel, Xlen};
 
 /// SYSTEM [`OpcodeHandler`].
 pub struct SystemHandler;
@@ -17,12 +20,14 @@ impl OpcodeHandler for SystemHandler {
     fn decode(
         &self,
         instruction: InstructionParts,
-        _pc: u32,
+        _pc: u64,
+        _xlen: Xlen,
     ) -> Result<Box<dyn Instruction>, ProcessorException> {
         let instruction = instruction.into_word()?;
         match instruction.imm_i {
             0b000000000000 => Ok(Box::new(ECallInstruction::new(&instruction)?)),
             0b000000000001 => Ok(Box::new(EBreakInstruction::new(&instruction)?)),
+            0b001100000010 => Ok(Box::new(MRetInstruction::new(&instruction)?)),
             _ => Err(ProcessorException::IllegalInstruction),
         }
     }
@@ -48,7 +53,7 @@ impl Instruction for ECallInstruction {
     fn execute(
         &self,
         _registers: &mut RegisterFile,
-        _mem: i32,
+        _mem: i64,
     ) -> Result<InstructionResult, ProcessorException> {
         Err(ProcessorException::EnvironmentCall)
     }
@@ -78,7 +83,7 @@ impl Instruction for EBreakInstruction {
     fn execute(
         &self,
         _registers: &mut RegisterFile,
-        _mem: i32,
+        _mem: i64,
     ) -> Result<InstructionResult, ProcessorException> {
         Err(ProcessorException::EnvironmentBreak)
     }
@@ -87,3 +92,150 @@ impl Instruction for EBreakInstruction {
         String::from("ebreak")
     }
 }
+
+/// A machine-mode trap return (MRET) instruction.
+///
+/// This instruction returns from a machine-mode trap handler, resuming execution at the address
+/// stored in `mepc` at the privilege level stored in `mstatus.MPP`.
+pub struct MRetInstruction;
+
+impl MRetInstruction {
+    /// Create a new MRetInstruction.
+    pub fn new(instruction: &InstructionWordParts) -> Result<Self, ProcessorException> {
+        if instruction.rs1 != 0 || instruction.funct3 != 0 || instruction.rd != 0 {
+            return Err(ProcessorException::IllegalInstruction);
+        }
+
+        Ok(Self)
+    }
+}
+
+impl Instruction for MRetInstruction {
+    fn execute(
+        &self,
+        _registers: &mut RegisterFile,
+        _mem: i64,
+    ) -> Result<InstructionResult, ProcessorException> {
+        Ok(InstructionResult::set_trap_return(PrivilegeLevel::Machine))
+    }
+
+    fn format(&self) -> String {
+        String::from("mret")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::rv32i::RV32I;
+    use crate::test_utils;
+    use crate::zicsr::Zicsr;
+    use z2l_core::error::{MemoryAccessError, ProcessorException};
+    use z2l_core::processor::{PrivilegeLevel, Processor};
+
+    /// Create an RV32I_Zicsr processor, with a ROM containing the provided program.
+    ///
+    /// The trap handler is expected to start at 0x40.
+    fn processor(program: &[u32], handler: &[u32]) -> Processor {
+        let mut rom = program.to_vec();
+        rom.resize(0x10, 0);
+        rom.extend(handler);
+        test_utils::processor(vec![Box::new(RV32I), Box::new(Zicsr)], &rom)
+    }
+
+    /// Run the provided number of processor cycles.
+    fn run(processor: &mut Processor, cycles: usize) -> Result<(), (ProcessorException, u64)> {
+        for _ in 0..cycles {
+            processor.cycle()?;
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn trap_and_return() {
+        let mut processor = processor(
+            &[
+                0x0400_0293, // addi x5, x0, 0x40
+                0x3052_9073, // csrrw x0, mtvec, x5
+                0x0000_0073, // ecall
+                0x0010_0313, // addi x6, x0, 1
+                0x4000_0537, // lui x10, 0x40000
+                0x0005_2483, // lw x9, 0(x10)
+                0x0020_0693, // addi x13, x0, 2
+                0x0000_006f, // jal x0, 0
+            ],
+            &[
+                0x3420_23f3, // csrrs x7, mcause, x0
+                0x3430_25f3, // csrrs x11, mtval, x0
+                0x0016_0613, // addi x12, x12, 1
+                0x3410_2473, // csrrs x8, mepc, x0
+                0x0044_0413, // addi x8, x8, 4
+                0x3414_1073, // csrrw x0, mepc, x8
+                0x3020_0073, // mret
+            ],
+        );
+        run(&mut processor, 30).unwrap();
+
+        let reg = |n: u8| processor.hart.registers.get(&n).unwrap().load().unwrap();
+        assert_eq!(reg(12), 2);
+        assert_eq!(reg(6), 1);
+        assert_eq!(reg(13), 2);
+        assert_eq!(reg(7), 5);
+        assert_eq!(reg(11), 0x4000_0000);
+        assert_eq!(reg(8), 0x18);
+    }
+
+    #[test]
+    fn user_mode() {
+        let mut processor = processor(
+            &[
+                0x0400_0293, // addi x5, x0, 0x40
+                0x3052_9073, // csrrw x0, mtvec, x5
+                0x0200_0313, // addi x6, x0, 0x20
+                0x3413_1073, // csrrw x0, mepc, x6
+                0x0000_23b7, // lui x7, 2
+                0x8003_8393, // addi x7, x7, -2048
+                0x3003_b073, // csrrc x0, mstatus, x7
+                0x3020_0073, // mret
+                0x0000_0073, // ecall
+            ],
+            &[
+                0x3420_23f3, // csrrs x7, mcause, x0
+                0x0000_006f, // jal x0, 0
+            ],
+        );
+
+        run(&mut processor, 10).unwrap();
+        assert_eq!(processor.hart.privilege, PrivilegeLevel::User);
+
+        run(&mut processor, 4).unwrap();
+        assert_eq!(processor.hart.privilege, PrivilegeLevel::Machine);
+        assert_eq!(processor.hart.registers.get(&7).unwrap().load(), Ok(8));
+    }
+
+    #[test]
+    fn unrecoverable() {
+        // No trap handler installed
+        let mut unset = processor(&[0x0000_0073], &[]);
+        assert_eq!(
+            run(&mut unset, 3),
+            Err((ProcessorException::EnvironmentCall, 0))
+        );
+
+        // Trap handler is not mapped to memory
+        let mut unmapped = processor(
+            &[
+                0x4000_02b7, // lui x5, 0x40000
+                0x3052_9073, // csrrw x0, mtvec, x5
+                0x0000_0073, // ecall
+            ],
+            &[],
+        );
+        assert_eq!(
+            run(&mut unmapped, 10),
+            Err((
+                ProcessorException::InvalidMemoryAccess(MemoryAccessError::OutOfBounds),
+                0x4000_0000
+            ))
+        );
+    }
+}
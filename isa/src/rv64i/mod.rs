@@ -0,0 +1,209 @@
+//! The RV64I base instruction set.
+//!
+//! RV64I widens the integer registers & address space to 64 bits. Most of its instructions are
+//! shared with RV32I, operating on the full 64-bit registers: Shifts take a 6-bit shift amount, and
+//! the LD, SD, and LWU instructions load & store double-words. The "W" instructions, which use the
+//! new OP-IMM-32 and OP-32 opcodes, perform the 32-bit RV32I operations on the lowest 32 bits of
+//! their operands, sign-extending their results to 64 bits.
+
+pub mod op_32;
+pub mod op_imm_32;
+
+use z2l_core::extension::Extension;
+use z2l_core::processor::hart::Hart;
+use z2l_core::processor::Xlen;
+
+/// An [`Extension`] defining the RV64I base instruction set.
+pub struct RV64I;
+
+impl Extension for RV64I {
+    fn code(&self) -> &'static str {
+        "RV64I"
+    }
+
+    fn name(&self) -> &'static str {
+        "64-bit Base Integer Instruction Set"
+    }
+
+    fn register(&self, hart: &mut Hart) {
+        crate::rv32i::register(hart, Xlen::Rv64);
+        hart.register_opcode(0x1b, Box::new(op_imm_32::OpImm32Handler));
+        hart.register_opcode(0x3b, Box::new(op_32::Op32Handler));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::RV64I;
+    use crate::rv32i::RV32I;
+    use crate::test_utils::{self, execute, hart, reg};
+    use z2l_core::error::{MemoryAccessError, ProcessorException};
+    use z2l_core::instruction::InstructionParts;
+    use z2l_core::processor::Processor;
+
+    /// Create an RV64I processor, with a ROM at 0 containing the provided program, and RAM at
+    /// 0x80000000.
+    fn processor(program: &[u32]) -> Processor {
+        test_utils::processor(vec![Box::new(RV64I)], program)
+    }
+
+    /// Run the provided program to completion, returning the processor.
+    fn run(program: &[u32]) -> Processor {
+        let mut program = program.to_vec();
+        program.push(0x0000_006f); // jal x0, 0
+
+        let mut processor = processor(&program);
+        for _ in 0..program.len() {
+            processor.cycle().unwrap();
+        }
+        processor
+    }
+
+    /// Decode the provided instruction, returning its human-readable format.
+    fn decode(raw: u32) -> Result<String, ProcessorException> {
+        let hart = hart(&[&RV64I]);
+        let parts = InstructionParts::new(raw)?;
+        let handler = hart.opcodes.get(&parts.opcode()).unwrap();
+        Ok(handler.decode(parts, 0, hart.xlen)?.format())
+    }
+
+    #[test]
+    fn misa() {
+        let processor = processor(&[]);
+        let misa = processor.hart.csrs.get(0x301).unwrap().value;
+        assert_eq!(misa >> 62, 2);
+        assert_ne!(misa & (1 << 8), 0);
+    }
+
+    #[test]
+    fn decode_rv64_instructions() {
+        let cases: &[(u32, &str)] = &[
+            (0x0081_3283, "ld x5, 0x00000008(x2)"),
+            (0x0081_6283, "lwu x5, 0x00000008(x2)"),
+            (0x0051_3423, "sd x5, 0x00000008(x2)"),
+            (0x03f2_9293, "slli x5, x5, 63"),
+            (0x4202_d293, "srai x5, x5, 32"),
+            (0xfff2_829b, "addiw x5, x5, 0xffffffff"),
+            (0x01f2_929b, "slliw x5, x5, 31"),
+            (0x4012_d29b, "sraiw x5, x5, 1"),
+            (0x0062_82bb, "addw x5, x5, x6"),
+            (0x4062_82bb, "subw x5, x5, x6"),
+            (0x0062_92bb, "sllw x5, x5, x6"),
+            (0x4062_d2bb, "sraw x5, x5, x6"),
+        ];
+        for (raw, expected) in cases {
+            assert_eq!(decode(*raw).unwrap(), *expected);
+        }
+
+        // The W shifts only take a 5-bit shift amount
+        assert_eq!(
+            decode(0x0202_929b), // slliw x5, x5, 32
+            Err(ProcessorException::IllegalInstruction)
+        );
+    }
+
+    #[test]
+    fn load_store_doubleword() {
+        let processor = run(&[
+            0x0010_0113, // addi x2, x0, 1
+            0x01f1_1113, // slli x2, x2, 31
+            0xfff0_0293, // addi x5, x0, -1
+            0x0202_d293, // srli x5, x5, 32
+            0x0051_3423, // sd x5, 8(x2)
+            0x0081_3303, // ld x6, 8(x2)
+            0x0081_2383, // lw x7, 8(x2)
+            0x0081_6403, // lwu x8, 8(x2)
+        ]);
+
+        assert_eq!(reg(&processor, 2), 0x8000_0000);
+        assert_eq!(reg(&processor, 6), 0xffff_ffff);
+        assert_eq!(reg(&processor, 7), -1);
+        assert_eq!(reg(&processor, 8), 0xffff_ffff);
+    }
+
+    #[test]
+    fn store_top_of_address_space() {
+        let mut processor = processor(&[
+            0xfff0_0093, // addi x1, x0, -1
+            0x0000_b023, // sd x0, 0(x1)
+        ]);
+        processor.cycle().unwrap();
+        processor.cycle().unwrap();
+
+        // The store extends beyond the top of the address space, rather than wrapping
+        let out_of_bounds = ProcessorException::InvalidMemoryAccess(MemoryAccessError::OutOfBounds);
+        assert_eq!(processor.cycle(), Err((out_of_bounds, 4)));
+    }
+
+    #[test]
+    fn word_instructions() {
+        let processor = run(&[
+            0x7fff_f2b7, // lui x5, 0x7ffff
+            0x0052_833b, // addw x6, x5, x5
+            0x0052_83b3, // add x7, x5, x5
+            0x4043_549b, // sraiw x9, x6, 4
+            0x03c3_5513, // srli x10, x6, 60
+        ]);
+
+        // ADDW overflows at 32 bits, & sign-extends the result
+        assert_eq!(reg(&processor, 6), -0x2000);
+        assert_eq!(reg(&processor, 7), 0xffff_e000);
+        assert_eq!(reg(&processor, 9), -0x200);
+        assert_eq!(reg(&processor, 10), 0xf);
+    }
+
+    #[test]
+    fn shift_immediate() {
+        const SLLI: u32 = 0x0200_9193; // slli x3, x1, 32
+        const SRLI: u32 = 0x03f0_d193; // srli x3, x1, 63
+        const SRAI: u32 = 0x43f0_d193; // srai x3, x1, 63
+
+        // RV64I shifts take a 6-bit shift amount
+        let mut rv64 = hart(&[&RV64I]);
+        assert_eq!(execute(&mut rv64, SLLI, 1, 0), 0x1_0000_0000);
+        assert_eq!(execute(&mut rv64, SLLI, 0x8000_0001, 0), 0x8000_0001 << 32);
+        assert_eq!(execute(&mut rv64, SRLI, -1, 0), 1);
+        assert_eq!(execute(&mut rv64, SRAI, i64::MIN, 0), -1);
+        assert_eq!(execute(&mut rv64, SRAI, i64::MAX, 0), 0);
+        assert_eq!(execute(&mut rv64, 0x4200_d193, i64::MIN, 0), -0x8000_0000); // srai x3, x1, 32
+
+        // On RV32, shift amounts with bit 5 set are reserved
+        let rv32 = hart(&[&RV32I]);
+        let handler = rv32.opcodes.get(&0x13).unwrap();
+        for raw in [SLLI, SRLI, SRAI] {
+            let parts = InstructionParts::new(raw).unwrap();
+            assert_eq!(
+                handler.decode(parts, 0, rv32.xlen).err(),
+                Some(ProcessorException::IllegalInstruction)
+            );
+        }
+    }
+
+    #[test]
+    fn load_store_data() {
+        let program = [
+            0x0000_0097, // auipc x1, 0
+            0x0200_b283, // ld x5, 32(x1)
+            0x0240_e303, // lwu x6, 36(x1)
+            0x0240_a383, // lw x7, 36(x1)
+            0x0200_e403, // lwu x8, 32(x1)
+            0x0250_b423, // sd x5, 40(x1)
+            0x0280_b483, // ld x9, 40(x1)
+            0x0000_006f, // jal x0, 0
+        ];
+        let data = 0x8765_4321_0fed_cba9u64.to_le_bytes();
+        let mut processor = test_utils::processor_in_ram(vec![Box::new(RV64I)], &program, &data);
+        for _ in 0..program.len() {
+            processor.cycle().unwrap();
+        }
+
+        // LD & SD transfer all 64 bits, in little-endian order
+        assert_eq!(reg(&processor, 5), 0x8765_4321_0fed_cba9u64 as i64);
+        assert_eq!(reg(&processor, 9), 0x8765_4321_0fed_cba9u64 as i64);
+
+        // LWU zero-extends the loaded word, whereas LW sign-extends it
+        assert_eq!(reg(&processor, 6), 0x8765_4321);
+        assert_eq!(reg(&processor, 7), 0x8765_4321u32 as i32 as i64);
+        assert_eq!(reg(&processor, 8), 0x0fed_cba9);
+    }
+}
@@ -0,0 +1,95 @@
+//! OP-32 opcode instructions (ADDW, SUBW, SLLW, SRLW, SRAW).
+//!
+//! These instructions are the RV64 word variants of the OP instructions: They operate on the lowest
+//! 32 bits of rs1 & rs2, and sign-extend the 32-bit result into rd.
+
+use crate::rv32i::op::{ArithmeticInstruction, SllInstruction, SrInstruction};
+use z2l_core::error::ProcessorException;
+use z2l_core::extension::OpcodeHandler;
+use z2l_core::instruction::{Instruction, InstructionParts};
+use z2l_core::processor::Xlen;
+
+/// OP-32 opcode handler.
+pub struct Op32Handler;
+
+impl OpcodeHandler for Op32Handler {
+    fn decode(
+        &self,
+        instruction: InstructionParts,
+        _pc: u64,
+        _xlen: Xlen,
+    ) -> Result<Box<dyn Instruction>, ProcessorException> {
+        let instruction = instruction.into_word()?;
+
+        Ok(match (instruction.funct3, instruction.funct7) {
+            (0b000, _) => Box::new(ArithmeticInstruction::new_word(&instruction)?),
+            (0b001, 0b0000000) => Box::new(SllInstruction::new_word(&instruction)),
+            (0b101, _) => Box::new(SrInstruction::new_word(&instruction)?),
+            _ => return Err(ProcessorException::IllegalInstruction),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::rv64i::RV64I;
+    use crate::test_utils::{execute, hart};
+    use z2l_core::error::ProcessorException;
+    use z2l_core::instruction::InstructionParts;
+
+    const ADDW: u32 = 0x0020_81bb; // addw x3, x1, x2
+    const SUBW: u32 = 0x4020_81bb; // subw x3, x1, x2
+    const SLLW: u32 = 0x0020_91bb; // sllw x3, x1, x2
+    const SRLW: u32 = 0x0020_d1bb; // srlw x3, x1, x2
+    const SRAW: u32 = 0x4020_d1bb; // sraw x3, x1, x2
+
+    #[test]
+    fn arithmetic() {
+        let mut hart = hart(&[&RV64I]);
+
+        // The result overflows at 32 bits, & is sign-extended
+        assert_eq!(execute(&mut hart, ADDW, 0x7fff_ffff, 1), i32::MIN as i64);
+        assert_eq!(execute(&mut hart, ADDW, -1, 0), -1);
+        assert_eq!(execute(&mut hart, SUBW, 0, 1), -1);
+        assert_eq!(execute(&mut hart, SUBW, i32::MIN as i64, 1), 0x7fff_ffff);
+
+        // The upper 32 bits of the operands are ignored
+        assert_eq!(execute(&mut hart, ADDW, 0x1_0000_0001, -0xffff_ffff), 2);
+        assert_eq!(execute(&mut hart, SUBW, 0x5, 0x7_0000_0002), 3);
+    }
+
+    #[test]
+    fn shift() {
+        let mut hart = hart(&[&RV64I]);
+        assert_eq!(execute(&mut hart, SLLW, 1, 31), i32::MIN as i64);
+        assert_eq!(execute(&mut hart, SLLW, 0x1_0000_0001, 4), 0x10);
+        assert_eq!(execute(&mut hart, SRLW, -1, 1), 0x7fff_ffff);
+        assert_eq!(execute(&mut hart, SRLW, -0x1_0000_0000, 0), 0);
+        assert_eq!(execute(&mut hart, SRAW, 0x8000_0000, 4), -0x0800_0000);
+        assert_eq!(execute(&mut hart, SRAW, 0x1_7fff_ffff, 30), 1);
+
+        // The shift amount is the lowest 5 bits of rs2, & the result is sign-extended even when
+        // shifting by 0
+        assert_eq!(execute(&mut hart, SLLW, 1, 33), 2);
+        assert_eq!(execute(&mut hart, SRLW, 0x8000_0000, 32), i32::MIN as i64);
+        assert_eq!(execute(&mut hart, SRAW, -0x10, 0x24), -1);
+    }
+
+    #[test]
+    fn illegal_instructions() {
+        let hart = hart(&[&RV64I]);
+        let handler = hart.opcodes.get(&0x3b).unwrap();
+        for raw in [
+            0x0020_a1bb, // funct3 = 010
+            0x2020_91bb, // sllw with funct7 = 0010000
+            0x2020_d1bb, // srlw with funct7 = 0010000
+            0x2020_81bb, // addw with funct7 = 0010000
+        ] {
+            let parts = InstructionParts::new(raw).unwrap();
+            assert_eq!(
+                handler.decode(parts, 0, hart.xlen).err(),
+                Some(ProcessorException::IllegalInstruction)
+            );
+        }
+    }
+}
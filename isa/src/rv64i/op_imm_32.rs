@@ -0,0 +1,86 @@
+//! OP-IMM-32 opcode instructions (ADDIW, SLLIW, SRLIW, SRAIW).
+//!
+//! These instructions are the RV64 word variants of the OP-IMM instructions: They operate on the
+//! lowest 32 bits of rs1, and sign-extend the 32-bit result into rd.
+
+use crate::rv32i::op_imm::{AddIInstruction, SllIInstruction, SrIInstruction};
+use z2l_core::error::ProcessorException;
+use z2l_core::extension::OpcodeHandler;
+use z2l_core::instruction::{Instruction, InstructionParts};
+use z2l_core::processor::Xlen;
+
+/// OP-IMM-32 opcode handler.
+pub struct OpImm32Handler;
+
+impl OpcodeHandler for OpImm32Handler {
+    fn decode(
+        &self,
+        instruction: InstructionParts,
+        _pc: u64,
+        _xlen: Xlen,
+    ) -> Result<Box<dyn Instruction>, ProcessorException> {
+        let instruction = instruction.into_word()?;
+
+        Ok(match instruction.funct3 {
+            0b000 => Box::new(AddIInstruction::new_word(&instruction)),
+            0b001 => Box::new(SllIInstruction::new_word(&instruction)?),
+            0b101 => Box::new(SrIInstruction::new_word(&instruction)?),
+            _ => return Err(ProcessorException::IllegalInstruction),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::rv64i::RV64I;
+    use crate::test_utils::{execute, hart};
+    use z2l_core::error::ProcessorException;
+    use z2l_core::instruction::InstructionParts;
+
+    const ADDIW: u32 = 0x0010_819b; // addiw x3, x1, 1
+    const SEXT_W: u32 = 0x0000_819b; // addiw x3, x1, 0
+    const SLLIW: u32 = 0x01f0_919b; // slliw x3, x1, 31
+    const SRLIW: u32 = 0x0040_d19b; // srliw x3, x1, 4
+    const SRAIW: u32 = 0x4040_d19b; // sraiw x3, x1, 4
+
+    #[test]
+    fn add() {
+        let mut hart = hart(&[&RV64I]);
+        assert_eq!(execute(&mut hart, ADDIW, 0x7fff_ffff, 0), i32::MIN as i64);
+        assert_eq!(execute(&mut hart, ADDIW, -1, 0), 0);
+        assert_eq!(execute(&mut hart, ADDIW, 0x1_ffff_fffe, 0), -1);
+
+        // ADDIW with an immediate of 0 sign-extends the lowest 32 bits
+        assert_eq!(execute(&mut hart, SEXT_W, 0xffff_ffff, 0), -1);
+        assert_eq!(execute(&mut hart, SEXT_W, 0x1_0000_0005, 0), 5);
+    }
+
+    #[test]
+    fn shift() {
+        let mut hart = hart(&[&RV64I]);
+        assert_eq!(execute(&mut hart, SLLIW, 1, 0), i32::MIN as i64);
+        assert_eq!(execute(&mut hart, SLLIW, 2, 0), 0);
+        assert_eq!(execute(&mut hart, SRLIW, -1, 0), 0x0fff_ffff);
+        assert_eq!(execute(&mut hart, SRLIW, 0x1_0000_0010, 0), 1);
+        assert_eq!(execute(&mut hart, SRAIW, 0x8000_0000, 0), -0x0800_0000);
+        assert_eq!(execute(&mut hart, SRAIW, 0x1_7fff_ffff, 0), 0x07ff_ffff);
+    }
+
+    #[test]
+    fn illegal_instructions() {
+        let hart = hart(&[&RV64I]);
+        let handler = hart.opcodes.get(&0x1b).unwrap();
+        for raw in [
+            0x0000_a19b, // funct3 = 010
+            0x0200_919b, // slliw x3, x1, 32
+            0x0200_d19b, // srliw x3, x1, 32
+            0x4200_d19b, // sraiw x3, x1, 32
+        ] {
+            let parts = InstructionParts::new(raw).unwrap();
+            assert_eq!(
+                handler.decode(parts, 0, hart.xlen).err(),
+                Some(ProcessorException::IllegalInstruction)
+            );
+        }
+    }
+}
@@ -0,0 +1,102 @@
+//! Fixtures shared by the instruction set tests.
+
+use std::sync::{Arc, Mutex};
+use z2l_core::extension::Extension;
+use z2l_core::instruction::InstructionParts;
+use z2l_core::mmu::MMU;
+use z2l_core::processor::hart::Hart;
+use z2l_core::processor::{Processor, ProcessorConfig};
+use z2l_core::ram::RAM;
+use z2l_core::rom::ROM;
+
+/// Create a processor with the provided extensions, with a ROM at 0 containing the provided
+/// program, and RAM at 0x80000000.
+pub fn processor(extensions: Vec<Box<dyn Extension>>, program: &[u32]) -> Processor {
+    let rom = program
+        .iter()
+        .flat_map(|i| i.to_le_bytes())
+        .collect::<Vec<_>>();
+
+    let mut mmu = MMU::new();
+    mmu.map(0, 0x100, Box::new(ROM::new(rom))).unwrap();
+    mmu.map(0x8000_0000, 0x100, Box::new(RAM::new(0x100)))
+        .unwrap();
+    Processor::new(ProcessorConfig {
+        harts: 1,
+        mmu: Arc::new(Mutex::new(mmu)),
+        extensions,
+        reset_vector: 0,
+    })
+}
+
+/// Create a processor with the provided extensions, running the provided program from RAM at
+/// 0x80000000, with the provided data following it.
+pub fn processor_in_ram(
+    extensions: Vec<Box<dyn Extension>>,
+    program: &[u32],
+    data: &[u8],
+) -> Processor {
+    let mut ram = program
+        .iter()
+        .flat_map(|i| i.to_le_bytes())
+        .collect::<Vec<_>>();
+    ram.extend(data);
+
+    let mut mmu = MMU::new();
+    mmu.map(0x8000_0000, 0x100, Box::new(RAM::new(0x100)))
+        .unwrap();
+    mmu.store_bytes(0x8000_0000, &ram).unwrap();
+    Processor::new(ProcessorConfig {
+        harts: 1,
+        mmu: Arc::new(Mutex::new(mmu)),
+        extensions,
+        reset_vector: 0x8000_0000,
+    })
+}
+
+/// Get the value of the provided integer register.
+pub fn reg(processor: &Processor, n: u8) -> i64 {
+    processor.hart.registers.get(&n).unwrap().load().unwrap()
+}
+
+/// Create a hart with the provided extensions, registered in order.
+pub fn hart(extensions: &[&dyn Extension]) -> Hart {
+    let mut hart = Hart::new();
+    for extension in extensions {
+        extension.register(&mut hart);
+    }
+    hart
+}
+
+/// Decode the provided instruction, returning its human-readable format.
+pub fn decode(hart: &Hart, raw: u32) -> String {
+    let parts = InstructionParts::new(raw).unwrap();
+    let handler = hart.opcodes.get(&parts.opcode()).unwrap();
+    handler.decode(parts, 0, hart.xlen).unwrap().format()
+}
+
+/// Execute the provided instruction with x1 = `a` and x2 = `b`, returning the value of x3.
+pub fn execute(hart: &mut Hart, raw: u32, a: i64, b: i64) -> i64 {
+    hart.registers.get_mut(&1).unwrap().store(a).unwrap();
+    hart.registers.get_mut(&2).unwrap().store(b).unwrap();
+
+    let parts = InstructionParts::new(raw).unwrap();
+    let handler = hart.opcodes.get(&parts.opcode()).unwrap();
+    let instruction = handler.decode(parts, 0, hart.xlen).unwrap();
+    instruction.execute(&mut hart.registers, 0).unwrap();
+
+    hart.registers.get(&3).unwrap().load().unwrap()
+}
+
+/// Execute the provided RV64 instruction with x1 = `a` and x2 = `b`, returning the value of x3 as
+/// an unsigned value.
+pub fn execute64(hart: &mut Hart, raw: u32, a: u64, b: u64) -> u64 {
+    execute(hart, raw, a as i64, b as i64) as u64
+}
+
+/// Execute the provided RV32 instruction with x1 = `a` and x2 = `b`, returning the value of x3.
+pub fn execute32(hart: &mut Hart, raw: u32, a: u32, b: u32) -> u32 {
+    let result = execute(hart, raw, a as i32 as i64, b as i32 as i64);
+    assert_eq!(result, result as i32 as i64, "Result is sign-extended");
+    result as u32
+}
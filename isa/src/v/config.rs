@@ -0,0 +1,132 @@
+//! Configuration-setting instructions (VSETVLI, VSETIVLI, VSETVL).
+//!
+//! These instructions set `vtype`, then set `vl` to the application vector length (AVL), limited to
+//! the maximum vector length for the new setting (VLMAX), and write `vl` to `rd`. If `rs1` is `x0`,
+//! `vl` is instead set to VLMAX, or if `rd` is also `x0`, is kept (limited to the new VLMAX). If
+//! the new setting is not supported, `vill` is set and `vl` is set to zero.
+
+use super::{read_x, write_x, VectorState, Vtype};
+use std::fmt;
+use std::sync::{Arc, Mutex};
+use z2l_core::error::ProcessorException;
+use z2l_core::instruction::{Instruction, InstructionResult, InstructionWordParts};
+use z2l_core::processor::register::RegisterFile;
+use z2l_core::processor::Xlen;
+
+/// Source of a value used by a configuration-setting instruction.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+enum Source {
+    Register(u8),
+    Immediate(u64),
+}
+
+impl fmt::Display for Source {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Source::Register(reg) => write!(f, "x{}", reg),
+            Source::Immediate(imm) => write!(f, "{}", imm),
+        }
+    }
+}
+
+/// VSETVLI, VSETIVLI, or VSETVL instruction.
+pub struct VsetvlInstruction {
+    state: Arc<Mutex<VectorState>>,
+    avl: Source,
+    vtype: Source,
+    dest: u8,
+    xlen: Xlen,
+}
+
+impl VsetvlInstruction {
+    /// Create a new VsetvlInstruction, operating on the provided vector state, for a hart with the
+    /// provided XLEN.
+    pub fn new(
+        instruction: &InstructionWordParts,
+        state: Arc<Mutex<VectorState>>,
+        xlen: Xlen,
+    ) -> Result<Self, ProcessorException> {
+        let raw = instruction.raw as u64;
+        let (avl, vtype) = match raw >> 30 {
+            0b00 | 0b01 => (
+                Source::Register(instruction.rs1),
+                Source::Immediate((raw >> 20) & 0x7ff),
+            ),
+            0b11 => (
+                Source::Immediate(instruction.rs1 as u64),
+                Source::Immediate((raw >> 20) & 0x3ff),
+            ),
+            _ if instruction.funct7 == 0b1000000 => (
+                Source::Register(instruction.rs1),
+                Source::Register(instruction.rs2),
+            ),
+            _ => return Err(ProcessorException::IllegalInstruction),
+        };
+
+        Ok(Self {
+            state,
+            avl,
+            vtype,
+            dest: instruction.rd,
+            xlen,
+        })
+    }
+}
+
+impl Instruction for VsetvlInstruction {
+    fn execute(
+        &self,
+        registers: &mut RegisterFile,
+        _mem: i64,
+    ) -> Result<InstructionResult, ProcessorException> {
+        let mut state = self.state.lock().unwrap();
+        let vtype = match self.vtype {
+            Source::Register(reg) => self.xlen.zero_extend(read_x(registers, reg)?),
+            Source::Immediate(imm) => imm,
+        };
+        let vtype = Vtype::decode(vtype, state.elen());
+
+        let avl = match self.avl {
+            Source::Immediate(imm) => imm,
+            Source::Register(0) if self.dest != 0 => u64::MAX,
+            Source::Register(0) => state.vl as u64,
+            Source::Register(reg) => self.xlen.zero_extend(read_x(registers, reg)?),
+        };
+        state.vl = match vtype {
+            Some(vtype) => avl.min(vtype.vlmax(state.vlen()) as u64) as usize,
+            None => 0,
+        };
+        state.vtype = vtype;
+        state.vstart = 0;
+
+        write_x(registers, self.dest, state.vl as i64)?;
+        Ok(InstructionResult::default())
+    }
+
+    fn format(&self) -> String {
+        let elen = self.state.lock().unwrap().elen();
+        match self.vtype {
+            Source::Register(vtype) => format!("vsetvl x{}, {}, x{}", self.dest, self.avl, vtype),
+            Source::Immediate(vtype) => {
+                let name = match self.avl {
+                    Source::Register(_) => "vsetvli",
+                    Source::Immediate(_) => "vsetivli",
+                };
+                let vtype = Setting(vtype, elen);
+                format!("{} x{}, {}, {}", name, self.dest, self.avl, vtype)
+            }
+        }
+    }
+}
+
+/// Formats an immediate `vtype` setting, for a hart with the provided ELEN.
+struct Setting(u64, u32);
+
+impl fmt::Display for Setting {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match Vtype::decode(self.0, self.1) {
+            Some(vtype) => write!(f, "{}", vtype),
+            None => write!(f, "0x{:03x}", self.0),
+        }
+    }
+}
@@ -0,0 +1,530 @@
+//! Integer arithmetic instructions.
+//!
+//! These instructions operate element-wise on `vs2` and the first source operand (`vs1`, `rs1`, or
+//! an immediate), writing a vector to `vd`: Add, subtract, minimum/maximum, bitwise logical, shift,
+//! multiply, divide, multiply-add, merge & move, and zero/sign extension. The widening
+//! instructions write elements of 2*SEW bits, and the `.w` forms also read `vs2` as 2*SEW bits,
+//! while the narrowing shifts read `vs2` as 2*SEW bits and write SEW bits. Division by zero and
+//! signed overflow don't raise exceptions, and produce the same results as the M extension.
+//!
+//! The comparison instructions instead write a mask register, with one bit per element.
+
+use super::state::{check_group, scale, sign_extend, truncate};
+use super::{
+    funct6, mask_operand, masked, Operand, VectorState, OPIVI, OPIVV, OPIVX, OPMVV, OPMVX,
+};
+use std::sync::{Arc, Mutex};
+use z2l_core::error::ProcessorException;
+use z2l_core::instruction::{Instruction, InstructionResult, InstructionWordParts};
+use z2l_core::processor::register::RegisterFile;
+
+/// Integer operation to perform on each element.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+enum Operation {
+    Add,
+    Sub,
+    ReverseSub,
+    MinUnsigned,
+    Min,
+    MaxUnsigned,
+    Max,
+    And,
+    Or,
+    Xor,
+    ShiftLeft,
+    ShiftRightLogical,
+    ShiftRightArithmetic,
+    Merge,
+    Move,
+    Mul,
+    MulHigh,
+    MulHighUnsigned,
+    MulHighSignedUnsigned,
+    DivUnsigned,
+    Div,
+    RemUnsigned,
+    Rem,
+    MultiplyAdd,
+    NegMultiplySubAdd,
+    MultiplyAddOverwrite,
+    NegMultiplySubOverwrite,
+    WideAddUnsigned,
+    WideAdd,
+    WideSubUnsigned,
+    WideSub,
+    WideAddUnsignedWide,
+    WideAddWide,
+    WideSubUnsignedWide,
+    WideSubWide,
+    WideMulUnsigned,
+    WideMul,
+    WideMulSignedUnsigned,
+    WideMultiplyAddUnsigned,
+    WideMultiplyAdd,
+    WideMultiplyAddSignedUnsigned,
+    WideMultiplyAddUnsignedSigned,
+    NarrowShiftRightLogical,
+    NarrowShiftRightArithmetic,
+    ZeroExtend(u32),
+    SignExtend(u32),
+}
+
+impl Operation {
+    /// Mnemonic for this operation, without the suffix identifying the kinds of operands.
+    fn name(&self) -> &'static str {
+        match self {
+            Operation::Add => "vadd",
+            Operation::Sub => "vsub",
+            Operation::ReverseSub => "vrsub",
+            Operation::MinUnsigned => "vminu",
+            Operation::Min => "vmin",
+            Operation::MaxUnsigned => "vmaxu",
+            Operation::Max => "vmax",
+            Operation::And => "vand",
+            Operation::Or => "vor",
+            Operation::Xor => "vxor",
+            Operation::ShiftLeft => "vsll",
+            Operation::ShiftRightLogical => "vsrl",
+            Operation::ShiftRightArithmetic => "vsra",
+            Operation::Merge => "vmerge",
+            Operation::Move => "vmv",
+            Operation::Mul => "vmul",
+            Operation::MulHigh => "vmulh",
+            Operation::MulHighUnsigned => "vmulhu",
+            Operation::MulHighSignedUnsigned => "vmulhsu",
+            Operation::DivUnsigned => "vdivu",
+            Operation::Div => "vdiv",
+            Operation::RemUnsigned => "vremu",
+            Operation::Rem => "vrem",
+            Operation::MultiplyAdd => "vmacc",
+            Operation::NegMultiplySubAdd => "vnmsac",
+            Operation::MultiplyAddOverwrite => "vmadd",
+            Operation::NegMultiplySubOverwrite => "vnmsub",
+            Operation::WideAddUnsigned | Operation::WideAddUnsignedWide => "vwaddu",
+            Operation::WideAdd | Operation::WideAddWide => "vwadd",
+            Operation::WideSubUnsigned | Operation::WideSubUnsignedWide => "vwsubu",
+            Operation::WideSub | Operation::WideSubWide => "vwsub",
+            Operation::WideMulUnsigned => "vwmulu",
+            Operation::WideMul => "vwmul",
+            Operation::WideMulSignedUnsigned => "vwmulsu",
+            Operation::WideMultiplyAddUnsigned => "vwmaccu",
+            Operation::WideMultiplyAdd => "vwmacc",
+            Operation::WideMultiplyAddSignedUnsigned => "vwmaccsu",
+            Operation::WideMultiplyAddUnsignedSigned => "vwmaccus",
+            Operation::NarrowShiftRightLogical => "vnsrl",
+            Operation::NarrowShiftRightArithmetic => "vnsra",
+            Operation::ZeroExtend(_) => "vzext",
+            Operation::SignExtend(_) => "vsext",
+        }
+    }
+
+    /// Base-2 logarithms of the widths of the elements of the destination & `vs2`, relative to
+    /// SEW.
+    fn widths(&self) -> (i32, i32) {
+        match self {
+            Operation::WideAddUnsigned
+            | Operation::WideAdd
+            | Operation::WideSubUnsigned
+            | Operation::WideSub
+            | Operation::WideMulUnsigned
+            | Operation::WideMul
+            | Operation::WideMulSignedUnsigned
+            | Operation::WideMultiplyAddUnsigned
+            | Operation::WideMultiplyAdd
+            | Operation::WideMultiplyAddSignedUnsigned
+            | Operation::WideMultiplyAddUnsignedSigned => (1, 0),
+            Operation::WideAddUnsignedWide
+            | Operation::WideAddWide
+            | Operation::WideSubUnsignedWide
+            | Operation::WideSubWide => (1, 1),
+            Operation::NarrowShiftRightLogical | Operation::NarrowShiftRightArithmetic => (0, 1),
+            Operation::ZeroExtend(factor) | Operation::SignExtend(factor) => {
+                (0, -(factor.trailing_zeros() as i32))
+            }
+            _ => (0, 0),
+        }
+    }
+
+    /// Determine whether this operation reads the destination as an operand.
+    fn reads_dest(&self) -> bool {
+        matches!(
+            self,
+            Operation::MultiplyAdd
+                | Operation::NegMultiplySubAdd
+                | Operation::MultiplyAddOverwrite
+                | Operation::NegMultiplySubOverwrite
+                | Operation::WideMultiplyAddUnsigned
+                | Operation::WideMultiplyAdd
+                | Operation::WideMultiplyAddSignedUnsigned
+                | Operation::WideMultiplyAddUnsignedSigned
+        )
+    }
+
+    /// Compute the result for one element, where `a` is the element of `vs2`, `b` is the element
+    /// of the first source operand, `d` is the element of the destination, and `sew` is SEW.
+    ///
+    /// Each operand is zero-extended from its own width, and the result is truncated by the caller.
+    fn compute(&self, a: u64, b: u64, d: u64, sew: u32) -> u64 {
+        let (ua, ub, ud) = (a as u128 as i128, b as u128 as i128, d as u128 as i128);
+        let (sa, sb) = (sign_extend(a, sew) as i128, sign_extend(b, sew) as i128);
+        let wide = sew * 2;
+        let shift = |bits: u32| (b & (bits as u64 - 1)) as u32;
+
+        let result: i128 = match self {
+            Operation::Add => ua + ub,
+            Operation::Sub => ua - ub,
+            Operation::ReverseSub => ub - ua,
+            Operation::MinUnsigned => ua.min(ub),
+            Operation::Min => sa.min(sb),
+            Operation::MaxUnsigned => ua.max(ub),
+            Operation::Max => sa.max(sb),
+            Operation::And => ua & ub,
+            Operation::Or => ua | ub,
+            Operation::Xor => ua ^ ub,
+            Operation::ShiftLeft => ua << shift(sew),
+            Operation::ShiftRightLogical => ua >> shift(sew),
+            Operation::ShiftRightArithmetic => sa >> shift(sew),
+            Operation::Merge | Operation::Move => ub,
+            Operation::Mul => ua.wrapping_mul(ub),
+            Operation::MulHigh => (sa * sb) >> sew,
+            Operation::MulHighUnsigned => ((a as u128 * b as u128) >> sew) as i128,
+            Operation::MulHighSignedUnsigned => (sa * ub) >> sew,
+            Operation::DivUnsigned if ub == 0 => -1,
+            Operation::DivUnsigned => ua / ub,
+            Operation::Div if sb == 0 => -1,
+            Operation::Div => sa / sb,
+            Operation::RemUnsigned if ub == 0 => ua,
+            Operation::RemUnsigned => ua % ub,
+            Operation::Rem if sb == 0 => sa,
+            Operation::Rem => sa % sb,
+            Operation::MultiplyAdd => ud.wrapping_add(ub.wrapping_mul(ua)),
+            Operation::NegMultiplySubAdd => ud.wrapping_sub(ub.wrapping_mul(ua)),
+            Operation::MultiplyAddOverwrite => ub.wrapping_mul(ud).wrapping_add(ua),
+            Operation::NegMultiplySubOverwrite => ua.wrapping_sub(ub.wrapping_mul(ud)),
+            Operation::WideAddUnsigned | Operation::WideAddUnsignedWide => ua + ub,
+            Operation::WideSubUnsigned | Operation::WideSubUnsignedWide => ua - ub,
+            Operation::WideAdd => sa + sb,
+            Operation::WideSub => sa - sb,
+            Operation::WideAddWide => sign_extend(a, wide) as i128 + sb,
+            Operation::WideSubWide => sign_extend(a, wide) as i128 - sb,
+            Operation::WideMulUnsigned => ua * ub,
+            Operation::WideMul => sa * sb,
+            Operation::WideMulSignedUnsigned => sa * ub,
+            Operation::WideMultiplyAddUnsigned => ud + ub * ua,
+            Operation::WideMultiplyAdd => ud + sb * sa,
+            Operation::WideMultiplyAddSignedUnsigned => ud + sb * ua,
+            Operation::WideMultiplyAddUnsignedSigned => ud + ub * sa,
+            Operation::NarrowShiftRightLogical => ua >> shift(wide),
+            Operation::NarrowShiftRightArithmetic => sign_extend(a, wide) as i128 >> shift(wide),
+            Operation::ZeroExtend(_) => ua,
+            Operation::SignExtend(factor) => sign_extend(a, sew / factor) as i128,
+        };
+        result as u64
+    }
+}
+
+/// Integer arithmetic instruction, writing a vector.
+pub struct IntegerInstruction {
+    state: Arc<Mutex<VectorState>>,
+    operation: Operation,
+    src1: Operand,
+    src2: u8,
+    dest: u8,
+    masked: bool,
+}
+
+impl IntegerInstruction {
+    /// Create a new IntegerInstruction, operating on the provided vector state.
+    pub fn new(
+        instruction: &InstructionWordParts,
+        state: Arc<Mutex<VectorState>>,
+    ) -> Result<Self, ProcessorException> {
+        let masked = masked(instruction);
+        let funct6 = funct6(instruction);
+        let operation = match (funct6, instruction.funct3) {
+            (0b000000, OPIVV | OPIVX | OPIVI) => Operation::Add,
+            (0b000010, OPIVV | OPIVX) => Operation::Sub,
+            (0b000011, OPIVX | OPIVI) => Operation::ReverseSub,
+            (0b000100, OPIVV | OPIVX) => Operation::MinUnsigned,
+            (0b000101, OPIVV | OPIVX) => Operation::Min,
+            (0b000110, OPIVV | OPIVX) => Operation::MaxUnsigned,
+            (0b000111, OPIVV | OPIVX) => Operation::Max,
+            (0b001001, OPIVV | OPIVX | OPIVI) => Operation::And,
+            (0b001010, OPIVV | OPIVX | OPIVI) => Operation::Or,
+            (0b001011, OPIVV | OPIVX | OPIVI) => Operation::Xor,
+            (0b010111, OPIVV | OPIVX | OPIVI) if masked => Operation::Merge,
+            (0b010111, OPIVV | OPIVX | OPIVI) if instruction.rs2 == 0 => Operation::Move,
+            (0b100101, OPIVV | OPIVX | OPIVI) => Operation::ShiftLeft,
+            (0b101000, OPIVV | OPIVX | OPIVI) => Operation::ShiftRightLogical,
+            (0b101001, OPIVV | OPIVX | OPIVI) => Operation::ShiftRightArithmetic,
+            (0b101100, OPIVV | OPIVX | OPIVI) => Operation::NarrowShiftRightLogical,
+            (0b101101, OPIVV | OPIVX | OPIVI) => Operation::NarrowShiftRightArithmetic,
+            (0b010010, OPMVV) => match instruction.rs1 {
+                0b00010 => Operation::ZeroExtend(8),
+                0b00011 => Operation::SignExtend(8),
+                0b00100 => Operation::ZeroExtend(4),
+                0b00101 => Operation::SignExtend(4),
+                0b00110 => Operation::ZeroExtend(2),
+                0b00111 => Operation::SignExtend(2),
+                _ => return Err(ProcessorException::IllegalInstruction),
+            },
+            (0b100000, OPMVV | OPMVX) => Operation::DivUnsigned,
+            (0b100001, OPMVV | OPMVX) => Operation::Div,
+            (0b100010, OPMVV | OPMVX) => Operation::RemUnsigned,
+            (0b100011, OPMVV | OPMVX) => Operation::Rem,
+            (0b100100, OPMVV | OPMVX) => Operation::MulHighUnsigned,
+            (0b100101, OPMVV | OPMVX) => Operation::Mul,
+            (0b100110, OPMVV | OPMVX) => Operation::MulHighSignedUnsigned,
+            (0b100111, OPMVV | OPMVX) => Operation::MulHigh,
+            (0b101001, OPMVV | OPMVX) => Operation::MultiplyAddOverwrite,
+            (0b101011, OPMVV | OPMVX) => Operation::NegMultiplySubOverwrite,
+            (0b101101, OPMVV | OPMVX) => Operation::MultiplyAdd,
+            (0b101111, OPMVV | OPMVX) => Operation::NegMultiplySubAdd,
+            (0b110000, OPMVV | OPMVX) => Operation::WideAddUnsigned,
+            (0b110001, OPMVV | OPMVX) => Operation::WideAdd,
+            (0b110010, OPMVV | OPMVX) => Operation::WideSubUnsigned,
+            (0b110011, OPMVV | OPMVX) => Operation::WideSub,
+            (0b110100, OPMVV | OPMVX) => Operation::WideAddUnsignedWide,
+            (0b110101, OPMVV | OPMVX) => Operation::WideAddWide,
+            (0b110110, OPMVV | OPMVX) => Operation::WideSubUnsignedWide,
+            (0b110111, OPMVV | OPMVX) => Operation::WideSubWide,
+            (0b111000, OPMVV | OPMVX) => Operation::WideMulUnsigned,
+            (0b111010, OPMVV | OPMVX) => Operation::WideMulSignedUnsigned,
+            (0b111011, OPMVV | OPMVX) => Operation::WideMul,
+            (0b111100, OPMVV | OPMVX) => Operation::WideMultiplyAddUnsigned,
+            (0b111101, OPMVV | OPMVX) => Operation::WideMultiplyAdd,
+            (0b111110, OPMVX) => Operation::WideMultiplyAddUnsignedSigned,
+            (0b111111, OPMVV | OPMVX) => Operation::WideMultiplyAddSignedUnsigned,
+            _ => return Err(ProcessorException::IllegalInstruction),
+        };
+
+        // Shift amounts are unsigned
+        let unsigned = matches!(
+            operation,
+            Operation::ShiftLeft
+                | Operation::ShiftRightLogical
+                | Operation::ShiftRightArithmetic
+                | Operation::NarrowShiftRightLogical
+                | Operation::NarrowShiftRightArithmetic
+        );
+
+        // The destination can't overlap the mask, unless it is only used to select elements
+        let dest = instruction.rd;
+        if masked && dest == 0 {
+            return Err(ProcessorException::IllegalInstruction);
+        }
+
+        // For the extensions, the vs1 field selects the operation instead of an operand
+        let src1 = match operation {
+            Operation::ZeroExtend(_) | Operation::SignExtend(_) => Operand::Immediate(0),
+            _ => Operand::new(instruction, unsigned)?,
+        };
+
+        Ok(Self {
+            state,
+            operation,
+            src1,
+            src2: instruction.rs2,
+            dest,
+            masked: masked && operation != Operation::Merge,
+        })
+    }
+}
+
+impl Instruction for IntegerInstruction {
+    fn execute(
+        &self,
+        registers: &mut RegisterFile,
+        _mem: i64,
+    ) -> Result<InstructionResult, ProcessorException> {
+        let mut state = self.state.lock().unwrap();
+        let vtype = state.vtype()?;
+        let sew = vtype.sew;
+        let (dest_width, src2_width) = self.operation.widths();
+        let dest_eew = scale(sew as usize, dest_width) as u32;
+        let src2_eew = scale(sew as usize, src2_width) as u32;
+        if dest_eew > state.elen() || src2_eew > state.elen() || src2_eew < 8 {
+            return Err(ProcessorException::IllegalInstruction);
+        }
+
+        let dest_emul = vtype.lmul + dest_width;
+        check_group(self.dest, dest_emul)?;
+        if self.operation != Operation::Move {
+            check_group(self.src2, vtype.lmul + src2_width)?;
+        }
+        if let Operand::Vector(src1) = self.src1 {
+            check_group(src1, vtype.lmul)?;
+        }
+
+        let scalar = self.src1.scalar(registers)?;
+        let (operation, src1, src2, dest) = (self.operation, self.src1, self.src2, self.dest);
+        state.update(dest, dest_eew, dest_emul, vtype, self.masked, |state, i| {
+            let b = src1.element(state, scalar, i, sew);
+            if operation == Operation::Merge && !state.mask(0, i) {
+                return Ok(state.read(src2, i, sew));
+            }
+            let a = match operation {
+                Operation::Merge | Operation::Move => 0,
+                _ => state.read(src2, i, src2_eew),
+            };
+            let d = match operation.reads_dest() {
+                true => state.read(dest, i, dest_eew),
+                false => 0,
+            };
+            Ok(truncate(operation.compute(a, b, d, sew), dest_eew))
+        })?;
+        Ok(InstructionResult::default())
+    }
+
+    fn format(&self) -> String {
+        let name = self.operation.name();
+        let kind = self.src1.kind();
+        let mask = mask_operand(self.masked);
+        match self.operation {
+            Operation::Move => format!("vmv.v.{} v{}, {}", kind, self.dest, self.src1),
+            Operation::Merge => format!(
+                "vmerge.v{}m v{}, v{}, {}, v0",
+                kind, self.dest, self.src2, self.src1
+            ),
+            Operation::ZeroExtend(factor) | Operation::SignExtend(factor) => {
+                format!(
+                    "{}.vf{} v{}, v{}{}",
+                    name, factor, self.dest, self.src2, mask
+                )
+            }
+            // Multiply-adds take the multiplicand before the multiplier
+            _ if self.operation.reads_dest() => format!(
+                "{}.v{} v{}, {}, v{}{}",
+                name, kind, self.dest, self.src1, self.src2, mask
+            ),
+            _ => {
+                let src2 = match self.operation.widths() {
+                    (_, 1) => 'w',
+                    _ => 'v',
+                };
+                format!(
+                    "{}.{}{} v{}, v{}, {}{}",
+                    name, src2, kind, self.dest, self.src2, self.src1, mask
+                )
+            }
+        }
+    }
+}
+
+/// Comparison to perform on each element.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+enum Comparison {
+    Equal,
+    NotEqual,
+    LessThanUnsigned,
+    LessThan,
+    LessOrEqualUnsigned,
+    LessOrEqual,
+    GreaterThanUnsigned,
+    GreaterThan,
+}
+
+impl Comparison {
+    /// Mnemonic for this comparison, without the suffix identifying the kinds of operands.
+    fn name(&self) -> &'static str {
+        match self {
+            Comparison::Equal => "vmseq",
+            Comparison::NotEqual => "vmsne",
+            Comparison::LessThanUnsigned => "vmsltu",
+            Comparison::LessThan => "vmslt",
+            Comparison::LessOrEqualUnsigned => "vmsleu",
+            Comparison::LessOrEqual => "vmsle",
+            Comparison::GreaterThanUnsigned => "vmsgtu",
+            Comparison::GreaterThan => "vmsgt",
+        }
+    }
+}
+
+/// Integer comparison instruction, writing a mask register.
+pub struct CompareInstruction {
+    state: Arc<Mutex<VectorState>>,
+    comparison: Comparison,
+    src1: Operand,
+    src2: u8,
+    dest: u8,
+    masked: bool,
+}
+
+impl CompareInstruction {
+    /// Create a new CompareInstruction, operating on the provided vector state.
+    pub fn new(
+        instruction: &InstructionWordParts,
+        state: Arc<Mutex<VectorState>>,
+    ) -> Result<Self, ProcessorException> {
+        let funct6 = funct6(instruction);
+        let comparison = match (funct6, instruction.funct3) {
+            (0b011000, OPIVV | OPIVX | OPIVI) => Comparison::Equal,
+            (0b011001, OPIVV | OPIVX | OPIVI) => Comparison::NotEqual,
+            (0b011010, OPIVV | OPIVX) => Comparison::LessThanUnsigned,
+            (0b011011, OPIVV | OPIVX) => Comparison::LessThan,
+            (0b011100, OPIVV | OPIVX | OPIVI) => Comparison::LessOrEqualUnsigned,
+            (0b011101, OPIVV | OPIVX | OPIVI) => Comparison::LessOrEqual,
+            (0b011110, OPIVX | OPIVI) => Comparison::GreaterThanUnsigned,
+            (0b011111, OPIVX | OPIVI) => Comparison::GreaterThan,
+            _ => return Err(ProcessorException::IllegalInstruction),
+        };
+
+        Ok(Self {
+            state,
+            comparison,
+            src1: Operand::new(instruction, false)?,
+            src2: instruction.rs2,
+            dest: instruction.rd,
+            masked: masked(instruction),
+        })
+    }
+}
+
+impl Instruction for CompareInstruction {
+    fn execute(
+        &self,
+        registers: &mut RegisterFile,
+        _mem: i64,
+    ) -> Result<InstructionResult, ProcessorException> {
+        let mut state = self.state.lock().unwrap();
+        let vtype = state.vtype()?;
+        let sew = vtype.sew;
+        check_group(self.src2, vtype.lmul)?;
+        if let Operand::Vector(src1) = self.src1 {
+            check_group(src1, vtype.lmul)?;
+        }
+
+        let scalar = self.src1.scalar(registers)?;
+        let (comparison, src1, src2) = (self.comparison, self.src1, self.src2);
+        state.update_mask(self.dest, vtype, self.masked, |state, i| {
+            let (a, b) = (
+                state.read(src2, i, sew),
+                src1.element(state, scalar, i, sew),
+            );
+            let (sa, sb) = (sign_extend(a, sew), sign_extend(b, sew));
+            Ok(match comparison {
+                Comparison::Equal => a == b,
+                Comparison::NotEqual => a != b,
+                Comparison::LessThanUnsigned => a < b,
+                Comparison::LessThan => sa < sb,
+                Comparison::LessOrEqualUnsigned => a <= b,
+                Comparison::LessOrEqual => sa <= sb,
+                Comparison::GreaterThanUnsigned => a > b,
+                Comparison::GreaterThan => sa > sb,
+            })
+        })?;
+        Ok(InstructionResult::default())
+    }
+
+    fn format(&self) -> String {
+        format!(
+            "{}.v{} v{}, v{}, {}{}",
+            self.comparison.name(),
+            self.src1.kind(),
+            self.dest,
+            self.src2,
+            self.src1,
+            mask_operand(self.masked)
+        )
+    }
+}
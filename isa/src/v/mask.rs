@@ -0,0 +1,330 @@
+//! Mask instructions.
+//!
+//! The mask-register logical instructions combine two mask registers bit by bit. VCPOP.M and
+//! VFIRST.M count the active set bits of a mask register and find the first of them, writing the
+//! result to an integer register. VMSBF.M, VMSIF.M and VMSOF.M write a mask of the active elements
+//! before, up to & including, or only at the first active set bit, while VIOTA.M writes to each
+//! element the number of active set bits before it, and VID.V writes each element's index.
+//!
+//! The instructions which scan for set bits can't be resumed part-way through, so raise an
+//! IllegalInstruction exception if `vstart` is not zero.
+
+use super::state::check_group;
+use super::{funct6, mask_operand, masked, write_x, VectorState};
+use std::sync::{Arc, Mutex};
+use z2l_core::error::ProcessorException;
+use z2l_core::instruction::{Instruction, InstructionResult, InstructionWordParts};
+use z2l_core::processor::register::RegisterFile;
+use z2l_core::processor::Xlen;
+
+/// Logical operation to perform on each bit.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+enum Logical {
+    AndNot,
+    And,
+    Or,
+    Xor,
+    OrNot,
+    Nand,
+    Nor,
+    Xnor,
+}
+
+impl Logical {
+    /// Mnemonic for this operation.
+    fn name(&self) -> &'static str {
+        match self {
+            Logical::AndNot => "vmandn",
+            Logical::And => "vmand",
+            Logical::Or => "vmor",
+            Logical::Xor => "vmxor",
+            Logical::OrNot => "vmorn",
+            Logical::Nand => "vmnand",
+            Logical::Nor => "vmnor",
+            Logical::Xnor => "vmxnor",
+        }
+    }
+}
+
+/// Mask-register logical instruction (VMAND.MM, VMNAND.MM, VMANDN.MM, VMXOR.MM, VMOR.MM,
+/// VMNOR.MM, VMORN.MM, or VMXNOR.MM).
+pub struct MaskLogicalInstruction {
+    state: Arc<Mutex<VectorState>>,
+    logical: Logical,
+    src1: u8,
+    src2: u8,
+    dest: u8,
+}
+
+impl MaskLogicalInstruction {
+    /// Create a new MaskLogicalInstruction, operating on the provided vector state.
+    pub fn new(
+        instruction: &InstructionWordParts,
+        state: Arc<Mutex<VectorState>>,
+    ) -> Result<Self, ProcessorException> {
+        let logical = match funct6(instruction) {
+            0b011000 => Logical::AndNot,
+            0b011001 => Logical::And,
+            0b011010 => Logical::Or,
+            0b011011 => Logical::Xor,
+            0b011100 => Logical::OrNot,
+            0b011101 => Logical::Nand,
+            0b011110 => Logical::Nor,
+            0b011111 => Logical::Xnor,
+            _ => return Err(ProcessorException::IllegalInstruction),
+        };
+        if masked(instruction) {
+            return Err(ProcessorException::IllegalInstruction);
+        }
+
+        Ok(Self {
+            state,
+            logical,
+            src1: instruction.rs1,
+            src2: instruction.rs2,
+            dest: instruction.rd,
+        })
+    }
+}
+
+impl Instruction for MaskLogicalInstruction {
+    fn execute(
+        &self,
+        _registers: &mut RegisterFile,
+        _mem: i64,
+    ) -> Result<InstructionResult, ProcessorException> {
+        let mut state = self.state.lock().unwrap();
+        let vtype = state.vtype()?;
+
+        let (logical, src1, src2) = (self.logical, self.src1, self.src2);
+        state.update_mask(self.dest, vtype, false, |state, i| {
+            let (a, b) = (state.mask(src2, i), state.mask(src1, i));
+            Ok(match logical {
+                Logical::AndNot => a && !b,
+                Logical::And => a && b,
+                Logical::Or => a || b,
+                Logical::Xor => a != b,
+                Logical::OrNot => a || !b,
+                Logical::Nand => !(a && b),
+                Logical::Nor => !(a || b),
+                Logical::Xnor => a == b,
+            })
+        })?;
+        Ok(InstructionResult::default())
+    }
+
+    fn format(&self) -> String {
+        format!(
+            "{}.mm v{}, v{}, v{}",
+            self.logical.name(),
+            self.dest,
+            self.src2,
+            self.src1
+        )
+    }
+}
+
+/// VCPOP.M or VFIRST.M instruction.
+pub struct MaskCountInstruction {
+    state: Arc<Mutex<VectorState>>,
+    first: bool,
+    src: u8,
+    dest: u8,
+    masked: bool,
+    xlen: Xlen,
+}
+
+impl MaskCountInstruction {
+    /// Create a new MaskCountInstruction, operating on the provided vector state, for a hart with
+    /// the provided XLEN.
+    pub fn new(
+        instruction: &InstructionWordParts,
+        state: Arc<Mutex<VectorState>>,
+        xlen: Xlen,
+    ) -> Result<Self, ProcessorException> {
+        let first = match instruction.rs1 {
+            0b10000 => false,
+            0b10001 => true,
+            _ => return Err(ProcessorException::IllegalInstruction),
+        };
+
+        Ok(Self {
+            state,
+            first,
+            src: instruction.rs2,
+            dest: instruction.rd,
+            masked: masked(instruction),
+            xlen,
+        })
+    }
+}
+
+impl Instruction for MaskCountInstruction {
+    fn execute(
+        &self,
+        registers: &mut RegisterFile,
+        _mem: i64,
+    ) -> Result<InstructionResult, ProcessorException> {
+        let state = self.state.lock().unwrap();
+        state.vtype()?;
+        if state.vstart != 0 {
+            return Err(ProcessorException::IllegalInstruction);
+        }
+
+        let mut set =
+            (0..state.vl).filter(|&i| state.active(self.masked, i) && state.mask(self.src, i));
+        let result = match self.first {
+            true => set.next().map_or(-1, |i| i as i64),
+            false => set.count() as i64,
+        };
+        write_x(registers, self.dest, self.xlen.sign_extend(result))?;
+        Ok(InstructionResult::default())
+    }
+
+    fn format(&self) -> String {
+        let name = if self.first { "vfirst" } else { "vcpop" };
+        format!(
+            "{}.m x{}, v{}{}",
+            name,
+            self.dest,
+            self.src,
+            mask_operand(self.masked)
+        )
+    }
+}
+
+/// Operation performed by a mask unary instruction.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+enum Unary {
+    SetBeforeFirst,
+    SetOnlyFirst,
+    SetIncludingFirst,
+    Iota,
+    Index,
+}
+
+impl Unary {
+    /// Mnemonic for this operation.
+    fn name(&self) -> &'static str {
+        match self {
+            Unary::SetBeforeFirst => "vmsbf.m",
+            Unary::SetOnlyFirst => "vmsof.m",
+            Unary::SetIncludingFirst => "vmsif.m",
+            Unary::Iota => "viota.m",
+            Unary::Index => "vid.v",
+        }
+    }
+}
+
+/// VMSBF.M, VMSIF.M, VMSOF.M, VIOTA.M, or VID.V instruction.
+pub struct MaskUnaryInstruction {
+    state: Arc<Mutex<VectorState>>,
+    unary: Unary,
+    src: u8,
+    dest: u8,
+    masked: bool,
+}
+
+impl MaskUnaryInstruction {
+    /// Create a new MaskUnaryInstruction, operating on the provided vector state.
+    pub fn new(
+        instruction: &InstructionWordParts,
+        state: Arc<Mutex<VectorState>>,
+    ) -> Result<Self, ProcessorException> {
+        let unary = match (instruction.rs1, instruction.rs2) {
+            (0b00001, _) => Unary::SetBeforeFirst,
+            (0b00010, _) => Unary::SetOnlyFirst,
+            (0b00011, _) => Unary::SetIncludingFirst,
+            (0b10000, _) => Unary::Iota,
+            (0b10001, 0) => Unary::Index,
+            _ => return Err(ProcessorException::IllegalInstruction),
+        };
+
+        // The destination can't overlap the source, or the mask if the instruction is masked
+        let (src, dest, masked) = (instruction.rs2, instruction.rd, masked(instruction));
+        if (unary != Unary::Index && dest == src) || (masked && dest == 0) {
+            return Err(ProcessorException::IllegalInstruction);
+        }
+
+        Ok(Self {
+            state,
+            unary,
+            src,
+            dest,
+            masked,
+        })
+    }
+}
+
+impl Instruction for MaskUnaryInstruction {
+    fn execute(
+        &self,
+        _registers: &mut RegisterFile,
+        _mem: i64,
+    ) -> Result<InstructionResult, ProcessorException> {
+        let mut state = self.state.lock().unwrap();
+        let vtype = state.vtype()?;
+        if state.vstart != 0 && self.unary != Unary::Index {
+            return Err(ProcessorException::IllegalInstruction);
+        }
+
+        let (src, masked) = (self.src, self.masked);
+        let first = (0..state.vl).find(|&i| state.active(masked, i) && state.mask(src, i));
+        match self.unary {
+            Unary::SetBeforeFirst | Unary::SetOnlyFirst | Unary::SetIncludingFirst => {
+                let unary = self.unary;
+                state.update_mask(self.dest, vtype, masked, |_, i| {
+                    Ok(match (unary, first) {
+                        (_, None) => {
+                            unary == Unary::SetBeforeFirst || unary == Unary::SetIncludingFirst
+                        }
+                        (Unary::SetBeforeFirst, Some(first)) => i < first,
+                        (Unary::SetIncludingFirst, Some(first)) => i <= first,
+                        (_, Some(first)) => i == first,
+                    })
+                })?;
+            }
+            Unary::Iota => {
+                check_group(self.dest, vtype.lmul)?;
+                let mut count = 0;
+                state.update(
+                    self.dest,
+                    vtype.sew,
+                    vtype.lmul,
+                    vtype,
+                    masked,
+                    |state, i| {
+                        let result = count;
+                        count += state.mask(src, i) as u64;
+                        Ok(result)
+                    },
+                )?;
+            }
+            Unary::Index => {
+                check_group(self.dest, vtype.lmul)?;
+                state.update(self.dest, vtype.sew, vtype.lmul, vtype, masked, |_, i| {
+                    Ok(i as u64)
+                })?;
+            }
+        }
+        Ok(InstructionResult::default())
+    }
+
+    fn format(&self) -> String {
+        match self.unary {
+            Unary::Index => format!(
+                "{} v{}{}",
+                self.unary.name(),
+                self.dest,
+                mask_operand(self.masked)
+            ),
+            _ => format!(
+                "{} v{}, v{}{}",
+                self.unary.name(),
+                self.dest,
+                self.src,
+                mask_operand(self.masked)
+            ),
+        }
+    }
+}
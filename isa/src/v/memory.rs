@@ -0,0 +1,472 @@
+//! Vector loads and stores.
+//!
+//! These instructions share the LOAD-FP & STORE-FP opcodes with the floating-point loads & stores,
+//! using the widths those don't, so are registered as [`SubDecoder`]s on them. Each element is
+//! accessed separately, at an address determined by the addressing mode:
+//!
+//! - Unit-stride: Elements are contiguous in memory, from the address in `rs1`.
+//! - Strided: Elements are separated by the signed byte stride in `rs2`.
+//! - Indexed: Each element is at the unsigned byte offset held in the corresponding element of
+//!   `vs2`, which has the width encoded by the instruction, while the data has SEW bits. Ordered &
+//!   unordered indexed accesses are both performed in element order.
+//! - Whole register: 1, 2, 4, or 8 registers are accessed as a unit-stride access, independently of
+//!   `vtype` and `vl`.
+//! - Mask: `vl` bits of a mask register are accessed as a unit-stride access of bytes.
+//!
+//! Segment accesses (with `nf` set) access up to 8 fields for each element, each held in its own
+//! register group, with the fields of each element adjacent in memory.
+
+use super::state::{check_group, VectorState, Vtype};
+use super::{mask_operand, masked, read_x};
+use std::sync::{Arc, Mutex};
+use z2l_core::error::ProcessorException;
+use z2l_core::extension::SubDecoder;
+use z2l_core::instruction::{Instruction, InstructionResult, InstructionWordParts};
+use z2l_core::mmu::{LoadSpec, MemoryAccessType, StoreSpec};
+use z2l_core::processor::register::RegisterFile;
+use z2l_core::processor::Xlen;
+
+/// Get the element width encoded by the width field of a vector load or store, or `None` if the
+/// width is not that of a vector instruction.
+fn width(funct3: u8) -> Option<u32> {
+    match funct3 {
+        0b000 => Some(8),
+        0b101 => Some(16),
+        0b110 => Some(32),
+        0b111 => Some(64),
+        _ => None,
+    }
+}
+
+/// Get the memory access type used for elements of `eew` bits.
+fn access_type(eew: u32) -> MemoryAccessType {
+    match eew {
+        8 => MemoryAccessType::UnsignedByte,
+        16 => MemoryAccessType::UnsignedHalfWord,
+        32 => MemoryAccessType::UnsignedWord,
+        _ => MemoryAccessType::DoubleWord,
+    }
+}
+
+/// Addressing mode of a vector load or store.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+enum Mode {
+    UnitStride,
+    Strided(u8),
+    Indexed { index: u8, ordered: bool },
+    WholeRegister,
+    Mask,
+}
+
+/// Layout of the register groups accessed by a vector load or store.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+struct Layout {
+    /// Width of each element of data, in bits.
+    eew: u32,
+
+    /// Register group multiplier of each field.
+    emul: i32,
+
+    /// Number of elements accessed, including prestart & inactive elements.
+    evl: usize,
+
+    /// Number of fields accessed for each element.
+    fields: u8,
+
+    /// Vector type setting the access depends on, if any.
+    vtype: Option<Vtype>,
+}
+
+/// An element accessed by a vector load or store.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+struct Element {
+    /// First register of the group holding the element.
+    reg: u8,
+
+    /// Index of the element in its register group.
+    index: usize,
+
+    /// Address of the element in memory.
+    addr: usize,
+}
+
+/// Operands of a vector load or store.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+struct Access {
+    mode: Mode,
+    data: u8,
+    base: u8,
+    eew: u32,
+    fields: u8,
+    masked: bool,
+}
+
+impl Access {
+    /// Decode the operands of a vector load or store.
+    fn new(instruction: &InstructionWordParts, store: bool) -> Result<Self, ProcessorException> {
+        let eew = width(instruction.funct3).ok_or(ProcessorException::IllegalInstruction)?;
+        let raw = instruction.raw;
+
+        // The mew bit is reserved for elements wider than 64 bits
+        if raw & (1 << 28) != 0 {
+            return Err(ProcessorException::IllegalInstruction);
+        }
+        let fields = (raw >> 29) as u8 + 1;
+        let masked = masked(instruction);
+
+        // Fault-only-first loads are not supported
+        let mode = match (raw >> 26) & 0b11 {
+            0b00 => match instruction.rs2 {
+                0b00000 => Mode::UnitStride,
+                0b01000 if !masked && fields.is_power_of_two() && (!store || eew == 8) => {
+                    Mode::WholeRegister
+                }
+                0b01011 if !masked && fields == 1 && eew == 8 => Mode::Mask,
+                _ => return Err(ProcessorException::IllegalInstruction),
+            },
+            0b01 => Mode::Indexed {
+                index: instruction.rs2,
+                ordered: false,
+            },
+            0b10 => Mode::Strided(instruction.rs2),
+            _ => Mode::Indexed {
+                index: instruction.rs2,
+                ordered: true,
+            },
+        };
+
+        // A load's destination can't overlap the mask
+        if !store && masked && instruction.rd == 0 {
+            return Err(ProcessorException::IllegalInstruction);
+        }
+
+        Ok(Self {
+            mode,
+            data: instruction.rd,
+            base: instruction.rs1,
+            eew,
+            fields,
+            masked,
+        })
+    }
+
+    /// Determine the layout of the register groups accessed, and the elements to access, from
+    /// `vstart` to the end of the body, in order.
+    ///
+    /// Inactive elements are not accessed.
+    fn plan(
+        &self,
+        state: &VectorState,
+        registers: &RegisterFile,
+        xlen: Xlen,
+    ) -> Result<(Layout, Vec<Element>), ProcessorException> {
+        let layout = match self.mode {
+            Mode::WholeRegister => {
+                let emul = self.fields.trailing_zeros() as i32;
+                check_group(self.data, emul)?;
+                Layout {
+                    eew: self.eew,
+                    emul,
+                    evl: self.fields as usize * state.vlen() / self.eew as usize,
+                    fields: 1,
+                    vtype: None,
+                }
+            }
+            Mode::Mask => Layout {
+                eew: 8,
+                emul: 0,
+                evl: state.vl.div_ceil(8),
+                fields: 1,
+                vtype: Some(state.vtype()?),
+            },
+            _ => {
+                let vtype = state.vtype()?;
+                if self.eew > state.elen() {
+                    return Err(ProcessorException::IllegalInstruction);
+                }
+                let (eew, emul) = match self.mode {
+                    Mode::Indexed { index, .. } => {
+                        check_group(index, vtype.emul(self.eew))?;
+                        (vtype.sew, vtype.lmul)
+                    }
+                    _ => (self.eew, vtype.emul(self.eew)),
+                };
+
+                // The register groups of all the fields must fit in the register file
+                check_group(self.data, emul)?;
+                let group = 1 << emul.max(0);
+                if group * self.fields > 8 || self.data + group * self.fields > 32 {
+                    return Err(ProcessorException::IllegalInstruction);
+                }
+
+                Layout {
+                    eew,
+                    emul,
+                    evl: state.vl,
+                    fields: self.fields,
+                    vtype: Some(vtype),
+                }
+            }
+        };
+
+        let base = read_x(registers, self.base)? as u64;
+        let stride = match self.mode {
+            Mode::Strided(stride) => read_x(registers, stride)? as u64,
+            _ => 0,
+        };
+        let bytes = layout.eew as u64 / 8;
+
+        let mut elements = Vec::new();
+        for i in state.vstart..layout.evl {
+            if !state.active(self.masked, i) {
+                continue;
+            }
+            for field in 0..layout.fields {
+                let offset = match self.mode {
+                    Mode::UnitStride => (i as u64 * layout.fields as u64 + field as u64) * bytes,
+                    Mode::Strided(_) => (i as u64)
+                        .wrapping_mul(stride)
+                        .wrapping_add(field as u64 * bytes),
+                    Mode::Indexed { index, .. } => {
+                        state.read(index, i, self.eew) + field as u64 * bytes
+                    }
+                    Mode::WholeRegister | Mode::Mask => i as u64 * bytes,
+                };
+                elements.push(Element {
+                    reg: self.group(&layout, field),
+                    index: i,
+                    addr: xlen.zero_extend(base.wrapping_add(offset) as i64) as usize,
+                });
+            }
+        }
+        Ok((layout, elements))
+    }
+
+    /// Get the first register of the group holding the provided field.
+    fn group(&self, layout: &Layout, field: u8) -> u8 {
+        self.data + field * (1 << layout.emul.max(0))
+    }
+
+    /// Mnemonic of this access, for a load or store.
+    fn name(&self, store: bool) -> String {
+        let prefix = if store { "vs" } else { "vl" };
+        let segment = match self.fields {
+            1 => String::new(),
+            fields => format!("seg{}", fields),
+        };
+        match self.mode {
+            Mode::UnitStride => format!("{}{}e{}.v", prefix, segment, self.eew),
+            Mode::Strided(_) => format!("{}s{}e{}.v", prefix, segment, self.eew),
+            Mode::Indexed { ordered, .. } => {
+                let order = if ordered { 'o' } else { 'u' };
+                format!("{}{}x{}ei{}.v", prefix, order, segment, self.eew)
+            }
+            Mode::WholeRegister if store => format!("vs{}r.v", self.fields),
+            Mode::WholeRegister => format!("vl{}re{}.v", self.fields, self.eew),
+            Mode::Mask => format!("{}m.v", prefix),
+        }
+    }
+
+    /// Format this access as an instruction.
+    fn format(&self, store: bool) -> String {
+        let operand = match self.mode {
+            Mode::Strided(stride) => format!(", x{}", stride),
+            Mode::Indexed { index, .. } => format!(", v{}", index),
+            _ => String::new(),
+        };
+        format!(
+            "{} v{}, (x{}){}{}",
+            self.name(store),
+            self.data,
+            self.base,
+            operand,
+            mask_operand(self.masked)
+        )
+    }
+}
+
+/// Decoder for the vector loads, in the LOAD-FP opcode.
+pub struct VectorLoadDecoder {
+    state: Arc<Mutex<VectorState>>,
+}
+
+impl VectorLoadDecoder {
+    /// Create a new VectorLoadDecoder, operating on the provided vector state.
+    pub fn new(state: Arc<Mutex<VectorState>>) -> Self {
+        Self { state }
+    }
+}
+
+impl SubDecoder for VectorLoadDecoder {
+    fn decode(
+        &self,
+        instruction: &InstructionWordParts,
+        _pc: u64,
+        xlen: Xlen,
+    ) -> Result<Option<Box<dyn Instruction>>, ProcessorException> {
+        if width(instruction.funct3).is_none() {
+            return Ok(None);
+        }
+        let state = self.state.clone();
+        Ok(Some(Box::new(VectorLoadInstruction::new(
+            instruction,
+            state,
+            xlen,
+        )?)))
+    }
+}
+
+/// Vector load instruction.
+pub struct VectorLoadInstruction {
+    state: Arc<Mutex<VectorState>>,
+    access: Access,
+    xlen: Xlen,
+}
+
+impl VectorLoadInstruction {
+    /// Create a new VectorLoadInstruction, operating on the provided vector state, for a hart with
+    /// the provided XLEN.
+    pub fn new(
+        instruction: &InstructionWordParts,
+        state: Arc<Mutex<VectorState>>,
+        xlen: Xlen,
+    ) -> Result<Self, ProcessorException> {
+        Ok(Self {
+            state,
+            access: Access::new(instruction, false)?,
+            xlen,
+        })
+    }
+}
+
+impl Instruction for VectorLoadInstruction {
+    fn loads(&self, registers: &RegisterFile) -> Result<Vec<LoadSpec>, ProcessorException> {
+        let state = self.state.lock().unwrap();
+        let (layout, elements) = self.access.plan(&state, registers, self.xlen)?;
+        Ok(elements
+            .iter()
+            .map(|element| LoadSpec::new(access_type(layout.eew), element.addr))
+            .collect())
+    }
+
+    fn execute(
+        &self,
+        registers: &mut RegisterFile,
+        _mem: i64,
+    ) -> Result<InstructionResult, ProcessorException> {
+        // No elements are loaded if all are inactive, or vstart is past the end of the body
+        self.execute_loads(registers, &[])
+    }
+
+    fn execute_loads(
+        &self,
+        registers: &mut RegisterFile,
+        mem: &[i64],
+    ) -> Result<InstructionResult, ProcessorException> {
+        let mut state = self.state.lock().unwrap();
+        let (layout, elements) = self.access.plan(&state, registers, self.xlen)?;
+        for (element, &value) in elements.iter().zip(mem) {
+            state.write(element.reg, element.index, layout.eew, value as u64);
+        }
+
+        // Mask loads always leave the tail of the mask register undisturbed
+        if let Some(vtype) = layout.vtype.filter(|_| self.access.mode != Mode::Mask) {
+            let (start, evl) = (state.vstart, layout.evl);
+            for field in (0..layout.fields).filter(|_| start < evl) {
+                let reg = self.access.group(&layout, field);
+                if vtype.mask_agnostic {
+                    for i in start..evl {
+                        if !state.active(self.access.masked, i) {
+                            state.agnostic(reg, i, layout.eew);
+                        }
+                    }
+                }
+                state.agnostic_tail(reg, evl, layout.eew, layout.emul, vtype);
+            }
+        }
+        state.vstart = 0;
+        Ok(InstructionResult::default())
+    }
+
+    fn format(&self) -> String {
+        self.access.format(false)
+    }
+}
+
+/// Decoder for the vector stores, in the STORE-FP opcode.
+pub struct VectorStoreDecoder {
+    state: Arc<Mutex<VectorState>>,
+}
+
+impl VectorStoreDecoder {
+    /// Create a new VectorStoreDecoder, operating on the provided vector state.
+    pub fn new(state: Arc<Mutex<VectorState>>) -> Self {
+        Self { state }
+    }
+}
+
+impl SubDecoder for VectorStoreDecoder {
+    fn decode(
+        &self,
+        instruction: &InstructionWordParts,
+        _pc: u64,
+        xlen: Xlen,
+    ) -> Result<Option<Box<dyn Instruction>>, ProcessorException> {
+        if width(instruction.funct3).is_none() {
+            return Ok(None);
+        }
+        let state = self.state.clone();
+        Ok(Some(Box::new(VectorStoreInstruction::new(
+            instruction,
+            state,
+            xlen,
+        )?)))
+    }
+}
+
+/// Vector store instruction.
+pub struct VectorStoreInstruction {
+    state: Arc<Mutex<VectorState>>,
+    access: Access,
+    xlen: Xlen,
+}
+
+impl VectorStoreInstruction {
+    /// Create a new VectorStoreInstruction, operating on the provided vector state, for a hart
+    /// with the provided XLEN.
+    pub fn new(
+        instruction: &InstructionWordParts,
+        state: Arc<Mutex<VectorState>>,
+        xlen: Xlen,
+    ) -> Result<Self, ProcessorException> {
+        Ok(Self {
+            state,
+            access: Access::new(instruction, true)?,
+            xlen,
+        })
+    }
+}
+
+impl Instruction for VectorStoreInstruction {
+    fn execute(
+        &self,
+        registers: &mut RegisterFile,
+        _mem: i64,
+    ) -> Result<InstructionResult, ProcessorException> {
+        let mut state = self.state.lock().unwrap();
+        let (layout, elements) = self.access.plan(&state, registers, self.xlen)?;
+        let stores = elements
+            .iter()
+            .map(|element| {
+                let value = state.read(element.reg, element.index, layout.eew);
+                StoreSpec::new(access_type(layout.eew), element.addr, value as i64)
+            })
+            .collect();
+        state.vstart = 0;
+        Ok(InstructionResult::set_stores(stores))
+    }
+
+    fn format(&self) -> String {
+        self.access.format(true)
+    }
+}
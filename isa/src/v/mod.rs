@@ -0,0 +1,302 @@
+//! The vector extensions.
+//!
+//! This module implements the instructions shared by the vector extension profiles, such as
+//! [`Zve32x`]. They add 32 vector registers, `v0` to `v31`, each VLEN bits wide, which are held in
+//! a [`VectorState`] shared by the vector CSRs and instructions rather than in the hart's
+//! [`RegisterFile`]. Each register is divided into elements of the selected element width (SEW),
+//! and instructions operate on the first `vl` elements of a group of LMUL registers: Both are set
+//! by `vsetvli`, `vsetivli`, or `vsetvl`, which write the `vtype` and `vl` CSRs. Instructions which
+//! depend on `vtype` are illegal while `vill` is set, as it is from reset.
+//!
+//! Most instructions can be masked by `v0`. Elements which are masked off (inactive), and elements
+//! past `vl` (the tail), are either left undisturbed or are agnostic, as selected by the `vma` and
+//! `vta` bits of `vtype`: Agnostic elements are written according to the [`AgnosticPolicy`]. The
+//! tails of mask registers are always left undisturbed.
+//!
+//! Vector loads & stores access each element separately, through the hart's multi-element memory
+//! path (see [`Instruction::loads`] and [`InstructionResult::stores`]). If an element access
+//! faults, the instruction traps without updating `vstart`, so it restarts from its first element:
+//! The elements stored before the fault remain stored, and loads do not write any element.
+//!
+//! The fixed-point instructions (and the `vxrm`, `vxsat` & `vcsr` CSRs), add-with-carry and
+//! subtract-with-borrow instructions, and fault-only-first loads are not yet supported, and raise
+//! an IllegalInstruction exception. `mstatus.VS` is not implemented, so the vector unit is always
+//! enabled.
+//!
+//! [`Zve32x`]: crate::zve32x::Zve32x
+//! [`Instruction::loads`]: z2l_core::instruction::Instruction::loads
+//! [`InstructionResult::stores`]: z2l_core::instruction::InstructionResult::stores
+
+mod config;
+mod integer;
+mod mask;
+mod memory;
+mod permute;
+mod reduction;
+mod state;
+
+pub use config::VsetvlInstruction;
+pub use integer::{CompareInstruction, IntegerInstruction};
+pub use mask::{MaskCountInstruction, MaskLogicalInstruction, MaskUnaryInstruction};
+pub use memory::{
+    VectorLoadDecoder, VectorLoadInstruction, VectorStoreDecoder, VectorStoreInstruction,
+};
+pub use permute::{
+    CompressInstruction, GatherInstruction, MoveScalarInstruction, SlideInstruction,
+    WholeMoveInstruction,
+};
+pub use reduction::ReductionInstruction;
+pub use state::{AgnosticPolicy, VectorState, Vtype};
+
+use state::{sign_extend, truncate};
+use std::fmt;
+use std::sync::{Arc, Mutex};
+use z2l_core::error::ProcessorException;
+use z2l_core::extension::OpcodeHandler;
+use z2l_core::instruction::{Instruction, InstructionParts, InstructionWordParts};
+use z2l_core::processor::csr::Csr;
+use z2l_core::processor::hart::Hart;
+use z2l_core::processor::register::RegisterFile;
+use z2l_core::processor::Xlen;
+
+/// Vector start index.
+pub const VSTART: u16 = 0x008;
+
+/// Vector length.
+pub const VL: u16 = 0xc20;
+
+/// Vector data type.
+pub const VTYPE: u16 = 0xc21;
+
+/// Vector register length, in bytes.
+pub const VLENB: u16 = 0xc22;
+
+/// LOAD-FP opcode, shared by the vector loads.
+const LOAD_FP: u8 = 0x07;
+
+/// STORE-FP opcode, shared by the vector stores.
+const STORE_FP: u8 = 0x27;
+
+/// OP-V opcode.
+const OP_V: u8 = 0x57;
+
+/// OP-V funct3 for integer vector-vector operations.
+const OPIVV: u8 = 0b000;
+
+/// OP-V funct3 for integer vector-immediate operations.
+const OPIVI: u8 = 0b011;
+
+/// OP-V funct3 for integer vector-scalar operations.
+const OPIVX: u8 = 0b100;
+
+/// OP-V funct3 for integer vector-vector operations, in the second (multiply/mask/etc.) space.
+const OPMVV: u8 = 0b010;
+
+/// OP-V funct3 for integer vector-scalar operations, in the second space.
+const OPMVX: u8 = 0b110;
+
+/// OP-V funct3 for the configuration-setting instructions.
+const OPCFG: u8 = 0b111;
+
+/// Register the vector registers, CSRs, and instructions, supporting elements of up to `elen` bits
+/// in registers of `vlen` bits.
+pub(crate) fn register(hart: &mut Hart, elen: u32, vlen: usize, policy: AgnosticPolicy) {
+    let state = Arc::new(Mutex::new(VectorState::new(vlen, elen, policy)));
+
+    let (read, write) = (state.clone(), state.clone());
+    let vstart = Csr::new(0)
+        .on_read(move |_, _| read.lock().unwrap().vstart as u64)
+        .on_write(move |_, new, _| {
+            // WARL: Only the bits needed to hold any element index are writable
+            let vstart = new as usize & (vlen - 1);
+            write.lock().unwrap().vstart = vstart;
+            vstart as u64
+        });
+    hart.csrs.register(VSTART, vstart);
+
+    let read = state.clone();
+    let vl = Csr::new(0).on_read(move |_, _| read.lock().unwrap().vl as u64);
+    hart.csrs.register(VL, vl);
+
+    let read = state.clone();
+    // vill is the top bit of vtype, which depends on the XLEN when it is read, rather than when the
+    // vector extension is registered
+    let vtype = Csr::new(0).on_read(move |_, xlen| match read.lock().unwrap().vtype {
+        Some(vtype) => vtype.encode(),
+        None => 1 << (xlen.bits() - 1),
+    });
+    hart.csrs.register(VTYPE, vtype);
+    hart.csrs.register(VLENB, Csr::new(vlen as u64 / 8));
+
+    hart.register_opcode(OP_V, Box::new(OpVHandler::new(state.clone())));
+    hart.register_sub_decoder(LOAD_FP, Box::new(VectorLoadDecoder::new(state.clone())));
+    hart.register_sub_decoder(STORE_FP, Box::new(VectorStoreDecoder::new(state)));
+}
+
+/// OP-V opcode handler.
+pub struct OpVHandler {
+    state: Arc<Mutex<VectorState>>,
+}
+
+impl OpVHandler {
+    /// Create a new OpVHandler, operating on the provided vector state.
+    pub fn new(state: Arc<Mutex<VectorState>>) -> Self {
+        Self { state }
+    }
+}
+
+impl OpcodeHandler for OpVHandler {
+    fn decode(
+        &self,
+        instruction: InstructionParts,
+        _pc: u64,
+        xlen: Xlen,
+    ) -> Result<Box<dyn Instruction>, ProcessorException> {
+        let instruction = instruction.into_word()?;
+        let state = self.state.clone();
+
+        Ok(match (instruction.funct3, funct6(&instruction)) {
+            (OPCFG, _) => Box::new(VsetvlInstruction::new(&instruction, state, xlen)?),
+            (
+                OPIVV | OPIVX | OPIVI,
+                0b000000
+                | 0b000010..=0b000111
+                | 0b001001..=0b001011
+                | 0b010111
+                | 0b100101
+                | 0b101000
+                | 0b101001
+                | 0b101100
+                | 0b101101,
+            )
+            | (OPMVV, 0b010010)
+            | (OPMVV | OPMVX, 0b100000..=0b100111 | 0b101001 | 0b101011 | 0b101101 | 0b101111)
+            | (OPMVV | OPMVX, 0b110000..=0b111111) => {
+                Box::new(IntegerInstruction::new(&instruction, state)?)
+            }
+            (OPIVV | OPIVX | OPIVI, 0b011000..=0b011111) => {
+                Box::new(CompareInstruction::new(&instruction, state)?)
+            }
+            (OPMVV, 0b000000..=0b000111) | (OPIVV, 0b110000 | 0b110001) => {
+                Box::new(ReductionInstruction::new(&instruction, state)?)
+            }
+            (OPMVV, 0b011000..=0b011111) => {
+                Box::new(MaskLogicalInstruction::new(&instruction, state)?)
+            }
+            (OPMVV, 0b010000) if instruction.rs1 & 0b10000 != 0 => {
+                Box::new(MaskCountInstruction::new(&instruction, state, xlen)?)
+            }
+            (OPMVV | OPMVX, 0b010000) => {
+                Box::new(MoveScalarInstruction::new(&instruction, state, xlen)?)
+            }
+            (OPMVV, 0b010100) => Box::new(MaskUnaryInstruction::new(&instruction, state)?),
+            (OPIVX | OPIVI | OPMVX, 0b001110 | 0b001111) => {
+                Box::new(SlideInstruction::new(&instruction, state, xlen)?)
+            }
+            (OPIVV | OPIVX | OPIVI, 0b001100) | (OPIVV, 0b001110) => {
+                Box::new(GatherInstruction::new(&instruction, state, xlen)?)
+            }
+            (OPMVV, 0b010111) => Box::new(CompressInstruction::new(&instruction, state)?),
+            (OPIVI, 0b100111) => Box::new(WholeMoveInstruction::new(&instruction, state)?),
+            _ => return Err(ProcessorException::IllegalInstruction),
+        })
+    }
+}
+
+/// Get the funct6 field of an OP-V instruction, which selects the operation.
+fn funct6(instruction: &InstructionWordParts) -> u8 {
+    (instruction.raw >> 26) as u8
+}
+
+/// Determine whether an instruction is masked by `v0`, i.e. whether its `vm` bit is clear.
+fn masked(instruction: &InstructionWordParts) -> bool {
+    instruction.raw & (1 << 25) == 0
+}
+
+/// Format the mask operand of an instruction, to append to its other operands.
+fn mask_operand(masked: bool) -> &'static str {
+    if masked {
+        ", v0.t"
+    } else {
+        ""
+    }
+}
+
+/// Read an integer register.
+fn read_x(registers: &RegisterFile, reg: u8) -> Result<i64, ProcessorException> {
+    registers
+        .get(&reg)
+        .ok_or(ProcessorException::IllegalInstruction)?
+        .load()
+}
+
+/// Write an integer register.
+fn write_x(registers: &mut RegisterFile, reg: u8, value: i64) -> Result<(), ProcessorException> {
+    registers
+        .get_mut(&reg)
+        .ok_or(ProcessorException::IllegalInstruction)?
+        .store(value)?;
+    Ok(())
+}
+
+/// First source operand of an arithmetic instruction, selected by funct3: A vector register
+/// (`vs1`), an integer register (`rs1`), or a 5-bit immediate.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+enum Operand {
+    Vector(u8),
+    Scalar(u8),
+    Immediate(i64),
+}
+
+impl Operand {
+    /// Decode the first source operand of an OP-V instruction.
+    ///
+    /// Immediates are sign-extended, unless `unsigned` is set.
+    fn new(instruction: &InstructionWordParts, unsigned: bool) -> Result<Self, ProcessorException> {
+        Ok(match instruction.funct3 {
+            OPIVV | OPMVV => Operand::Vector(instruction.rs1),
+            OPIVX | OPMVX => Operand::Scalar(instruction.rs1),
+            OPIVI if unsigned => Operand::Immediate(instruction.rs1 as i64),
+            OPIVI => Operand::Immediate(sign_extend(instruction.rs1 as u64, 5)),
+            _ => return Err(ProcessorException::IllegalInstruction),
+        })
+    }
+
+    /// Get the scalar value of this operand: The value of the integer register or immediate, or
+    /// zero for a vector register.
+    fn scalar(&self, registers: &RegisterFile) -> Result<i64, ProcessorException> {
+        match self {
+            Operand::Vector(_) => Ok(0),
+            Operand::Scalar(reg) => read_x(registers, *reg),
+            Operand::Immediate(imm) => Ok(*imm),
+        }
+    }
+
+    /// Get element `index` of this operand, with `eew` bits, given its [`scalar`](Self::scalar)
+    /// value: Scalars are truncated to the element width.
+    fn element(&self, state: &VectorState, scalar: i64, index: usize, eew: u32) -> u64 {
+        match self {
+            Operand::Vector(reg) => state.read(*reg, index, eew),
+            _ => truncate(scalar as u64, eew),
+        }
+    }
+
+    /// Letter identifying the kind of operand, as used in instruction mnemonics.
+    fn kind(&self) -> char {
+        match self {
+            Operand::Vector(_) => 'v',
+            Operand::Scalar(_) => 'x',
+            Operand::Immediate(_) => 'i',
+        }
+    }
+}
+
+impl fmt::Display for Operand {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Operand::Vector(reg) => write!(f, "v{}", reg),
+            Operand::Scalar(reg) => write!(f, "x{}", reg),
+            Operand::Immediate(imm) => write!(f, "{}", imm),
+        }
+    }
+}
@@ -0,0 +1,474 @@
+//! Permutation instructions.
+//!
+//! VMV.X.S and VMV.S.X move element 0 of a vector register to or from an integer register. The
+//! slide instructions move each element up or down by an offset, with VSLIDE1UP and VSLIDE1DOWN
+//! inserting an integer register at the vacated end, while VRGATHER reads the element of `vs2`
+//! selected by each index (zero if the index is out of range), and VCOMPRESS packs the elements of
+//! `vs2` selected by a mask into the start of `vd`. The whole register moves (VMV<NR>R.V) copy 1,
+//! 2, 4, or 8 registers, independently of `vtype` and `vl`.
+
+use super::state::{check_group, sign_extend, truncate};
+use super::{
+    funct6, mask_operand, masked, read_x, write_x, Operand, VectorState, OPIVI, OPIVV, OPIVX,
+    OPMVV, OPMVX,
+};
+use std::sync::{Arc, Mutex};
+use z2l_core::error::ProcessorException;
+use z2l_core::instruction::{Instruction, InstructionResult, InstructionWordParts};
+use z2l_core::processor::register::RegisterFile;
+use z2l_core::processor::Xlen;
+
+/// VMV.X.S or VMV.S.X instruction.
+pub struct MoveScalarInstruction {
+    state: Arc<Mutex<VectorState>>,
+    to_vector: bool,
+    src: u8,
+    dest: u8,
+    xlen: Xlen,
+}
+
+impl MoveScalarInstruction {
+    /// Create a new MoveScalarInstruction, operating on the provided vector state, for a hart with
+    /// the provided XLEN.
+    pub fn new(
+        instruction: &InstructionWordParts,
+        state: Arc<Mutex<VectorState>>,
+        xlen: Xlen,
+    ) -> Result<Self, ProcessorException> {
+        let (to_vector, src) = match instruction.funct3 {
+            OPMVV if instruction.rs1 == 0 => (false, instruction.rs2),
+            OPMVX if instruction.rs2 == 0 => (true, instruction.rs1),
+            _ => return Err(ProcessorException::IllegalInstruction),
+        };
+        if masked(instruction) {
+            return Err(ProcessorException::IllegalInstruction);
+        }
+
+        Ok(Self {
+            state,
+            to_vector,
+            src,
+            dest: instruction.rd,
+            xlen,
+        })
+    }
+}
+
+impl Instruction for MoveScalarInstruction {
+    fn execute(
+        &self,
+        registers: &mut RegisterFile,
+        _mem: i64,
+    ) -> Result<InstructionResult, ProcessorException> {
+        let mut state = self.state.lock().unwrap();
+        let vtype = state.vtype()?;
+        let sew = vtype.sew;
+
+        if self.to_vector {
+            let value = read_x(registers, self.src)? as u64;
+            if state.vstart < state.vl {
+                state.write(self.dest, 0, sew, truncate(value, sew));
+                state.agnostic_tail(self.dest, 1, sew, 0, vtype);
+            }
+        } else {
+            // Element 0 is moved even if vl is zero
+            let value = sign_extend(state.read(self.src, 0, sew), sew);
+            write_x(registers, self.dest, self.xlen.sign_extend(value))?;
+        }
+        state.vstart = 0;
+        Ok(InstructionResult::default())
+    }
+
+    fn format(&self) -> String {
+        match self.to_vector {
+            true => format!("vmv.s.x v{}, x{}", self.dest, self.src),
+            false => format!("vmv.x.s x{}, v{}", self.dest, self.src),
+        }
+    }
+}
+
+/// Direction & kind of slide.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+enum Slide {
+    Up,
+    Down,
+    Up1,
+    Down1,
+}
+
+impl Slide {
+    /// Mnemonic for this slide, without the suffix identifying the kinds of operands.
+    fn name(&self) -> &'static str {
+        match self {
+            Slide::Up => "vslideup",
+            Slide::Down => "vslidedown",
+            Slide::Up1 => "vslide1up",
+            Slide::Down1 => "vslide1down",
+        }
+    }
+}
+
+/// VSLIDEUP, VSLIDEDOWN, VSLIDE1UP, or VSLIDE1DOWN instruction.
+pub struct SlideInstruction {
+    state: Arc<Mutex<VectorState>>,
+    slide: Slide,
+    src1: Operand,
+    src2: u8,
+    dest: u8,
+    masked: bool,
+    xlen: Xlen,
+}
+
+impl SlideInstruction {
+    /// Create a new SlideInstruction, operating on the provided vector state, for a hart with the
+    /// provided XLEN.
+    pub fn new(
+        instruction: &InstructionWordParts,
+        state: Arc<Mutex<VectorState>>,
+        xlen: Xlen,
+    ) -> Result<Self, ProcessorException> {
+        let slide = match (funct6(instruction), instruction.funct3) {
+            (0b001110, OPIVX | OPIVI) => Slide::Up,
+            (0b001111, OPIVX | OPIVI) => Slide::Down,
+            (0b001110, OPMVX) => Slide::Up1,
+            (0b001111, OPMVX) => Slide::Down1,
+            _ => return Err(ProcessorException::IllegalInstruction),
+        };
+
+        // The destination can't overlap the mask, or for slides up, the source
+        let (src2, dest, masked) = (instruction.rs2, instruction.rd, masked(instruction));
+        let up = matches!(slide, Slide::Up | Slide::Up1);
+        if (masked && dest == 0) || (up && dest == src2) {
+            return Err(ProcessorException::IllegalInstruction);
+        }
+
+        Ok(Self {
+            state,
+            slide,
+            src1: Operand::new(instruction, true)?,
+            src2,
+            dest,
+            masked,
+            xlen,
+        })
+    }
+}
+
+impl Instruction for SlideInstruction {
+    fn execute(
+        &self,
+        registers: &mut RegisterFile,
+        _mem: i64,
+    ) -> Result<InstructionResult, ProcessorException> {
+        let mut state = self.state.lock().unwrap();
+        let vtype = state.vtype()?;
+        let (sew, vl, vlmax) = (vtype.sew, state.vl, vtype.vlmax(state.vlen()));
+        check_group(self.dest, vtype.lmul)?;
+        check_group(self.src2, vtype.lmul)?;
+
+        let scalar = self.src1.scalar(registers)?;
+        let offset = self.xlen.zero_extend(scalar);
+        let src2 = self.src2;
+        match self.slide {
+            Slide::Up => {
+                // Elements below the offset are left undisturbed, as if they were prestart
+                // elements
+                let offset = offset.min(vl as u64) as usize;
+                state.vstart = state.vstart.max(offset);
+                state.update(
+                    self.dest,
+                    sew,
+                    vtype.lmul,
+                    vtype,
+                    self.masked,
+                    |state, i| Ok(state.read(src2, i - offset, sew)),
+                )?;
+            }
+            Slide::Down => {
+                state.update(
+                    self.dest,
+                    sew,
+                    vtype.lmul,
+                    vtype,
+                    self.masked,
+                    |state, i| {
+                        Ok(match (i as u64).checked_add(offset) {
+                            Some(index) if index < vlmax as u64 => {
+                                state.read(src2, index as usize, sew)
+                            }
+                            _ => 0,
+                        })
+                    },
+                )?;
+            }
+            Slide::Up1 => {
+                state.update(
+                    self.dest,
+                    sew,
+                    vtype.lmul,
+                    vtype,
+                    self.masked,
+                    |state, i| {
+                        Ok(match i {
+                            0 => truncate(scalar as u64, sew),
+                            _ => state.read(src2, i - 1, sew),
+                        })
+                    },
+                )?;
+            }
+            Slide::Down1 => {
+                state.update(
+                    self.dest,
+                    sew,
+                    vtype.lmul,
+                    vtype,
+                    self.masked,
+                    |state, i| {
+                        Ok(match i + 1 == vl {
+                            true => truncate(scalar as u64, sew),
+                            false => state.read(src2, i + 1, sew),
+                        })
+                    },
+                )?;
+            }
+        }
+        Ok(InstructionResult::default())
+    }
+
+    fn format(&self) -> String {
+        format!(
+            "{}.v{} v{}, v{}, {}{}",
+            self.slide.name(),
+            self.src1.kind(),
+            self.dest,
+            self.src2,
+            self.src1,
+            mask_operand(self.masked)
+        )
+    }
+}
+
+/// VRGATHER or VRGATHEREI16 instruction.
+pub struct GatherInstruction {
+    state: Arc<Mutex<VectorState>>,
+    index: Operand,
+    ei16: bool,
+    src2: u8,
+    dest: u8,
+    masked: bool,
+    xlen: Xlen,
+}
+
+impl GatherInstruction {
+    /// Create a new GatherInstruction, operating on the provided vector state, for a hart with
+    /// the provided XLEN.
+    pub fn new(
+        instruction: &InstructionWordParts,
+        state: Arc<Mutex<VectorState>>,
+        xlen: Xlen,
+    ) -> Result<Self, ProcessorException> {
+        let ei16 = match (funct6(instruction), instruction.funct3) {
+            (0b001100, OPIVV | OPIVX | OPIVI) => false,
+            (0b001110, OPIVV) => true,
+            _ => return Err(ProcessorException::IllegalInstruction),
+        };
+
+        // The destination can't overlap the sources, or the mask
+        let index = Operand::new(instruction, true)?;
+        let (src2, dest, masked) = (instruction.rs2, instruction.rd, masked(instruction));
+        if dest == src2 || index == Operand::Vector(dest) || (masked && dest == 0) {
+            return Err(ProcessorException::IllegalInstruction);
+        }
+
+        Ok(Self {
+            state,
+            index,
+            ei16,
+            src2,
+            dest,
+            masked,
+            xlen,
+        })
+    }
+}
+
+impl Instruction for GatherInstruction {
+    fn execute(
+        &self,
+        registers: &mut RegisterFile,
+        _mem: i64,
+    ) -> Result<InstructionResult, ProcessorException> {
+        let mut state = self.state.lock().unwrap();
+        let vtype = state.vtype()?;
+        let (sew, vlmax) = (vtype.sew, vtype.vlmax(state.vlen()) as u64);
+        check_group(self.dest, vtype.lmul)?;
+        check_group(self.src2, vtype.lmul)?;
+        let index_eew = if self.ei16 { 16 } else { sew };
+        if let Operand::Vector(index) = self.index {
+            check_group(index, vtype.emul(index_eew))?;
+        }
+
+        let scalar = self.xlen.zero_extend(self.index.scalar(registers)?);
+        let (index, src2) = (self.index, self.src2);
+        state.update(
+            self.dest,
+            sew,
+            vtype.lmul,
+            vtype,
+            self.masked,
+            |state, i| {
+                let index = match index {
+                    Operand::Vector(reg) => state.read(reg, i, index_eew),
+                    _ => scalar,
+                };
+                Ok(match index < vlmax {
+                    true => state.read(src2, index as usize, sew),
+                    false => 0,
+                })
+            },
+        )?;
+        Ok(InstructionResult::default())
+    }
+
+    fn format(&self) -> String {
+        let name = if self.ei16 {
+            "vrgatherei16"
+        } else {
+            "vrgather"
+        };
+        format!(
+            "{}.v{} v{}, v{}, {}{}",
+            name,
+            self.index.kind(),
+            self.dest,
+            self.src2,
+            self.index,
+            mask_operand(self.masked)
+        )
+    }
+}
+
+/// VCOMPRESS.VM instruction.
+pub struct CompressInstruction {
+    state: Arc<Mutex<VectorState>>,
+    src1: u8,
+    src2: u8,
+    dest: u8,
+}
+
+impl CompressInstruction {
+    /// Create a new CompressInstruction, operating on the provided vector state.
+    pub fn new(
+        instruction: &InstructionWordParts,
+        state: Arc<Mutex<VectorState>>,
+    ) -> Result<Self, ProcessorException> {
+        // The destination can't overlap either source
+        let (src1, src2, dest) = (instruction.rs1, instruction.rs2, instruction.rd);
+        if instruction.funct3 != OPMVV || masked(instruction) || dest == src1 || dest == src2 {
+            return Err(ProcessorException::IllegalInstruction);
+        }
+
+        Ok(Self {
+            state,
+            src1,
+            src2,
+            dest,
+        })
+    }
+}
+
+impl Instruction for CompressInstruction {
+    fn execute(
+        &self,
+        _registers: &mut RegisterFile,
+        _mem: i64,
+    ) -> Result<InstructionResult, ProcessorException> {
+        let mut state = self.state.lock().unwrap();
+        let vtype = state.vtype()?;
+        let sew = vtype.sew;
+        check_group(self.dest, vtype.lmul)?;
+        check_group(self.src2, vtype.lmul)?;
+
+        // Elements are packed from the start of the destination, so it can't be resumed
+        if state.vstart != 0 {
+            return Err(ProcessorException::IllegalInstruction);
+        }
+
+        if state.vl > 0 {
+            let packed = (0..state.vl)
+                .filter(|&i| state.mask(self.src1, i))
+                .map(|i| state.read(self.src2, i, sew))
+                .collect::<Vec<_>>();
+            for (i, &value) in packed.iter().enumerate() {
+                state.write(self.dest, i, sew, value);
+            }
+
+            // The elements following those packed are tail elements
+            state.agnostic_tail(self.dest, packed.len(), sew, vtype.lmul, vtype);
+        }
+        Ok(InstructionResult::default())
+    }
+
+    fn format(&self) -> String {
+        format!(
+            "vcompress.vm v{}, v{}, v{}",
+            self.dest, self.src2, self.src1
+        )
+    }
+}
+
+/// VMV1R.V, VMV2R.V, VMV4R.V, or VMV8R.V instruction.
+pub struct WholeMoveInstruction {
+    state: Arc<Mutex<VectorState>>,
+    registers: u8,
+    src: u8,
+    dest: u8,
+}
+
+impl WholeMoveInstruction {
+    /// Create a new WholeMoveInstruction, operating on the provided vector state.
+    pub fn new(
+        instruction: &InstructionWordParts,
+        state: Arc<Mutex<VectorState>>,
+    ) -> Result<Self, ProcessorException> {
+        let registers = instruction.rs1 + 1;
+        if instruction.funct3 != OPIVI || !registers.is_power_of_two() || masked(instruction) {
+            return Err(ProcessorException::IllegalInstruction);
+        }
+
+        let (src, dest) = (instruction.rs2, instruction.rd);
+        let emul = registers.trailing_zeros() as i32;
+        check_group(src, emul)?;
+        check_group(dest, emul)?;
+
+        Ok(Self {
+            state,
+            registers,
+            src,
+            dest,
+        })
+    }
+}
+
+impl Instruction for WholeMoveInstruction {
+    fn execute(
+        &self,
+        _registers: &mut RegisterFile,
+        _mem: i64,
+    ) -> Result<InstructionResult, ProcessorException> {
+        // Elements are SEW bits wide, for the purposes of vstart, or bytes if vill is set
+        let mut state = self.state.lock().unwrap();
+        let eew = state.vtype.map_or(8, |vtype| vtype.sew);
+        let evl = self.registers as usize * state.vlen() / eew as usize;
+        for i in state.vstart..evl {
+            let value = state.read(self.src, i, eew);
+            state.write(self.dest, i, eew, value);
+        }
+        state.vstart = 0;
+        Ok(InstructionResult::default())
+    }
+
+    fn format(&self) -> String {
+        format!("vmv{}r.v v{}, v{}", self.registers, self.dest, self.src)
+    }
+}
@@ -0,0 +1,154 @@
+//! Reduction instructions.
+//!
+//! These instructions combine element 0 of `vs1` with each active element of `vs2`, writing the
+//! result to element 0 of `vd`: Sum, bitwise AND, OR & XOR, and minimum/maximum. The widening sums
+//! (VWREDSUMU & VWREDSUM) extend each element of `vs2` to 2*SEW bits, and read & write elements of
+//! 2*SEW bits. If `vl` is zero, `vd` is not written. The rest of `vd` is treated as its tail.
+//!
+//! Reductions can't be resumed part-way through, so raise an IllegalInstruction exception if
+//! `vstart` is not zero.
+
+use super::state::{check_group, sign_extend, truncate};
+use super::{funct6, mask_operand, masked, VectorState, OPIVV, OPMVV};
+use std::sync::{Arc, Mutex};
+use z2l_core::error::ProcessorException;
+use z2l_core::instruction::{Instruction, InstructionResult, InstructionWordParts};
+use z2l_core::processor::register::RegisterFile;
+
+/// Operation used to combine the elements.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+enum Reduction {
+    Sum,
+    And,
+    Or,
+    Xor,
+    MinUnsigned,
+    Min,
+    MaxUnsigned,
+    Max,
+    WideSumUnsigned,
+    WideSum,
+}
+
+impl Reduction {
+    /// Mnemonic for this reduction.
+    fn name(&self) -> &'static str {
+        match self {
+            Reduction::Sum => "vredsum",
+            Reduction::And => "vredand",
+            Reduction::Or => "vredor",
+            Reduction::Xor => "vredxor",
+            Reduction::MinUnsigned => "vredminu",
+            Reduction::Min => "vredmin",
+            Reduction::MaxUnsigned => "vredmaxu",
+            Reduction::Max => "vredmax",
+            Reduction::WideSumUnsigned => "vwredsumu",
+            Reduction::WideSum => "vwredsum",
+        }
+    }
+
+    /// Combine the accumulated value with an element, both `eew` bits wide.
+    fn combine(&self, acc: u64, value: u64, eew: u32) -> u64 {
+        let (sacc, svalue) = (sign_extend(acc, eew), sign_extend(value, eew));
+        match self {
+            Reduction::Sum | Reduction::WideSumUnsigned | Reduction::WideSum => {
+                acc.wrapping_add(value)
+            }
+            Reduction::And => acc & value,
+            Reduction::Or => acc | value,
+            Reduction::Xor => acc ^ value,
+            Reduction::MinUnsigned => acc.min(value),
+            Reduction::Min => sacc.min(svalue) as u64,
+            Reduction::MaxUnsigned => acc.max(value),
+            Reduction::Max => sacc.max(svalue) as u64,
+        }
+    }
+}
+
+/// Reduction instruction.
+pub struct ReductionInstruction {
+    state: Arc<Mutex<VectorState>>,
+    reduction: Reduction,
+    src1: u8,
+    src2: u8,
+    dest: u8,
+    masked: bool,
+}
+
+impl ReductionInstruction {
+    /// Create a new ReductionInstruction, operating on the provided vector state.
+    pub fn new(
+        instruction: &InstructionWordParts,
+        state: Arc<Mutex<VectorState>>,
+    ) -> Result<Self, ProcessorException> {
+        let reduction = match (funct6(instruction), instruction.funct3) {
+            (0b000000, OPMVV) => Reduction::Sum,
+            (0b000001, OPMVV) => Reduction::And,
+            (0b000010, OPMVV) => Reduction::Or,
+            (0b000011, OPMVV) => Reduction::Xor,
+            (0b000100, OPMVV) => Reduction::MinUnsigned,
+            (0b000101, OPMVV) => Reduction::Min,
+            (0b000110, OPMVV) => Reduction::MaxUnsigned,
+            (0b000111, OPMVV) => Reduction::Max,
+            (0b110000, OPIVV) => Reduction::WideSumUnsigned,
+            (0b110001, OPIVV) => Reduction::WideSum,
+            _ => return Err(ProcessorException::IllegalInstruction),
+        };
+
+        Ok(Self {
+            state,
+            reduction,
+            src1: instruction.rs1,
+            src2: instruction.rs2,
+            dest: instruction.rd,
+            masked: masked(instruction),
+        })
+    }
+}
+
+impl Instruction for ReductionInstruction {
+    fn execute(
+        &self,
+        _registers: &mut RegisterFile,
+        _mem: i64,
+    ) -> Result<InstructionResult, ProcessorException> {
+        let mut state = self.state.lock().unwrap();
+        let vtype = state.vtype()?;
+        let sew = vtype.sew;
+        let wide = matches!(
+            self.reduction,
+            Reduction::WideSumUnsigned | Reduction::WideSum
+        );
+        let eew = if wide { sew * 2 } else { sew };
+        if eew > state.elen() || state.vstart != 0 {
+            return Err(ProcessorException::IllegalInstruction);
+        }
+        check_group(self.src2, vtype.lmul)?;
+
+        if state.vl > 0 {
+            let mut acc = state.read(self.src1, 0, eew);
+            for i in (0..state.vl).filter(|&i| state.active(self.masked, i)) {
+                let value = state.read(self.src2, i, sew);
+                let value = match self.reduction {
+                    Reduction::WideSum => truncate(sign_extend(value, sew) as u64, eew),
+                    _ => value,
+                };
+                acc = truncate(self.reduction.combine(acc, value, eew), eew);
+            }
+            state.write(self.dest, 0, eew, acc);
+            state.agnostic_tail(self.dest, 1, eew, 0, vtype);
+        }
+        Ok(InstructionResult::default())
+    }
+
+    fn format(&self) -> String {
+        format!(
+            "{}.vs v{}, v{}, v{}{}",
+            self.reduction.name(),
+            self.dest,
+            self.src2,
+            self.src1,
+            mask_operand(self.masked)
+        )
+    }
+}
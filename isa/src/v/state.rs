@@ -0,0 +1,415 @@
+//! Vector register file and configuration state.
+
+use std::fmt;
+use z2l_core::error::ProcessorException;
+
+/// How elements which the `vta` and `vma` bits of `vtype` make agnostic are written.
+///
+/// Software may not rely on the value of agnostic elements, so either is permitted by the spec.
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, PartialEq)]
+pub enum AgnosticPolicy {
+    /// Agnostic elements are overwritten with all ones.
+    ///
+    /// This exposes software which wrongly expects agnostic elements to be left undisturbed.
+    #[default]
+    Ones,
+
+    /// Agnostic elements are left undisturbed, as if `vta` and `vma` were clear.
+    Undisturbed,
+}
+
+/// A vector type setting, as held in the `vtype` CSR.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub struct Vtype {
+    /// Selected element width (SEW), in bits.
+    pub sew: u32,
+
+    /// Base-2 logarithm of the register group multiplier (LMUL), from -3 (1/8) to 3 (8).
+    pub lmul: i32,
+
+    /// Whether tail elements are agnostic (`vta`).
+    pub tail_agnostic: bool,
+
+    /// Whether inactive elements are agnostic (`vma`).
+    pub mask_agnostic: bool,
+}
+
+impl Vtype {
+    /// Decode a `vtype` value for a hart with the provided ELEN.
+    ///
+    /// Returns `None` if the setting is reserved or unsupported, in which case `vill` is set.
+    pub fn decode(value: u64, elen: u32) -> Option<Self> {
+        if value >> 8 != 0 {
+            return None;
+        }
+        let lmul = match value & 0b111 {
+            0b000..=0b011 => (value & 0b111) as i32,
+            0b101 => -3,
+            0b110 => -2,
+            0b111 => -1,
+            _ => return None,
+        };
+        let sew = 8 << ((value >> 3) & 0b111);
+
+        // A fractional LMUL must leave room for at least one element of ELEN bits
+        if sew > elen || (lmul < 0 && sew << -lmul > elen) {
+            return None;
+        }
+
+        Some(Self {
+            sew,
+            lmul,
+            tail_agnostic: value & (1 << 6) != 0,
+            mask_agnostic: value & (1 << 7) != 0,
+        })
+    }
+
+    /// Encode this setting as a `vtype` value.
+    pub fn encode(&self) -> u64 {
+        ((self.mask_agnostic as u64) << 7)
+            | ((self.tail_agnostic as u64) << 6)
+            | ((self.sew.trailing_zeros() as u64 - 3) << 3)
+            | (self.lmul as u64 & 0b111)
+    }
+
+    /// Maximum number of elements an instruction can operate on with this setting, on a hart with
+    /// the provided VLEN (VLMAX).
+    pub fn vlmax(&self, vlen: usize) -> usize {
+        scale(vlen, self.lmul) / self.sew as usize
+    }
+
+    /// Base-2 logarithm of the register group multiplier for elements of `eew` bits (EMUL), keeping
+    /// the ratio of element width to group size the same as for SEW.
+    pub fn emul(&self, eew: u32) -> i32 {
+        self.lmul + eew.trailing_zeros() as i32 - self.sew.trailing_zeros() as i32
+    }
+}
+
+impl fmt::Display for Vtype {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "e{}, ", self.sew)?;
+        match self.lmul {
+            lmul if lmul < 0 => write!(f, "mf{}", 1 << -lmul)?,
+            lmul => write!(f, "m{}", 1 << lmul)?,
+        }
+        let tail = if self.tail_agnostic { "ta" } else { "tu" };
+        let mask = if self.mask_agnostic { "ma" } else { "mu" };
+        write!(f, ", {}, {}", tail, mask)
+    }
+}
+
+/// Multiply `value` by 2 to the power of `log2`, which may be negative.
+pub(crate) fn scale(value: usize, log2: i32) -> usize {
+    if log2 < 0 {
+        value >> -log2
+    } else {
+        value << log2
+    }
+}
+
+/// Check that a group of registers holding elements with the provided EMUL can start at `reg`.
+///
+/// Raises an IllegalInstruction exception if EMUL is out of range, or if `reg` is not a multiple of
+/// the number of registers in the group.
+pub(crate) fn check_group(reg: u8, emul: i32) -> Result<(), ProcessorException> {
+    if !(-3..=3).contains(&emul) || !reg.is_multiple_of(1 << emul.max(0)) {
+        return Err(ProcessorException::IllegalInstruction);
+    }
+    Ok(())
+}
+
+/// State of the vector unit of a hart.
+///
+/// This is shared between the vector CSRs and the vector instructions.
+#[derive(Clone, Debug)]
+pub struct VectorState {
+    /// Width of each vector register, in bits.
+    vlen: usize,
+
+    /// Width of the widest supported element, in bits.
+    elen: u32,
+
+    /// How agnostic elements are written.
+    policy: AgnosticPolicy,
+
+    /// Contents of `v0` to `v31`, in order, with each register holding its elements in
+    /// little-endian order.
+    data: Vec<u8>,
+
+    /// Number of elements to operate on (`vl`).
+    pub vl: usize,
+
+    /// Current vector type setting, or `None` if `vill` is set.
+    pub vtype: Option<Vtype>,
+
+    /// Index of the first element to operate on (`vstart`).
+    pub vstart: usize,
+}
+
+impl VectorState {
+    /// Create a new VectorState with the provided VLEN & ELEN, in bits.
+    ///
+    /// The registers are zeroed, and `vill` is set.
+    pub fn new(vlen: usize, elen: u32, policy: AgnosticPolicy) -> Self {
+        Self {
+            vlen,
+            elen,
+            policy,
+            data: vec![0; 32 * vlen / 8],
+            vl: 0,
+            vtype: None,
+            vstart: 0,
+        }
+    }
+
+    /// Width of each vector register, in bits.
+    pub fn vlen(&self) -> usize {
+        self.vlen
+    }
+
+    /// Width of each vector register, in bytes.
+    pub fn vlenb(&self) -> usize {
+        self.vlen / 8
+    }
+
+    /// Width of the widest supported element, in bits.
+    pub fn elen(&self) -> u32 {
+        self.elen
+    }
+
+    /// Get the current vector type setting.
+    ///
+    /// Raises an IllegalInstruction exception if `vill` is set.
+    pub fn vtype(&self) -> Result<Vtype, ProcessorException> {
+        self.vtype.ok_or(ProcessorException::IllegalInstruction)
+    }
+
+    /// Get the contents of the vector register with the provided number.
+    pub fn register(&self, reg: u8) -> &[u8] {
+        let start = reg as usize * self.vlenb();
+        &self.data[start..start + self.vlenb()]
+    }
+
+    /// Get the contents of the vector register with the provided number, for writing.
+    pub fn register_mut(&mut self, reg: u8) -> &mut [u8] {
+        let start = reg as usize * self.vlenb();
+        let vlenb = self.vlenb();
+        &mut self.data[start..start + vlenb]
+    }
+
+    /// Read element `index` of the register group starting at `reg`, with elements of `eew` bits.
+    ///
+    /// The element is zero-extended to 64 bits.
+    pub fn read(&self, reg: u8, index: usize, eew: u32) -> u64 {
+        let bytes = eew as usize / 8;
+        let start = reg as usize * self.vlenb() + index * bytes;
+        self.data[start..start + bytes]
+            .iter()
+            .rev()
+            .fold(0, |value, &byte| (value << 8) | byte as u64)
+    }
+
+    /// Write element `index` of the register group starting at `reg`, with elements of `eew` bits.
+    ///
+    /// The most significant bits of `value` which don't fit in the element are discarded.
+    pub fn write(&mut self, reg: u8, index: usize, eew: u32, value: u64) {
+        let bytes = eew as usize / 8;
+        let start = reg as usize * self.vlenb() + index * bytes;
+        let value = value.to_le_bytes();
+        self.data[start..start + bytes].copy_from_slice(&value[..bytes]);
+    }
+
+    /// Read bit `index` of the mask register `reg`.
+    pub fn mask(&self, reg: u8, index: usize) -> bool {
+        self.register(reg)[index / 8] & (1 << (index % 8)) != 0
+    }
+
+    /// Write bit `index` of the mask register `reg`.
+    pub fn set_mask(&mut self, reg: u8, index: usize, value: bool) {
+        let byte = &mut self.register_mut(reg)[index / 8];
+        *byte = (*byte & !(1 << (index % 8))) | ((value as u8) << (index % 8));
+    }
+
+    /// Determine whether element `index` is active: Elements are only inactive if the instruction
+    /// is masked, and the element's bit in `v0` is clear.
+    pub fn active(&self, masked: bool, index: usize) -> bool {
+        !masked || self.mask(0, index)
+    }
+
+    /// Number of elements of `eew` bits in a register group with the provided EMUL.
+    ///
+    /// Fractional groups occupy a whole register, so their tail extends to the end of it.
+    pub(crate) fn group_elements(&self, eew: u32, emul: i32) -> usize {
+        scale(self.vlen, emul.max(0)) / eew as usize
+    }
+
+    /// Write an agnostic element: Element `index` of the register group starting at `reg`.
+    pub(crate) fn agnostic(&mut self, reg: u8, index: usize, eew: u32) {
+        if self.policy == AgnosticPolicy::Ones {
+            self.write(reg, index, eew, u64::MAX);
+        }
+    }
+
+    /// Write an agnostic mask bit: Bit `index` of the mask register `reg`.
+    pub(crate) fn agnostic_mask(&mut self, reg: u8, index: usize) {
+        if self.policy == AgnosticPolicy::Ones {
+            self.set_mask(reg, index, true);
+        }
+    }
+
+    /// Write the tail of the register group starting at `reg`, from element `start`, if tail
+    /// elements are agnostic.
+    pub(crate) fn agnostic_tail(
+        &mut self,
+        reg: u8,
+        start: usize,
+        eew: u32,
+        emul: i32,
+        vtype: Vtype,
+    ) {
+        if vtype.tail_agnostic {
+            for index in start..self.group_elements(eew, emul) {
+                self.agnostic(reg, index, eew);
+            }
+        }
+    }
+
+    /// Write the results of an element-wise instruction to the register group starting at `vd`,
+    /// which holds elements of `eew` bits with the provided EMUL, then reset `vstart`.
+    ///
+    /// `result` is called with the index of each active element from `vstart` to `vl`, and all
+    /// results are computed before any are written, so the destination may overlap the sources.
+    /// Inactive and tail elements are written according to `vtype`, and the elements before
+    /// `vstart` are left undisturbed. If `vstart` is not below `vl`, no elements are written.
+    pub(crate) fn update<F>(
+        &mut self,
+        vd: u8,
+        eew: u32,
+        emul: i32,
+        vtype: Vtype,
+        masked: bool,
+        mut result: F,
+    ) -> Result<(), ProcessorException>
+    where
+        F: FnMut(&Self, usize) -> Result<u64, ProcessorException>,
+    {
+        let (start, vl) = (self.vstart, self.vl);
+        if start < vl {
+            let results = (start..vl)
+                .map(|i| match self.active(masked, i) {
+                    true => result(self, i).map(Some),
+                    false => Ok(None),
+                })
+                .collect::<Result<Vec<_>, _>>()?;
+
+            for (i, value) in (start..vl).zip(results) {
+                match value {
+                    Some(value) => self.write(vd, i, eew, value),
+                    None if vtype.mask_agnostic => self.agnostic(vd, i, eew),
+                    None => {}
+                }
+            }
+            self.agnostic_tail(vd, vl, eew, emul, vtype);
+        }
+        self.vstart = 0;
+        Ok(())
+    }
+
+    /// Write the results of an instruction producing a mask to the mask register `vd`, then reset
+    /// `vstart`.
+    ///
+    /// This behaves like [`update`](Self::update), except that the tail of the mask register is
+    /// always left undisturbed.
+    pub(crate) fn update_mask<F>(
+        &mut self,
+        vd: u8,
+        vtype: Vtype,
+        masked: bool,
+        mut result: F,
+    ) -> Result<(), ProcessorException>
+    where
+        F: FnMut(&Self, usize) -> Result<bool, ProcessorException>,
+    {
+        let (start, vl) = (self.vstart, self.vl);
+        if start < vl {
+            let results = (start..vl)
+                .map(|i| match self.active(masked, i) {
+                    true => result(self, i).map(Some),
+                    false => Ok(None),
+                })
+                .collect::<Result<Vec<_>, _>>()?;
+
+            for (i, value) in (start..vl).zip(results) {
+                match value {
+                    Some(value) => self.set_mask(vd, i, value),
+                    None if vtype.mask_agnostic => self.agnostic_mask(vd, i),
+                    None => {}
+                }
+            }
+        }
+        self.vstart = 0;
+        Ok(())
+    }
+}
+
+/// Sign-extend the lowest `bits` bits of `value`.
+pub(crate) fn sign_extend(value: u64, bits: u32) -> i64 {
+    ((value << (64 - bits)) as i64) >> (64 - bits)
+}
+
+/// Discard all but the lowest `bits` bits of `value`.
+pub(crate) fn truncate(value: u64, bits: u32) -> u64 {
+    match bits {
+        64 => value,
+        _ => value & ((1 << bits) - 1),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{AgnosticPolicy, VectorState, Vtype};
+
+    #[test]
+    fn vtype() {
+        // e32, m1, ta, ma
+        let vtype = Vtype::decode(0xd0, 32).unwrap();
+        assert_eq!((vtype.sew, vtype.lmul), (32, 0));
+        assert!(vtype.tail_agnostic && vtype.mask_agnostic);
+        assert_eq!(vtype.encode(), 0xd0);
+        assert_eq!(vtype.vlmax(128), 4);
+        assert_eq!(vtype.to_string(), "e32, m1, ta, ma");
+
+        // e8, mf4, tu, mu
+        let vtype = Vtype::decode(0x06, 32).unwrap();
+        assert_eq!((vtype.sew, vtype.lmul), (8, -2));
+        assert_eq!(vtype.encode(), 0x06);
+        assert_eq!(vtype.vlmax(128), 4);
+        assert_eq!(vtype.emul(32), 0);
+        assert_eq!(vtype.to_string(), "e8, mf4, tu, mu");
+
+        // e64, reserved LMUL, SEW/LMUL wider than ELEN, reserved bits
+        assert_eq!(Vtype::decode(0x18, 32), None);
+        assert!(Vtype::decode(0x18, 64).is_some());
+        assert_eq!(Vtype::decode(0x04, 32), None);
+        assert_eq!(Vtype::decode(0x15, 32), None);
+        assert_eq!(Vtype::decode(0x100, 32), None);
+    }
+
+    #[test]
+    fn elements() {
+        let mut state = VectorState::new(64, 32, AgnosticPolicy::Ones);
+        assert_eq!(state.vlenb(), 8);
+        assert!(state.vtype().is_err());
+
+        // Elements are little-endian, and groups span consecutive registers
+        state.write(2, 3, 32, 0x1_1234_5678);
+        assert_eq!(state.read(2, 3, 32), 0x1234_5678);
+        assert_eq!(state.read(3, 2, 16), 0x5678);
+        assert_eq!(state.register(3)[4..], [0x78, 0x56, 0x34, 0x12]);
+
+        state.set_mask(0, 9, true);
+        assert!(state.mask(0, 9) && !state.mask(0, 8));
+        assert_eq!(state.read(0, 1, 8), 0x02);
+        assert!(state.active(false, 8) && !state.active(true, 8));
+    }
+}
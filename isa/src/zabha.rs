@@ -0,0 +1,129 @@
+//! The "Zabha" standard extension for byte and half-word atomic memory operations.
+//!
+//! Zabha adds byte & half-word variants of the atomic memory operation instructions defined by the
+//! "A" extension: AMOSWAP, AMOADD, AMOXOR, AMOAND, AMOOR, AMOMIN, AMOMAX, AMOMINU, and AMOMAXU,
+//! each with a .B and .H suffix. The value loaded from memory is sign-extended to XLEN bits before
+//! being written to rd. There are no byte or half-word load-reserved/store-conditional
+//! instructions.
+//!
+//! The instructions share the AMO opcode with the "A" extension, so are registered as a
+//! [`SubDecoder`] on that opcode. If the Zacas extension is also present, Zabha additionally
+//! provides AMOCAS.B and AMOCAS.H.
+
+use crate::a::{self, AmoInstruction};
+use crate::zacas::{self, AmoCasInstruction, AMOCAS};
+use z2l_core::error::ProcessorException;
+use z2l_core::extension::{Extension, SubDecoder};
+use z2l_core::instruction::{Instruction, InstructionWordParts};
+use z2l_core::mmu::MemoryAccessType;
+use z2l_core::processor::hart::Hart;
+use z2l_core::processor::Xlen;
+
+/// An AMOADD.B instruction, used to detect whether Zabha is present.
+const PROBE: u32 = 0x0000_002f; // amoadd.b x0, x0, (x0)
+
+/// An [`Extension`] defining the Zabha standard extension.
+pub struct Zabha;
+
+impl Extension for Zabha {
+    fn code(&self) -> &'static str {
+        "Zabha"
+    }
+
+    fn name(&self) -> &'static str {
+        "Standard Extension for Byte and Halfword Atomic Memory Operations"
+    }
+
+    fn register(&self, hart: &mut Hart) {
+        let cas = zacas::is_present(hart);
+        hart.register_sub_decoder(0x2f, Box::new(ZabhaDecoder::new(cas)));
+    }
+}
+
+/// Determine whether Zabha has already been registered on the provided hart.
+pub(crate) fn is_present(hart: &Hart) -> bool {
+    a::decodes(hart, PROBE)
+}
+
+/// Decoder for the Zabha extension's instructions, in the AMO opcode.
+pub struct ZabhaDecoder {
+    cas: bool,
+}
+
+impl ZabhaDecoder {
+    /// Create a new ZabhaDecoder. If `cas` is set, AMOCAS.B and AMOCAS.H are also decoded.
+    pub fn new(cas: bool) -> Self {
+        Self { cas }
+    }
+}
+
+impl SubDecoder for ZabhaDecoder {
+    fn decode(
+        &self,
+        instruction: &InstructionWordParts,
+        _pc: u64,
+        xlen: Xlen,
+    ) -> Result<Option<Box<dyn Instruction>>, ProcessorException> {
+        let width = match (instruction.opcode, instruction.funct3) {
+            (0x2f, 0b000) => MemoryAccessType::SignedByte,
+            (0x2f, 0b001) => MemoryAccessType::SignedHalfWord,
+            _ => return Ok(None),
+        };
+
+        let funct5 = instruction.funct7 >> 2;
+        if funct5 == AMOCAS && self.cas {
+            let instruction = AmoCasInstruction::new(instruction, width, xlen)?;
+            return Ok(Some(Box::new(instruction)));
+        }
+        Ok(a::operation(funct5).map(|op| {
+            Box::new(AmoInstruction::new(instruction, op, width, xlen)) as Box<dyn Instruction>
+        }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Zabha;
+    use crate::a::A;
+    use crate::rv32i::RV32I;
+    use crate::rv64i::RV64I;
+    use crate::test_utils::{processor_in_ram, reg};
+    use crate::zacas::Zacas;
+    use z2l_core::extension::Extension;
+
+    #[test]
+    fn byte_halfword_operations() {
+        // Data follows the 11 instructions, at offset 44
+        let program = [
+            0x0000_0097, // auipc x1, 0
+            0x02c0_8093, // addi x1, x1, 44
+            0x0010_0113, // addi x2, x0, 1
+            0x0020_81af, // amoadd.b x3, x2, (x1)
+            0x0010_8213, // addi x4, x1, 1
+            0xe022_02af, // amomaxu.b x5, x2, (x4)
+            0x0020_8213, // addi x4, x1, 2
+            0x0822_132f, // amoswap.h x6, x2, (x4)
+            0xfff0_0393, // addi x7, x0, -1
+            0x2870_942f, // amocas.h x8, x7, (x1)
+            0x0000_006f, // jal x0, 0
+        ];
+        let data = 0x8000_807fu32.to_le_bytes();
+
+        for base in [Box::new(RV32I) as Box<dyn Extension>, Box::new(RV64I)] {
+            let extensions = vec![base, Box::new(A), Box::new(Zabha), Box::new(Zacas)];
+            let mut processor = processor_in_ram(extensions, &program, &data);
+            for _ in 0..20 {
+                processor.cycle().unwrap();
+            }
+
+            // Loaded values are sign-extended, and only the addressed bytes are modified
+            assert_eq!(reg(&processor, 3), 0x7f);
+            assert_eq!(reg(&processor, 5), -0x80);
+            assert_eq!(reg(&processor, 6), -0x8000);
+            // The comparison fails, as x8 was zero
+            assert_eq!(reg(&processor, 8), -0x7f80);
+            let mut mmu = processor.mmu.lock().unwrap();
+            assert_eq!(mmu.load_unsigned_word(0x8000_002c).unwrap(), 0x0001_8080);
+        }
+    }
+}
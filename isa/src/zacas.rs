@@ -0,0 +1,300 @@
+//! The "Zacas" standard extension for atomic compare-and-swap instructions.
+//!
+//! Zacas adds AMOCAS.W, and AMOCAS.D, which atomically load the value at the address in rs1 into
+//! rd, then store the value in rs2 to the same address, but only if the loaded value was equal to
+//! the value originally in rd. On RV32, AMOCAS.D operates on register pairs: The low 32 bits of
+//! each 64-bit value are held in an even-numbered register, and the high 32 bits in the register
+//! following it. A pair naming `x0` reads as zero, and is not written. Odd-numbered registers are
+//! reserved for AMOCAS.D on RV32, so raise an IllegalInstruction exception.
+//!
+//! AMOCAS.Q, the 128-bit variant for RV64, is not supported, as our memory accesses are at most 64
+//! bits wide.
+//!
+//! The instructions share the AMO opcode with the "A" extension, so are registered as a
+//! [`SubDecoder`] on that opcode. If the Zabha extension is also present, Zacas additionally
+//! provides the byte & half-word variants, AMOCAS.B and AMOCAS.H.
+
+use crate::a::{self, Ordering};
+use crate::zabha;
+use z2l_core::error::ProcessorException;
+use z2l_core::extension::{Extension, SubDecoder};
+use z2l_core::instruction::{Instruction, InstructionResult, InstructionWordParts};
+use z2l_core::mmu::{AtomicOperation, AtomicSpec, MemoryAccessType};
+use z2l_core::processor::hart::Hart;
+use z2l_core::processor::register::RegisterFile;
+use z2l_core::processor::Xlen;
+
+/// funct5 field of the AMOCAS instructions.
+pub(crate) const AMOCAS: u8 = 0b00101;
+
+/// An AMOCAS.W instruction, used to detect whether Zacas is present.
+const PROBE: u32 = 0x2800_202f; // amocas.w x0, x0, (x0)
+
+/// An [`Extension`] defining the Zacas standard extension.
+pub struct Zacas;
+
+impl Extension for Zacas {
+    fn code(&self) -> &'static str {
+        "Zacas"
+    }
+
+    fn name(&self) -> &'static str {
+        "Standard Extension for Atomic Compare-and-Swap Instructions"
+    }
+
+    fn register(&self, hart: &mut Hart) {
+        let narrow = zabha::is_present(hart);
+        hart.register_sub_decoder(0x2f, Box::new(ZacasDecoder::new(narrow)));
+    }
+}
+
+/// Determine whether Zacas has already been registered on the provided hart.
+pub(crate) fn is_present(hart: &Hart) -> bool {
+    a::decodes(hart, PROBE)
+}
+
+/// Decoder for the Zacas extension's instructions, in the AMO opcode.
+pub struct ZacasDecoder {
+    narrow: bool,
+}
+
+impl ZacasDecoder {
+    /// Create a new ZacasDecoder. If `narrow` is set, AMOCAS.B and AMOCAS.H are also decoded.
+    pub fn new(narrow: bool) -> Self {
+        Self { narrow }
+    }
+}
+
+impl SubDecoder for ZacasDecoder {
+    fn decode(
+        &self,
+        instruction: &InstructionWordParts,
+        _pc: u64,
+        xlen: Xlen,
+    ) -> Result<Option<Box<dyn Instruction>>, ProcessorException> {
+        if instruction.opcode != 0x2f || instruction.funct7 >> 2 != AMOCAS {
+            return Ok(None);
+        }
+        let width = match instruction.funct3 {
+            0b000 if self.narrow => MemoryAccessType::SignedByte,
+            0b001 if self.narrow => MemoryAccessType::SignedHalfWord,
+            0b010 => MemoryAccessType::Word,
+            0b011 => MemoryAccessType::DoubleWord,
+            _ => return Ok(None),
+        };
+
+        Ok(Some(Box::new(AmoCasInstruction::new(
+            instruction,
+            width,
+            xlen,
+        )?)))
+    }
+}
+
+/// An AMOCAS.B, AMOCAS.H, AMOCAS.W or AMOCAS.D instruction.
+pub struct AmoCasInstruction {
+    addr: u8,
+    src: u8,
+    dest: u8,
+    width: MemoryAccessType,
+    ordering: Ordering,
+    xlen: Xlen,
+}
+
+impl AmoCasInstruction {
+    /// Create a new AmoCasInstruction, which will compare & swap a value of the provided width.
+    pub fn new(
+        instruction: &InstructionWordParts,
+        width: MemoryAccessType,
+        xlen: Xlen,
+    ) -> Result<Self, ProcessorException> {
+        let instruction = Self {
+            addr: instruction.rs1,
+            src: instruction.rs2,
+            dest: instruction.rd,
+            width,
+            ordering: Ordering::new(instruction),
+            xlen,
+        };
+        if instruction.paired() && (instruction.src | instruction.dest) & 1 != 0 {
+            return Err(ProcessorException::IllegalInstruction);
+        }
+
+        Ok(instruction)
+    }
+
+    /// Determine whether the instruction operates on register pairs.
+    fn paired(&self) -> bool {
+        self.width.bytes() * 8 > self.xlen.bits() as usize
+    }
+
+    /// Read a value from the provided register, or register pair.
+    fn read(&self, registers: &RegisterFile, reg: u8) -> Result<i64, ProcessorException> {
+        let load = |reg| {
+            registers
+                .get(&reg)
+                .ok_or(ProcessorException::IllegalInstruction)?
+                .load()
+        };
+        if !self.paired() {
+            load(reg)
+        } else if reg == 0 {
+            Ok(0)
+        } else {
+            Ok((load(reg + 1)? << 32) | self.xlen.zero_extend(load(reg)?) as i64)
+        }
+    }
+}
+
+impl Instruction for AmoCasInstruction {
+    fn atomic(&self, registers: &RegisterFile) -> Result<Option<AtomicSpec>, ProcessorException> {
+        // Check the destination exists before the memory access, which may have side effects
+        let expected = self.read(registers, self.dest)?;
+        let addr = registers
+            .get(&self.addr)
+            .ok_or(ProcessorException::IllegalInstruction)?
+            .load()?;
+        let addr = self.xlen.zero_extend(addr) as usize;
+        let src = self.read(registers, self.src)?;
+
+        Ok(Some(AtomicSpec::new(
+            AtomicOperation::CompareAndSwap(expected),
+            self.width,
+            addr,
+            src,
+        )))
+    }
+
+    fn execute(
+        &self,
+        registers: &mut RegisterFile,
+        mem: i64,
+    ) -> Result<InstructionResult, ProcessorException> {
+        let mut store = |reg, value| {
+            registers
+                .get_mut(&reg)
+                .ok_or(ProcessorException::IllegalInstruction)?
+                .store(value)
+        };
+        if !self.paired() {
+            store(self.dest, mem)?;
+        } else if self.dest != 0 {
+            store(self.dest, mem as i32 as i64)?;
+            store(self.dest + 1, mem >> 32)?;
+        }
+
+        Ok(InstructionResult::default())
+    }
+
+    fn format(&self) -> String {
+        format!(
+            "amocas.{}{} x{}, x{}, (x{})",
+            self.width, self.ordering, self.dest, self.src, self.addr
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Zacas;
+    use crate::a::A;
+    use crate::rv32i::RV32I;
+    use crate::rv64i::RV64I;
+    use crate::test_utils::{processor_in_ram, reg};
+    use crate::zabha::Zabha;
+    use z2l_core::error::ProcessorException;
+    use z2l_core::extension::Extension;
+    use z2l_core::instruction::InstructionParts;
+    use z2l_core::processor::hart::Hart;
+    use z2l_core::processor::Processor;
+
+    fn run(processor: &mut Processor) {
+        for _ in 0..20 {
+            processor.cycle().unwrap();
+        }
+    }
+
+    #[test]
+    fn compare_and_swap() {
+        // Data follows the 8 instructions, at offset 32
+        let program = [
+            0x0000_0097, // auipc x1, 0
+            0x0200_8093, // addi x1, x1, 32
+            0x0050_0113, // addi x2, x0, 5
+            0xfff0_0193, // addi x3, x0, -1
+            0x2e20_a1af, // amocas.w.aqrl x3, x2, (x1)
+            0x0020_0213, // addi x4, x0, 2
+            0x2800_a22f, // amocas.w x4, x0, (x1)
+            0x0000_006f, // jal x0, 0
+        ];
+        let data = (-1i32).to_le_bytes();
+
+        for base in [Box::new(RV32I) as Box<dyn Extension>, Box::new(RV64I)] {
+            let extensions = vec![base, Box::new(A), Box::new(Zacas)];
+            let mut processor = processor_in_ram(extensions, &program, &data);
+            run(&mut processor);
+
+            // The first succeeds, and the second fails, leaving memory unchanged
+            assert_eq!(reg(&processor, 3), -1);
+            assert_eq!(reg(&processor, 4), 5);
+            let mut mmu = processor.mmu.lock().unwrap();
+            assert_eq!(mmu.load_word(0x8000_0020).unwrap(), 5);
+        }
+    }
+
+    #[test]
+    fn register_pairs() {
+        let program = [
+            0x0000_0097, // auipc x1, 0
+            0x0200_8093, // addi x1, x1, 32
+            0x0030_0113, // addi x2, x0, 3
+            0x0040_0193, // addi x3, x0, 4
+            0x0010_0213, // addi x4, x0, 1
+            0x0020_0293, // addi x5, x0, 2
+            0x2820_b22f, // amocas.d x4, x2, (x1)
+            0x0000_006f, // jal x0, 0
+        ];
+        let data = 0x2_0000_0001u64.to_le_bytes();
+
+        let extensions = vec![Box::new(RV32I) as Box<dyn Extension>, Box::new(Zacas)];
+        let mut processor = processor_in_ram(extensions, &program, &data);
+        run(&mut processor);
+
+        // The 64-bit value in x5:x4 matched, so x3:x2 was stored
+        assert_eq!(reg(&processor, 4), 1);
+        assert_eq!(reg(&processor, 5), 2);
+        let mut mmu = processor.mmu.lock().unwrap();
+        assert_eq!(mmu.load_doubleword(0x8000_0020).unwrap(), 0x4_0000_0003);
+    }
+
+    #[test]
+    fn decoding() {
+        let decode = |extensions: &[&dyn Extension], raw| {
+            let mut hart = Hart::new();
+            for extension in extensions {
+                extension.register(&mut hart);
+            }
+            let parts = InstructionParts::new(raw).unwrap();
+            let handler = hart.opcodes.get(&parts.opcode()).unwrap();
+            handler.decode(parts, 0, hart.xlen).map(|i| i.format())
+        };
+        let illegal = Err(ProcessorException::IllegalInstruction);
+
+        assert_eq!(
+            decode(&[&RV64I, &A, &Zacas], 0x2820_b22f),
+            Ok("amocas.d x4, x2, (x1)".to_owned())
+        );
+        // Odd registers are reserved for register pairs
+        assert_eq!(decode(&[&RV32I, &A, &Zacas], 0x2830_b22f), illegal);
+        assert_eq!(decode(&[&RV32I, &A, &Zacas], 0x2820_b2af), illegal);
+        // AMOCAS.Q isn't supported
+        assert_eq!(decode(&[&RV64I, &A, &Zacas], 0x2820_c22f), illegal);
+
+        // AMOCAS.B & AMOCAS.H require Zabha, which may be registered before or after Zacas
+        let amocas_h = 0x2e20_922f; // amocas.h.aqrl x4, x2, (x1)
+        assert_eq!(decode(&[&RV64I, &A, &Zacas], amocas_h), illegal);
+        let formatted = Ok("amocas.h.aqrl x4, x2, (x1)".to_owned());
+        assert_eq!(decode(&[&RV64I, &Zacas, &Zabha], amocas_h), formatted);
+        assert_eq!(decode(&[&RV64I, &Zabha, &Zacas], amocas_h), formatted);
+    }
+}
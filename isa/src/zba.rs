@@ -0,0 +1,247 @@
+//! The "Zba" standard extension for address generation.
+//!
+//! Zba adds instructions which accelerate the generation of addresses that index into arrays of
+//! basic types:
+//!
+//! * SH1ADD, SH2ADD, and SH3ADD shift rs1 left by 1, 2, or 3 bits, and add rs2.
+//! * On RV64, ADD.UW and SH1ADD.UW through SH3ADD.UW zero-extend the lowest 32 bits of rs1 before
+//!   shifting it, and SLLI.UW shifts the zero-extended word left by an immediate.
+//!
+//! The instructions share the OP opcode (and on RV64, OP-32 and OP-IMM-32) with the base integer
+//! instruction set, so are registered as [`SubDecoder`]s on those opcodes.
+
+use z2l_core::error::ProcessorException;
+use z2l_core::extension::{Extension, SubDecoder};
+use z2l_core::instruction::{Instruction, InstructionResult, InstructionWordParts};
+use z2l_core::processor::hart::Hart;
+use z2l_core::processor::register::RegisterFile;
+use z2l_core::processor::Xlen;
+
+/// An [`Extension`] defining the Zba standard extension.
+pub struct Zba;
+
+impl Extension for Zba {
+    fn code(&self) -> &'static str {
+        "Zba"
+    }
+
+    fn name(&self) -> &'static str {
+        "Standard Extension for Address Generation"
+    }
+
+    fn register(&self, hart: &mut Hart) {
+        for opcode in [0x1b, 0x33, 0x3b] {
+            hart.register_sub_decoder(opcode, Box::new(ZbaDecoder));
+        }
+    }
+}
+
+/// Decoder for the Zba extension's instructions, in the OP, OP-32, and OP-IMM-32 opcodes.
+pub struct ZbaDecoder;
+
+impl SubDecoder for ZbaDecoder {
+    fn decode(
+        &self,
+        instruction: &InstructionWordParts,
+        _pc: u64,
+        xlen: Xlen,
+    ) -> Result<Option<Box<dyn Instruction>>, ProcessorException> {
+        // The OP-32 and OP-IMM-32 opcodes are only defined for RV64
+        if xlen == Xlen::Rv32 && matches!(instruction.opcode, 0x1b | 0x3b) {
+            return Ok(None);
+        }
+
+        let funct6 = instruction.funct7 >> 1;
+
+        Ok(Some(
+            match (instruction.opcode, instruction.funct7, instruction.funct3) {
+                (0x33, 0b0010000, 0b010 | 0b100 | 0b110) => {
+                    Box::new(ShiftAddInstruction::new(instruction, xlen))
+                }
+                (0x3b, 0b0000100, 0b000) | (0x3b, 0b0010000, 0b010 | 0b100 | 0b110) => {
+                    Box::new(ShiftAddInstruction::new_unsigned_word(instruction))
+                }
+                (0x1b, _, 0b001) if funct6 == 0b000010 => {
+                    Box::new(SllIUwInstruction::new(instruction))
+                }
+                _ => return Ok(None),
+            },
+        ))
+    }
+}
+
+/// SH1ADD, SH2ADD, or SH3ADD instruction, or on RV64, ADD.UW or one of the `.uw` variants.
+pub struct ShiftAddInstruction {
+    src1: u8,
+    src2: u8,
+    dest: u8,
+    shift: u32,
+    unsigned_word: bool,
+    xlen: Xlen,
+}
+
+impl ShiftAddInstruction {
+    /// Create a new SH1ADD, SH2ADD, or SH3ADD instruction, for a hart with the provided XLEN.
+    pub fn new(instruction: &InstructionWordParts, xlen: Xlen) -> Self {
+        Self {
+            src1: instruction.rs1,
+            src2: instruction.rs2,
+            dest: instruction.rd,
+            // funct3 is 0b000 for ADD.UW, and 0b010, 0b100, or 0b110 for a shift of 1, 2, or 3 bits
+            shift: (instruction.funct3 >> 1) as u32,
+            unsigned_word: false,
+            xlen,
+        }
+    }
+
+    /// Create a new ADD.UW, SH1ADD.UW, SH2ADD.UW, or SH3ADD.UW instruction.
+    pub fn new_unsigned_word(instruction: &InstructionWordParts) -> Self {
+        Self {
+            unsigned_word: true,
+            ..Self::new(instruction, Xlen::Rv64)
+        }
+    }
+}
+
+impl Instruction for ShiftAddInstruction {
+    fn execute(
+        &self,
+        registers: &mut RegisterFile,
+        _mem: i64,
+    ) -> Result<InstructionResult, ProcessorException> {
+        let src1 = registers
+            .get(&self.src1)
+            .ok_or(ProcessorException::IllegalInstruction)?
+            .load()?;
+        let src2 = registers
+            .get(&self.src2)
+            .ok_or(ProcessorException::IllegalInstruction)?
+            .load()?;
+
+        let base = if self.unsigned_word {
+            src1 as u32 as i64
+        } else {
+            src1
+        };
+        let result = self
+            .xlen
+            .sign_extend((base << self.shift).wrapping_add(src2));
+
+        let dest = registers
+            .get_mut(&self.dest)
+            .ok_or(ProcessorException::IllegalInstruction)?;
+        dest.store(result)?;
+
+        Ok(InstructionResult::default())
+    }
+
+    fn format(&self) -> String {
+        let shift = match self.shift {
+            0 => String::new(),
+            shift => format!("sh{}", shift),
+        };
+        let suffix = if self.unsigned_word { ".uw" } else { "" };
+        format!(
+            "{}add{} x{}, x{}, x{}",
+            shift, suffix, self.dest, self.src1, self.src2
+        )
+    }
+}
+
+/// SLLI.UW instruction.
+pub struct SllIUwInstruction {
+    src: u8,
+    shift: u32,
+    dest: u8,
+}
+
+impl SllIUwInstruction {
+    /// Create a new SllIUwInstruction.
+    pub fn new(instruction: &InstructionWordParts) -> Self {
+        Self {
+            src: instruction.rs1,
+            shift: (instruction.imm_i & 0b111111) as u32,
+            dest: instruction.rd,
+        }
+    }
+}
+
+impl Instruction for SllIUwInstruction {
+    fn execute(
+        &self,
+        registers: &mut RegisterFile,
+        _mem: i64,
+    ) -> Result<InstructionResult, ProcessorException> {
+        let src = registers
+            .get(&self.src)
+            .ok_or(ProcessorException::IllegalInstruction)?
+            .load()?;
+
+        let result = ((src as u32 as u64) << self.shift) as i64;
+
+        let dest = registers
+            .get_mut(&self.dest)
+            .ok_or(ProcessorException::IllegalInstruction)?;
+        dest.store(result)?;
+
+        Ok(InstructionResult::default())
+    }
+
+    fn format(&self) -> String {
+        format!("slli.uw x{}, x{}, {}", self.dest, self.src, self.shift)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Zba;
+    use crate::rv32i::RV32I;
+    use crate::rv64i::RV64I;
+    use crate::test_utils::{self, decode, execute};
+
+    const SH1ADD: u32 = 0x2020_a1b3; // sh1add x3, x1, x2
+    const SH2ADD: u32 = 0x2020_c1b3; // sh2add x3, x1, x2
+    const SH3ADD: u32 = 0x2020_e1b3; // sh3add x3, x1, x2
+    const ADD_UW: u32 = 0x0820_81bb; // add.uw x3, x1, x2
+    const SH1ADD_UW: u32 = 0x2020_a1bb; // sh1add.uw x3, x1, x2
+    const SLLI_UW: u32 = 0x0840_919b; // slli.uw x3, x1, 4
+
+    #[test]
+    fn decode_alongside_base() {
+        let hart = test_utils::hart(&[&RV64I, &Zba]);
+
+        assert_eq!(decode(&hart, 0x0073_02b3), "add x5, x6, x7");
+        assert_eq!(decode(&hart, 0x4073_02b3), "sub x5, x6, x7");
+        assert_eq!(decode(&hart, 0x0073_02bb), "addw x5, x6, x7");
+        assert_eq!(decode(&hart, 0x0031_109b), "slliw x1, x2, 3");
+
+        assert_eq!(decode(&hart, SH1ADD), "sh1add x3, x1, x2");
+        assert_eq!(decode(&hart, SH3ADD), "sh3add x3, x1, x2");
+        assert_eq!(decode(&hart, ADD_UW), "add.uw x3, x1, x2");
+        assert_eq!(decode(&hart, SH1ADD_UW), "sh1add.uw x3, x1, x2");
+        assert_eq!(decode(&hart, SLLI_UW), "slli.uw x3, x1, 4");
+        assert_eq!(decode(&hart, 0x0a80_919b), "slli.uw x3, x1, 40");
+    }
+
+    #[test]
+    fn shift_add() {
+        let mut hart = test_utils::hart(&[&RV32I, &Zba]);
+        assert_eq!(execute(&mut hart, SH1ADD, 5, 100), 110);
+        assert_eq!(execute(&mut hart, SH2ADD, 5, 100), 120);
+        assert_eq!(execute(&mut hart, SH3ADD, -1, 8), 0);
+        // Results wrap, & are sign-extended
+        assert_eq!(execute(&mut hart, SH3ADD, 0x2000_0000, 1), 1);
+        assert_eq!(execute(&mut hart, SH3ADD, 0x1000_0000, 0), i32::MIN as i64);
+    }
+
+    #[test]
+    fn unsigned_word() {
+        let mut hart = test_utils::hart(&[&RV64I, &Zba]);
+        assert_eq!(execute(&mut hart, ADD_UW, -1, 1), 0x1_0000_0000);
+        assert_eq!(
+            execute(&mut hart, SH1ADD_UW, 0xffff_ffff_8000_0001u64 as i64, 0),
+            0x1_0000_0002
+        );
+        assert_eq!(execute(&mut hart, SLLI_UW, -1, 0), 0xf_ffff_fff0);
+    }
+}
@@ -0,0 +1,118 @@
+//! Logical operations with negation (ANDN, ORN, XNOR).
+//!
+//! These instructions perform a bitwise AND, OR, or XOR of rs1 with rs2, inverting rs2 (ANDN, ORN)
+//! or the result (XNOR), and store the result in rd.
+
+use std::fmt;
+use z2l_core::error::ProcessorException;
+use z2l_core::instruction::{Instruction, InstructionResult, InstructionWordParts};
+use z2l_core::processor::register::RegisterFile;
+
+/// Operation to perform.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+enum Operation {
+    Andn,
+    Orn,
+    Xnor,
+}
+
+impl fmt::Display for Operation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Operation::Andn => f.write_str("andn"),
+            Operation::Orn => f.write_str("orn"),
+            Operation::Xnor => f.write_str("xnor"),
+        }
+    }
+}
+
+/// ANDN, ORN, or XNOR instruction.
+pub struct LogicInstruction {
+    src1: u8,
+    src2: u8,
+    dest: u8,
+    op: Operation,
+}
+
+impl LogicInstruction {
+    /// Create a new LogicInstruction.
+    pub fn new(instruction: &InstructionWordParts) -> Result<Self, ProcessorException> {
+        let op = match instruction.funct3 {
+            0b111 => Operation::Andn,
+            0b110 => Operation::Orn,
+            0b100 => Operation::Xnor,
+            _ => return Err(ProcessorException::IllegalInstruction),
+        };
+
+        Ok(Self {
+            src1: instruction.rs1,
+            src2: instruction.rs2,
+            dest: instruction.rd,
+            op,
+        })
+    }
+}
+
+impl Instruction for LogicInstruction {
+    fn execute(
+        &self,
+        registers: &mut RegisterFile,
+        _mem: i64,
+    ) -> Result<InstructionResult, ProcessorException> {
+        let src1 = registers
+            .get(&self.src1)
+            .ok_or(ProcessorException::IllegalInstruction)?
+            .load()?;
+        let src2 = registers
+            .get(&self.src2)
+            .ok_or(ProcessorException::IllegalInstruction)?
+            .load()?;
+
+        // Registers hold sign-extended values on RV32, so the results are also sign-extended
+        let result = match self.op {
+            Operation::Andn => src1 & !src2,
+            Operation::Orn => src1 | !src2,
+            Operation::Xnor => !(src1 ^ src2),
+        };
+
+        let dest = registers
+            .get_mut(&self.dest)
+            .ok_or(ProcessorException::IllegalInstruction)?;
+        dest.store(result)?;
+
+        Ok(InstructionResult::default())
+    }
+
+    fn format(&self) -> String {
+        format!("{} x{}, x{}, x{}", self.op, self.dest, self.src1, self.src2)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::rv32i::RV32I;
+    use crate::rv64i::RV64I;
+    use crate::test_utils::{execute, hart};
+    use crate::zbb::Zbb;
+
+    const ANDN: u32 = 0x4020_f1b3; // andn x3, x1, x2
+    const ORN: u32 = 0x4020_e1b3; // orn x3, x1, x2
+    const XNOR: u32 = 0x4020_c1b3; // xnor x3, x1, x2
+
+    #[test]
+    fn logic() {
+        let mut rv32 = hart(&[&RV32I, &Zbb]);
+        assert_eq!(execute(&mut rv32, ANDN, 0b1100, 0b1010), 0b0100);
+        assert_eq!(execute(&mut rv32, ORN, 0, 0), -1);
+        assert_eq!(execute(&mut rv32, ORN, 0b1100, -1), 0b1100);
+        assert_eq!(execute(&mut rv32, XNOR, 5, 5), -1);
+        assert_eq!(execute(&mut rv32, XNOR, 0, -1), 0);
+
+        // Inverting a sign-extended operand keeps the result sign-extended
+        assert_eq!(execute(&mut rv32, ANDN, -1, 0x7fff_ffff), i32::MIN as i64);
+
+        let mut rv64 = hart(&[&RV64I, &Zbb]);
+        assert_eq!(execute(&mut rv64, ORN, 0, 0xffff_ffff), -0x1_0000_0000);
+        assert_eq!(execute(&mut rv64, XNOR, i64::MIN, 0), i64::MAX);
+    }
+}
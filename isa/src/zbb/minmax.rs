@@ -0,0 +1,131 @@
+//! Integer minimum and maximum (MIN, MINU, MAX, MAXU).
+//!
+//! These instructions store the smaller (MIN, MINU) or larger (MAX, MAXU) of rs1 and rs2 in rd,
+//! comparing them as signed (MIN, MAX) or unsigned (MINU, MAXU) integers.
+
+use std::fmt;
+use z2l_core::error::ProcessorException;
+use z2l_core::instruction::{Instruction, InstructionResult, InstructionWordParts};
+use z2l_core::processor::register::RegisterFile;
+use z2l_core::processor::Xlen;
+
+/// Operation to perform.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+enum Operation {
+    Min,
+    MinU,
+    Max,
+    MaxU,
+}
+
+impl fmt::Display for Operation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Operation::Min => f.write_str("min"),
+            Operation::MinU => f.write_str("minu"),
+            Operation::Max => f.write_str("max"),
+            Operation::MaxU => f.write_str("maxu"),
+        }
+    }
+}
+
+/// MIN, MINU, MAX, or MAXU instruction.
+pub struct MinMaxInstruction {
+    src1: u8,
+    src2: u8,
+    dest: u8,
+    op: Operation,
+    xlen: Xlen,
+}
+
+impl MinMaxInstruction {
+    /// Create a new MinMaxInstruction, for a hart with the provided XLEN.
+    pub fn new(instruction: &InstructionWordParts, xlen: Xlen) -> Result<Self, ProcessorException> {
+        let op = match instruction.funct3 {
+            0b100 => Operation::Min,
+            0b101 => Operation::MinU,
+            0b110 => Operation::Max,
+            0b111 => Operation::MaxU,
+            _ => return Err(ProcessorException::IllegalInstruction),
+        };
+
+        Ok(Self {
+            src1: instruction.rs1,
+            src2: instruction.rs2,
+            dest: instruction.rd,
+            op,
+            xlen,
+        })
+    }
+}
+
+impl Instruction for MinMaxInstruction {
+    fn execute(
+        &self,
+        registers: &mut RegisterFile,
+        _mem: i64,
+    ) -> Result<InstructionResult, ProcessorException> {
+        let src1 = registers
+            .get(&self.src1)
+            .ok_or(ProcessorException::IllegalInstruction)?
+            .load()?;
+        let src2 = registers
+            .get(&self.src2)
+            .ok_or(ProcessorException::IllegalInstruction)?
+            .load()?;
+
+        let (unsigned1, unsigned2) = (self.xlen.zero_extend(src1), self.xlen.zero_extend(src2));
+        let result = match self.op {
+            Operation::Min => src1.min(src2),
+            Operation::Max => src1.max(src2),
+            Operation::MinU if unsigned1 <= unsigned2 => src1,
+            Operation::MaxU if unsigned1 >= unsigned2 => src1,
+            Operation::MinU | Operation::MaxU => src2,
+        };
+
+        let dest = registers
+            .get_mut(&self.dest)
+            .ok_or(ProcessorException::IllegalInstruction)?;
+        dest.store(result)?;
+
+        Ok(InstructionResult::default())
+    }
+
+    fn format(&self) -> String {
+        format!("{} x{}, x{}, x{}", self.op, self.dest, self.src1, self.src2)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::rv32i::RV32I;
+    use crate::rv64i::RV64I;
+    use crate::test_utils::{execute, hart};
+    use crate::zbb::Zbb;
+
+    const MIN: u32 = 0x0a20_c1b3; // min x3, x1, x2
+    const MINU: u32 = 0x0a20_d1b3; // minu x3, x1, x2
+    const MAX: u32 = 0x0a20_e1b3; // max x3, x1, x2
+    const MAXU: u32 = 0x0a20_f1b3; // maxu x3, x1, x2
+
+    #[test]
+    fn min_max() {
+        let mut rv32 = hart(&[&RV32I, &Zbb]);
+        assert_eq!(execute(&mut rv32, MIN, -1, 1), -1);
+        assert_eq!(execute(&mut rv32, MINU, -1, 1), 1);
+        assert_eq!(execute(&mut rv32, MAX, -1, 1), 1);
+        assert_eq!(execute(&mut rv32, MAXU, -1, 1), -1);
+
+        // Sign-extended RV32 values compare as their unsigned 32-bit values
+        let min = i32::MIN as i64;
+        let max = i32::MAX as i64;
+        assert_eq!(execute(&mut rv32, MIN, min, max), min);
+        assert_eq!(execute(&mut rv32, MINU, min, max), max);
+        assert_eq!(execute(&mut rv32, MAXU, min, max), min);
+
+        let mut rv64 = hart(&[&RV64I, &Zbb]);
+        assert_eq!(execute(&mut rv64, MINU, -1, 1 << 40), 1 << 40);
+        assert_eq!(execute(&mut rv64, MAX, i64::MIN, 0), 0);
+        assert_eq!(execute(&mut rv64, MAXU, i64::MIN, 0), i64::MIN);
+    }
+}
@@ -0,0 +1,205 @@
+//! The "Zbb" standard extension for basic bit-manipulation.
+//!
+//! Zbb adds logical operations with negation, integer minimum/maximum, bit counting, sign- and
+//! zero-extension, bitwise rotation, and byte-wise operations. Its instructions share the OP and
+//! OP-IMM opcodes (and on RV64, OP-32 and OP-IMM-32) with the base integer instruction set, so are
+//! registered as [`SubDecoder`]s on those opcodes.
+
+mod logic;
+mod minmax;
+mod rotate;
+mod unary;
+
+pub use logic::LogicInstruction;
+pub use minmax::MinMaxInstruction;
+pub use rotate::RotateInstruction;
+pub use unary::UnaryInstruction;
+
+use z2l_core::error::ProcessorException;
+use z2l_core::extension::{Extension, SubDecoder};
+use z2l_core::instruction::{Instruction, InstructionWordParts};
+use z2l_core::processor::hart::Hart;
+use z2l_core::processor::Xlen;
+
+/// An [`Extension`] defining the Zbb standard extension.
+pub struct Zbb;
+
+impl Extension for Zbb {
+    fn code(&self) -> &'static str {
+        "Zbb"
+    }
+
+    fn name(&self) -> &'static str {
+        "Standard Extension for Basic Bit-Manipulation"
+    }
+
+    fn register(&self, hart: &mut Hart) {
+        for opcode in [0x13, 0x1b, 0x33, 0x3b] {
+            hart.register_sub_decoder(opcode, Box::new(ZbbDecoder));
+        }
+    }
+}
+
+/// Decoder for the Zbb extension's instructions, in the OP, OP-IMM, OP-32, and OP-IMM-32 opcodes.
+pub struct ZbbDecoder;
+
+impl SubDecoder for ZbbDecoder {
+    fn decode(
+        &self,
+        instruction: &InstructionWordParts,
+        _pc: u64,
+        xlen: Xlen,
+    ) -> Result<Option<Box<dyn Instruction>>, ProcessorException> {
+        // The OP-32 and OP-IMM-32 opcodes are only defined for RV64
+        if xlen == Xlen::Rv32 && matches!(instruction.opcode, 0x1b | 0x3b) {
+            return Ok(None);
+        }
+
+        let funct6 = instruction.funct7 >> 1;
+        let imm = instruction.imm_i & 0xfff;
+        let rev8 = match xlen {
+            Xlen::Rv32 => 0x698,
+            Xlen::Rv64 => 0x6b8,
+        };
+
+        Ok(Some(
+            match (instruction.opcode, instruction.funct7, instruction.funct3) {
+                // OP
+                (0x33, 0b0100000, 0b100 | 0b110 | 0b111) => {
+                    Box::new(LogicInstruction::new(instruction)?)
+                }
+                (0x33, 0b0000101, 0b100..=0b111) => {
+                    Box::new(MinMaxInstruction::new(instruction, xlen)?)
+                }
+                (0x33, 0b0110000, 0b001 | 0b101) => {
+                    Box::new(RotateInstruction::new(instruction, xlen)?)
+                }
+                (0x33, 0b0000100, 0b100) if xlen == Xlen::Rv32 && instruction.rs2 == 0 => {
+                    Box::new(UnaryInstruction::new(instruction, xlen)?)
+                }
+
+                // OP-IMM
+                (0x13, _, 0b001) if matches!(imm, 0x600..=0x602 | 0x604 | 0x605) => {
+                    Box::new(UnaryInstruction::new(instruction, xlen)?)
+                }
+                (0x13, _, 0b101) if imm == 0x287 || imm == rev8 => {
+                    Box::new(UnaryInstruction::new(instruction, xlen)?)
+                }
+                (0x13, _, 0b101) if funct6 == 0b011000 => {
+                    Box::new(RotateInstruction::new_immediate(instruction, xlen)?)
+                }
+
+                // OP-32
+                (0x3b, 0b0110000, 0b001 | 0b101) => {
+                    Box::new(RotateInstruction::new_word(instruction)?)
+                }
+                (0x3b, 0b0000100, 0b100) if instruction.rs2 == 0 => {
+                    Box::new(UnaryInstruction::new(instruction, xlen)?)
+                }
+
+                // OP-IMM-32
+                (0x1b, _, 0b001) if matches!(imm, 0x600..=0x602) => {
+                    Box::new(UnaryInstruction::new_word(instruction)?)
+                }
+                (0x1b, 0b0110000, 0b101) => {
+                    Box::new(RotateInstruction::new_immediate_word(instruction)?)
+                }
+
+                _ => return Ok(None),
+            },
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Zbb;
+    use crate::m::M;
+    use crate::rv32i::RV32I;
+    use crate::rv64i::RV64I;
+    use crate::test_utils::{self, decode};
+    use crate::zba::Zba;
+    use crate::zbc::Zbc;
+    use crate::zbs::Zbs;
+    use z2l_core::extension::Extension;
+    use z2l_core::instruction::InstructionParts;
+    use z2l_core::processor::hart::Hart;
+
+    const ANDN: u32 = 0x4020_f1b3; // andn x3, x1, x2
+    const XNOR: u32 = 0x4020_c1b3; // xnor x3, x1, x2
+    const MIN: u32 = 0x0a20_c1b3; // min x3, x1, x2
+    const MAXU: u32 = 0x0a20_f1b3; // maxu x3, x1, x2
+    const CLZ: u32 = 0x6000_9193; // clz x3, x1
+    const SEXT_H: u32 = 0x6050_9193; // sext.h x3, x1
+    const ZEXT_H_32: u32 = 0x0800_c1b3; // zext.h x3, x1 (RV32)
+    const ZEXT_H_64: u32 = 0x0800_c1bb; // zext.h x3, x1 (RV64)
+    const ORC_B: u32 = 0x2870_d193; // orc.b x3, x1
+    const REV8_32: u32 = 0x6980_d193; // rev8 x3, x1 (RV32)
+    const REV8_64: u32 = 0x6b80_d193; // rev8 x3, x1 (RV64)
+    const ROL: u32 = 0x6020_91b3; // rol x3, x1, x2
+    const RORI: u32 = 0x6080_d193; // rori x3, x1, 8
+    const CPOPW: u32 = 0x6020_919b; // cpopw x3, x1
+    const RORW: u32 = 0x6020_d1bb; // rorw x3, x1, x2
+    const RORIW: u32 = 0x6080_d19b; // roriw x3, x1, 8
+
+    #[test]
+    fn decode_rv32() {
+        let hart = test_utils::hart(&[&RV32I, &Zbb]);
+
+        assert_eq!(decode(&hart, ANDN), "andn x3, x1, x2");
+        assert_eq!(decode(&hart, XNOR), "xnor x3, x1, x2");
+        assert_eq!(decode(&hart, MAXU), "maxu x3, x1, x2");
+        assert_eq!(decode(&hart, CLZ), "clz x3, x1");
+        assert_eq!(decode(&hart, SEXT_H), "sext.h x3, x1");
+        assert_eq!(decode(&hart, ZEXT_H_32), "zext.h x3, x1");
+        assert_eq!(decode(&hart, ORC_B), "orc.b x3, x1");
+        assert_eq!(decode(&hart, REV8_32), "rev8 x3, x1");
+        assert_eq!(decode(&hart, ROL), "rol x3, x1, x2");
+        assert_eq!(decode(&hart, RORI), "rori x3, x1, 8");
+
+        // Rotate amounts must fit in XLEN
+        let parts = InstructionParts::new(0x6280_d193).unwrap();
+        assert!(hart
+            .opcodes
+            .get(&0x13)
+            .unwrap()
+            .decode(parts, 0, hart.xlen)
+            .is_err());
+    }
+
+    #[test]
+    fn decode_rv64() {
+        let hart = test_utils::hart(&[&RV64I, &Zbb]);
+
+        assert_eq!(decode(&hart, ZEXT_H_64), "zext.h x3, x1");
+        assert_eq!(decode(&hart, REV8_64), "rev8 x3, x1");
+        assert_eq!(decode(&hart, 0x6280_d193), "rori x3, x1, 40");
+        assert_eq!(decode(&hart, CPOPW), "cpopw x3, x1");
+        assert_eq!(decode(&hart, RORW), "rorw x3, x1, x2");
+        assert_eq!(decode(&hart, RORIW), "roriw x3, x1, 8");
+    }
+
+    #[test]
+    fn decode_alongside_other_extensions() {
+        let mut hart = Hart::new();
+        RV64I.register(&mut hart);
+        M.register(&mut hart);
+        Zba.register(&mut hart);
+        Zbb.register(&mut hart);
+        Zbc.register(&mut hart);
+        Zbs.register(&mut hart);
+
+        assert_eq!(decode(&hart, 0x0073_02b3), "add x5, x6, x7");
+        assert_eq!(decode(&hart, 0x4073_02b3), "sub x5, x6, x7");
+        assert_eq!(decode(&hart, 0x4073_52b3), "sra x5, x6, x7");
+        assert_eq!(decode(&hart, 0x4031_5093), "srai x1, x2, 3");
+        assert_eq!(decode(&hart, 0x0031_1093), "slli x1, x2, 3");
+        assert_eq!(decode(&hart, 0x0220_81b3), "mul x3, x1, x2");
+        assert_eq!(decode(&hart, 0x2020_c1b3), "sh2add x3, x1, x2");
+        assert_eq!(decode(&hart, 0x0840_919b), "slli.uw x3, x1, 4");
+        assert_eq!(decode(&hart, MIN), "min x3, x1, x2");
+        assert_eq!(decode(&hart, 0x0a20_91b3), "clmul x3, x1, x2");
+        assert_eq!(decode(&hart, 0x4840_d193), "bexti x3, x1, 4");
+        assert_eq!(decode(&hart, ZEXT_H_64), "zext.h x3, x1");
+    }
+}
@@ -0,0 +1,179 @@
+//! Bitwise rotation (ROL, ROR, RORI, and on RV64, ROLW, RORW, RORIW).
+//!
+//! These instructions rotate the bits of rs1 left (ROL) or right (ROR, RORI) by the value of rs2 or
+//! an immediate, storing the result in rd. Only the lowest log2(XLEN) bits of rs2 are used as the
+//! rotation amount. The word variants rotate the lowest 32 bits of rs1, sign-extending the 32-bit
+//! result. There is no ROLI, as it is equivalent to RORI by `XLEN - shamt`.
+
+use z2l_core::error::ProcessorException;
+use z2l_core::instruction::{Instruction, InstructionResult, InstructionWordParts};
+use z2l_core::processor::register::RegisterFile;
+use z2l_core::processor::Xlen;
+
+/// Source of the rotation amount.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+enum Amount {
+    Register(u8),
+    Immediate(u32),
+}
+
+/// ROL, ROR, RORI, or one of their word variants.
+pub struct RotateInstruction {
+    src: u8,
+    amount: Amount,
+    dest: u8,
+    left: bool,
+    xlen: Xlen,
+    suffix: &'static str,
+}
+
+impl RotateInstruction {
+    /// Create a new ROL or ROR instruction, for a hart with the provided XLEN.
+    pub fn new(instruction: &InstructionWordParts, xlen: Xlen) -> Result<Self, ProcessorException> {
+        let left = match instruction.funct3 {
+            0b001 => true,
+            0b101 => false,
+            _ => return Err(ProcessorException::IllegalInstruction),
+        };
+
+        Ok(Self {
+            src: instruction.rs1,
+            amount: Amount::Register(instruction.rs2),
+            dest: instruction.rd,
+            left,
+            xlen,
+            suffix: "",
+        })
+    }
+
+    /// Create a new RORI instruction, for a hart with the provided XLEN.
+    ///
+    /// Returns an error if the rotation amount is too wide for the provided XLEN.
+    pub fn new_immediate(
+        instruction: &InstructionWordParts,
+        xlen: Xlen,
+    ) -> Result<Self, ProcessorException> {
+        let amount = (instruction.imm_i & 0b111111) as u32;
+        if instruction.funct3 != 0b101 || amount & !xlen.shift_mask() != 0 {
+            return Err(ProcessorException::IllegalInstruction);
+        }
+
+        Ok(Self {
+            src: instruction.rs1,
+            amount: Amount::Immediate(amount),
+            dest: instruction.rd,
+            left: false,
+            xlen,
+            suffix: "",
+        })
+    }
+
+    /// Create a new ROLW or RORW instruction.
+    pub fn new_word(instruction: &InstructionWordParts) -> Result<Self, ProcessorException> {
+        Ok(Self {
+            suffix: "w",
+            ..Self::new(instruction, Xlen::Rv32)?
+        })
+    }
+
+    /// Create a new RORIW instruction.
+    pub fn new_immediate_word(
+        instruction: &InstructionWordParts,
+    ) -> Result<Self, ProcessorException> {
+        Ok(Self {
+            suffix: "w",
+            ..Self::new_immediate(instruction, Xlen::Rv32)?
+        })
+    }
+}
+
+impl Instruction for RotateInstruction {
+    fn execute(
+        &self,
+        registers: &mut RegisterFile,
+        _mem: i64,
+    ) -> Result<InstructionResult, ProcessorException> {
+        let src = registers
+            .get(&self.src)
+            .ok_or(ProcessorException::IllegalInstruction)?
+            .load()?;
+        let amount = match self.amount {
+            Amount::Register(reg) => {
+                registers
+                    .get(&reg)
+                    .ok_or(ProcessorException::IllegalInstruction)?
+                    .load()? as u32
+                    & self.xlen.shift_mask()
+            }
+            Amount::Immediate(amount) => amount,
+        };
+
+        let result = match (self.xlen, self.left) {
+            (Xlen::Rv32, true) => (src as u32).rotate_left(amount) as i32 as i64,
+            (Xlen::Rv32, false) => (src as u32).rotate_right(amount) as i32 as i64,
+            (Xlen::Rv64, true) => src.rotate_left(amount),
+            (Xlen::Rv64, false) => src.rotate_right(amount),
+        };
+
+        let dest = registers
+            .get_mut(&self.dest)
+            .ok_or(ProcessorException::IllegalInstruction)?;
+        dest.store(result)?;
+
+        Ok(InstructionResult::default())
+    }
+
+    fn format(&self) -> String {
+        let direction = if self.left { "l" } else { "r" };
+        match self.amount {
+            Amount::Register(reg) => format!(
+                "ro{}{} x{}, x{}, x{}",
+                direction, self.suffix, self.dest, self.src, reg
+            ),
+            Amount::Immediate(amount) => format!(
+                "ro{}i{} x{}, x{}, {}",
+                direction, self.suffix, self.dest, self.src, amount
+            ),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::rv32i::RV32I;
+    use crate::rv64i::RV64I;
+    use crate::test_utils::{execute, hart};
+    use crate::zbb::Zbb;
+
+    const ROL: u32 = 0x6020_91b3; // rol x3, x1, x2
+    const ROR: u32 = 0x6020_d1b3; // ror x3, x1, x2
+    const RORI: u32 = 0x6080_d193; // rori x3, x1, 8
+    const ROLW: u32 = 0x6020_91bb; // rolw x3, x1, x2
+    const RORW: u32 = 0x6020_d1bb; // rorw x3, x1, x2
+    const RORIW: u32 = 0x6080_d19b; // roriw x3, x1, 8
+
+    #[test]
+    fn rotate_rv32() {
+        let mut hart = hart(&[&RV32I, &Zbb]);
+        assert_eq!(execute(&mut hart, ROL, 0x8000_0001u32 as i32 as i64, 1), 3);
+        assert_eq!(execute(&mut hart, ROR, 1, 1), i32::MIN as i64);
+        assert_eq!(execute(&mut hart, ROR, 0x1234_5678, 0), 0x1234_5678);
+        // Rotate amounts are taken modulo XLEN
+        assert_eq!(execute(&mut hart, ROL, 1, 33), 2);
+        assert_eq!(execute(&mut hart, RORI, 0x1234_5678, 0), 0x7812_3456);
+    }
+
+    #[test]
+    fn rotate_rv64() {
+        let mut hart = hart(&[&RV64I, &Zbb]);
+        assert_eq!(execute(&mut hart, ROR, 1, 1), i64::MIN);
+        assert_eq!(execute(&mut hart, ROL, 1, 65), 2);
+        assert_eq!(execute(&mut hart, RORI, 0xff, 0), -0x0100_0000_0000_0000);
+
+        // The word variants rotate the lowest 32 bits, & sign-extend the result
+        assert_eq!(execute(&mut hart, ROLW, 0x4000_0000, 1), i32::MIN as i64);
+        assert_eq!(execute(&mut hart, ROLW, 1, 33), 2);
+        assert_eq!(execute(&mut hart, RORW, 1, 1), i32::MIN as i64);
+        assert_eq!(execute(&mut hart, RORIW, 0x1_1234_5678, 0), 0x7812_3456);
+    }
+}
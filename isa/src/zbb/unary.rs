@@ -0,0 +1,233 @@
+//! Single-operand bit-manipulation instructions (CLZ, CTZ, CPOP, SEXT.B, SEXT.H, ZEXT.H, ORC.B,
+//! REV8, and on RV64, CLZW, CTZW, CPOPW).
+//!
+//! These instructions operate on rs1 alone, storing the result in rd:
+//!
+//! * CLZ, CTZ, and CPOP count the leading zero bits, trailing zero bits, and set bits of rs1. The
+//!   word variants count the bits of the lowest 32 bits of rs1.
+//! * SEXT.B and SEXT.H sign-extend the lowest byte or halfword of rs1, and ZEXT.H zero-extends its
+//!   lowest halfword.
+//! * ORC.B sets each byte of the result to all ones if the corresponding byte of rs1 is non-zero,
+//!   and to zero otherwise.
+//! * REV8 reverses the order of the bytes of rs1.
+//!
+//! Most of these are encoded in the OP-IMM opcode, with the operation selected by the immediate.
+//! ZEXT.H is encoded as a PACK instruction with rs2 = `x0`, in the OP opcode on RV32, and the OP-32
+//! opcode on RV64.
+
+use std::fmt;
+use z2l_core::error::ProcessorException;
+use z2l_core::instruction::{Instruction, InstructionResult, InstructionWordParts};
+use z2l_core::processor::register::RegisterFile;
+use z2l_core::processor::Xlen;
+
+/// Operation to perform.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+enum Operation {
+    CountLeadingZeros,
+    CountTrailingZeros,
+    CountPopulation,
+    SignExtendByte,
+    SignExtendHalf,
+    ZeroExtendHalf,
+    OrCombineBytes,
+    ReverseBytes,
+}
+
+impl fmt::Display for Operation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Operation::CountLeadingZeros => f.write_str("clz"),
+            Operation::CountTrailingZeros => f.write_str("ctz"),
+            Operation::CountPopulation => f.write_str("cpop"),
+            Operation::SignExtendByte => f.write_str("sext.b"),
+            Operation::SignExtendHalf => f.write_str("sext.h"),
+            Operation::ZeroExtendHalf => f.write_str("zext.h"),
+            Operation::OrCombineBytes => f.write_str("orc.b"),
+            Operation::ReverseBytes => f.write_str("rev8"),
+        }
+    }
+}
+
+/// CLZ, CTZ, CPOP, SEXT.B, SEXT.H, ZEXT.H, ORC.B, or REV8 instruction, or one of their word
+/// variants.
+pub struct UnaryInstruction {
+    src: u8,
+    dest: u8,
+    op: Operation,
+    xlen: Xlen,
+    suffix: &'static str,
+}
+
+impl UnaryInstruction {
+    /// Create a new UnaryInstruction, for a hart with the provided XLEN.
+    pub fn new(instruction: &InstructionWordParts, xlen: Xlen) -> Result<Self, ProcessorException> {
+        let imm = instruction.imm_i & 0xfff;
+        let op = match (instruction.opcode, instruction.funct3, imm, xlen) {
+            (0x13, 0b001, 0x600, _) => Operation::CountLeadingZeros,
+            (0x13, 0b001, 0x601, _) => Operation::CountTrailingZeros,
+            (0x13, 0b001, 0x602, _) => Operation::CountPopulation,
+            (0x13, 0b001, 0x604, _) => Operation::SignExtendByte,
+            (0x13, 0b001, 0x605, _) => Operation::SignExtendHalf,
+            (0x13, 0b101, 0x287, _) => Operation::OrCombineBytes,
+            (0x13, 0b101, 0x698, Xlen::Rv32) | (0x13, 0b101, 0x6b8, Xlen::Rv64) => {
+                Operation::ReverseBytes
+            }
+            (0x33, 0b100, 0x080, Xlen::Rv32) | (0x3b, 0b100, 0x080, Xlen::Rv64) => {
+                Operation::ZeroExtendHalf
+            }
+            _ => return Err(ProcessorException::IllegalInstruction),
+        };
+
+        Ok(Self {
+            src: instruction.rs1,
+            dest: instruction.rd,
+            op,
+            xlen,
+            suffix: "",
+        })
+    }
+
+    /// Create a new CLZW, CTZW, or CPOPW instruction.
+    pub fn new_word(instruction: &InstructionWordParts) -> Result<Self, ProcessorException> {
+        let op = match (instruction.funct3, instruction.imm_i & 0xfff) {
+            (0b001, 0x600) => Operation::CountLeadingZeros,
+            (0b001, 0x601) => Operation::CountTrailingZeros,
+            (0b001, 0x602) => Operation::CountPopulation,
+            _ => return Err(ProcessorException::IllegalInstruction),
+        };
+
+        Ok(Self {
+            src: instruction.rs1,
+            dest: instruction.rd,
+            op,
+            xlen: Xlen::Rv32,
+            suffix: "w",
+        })
+    }
+}
+
+impl Instruction for UnaryInstruction {
+    fn execute(
+        &self,
+        registers: &mut RegisterFile,
+        _mem: i64,
+    ) -> Result<InstructionResult, ProcessorException> {
+        let src = registers
+            .get(&self.src)
+            .ok_or(ProcessorException::IllegalInstruction)?
+            .load()?;
+
+        let xlen = self.xlen;
+        let unsigned = xlen.zero_extend(src);
+        let result = match self.op {
+            Operation::CountLeadingZeros => (unsigned.leading_zeros() - (64 - xlen.bits())) as i64,
+            Operation::CountTrailingZeros => unsigned.trailing_zeros().min(xlen.bits()) as i64,
+            Operation::CountPopulation => unsigned.count_ones() as i64,
+            Operation::SignExtendByte => src as i8 as i64,
+            Operation::SignExtendHalf => src as i16 as i64,
+            Operation::ZeroExtendHalf => src as u16 as i64,
+            Operation::OrCombineBytes => {
+                let combined = (0..xlen.bytes())
+                    .map(|i| 0xff << (i * 8))
+                    .filter(|mask| unsigned & mask != 0)
+                    .fold(0, |result, mask| result | mask);
+                xlen.sign_extend(combined as i64)
+            }
+            Operation::ReverseBytes => {
+                let reversed = unsigned.swap_bytes() >> (64 - xlen.bits());
+                xlen.sign_extend(reversed as i64)
+            }
+        };
+
+        let dest = registers
+            .get_mut(&self.dest)
+            .ok_or(ProcessorException::IllegalInstruction)?;
+        dest.store(result)?;
+
+        Ok(InstructionResult::default())
+    }
+
+    fn format(&self) -> String {
+        format!("{}{} x{}, x{}", self.op, self.suffix, self.dest, self.src)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::rv32i::RV32I;
+    use crate::rv64i::RV64I;
+    use crate::test_utils::{execute, hart};
+    use crate::zbb::Zbb;
+
+    const CLZ: u32 = 0x6000_9193; // clz x3, x1
+    const CTZ: u32 = 0x6010_9193; // ctz x3, x1
+    const CPOP: u32 = 0x6020_9193; // cpop x3, x1
+    const SEXT_B: u32 = 0x6040_9193; // sext.b x3, x1
+    const SEXT_H: u32 = 0x6050_9193; // sext.h x3, x1
+    const ZEXT_H_32: u32 = 0x0800_c1b3; // zext.h x3, x1 (RV32)
+    const ZEXT_H_64: u32 = 0x0800_c1bb; // zext.h x3, x1 (RV64)
+    const ORC_B: u32 = 0x2870_d193; // orc.b x3, x1
+    const REV8_32: u32 = 0x6980_d193; // rev8 x3, x1 (RV32)
+    const REV8_64: u32 = 0x6b80_d193; // rev8 x3, x1 (RV64)
+    const CLZW: u32 = 0x6000_919b; // clzw x3, x1
+    const CTZW: u32 = 0x6010_919b; // ctzw x3, x1
+    const CPOPW: u32 = 0x6020_919b; // cpopw x3, x1
+
+    #[test]
+    fn unary_rv32() {
+        let mut hart = hart(&[&RV32I, &Zbb]);
+        assert_eq!(execute(&mut hart, CLZ, 0, 0), 32);
+        assert_eq!(execute(&mut hart, CLZ, 1, 0), 31);
+        assert_eq!(execute(&mut hart, CLZ, -1, 0), 0);
+        assert_eq!(execute(&mut hart, CTZ, 0, 0), 32);
+        assert_eq!(execute(&mut hart, CTZ, 8, 0), 3);
+        assert_eq!(execute(&mut hart, CTZ, i32::MIN as i64, 0), 31);
+        // The sign-extended upper bits of the register aren't counted
+        assert_eq!(execute(&mut hart, CPOP, -1, 0), 32);
+        assert_eq!(execute(&mut hart, CPOP, 0b1011, 0), 3);
+
+        assert_eq!(execute(&mut hart, SEXT_B, 0x80, 0), -128);
+        assert_eq!(execute(&mut hart, SEXT_B, 0x17f, 0), 0x7f);
+        assert_eq!(execute(&mut hart, SEXT_H, 0x1_7fff, 0), 0x7fff);
+        assert_eq!(execute(&mut hart, SEXT_H, 0x8000, 0), -0x8000);
+        assert_eq!(execute(&mut hart, ZEXT_H_32, -1, 0), 0xffff);
+
+        assert_eq!(execute(&mut hart, ORC_B, 0x00ff_0100, 0), 0x00ff_ff00);
+        assert_eq!(
+            execute(&mut hart, ORC_B, 0x0100_0000, 0),
+            0xff00_0000u32 as i32 as i64
+        );
+        assert_eq!(execute(&mut hart, REV8_32, 0x1234_5678, 0), 0x7856_3412);
+        assert_eq!(
+            execute(&mut hart, REV8_32, 0xff, 0),
+            0xff00_0000u32 as i32 as i64
+        );
+    }
+
+    #[test]
+    fn unary_rv64() {
+        let mut hart = hart(&[&RV64I, &Zbb]);
+        assert_eq!(execute(&mut hart, CLZ, 1, 0), 63);
+        assert_eq!(execute(&mut hart, CTZ, 0, 0), 64);
+        assert_eq!(execute(&mut hart, CPOP, -1, 0), 64);
+
+        // The word variants only count the lowest 32 bits
+        assert_eq!(execute(&mut hart, CLZW, 1, 0), 31);
+        assert_eq!(execute(&mut hart, CLZW, 1 << 32, 0), 32);
+        assert_eq!(execute(&mut hart, CTZW, 0, 0), 32);
+        assert_eq!(execute(&mut hart, CTZW, 1 << 32, 0), 32);
+        assert_eq!(execute(&mut hart, CPOPW, -1, 0), 32);
+
+        assert_eq!(execute(&mut hart, SEXT_B, 0xff, 0), -1);
+        assert_eq!(execute(&mut hart, ZEXT_H_64, -1, 0), 0xffff);
+        assert_eq!(
+            execute(&mut hart, ORC_B, 0x0100_0000_0000_0001, 0),
+            0xff00_0000_0000_00ffu64 as i64
+        );
+        assert_eq!(
+            execute(&mut hart, REV8_64, 0x0102_0304_0506_0708, 0),
+            0x0807_0605_0403_0201
+        );
+    }
+}
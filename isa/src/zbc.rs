@@ -0,0 +1,190 @@
+//! The "Zbc" standard extension for carry-less multiplication.
+//!
+//! Carry-less multiplication multiplies rs1 by rs2 as polynomials over GF(2): Partial products are
+//! combined with XOR rather than addition. CLMUL stores the lower XLEN bits of the 2*XLEN-bit
+//! product in rd, CLMULH stores the upper XLEN bits, and CLMULR stores bits `2*XLEN-2` to `XLEN-1`
+//! (the product of the bit-reversed operands, bit-reversed).
+//!
+//! The instructions share the OP opcode with the base integer instruction set, so are registered as
+//! a [`SubDecoder`] on that opcode.
+
+use std::fmt;
+use z2l_core::error::ProcessorException;
+use z2l_core::extension::{Extension, SubDecoder};
+use z2l_core::instruction::{Instruction, InstructionResult, InstructionWordParts};
+use z2l_core::processor::hart::Hart;
+use z2l_core::processor::register::RegisterFile;
+use z2l_core::processor::Xlen;
+
+/// An [`Extension`] defining the Zbc standard extension.
+pub struct Zbc;
+
+impl Extension for Zbc {
+    fn code(&self) -> &'static str {
+        "Zbc"
+    }
+
+    fn name(&self) -> &'static str {
+        "Standard Extension for Carry-Less Multiplication"
+    }
+
+    fn register(&self, hart: &mut Hart) {
+        hart.register_sub_decoder(0x33, Box::new(ZbcDecoder));
+    }
+}
+
+/// Decoder for the Zbc extension's instructions, in the OP opcode.
+pub struct ZbcDecoder;
+
+impl SubDecoder for ZbcDecoder {
+    fn decode(
+        &self,
+        instruction: &InstructionWordParts,
+        _pc: u64,
+        xlen: Xlen,
+    ) -> Result<Option<Box<dyn Instruction>>, ProcessorException> {
+        Ok(Some(
+            match (instruction.opcode, instruction.funct7, instruction.funct3) {
+                (0x33, 0b0000101, 0b001..=0b011) => {
+                    Box::new(ClmulInstruction::new(instruction, xlen)?)
+                }
+                _ => return Ok(None),
+            },
+        ))
+    }
+}
+
+/// Operation to perform.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+enum Operation {
+    Low,
+    High,
+    Reversed,
+}
+
+impl fmt::Display for Operation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Operation::Low => f.write_str("clmul"),
+            Operation::High => f.write_str("clmulh"),
+            Operation::Reversed => f.write_str("clmulr"),
+        }
+    }
+}
+
+/// CLMUL, CLMULH, or CLMULR instruction.
+pub struct ClmulInstruction {
+    src1: u8,
+    src2: u8,
+    dest: u8,
+    op: Operation,
+    xlen: Xlen,
+}
+
+impl ClmulInstruction {
+    /// Create a new ClmulInstruction, for a hart with the provided XLEN.
+    pub fn new(instruction: &InstructionWordParts, xlen: Xlen) -> Result<Self, ProcessorException> {
+        let op = match instruction.funct3 {
+            0b001 => Operation::Low,
+            0b011 => Operation::High,
+            0b010 => Operation::Reversed,
+            _ => return Err(ProcessorException::IllegalInstruction),
+        };
+
+        Ok(Self {
+            src1: instruction.rs1,
+            src2: instruction.rs2,
+            dest: instruction.rd,
+            op,
+            xlen,
+        })
+    }
+}
+
+impl Instruction for ClmulInstruction {
+    fn execute(
+        &self,
+        registers: &mut RegisterFile,
+        _mem: i64,
+    ) -> Result<InstructionResult, ProcessorException> {
+        let xlen = self.xlen;
+        let src1 = xlen.zero_extend(
+            registers
+                .get(&self.src1)
+                .ok_or(ProcessorException::IllegalInstruction)?
+                .load()?,
+        );
+        let src2 = xlen.zero_extend(
+            registers
+                .get(&self.src2)
+                .ok_or(ProcessorException::IllegalInstruction)?
+                .load()?,
+        );
+
+        let product = (0..xlen.bits())
+            .filter(|i| src2 >> i & 1 != 0)
+            .fold(0u128, |product, i| product ^ ((src1 as u128) << i));
+        let result = match self.op {
+            Operation::Low => product,
+            Operation::High => product >> xlen.bits(),
+            Operation::Reversed => product >> (xlen.bits() - 1),
+        };
+        let result = xlen.sign_extend(result as i64);
+
+        let dest = registers
+            .get_mut(&self.dest)
+            .ok_or(ProcessorException::IllegalInstruction)?;
+        dest.store(result)?;
+
+        Ok(InstructionResult::default())
+    }
+
+    fn format(&self) -> String {
+        format!("{} x{}, x{}, x{}", self.op, self.dest, self.src1, self.src2)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Zbc;
+    use crate::rv32i::RV32I;
+    use crate::rv64i::RV64I;
+    use crate::test_utils::{self, decode, execute};
+
+    const CLMUL: u32 = 0x0a20_91b3; // clmul x3, x1, x2
+    const CLMULR: u32 = 0x0a20_a1b3; // clmulr x3, x1, x2
+    const CLMULH: u32 = 0x0a20_b1b3; // clmulh x3, x1, x2
+
+    #[test]
+    fn rv32() {
+        let mut hart = test_utils::hart(&[&RV32I, &Zbc]);
+        assert_eq!(decode(&hart, CLMUL), "clmul x3, x1, x2");
+        assert_eq!(decode(&hart, CLMULR), "clmulr x3, x1, x2");
+        assert_eq!(decode(&hart, CLMULH), "clmulh x3, x1, x2");
+
+        assert_eq!(execute(&mut hart, CLMUL, 3, 3), 5);
+        assert_eq!(execute(&mut hart, CLMUL, 0x8000_0000, 2), 0);
+        assert_eq!(execute(&mut hart, CLMULH, 0x8000_0000, 2), 1);
+        assert_eq!(execute(&mut hart, CLMULR, 0x8000_0000, 2), 2);
+
+        // The square of all ones has every even bit set
+        assert_eq!(execute(&mut hart, CLMUL, -1, -1), 0x5555_5555);
+        assert_eq!(execute(&mut hart, CLMULH, -1, -1), 0x5555_5555);
+        assert_eq!(
+            execute(&mut hart, CLMULR, -1, -1),
+            0xaaaa_aaaau32 as i32 as i64
+        );
+    }
+
+    #[test]
+    fn rv64() {
+        let mut hart = test_utils::hart(&[&RV64I, &Zbc]);
+        assert_eq!(execute(&mut hart, CLMUL, 0x8000_0000, 2), 0x1_0000_0000);
+        assert_eq!(execute(&mut hart, CLMULH, i64::MIN, 2), 1);
+        assert_eq!(execute(&mut hart, CLMULH, -1, -1), 0x5555_5555_5555_5555);
+        assert_eq!(
+            execute(&mut hart, CLMULR, -1, -1),
+            0xaaaa_aaaa_aaaa_aaaau64 as i64
+        );
+    }
+}
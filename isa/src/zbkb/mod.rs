@@ -0,0 +1,166 @@
+//! The "Zbkb" standard extension for bit-manipulation for cryptography.
+//!
+//! Zbkb contains the rotation, negated logical, and byte-reversal instructions of the Zbb
+//! extension, along with instructions packing the halves or bytes of two registers together,
+//! reversing the bits of each byte (BREV8), and on RV32, interleaving the halves of a register
+//! (ZIP and UNZIP). Like Zbb, its instructions share opcodes with the base integer instruction set,
+//! so are registered as [`SubDecoder`]s.
+
+mod pack;
+mod permute;
+
+pub use pack::PackInstruction;
+pub use permute::PermuteInstruction;
+
+use crate::zbb::{LogicInstruction, RotateInstruction, UnaryInstruction};
+use z2l_core::error::ProcessorException;
+use z2l_core::extension::{Extension, SubDecoder};
+use z2l_core::instruction::{Instruction, InstructionWordParts};
+use z2l_core::processor::hart::Hart;
+use z2l_core::processor::Xlen;
+
+/// An [`Extension`] defining the Zbkb standard extension.
+pub struct Zbkb;
+
+impl Extension for Zbkb {
+    fn code(&self) -> &'static str {
+        "Zbkb"
+    }
+
+    fn name(&self) -> &'static str {
+        "Standard Extension for Bit-Manipulation for Cryptography"
+    }
+
+    fn register(&self, hart: &mut Hart) {
+        for opcode in [0x13, 0x1b, 0x33, 0x3b] {
+            hart.register_sub_decoder(opcode, Box::new(ZbkbDecoder));
+        }
+    }
+}
+
+/// Decoder for the Zbkb extension's instructions, in the OP, OP-IMM, OP-32, and OP-IMM-32 opcodes.
+pub struct ZbkbDecoder;
+
+impl SubDecoder for ZbkbDecoder {
+    fn decode(
+        &self,
+        instruction: &InstructionWordParts,
+        _pc: u64,
+        xlen: Xlen,
+    ) -> Result<Option<Box<dyn Instruction>>, ProcessorException> {
+        // The OP-32 and OP-IMM-32 opcodes are only defined for RV64
+        if xlen == Xlen::Rv32 && matches!(instruction.opcode, 0x1b | 0x3b) {
+            return Ok(None);
+        }
+
+        let funct6 = instruction.funct7 >> 1;
+        let imm = instruction.imm_i & 0xfff;
+        let rev8 = match xlen {
+            Xlen::Rv32 => 0x698,
+            Xlen::Rv64 => 0x6b8,
+        };
+
+        Ok(Some(
+            match (instruction.opcode, instruction.funct7, instruction.funct3) {
+                // OP
+                (0x33, 0b0100000, 0b100 | 0b110 | 0b111) => {
+                    Box::new(LogicInstruction::new(instruction)?)
+                }
+                (0x33, 0b0110000, 0b001 | 0b101) => {
+                    Box::new(RotateInstruction::new(instruction, xlen)?)
+                }
+                (0x33, 0b0000100, 0b100 | 0b111) => {
+                    Box::new(PackInstruction::new(instruction, xlen)?)
+                }
+
+                // OP-IMM
+                (0x13, _, 0b101) if imm == rev8 => {
+                    Box::new(UnaryInstruction::new(instruction, xlen)?)
+                }
+                (0x13, _, 0b101) if imm == 0x687 => {
+                    Box::new(PermuteInstruction::new(instruction, xlen)?)
+                }
+                (0x13, _, 0b001 | 0b101) if imm == 0x08f && xlen == Xlen::Rv32 => {
+                    Box::new(PermuteInstruction::new(instruction, xlen)?)
+                }
+                (0x13, _, 0b101) if funct6 == 0b011000 => {
+                    Box::new(RotateInstruction::new_immediate(instruction, xlen)?)
+                }
+
+                // OP-32
+                (0x3b, 0b0110000, 0b001 | 0b101) => {
+                    Box::new(RotateInstruction::new_word(instruction)?)
+                }
+                (0x3b, 0b0000100, 0b100) => Box::new(PackInstruction::new_word(instruction)?),
+
+                // OP-IMM-32
+                (0x1b, 0b0110000, 0b101) => {
+                    Box::new(RotateInstruction::new_immediate_word(instruction)?)
+                }
+
+                _ => return Ok(None),
+            },
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Zbkb;
+    use crate::rv32i::RV32I;
+    use crate::rv64i::RV64I;
+    use crate::test_utils::{self, decode};
+    use crate::zbb::Zbb;
+    use z2l_core::instruction::InstructionParts;
+
+    const PACK: u32 = 0x0820_c1b3; // pack x3, x1, x2
+    const PACKH: u32 = 0x0820_f1b3; // packh x3, x1, x2
+    const PACKW: u32 = 0x0820_c1bb; // packw x3, x1, x2
+    const BREV8: u32 = 0x6870_d193; // brev8 x3, x1
+    const ZIP: u32 = 0x08f0_9193; // zip x3, x1
+    const UNZIP: u32 = 0x08f0_d193; // unzip x3, x1
+
+    #[test]
+    fn decode_rv32() {
+        let hart = test_utils::hart(&[&RV32I, &Zbkb]);
+
+        assert_eq!(decode(&hart, PACK), "pack x3, x1, x2");
+        assert_eq!(decode(&hart, PACKH), "packh x3, x1, x2");
+        assert_eq!(decode(&hart, BREV8), "brev8 x3, x1");
+        assert_eq!(decode(&hart, ZIP), "zip x3, x1");
+        assert_eq!(decode(&hart, UNZIP), "unzip x3, x1");
+        assert_eq!(decode(&hart, 0x6980_d193), "rev8 x3, x1");
+        assert_eq!(decode(&hart, 0x6020_91b3), "rol x3, x1, x2");
+        assert_eq!(decode(&hart, 0x6080_d193), "rori x3, x1, 8");
+        assert_eq!(decode(&hart, 0x4020_f1b3), "andn x3, x1, x2");
+
+        // Zbb instructions not included in Zbkb are still decoded by the base instruction set
+        assert_eq!(decode(&hart, 0x0073_02b3), "add x5, x6, x7");
+        assert_eq!(decode(&hart, 0x4031_5093), "srai x1, x2, 3");
+    }
+
+    #[test]
+    fn decode_rv64() {
+        let hart = test_utils::hart(&[&RV64I, &Zbkb]);
+
+        assert_eq!(decode(&hart, PACKW), "packw x3, x1, x2");
+        assert_eq!(decode(&hart, 0x6b80_d193), "rev8 x3, x1");
+        assert_eq!(decode(&hart, 0x6020_d1bb), "rorw x3, x1, x2");
+        assert_eq!(decode(&hart, 0x6080_d19b), "roriw x3, x1, 8");
+
+        // ZIP & UNZIP are only available on RV32
+        let parts = InstructionParts::new(ZIP).unwrap();
+        let decoded = hart.opcodes.get(&0x13).unwrap().decode(parts, 0, hart.xlen);
+        assert_ne!(decoded.map(|i| i.format()).ok(), Some("zip x3, x1".into()));
+    }
+
+    #[test]
+    fn decode_alongside_zbb() {
+        let hart = test_utils::hart(&[&RV32I, &Zbb, &Zbkb]);
+
+        assert_eq!(decode(&hart, 0x6000_9193), "clz x3, x1");
+        assert_eq!(decode(&hart, 0x2870_d193), "orc.b x3, x1");
+        assert_eq!(decode(&hart, BREV8), "brev8 x3, x1");
+        assert_eq!(decode(&hart, PACKH), "packh x3, x1, x2");
+    }
+}
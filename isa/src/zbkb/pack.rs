@@ -0,0 +1,166 @@
+//! Packing instructions (PACK, PACKH, and on RV64, PACKW).
+//!
+//! PACK concatenates the lower halves of rs1 and rs2, with rs1 in the lower half of the result.
+//! PACKH does the same for the least-significant bytes of rs1 and rs2, zero-extending the 16-bit
+//! result, and PACKW for the lower 16 bits of each, sign-extending the 32-bit result.
+
+use std::fmt;
+use z2l_core::error::ProcessorException;
+use z2l_core::instruction::{Instruction, InstructionResult, InstructionWordParts};
+use z2l_core::processor::register::RegisterFile;
+use z2l_core::processor::Xlen;
+
+/// Width of the values being packed.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+enum Width {
+    Half(Xlen),
+    Byte,
+    Word,
+}
+
+impl Width {
+    /// Number of bits taken from each operand.
+    fn bits(&self) -> u32 {
+        match self {
+            Width::Half(xlen) => xlen.bits() / 2,
+            Width::Byte => 8,
+            Width::Word => 16,
+        }
+    }
+}
+
+impl fmt::Display for Width {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Width::Half(_) => f.write_str("pack"),
+            Width::Byte => f.write_str("packh"),
+            Width::Word => f.write_str("packw"),
+        }
+    }
+}
+
+/// PACK, PACKH, or PACKW instruction.
+pub struct PackInstruction {
+    src1: u8,
+    src2: u8,
+    dest: u8,
+    width: Width,
+}
+
+impl PackInstruction {
+    /// Create a new PACK or PACKH instruction, for a hart with the provided XLEN.
+    pub fn new(instruction: &InstructionWordParts, xlen: Xlen) -> Result<Self, ProcessorException> {
+        let width = match instruction.funct3 {
+            0b100 => Width::Half(xlen),
+            0b111 => Width::Byte,
+            _ => return Err(ProcessorException::IllegalInstruction),
+        };
+
+        Ok(Self {
+            src1: instruction.rs1,
+            src2: instruction.rs2,
+            dest: instruction.rd,
+            width,
+        })
+    }
+
+    /// Create a new PACKW instruction.
+    pub fn new_word(instruction: &InstructionWordParts) -> Result<Self, ProcessorException> {
+        if instruction.funct3 != 0b100 {
+            return Err(ProcessorException::IllegalInstruction);
+        }
+
+        Ok(Self {
+            src1: instruction.rs1,
+            src2: instruction.rs2,
+            dest: instruction.rd,
+            width: Width::Word,
+        })
+    }
+}
+
+impl Instruction for PackInstruction {
+    fn execute(
+        &self,
+        registers: &mut RegisterFile,
+        _mem: i64,
+    ) -> Result<InstructionResult, ProcessorException> {
+        let src1 = registers
+            .get(&self.src1)
+            .ok_or(ProcessorException::IllegalInstruction)?
+            .load()?;
+        let src2 = registers
+            .get(&self.src2)
+            .ok_or(ProcessorException::IllegalInstruction)?
+            .load()?;
+
+        let bits = self.width.bits();
+        let mask = (1u64 << bits) - 1;
+        let packed = ((src2 as u64 & mask) << bits) | (src1 as u64 & mask);
+        let result = match self.width {
+            Width::Half(xlen) => xlen.sign_extend(packed as i64),
+            Width::Byte => packed as i64,
+            Width::Word => packed as i32 as i64,
+        };
+
+        let dest = registers
+            .get_mut(&self.dest)
+            .ok_or(ProcessorException::IllegalInstruction)?;
+        dest.store(result)?;
+
+        Ok(InstructionResult::default())
+    }
+
+    fn format(&self) -> String {
+        format!(
+            "{} x{}, x{}, x{}",
+            self.width, self.dest, self.src1, self.src2
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::rv32i::RV32I;
+    use crate::rv64i::RV64I;
+    use crate::test_utils::{execute, hart};
+    use crate::zbkb::Zbkb;
+
+    const PACK: u32 = 0x0820_c1b3; // pack x3, x1, x2
+    const PACKH: u32 = 0x0820_f1b3; // packh x3, x1, x2
+    const PACKW: u32 = 0x0820_c1bb; // packw x3, x1, x2
+
+    #[test]
+    fn pack_rv32() {
+        let mut hart = hart(&[&RV32I, &Zbkb]);
+        assert_eq!(
+            execute(&mut hart, PACK, 0x1234_5678, 0x9abc_def0),
+            0xdef0_5678u32 as i32 as i64
+        );
+        assert_eq!(execute(&mut hart, PACK, -1, 0), 0xffff);
+        assert_eq!(execute(&mut hart, PACKH, 0x1234, -0x988), 0x7834);
+        assert_eq!(execute(&mut hart, PACKH, -1, -1), 0xffff);
+    }
+
+    #[test]
+    fn pack_rv64() {
+        let mut hart = hart(&[&RV64I, &Zbkb]);
+        assert_eq!(
+            execute(
+                &mut hart,
+                PACK,
+                0x1111_2222_3333_4444,
+                0x5555_6666_7777_8888
+            ),
+            0x7777_8888_3333_4444
+        );
+        assert_eq!(execute(&mut hart, PACKH, -1, -1), 0xffff);
+
+        // PACKW packs the lowest half-words, & sign-extends the result
+        assert_eq!(
+            execute(&mut hart, PACKW, 0x1234_5678, 0x9abc_def0),
+            0xdef0_5678u32 as i32 as i64
+        );
+        assert_eq!(execute(&mut hart, PACKW, -1, 0x1_0000_7fff), 0x7fff_ffff);
+    }
+}
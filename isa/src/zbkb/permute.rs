@@ -0,0 +1,143 @@
+//! Bit permutation instructions (BREV8, and on RV32, ZIP and UNZIP).
+//!
+//! BREV8 reverses the order of the bits within each byte of rs1. ZIP interleaves the bits of the
+//! lower and upper halves of rs1, placing bit `i` of the lower half at bit `2*i` of the result and
+//! bit `i` of the upper half at bit `2*i+1`, and UNZIP performs the inverse permutation.
+
+use std::fmt;
+use z2l_core::error::ProcessorException;
+use z2l_core::instruction::{Instruction, InstructionResult, InstructionWordParts};
+use z2l_core::processor::register::RegisterFile;
+use z2l_core::processor::Xlen;
+
+/// Permutation to perform.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+enum Permutation {
+    ReverseBits,
+    Zip,
+    Unzip,
+}
+
+impl fmt::Display for Permutation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Permutation::ReverseBits => f.write_str("brev8"),
+            Permutation::Zip => f.write_str("zip"),
+            Permutation::Unzip => f.write_str("unzip"),
+        }
+    }
+}
+
+/// BREV8, ZIP, or UNZIP instruction.
+pub struct PermuteInstruction {
+    src: u8,
+    dest: u8,
+    permutation: Permutation,
+    xlen: Xlen,
+}
+
+impl PermuteInstruction {
+    /// Create a new PermuteInstruction, for a hart with the provided XLEN.
+    ///
+    /// The permutation is decoded from funct3 and the immediate, and ZIP and UNZIP are only
+    /// available on RV32.
+    pub fn new(instruction: &InstructionWordParts, xlen: Xlen) -> Result<Self, ProcessorException> {
+        let permutation = match (instruction.funct3, instruction.imm_i & 0xfff, xlen) {
+            (0b101, 0x687, _) => Permutation::ReverseBits,
+            (0b001, 0x08f, Xlen::Rv32) => Permutation::Zip,
+            (0b101, 0x08f, Xlen::Rv32) => Permutation::Unzip,
+            _ => return Err(ProcessorException::IllegalInstruction),
+        };
+
+        Ok(Self {
+            src: instruction.rs1,
+            dest: instruction.rd,
+            permutation,
+            xlen,
+        })
+    }
+}
+
+impl Instruction for PermuteInstruction {
+    fn execute(
+        &self,
+        registers: &mut RegisterFile,
+        _mem: i64,
+    ) -> Result<InstructionResult, ProcessorException> {
+        let src = registers
+            .get(&self.src)
+            .ok_or(ProcessorException::IllegalInstruction)?
+            .load()?;
+
+        let half = self.xlen.bits() / 2;
+        let bit = |value: i64, index: u32| (value >> index) & 1;
+        let result = match self.permutation {
+            Permutation::ReverseBits => i64::from_le_bytes(src.to_le_bytes().map(u8::reverse_bits)),
+            Permutation::Zip => (0..half).fold(0, |result, i| {
+                result | (bit(src, i) << (2 * i)) | (bit(src, i + half) << (2 * i + 1))
+            }),
+            Permutation::Unzip => (0..half).fold(0, |result, i| {
+                result | (bit(src, 2 * i) << i) | (bit(src, 2 * i + 1) << (i + half))
+            }),
+        };
+        let result = self.xlen.sign_extend(result);
+
+        let dest = registers
+            .get_mut(&self.dest)
+            .ok_or(ProcessorException::IllegalInstruction)?;
+        dest.store(result)?;
+
+        Ok(InstructionResult::default())
+    }
+
+    fn format(&self) -> String {
+        format!("{} x{}, x{}", self.permutation, self.dest, self.src)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::rv32i::RV32I;
+    use crate::rv64i::RV64I;
+    use crate::test_utils::{execute, hart};
+    use crate::zbkb::Zbkb;
+
+    const BREV8: u32 = 0x6870_d193; // brev8 x3, x1
+    const ZIP: u32 = 0x08f0_9193; // zip x3, x1
+    const UNZIP: u32 = 0x08f0_d193; // unzip x3, x1
+
+    #[test]
+    fn permute_rv32() {
+        let mut hart = hart(&[&RV32I, &Zbkb]);
+        assert_eq!(
+            execute(&mut hart, BREV8, 0x0102_0380, 0),
+            0x8040_c001u32 as i32 as i64
+        );
+        assert_eq!(execute(&mut hart, BREV8, -1, 0), -1);
+
+        assert_eq!(execute(&mut hart, ZIP, 0x0000_ffff, 0), 0x5555_5555);
+        assert_eq!(
+            execute(&mut hart, ZIP, 0xffff_0000u32 as i32 as i64, 0),
+            0xaaaa_aaaau32 as i32 as i64
+        );
+        assert_eq!(execute(&mut hart, UNZIP, 0x5555_5555, 0), 0x0000_ffff);
+        assert_eq!(
+            execute(&mut hart, UNZIP, 0xaaaa_aaaau32 as i32 as i64, 0),
+            0xffff_0000u32 as i32 as i64
+        );
+
+        // UNZIP reverses ZIP
+        let zipped = execute(&mut hart, ZIP, 0x1234_5678, 0);
+        assert_eq!(execute(&mut hart, UNZIP, zipped, 0), 0x1234_5678);
+    }
+
+    #[test]
+    fn permute_rv64() {
+        let mut hart = hart(&[&RV64I, &Zbkb]);
+        assert_eq!(
+            execute(&mut hart, BREV8, 0x0102_0304_0506_0780, 0),
+            0x8040_c020_a060_e001u64 as i64
+        );
+        assert_eq!(execute(&mut hart, BREV8, 1 << 56, 0), i64::MIN);
+    }
+}
@@ -0,0 +1,79 @@
+//! The "Zbkc" standard extension for carry-less multiplication for cryptography.
+//!
+//! Zbkc is the subset of the Zbc extension used by cryptographic code, such as GCM and other
+//! polynomial hashes: CLMUL and CLMULH, but not CLMULR. See [`crate::zbc`] for the instructions.
+
+use crate::zbc::ClmulInstruction;
+use z2l_core::error::ProcessorException;
+use z2l_core::extension::{Extension, SubDecoder};
+use z2l_core::instruction::{Instruction, InstructionWordParts};
+use z2l_core::processor::hart::Hart;
+use z2l_core::processor::Xlen;
+
+/// An [`Extension`] defining the Zbkc standard extension.
+pub struct Zbkc;
+
+impl Extension for Zbkc {
+    fn code(&self) -> &'static str {
+        "Zbkc"
+    }
+
+    fn name(&self) -> &'static str {
+        "Standard Extension for Carry-Less Multiplication for Cryptography"
+    }
+
+    fn register(&self, hart: &mut Hart) {
+        hart.register_sub_decoder(0x33, Box::new(ZbkcDecoder));
+    }
+}
+
+/// Decoder for the Zbkc extension's instructions, in the OP opcode.
+pub struct ZbkcDecoder;
+
+impl SubDecoder for ZbkcDecoder {
+    fn decode(
+        &self,
+        instruction: &InstructionWordParts,
+        _pc: u64,
+        xlen: Xlen,
+    ) -> Result<Option<Box<dyn Instruction>>, ProcessorException> {
+        Ok(Some(
+            match (instruction.opcode, instruction.funct7, instruction.funct3) {
+                (0x33, 0b0000101, 0b001 | 0b011) => {
+                    Box::new(ClmulInstruction::new(instruction, xlen)?)
+                }
+                _ => return Ok(None),
+            },
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Zbkc;
+    use crate::rv64i::RV64I;
+    use crate::test_utils::{self, decode};
+    use z2l_core::instruction::InstructionParts;
+
+    #[test]
+    fn clmul() {
+        let mut hart = test_utils::hart(&[&RV64I, &Zbkc]);
+        let program = [
+            (0x0a20_91b3, "clmul x3, x1, x2", 0x5555_5555_5555_5555),
+            (0x0a20_b1b3, "clmulh x3, x1, x2", 0x5555_5555_5555_5555),
+        ];
+        for (raw, format, result) in program {
+            assert_eq!(decode(&hart, raw), format);
+            assert_eq!(test_utils::execute(&mut hart, raw, -1, -1), result);
+        }
+
+        // CLMULR is only part of Zbc
+        let parts = InstructionParts::new(0x0a20_a1b3).unwrap();
+        assert!(hart
+            .opcodes
+            .get(&0x33)
+            .unwrap()
+            .decode(parts, 0, hart.xlen)
+            .is_err());
+    }
+}
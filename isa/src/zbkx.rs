@@ -0,0 +1,181 @@
+//! The "Zbkx" standard extension for crossbar permutations.
+//!
+//! Zbkx adds XPERM4 and XPERM8, which treat rs1 as a lookup table of nibbles or bytes, and replace
+//! each nibble or byte of rs2 with the element of rs1 it indexes, or with zero if the index is out
+//! of range. They allow S-boxes to be implemented in constant time, without memory accesses.
+//!
+//! The instructions share the OP opcode with the base integer instruction set, so are registered as
+//! a [`SubDecoder`] on that opcode.
+
+use z2l_core::error::ProcessorException;
+use z2l_core::extension::{Extension, SubDecoder};
+use z2l_core::instruction::{Instruction, InstructionResult, InstructionWordParts};
+use z2l_core::processor::hart::Hart;
+use z2l_core::processor::register::RegisterFile;
+use z2l_core::processor::Xlen;
+
+/// An [`Extension`] defining the Zbkx standard extension.
+pub struct Zbkx;
+
+impl Extension for Zbkx {
+    fn code(&self) -> &'static str {
+        "Zbkx"
+    }
+
+    fn name(&self) -> &'static str {
+        "Standard Extension for Crossbar Permutations"
+    }
+
+    fn register(&self, hart: &mut Hart) {
+        hart.register_sub_decoder(0x33, Box::new(ZbkxDecoder));
+    }
+}
+
+/// Decoder for the Zbkx extension's instructions, in the OP opcode.
+pub struct ZbkxDecoder;
+
+impl SubDecoder for ZbkxDecoder {
+    fn decode(
+        &self,
+        instruction: &InstructionWordParts,
+        _pc: u64,
+        xlen: Xlen,
+    ) -> Result<Option<Box<dyn Instruction>>, ProcessorException> {
+        Ok(Some(
+            match (instruction.opcode, instruction.funct7, instruction.funct3) {
+                (0x33, 0b0010100, 0b010 | 0b100) => {
+                    Box::new(XpermInstruction::new(instruction, xlen)?)
+                }
+                _ => return Ok(None),
+            },
+        ))
+    }
+}
+
+/// XPERM4 or XPERM8 instruction.
+pub struct XpermInstruction {
+    src1: u8,
+    src2: u8,
+    dest: u8,
+    /// Width of each element, in bits.
+    width: u32,
+    xlen: Xlen,
+}
+
+impl XpermInstruction {
+    /// Create a new XpermInstruction, for a hart with the provided XLEN.
+    pub fn new(instruction: &InstructionWordParts, xlen: Xlen) -> Result<Self, ProcessorException> {
+        let width = match instruction.funct3 {
+            0b010 => 4,
+            0b100 => 8,
+            _ => return Err(ProcessorException::IllegalInstruction),
+        };
+
+        Ok(Self {
+            src1: instruction.rs1,
+            src2: instruction.rs2,
+            dest: instruction.rd,
+            width,
+            xlen,
+        })
+    }
+}
+
+impl Instruction for XpermInstruction {
+    fn execute(
+        &self,
+        registers: &mut RegisterFile,
+        _mem: i64,
+    ) -> Result<InstructionResult, ProcessorException> {
+        let xlen = self.xlen;
+        let src1 = xlen.zero_extend(
+            registers
+                .get(&self.src1)
+                .ok_or(ProcessorException::IllegalInstruction)?
+                .load()?,
+        );
+        let src2 = xlen.zero_extend(
+            registers
+                .get(&self.src2)
+                .ok_or(ProcessorException::IllegalInstruction)?
+                .load()?,
+        );
+
+        let width = self.width;
+        let mask = (1 << width) - 1;
+        let elements = xlen.bits() / width;
+        let result = (0..elements).fold(0, |result, i| {
+            let index = (src2 >> (i * width)) & mask;
+            let element = if index < elements as u64 {
+                (src1 >> (index as u32 * width)) & mask
+            } else {
+                0
+            };
+            result | (element << (i * width))
+        });
+        let result = xlen.sign_extend(result as i64);
+
+        let dest = registers
+            .get_mut(&self.dest)
+            .ok_or(ProcessorException::IllegalInstruction)?;
+        dest.store(result)?;
+
+        Ok(InstructionResult::default())
+    }
+
+    fn format(&self) -> String {
+        format!(
+            "xperm{} x{}, x{}, x{}",
+            self.width, self.dest, self.src1, self.src2
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Zbkx;
+    use crate::rv32i::RV32I;
+    use crate::rv64i::RV64I;
+    use crate::test_utils::{self, decode, execute32, execute64};
+
+    const XPERM4: u32 = 0x2820_a1b3; // xperm4 x3, x1, x2
+    const XPERM8: u32 = 0x2820_c1b3; // xperm8 x3, x1, x2
+
+    #[test]
+    fn rv32() {
+        let mut hart = test_utils::hart(&[&RV32I, &Zbkx]);
+        assert_eq!(decode(&hart, XPERM4), "xperm4 x3, x1, x2");
+        assert_eq!(decode(&hart, XPERM8), "xperm8 x3, x1, x2");
+        assert_eq!(
+            execute32(&mut hart, XPERM8, 0x4433_2211, 0x0004_0102),
+            0x1100_2233
+        );
+        assert_eq!(
+            execute32(&mut hart, XPERM4, 0x7654_3210, 0x0123_4567),
+            0x0123_4567
+        );
+        // Out of range indices select zero
+        assert_eq!(
+            execute32(&mut hart, XPERM4, 0xfedc_ba98, 0x0000_0008),
+            0x8888_8880
+        );
+    }
+
+    #[test]
+    fn rv64() {
+        let mut hart = test_utils::hart(&[&RV64I, &Zbkx]);
+        assert_eq!(
+            execute64(&mut hart, XPERM8, 0x8877_6655_4433_2211, 0x0007_0008),
+            0x1111_1111_1188_1100
+        );
+        assert_eq!(
+            execute64(
+                &mut hart,
+                XPERM4,
+                0xfedc_ba98_7654_3210,
+                0x0000_0000_0000_00f1
+            ),
+            0x0000_0000_0000_00f1
+        );
+    }
+}
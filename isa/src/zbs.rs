@@ -0,0 +1,273 @@
+//! The "Zbs" standard extension for single-bit instructions.
+//!
+//! Zbs adds instructions which clear (BCLR), extract (BEXT), invert (BINV), or set (BSET) a single
+//! bit of rs1, storing the result in rd. The bit is selected by the lowest log2(XLEN) bits of rs2,
+//! or by an immediate (BCLRI, BEXTI, BINVI, BSETI).
+//!
+//! The instructions share the OP and OP-IMM opcodes with the base integer instruction set, so are
+//! registered as [`SubDecoder`]s on those opcodes.
+
+use std::fmt;
+use z2l_core::error::ProcessorException;
+use z2l_core::extension::{Extension, SubDecoder};
+use z2l_core::instruction::{Instruction, InstructionResult, InstructionWordParts};
+use z2l_core::processor::hart::Hart;
+use z2l_core::processor::register::RegisterFile;
+use z2l_core::processor::Xlen;
+
+/// An [`Extension`] defining the Zbs standard extension.
+pub struct Zbs;
+
+impl Extension for Zbs {
+    fn code(&self) -> &'static str {
+        "Zbs"
+    }
+
+    fn name(&self) -> &'static str {
+        "Standard Extension for Single-Bit Instructions"
+    }
+
+    fn register(&self, hart: &mut Hart) {
+        for opcode in [0x13, 0x33] {
+            hart.register_sub_decoder(opcode, Box::new(ZbsDecoder));
+        }
+    }
+}
+
+/// Decoder for the Zbs extension's instructions, in the OP and OP-IMM opcodes.
+pub struct ZbsDecoder;
+
+impl SubDecoder for ZbsDecoder {
+    fn decode(
+        &self,
+        instruction: &InstructionWordParts,
+        _pc: u64,
+        xlen: Xlen,
+    ) -> Result<Option<Box<dyn Instruction>>, ProcessorException> {
+        let funct6 = instruction.funct7 >> 1;
+
+        Ok(Some(
+            match (instruction.opcode, instruction.funct7, instruction.funct3) {
+                (0x33, 0b0100100 | 0b0110100 | 0b0010100, 0b001) | (0x33, 0b0100100, 0b101) => {
+                    Box::new(BitInstruction::new(instruction, xlen)?)
+                }
+                (0x13, _, 0b001) if matches!(funct6, 0b010010 | 0b011010 | 0b001010) => {
+                    Box::new(BitInstruction::new_immediate(instruction, xlen)?)
+                }
+                (0x13, _, 0b101) if funct6 == 0b010010 => {
+                    Box::new(BitInstruction::new_immediate(instruction, xlen)?)
+                }
+                _ => return Ok(None),
+            },
+        ))
+    }
+}
+
+/// Operation to perform.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+enum Operation {
+    Clear,
+    Extract,
+    Invert,
+    Set,
+}
+
+impl Operation {
+    /// Decode the operation from the upper 6 bits of funct7 (the bits above the shift amount of an
+    /// immediate) and funct3.
+    fn new(funct6: u8, funct3: u8) -> Result<Self, ProcessorException> {
+        match (funct6, funct3) {
+            (0b010010, 0b001) => Ok(Operation::Clear),
+            (0b010010, 0b101) => Ok(Operation::Extract),
+            (0b011010, 0b001) => Ok(Operation::Invert),
+            (0b001010, 0b001) => Ok(Operation::Set),
+            _ => Err(ProcessorException::IllegalInstruction),
+        }
+    }
+}
+
+impl fmt::Display for Operation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Operation::Clear => f.write_str("bclr"),
+            Operation::Extract => f.write_str("bext"),
+            Operation::Invert => f.write_str("binv"),
+            Operation::Set => f.write_str("bset"),
+        }
+    }
+}
+
+/// Source of the bit index.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+enum Index {
+    Register(u8),
+    Immediate(u32),
+}
+
+/// BCLR, BEXT, BINV, or BSET instruction, or one of their immediate variants.
+pub struct BitInstruction {
+    src: u8,
+    index: Index,
+    dest: u8,
+    op: Operation,
+    xlen: Xlen,
+}
+
+impl BitInstruction {
+    /// Create a new BCLR, BEXT, BINV, or BSET instruction, for a hart with the provided XLEN.
+    pub fn new(instruction: &InstructionWordParts, xlen: Xlen) -> Result<Self, ProcessorException> {
+        if instruction.funct7 & 1 != 0 {
+            return Err(ProcessorException::IllegalInstruction);
+        }
+
+        Ok(Self {
+            src: instruction.rs1,
+            index: Index::Register(instruction.rs2),
+            dest: instruction.rd,
+            op: Operation::new(instruction.funct7 >> 1, instruction.funct3)?,
+            xlen,
+        })
+    }
+
+    /// Create a new BCLRI, BEXTI, BINVI, or BSETI instruction, for a hart with the provided XLEN.
+    ///
+    /// Returns an error if the bit index is too wide for the provided XLEN.
+    pub fn new_immediate(
+        instruction: &InstructionWordParts,
+        xlen: Xlen,
+    ) -> Result<Self, ProcessorException> {
+        let index = (instruction.imm_i & 0b111111) as u32;
+        if index & !xlen.shift_mask() != 0 {
+            return Err(ProcessorException::IllegalInstruction);
+        }
+
+        Ok(Self {
+            src: instruction.rs1,
+            index: Index::Immediate(index),
+            dest: instruction.rd,
+            op: Operation::new(instruction.funct7 >> 1, instruction.funct3)?,
+            xlen,
+        })
+    }
+}
+
+impl Instruction for BitInstruction {
+    fn execute(
+        &self,
+        registers: &mut RegisterFile,
+        _mem: i64,
+    ) -> Result<InstructionResult, ProcessorException> {
+        let src = registers
+            .get(&self.src)
+            .ok_or(ProcessorException::IllegalInstruction)?
+            .load()?;
+        let index = match self.index {
+            Index::Register(reg) => {
+                registers
+                    .get(&reg)
+                    .ok_or(ProcessorException::IllegalInstruction)?
+                    .load()? as u32
+                    & self.xlen.shift_mask()
+            }
+            Index::Immediate(index) => index,
+        };
+
+        let bit = 1i64 << index;
+        let result = match self.op {
+            Operation::Clear => src & !bit,
+            Operation::Extract => (src >> index) & 1,
+            Operation::Invert => src ^ bit,
+            Operation::Set => src | bit,
+        };
+        let result = self.xlen.sign_extend(result);
+
+        let dest = registers
+            .get_mut(&self.dest)
+            .ok_or(ProcessorException::IllegalInstruction)?;
+        dest.store(result)?;
+
+        Ok(InstructionResult::default())
+    }
+
+    fn format(&self) -> String {
+        match self.index {
+            Index::Register(reg) => {
+                format!("{} x{}, x{}, x{}", self.op, self.dest, self.src, reg)
+            }
+            Index::Immediate(index) => {
+                format!("{}i x{}, x{}, {}", self.op, self.dest, self.src, index)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Zbs;
+    use crate::rv32i::RV32I;
+    use crate::rv64i::RV64I;
+    use crate::test_utils::{self, decode, execute};
+    use z2l_core::instruction::InstructionParts;
+
+    const BCLR: u32 = 0x4820_91b3; // bclr x3, x1, x2
+    const BEXT: u32 = 0x4820_d1b3; // bext x3, x1, x2
+    const BINV: u32 = 0x6820_91b3; // binv x3, x1, x2
+    const BSET: u32 = 0x2820_91b3; // bset x3, x1, x2
+    const BCLRI: u32 = 0x4840_9193; // bclri x3, x1, 4
+    const BEXTI: u32 = 0x4840_d193; // bexti x3, x1, 4
+    const BINVI: u32 = 0x69f0_9193; // binvi x3, x1, 31
+    const BSETI: u32 = 0x2a80_9193; // bseti x3, x1, 40
+
+    #[test]
+    fn decode_alongside_base() {
+        let hart = test_utils::hart(&[&RV32I, &Zbs]);
+
+        assert_eq!(decode(&hart, 0x4073_52b3), "sra x5, x6, x7");
+        assert_eq!(decode(&hart, 0x4031_5093), "srai x1, x2, 3");
+        assert_eq!(decode(&hart, 0x0031_1093), "slli x1, x2, 3");
+
+        assert_eq!(decode(&hart, BCLR), "bclr x3, x1, x2");
+        assert_eq!(decode(&hart, BEXT), "bext x3, x1, x2");
+        assert_eq!(decode(&hart, BINV), "binv x3, x1, x2");
+        assert_eq!(decode(&hart, BSET), "bset x3, x1, x2");
+        assert_eq!(decode(&hart, BCLRI), "bclri x3, x1, 4");
+        assert_eq!(decode(&hart, BEXTI), "bexti x3, x1, 4");
+        assert_eq!(decode(&hart, BINVI), "binvi x3, x1, 31");
+
+        // Bit indices must fit in XLEN
+        let parts = InstructionParts::new(BSETI).unwrap();
+        assert!(hart
+            .opcodes
+            .get(&0x13)
+            .unwrap()
+            .decode(parts, 0, hart.xlen)
+            .is_err());
+    }
+
+    #[test]
+    fn rv32() {
+        let mut hart = test_utils::hart(&[&RV32I, &Zbs]);
+        assert_eq!(execute(&mut hart, BSET, 0, 31), i32::MIN as i64);
+        assert_eq!(execute(&mut hart, BCLR, -1, 0), -2);
+        assert_eq!(execute(&mut hart, BINV, 5, 0), 4);
+        assert_eq!(execute(&mut hart, BEXT, 0x10, 4), 1);
+        assert_eq!(execute(&mut hart, BEXT, 0x10, 3), 0);
+        // Bit indices are taken modulo XLEN
+        assert_eq!(execute(&mut hart, BSET, 0, 33), 2);
+
+        assert_eq!(execute(&mut hart, BCLRI, 0x1f, 0), 0x0f);
+        assert_eq!(execute(&mut hart, BEXTI, 0x10, 0), 1);
+        assert_eq!(execute(&mut hart, BINVI, 0, 0), i32::MIN as i64);
+    }
+
+    #[test]
+    fn rv64() {
+        let mut hart = test_utils::hart(&[&RV64I, &Zbs]);
+        assert_eq!(decode(&hart, BSETI), "bseti x3, x1, 40");
+
+        assert_eq!(execute(&mut hart, BSETI, 0, 0), 1 << 40);
+        assert_eq!(execute(&mut hart, BSET, 0, 31), 0x8000_0000);
+        assert_eq!(execute(&mut hart, BEXT, -1, 63), 1);
+        assert_eq!(execute(&mut hart, BCLR, -1, 63), i64::MAX);
+    }
+}
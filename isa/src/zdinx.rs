@@ -0,0 +1,131 @@
+//! The "Zdinx" standard extension for double-precision floating-point in integer registers.
+//!
+//! Zdinx provides the double-precision instructions of the D extension, operating on the integer
+//! registers like [`Zfinx`](crate::zfinx::Zfinx), which it implies. On RV64, each double-precision
+//! value is held in a single register. On RV32, values are held in an even/odd register pair: The
+//! even register holds the lower 32 bits, naming an odd register raises an IllegalInstruction
+//! exception, and the pair starting at `x0` reads as zero and ignores writes.
+//!
+//! As with Zfinx, FLD, FSD, FMV.X.D, and FMV.D.X are not provided.
+
+use crate::f::softfloat::Format;
+use crate::f::Operands;
+use z2l_core::extension::Extension;
+use z2l_core::processor::hart::Hart;
+
+/// An [`Extension`] defining the Zdinx standard extension.
+pub struct Zdinx;
+
+impl Extension for Zdinx {
+    fn code(&self) -> &'static str {
+        "Zdinx"
+    }
+
+    fn name(&self) -> &'static str {
+        "Standard Extension for Double-Precision Floating-Point in Integer Registers"
+    }
+
+    fn register(&self, hart: &mut Hart) {
+        crate::f::register(hart, Format::Double, Operands::Integer);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Zdinx;
+    use crate::rv32i::RV32I;
+    use crate::rv64i::RV64I;
+    use crate::test_utils::{self, reg};
+    use crate::zfinx::Zfinx;
+    use crate::zicsr::Zicsr;
+    use z2l_core::error::ProcessorException;
+    use z2l_core::extension::Extension;
+    use z2l_core::processor::Processor;
+
+    /// Create a processor with the provided base instruction set, Zdinx, Zfinx, and Zicsr, with a
+    /// ROM at 0 containing the provided program, and RAM at 0x80000000.
+    fn processor(base: Box<dyn Extension>, program: &[u32]) -> Processor {
+        let extensions = vec![base, Box::new(Zdinx), Box::new(Zfinx), Box::new(Zicsr)];
+        test_utils::processor(extensions, program)
+    }
+
+    /// Run the provided program until its last instruction executes, returning the processor.
+    fn run(base: Box<dyn Extension>, program: &[u32]) -> Processor {
+        let mut processor = processor(base, program);
+        for _ in 0..=program.len() {
+            processor.cycle().unwrap();
+        }
+        processor
+    }
+
+    #[test]
+    fn register_pairs() {
+        let processor = run(
+            Box::new(RV32I),
+            &[
+                0x0010_0093, // addi x1, x0, 1
+                0x3ff8_01b7, // lui x3, 0x3ff80
+                0x0221_7253, // fadd.d x4, x2, x2
+                0x0220_7353, // fadd.d x6, x0, x2
+                0x0221_7053, // fadd.d x0, x2, x2
+                0xc201_7453, // fcvt.w.d x8, x2
+                0x4011_74d3, // fcvt.s.d x9, x2
+                0x2221_7543, // fmadd.d x10, x2, x2, x4
+                0xa241_02d3, // fle.d x5, x2, x4
+                0xd200_f653, // fcvt.d.w x12, x1
+            ],
+        );
+
+        // x2/x3 = 1.5, so x4/x5 = 3.0
+        assert_eq!(reg(&processor, 4), 0);
+        // x5 is overwritten by fle.d, as 1.5 <= 3.0
+        assert_eq!(reg(&processor, 5), 1);
+        // The pair starting at x0 reads as zero, & ignores writes
+        assert_eq!(reg(&processor, 6), 0);
+        assert_eq!(reg(&processor, 7), 0x3ff8_0000);
+        assert_eq!(reg(&processor, 1), 1);
+        // 1.5 rounds to 2
+        assert_eq!(reg(&processor, 8), 2);
+        assert_eq!(reg(&processor, 9), 0x3fc0_0000);
+        // 1.5 * 1.5 + 3.0 = 5.25
+        assert_eq!(reg(&processor, 10), 0);
+        assert_eq!(reg(&processor, 11), 0x4015_0000);
+        assert_eq!(reg(&processor, 12), 0);
+        assert_eq!(reg(&processor, 13), 0x3ff0_0000);
+    }
+
+    #[test]
+    fn odd_registers() {
+        let illegal = [
+            0x0221_70d3, // fadd.d x1, x2, x2
+            0x0221_f253, // fadd.d x4, x3, x2
+        ];
+        for raw in illegal {
+            let mut processor = processor(Box::new(RV32I), &[raw]);
+            processor.cycle().unwrap();
+            assert_eq!(
+                processor.cycle(),
+                Err((ProcessorException::IllegalInstruction, 0)),
+                "{:08x}",
+                raw
+            );
+        }
+    }
+
+    #[test]
+    fn rv64() {
+        let processor = run(
+            Box::new(RV64I),
+            &[
+                0x3ff8_01b7, // lui x3, 0x3ff80
+                0x0201_9193, // slli x3, x3, 32
+                0x0221_f253, // fadd.d x4, x3, x2
+                0x0221_70d3, // fadd.d x1, x2, x2
+            ],
+        );
+
+        // Each register holds a whole value, so odd registers can be used
+        assert_eq!(reg(&processor, 4), 1.5f64.to_bits() as i64);
+        assert_eq!(reg(&processor, 1), 0);
+    }
+}
@@ -0,0 +1,121 @@
+//! The "Zfinx" standard extension for single-precision floating-point in integer registers.
+//!
+//! Zfinx provides the single-precision instructions of the F extension, but operating on the
+//! integer registers rather than a separate bank of floating-point registers. Single-precision
+//! values are sign-extended rather than NaN-boxed, and as integer registers can already be loaded,
+//! stored & moved, the FLW, FSW, FMV.X.W & FMV.W.X instructions are not provided. `fcsr` behaves
+//! exactly as it does with the F extension.
+//!
+//! Zfinx and F are mutually exclusive. See [`crate::f::RegisterBank`] for how values are stored in
+//! the integer registers.
+
+use crate::f::softfloat::Format;
+use crate::f::{Operands, OP_FP};
+use z2l_core::extension::Extension;
+use z2l_core::processor::hart::Hart;
+
+/// An [`Extension`] defining the Zfinx standard extension.
+pub struct Zfinx;
+
+impl Extension for Zfinx {
+    fn code(&self) -> &'static str {
+        "Zfinx"
+    }
+
+    fn name(&self) -> &'static str {
+        "Standard Extension for Single-Precision Floating-Point in Integer Registers"
+    }
+
+    fn register(&self, hart: &mut Hart) {
+        // The Zdinx extension also registers the Zfinx extension's instructions, which must not be
+        // replaced by the single-precision-only handlers
+        if hart.opcodes.contains_key(&OP_FP) {
+            return;
+        }
+        crate::f::register(hart, Format::Single, Operands::Integer);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Zfinx;
+    use crate::rv32i::RV32I;
+    use crate::rv64i::RV64I;
+    use crate::test_utils::{self, reg};
+    use crate::zicsr::Zicsr;
+    use z2l_core::error::ProcessorException;
+    use z2l_core::extension::Extension;
+    use z2l_core::processor::Processor;
+
+    /// Create a processor with the provided base instruction set, Zfinx, and Zicsr, with a ROM at 0
+    /// containing the provided program, and RAM at 0x80000000.
+    fn processor(base: Box<dyn Extension>, program: &[u32]) -> Processor {
+        test_utils::processor(vec![base, Box::new(Zfinx), Box::new(Zicsr)], program)
+    }
+
+    /// Run the provided program until its last instruction executes, returning the processor.
+    fn run(base: Box<dyn Extension>, program: &[u32]) -> Processor {
+        let mut processor = processor(base, program);
+        for _ in 0..=program.len() {
+            processor.cycle().unwrap();
+        }
+        processor
+    }
+
+    #[test]
+    fn registers() {
+        let processor = processor(Box::new(RV32I), &[]);
+        let registers = processor.hart.registers.keys().copied().collect::<Vec<_>>();
+        assert!(registers.iter().all(|&n| n < 32 || n == 64));
+
+        // Zfinx is not the F extension, so is not reported in misa
+        let misa = processor.hart.csrs.get(0x301).unwrap().value;
+        assert_eq!(misa & (1 << 5), 0);
+
+        // No FLW/FSW, as values are loaded & stored with the integer instructions
+        assert!(!processor.hart.opcodes.contains_key(&0x07));
+        assert!(!processor.hart.opcodes.contains_key(&0x27));
+    }
+
+    #[test]
+    fn arithmetic() {
+        let program = [
+            0x3f80_00b7, // lui x1, 0x3f800
+            0x4000_0137, // lui x2, 0x40000
+            0x0020_f1d3, // fadd.s x3, x1, x2
+            0x1830_8253, // fdiv.s x4, x1, x3, rne
+            0x0820_f2d3, // fsub.s x5, x1, x2
+            0x0010_2373, // csrrs x6, fflags, x0
+        ];
+
+        for base in [Box::new(RV32I) as Box<dyn Extension>, Box::new(RV64I)] {
+            let processor = run(base, &program);
+            // 1.0 + 2.0 = 3.0
+            assert_eq!(reg(&processor, 3), 0x4040_0000);
+            // 1.0 / 3.0 is inexact
+            assert_eq!(reg(&processor, 4), 0x3eaa_aaab);
+            assert_eq!(reg(&processor, 6), 0b00001);
+            // 1.0 - 2.0 = -1.0, sign-extended
+            assert_eq!(reg(&processor, 5), 0xbf80_0000u32 as i32 as i64);
+        }
+    }
+
+    #[test]
+    fn illegal_instructions() {
+        let illegal = [
+            0xe001_00d3, // fmv.x.w x1, x2
+            0xf001_00d3, // fmv.w.x x1, x2
+            0x0221_70d3, // fadd.d x1, x2, x2
+        ];
+        for raw in illegal {
+            let mut processor = processor(Box::new(RV32I), &[raw]);
+            processor.cycle().unwrap();
+            assert_eq!(
+                processor.cycle(),
+                Err((ProcessorException::IllegalInstruction, 0)),
+                "{:08x}",
+                raw
+            );
+        }
+    }
+}
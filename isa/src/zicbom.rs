@@ -0,0 +1,285 @@
+//! The "Zicbom" standard extension for cache-block management instructions.
+//!
+//! Zicbom adds CBO.CLEAN, CBO.FLUSH and CBO.INVAL, which operate on the cache block containing the
+//! address in rs1. Our harts have no caches, so these instructions have no effect beyond checking
+//! they are permitted: Machine mode may always execute them, but supervisor & user mode may only do
+//! so if they are enabled by the `CBCFE` bit and `CBIE` field of the `menvcfg` CSR (and for user
+//! mode, `senvcfg`). Otherwise, they raise an IllegalInstruction exception. This extension
+//! registers `menvcfg` (and on RV32, `menvcfgh`) & `senvcfg` if no other extension has done so
+//! already.
+//!
+//! The cache-block size is a parameter of the extension, although it is only observable through
+//! the Zicboz extension's CBO.ZERO instruction.
+//!
+//! The instructions share the MISC-MEM opcode with FENCE, so are registered as a [`SubDecoder`] on
+//! it.
+
+use z2l_core::error::ProcessorException;
+use z2l_core::extension::{Extension, SubDecoder};
+use z2l_core::instruction::{
+    CacheBlockOperation, Instruction, InstructionResult, InstructionWordParts,
+};
+use z2l_core::mmu::{MemoryAccessType, StoreSpec};
+use z2l_core::processor::csr::{Csr, ENVCFG_CBCFE, ENVCFG_CBIE, MENVCFG, MENVCFGH, SENVCFG};
+use z2l_core::processor::hart::Hart;
+use z2l_core::processor::register::RegisterFile;
+use z2l_core::processor::Xlen;
+
+/// An [`Extension`] defining the Zicbom standard extension.
+pub struct Zicbom {
+    block_size: usize,
+}
+
+impl Zicbom {
+    /// Create a new Zicbom extension, with cache blocks of `block_size` bytes.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `block_size` is not a power of 2 between 8 and 4096.
+    pub fn new(block_size: usize) -> Self {
+        Self {
+            block_size: check_block_size(block_size),
+        }
+    }
+}
+
+impl Extension for Zicbom {
+    fn code(&self) -> &'static str {
+        "Zicbom"
+    }
+
+    fn name(&self) -> &'static str {
+        "Standard Extension for Cache-Block Management Instructions"
+    }
+
+    fn register(&self, hart: &mut Hart) {
+        register_envcfg(hart, ENVCFG_CBIE | ENVCFG_CBCFE);
+        let decoder = CboDecoder::new(self.block_size, false);
+        hart.register_sub_decoder(0x0f, Box::new(decoder));
+    }
+}
+
+/// Check the provided cache-block size is supported, returning it if so.
+pub(crate) fn check_block_size(block_size: usize) -> usize {
+    assert!(
+        block_size.is_power_of_two() && (8..=4096).contains(&block_size),
+        "Cache-block size must be a power of 2 between 8 and 4096"
+    );
+    block_size
+}
+
+/// Register `menvcfg` (and on RV32, `menvcfgh`) & `senvcfg`, if they are not already registered,
+/// and allow the bits in `mask` to be written in both.
+pub(crate) fn register_envcfg(hart: &mut Hart, mask: u64) {
+    if hart.csrs.get(MENVCFG).is_none() {
+        hart.csrs.register(MENVCFG, Csr::new(0).with_write_mask(0));
+        if hart.xlen == Xlen::Rv32 {
+            hart.csrs
+                .register(MENVCFGH, Csr::new(0).with_write_mask(0).rv32_only());
+        }
+    }
+    if hart.csrs.get(SENVCFG).is_none() {
+        hart.csrs.register(SENVCFG, Csr::new(0).with_write_mask(0));
+    }
+    hart.csrs.get_mut(MENVCFG).unwrap().write_mask |= mask;
+    hart.csrs.get_mut(SENVCFG).unwrap().write_mask |= mask;
+}
+
+/// Decoder for the cache-block operation instructions, in the MISC-MEM opcode.
+///
+/// This decodes either the Zicbom extension's instructions, or the Zicboz extension's CBO.ZERO.
+pub struct CboDecoder {
+    block_size: usize,
+    zero: bool,
+}
+
+impl CboDecoder {
+    /// Create a new CboDecoder, for the provided cache-block size. If `zero` is set, only CBO.ZERO
+    /// is decoded: Otherwise, only CBO.CLEAN, CBO.FLUSH & CBO.INVAL are.
+    pub fn new(block_size: usize, zero: bool) -> Self {
+        Self { block_size, zero }
+    }
+}
+
+impl SubDecoder for CboDecoder {
+    fn decode(
+        &self,
+        instruction: &InstructionWordParts,
+        _pc: u64,
+        xlen: Xlen,
+    ) -> Result<Option<Box<dyn Instruction>>, ProcessorException> {
+        if (instruction.opcode, instruction.funct3, instruction.rd) != (0x0f, 0b010, 0) {
+            return Ok(None);
+        }
+        let op = match (instruction.imm_i, self.zero) {
+            (0b000, false) => CacheBlockOperation::Invalidate,
+            (0b001, false) => CacheBlockOperation::Clean,
+            (0b010, false) => CacheBlockOperation::Flush,
+            (0b100, true) => CacheBlockOperation::Zero,
+            _ => return Ok(None),
+        };
+
+        Ok(Some(Box::new(CboInstruction::new(
+            instruction,
+            op,
+            xlen,
+            self.block_size,
+        ))))
+    }
+}
+
+/// CBO.CLEAN, CBO.FLUSH, CBO.INVAL, or CBO.ZERO instruction.
+pub struct CboInstruction {
+    addr: u8,
+    op: CacheBlockOperation,
+    xlen: Xlen,
+    block_size: usize,
+}
+
+impl CboInstruction {
+    /// Create a new CboInstruction, performing the provided operation on cache blocks of
+    /// `block_size` bytes.
+    pub fn new(
+        instruction: &InstructionWordParts,
+        op: CacheBlockOperation,
+        xlen: Xlen,
+        block_size: usize,
+    ) -> Self {
+        Self {
+            addr: instruction.rs1,
+            op,
+            xlen,
+            block_size,
+        }
+    }
+}
+
+impl Instruction for CboInstruction {
+    fn execute(
+        &self,
+        registers: &mut RegisterFile,
+        _mem: i64,
+    ) -> Result<InstructionResult, ProcessorException> {
+        let addr = registers
+            .get(&self.addr)
+            .ok_or(ProcessorException::IllegalInstruction)?
+            .load()?;
+        let base = self.xlen.zero_extend(addr) as usize & !(self.block_size - 1);
+
+        // Zero the block using the widest stores the hart supports
+        let stores = match self.op {
+            CacheBlockOperation::Zero => {
+                let width = match self.xlen {
+                    Xlen::Rv32 => MemoryAccessType::Word,
+                    Xlen::Rv64 => MemoryAccessType::DoubleWord,
+                };
+                (base..base + self.block_size)
+                    .step_by(width.bytes())
+                    .map(|addr| StoreSpec::new(width, addr, 0))
+                    .collect()
+            }
+            _ => Vec::new(),
+        };
+
+        Ok(InstructionResult::set_cache_block(self.op, stores))
+    }
+
+    fn format(&self) -> String {
+        let op = match self.op {
+            CacheBlockOperation::Clean => "clean",
+            CacheBlockOperation::Flush => "flush",
+            CacheBlockOperation::Invalidate => "inval",
+            CacheBlockOperation::Zero => "zero",
+        };
+        format!("cbo.{} (x{})", op, self.addr)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Zicbom;
+    use crate::rv32i::RV32I;
+    use crate::rv64i::RV64I;
+    use crate::zicboz::Zicboz;
+    use crate::zicsr::Zicsr;
+    use z2l_core::error::ProcessorException;
+    use z2l_core::extension::Extension;
+    use z2l_core::processor::csr::{MENVCFG, MENVCFGH, SENVCFG};
+    use z2l_core::processor::hart::Hart;
+    use z2l_core::processor::PrivilegeLevel;
+
+    const CBO_CLEAN: u32 = 0x0015_200f; // cbo.clean (x10)
+    const CBO_FLUSH: u32 = 0x0025_200f; // cbo.flush (x10)
+    const CBO_INVAL: u32 = 0x0005_200f; // cbo.inval (x10)
+
+    /// Create a hart with the provided base instruction set, Zicsr, Zicbom & Zicboz.
+    fn hart(base: &dyn Extension) -> Hart {
+        let mut hart = Hart::new();
+        base.register(&mut hart);
+        Zicsr.register(&mut hart);
+        Zicbom::new(64).register(&mut hart);
+        Zicboz::new(64).register(&mut hart);
+        hart
+    }
+
+    /// Execute a single instruction, returning its formatting.
+    fn run(hart: &mut Hart, raw: u32) -> Result<String, (ProcessorException, u64)> {
+        hart.cycle(Ok(raw), 0, &[])?;
+        hart.cycle(Ok(0x0000_0013), 0, &[])?;
+        Ok(hart.last_instr.clone().unwrap())
+    }
+
+    #[test]
+    fn registers_csrs() {
+        let rv32 = hart(&RV32I);
+        assert!(rv32.csrs.get(MENVCFG).is_some());
+        assert!(rv32.csrs.get(MENVCFGH).is_some());
+        assert!(rv32.csrs.get(SENVCFG).is_some());
+
+        let rv64 = hart(&RV64I);
+        assert_eq!(rv64.csrs.get(MENVCFG).unwrap().write_mask, 0xf0);
+        assert!(rv64.csrs.get(MENVCFGH).is_none());
+    }
+
+    #[test]
+    fn envcfg_gating() {
+        for (raw, bits, formatted) in [
+            (CBO_CLEAN, 1 << 6, "cbo.clean (x10)"),
+            (CBO_FLUSH, 1 << 6, "cbo.flush (x10)"),
+            (CBO_INVAL, 0b01 << 4, "cbo.inval (x10)"),
+            (CBO_INVAL, 0b11 << 4, "cbo.inval (x10)"),
+        ] {
+            let mut hart = hart(&RV64I);
+            assert_eq!(run(&mut hart, raw).as_deref(), Ok(formatted));
+
+            for privilege in [PrivilegeLevel::Supervisor, PrivilegeLevel::User] {
+                hart.privilege = privilege;
+                assert!(matches!(
+                    run(&mut hart, raw),
+                    Err((ProcessorException::IllegalInstruction, _))
+                ));
+            }
+
+            // menvcfg enables supervisor mode, but user mode also requires senvcfg
+            hart.csrs.get_mut(MENVCFG).unwrap().value = bits;
+            hart.privilege = PrivilegeLevel::Supervisor;
+            assert!(run(&mut hart, raw).is_ok());
+            hart.privilege = PrivilegeLevel::User;
+            assert!(run(&mut hart, raw).is_err());
+            hart.csrs.get_mut(SENVCFG).unwrap().value = bits;
+            assert!(run(&mut hart, raw).is_ok());
+        }
+
+        // The reserved value of CBIE doesn't enable CBO.INVAL
+        let mut hart = hart(&RV32I);
+        hart.csrs.get_mut(MENVCFG).unwrap().value = 0b10 << 4;
+        hart.privilege = PrivilegeLevel::Supervisor;
+        assert!(run(&mut hart, CBO_INVAL).is_err());
+    }
+
+    #[test]
+    #[should_panic(expected = "Cache-block size must be a power of 2")]
+    fn invalid_block_size() {
+        Zicbom::new(48);
+    }
+}
@@ -0,0 +1,135 @@
+//! The "Zicbop" standard extension for cache-block prefetch instructions.
+//!
+//! Zicbop adds PREFETCH.I, PREFETCH.R and PREFETCH.W, which hint that the cache block containing
+//! the address in rs1 plus an offset will soon be accessed by an instruction fetch, a load, or a
+//! store, respectively. The offset is a multiple of 32, encoded in the upper 7 bits of the
+//! immediate.
+//!
+//! The instructions are encoded as ORI instructions with `x0` as their destination, so they have
+//! no architectural effect, and are executed as no-ops even without this extension. Our harts have
+//! no caches, so this extension only decodes them as prefetches, so that they are formatted as such
+//! (e.g. in the instruction log). As hints, they never raise an exception.
+//!
+//! The instructions share the OP-IMM opcode with the base integer instruction set, so are
+//! registered as a [`SubDecoder`] on it.
+
+use std::fmt;
+use z2l_core::error::ProcessorException;
+use z2l_core::extension::{Extension, SubDecoder};
+use z2l_core::instruction::{Instruction, InstructionResult, InstructionWordParts};
+use z2l_core::processor::hart::Hart;
+use z2l_core::processor::register::RegisterFile;
+use z2l_core::processor::Xlen;
+
+/// An [`Extension`] defining the Zicbop standard extension.
+pub struct Zicbop;
+
+impl Extension for Zicbop {
+    fn code(&self) -> &'static str {
+        "Zicbop"
+    }
+
+    fn name(&self) -> &'static str {
+        "Standard Extension for Cache-Block Prefetch Instructions"
+    }
+
+    fn register(&self, hart: &mut Hart) {
+        hart.register_sub_decoder(0x13, Box::new(ZicbopDecoder));
+    }
+}
+
+/// Decoder for the Zicbop extension's instructions, in the OP-IMM opcode.
+pub struct ZicbopDecoder;
+
+impl SubDecoder for ZicbopDecoder {
+    fn decode(
+        &self,
+        instruction: &InstructionWordParts,
+        _pc: u64,
+        _xlen: Xlen,
+    ) -> Result<Option<Box<dyn Instruction>>, ProcessorException> {
+        if (instruction.opcode, instruction.funct3, instruction.rd) != (0x13, 0b110, 0) {
+            return Ok(None);
+        }
+        let access = match instruction.rs2 {
+            0b00000 => Access::Fetch,
+            0b00001 => Access::Read,
+            0b00011 => Access::Write,
+            _ => return Ok(None),
+        };
+
+        Ok(Some(Box::new(PrefetchInstruction::new(
+            instruction,
+            access,
+        ))))
+    }
+}
+
+/// Type of access a prefetch anticipates.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+enum Access {
+    Fetch,
+    Read,
+    Write,
+}
+
+impl fmt::Display for Access {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Access::Fetch => f.write_str("prefetch.i"),
+            Access::Read => f.write_str("prefetch.r"),
+            Access::Write => f.write_str("prefetch.w"),
+        }
+    }
+}
+
+/// PREFETCH.I, PREFETCH.R, or PREFETCH.W instruction.
+pub struct PrefetchInstruction {
+    base: u8,
+    offset: i32,
+    access: Access,
+}
+
+impl PrefetchInstruction {
+    /// Create a new PrefetchInstruction.
+    fn new(instruction: &InstructionWordParts, access: Access) -> Self {
+        Self {
+            base: instruction.rs1,
+            offset: instruction.imm_i & !0x1f,
+            access,
+        }
+    }
+}
+
+impl Instruction for PrefetchInstruction {
+    fn execute(
+        &self,
+        _registers: &mut RegisterFile,
+        _mem: i64,
+    ) -> Result<InstructionResult, ProcessorException> {
+        Ok(InstructionResult::default())
+    }
+
+    fn format(&self) -> String {
+        format!("{} {}(x{})", self.access, self.offset, self.base)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Zicbop;
+    use crate::rv64i::RV64I;
+    use crate::test_utils::{self, decode};
+
+    #[test]
+    fn decoding() {
+        let hart = test_utils::hart(&[&RV64I, &Zicbop]);
+        assert_eq!(decode(&hart, 0x0205_6013), "prefetch.i 32(x10)");
+        assert_eq!(decode(&hart, 0xfc15_6013), "prefetch.r -64(x10)");
+        assert_eq!(decode(&hart, 0x0035_6013), "prefetch.w 0(x10)");
+
+        // Other ORI instructions are unaffected
+        assert_eq!(decode(&hart, 0x0025_6013), "ori x0, x10, 0x00000002");
+        assert_eq!(decode(&hart, 0x0015_6093), "ori x1, x10, 0x00000001");
+    }
+}
@@ -0,0 +1,118 @@
+//! The "Zicboz" standard extension for cache-block zero instructions.
+//!
+//! Zicboz adds CBO.ZERO, which writes zeros to the whole cache block containing the address in rs1.
+//! Our harts have no caches, so the block is zeroed in memory, using a sequence of stores of the
+//! widest size the hart supports. If one of these stores fails, the stores preceding it have still
+//! been performed. Machine mode may always execute CBO.ZERO, but supervisor & user mode may only do
+//! so if it is enabled by the `CBZE` bit of the `menvcfg` CSR (and for user mode, `senvcfg`), as
+//! for the Zicbom extension's instructions.
+//!
+//! CBO.ZERO shares the MISC-MEM opcode with FENCE, so is registered as a [`SubDecoder`] on it.
+//!
+//! [`SubDecoder`]: z2l_core::extension::SubDecoder
+
+use crate::zicbom::{check_block_size, register_envcfg, CboDecoder};
+use z2l_core::extension::Extension;
+use z2l_core::processor::csr::ENVCFG_CBZE;
+use z2l_core::processor::hart::Hart;
+
+/// An [`Extension`] defining the Zicboz standard extension.
+pub struct Zicboz {
+    block_size: usize,
+}
+
+impl Zicboz {
+    /// Create a new Zicboz extension, with cache blocks of `block_size` bytes.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `block_size` is not a power of 2 between 8 and 4096.
+    pub fn new(block_size: usize) -> Self {
+        Self {
+            block_size: check_block_size(block_size),
+        }
+    }
+}
+
+impl Extension for Zicboz {
+    fn code(&self) -> &'static str {
+        "Zicboz"
+    }
+
+    fn name(&self) -> &'static str {
+        "Standard Extension for Cache-Block Zero Instructions"
+    }
+
+    fn register(&self, hart: &mut Hart) {
+        register_envcfg(hart, ENVCFG_CBZE);
+        let decoder = CboDecoder::new(self.block_size, true);
+        hart.register_sub_decoder(0x0f, Box::new(decoder));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Zicboz;
+    use crate::rv32i::RV32I;
+    use crate::rv64i::RV64I;
+    use crate::test_utils;
+    use crate::zicsr::Zicsr;
+    use z2l_core::extension::Extension;
+    use z2l_core::processor::csr::MENVCFG;
+    use z2l_core::processor::{PrivilegeLevel, Processor};
+
+    /// Create a processor with the provided base instruction set, Zicsr & Zicboz, with 32-byte
+    /// cache blocks, running the provided program from RAM at 0x80000000. The rest of the RAM is
+    /// filled with ones.
+    fn processor(base: Box<dyn Extension>, program: &[u32]) -> Processor {
+        let extensions = vec![base, Box::new(Zicsr), Box::new(Zicboz::new(32))];
+        let ones = vec![0xff; 0x100 - 4 * program.len()];
+        test_utils::processor_in_ram(extensions, program, &ones)
+    }
+
+    /// Read `len` bytes of RAM, from offset `offset`.
+    fn ram(processor: &Processor, offset: usize, len: usize) -> Vec<u8> {
+        let mut mmu = processor.mmu.lock().unwrap();
+        mmu.load_bytes(0x8000_0000 + offset, len).unwrap()
+    }
+
+    const PROGRAM: [u32; 4] = [
+        0x0000_0517, // auipc x10, 0
+        0x0545_0513, // addi x10, x10, 84
+        0x0045_200f, // cbo.zero (x10)
+        0x0000_006f, // jal x0, 0
+    ];
+
+    #[test]
+    fn zeroes_aligned_block() {
+        for base in [Box::new(RV32I) as Box<dyn Extension>, Box::new(RV64I)] {
+            let mut processor = processor(base, &PROGRAM);
+            for _ in 0..8 {
+                processor.cycle().unwrap();
+            }
+
+            // The block containing offset 84 spans offsets 64 to 96
+            assert_eq!(ram(&processor, 60, 4), [0xff; 4]);
+            assert_eq!(ram(&processor, 64, 32), [0; 32]);
+            assert_eq!(ram(&processor, 96, 4), [0xff; 4]);
+        }
+    }
+
+    #[test]
+    fn disabled() {
+        let mut disabled = processor(Box::new(RV64I), &PROGRAM);
+        disabled.hart.privilege = PrivilegeLevel::Supervisor;
+        let result = (0..8).try_for_each(|_| disabled.cycle());
+        assert!(result.is_err());
+        assert_eq!(ram(&disabled, 64, 32), [0xff; 32]);
+
+        // Enabled for supervisor mode by menvcfg
+        let mut enabled = processor(Box::new(RV64I), &PROGRAM);
+        enabled.hart.csrs.get_mut(MENVCFG).unwrap().value = 1 << 7;
+        enabled.hart.privilege = PrivilegeLevel::Supervisor;
+        for _ in 0..8 {
+            enabled.cycle().unwrap();
+        }
+        assert_eq!(ram(&enabled, 64, 32), [0; 32]);
+    }
+}
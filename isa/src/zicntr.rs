@@ -0,0 +1,341 @@
+//! The "Zicntr" standard extension for base counters and timers.
+//!
+//! Zicntr exposes three of the hart's [`Counters`] as read-only CSRs: `cycle`, counting the cycles
+//! the hart has run; `time`, counting ticks of the clock driving the processor; and `instret`,
+//! counting the instructions the hart has retired. On RV32, the upper 32 bits of each are read from
+//! `cycleh`, `timeh`, and `instreth`.
+//!
+//! Machine mode can also write the cycle & instructions-retired counters through `mcycle` and
+//! `minstret` (plus `mcycleh` and `minstreth` on RV32), stop them incrementing with
+//! `mcountinhibit`, and allow less-privileged modes to read them with `mcounteren`. These CSRs are
+//! shared with the Zihpm extension, which adds the remaining counters to them.
+
+use z2l_core::extension::Extension;
+use z2l_core::processor::counter::{self, Counters};
+use z2l_core::processor::csr::Csr;
+use z2l_core::processor::hart::Hart;
+use z2l_core::processor::{PrivilegeLevel, Xlen};
+
+/// Cycle counter.
+pub const CYCLE: u16 = 0xc00;
+
+/// Timer.
+pub const TIME: u16 = 0xc01;
+
+/// Instructions-retired counter.
+pub const INSTRET: u16 = 0xc02;
+
+/// Upper 32 bits of `cycle` (RV32 only).
+pub const CYCLEH: u16 = 0xc80;
+
+/// Upper 32 bits of `time` (RV32 only).
+pub const TIMEH: u16 = 0xc81;
+
+/// Upper 32 bits of `instret` (RV32 only).
+pub const INSTRETH: u16 = 0xc82;
+
+/// Machine cycle counter.
+pub const MCYCLE: u16 = 0xb00;
+
+/// Machine instructions-retired counter.
+pub const MINSTRET: u16 = 0xb02;
+
+/// Upper 32 bits of `mcycle` (RV32 only).
+pub const MCYCLEH: u16 = 0xb80;
+
+/// Upper 32 bits of `minstret` (RV32 only).
+pub const MINSTRETH: u16 = 0xb82;
+
+/// Machine counter enable.
+pub const MCOUNTEREN: u16 = 0x306;
+
+/// Machine counter-inhibit register.
+pub const MCOUNTINHIBIT: u16 = 0x320;
+
+/// Offset from the address of a counter CSR to the CSR holding its upper 32 bits on RV32.
+const HIGH_OFFSET: u16 = 0x80;
+
+/// An [`Extension`] defining the Zicntr standard extension.
+pub struct Zicntr;
+
+impl Extension for Zicntr {
+    fn code(&self) -> &'static str {
+        "Zicntr"
+    }
+
+    fn name(&self) -> &'static str {
+        "Standard Extension for Base Counters and Timers"
+    }
+
+    fn register(&self, hart: &mut Hart) {
+        let fixed = (1 << counter::CYCLE) | (1 << counter::TIME) | (1 << counter::INSTRET);
+        register_controls(hart, fixed);
+
+        register_counter(hart, counter::CYCLE, CYCLE, Some(MCYCLE));
+        register_counter(hart, counter::TIME, TIME, None);
+        register_counter(hart, counter::INSTRET, INSTRET, Some(MINSTRET));
+    }
+}
+
+/// Part of a counter exposed by a CSR.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+enum Half {
+    /// The whole counter (RV64).
+    Full,
+
+    /// The lower 32 bits of the counter (RV32).
+    Low,
+
+    /// The upper 32 bits of the counter (RV32).
+    High,
+}
+
+impl Half {
+    /// Get the part of a counter exposed by its CSR, or if `high` is set, by the CSR holding its
+    /// upper 32 bits, on a hart with the provided XLEN.
+    fn new(high: bool, xlen: Xlen) -> Self {
+        match (high, xlen) {
+            (true, _) => Half::High,
+            (false, Xlen::Rv32) => Half::Low,
+            (false, Xlen::Rv64) => Half::Full,
+        }
+    }
+
+    /// Extract this part of the value of a counter.
+    fn read(&self, value: u64) -> u64 {
+        match self {
+            Half::Full => value,
+            Half::Low => value & 0xffff_ffff,
+            Half::High => value >> 32,
+        }
+    }
+
+    /// Replace this part of the value of a counter.
+    fn write(&self, value: u64, new: u64) -> u64 {
+        match self {
+            Half::Full => new,
+            Half::Low => (value & !0xffff_ffff) | (new & 0xffff_ffff),
+            Half::High => (value & 0xffff_ffff) | (new << 32),
+        }
+    }
+}
+
+/// Register `mcounteren` & `mcountinhibit`, if they are not already registered, and allow the
+/// counters in `mask` to be enabled & inhibited through them.
+///
+/// The `time` timebase can be enabled, but not inhibited.
+pub(crate) fn register_controls(hart: &mut Hart, mask: u64) {
+    if hart.csrs.get(MCOUNTEREN).is_none() {
+        hart.csrs
+            .register(MCOUNTEREN, Csr::new(0).with_write_mask(0));
+    }
+    hart.csrs.get_mut(MCOUNTEREN).unwrap().write_mask |= mask;
+
+    if hart.csrs.get(MCOUNTINHIBIT).is_none() {
+        let (read, write) = (hart.counters.clone(), hart.counters.clone());
+        let mcountinhibit = Csr::new(0)
+            .with_write_mask(0)
+            .on_read(move |_, _| read.inhibit())
+            .on_write(move |_, new, _| {
+                write.set_inhibit(new);
+                write.inhibit()
+            });
+        hart.csrs.register(MCOUNTINHIBIT, mcountinhibit);
+    }
+    hart.csrs.get_mut(MCOUNTINHIBIT).unwrap().write_mask |= mask & !(1 << counter::TIME);
+}
+
+/// Register the CSRs exposing the counter with the provided index.
+///
+/// The counter can be read from the read-only CSR at `addr` in any mode in which it is enabled by
+/// `mcounteren`, and if `maddr` is set, read & written from the machine-mode CSR at that address.
+/// On RV32, each CSR only holds the lower 32 bits of the counter, and the upper 32 bits are held by
+/// the CSR `0x80` above it. The part of the counter each CSR holds is chosen by the XLEN when it is
+/// accessed, and the upper CSRs are removed if the XLEN is later set to RV64, so the CSRs have the
+/// same layout whether they are registered before or after the base instruction set.
+pub(crate) fn register_counter(hart: &mut Hart, index: usize, addr: u16, maddr: Option<u16>) {
+    let halves: &[(bool, u16)] = match hart.xlen {
+        Xlen::Rv32 => &[(false, 0), (true, HIGH_OFFSET)],
+        Xlen::Rv64 => &[(false, 0)],
+    };
+
+    for &(high, offset) in halves {
+        let csr = counter_csr(&hart.counters, index, high).on_access(move |csrs, privilege, _| {
+            let mcounteren = csrs.get(MCOUNTEREN).map_or(0, |csr| csr.value);
+            privilege == PrivilegeLevel::Machine || mcounteren & (1 << index) != 0
+        });
+        hart.csrs.register(addr + offset, csr);
+
+        if let Some(maddr) = maddr {
+            let csr = counter_csr(&hart.counters, index, high);
+            hart.csrs.register(maddr + offset, csr);
+        }
+    }
+}
+
+/// Create a CSR exposing the counter with the provided index, or if `high` is set, the CSR holding
+/// its upper 32 bits on RV32.
+fn counter_csr(counters: &Counters, index: usize, high: bool) -> Csr {
+    let (read, write) = (counters.clone(), counters.clone());
+    let csr = Csr::new(0)
+        .on_read(move |_, xlen| Half::new(high, xlen).read(read.read(index)))
+        .on_write(move |_, new, xlen| {
+            let half = Half::new(high, xlen);
+            write.write(index, half.write(write.read(index), new));
+            0
+        });
+    if high {
+        csr.rv32_only()
+    } else {
+        csr
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Zicntr, CYCLE, CYCLEH, MCYCLE};
+    use crate::rv32i::RV32I;
+    use crate::rv64i::RV64I;
+    use crate::test_utils::{self, reg};
+    use crate::zicsr::Zicsr;
+    use z2l_core::error::ProcessorException;
+    use z2l_core::extension::Extension;
+    use z2l_core::processor::counter;
+    use z2l_core::processor::{PrivilegeLevel, Processor, Xlen};
+
+    /// Create a processor with the provided base instruction set, Zicsr, and Zicntr, with a ROM at
+    /// 0 containing the provided program, and RAM at 0x80000000.
+    fn processor(base: Box<dyn Extension>, program: &[u32]) -> Processor {
+        test_utils::processor(vec![base, Box::new(Zicsr), Box::new(Zicntr)], program)
+    }
+
+    /// Run the provided number of cycles.
+    fn run(processor: &mut Processor, cycles: usize) -> Result<(), (ProcessorException, u64)> {
+        for _ in 0..cycles {
+            processor.cycle()?;
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn counters() {
+        let program = [
+            0xc000_20f3, // csrrs x1, cycle, x0
+            0xc020_2173, // csrrs x2, instret, x0
+            0x0000_0463, // beq x0, x0, 8
+            0x0010_0193, // addi x3, x0, 1
+            0xc000_2273, // csrrs x4, cycle, x0
+            0xc020_22f3, // csrrs x5, instret, x0
+            0xc010_2373, // csrrs x6, time, x0
+        ];
+
+        for base in [Box::new(RV32I) as Box<dyn Extension>, Box::new(RV64I)] {
+            let mut processor = processor(base, &program);
+            processor.hart.counters.advance_time(1234);
+            run(&mut processor, 8).unwrap();
+
+            // The first cycle only decodes, and the taken branch flushes the skipped instruction
+            assert_eq!(reg(&processor, 1), 1);
+            assert_eq!(reg(&processor, 2), 1);
+            assert_eq!(reg(&processor, 3), 0);
+            assert_eq!(reg(&processor, 4), 5);
+            assert_eq!(reg(&processor, 5), 4);
+            assert_eq!(reg(&processor, 6), 1234);
+        }
+    }
+
+    #[test]
+    fn machine_counters() {
+        let program = [
+            0x0010_0313, // addi x6, x0, 1
+            0xb823_1073, // csrrw x0, minstreth, x6
+            0xb020_d073, // csrrwi x0, minstret, 1
+            0xc020_23f3, // csrrs x7, instret, x0
+            0xc820_2473, // csrrs x8, instreth, x0
+            0x3200_e073, // csrrsi x0, mcountinhibit, 1
+            0xc000_24f3, // csrrs x9, cycle, x0
+            0xc000_2573, // csrrs x10, cycle, x0
+        ];
+
+        let mut processor = processor(Box::new(RV32I), &program);
+        run(&mut processor, 9).unwrap();
+
+        // The write to minstret replaces the increment for that instruction
+        assert_eq!(reg(&processor, 7), 1);
+        assert_eq!(reg(&processor, 8), 1);
+        assert_eq!(processor.hart.counters.read(2), (1 << 32) | 6);
+
+        // The cycle counter is inhibited
+        assert_eq!(reg(&processor, 9), reg(&processor, 10));
+    }
+
+    #[test]
+    fn mcounteren() {
+        let program = [0xc000_20f3]; // csrrs x1, cycle, x0
+
+        let mut disabled = processor(Box::new(RV64I), &program);
+        disabled.hart.privilege = PrivilegeLevel::User;
+        assert_eq!(
+            run(&mut disabled, 2),
+            Err((ProcessorException::IllegalInstruction, 0))
+        );
+
+        let mut enabled = processor(Box::new(RV64I), &program);
+        enabled.hart.privilege = PrivilegeLevel::User;
+        enabled.hart.csrs.get_mut(0x306).unwrap().value = 0b001;
+        run(&mut enabled, 2).unwrap();
+        assert_eq!(reg(&enabled, 1), 1);
+    }
+
+    #[test]
+    fn registers_csrs() {
+        let rv32 = processor(Box::new(RV32I), &[]);
+        let rv64 = processor(Box::new(RV64I), &[]);
+        for addr in [0xc00, 0xc01, 0xc02, 0xb00, 0xb02, 0x306, 0x320] {
+            assert!(rv32.hart.csrs.get(addr).is_some());
+            assert!(rv64.hart.csrs.get(addr).is_some());
+        }
+        for addr in [0xc80, 0xc81, 0xc82, 0xb80, 0xb82] {
+            assert!(rv32.hart.csrs.get(addr).is_some());
+            assert!(rv64.hart.csrs.get(addr).is_none());
+        }
+
+        // There is no mtime CSR
+        assert!(rv32.hart.csrs.get(0xb01).is_none());
+    }
+
+    #[test]
+    fn registration_order() {
+        let orders: [(&[&dyn Extension], _); 4] = [
+            (&[&RV32I, &Zicsr, &Zicntr], Xlen::Rv32),
+            (&[&Zicsr, &Zicntr, &RV32I], Xlen::Rv32),
+            (&[&RV64I, &Zicsr, &Zicntr], Xlen::Rv64),
+            (&[&Zicsr, &Zicntr, &RV64I], Xlen::Rv64),
+        ];
+        for (extensions, xlen) in orders {
+            let mut hart = test_utils::hart(extensions);
+            hart.counters.write(counter::CYCLE, 0x1_0000_0005);
+            let cycle = hart.csrs.read(CYCLE, PrivilegeLevel::Machine).unwrap();
+            let cycleh = hart.csrs.read(CYCLEH, PrivilegeLevel::Machine);
+
+            match xlen {
+                Xlen::Rv32 => {
+                    assert_eq!(cycle, 5);
+                    assert_eq!(cycleh, Ok(1));
+                }
+                Xlen::Rv64 => {
+                    assert_eq!(cycle, 0x1_0000_0005);
+                    assert_eq!(cycleh, Err(ProcessorException::IllegalInstruction));
+                }
+            }
+
+            // Writes only replace the part of the counter held by the CSR
+            hart.csrs.write(MCYCLE, 7, PrivilegeLevel::Machine).unwrap();
+            let expected = match xlen {
+                Xlen::Rv32 => 0x1_0000_0007,
+                Xlen::Rv64 => 7,
+            };
+            assert_eq!(hart.counters.read(counter::CYCLE), expected);
+        }
+    }
+}
@@ -0,0 +1,224 @@
+//! The "Zicond" standard extension for integer conditional operations.
+//!
+//! Zicond adds two instructions which conditionally zero a value, from which branchless conditional
+//! selects can be built: CZERO.EQZ writes 0 to rd if rs2 is zero, and otherwise copies rs1, while
+//! CZERO.NEZ writes 0 to rd if rs2 is non-zero, and otherwise copies rs1.
+//!
+//! The instructions share the OP opcode with the base integer instruction set, so are registered as
+//! a [`SubDecoder`] on that opcode.
+
+use std::fmt;
+use z2l_core::error::ProcessorException;
+use z2l_core::extension::{Extension, SubDecoder};
+use z2l_core::instruction::{Instruction, InstructionResult, InstructionWordParts};
+use z2l_core::processor::hart::Hart;
+use z2l_core::processor::register::RegisterFile;
+use z2l_core::processor::Xlen;
+
+/// An [`Extension`] defining the Zicond standard extension.
+pub struct Zicond;
+
+impl Extension for Zicond {
+    fn code(&self) -> &'static str {
+        "Zicond"
+    }
+
+    fn name(&self) -> &'static str {
+        "Standard Extension for Integer Conditional Operations"
+    }
+
+    fn register(&self, hart: &mut Hart) {
+        hart.register_sub_decoder(0x33, Box::new(ZicondDecoder));
+    }
+}
+
+/// Decoder for the Zicond extension's instructions, in the OP opcode.
+pub struct ZicondDecoder;
+
+impl SubDecoder for ZicondDecoder {
+    fn decode(
+        &self,
+        instruction: &InstructionWordParts,
+        _pc: u64,
+        _xlen: Xlen,
+    ) -> Result<Option<Box<dyn Instruction>>, ProcessorException> {
+        Ok(Some(
+            match (instruction.opcode, instruction.funct7, instruction.funct3) {
+                (0x33, 0b0000111, 0b101 | 0b111) => Box::new(CzeroInstruction::new(instruction)?),
+                _ => return Ok(None),
+            },
+        ))
+    }
+}
+
+/// Condition under which the result is zeroed.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+enum Condition {
+    EqualZero,
+    NotEqualZero,
+}
+
+impl fmt::Display for Condition {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Condition::EqualZero => f.write_str("czero.eqz"),
+            Condition::NotEqualZero => f.write_str("czero.nez"),
+        }
+    }
+}
+
+/// CZERO.EQZ or CZERO.NEZ instruction.
+pub struct CzeroInstruction {
+    src1: u8,
+    src2: u8,
+    dest: u8,
+    condition: Condition,
+}
+
+impl CzeroInstruction {
+    /// Create a new CzeroInstruction.
+    pub fn new(instruction: &InstructionWordParts) -> Result<Self, ProcessorException> {
+        let condition = match instruction.funct3 {
+            0b101 => Condition::EqualZero,
+            0b111 => Condition::NotEqualZero,
+            _ => return Err(ProcessorException::IllegalInstruction),
+        };
+
+        Ok(Self {
+            src1: instruction.rs1,
+            src2: instruction.rs2,
+            dest: instruction.rd,
+            condition,
+        })
+    }
+}
+
+impl Instruction for CzeroInstruction {
+    fn execute(
+        &self,
+        registers: &mut RegisterFile,
+        _mem: i64,
+    ) -> Result<InstructionResult, ProcessorException> {
+        let src1 = registers
+            .get(&self.src1)
+            .ok_or(ProcessorException::IllegalInstruction)?
+            .load()?;
+        let src2 = registers
+            .get(&self.src2)
+            .ok_or(ProcessorException::IllegalInstruction)?
+            .load()?;
+
+        let zero = match self.condition {
+            Condition::EqualZero => src2 == 0,
+            Condition::NotEqualZero => src2 != 0,
+        };
+        let result = if zero { 0 } else { src1 };
+
+        let dest = registers
+            .get_mut(&self.dest)
+            .ok_or(ProcessorException::IllegalInstruction)?;
+        dest.store(result)?;
+
+        Ok(InstructionResult::default())
+    }
+
+    fn format(&self) -> String {
+        format!(
+            "{} x{}, x{}, x{}",
+            self.condition, self.dest, self.src1, self.src2
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Zicond;
+    use crate::m::M;
+    use crate::rv32i::RV32I;
+    use crate::rv64i::RV64I;
+    use z2l_core::error::ProcessorException;
+    use z2l_core::extension::Extension;
+    use z2l_core::instruction::InstructionParts;
+    use z2l_core::processor::hart::Hart;
+
+    /// Execute the provided instruction on a hart with the provided extensions, registered in
+    /// order, with x1 = `a` and x2 = `b`, returning the value of x3.
+    fn execute(
+        extensions: &[&dyn Extension],
+        raw: u32,
+        a: i64,
+        b: i64,
+    ) -> Result<i64, ProcessorException> {
+        let mut hart = Hart::new();
+        for extension in extensions {
+            extension.register(&mut hart);
+        }
+        hart.registers.get_mut(&1).unwrap().store(a).unwrap();
+        hart.registers.get_mut(&2).unwrap().store(b).unwrap();
+
+        let parts = InstructionParts::new(raw).unwrap();
+        let handler = hart.opcodes.get(&parts.opcode()).unwrap();
+        let instruction = handler.decode(parts, 0, hart.xlen)?;
+        assert!(instruction.format().ends_with(" x3, x1, x2"));
+        instruction.execute(&mut hart.registers, 0)?;
+
+        Ok(hart.registers.get(&3).unwrap().load().unwrap())
+    }
+
+    const CZERO_EQZ: u32 = 0x0e20_d1b3; // czero.eqz x3, x1, x2
+    const CZERO_NEZ: u32 = 0x0e20_f1b3; // czero.nez x3, x1, x2
+    const ADD: u32 = 0x0020_81b3; // add x3, x1, x2
+    const MUL: u32 = 0x0220_81b3; // mul x3, x1, x2
+    const MULW: u32 = 0x0220_81bb; // mulw x3, x1, x2
+
+    #[test]
+    fn czero() {
+        for base in [&RV32I as &dyn Extension, &RV64I] {
+            let extensions = [base, &Zicond];
+            assert_eq!(execute(&extensions, CZERO_EQZ, -7, 0), Ok(0));
+            assert_eq!(execute(&extensions, CZERO_EQZ, -7, 1), Ok(-7));
+            assert_eq!(execute(&extensions, CZERO_NEZ, -7, 0), Ok(-7));
+            assert_eq!(execute(&extensions, CZERO_NEZ, -7, -1), Ok(0));
+        }
+
+        // The whole register is tested, not just its lower 32 bits
+        let extensions = [&RV64I as &dyn Extension, &Zicond];
+        assert_eq!(execute(&extensions, CZERO_EQZ, 5, 1 << 32), Ok(5));
+    }
+
+    #[test]
+    fn registration_order() {
+        for base in [&RV32I as &dyn Extension, &RV64I] {
+            let rv64 = base.code() == "RV64I";
+            let orders: [&[&dyn Extension]; 5] = [
+                &[base, &M, &Zicond],
+                &[base, &Zicond, &M],
+                &[&Zicond, base, &M],
+                &[&Zicond, &M, base],
+                &[&M, &Zicond, base],
+            ];
+
+            // Each extension decodes its own slice of the OP opcode, whatever the order
+            for extensions in orders {
+                assert_eq!(execute(extensions, CZERO_EQZ, 6, 0), Ok(0));
+                assert_eq!(execute(extensions, CZERO_NEZ, 6, 0), Ok(6));
+                assert_eq!(execute(extensions, ADD, 6, 7), Ok(13));
+                assert_eq!(execute(extensions, MUL, 6, 7), Ok(42));
+
+                // The XLEN is that of the base instruction set, even if it is registered last
+                let (mul, mulw) = if rv64 {
+                    (Ok(1 << 40), Ok(-1 << 31))
+                } else {
+                    (Ok(0), Err(ProcessorException::IllegalInstruction))
+                };
+                assert_eq!(execute(extensions, MUL, 1 << 20, 1 << 20), mul);
+                assert_eq!(execute(extensions, MULW, 1 << 16, 1 << 15), mulw);
+            }
+        }
+
+        // Without the base instruction set, the other instructions are illegal
+        let extensions = [&Zicond as &dyn Extension, &M];
+        assert!(execute(&extensions, ADD, 6, 7).is_err());
+        assert_eq!(execute(&extensions, CZERO_EQZ, 6, 0), Ok(0));
+    }
+}
@@ -0,0 +1,270 @@
+//! The "Zicsr" standard extension for control and status register instructions.
+//!
+//! The Zicsr instructions atomically read-modify-write a single CSR. They share the SYSTEM opcode
+//! with the ECALL/EBREAK instructions of the base integer instruction set, and are distinguished by
+//! a non-zero funct3 value. Registering this extension therefore wraps whichever handler is already
+//! registered for the SYSTEM opcode, rather than replacing it.
+//!
+//! This extension does not itself define any CSRs: These are registered by other extensions.
+
+use z2l_core::error::ProcessorException;
+use z2l_core::extension::{Extension, OpcodeHandler};
+use z2l_core::instruction::{
+    Instruction, InstructionParts, InstructionResult, InstructionWordParts,
+};
+use z2l_core::processor::csr::{CsrOperation, CsrSpec};
+use z2l_core::processor::hart::Hart;
+use z2l_core::processor::register::RegisterFile;
+use z2l_core::processor::Xlen;
+
+/// An [`Extension`] defining the Zicsr standard extension.
+pub struct Zicsr;
+
+impl Extension for Zicsr {
+    fn code(&self) -> &'static str {
+        "Zicsr"
+    }
+
+    fn name(&self) -> &'static str {
+        "Standard Extension for Control and Status Register (CSR) Instructions"
+    }
+
+    fn register(&self, hart: &mut Hart) {
+        let inner = hart.opcodes.remove(&0x73);
+        hart.opcodes.insert(0x73, Box::new(CsrHandler::new(inner)));
+    }
+}
+
+/// SYSTEM opcode handler for the Zicsr extension.
+///
+/// Decodes instructions with a non-zero funct3 as CSR instructions. Any other instruction is passed
+/// on to the wrapped handler, if there is one.
+pub struct CsrHandler {
+    inner: Option<Box<dyn OpcodeHandler>>,
+}
+
+impl CsrHandler {
+    /// Create a new CsrHandler, wrapping the provided SYSTEM opcode handler.
+    pub fn new(inner: Option<Box<dyn OpcodeHandler>>) -> Self {
+        Self { inner }
+    }
+}
+
+impl OpcodeHandler for CsrHandler {
+    fn decode(
+        &self,
+        instruction: InstructionParts,
+        pc: u64,
+        xlen: Xlen,
+    ) -> Result<Box<dyn Instruction>, ProcessorException> {
+        let parts = instruction.word()?;
+
+        match parts.funct3 {
+            0b000 => match &self.inner {
+                Some(inner) => inner.decode(instruction, pc, xlen),
+                None => Err(ProcessorException::IllegalInstruction),
+            },
+            0b100 => Err(ProcessorException::IllegalInstruction),
+            _ => Ok(Box::new(CsrInstruction::new(parts))),
+        }
+    }
+
+    fn inner_mut(&mut self) -> Option<&mut Option<Box<dyn OpcodeHandler>>> {
+        Some(&mut self.inner)
+    }
+}
+
+/// Source operand of a CSR instruction.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+enum Source {
+    /// Operand is read from a register.
+    Register(u8),
+
+    /// Operand is a 5-bit zero-extended immediate.
+    Immediate(u64),
+}
+
+/// A CSRRW, CSRRS, CSRRC, CSRRWI, CSRRSI, or CSRRCI instruction.
+pub struct CsrInstruction {
+    csr: u16,
+    src: Source,
+    dest: u8,
+    op: CsrOperation,
+}
+
+impl CsrInstruction {
+    /// Create a new CsrInstruction.
+    pub fn new(instruction: &InstructionWordParts) -> Self {
+        let op = match instruction.funct3 & 0b011 {
+            0b01 => CsrOperation::Write,
+            0b10 => CsrOperation::Set,
+            0b11 => CsrOperation::Clear,
+            _ => unreachable!("funct3 = 0b000/0b100 handled by CsrHandler"),
+        };
+
+        let src = if instruction.funct3 & 0b100 == 0 {
+            Source::Register(instruction.rs1)
+        } else {
+            Source::Immediate(instruction.rs1 as u64)
+        };
+
+        Self {
+            csr: (instruction.imm_i & 0xfff) as u16,
+            src,
+            dest: instruction.rd,
+            op,
+        }
+    }
+}
+
+impl Instruction for CsrInstruction {
+    fn execute(
+        &self,
+        registers: &mut RegisterFile,
+        _mem: i64,
+    ) -> Result<InstructionResult, ProcessorException> {
+        let value = match self.src {
+            Source::Register(src) => registers
+                .get(&src)
+                .ok_or(ProcessorException::IllegalInstruction)?
+                .load()? as u64,
+            Source::Immediate(imm) => imm,
+        };
+
+        // CSRRW(I) with rd = x0 does not read the CSR, while CSRRS(I)/CSRRC(I) with rs1 = x0 (or a
+        // zero immediate) do not write the CSR, so neither cause the corresponding side-effects
+        let (read, write) = match (self.op, self.src) {
+            (CsrOperation::Write, _) => (self.dest != 0, true),
+            (_, Source::Register(0) | Source::Immediate(0)) => (true, false),
+            _ => (true, true),
+        };
+
+        Ok(InstructionResult::set_csr(CsrSpec {
+            addr: self.csr,
+            op: self.op,
+            value,
+            dest: self.dest,
+            read,
+            write,
+        }))
+    }
+
+    fn format(&self) -> String {
+        let op = match self.op {
+            CsrOperation::Write => "csrrw",
+            CsrOperation::Set => "csrrs",
+            CsrOperation::Clear => "csrrc",
+        };
+
+        match self.src {
+            Source::Register(src) => format!("{} x{}, 0x{:03x}, x{}", op, self.dest, self.csr, src),
+            Source::Immediate(imm) => {
+                format!("{}i x{}, 0x{:03x}, 0x{:02x}", op, self.dest, self.csr, imm)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Zicsr;
+    use crate::rv32i::RV32I;
+    use std::sync::atomic::{AtomicU32, Ordering};
+    use std::sync::Arc;
+    use z2l_core::error::ProcessorException;
+    use z2l_core::extension::Extension;
+    use z2l_core::processor::csr::Csr;
+    use z2l_core::processor::hart::Hart;
+
+    /// Create a hart implementing RV32I_Zicsr, with a CSR registered at 0x340.
+    fn hart() -> Hart {
+        let mut hart = Hart::new();
+        RV32I.register(&mut hart);
+        Zicsr.register(&mut hart);
+        hart.csrs
+            .register(0x340, Csr::new(0).with_write_mask(0x0000_ffff));
+        hart
+    }
+
+    /// Execute the provided instructions in sequence.
+    fn run(hart: &mut Hart, program: &[u32]) -> Result<(), (ProcessorException, u64)> {
+        for instr in program {
+            hart.cycle(Ok(*instr), 0, &[])?;
+        }
+        hart.cycle(Ok(0x0000_0013), 0, &[])?;
+        Ok(())
+    }
+
+    #[test]
+    fn read_modify_write() {
+        let mut hart = hart();
+        run(
+            &mut hart,
+            &[
+                0x1230_0313, // addi x6, x0, 0x123
+                0x3403_12f3, // csrrw x5, 0x340, x6
+                0x3400_23f3, // csrrs x7, 0x340, x0
+                0x3401_f4f3, // csrrci x9, 0x340, 3
+                0x340f_d2f3, // csrrwi x5, 0x340, 31
+            ],
+        )
+        .unwrap();
+
+        let reg = |n: u8| hart.registers.get(&n).unwrap().load().unwrap();
+        assert_eq!(reg(7), 0x123);
+        assert_eq!(reg(9), 0x123);
+        assert_eq!(reg(5), 0x120);
+        assert_eq!(hart.csrs.get(0x340).unwrap().value, 31);
+    }
+
+    #[test]
+    fn side_effects() {
+        let reads = Arc::new(AtomicU32::new(0));
+        let writes = Arc::new(AtomicU32::new(0));
+        let (r, w) = (reads.clone(), writes.clone());
+
+        let mut hart = hart();
+        hart.csrs.register(
+            0x340,
+            Csr::new(0)
+                .on_read(move |value, _| {
+                    r.fetch_add(1, Ordering::Relaxed);
+                    value
+                })
+                .on_write(move |_, new, _| {
+                    w.fetch_add(1, Ordering::Relaxed);
+                    new
+                }),
+        );
+
+        run(
+            &mut hart,
+            &[
+                0x3400_23f3, // csrrs x7, 0x340, x0: Read only
+                0x3400_63f3, // csrrsi x7, 0x340, 0: Read only
+                0x3404_3073, // csrrc x0, 0x340, x8: Read & write
+            ],
+        )
+        .unwrap();
+        assert_eq!(reads.load(Ordering::Relaxed), 3);
+        assert_eq!(writes.load(Ordering::Relaxed), 1);
+    }
+
+    #[test]
+    fn illegal_access() {
+        // Unregistered CSR
+        let mut unregistered = hart();
+        assert_eq!(
+            run(&mut unregistered, &[0x7c00_23f3]), // csrrs x7, 0x7c0, x0
+            Err((ProcessorException::IllegalInstruction, 0))
+        );
+
+        // Write to read-only CSR
+        let mut read_only = hart();
+        read_only.csrs.register(0xc00, Csr::new(0));
+        assert_eq!(
+            run(&mut read_only, &[0xc003_1073]), // csrrw x0, 0xc00, x6
+            Err((ProcessorException::IllegalInstruction, 0))
+        );
+    }
+}
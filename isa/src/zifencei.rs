@@ -0,0 +1,188 @@
+//! The "Zifencei" standard extension for instruction-fetch fences.
+//!
+//! Zifencei adds the FENCE.I instruction, which synchronises the instruction stream with the stores
+//! the hart has performed, so that code written to memory can then be executed. The hart decodes
+//! each instruction one cycle before executing it, so FENCE.I discards the instruction following
+//! it, which is decoded again from memory. Whether a store to that instruction is visible without
+//! FENCE.I is instead determined by the hart's [`CodeCoherence`].
+//!
+//! FENCE.I shares the MISC-MEM opcode with FENCE, so is registered as a [`SubDecoder`] on it.
+//!
+//! [`CodeCoherence`]: z2l_core::processor::hart::CodeCoherence
+
+use z2l_core::error::ProcessorException;
+use z2l_core::extension::{Extension, SubDecoder};
+use z2l_core::instruction::{Instruction, InstructionResult, InstructionWordParts};
+use z2l_core::processor::hart::Hart;
+use z2l_core::processor::register::RegisterFile;
+use z2l_core::processor::Xlen;
+
+/// An [`Extension`] defining the Zifencei standard extension.
+pub struct Zifencei;
+
+impl Extension for Zifencei {
+    fn code(&self) -> &'static str {
+        "Zifencei"
+    }
+
+    fn name(&self) -> &'static str {
+        "Standard Extension for Instruction-Fetch Fence"
+    }
+
+    fn register(&self, hart: &mut Hart) {
+        hart.register_sub_decoder(0x0f, Box::new(ZifenceiDecoder));
+    }
+}
+
+/// Decoder for the Zifencei extension's instructions, in the MISC-MEM opcode.
+pub struct ZifenceiDecoder;
+
+impl SubDecoder for ZifenceiDecoder {
+    fn decode(
+        &self,
+        instruction: &InstructionWordParts,
+        _pc: u64,
+        _xlen: Xlen,
+    ) -> Result<Option<Box<dyn Instruction>>, ProcessorException> {
+        // The rd, rs1 & immediate fields are reserved for finer-grained fences, and must be ignored
+        if instruction.funct3 != 0b001 {
+            return Ok(None);
+        }
+        Ok(Some(Box::new(FenceIInstruction)))
+    }
+}
+
+/// FENCE.I instruction.
+pub struct FenceIInstruction;
+
+impl Instruction for FenceIInstruction {
+    fn execute(
+        &self,
+        _registers: &mut RegisterFile,
+        _mem: i64,
+    ) -> Result<InstructionResult, ProcessorException> {
+        // Stores are performed in program order, so only the instruction decoded ahead of this one
+        // can be stale
+        Ok(InstructionResult::set_flush())
+    }
+
+    fn format(&self) -> String {
+        String::from("fence.i")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Zifencei;
+    use crate::rv32i::RV32I;
+    use crate::rv64i::RV64I;
+    use crate::test_utils::{processor_in_ram, reg};
+    use z2l_core::error::{MemoryAccessError, ProcessorException};
+    use z2l_core::extension::Extension;
+    use z2l_core::processor::hart::CodeCoherence;
+
+    #[test]
+    fn stores_to_decoded_instruction() {
+        let program = [
+            0x0000_0097, // auipc x1, 0
+            0x0140_a103, // lw x2, 20(x1)
+            0x0020_a623, // sw x2, 12(x1)
+            0x0010_0193, // addi x3, x0, 1
+            0x0000_006f, // jal x0, 0
+            0x0020_0193, // addi x3, x0, 2
+        ];
+
+        for (coherence, expected) in [(CodeCoherence::FenceI, 1), (CodeCoherence::Immediate, 2)] {
+            for base in [Box::new(RV32I) as Box<dyn Extension>, Box::new(RV64I)] {
+                let mut processor = processor_in_ram(vec![base, Box::new(Zifencei)], &program, &[]);
+                processor.hart.code_coherence = coherence;
+                for _ in 0..8 {
+                    processor.cycle().unwrap();
+                }
+
+                // The addi was decoded before the store replaced it
+                assert_eq!(reg(&processor, 3), expected, "{:?}", coherence);
+            }
+        }
+    }
+
+    #[test]
+    fn store_to_top_of_address_space() {
+        let program = [
+            0xfe00_3c23, // sd x0, -8(x0)
+            0x0000_006f, // jal x0, 0
+        ];
+
+        let mut processor =
+            processor_in_ram(vec![Box::new(RV64I), Box::new(Zifencei)], &program, &[]);
+        processor.hart.code_coherence = CodeCoherence::Immediate;
+        processor.cycle().unwrap();
+
+        // The store extends beyond the top of the address space, so it faults rather than
+        // overwriting the next instruction
+        let out_of_bounds = ProcessorException::InvalidMemoryAccess(MemoryAccessError::OutOfBounds);
+        assert_eq!(processor.cycle(), Err((out_of_bounds, 0x8000_0000)));
+    }
+
+    #[test]
+    fn fence_i() {
+        let program = [
+            0x0000_0097, // auipc x1, 0
+            0x0180_a103, // lw x2, 24(x1)
+            0x0020_a823, // sw x2, 16(x1)
+            0x0000_100f, // fence.i
+            0x0010_0193, // addi x3, x0, 1
+            0x0000_006f, // jal x0, 0
+            0x0020_0193, // addi x3, x0, 2
+        ];
+
+        let mut processor =
+            processor_in_ram(vec![Box::new(RV32I), Box::new(Zifencei)], &program, &[]);
+        for _ in 0..4 {
+            processor.cycle().unwrap();
+        }
+        assert!(processor.hart.has_decoded_instruction());
+
+        // The instruction following the fence is decoded again
+        processor.cycle().unwrap();
+        assert!(!processor.hart.has_decoded_instruction());
+        assert_eq!(processor.hart.last_instr.as_deref(), Some("fence.i"));
+        for _ in 0..3 {
+            processor.cycle().unwrap();
+        }
+        assert_eq!(reg(&processor, 3), 2);
+    }
+
+    #[test]
+    fn illegal_instructions() {
+        let cases: [(&[u32], bool); 4] = [
+            (&[0x0000_100f], false), // fence.i, without Zifencei
+            (&[0x0000_200f], false), // MISC-MEM, funct3 = 0b010
+            (&[0x0000_200f], true),  // MISC-MEM, funct3 = 0b010
+            (&[0x0000_700f], true),  // MISC-MEM, funct3 = 0b111
+        ];
+        for (program, zifencei) in cases {
+            let mut extensions = vec![Box::new(RV32I) as Box<dyn Extension>];
+            if zifencei {
+                extensions.push(Box::new(Zifencei));
+            }
+            let mut processor = processor_in_ram(extensions, program, &[]);
+            processor.cycle().unwrap();
+            assert_eq!(
+                processor.cycle(),
+                Err((ProcessorException::IllegalInstruction, 0x8000_0000)),
+                "{:08x}",
+                program[0]
+            );
+        }
+
+        // Reserved fields of FENCE.I are ignored
+        let mut processor = processor_in_ram(
+            vec![Box::new(RV32I), Box::new(Zifencei)],
+            &[0xfff0_9f8f],
+            &[],
+        );
+        processor.cycle().unwrap();
+        processor.cycle().unwrap();
+    }
+}
@@ -0,0 +1,155 @@
+//! The "Zihpm" standard extension for hardware performance counters.
+//!
+//! Zihpm exposes the 29 hardware performance monitoring (HPM) counters of the hart's [`Counters`]
+//! as the read-only CSRs `hpmcounter3` to `hpmcounter31` (plus `hpmcounter3h` to `hpmcounter31h` on
+//! RV32), alongside the machine-mode `mhpmcounter3` to `mhpmcounter31`, which can also be written.
+//! Each counter counts the [`Event`] selected by writing its code to the corresponding
+//! `mhpmevent3` to `mhpmevent31` CSR: Writing any other value stops the counter, and reads back as
+//! zero.
+//!
+//! Like the Zicntr counters, HPM counters are enabled for less-privileged modes by `mcounteren`,
+//! and stopped by `mcountinhibit`.
+
+use crate::zicntr::{register_controls, register_counter};
+use z2l_core::extension::Extension;
+use z2l_core::processor::counter::{Counters, Event, COUNTER_COUNT, HPM_BASE};
+use z2l_core::processor::csr::Csr;
+use z2l_core::processor::hart::Hart;
+
+/// First HPM counter (`hpmcounter3`).
+///
+/// `hpmcounterN` is at `HPMCOUNTER3 + N - 3`.
+pub const HPMCOUNTER3: u16 = 0xc03;
+
+/// First machine HPM counter (`mhpmcounter3`).
+///
+/// `mhpmcounterN` is at `MHPMCOUNTER3 + N - 3`.
+pub const MHPMCOUNTER3: u16 = 0xb03;
+
+/// First machine HPM event selector (`mhpmevent3`).
+///
+/// `mhpmeventN` is at `MHPMEVENT3 + N - 3`.
+pub const MHPMEVENT3: u16 = 0x323;
+
+/// An [`Extension`] defining the Zihpm standard extension.
+pub struct Zihpm;
+
+impl Extension for Zihpm {
+    fn code(&self) -> &'static str {
+        "Zihpm"
+    }
+
+    fn name(&self) -> &'static str {
+        "Standard Extension for Hardware Performance Counters"
+    }
+
+    fn register(&self, hart: &mut Hart) {
+        let hpm = (u32::MAX as u64) & !((1 << HPM_BASE) - 1);
+        register_controls(hart, hpm);
+
+        for index in HPM_BASE..COUNTER_COUNT {
+            let offset = (index - HPM_BASE) as u16;
+            register_counter(
+                hart,
+                index,
+                HPMCOUNTER3 + offset,
+                Some(MHPMCOUNTER3 + offset),
+            );
+            let csr = event_csr(&hart.counters, index);
+            hart.csrs.register(MHPMEVENT3 + offset, csr);
+        }
+    }
+}
+
+/// Create the `mhpmevent` CSR selecting the event counted by the counter with the provided index.
+fn event_csr(counters: &Counters, index: usize) -> Csr {
+    let (read, write) = (counters.clone(), counters.clone());
+    Csr::new(0)
+        .on_read(move |_, _| read.event(index))
+        .on_write(move |_, new, _| {
+            // WARL: Unsupported events are replaced with 0, which counts nothing
+            let code = Event::from_code(new).map_or(0, |event| event.code());
+            write.set_event(index, code);
+            code
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Zihpm;
+    use crate::rv32i::RV32I;
+    use crate::rv64i::RV64I;
+    use crate::test_utils::{self, reg};
+    use crate::zicntr::Zicntr;
+    use crate::zicsr::Zicsr;
+    use z2l_core::extension::Extension;
+    use z2l_core::processor::Processor;
+
+    /// Create a processor with the provided base instruction set, Zicsr, Zicntr, and Zihpm, with a
+    /// ROM at 0 containing the provided program, and RAM at 0x80000000.
+    fn processor(base: Box<dyn Extension>, program: &[u32]) -> Processor {
+        let extensions = vec![base, Box::new(Zicsr), Box::new(Zicntr), Box::new(Zihpm)];
+        test_utils::processor(extensions, program)
+    }
+
+    #[test]
+    fn events() {
+        let program = [
+            0x0010_0093, // addi x1, x0, 1
+            0x01f0_9093, // slli x1, x1, 31
+            0x3230_d073, // csrrwi x0, mhpmevent3, 1
+            0x3241_5073, // csrrwi x0, mhpmevent4, 2
+            0x3252_5073, // csrrwi x0, mhpmevent5, 4
+            0x3262_d073, // csrrwi x0, mhpmevent6, 5
+            0x327f_d073, // csrrwi x0, mhpmevent7, 31
+            0x0000_a023, // sw x0, 0(x1)
+            0x0000_a103, // lw x2, 0(x1)
+            0x0040_a183, // lw x3, 4(x1)
+            0x0080_006f, // jal x0, 8
+            0x0010_0213, // addi x4, x0, 1
+            0x0000_1463, // bne x0, x0, 8
+            0xc030_22f3, // csrrs x5, hpmcounter3, x0
+            0xc040_2373, // csrrs x6, hpmcounter4, x0
+            0xc050_23f3, // csrrs x7, hpmcounter5, x0
+            0xc060_2473, // csrrs x8, hpmcounter6, x0
+            0x3270_24f3, // csrrs x9, mhpmevent7, x0
+        ];
+
+        for base in [Box::new(RV32I) as Box<dyn Extension>, Box::new(RV64I)] {
+            // One cycle only decodes, and one instruction is skipped by the jump
+            let mut processor = processor(base, &program);
+            for _ in 0..program.len() + 1 {
+                processor.cycle().unwrap();
+            }
+
+            // Loads, stores, taken branches & flushes
+            assert_eq!(reg(&processor, 5), 2);
+            assert_eq!(reg(&processor, 6), 1);
+            assert_eq!(reg(&processor, 7), 1);
+            assert_eq!(reg(&processor, 8), 1);
+
+            // Unsupported event
+            assert_eq!(reg(&processor, 9), 0);
+        }
+    }
+
+    #[test]
+    fn registers_csrs() {
+        let rv32 = processor(Box::new(RV32I), &[]);
+        let rv64 = processor(Box::new(RV64I), &[]);
+        for n in 3..32 {
+            for addr in [0xc00 + n, 0xb00 + n, 0x320 + n] {
+                assert!(rv32.hart.csrs.get(addr).is_some());
+                assert!(rv64.hart.csrs.get(addr).is_some());
+            }
+            for addr in [0xc80 + n, 0xb80 + n] {
+                assert!(rv32.hart.csrs.get(addr).is_some());
+                assert!(rv64.hart.csrs.get(addr).is_none());
+            }
+        }
+
+        // All counters can be enabled, & all but time inhibited
+        assert_eq!(rv64.hart.csrs.get(0x306).unwrap().write_mask, 0xffff_ffff);
+        assert_eq!(rv64.hart.csrs.get(0x320).unwrap().write_mask, 0xffff_fffd);
+    }
+}
@@ -0,0 +1,202 @@
+//! The "Zknd" standard extension for NIST suite AES decryption.
+//!
+//! On RV32, Zknd adds AES32DSI and AES32DSMI, which each compute a quarter of a column of an AES
+//! decryption round. On RV64, it adds AES64DS and AES64DSM, which each compute half of a round,
+//! AES64IM for transforming round keys for the equivalent inverse cipher, and the AES64KS1I and
+//! AES64KS2 key schedule instructions shared with the Zkne extension. See [`crate::zkne`] for the
+//! instructions.
+
+use crate::zkne::{
+    Aes32Instruction, Aes64Instruction, Aes64InverseMixInstruction, Aes64KeyScheduleInstruction,
+};
+use z2l_core::error::ProcessorException;
+use z2l_core::extension::{Extension, SubDecoder};
+use z2l_core::instruction::{Instruction, InstructionWordParts};
+use z2l_core::processor::hart::Hart;
+use z2l_core::processor::Xlen;
+
+/// An [`Extension`] defining the Zknd standard extension.
+pub struct Zknd;
+
+impl Extension for Zknd {
+    fn code(&self) -> &'static str {
+        "Zknd"
+    }
+
+    fn name(&self) -> &'static str {
+        "Standard Extension for NIST Suite: AES Decryption"
+    }
+
+    fn register(&self, hart: &mut Hart) {
+        for opcode in [0x13, 0x33] {
+            hart.register_sub_decoder(opcode, Box::new(ZkndDecoder));
+        }
+    }
+}
+
+/// Decoder for the Zknd extension's instructions, in the OP and (on RV64) OP-IMM opcodes.
+pub struct ZkndDecoder;
+
+impl SubDecoder for ZkndDecoder {
+    fn decode(
+        &self,
+        instruction: &InstructionWordParts,
+        _pc: u64,
+        xlen: Xlen,
+    ) -> Result<Option<Box<dyn Instruction>>, ProcessorException> {
+        let funct7 = instruction.funct7;
+        let imm = instruction.imm_i & 0xfff;
+
+        Ok(Some(match (instruction.opcode, instruction.funct3, xlen) {
+            (0x33, 0b000, Xlen::Rv32) if matches!(funct7 & 0b11111, 0b10101 | 0b10111) => {
+                Box::new(Aes32Instruction::new(instruction)?)
+            }
+            (0x33, 0b000, Xlen::Rv64) if matches!(funct7, 0b0011101 | 0b0011111) => {
+                Box::new(Aes64Instruction::new(instruction)?)
+            }
+            (0x33, 0b000, Xlen::Rv64) if funct7 == 0b0111111 => {
+                Box::new(Aes64KeyScheduleInstruction::new_second(instruction)?)
+            }
+            (0x13, 0b001, Xlen::Rv64) if imm == 0x300 => {
+                Box::new(Aes64InverseMixInstruction::new(instruction)?)
+            }
+            (0x13, 0b001, Xlen::Rv64) if imm >> 4 == 0x31 => {
+                Box::new(Aes64KeyScheduleInstruction::new_first(instruction)?)
+            }
+            _ => return Ok(None),
+        }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Zknd;
+    use crate::rv32i::RV32I;
+    use crate::rv64i::RV64I;
+    use crate::test_utils::{self, decode, execute32, execute64};
+    use crate::zkne::Zkne;
+
+    const AES32ESI: u32 = 0x2220_81b3; // aes32esi x3, x1, x2, 0
+    const AES32DSI: u32 = 0x2a20_81b3; // aes32dsi x3, x1, x2, 0
+    const AES32DSMI: u32 = 0x2e20_81b3; // aes32dsmi x3, x1, x2, 0
+    const AES64DS: u32 = 0x3a20_81b3; // aes64ds x3, x1, x2
+    const AES64DSM: u32 = 0x3e20_81b3; // aes64dsm x3, x1, x2
+    const AES64IM: u32 = 0x3000_9193; // aes64im x3, x1
+    const AES64KS1I: u32 = 0x3100_9193; // aes64ks1i x3, x1, 0
+    const AES64KS2: u32 = 0x7e20_81b3; // aes64ks2 x3, x1, x2
+
+    /// Round constants for the AES-128 key schedule.
+    const ROUND_CONSTANTS: [u32; 10] = [0x01, 0x02, 0x04, 0x08, 0x10, 0x20, 0x40, 0x80, 0x1b, 0x36];
+
+    // FIPS-197 Appendix C.1 (AES-128)
+    const KEY: u128 = 0x000102030405060708090a0b0c0d0e0f;
+    const PLAINTEXT: u128 = 0x00112233445566778899aabbccddeeff;
+    const CIPHERTEXT: u128 = 0x69c4e0d86a7b0430d8cdb78070b4c55a;
+
+    /// Split a block into columns, in the order they're stored in memory.
+    fn columns(block: u128) -> [u32; 4] {
+        let bytes = block.to_be_bytes();
+        std::array::from_fn(|i| u32::from_le_bytes(bytes[i * 4..i * 4 + 4].try_into().unwrap()))
+    }
+
+    #[test]
+    fn decode_instructions() {
+        let rv32 = test_utils::hart(&[&RV32I, &Zkne, &Zknd]);
+        assert_eq!(decode(&rv32, AES32DSI | 1 << 30), "aes32dsi x3, x1, x2, 1");
+        assert_eq!(decode(&rv32, AES32DSMI), "aes32dsmi x3, x1, x2, 0");
+        assert_eq!(decode(&rv32, AES32ESI), "aes32esi x3, x1, x2, 0");
+
+        let rv64 = test_utils::hart(&[&RV64I, &Zkne, &Zknd]);
+        assert_eq!(decode(&rv64, AES64DS), "aes64ds x3, x1, x2");
+        assert_eq!(decode(&rv64, AES64DSM), "aes64dsm x3, x1, x2");
+        assert_eq!(decode(&rv64, AES64IM), "aes64im x3, x1");
+        assert_eq!(decode(&rv64, AES64KS2), "aes64ks2 x3, x1, x2");
+    }
+
+    #[test]
+    fn decrypt_rv32() {
+        // Zkne is used to compute the key schedule
+        let mut hart = test_utils::hart(&[&RV32I, &Zkne, &Zknd]);
+
+        let mut words = [0; 44];
+        words[..4].copy_from_slice(&columns(KEY));
+        for i in 4..44 {
+            let mut word = words[i - 1];
+            if i % 4 == 0 {
+                let rotated = word.rotate_right(8);
+                word = ROUND_CONSTANTS[i / 4 - 1];
+                for byte in 0..4 {
+                    word = execute32(&mut hart, AES32ESI | byte << 30, word, rotated);
+                }
+            }
+            words[i] = words[i - 4] ^ word;
+        }
+
+        let mut state = columns(CIPHERTEXT);
+        for (column, key) in state.iter_mut().zip(&words[40..]) {
+            *column ^= key;
+        }
+        for round in (0..10).rev() {
+            let op = if round == 0 { AES32DSI } else { AES32DSMI };
+            state = std::array::from_fn(|column| {
+                // The equivalent inverse cipher applies InvMixColumns to the round keys, which is
+                // done by undoing AES32ESI's substitution with AES32DSMI
+                let key = words[round * 4 + column];
+                let key = match round {
+                    0 => key,
+                    _ => (0..4).fold(0, |word, byte| {
+                        let substituted = execute32(&mut hart, AES32ESI | byte << 30, 0, key);
+                        execute32(&mut hart, AES32DSMI | byte << 30, word, substituted)
+                    }),
+                };
+
+                // Row `byte` of the column comes from column `column - byte`, due to InvShiftRows
+                (0..4).fold(key, |word, byte| {
+                    let src = state[(column + 4 - byte as usize) % 4];
+                    execute32(&mut hart, op | byte << 30, word, src)
+                })
+            });
+        }
+
+        assert_eq!(state, columns(PLAINTEXT));
+    }
+
+    #[test]
+    fn decrypt_rv64() {
+        let mut hart = test_utils::hart(&[&RV64I, &Zkne, &Zknd]);
+        let [k0, k1, k2, k3] = columns(KEY);
+        let mut keys = [[0; 2]; 11];
+        keys[0] = [(k1 as u64) << 32 | k0 as u64, (k3 as u64) << 32 | k2 as u64];
+        for round in 0..10 {
+            let [low, high] = keys[round];
+            let temp = execute64(&mut hart, AES64KS1I | (round as u32) << 20, high, 0);
+            let low = execute64(&mut hart, AES64KS2, temp, low);
+            let high = execute64(&mut hart, AES64KS2, low, high);
+            keys[round + 1] = [low, high];
+        }
+
+        let [c0, c1, c2, c3] = columns(CIPHERTEXT);
+        let mut state = [
+            ((c1 as u64) << 32 | c0 as u64) ^ keys[10][0],
+            ((c3 as u64) << 32 | c2 as u64) ^ keys[10][1],
+        ];
+        for round in (0..10).rev() {
+            let (op, key) = match round {
+                0 => (AES64DS, keys[0]),
+                _ => (
+                    AES64DSM,
+                    keys[round].map(|key| execute64(&mut hart, AES64IM, key, 0)),
+                ),
+            };
+            let low = execute64(&mut hart, op, state[0], state[1]);
+            let high = execute64(&mut hart, op, state[1], state[0]);
+            state = [low ^ key[0], high ^ key[1]];
+        }
+
+        let [p0, p1, p2, p3] = columns(PLAINTEXT);
+        assert_eq!(
+            state,
+            [(p1 as u64) << 32 | p0 as u64, (p3 as u64) << 32 | p2 as u64]
+        );
+    }
+}
@@ -0,0 +1,136 @@
+//! AES primitives shared by the AES instructions.
+//!
+//! The S-boxes are computed at compile time from their definition, as the multiplicative inverse in
+//! GF(2^8) followed by an affine transformation, rather than being written out by hand.
+
+/// Multiply two elements of GF(2^8), modulo the AES polynomial x^8 + x^4 + x^3 + x + 1.
+const fn multiply(mut a: u8, mut b: u8) -> u8 {
+    let mut product = 0;
+    while b != 0 {
+        if b & 1 != 0 {
+            product ^= a;
+        }
+        a = (a << 1) ^ if a & 0x80 != 0 { 0x1b } else { 0 };
+        b >>= 1;
+    }
+    product
+}
+
+/// Compute the forward S-box.
+const fn forward_sbox() -> [u8; 256] {
+    let mut sbox = [0; 256];
+    let mut i = 0;
+    while i < 256 {
+        // The multiplicative inverse is x^254, & 0 maps to 0
+        let mut inverse = 1;
+        let mut j = 0;
+        while j < 254 {
+            inverse = multiply(inverse, i as u8);
+            j += 1;
+        }
+        if i == 0 {
+            inverse = 0;
+        }
+
+        sbox[i] = inverse
+            ^ inverse.rotate_left(1)
+            ^ inverse.rotate_left(2)
+            ^ inverse.rotate_left(3)
+            ^ inverse.rotate_left(4)
+            ^ 0x63;
+        i += 1;
+    }
+    sbox
+}
+
+/// Compute the inverse S-box, from the forward S-box.
+const fn inverse_sbox() -> [u8; 256] {
+    let mut sbox = [0; 256];
+    let mut i = 0;
+    while i < 256 {
+        sbox[FORWARD_SBOX[i] as usize] = i as u8;
+        i += 1;
+    }
+    sbox
+}
+
+const FORWARD_SBOX: [u8; 256] = forward_sbox();
+const INVERSE_SBOX: [u8; 256] = inverse_sbox();
+
+/// Direction of an AES operation.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub(crate) enum Direction {
+    Encrypt,
+    Decrypt,
+}
+
+impl Direction {
+    /// Apply this direction's S-box (SubBytes or InvSubBytes) to a byte.
+    pub(crate) fn sub_byte(&self, byte: u8) -> u8 {
+        match self {
+            Direction::Encrypt => FORWARD_SBOX[byte as usize],
+            Direction::Decrypt => INVERSE_SBOX[byte as usize],
+        }
+    }
+
+    /// Apply this direction's S-box to each byte of a word.
+    pub(crate) fn sub_word(&self, word: u32) -> u32 {
+        u32::from_le_bytes(word.to_le_bytes().map(|byte| self.sub_byte(byte)))
+    }
+
+    /// Apply this direction's MixColumns (or InvMixColumns) to a column of the state, stored with
+    /// row 0 in the least-significant byte.
+    pub(crate) fn mix_column(&self, column: u32) -> u32 {
+        let coefficients = match self {
+            Direction::Encrypt => [0x02, 0x03, 0x01, 0x01],
+            Direction::Decrypt => [0x0e, 0x0b, 0x0d, 0x09],
+        };
+        let column = column.to_le_bytes();
+        let mixed: [u8; 4] = std::array::from_fn(|row| {
+            (0..4).fold(0, |byte, i| {
+                byte ^ multiply(coefficients[(i + 4 - row) % 4], column[i])
+            })
+        });
+        u32::from_le_bytes(mixed)
+    }
+
+    /// Apply this direction's ShiftRows (or InvShiftRows) to the state held in the provided pair of
+    /// registers, and return the first two columns of the result.
+    ///
+    /// The first register holds columns 0 and 1 of the state, and the second holds columns 2 and 3,
+    /// each stored with row 0 in the least-significant byte.
+    pub(crate) fn shift_rows(&self, low: u64, high: u64) -> u64 {
+        let state = ((high as u128) << 64 | low as u128).to_le_bytes();
+        let shifted: [u8; 8] = std::array::from_fn(|i| {
+            let (row, column) = (i % 4, i / 4);
+            let column = match self {
+                Direction::Encrypt => (column + row) % 4,
+                Direction::Decrypt => (column + 4 - row) % 4,
+            };
+            state[row + 4 * column]
+        });
+        u64::from_le_bytes(shifted)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Direction, FORWARD_SBOX, INVERSE_SBOX};
+
+    #[test]
+    fn sbox() {
+        assert_eq!(FORWARD_SBOX[0x00], 0x63);
+        assert_eq!(FORWARD_SBOX[0x01], 0x7c);
+        assert_eq!(FORWARD_SBOX[0x53], 0xed);
+        assert_eq!(FORWARD_SBOX[0xff], 0x16);
+        assert_eq!(INVERSE_SBOX[0x63], 0x00);
+        assert_eq!(INVERSE_SBOX[0xed], 0x53);
+    }
+
+    #[test]
+    fn mix_column() {
+        assert_eq!(Direction::Encrypt.mix_column(0x4553_13db), 0xbca1_4d8e);
+        assert_eq!(Direction::Decrypt.mix_column(0xbca1_4d8e), 0x4553_13db);
+        assert_eq!(Direction::Encrypt.mix_column(0x0101_0101), 0x0101_0101);
+    }
+}
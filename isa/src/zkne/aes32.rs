@@ -0,0 +1,148 @@
+//! RV32 AES instructions (AES32ESI, AES32ESMI, AES32DSI, AES32DSMI).
+//!
+//! Each instruction applies the forward (ES) or inverse (DS) S-box to the byte of rs2 selected by
+//! the `bs` field, optionally applies the corresponding MixColumns to it in isolation (the "M"
+//! variants), then rotates it back into the byte's position and XORs it into rs1. A round of AES
+//! encryption or decryption takes 16 such instructions, with the final round skipping MixColumns.
+
+use super::aes::Direction;
+use z2l_core::error::ProcessorException;
+use z2l_core::instruction::{Instruction, InstructionResult, InstructionWordParts};
+use z2l_core::processor::register::RegisterFile;
+
+/// AES32ESI, AES32ESMI, AES32DSI, or AES32DSMI instruction.
+pub struct Aes32Instruction {
+    src1: u8,
+    src2: u8,
+    dest: u8,
+    direction: Direction,
+    mix: bool,
+    /// Index of the byte of rs2 to operate on.
+    byte: u8,
+}
+
+impl Aes32Instruction {
+    /// Create a new Aes32Instruction.
+    ///
+    /// The byte select is encoded in the top 2 bits of funct7, and the operation in the rest.
+    pub fn new(instruction: &InstructionWordParts) -> Result<Self, ProcessorException> {
+        let (direction, mix) = match instruction.funct7 & 0b11111 {
+            0b10001 => (Direction::Encrypt, false),
+            0b10011 => (Direction::Encrypt, true),
+            0b10101 => (Direction::Decrypt, false),
+            0b10111 => (Direction::Decrypt, true),
+            _ => return Err(ProcessorException::IllegalInstruction),
+        };
+        if instruction.funct3 != 0b000 {
+            return Err(ProcessorException::IllegalInstruction);
+        }
+
+        Ok(Self {
+            src1: instruction.rs1,
+            src2: instruction.rs2,
+            dest: instruction.rd,
+            direction,
+            mix,
+            byte: instruction.funct7 >> 5,
+        })
+    }
+}
+
+impl Instruction for Aes32Instruction {
+    fn execute(
+        &self,
+        registers: &mut RegisterFile,
+        _mem: i64,
+    ) -> Result<InstructionResult, ProcessorException> {
+        let src1 = registers
+            .get(&self.src1)
+            .ok_or(ProcessorException::IllegalInstruction)?
+            .load()?;
+        let src2 = registers
+            .get(&self.src2)
+            .ok_or(ProcessorException::IllegalInstruction)?
+            .load()?;
+
+        let shift = self.byte as u32 * 8;
+        let byte = self.direction.sub_byte((src2 >> shift) as u8) as u32;
+        let mixed = if self.mix {
+            self.direction.mix_column(byte)
+        } else {
+            byte
+        };
+        let result = src1 as u32 ^ mixed.rotate_left(shift);
+
+        let dest = registers
+            .get_mut(&self.dest)
+            .ok_or(ProcessorException::IllegalInstruction)?;
+        dest.store(result as i32 as i64)?;
+
+        Ok(InstructionResult::default())
+    }
+
+    fn format(&self) -> String {
+        let direction = match self.direction {
+            Direction::Encrypt => "e",
+            Direction::Decrypt => "d",
+        };
+        let mix = if self.mix { "m" } else { "" };
+        format!(
+            "aes32{}s{}i x{}, x{}, x{}, {}",
+            direction, mix, self.dest, self.src1, self.src2, self.byte
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::rv32i::RV32I;
+    use crate::test_utils::{execute, hart};
+    use crate::zknd::Zknd;
+    use crate::zkne::Zkne;
+
+    const AES32ESI: u32 = 0x2220_81b3; // aes32esi x3, x1, x2, 0
+    const AES32ESMI: u32 = 0x2620_81b3; // aes32esmi x3, x1, x2, 0
+    const AES32DSI: u32 = 0x2a20_81b3; // aes32dsi x3, x1, x2, 0
+    const AES32DSMI: u32 = 0x2e20_81b3; // aes32dsmi x3, x1, x2, 0
+
+    /// Encode the provided byte select into an instruction.
+    fn bs(raw: u32, byte: u32) -> u32 {
+        raw | byte << 30
+    }
+
+    #[test]
+    fn substitute() {
+        let mut hart = hart(&[&RV32I, &Zkne, &Zknd]);
+
+        // The S-box maps 0x00 to 0x63, & the selected byte stays in place
+        assert_eq!(execute(&mut hart, AES32ESI, 0, 0), 0x63);
+        assert_eq!(execute(&mut hart, bs(AES32ESI, 2), 0, 0), 0x0063_0000);
+        assert_eq!(execute(&mut hart, bs(AES32ESI, 1), 0, 0xff00), 0x1600);
+        assert_eq!(execute(&mut hart, bs(AES32DSI, 3), 0, 0x6300_0000), 0);
+        assert_eq!(execute(&mut hart, AES32DSI, 0, 0), 0x52);
+
+        // The other bytes of rs2 are ignored, & the result is XORed into rs1
+        assert_eq!(execute(&mut hart, AES32ESI, 0x63, -0x100), 0);
+        assert_eq!(
+            execute(&mut hart, bs(AES32ESI, 3), 0x8000_0000u32 as i32 as i64, 0),
+            0xe300_0000u32 as i32 as i64
+        );
+    }
+
+    #[test]
+    fn mix() {
+        let mut hart = hart(&[&RV32I, &Zkne, &Zknd]);
+
+        // MixColumns of the column [0x63, 0, 0, 0] is [0xc6, 0x63, 0x63, 0xa5], rotated into the
+        // selected byte's position
+        assert_eq!(
+            execute(&mut hart, AES32ESMI, 0, 0),
+            0xa563_63c6u32 as i32 as i64
+        );
+        assert_eq!(execute(&mut hart, bs(AES32ESMI, 1), 0, 0), 0x6363_c6a5);
+
+        // InvMixColumns of the column [0x52, 0, 0, 0] is [0x51, 0xf4, 0xa7, 0x50]
+        assert_eq!(execute(&mut hart, AES32DSMI, 0, 0), 0x50a7_f451);
+        assert_eq!(execute(&mut hart, bs(AES32DSMI, 3), 0, 0), 0x5150_a7f4);
+    }
+}
@@ -0,0 +1,317 @@
+//! RV64 AES instructions (AES64ES, AES64ESM, AES64DS, AES64DSM, AES64IM, AES64KS1I, AES64KS2).
+//!
+//! The 128-bit AES state is held in a pair of registers, with columns 0 and 1 in one and columns 2
+//! and 3 in the other. AES64ES and AES64DS apply ShiftRows and SubBytes (or their inverses) to the
+//! state in rs1 and rs2, producing half of the new state, and the "M" variants also apply
+//! MixColumns (or InvMixColumns). So a round takes two instructions, with the operands swapped for
+//! the second half.
+//!
+//! AES64KS1I and AES64KS2 compute the AES-128 and AES-256 key schedules, and AES64IM applies
+//! InvMixColumns to a round key, as needed for the equivalent inverse cipher used by AES64DSM.
+
+use super::aes::Direction;
+use z2l_core::error::ProcessorException;
+use z2l_core::instruction::{Instruction, InstructionResult, InstructionWordParts};
+use z2l_core::processor::register::RegisterFile;
+
+/// Round constants for the key schedule.
+const ROUND_CONSTANTS: [u8; 10] = [0x01, 0x02, 0x04, 0x08, 0x10, 0x20, 0x40, 0x80, 0x1b, 0x36];
+
+/// Load the value of the provided register.
+fn load(registers: &RegisterFile, reg: u8) -> Result<u64, ProcessorException> {
+    Ok(registers
+        .get(&reg)
+        .ok_or(ProcessorException::IllegalInstruction)?
+        .load()? as u64)
+}
+
+/// Store the provided value in the provided register.
+fn store(registers: &mut RegisterFile, reg: u8, value: u64) -> Result<(), ProcessorException> {
+    registers
+        .get_mut(&reg)
+        .ok_or(ProcessorException::IllegalInstruction)?
+        .store(value as i64)?;
+    Ok(())
+}
+
+/// Get the name of the provided direction, as used in the instruction mnemonics.
+fn mnemonic(direction: Direction) -> &'static str {
+    match direction {
+        Direction::Encrypt => "e",
+        Direction::Decrypt => "d",
+    }
+}
+
+/// AES64ES, AES64ESM, AES64DS, or AES64DSM instruction.
+pub struct Aes64Instruction {
+    src1: u8,
+    src2: u8,
+    dest: u8,
+    direction: Direction,
+    mix: bool,
+}
+
+impl Aes64Instruction {
+    /// Create a new Aes64Instruction.
+    pub fn new(instruction: &InstructionWordParts) -> Result<Self, ProcessorException> {
+        let (direction, mix) = match (instruction.funct7, instruction.funct3) {
+            (0b0011001, 0b000) => (Direction::Encrypt, false),
+            (0b0011011, 0b000) => (Direction::Encrypt, true),
+            (0b0011101, 0b000) => (Direction::Decrypt, false),
+            (0b0011111, 0b000) => (Direction::Decrypt, true),
+            _ => return Err(ProcessorException::IllegalInstruction),
+        };
+
+        Ok(Self {
+            src1: instruction.rs1,
+            src2: instruction.rs2,
+            dest: instruction.rd,
+            direction,
+            mix,
+        })
+    }
+}
+
+impl Instruction for Aes64Instruction {
+    fn execute(
+        &self,
+        registers: &mut RegisterFile,
+        _mem: i64,
+    ) -> Result<InstructionResult, ProcessorException> {
+        let src1 = load(registers, self.src1)?;
+        let src2 = load(registers, self.src2)?;
+
+        let direction = self.direction;
+        let shifted = direction.shift_rows(src1, src2);
+        let columns = [shifted as u32, (shifted >> 32) as u32].map(|column| {
+            let column = direction.sub_word(column);
+            if self.mix {
+                direction.mix_column(column)
+            } else {
+                column
+            }
+        });
+        let result = (columns[1] as u64) << 32 | columns[0] as u64;
+
+        store(registers, self.dest, result)?;
+        Ok(InstructionResult::default())
+    }
+
+    fn format(&self) -> String {
+        let mix = if self.mix { "m" } else { "" };
+        format!(
+            "aes64{}s{} x{}, x{}, x{}",
+            mnemonic(self.direction),
+            mix,
+            self.dest,
+            self.src1,
+            self.src2
+        )
+    }
+}
+
+/// AES64IM instruction.
+pub struct Aes64InverseMixInstruction {
+    src: u8,
+    dest: u8,
+}
+
+impl Aes64InverseMixInstruction {
+    /// Create a new Aes64InverseMixInstruction.
+    pub fn new(instruction: &InstructionWordParts) -> Result<Self, ProcessorException> {
+        if (instruction.funct3, instruction.imm_i & 0xfff) != (0b001, 0x300) {
+            return Err(ProcessorException::IllegalInstruction);
+        }
+
+        Ok(Self {
+            src: instruction.rs1,
+            dest: instruction.rd,
+        })
+    }
+}
+
+impl Instruction for Aes64InverseMixInstruction {
+    fn execute(
+        &self,
+        registers: &mut RegisterFile,
+        _mem: i64,
+    ) -> Result<InstructionResult, ProcessorException> {
+        let src = load(registers, self.src)?;
+
+        let low = Direction::Decrypt.mix_column(src as u32);
+        let high = Direction::Decrypt.mix_column((src >> 32) as u32);
+        let result = (high as u64) << 32 | low as u64;
+
+        store(registers, self.dest, result)?;
+        Ok(InstructionResult::default())
+    }
+
+    fn format(&self) -> String {
+        format!("aes64im x{}, x{}", self.dest, self.src)
+    }
+}
+
+/// Step of the key schedule to compute.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+enum KeySchedule {
+    /// AES64KS1I, with the provided round number.
+    First(u8),
+    /// AES64KS2.
+    Second(u8),
+}
+
+/// AES64KS1I or AES64KS2 instruction.
+pub struct Aes64KeyScheduleInstruction {
+    src1: u8,
+    dest: u8,
+    step: KeySchedule,
+}
+
+impl Aes64KeyScheduleInstruction {
+    /// Create a new AES64KS1I instruction.
+    ///
+    /// Raises an IllegalInstruction exception if the round number is greater than 0xA.
+    pub fn new_first(instruction: &InstructionWordParts) -> Result<Self, ProcessorException> {
+        let imm = instruction.imm_i & 0xfff;
+        let round = (imm & 0xf) as u8;
+        if instruction.funct3 != 0b001 || imm >> 4 != 0x31 || round > 0xa {
+            return Err(ProcessorException::IllegalInstruction);
+        }
+
+        Ok(Self {
+            src1: instruction.rs1,
+            dest: instruction.rd,
+            step: KeySchedule::First(round),
+        })
+    }
+
+    /// Create a new AES64KS2 instruction.
+    pub fn new_second(instruction: &InstructionWordParts) -> Result<Self, ProcessorException> {
+        if (instruction.funct7, instruction.funct3) != (0b0111111, 0b000) {
+            return Err(ProcessorException::IllegalInstruction);
+        }
+
+        Ok(Self {
+            src1: instruction.rs1,
+            dest: instruction.rd,
+            step: KeySchedule::Second(instruction.rs2),
+        })
+    }
+}
+
+impl Instruction for Aes64KeyScheduleInstruction {
+    fn execute(
+        &self,
+        registers: &mut RegisterFile,
+        _mem: i64,
+    ) -> Result<InstructionResult, ProcessorException> {
+        let src1 = load(registers, self.src1)?;
+
+        let result = match self.step {
+            KeySchedule::First(round) => {
+                // Round 0xA is used for AES-256, which substitutes the word without rotating it,
+                // & has no round constant
+                let word = (src1 >> 32) as u32;
+                let (word, constant) = match round {
+                    0xa => (word, 0),
+                    round => (word.rotate_right(8), ROUND_CONSTANTS[round as usize]),
+                };
+                let word = Direction::Encrypt.sub_word(word) ^ constant as u32;
+                (word as u64) << 32 | word as u64
+            }
+            KeySchedule::Second(src2) => {
+                let src2 = load(registers, src2)?;
+                let low = (src1 >> 32) ^ (src2 & 0xffff_ffff);
+                let high = low ^ (src2 >> 32);
+                high << 32 | low
+            }
+        };
+
+        store(registers, self.dest, result)?;
+        Ok(InstructionResult::default())
+    }
+
+    fn format(&self) -> String {
+        match self.step {
+            KeySchedule::First(round) => {
+                format!("aes64ks1i x{}, x{}, {}", self.dest, self.src1, round)
+            }
+            KeySchedule::Second(src2) => {
+                format!("aes64ks2 x{}, x{}, x{}", self.dest, self.src1, src2)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::rv64i::RV64I;
+    use crate::test_utils::{execute, hart};
+    use crate::zknd::Zknd;
+    use crate::zkne::Zkne;
+
+    const AES64ES: u32 = 0x3220_81b3; // aes64es x3, x1, x2
+    const AES64ESM: u32 = 0x3620_81b3; // aes64esm x3, x1, x2
+    const AES64DS: u32 = 0x3a20_81b3; // aes64ds x3, x1, x2
+    const AES64IM: u32 = 0x3000_9193; // aes64im x3, x1
+    const AES64KS1I: u32 = 0x3100_9193; // aes64ks1i x3, x1, 0
+    const AES64KS2: u32 = 0x7e20_81b3; // aes64ks2 x3, x1, x2
+
+    #[test]
+    fn round() {
+        let mut hart = hart(&[&RV64I, &Zkne, &Zknd]);
+        let sub = 0x6363_6363_6363_6363;
+
+        assert_eq!(execute(&mut hart, AES64ES, 0, 0), sub);
+        assert_eq!(execute(&mut hart, AES64DS, sub, sub), 0);
+        // MixColumns leaves a column of equal bytes unchanged
+        assert_eq!(execute(&mut hart, AES64ESM, 0, 0), sub);
+
+        // ShiftRows moves row 1 of column 1 into column 0, & row 1 of column 0 comes from column
+        // 1 of the second half of the state
+        assert_eq!(execute(&mut hart, AES64ES, 0xff << 40, 0), sub ^ 0x75 << 8);
+        assert_eq!(execute(&mut hart, AES64ES, 0, 0xff << 8), sub ^ 0x75 << 40);
+    }
+
+    #[test]
+    fn inverse_mix() {
+        let mut hart = hart(&[&RV64I, &Zknd]);
+
+        // MixColumns test vectors, with the columns stored in the low & high words
+        assert_eq!(
+            execute(&mut hart, AES64IM, 0x9d58_dc9f_bca1_4d8eu64 as i64, 0),
+            0x5c22_0af2_4553_13db
+        );
+        assert_eq!(execute(&mut hart, AES64IM, -1, 0), -1);
+    }
+
+    #[test]
+    fn key_schedule() {
+        let mut hart = hart(&[&RV64I, &Zkne]);
+
+        // AES64KS1I only reads the high word, rotating it & adding the round constant
+        assert_eq!(
+            execute(&mut hart, AES64KS1I, 0xffff_ffff, 0),
+            0x6363_6362_6363_6362
+        );
+        assert_eq!(
+            execute(&mut hart, AES64KS1I | 9 << 20, 0xff << 32, 0),
+            0x1663_6355_1663_6355
+        );
+        // Round 0xA doesn't rotate the word, or add a round constant
+        assert_eq!(
+            execute(&mut hart, AES64KS1I | 0xa << 20, 0xff << 32, 0),
+            0x6363_6316_6363_6316
+        );
+
+        assert_eq!(
+            execute(
+                &mut hart,
+                AES64KS2,
+                0x1111_1111_0000_0000,
+                0x2222_2222_3333_3333
+            ),
+            0x2222_2222
+        );
+    }
+}
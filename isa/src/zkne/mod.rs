@@ -0,0 +1,198 @@
+//! The "Zkne" standard extension for NIST suite AES encryption.
+//!
+//! On RV32, Zkne adds AES32ESI and AES32ESMI, which each compute a quarter of a column of an AES
+//! encryption round. On RV64, it adds AES64ES and AES64ESM, which each compute half of a round, and
+//! AES64KS1I and AES64KS2 for the key schedule. The instructions share the OP and OP-IMM opcodes
+//! with the base integer instruction set, so are registered as [`SubDecoder`]s on those opcodes.
+//!
+//! The instructions are also used by the Zknd extension, for decryption.
+
+mod aes;
+mod aes32;
+mod aes64;
+
+pub use aes32::Aes32Instruction;
+pub use aes64::{Aes64Instruction, Aes64InverseMixInstruction, Aes64KeyScheduleInstruction};
+
+use z2l_core::error::ProcessorException;
+use z2l_core::extension::{Extension, SubDecoder};
+use z2l_core::instruction::{Instruction, InstructionWordParts};
+use z2l_core::processor::hart::Hart;
+use z2l_core::processor::Xlen;
+
+/// An [`Extension`] defining the Zkne standard extension.
+pub struct Zkne;
+
+impl Extension for Zkne {
+    fn code(&self) -> &'static str {
+        "Zkne"
+    }
+
+    fn name(&self) -> &'static str {
+        "Standard Extension for NIST Suite: AES Encryption"
+    }
+
+    fn register(&self, hart: &mut Hart) {
+        for opcode in [0x13, 0x33] {
+            hart.register_sub_decoder(opcode, Box::new(ZkneDecoder));
+        }
+    }
+}
+
+/// Decoder for the Zkne extension's instructions, in the OP and (on RV64) OP-IMM opcodes.
+pub struct ZkneDecoder;
+
+impl SubDecoder for ZkneDecoder {
+    fn decode(
+        &self,
+        instruction: &InstructionWordParts,
+        _pc: u64,
+        xlen: Xlen,
+    ) -> Result<Option<Box<dyn Instruction>>, ProcessorException> {
+        let funct7 = instruction.funct7;
+        let imm = instruction.imm_i & 0xfff;
+
+        Ok(Some(match (instruction.opcode, instruction.funct3, xlen) {
+            (0x33, 0b000, Xlen::Rv32) if matches!(funct7 & 0b11111, 0b10001 | 0b10011) => {
+                Box::new(Aes32Instruction::new(instruction)?)
+            }
+            (0x33, 0b000, Xlen::Rv64) if matches!(funct7, 0b0011001 | 0b0011011) => {
+                Box::new(Aes64Instruction::new(instruction)?)
+            }
+            (0x33, 0b000, Xlen::Rv64) if funct7 == 0b0111111 => {
+                Box::new(Aes64KeyScheduleInstruction::new_second(instruction)?)
+            }
+            (0x13, 0b001, Xlen::Rv64) if imm >> 4 == 0x31 => {
+                Box::new(Aes64KeyScheduleInstruction::new_first(instruction)?)
+            }
+            _ => return Ok(None),
+        }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Zkne;
+    use crate::rv32i::RV32I;
+    use crate::rv64i::RV64I;
+    use crate::test_utils::{self, decode, execute32, execute64};
+    use z2l_core::instruction::InstructionParts;
+
+    const AES32ESI: u32 = 0x2220_81b3; // aes32esi x3, x1, x2, 0
+    const AES32ESMI: u32 = 0x2620_81b3; // aes32esmi x3, x1, x2, 0
+    const AES64ES: u32 = 0x3220_81b3; // aes64es x3, x1, x2
+    const AES64ESM: u32 = 0x3620_81b3; // aes64esm x3, x1, x2
+    const AES64KS1I: u32 = 0x3100_9193; // aes64ks1i x3, x1, 0
+    const AES64KS2: u32 = 0x7e20_81b3; // aes64ks2 x3, x1, x2
+
+    /// Round constants for the AES-128 key schedule.
+    const ROUND_CONSTANTS: [u32; 10] = [0x01, 0x02, 0x04, 0x08, 0x10, 0x20, 0x40, 0x80, 0x1b, 0x36];
+
+    // FIPS-197 Appendix C.1 (AES-128)
+    const KEY: u128 = 0x000102030405060708090a0b0c0d0e0f;
+    const PLAINTEXT: u128 = 0x00112233445566778899aabbccddeeff;
+    const CIPHERTEXT: u128 = 0x69c4e0d86a7b0430d8cdb78070b4c55a;
+
+    /// Split a block into columns, in the order they're stored in memory.
+    fn columns(block: u128) -> [u32; 4] {
+        let bytes = block.to_be_bytes();
+        std::array::from_fn(|i| u32::from_le_bytes(bytes[i * 4..i * 4 + 4].try_into().unwrap()))
+    }
+
+    #[test]
+    fn decode_instructions() {
+        let rv32 = test_utils::hart(&[&RV32I, &Zkne]);
+        assert_eq!(decode(&rv32, AES32ESI), "aes32esi x3, x1, x2, 0");
+        assert_eq!(
+            decode(&rv32, AES32ESMI | 3 << 30),
+            "aes32esmi x3, x1, x2, 3"
+        );
+        assert_eq!(decode(&rv32, 0x0073_02b3), "add x5, x6, x7");
+
+        let rv64 = test_utils::hart(&[&RV64I, &Zkne]);
+        assert_eq!(decode(&rv64, AES64ES), "aes64es x3, x1, x2");
+        assert_eq!(decode(&rv64, AES64ESM), "aes64esm x3, x1, x2");
+        assert_eq!(decode(&rv64, AES64KS1I | 0xa << 20), "aes64ks1i x3, x1, 10");
+        assert_eq!(decode(&rv64, AES64KS2), "aes64ks2 x3, x1, x2");
+        assert_eq!(decode(&rv64, 0x0031_1093), "slli x1, x2, 3");
+
+        // Round numbers above 0xA are reserved
+        let parts = InstructionParts::new(AES64KS1I | 0xb << 20).unwrap();
+        assert!(rv64
+            .opcodes
+            .get(&0x13)
+            .unwrap()
+            .decode(parts, 0, rv64.xlen)
+            .is_err());
+    }
+
+    #[test]
+    fn encrypt_rv32() {
+        let mut hart = test_utils::hart(&[&RV32I, &Zkne]);
+
+        // Expand the key, computing SubWord(RotWord(w)) with AES32ESI
+        let mut words = [0; 44];
+        words[..4].copy_from_slice(&columns(KEY));
+        for i in 4..44 {
+            let mut word = words[i - 1];
+            if i % 4 == 0 {
+                let rotated = word.rotate_right(8);
+                word = ROUND_CONSTANTS[i / 4 - 1];
+                for byte in 0..4 {
+                    word = execute32(&mut hart, AES32ESI | byte << 30, word, rotated);
+                }
+            }
+            words[i] = words[i - 4] ^ word;
+        }
+
+        let mut state = columns(PLAINTEXT);
+        for (column, key) in state.iter_mut().zip(&words[..4]) {
+            *column ^= key;
+        }
+        for round in 1..=10 {
+            let op = if round == 10 { AES32ESI } else { AES32ESMI };
+            state = std::array::from_fn(|column| {
+                // Row `byte` of the column comes from column `column + byte`, due to ShiftRows
+                (0..4).fold(words[round * 4 + column], |word, byte| {
+                    let src = state[(column + byte as usize) % 4];
+                    execute32(&mut hart, op | byte << 30, word, src)
+                })
+            });
+        }
+
+        assert_eq!(state, columns(CIPHERTEXT));
+    }
+
+    #[test]
+    fn encrypt_rv64() {
+        let mut hart = test_utils::hart(&[&RV64I, &Zkne]);
+        let [k0, k1, k2, k3] = columns(KEY);
+        let mut keys = [[0; 2]; 11];
+        keys[0] = [(k1 as u64) << 32 | k0 as u64, (k3 as u64) << 32 | k2 as u64];
+        for round in 0..10 {
+            let [low, high] = keys[round];
+            let temp = execute64(&mut hart, AES64KS1I | (round as u32) << 20, high, 0);
+            let low = execute64(&mut hart, AES64KS2, temp, low);
+            let high = execute64(&mut hart, AES64KS2, low, high);
+            keys[round + 1] = [low, high];
+        }
+
+        let [p0, p1, p2, p3] = columns(PLAINTEXT);
+        let mut state = [
+            ((p1 as u64) << 32 | p0 as u64) ^ keys[0][0],
+            ((p3 as u64) << 32 | p2 as u64) ^ keys[0][1],
+        ];
+        for (round, key) in keys.iter().enumerate().skip(1) {
+            let op = if round == 10 { AES64ES } else { AES64ESM };
+            let low = execute64(&mut hart, op, state[0], state[1]);
+            let high = execute64(&mut hart, op, state[1], state[0]);
+            state = [low ^ key[0], high ^ key[1]];
+        }
+
+        let [c0, c1, c2, c3] = columns(CIPHERTEXT);
+        assert_eq!(
+            state,
+            [(c1 as u64) << 32 | c0 as u64, (c3 as u64) << 32 | c2 as u64]
+        );
+    }
+}
@@ -0,0 +1,598 @@
+//! The "Zknh" standard extension for NIST suite hash function instructions.
+//!
+//! Zknh adds instructions computing the sigma (σ) and sum (Σ) functions of SHA-256 and SHA-512,
+//! which are built from rotations, shifts, and XORs of a single message or state word. On RV64, the
+//! SHA-512 functions operate on a single register, but on RV32, each 64-bit word is held in a pair
+//! of registers, and separate instructions compute the high and low halves of each function from
+//! both halves of the word.
+//!
+//! The instructions share the OP-IMM (and on RV32, OP) opcodes with the base integer instruction
+//! set, so are registered as [`SubDecoder`]s on those opcodes.
+
+use std::fmt;
+use z2l_core::error::ProcessorException;
+use z2l_core::extension::{Extension, SubDecoder};
+use z2l_core::instruction::{Instruction, InstructionResult, InstructionWordParts};
+use z2l_core::processor::hart::Hart;
+use z2l_core::processor::register::RegisterFile;
+use z2l_core::processor::Xlen;
+
+/// An [`Extension`] defining the Zknh standard extension.
+pub struct Zknh;
+
+impl Extension for Zknh {
+    fn code(&self) -> &'static str {
+        "Zknh"
+    }
+
+    fn name(&self) -> &'static str {
+        "Standard Extension for NIST Suite: Hash Function Instructions"
+    }
+
+    fn register(&self, hart: &mut Hart) {
+        for opcode in [0x13, 0x33] {
+            hart.register_sub_decoder(opcode, Box::new(ZknhDecoder));
+        }
+    }
+}
+
+/// Decoder for the Zknh extension's instructions, in the OP-IMM and (on RV32) OP opcodes.
+pub struct ZknhDecoder;
+
+impl SubDecoder for ZknhDecoder {
+    fn decode(
+        &self,
+        instruction: &InstructionWordParts,
+        _pc: u64,
+        xlen: Xlen,
+    ) -> Result<Option<Box<dyn Instruction>>, ProcessorException> {
+        let imm = instruction.imm_i & 0xfff;
+
+        Ok(Some(match (instruction.opcode, instruction.funct3, xlen) {
+            (0x13, 0b001, _) if matches!(imm, 0x100..=0x103) => {
+                Box::new(ShaInstruction::new(instruction, xlen)?)
+            }
+            (0x13, 0b001, Xlen::Rv64) if matches!(imm, 0x104..=0x107) => {
+                Box::new(ShaInstruction::new(instruction, xlen)?)
+            }
+            (0x33, 0b000, Xlen::Rv32) if matches!(instruction.funct7, 0b0101000..=0b0101111) => {
+                Box::new(Sha512PairInstruction::new(instruction)?)
+            }
+            _ => return Ok(None),
+        }))
+    }
+}
+
+/// Hash function to compute.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+enum Function {
+    Sha256Sig0,
+    Sha256Sig1,
+    Sha256Sum0,
+    Sha256Sum1,
+    Sha512Sig0,
+    Sha512Sig1,
+    Sha512Sum0,
+    Sha512Sum1,
+}
+
+impl Function {
+    /// Compute the function of the provided word. SHA-256 functions use only the lower 32 bits of
+    /// the word, and zero-extend their result.
+    fn apply(&self, x: u64) -> u64 {
+        let w = x as u32;
+        match self {
+            Function::Sha256Sig0 => (w.rotate_right(7) ^ w.rotate_right(18) ^ (w >> 3)) as u64,
+            Function::Sha256Sig1 => (w.rotate_right(17) ^ w.rotate_right(19) ^ (w >> 10)) as u64,
+            Function::Sha256Sum0 => {
+                (w.rotate_right(2) ^ w.rotate_right(13) ^ w.rotate_right(22)) as u64
+            }
+            Function::Sha256Sum1 => {
+                (w.rotate_right(6) ^ w.rotate_right(11) ^ w.rotate_right(25)) as u64
+            }
+            Function::Sha512Sig0 => x.rotate_right(1) ^ x.rotate_right(8) ^ (x >> 7),
+            Function::Sha512Sig1 => x.rotate_right(19) ^ x.rotate_right(61) ^ (x >> 6),
+            Function::Sha512Sum0 => x.rotate_right(28) ^ x.rotate_right(34) ^ x.rotate_right(39),
+            Function::Sha512Sum1 => x.rotate_right(14) ^ x.rotate_right(18) ^ x.rotate_right(41),
+        }
+    }
+}
+
+impl fmt::Display for Function {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Function::Sha256Sig0 => f.write_str("sha256sig0"),
+            Function::Sha256Sig1 => f.write_str("sha256sig1"),
+            Function::Sha256Sum0 => f.write_str("sha256sum0"),
+            Function::Sha256Sum1 => f.write_str("sha256sum1"),
+            Function::Sha512Sig0 => f.write_str("sha512sig0"),
+            Function::Sha512Sig1 => f.write_str("sha512sig1"),
+            Function::Sha512Sum0 => f.write_str("sha512sum0"),
+            Function::Sha512Sum1 => f.write_str("sha512sum1"),
+        }
+    }
+}
+
+/// SHA256SIG0, SHA256SIG1, SHA256SUM0, or SHA256SUM1 instruction, or on RV64, SHA512SIG0,
+/// SHA512SIG1, SHA512SUM0, or SHA512SUM1 instruction.
+pub struct ShaInstruction {
+    src: u8,
+    dest: u8,
+    function: Function,
+}
+
+impl ShaInstruction {
+    /// Create a new ShaInstruction, for a hart with the provided XLEN.
+    pub fn new(instruction: &InstructionWordParts, xlen: Xlen) -> Result<Self, ProcessorException> {
+        let function = match (instruction.funct3, instruction.imm_i & 0xfff, xlen) {
+            (0b001, 0x102, _) => Function::Sha256Sig0,
+            (0b001, 0x103, _) => Function::Sha256Sig1,
+            (0b001, 0x100, _) => Function::Sha256Sum0,
+            (0b001, 0x101, _) => Function::Sha256Sum1,
+            (0b001, 0x106, Xlen::Rv64) => Function::Sha512Sig0,
+            (0b001, 0x107, Xlen::Rv64) => Function::Sha512Sig1,
+            (0b001, 0x104, Xlen::Rv64) => Function::Sha512Sum0,
+            (0b001, 0x105, Xlen::Rv64) => Function::Sha512Sum1,
+            _ => return Err(ProcessorException::IllegalInstruction),
+        };
+
+        Ok(Self {
+            src: instruction.rs1,
+            dest: instruction.rd,
+            function,
+        })
+    }
+}
+
+impl Instruction for ShaInstruction {
+    fn execute(
+        &self,
+        registers: &mut RegisterFile,
+        _mem: i64,
+    ) -> Result<InstructionResult, ProcessorException> {
+        let src = registers
+            .get(&self.src)
+            .ok_or(ProcessorException::IllegalInstruction)?
+            .load()?;
+
+        let result = self.function.apply(src as u64);
+        // SHA-256 results are sign-extended from 32 bits, even on RV64
+        let result = match self.function {
+            Function::Sha256Sig0
+            | Function::Sha256Sig1
+            | Function::Sha256Sum0
+            | Function::Sha256Sum1 => result as i32 as i64,
+            _ => result as i64,
+        };
+
+        let dest = registers
+            .get_mut(&self.dest)
+            .ok_or(ProcessorException::IllegalInstruction)?;
+        dest.store(result)?;
+
+        Ok(InstructionResult::default())
+    }
+
+    fn format(&self) -> String {
+        format!("{} x{}, x{}", self.function, self.dest, self.src)
+    }
+}
+
+/// Half of a SHA-512 function computed by an RV32 instruction.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+enum Half {
+    /// The high half, with rs1 holding the high half of the word (SHA512SIG0H, SHA512SIG1H).
+    High,
+    /// The low half, with rs1 holding the low half of the word (SHA512SIG0L, SHA512SIG1L,
+    /// SHA512SUM0R, SHA512SUM1R).
+    Low,
+}
+
+/// SHA512SIG0H, SHA512SIG0L, SHA512SIG1H, SHA512SIG1L, SHA512SUM0R, or SHA512SUM1R instruction.
+///
+/// As the sum functions only rotate their operand, their high half is the low half of the function
+/// applied to the word with its halves swapped, so SHA512SUM0R and SHA512SUM1R compute either half
+/// depending on the order of the operands.
+pub struct Sha512PairInstruction {
+    src1: u8,
+    src2: u8,
+    dest: u8,
+    function: Function,
+    half: Half,
+}
+
+impl Sha512PairInstruction {
+    /// Create a new Sha512PairInstruction.
+    pub fn new(instruction: &InstructionWordParts) -> Result<Self, ProcessorException> {
+        let (function, half) = match (instruction.funct7, instruction.funct3) {
+            (0b0101000, 0b000) => (Function::Sha512Sum0, Half::Low),
+            (0b0101001, 0b000) => (Function::Sha512Sum1, Half::Low),
+            (0b0101010, 0b000) => (Function::Sha512Sig0, Half::Low),
+            (0b0101011, 0b000) => (Function::Sha512Sig1, Half::Low),
+            (0b0101110, 0b000) => (Function::Sha512Sig0, Half::High),
+            (0b0101111, 0b000) => (Function::Sha512Sig1, Half::High),
+            _ => return Err(ProcessorException::IllegalInstruction),
+        };
+
+        Ok(Self {
+            src1: instruction.rs1,
+            src2: instruction.rs2,
+            dest: instruction.rd,
+            function,
+            half,
+        })
+    }
+}
+
+impl Instruction for Sha512PairInstruction {
+    fn execute(
+        &self,
+        registers: &mut RegisterFile,
+        _mem: i64,
+    ) -> Result<InstructionResult, ProcessorException> {
+        let src1 = registers
+            .get(&self.src1)
+            .ok_or(ProcessorException::IllegalInstruction)?
+            .load()? as u32 as u64;
+        let src2 = registers
+            .get(&self.src2)
+            .ok_or(ProcessorException::IllegalInstruction)?
+            .load()? as u32 as u64;
+
+        let result = match self.half {
+            Half::High => self.function.apply(src1 << 32 | src2) >> 32,
+            Half::Low => self.function.apply(src2 << 32 | src1),
+        };
+
+        let dest = registers
+            .get_mut(&self.dest)
+            .ok_or(ProcessorException::IllegalInstruction)?;
+        dest.store(result as i32 as i64)?;
+
+        Ok(InstructionResult::default())
+    }
+
+    fn format(&self) -> String {
+        let suffix = match (self.function, self.half) {
+            (Function::Sha512Sum0 | Function::Sha512Sum1, _) => "r",
+            (_, Half::High) => "h",
+            (_, Half::Low) => "l",
+        };
+        format!(
+            "{}{} x{}, x{}, x{}",
+            self.function, suffix, self.dest, self.src1, self.src2
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Zknh;
+    use crate::rv32i::RV32I;
+    use crate::rv64i::RV64I;
+    use crate::test_utils::{self, decode, execute};
+    use z2l_core::extension::Extension;
+
+    const SHA256SIG0: u32 = 0x1020_9193; // sha256sig0 x3, x1
+    const SHA256SIG1: u32 = 0x1030_9193; // sha256sig1 x3, x1
+    const SHA256SUM0: u32 = 0x1000_9193; // sha256sum0 x3, x1
+    const SHA256SUM1: u32 = 0x1010_9193; // sha256sum1 x3, x1
+    const SHA512SIG0: u32 = 0x1060_9193; // sha512sig0 x3, x1
+    const SHA512SIG1: u32 = 0x1070_9193; // sha512sig1 x3, x1
+    const SHA512SUM0: u32 = 0x1040_9193; // sha512sum0 x3, x1
+    const SHA512SUM1: u32 = 0x1050_9193; // sha512sum1 x3, x1
+    const SHA512SUM0R: u32 = 0x5020_81b3; // sha512sum0r x3, x1, x2
+    const SHA512SUM1R: u32 = 0x5220_81b3; // sha512sum1r x3, x1, x2
+    const SHA512SIG0L: u32 = 0x5420_81b3; // sha512sig0l x3, x1, x2
+    const SHA512SIG1L: u32 = 0x5620_81b3; // sha512sig1l x3, x1, x2
+    const SHA512SIG0H: u32 = 0x5c20_81b3; // sha512sig0h x3, x1, x2
+    const SHA512SIG1H: u32 = 0x5e20_81b3; // sha512sig1h x3, x1, x2
+
+    /// SHA-256 round constants.
+    const K256: [u32; 64] = [
+        0x428a_2f98,
+        0x7137_4491,
+        0xb5c0_fbcf,
+        0xe9b5_dba5,
+        0x3956_c25b,
+        0x59f1_11f1,
+        0x923f_82a4,
+        0xab1c_5ed5,
+        0xd807_aa98,
+        0x1283_5b01,
+        0x2431_85be,
+        0x550c_7dc3,
+        0x72be_5d74,
+        0x80de_b1fe,
+        0x9bdc_06a7,
+        0xc19b_f174,
+        0xe49b_69c1,
+        0xefbe_4786,
+        0x0fc1_9dc6,
+        0x240c_a1cc,
+        0x2de9_2c6f,
+        0x4a74_84aa,
+        0x5cb0_a9dc,
+        0x76f9_88da,
+        0x983e_5152,
+        0xa831_c66d,
+        0xb003_27c8,
+        0xbf59_7fc7,
+        0xc6e0_0bf3,
+        0xd5a7_9147,
+        0x06ca_6351,
+        0x1429_2967,
+        0x27b7_0a85,
+        0x2e1b_2138,
+        0x4d2c_6dfc,
+        0x5338_0d13,
+        0x650a_7354,
+        0x766a_0abb,
+        0x81c2_c92e,
+        0x9272_2c85,
+        0xa2bf_e8a1,
+        0xa81a_664b,
+        0xc24b_8b70,
+        0xc76c_51a3,
+        0xd192_e819,
+        0xd699_0624,
+        0xf40e_3585,
+        0x106a_a070,
+        0x19a4_c116,
+        0x1e37_6c08,
+        0x2748_774c,
+        0x34b0_bcb5,
+        0x391c_0cb3,
+        0x4ed8_aa4a,
+        0x5b9c_ca4f,
+        0x682e_6ff3,
+        0x748f_82ee,
+        0x78a5_636f,
+        0x84c8_7814,
+        0x8cc7_0208,
+        0x90be_fffa,
+        0xa450_6ceb,
+        0xbef9_a3f7,
+        0xc671_78f2,
+    ];
+
+    /// SHA-512 round constants.
+    const K512: [u64; 80] = [
+        0x428a_2f98_d728_ae22,
+        0x7137_4491_23ef_65cd,
+        0xb5c0_fbcf_ec4d_3b2f,
+        0xe9b5_dba5_8189_dbbc,
+        0x3956_c25b_f348_b538,
+        0x59f1_11f1_b605_d019,
+        0x923f_82a4_af19_4f9b,
+        0xab1c_5ed5_da6d_8118,
+        0xd807_aa98_a303_0242,
+        0x1283_5b01_4570_6fbe,
+        0x2431_85be_4ee4_b28c,
+        0x550c_7dc3_d5ff_b4e2,
+        0x72be_5d74_f27b_896f,
+        0x80de_b1fe_3b16_96b1,
+        0x9bdc_06a7_25c7_1235,
+        0xc19b_f174_cf69_2694,
+        0xe49b_69c1_9ef1_4ad2,
+        0xefbe_4786_384f_25e3,
+        0x0fc1_9dc6_8b8c_d5b5,
+        0x240c_a1cc_77ac_9c65,
+        0x2de9_2c6f_592b_0275,
+        0x4a74_84aa_6ea6_e483,
+        0x5cb0_a9dc_bd41_fbd4,
+        0x76f9_88da_8311_53b5,
+        0x983e_5152_ee66_dfab,
+        0xa831_c66d_2db4_3210,
+        0xb003_27c8_98fb_213f,
+        0xbf59_7fc7_beef_0ee4,
+        0xc6e0_0bf3_3da8_8fc2,
+        0xd5a7_9147_930a_a725,
+        0x06ca_6351_e003_826f,
+        0x1429_2967_0a0e_6e70,
+        0x27b7_0a85_46d2_2ffc,
+        0x2e1b_2138_5c26_c926,
+        0x4d2c_6dfc_5ac4_2aed,
+        0x5338_0d13_9d95_b3df,
+        0x650a_7354_8baf_63de,
+        0x766a_0abb_3c77_b2a8,
+        0x81c2_c92e_47ed_aee6,
+        0x9272_2c85_1482_353b,
+        0xa2bf_e8a1_4cf1_0364,
+        0xa81a_664b_bc42_3001,
+        0xc24b_8b70_d0f8_9791,
+        0xc76c_51a3_0654_be30,
+        0xd192_e819_d6ef_5218,
+        0xd699_0624_5565_a910,
+        0xf40e_3585_5771_202a,
+        0x106a_a070_32bb_d1b8,
+        0x19a4_c116_b8d2_d0c8,
+        0x1e37_6c08_5141_ab53,
+        0x2748_774c_df8e_eb99,
+        0x34b0_bcb5_e19b_48a8,
+        0x391c_0cb3_c5c9_5a63,
+        0x4ed8_aa4a_e341_8acb,
+        0x5b9c_ca4f_7763_e373,
+        0x682e_6ff3_d6b2_b8a3,
+        0x748f_82ee_5def_b2fc,
+        0x78a5_636f_4317_2f60,
+        0x84c8_7814_a1f0_ab72,
+        0x8cc7_0208_1a64_39ec,
+        0x90be_fffa_2363_1e28,
+        0xa450_6ceb_de82_bde9,
+        0xbef9_a3f7_b2c6_7915,
+        0xc671_78f2_e372_532b,
+        0xca27_3ece_ea26_619c,
+        0xd186_b8c7_21c0_c207,
+        0xeada_7dd6_cde0_eb1e,
+        0xf57d_4f7f_ee6e_d178,
+        0x06f0_67aa_7217_6fba,
+        0x0a63_7dc5_a2c8_98a6,
+        0x113f_9804_bef9_0dae,
+        0x1b71_0b35_131c_471b,
+        0x28db_77f5_2304_7d84,
+        0x32ca_ab7b_40c7_2493,
+        0x3c9e_be0a_15c9_bebc,
+        0x431d_67c4_9c10_0d4c,
+        0x4cc5_d4be_cb3e_42b6,
+        0x597f_299c_fc65_7e2a,
+        0x5fcb_6fab_3ad6_faec,
+        0x6c44_198c_4a47_5817,
+    ];
+
+    /// Compute the SHA-256 hash of "abc", which fits in a single block, using the provided
+    /// function to compute σ0, σ1, Σ0, and Σ1 (in that order) of a word.
+    fn sha256_abc(mut f: impl FnMut(usize, u32) -> u32) -> [u32; 8] {
+        let mut w = [0; 64];
+        w[0] = 0x6162_6380;
+        w[15] = 24;
+        for i in 16..64 {
+            w[i] = f(1, w[i - 2])
+                .wrapping_add(w[i - 7])
+                .wrapping_add(f(0, w[i - 15]))
+                .wrapping_add(w[i - 16]);
+        }
+
+        let initial = [
+            0x6a09_e667,
+            0xbb67_ae85,
+            0x3c6e_f372,
+            0xa54f_f53a,
+            0x510e_527f,
+            0x9b05_688c,
+            0x1f83_d9ab,
+            0x5be0_cd19,
+        ];
+        let mut s: [u32; 8] = initial;
+        for i in 0..64 {
+            let [a, b, c, d, e, f_, g, h] = s;
+            let t1 = h
+                .wrapping_add(f(3, e))
+                .wrapping_add((e & f_) ^ (!e & g))
+                .wrapping_add(K256[i])
+                .wrapping_add(w[i]);
+            let t2 = f(2, a).wrapping_add((a & b) ^ (a & c) ^ (b & c));
+            s = [t1.wrapping_add(t2), a, b, c, d.wrapping_add(t1), e, f_, g];
+        }
+        std::array::from_fn(|i| initial[i].wrapping_add(s[i]))
+    }
+
+    /// Compute the SHA-512 hash of "abc", which fits in a single block, using the provided
+    /// function to compute σ0, σ1, Σ0, and Σ1 (in that order) of a word.
+    fn sha512_abc(mut f: impl FnMut(usize, u64) -> u64) -> [u64; 8] {
+        let mut w = [0; 80];
+        w[0] = 0x6162_6380_0000_0000;
+        w[15] = 24;
+        for i in 16..80 {
+            w[i] = f(1, w[i - 2])
+                .wrapping_add(w[i - 7])
+                .wrapping_add(f(0, w[i - 15]))
+                .wrapping_add(w[i - 16]);
+        }
+
+        let initial = [
+            0x6a09_e667_f3bc_c908,
+            0xbb67_ae85_84ca_a73b,
+            0x3c6e_f372_fe94_f82b,
+            0xa54f_f53a_5f1d_36f1,
+            0x510e_527f_ade6_82d1,
+            0x9b05_688c_2b3e_6c1f,
+            0x1f83_d9ab_fb41_bd6b,
+            0x5be0_cd19_137e_2179,
+        ];
+        let mut s: [u64; 8] = initial;
+        for i in 0..80 {
+            let [a, b, c, d, e, f_, g, h] = s;
+            let t1 = h
+                .wrapping_add(f(3, e))
+                .wrapping_add((e & f_) ^ (!e & g))
+                .wrapping_add(K512[i])
+                .wrapping_add(w[i]);
+            let t2 = f(2, a).wrapping_add((a & b) ^ (a & c) ^ (b & c));
+            s = [t1.wrapping_add(t2), a, b, c, d.wrapping_add(t1), e, f_, g];
+        }
+        std::array::from_fn(|i| initial[i].wrapping_add(s[i]))
+    }
+
+    const SHA256_ABC: [u32; 8] = [
+        0xba78_16bf,
+        0x8f01_cfea,
+        0x4141_40de,
+        0x5dae_2223,
+        0xb003_61a3,
+        0x9617_7a9c,
+        0xb410_ff61,
+        0xf200_15ad,
+    ];
+
+    const SHA512_ABC: [u64; 8] = [
+        0xddaf_35a1_9361_7aba,
+        0xcc41_7349_ae20_4131,
+        0x12e6_fa4e_89a9_7ea2,
+        0x0a9e_eee6_4b55_d39a,
+        0x2192_992a_274f_c1a8,
+        0x36ba_3c23_a3fe_ebbd,
+        0x454d_4423_643c_e80e,
+        0x2a9a_c94f_a54c_a49f,
+    ];
+
+    #[test]
+    fn decode_instructions() {
+        let rv32 = test_utils::hart(&[&RV32I, &Zknh]);
+        assert_eq!(decode(&rv32, SHA256SIG0), "sha256sig0 x3, x1");
+        assert_eq!(decode(&rv32, SHA256SUM1), "sha256sum1 x3, x1");
+        assert_eq!(decode(&rv32, SHA512SUM0R), "sha512sum0r x3, x1, x2");
+        assert_eq!(decode(&rv32, SHA512SIG0L), "sha512sig0l x3, x1, x2");
+        assert_eq!(decode(&rv32, SHA512SIG1H), "sha512sig1h x3, x1, x2");
+        assert_eq!(decode(&rv32, 0x0073_02b3), "add x5, x6, x7");
+        assert_eq!(decode(&rv32, 0x0031_1093), "slli x1, x2, 3");
+
+        let rv64 = test_utils::hart(&[&RV64I, &Zknh]);
+        assert_eq!(decode(&rv64, SHA256SIG1), "sha256sig1 x3, x1");
+        assert_eq!(decode(&rv64, SHA512SUM1), "sha512sum1 x3, x1");
+    }
+
+    #[test]
+    fn sha256() {
+        for base in [&RV32I as &dyn Extension, &RV64I] {
+            let mut hart = test_utils::hart(&[base, &Zknh]);
+            let functions = [SHA256SIG0, SHA256SIG1, SHA256SUM0, SHA256SUM1];
+            let hash = sha256_abc(|function, x| {
+                let result = execute(&mut hart, functions[function], x as i32 as i64, 0);
+                assert_eq!(result, result as i32 as i64, "Result is sign-extended");
+                result as u32
+            });
+            assert_eq!(hash, SHA256_ABC);
+        }
+    }
+
+    #[test]
+    fn sha512_rv64() {
+        let mut hart = test_utils::hart(&[&RV64I, &Zknh]);
+        let functions = [SHA512SIG0, SHA512SIG1, SHA512SUM0, SHA512SUM1];
+        let hash =
+            sha512_abc(|function, x| execute(&mut hart, functions[function], x as i64, 0) as u64);
+        assert_eq!(hash, SHA512_ABC);
+    }
+
+    #[test]
+    fn sha512_rv32() {
+        let mut hart = test_utils::hart(&[&RV32I, &Zknh]);
+        // Instructions computing the high & low halves of each function, each passed the
+        // corresponding half of the word in rs1, & the other half in rs2
+        let functions = [
+            (SHA512SIG0H, SHA512SIG0L),
+            (SHA512SIG1H, SHA512SIG1L),
+            (SHA512SUM0R, SHA512SUM0R),
+            (SHA512SUM1R, SHA512SUM1R),
+        ];
+        let hash = sha512_abc(|function, x| {
+            let (high, low) = functions[function];
+            let (x_high, x_low) = ((x >> 32) as i32 as i64, x as i32 as i64);
+            let high = execute(&mut hart, high, x_high, x_low) as u32;
+            let low = execute(&mut hart, low, x_low, x_high) as u32;
+            (high as u64) << 32 | low as u64
+        });
+        assert_eq!(hash, SHA512_ABC);
+    }
+}
@@ -0,0 +1,214 @@
+//! The "Zkr" standard extension for an entropy source.
+//!
+//! Zkr adds the `seed` CSR, which provides access to a physical entropy source. `seed` must be
+//! accessed with a read-write CSR instruction (e.g. `csrrw rd, seed, x0`): Read-only accesses raise
+//! an illegal instruction exception, and the written value is ignored. Each access polls the
+//! entropy source, returning its status in the `OPST` field (bits [31:30]), and if the status is
+//! `ES16`, 16 bits of entropy in bits [15:0]:
+//!
+//! | `OPST` | Status | Meaning                                                   |
+//! |--------|--------|-----------------------------------------------------------|
+//! | `00`   | `BIST` | The source is running its self test: Entropy will follow. |
+//! | `01`   | `WAIT` | No entropy is available yet: Poll again later.            |
+//! | `10`   | `ES16` | Bits [15:0] contain 16 bits of entropy.                   |
+//! | `11`   | `DEAD` | The source has failed unrecoverably.                      |
+//!
+//! Machine mode can always access `seed`. Supervisor and user mode can only access `seed` if the
+//! `SSEED` or `USEED` bits of the `mseccfg` CSR are set, respectively: This extension registers
+//! `mseccfg` (and on RV32, `mseccfgh`) if no other extension has done so already.
+//!
+//! The entropy source itself is pluggable: See [`z2l_core::entropy`] for the available sources.
+
+use std::sync::{Arc, Mutex};
+use z2l_core::entropy::{EntropySource, EntropyStatus};
+use z2l_core::extension::Extension;
+use z2l_core::processor::csr::{Csr, MSECCFG, MSECCFGH};
+use z2l_core::processor::hart::Hart;
+use z2l_core::processor::{PrivilegeLevel, Xlen};
+
+/// Seed for cryptographic random bit generators.
+pub const SEED: u16 = 0x015;
+
+/// Bit of `mseccfg` which allows user mode to access `seed`.
+pub const MSECCFG_USEED: u64 = 1 << 8;
+
+/// Bit of `mseccfg` which allows supervisor mode to access `seed`.
+pub const MSECCFG_SSEED: u64 = 1 << 9;
+
+/// An [`Extension`] defining the Zkr standard extension.
+///
+/// All harts share the same entropy source.
+pub struct Zkr {
+    source: Arc<Mutex<Box<dyn EntropySource>>>,
+}
+
+impl Zkr {
+    /// Create a new Zkr extension, drawing entropy from the provided source.
+    pub fn new(source: Box<dyn EntropySource>) -> Self {
+        Self {
+            source: Arc::new(Mutex::new(source)),
+        }
+    }
+}
+
+impl Extension for Zkr {
+    fn code(&self) -> &'static str {
+        "Zkr"
+    }
+
+    fn name(&self) -> &'static str {
+        "Standard Extension for Entropy Source"
+    }
+
+    fn register(&self, hart: &mut Hart) {
+        if hart.csrs.get(MSECCFG).is_none() {
+            hart.csrs.register(MSECCFG, Csr::new(0).with_write_mask(0));
+            if hart.xlen == Xlen::Rv32 {
+                hart.csrs
+                    .register(MSECCFGH, Csr::new(0).with_write_mask(0).rv32_only());
+            }
+        }
+        hart.csrs.get_mut(MSECCFG).unwrap().write_mask |= MSECCFG_USEED | MSECCFG_SSEED;
+
+        let source = self.source.clone();
+        hart.csrs.register(
+            SEED,
+            Csr::new(0)
+                .with_write_mask(0)
+                .on_access(|csrs, privilege, write| {
+                    let mseccfg = csrs.get(MSECCFG).map_or(0, |csr| csr.value);
+                    write
+                        && match privilege {
+                            PrivilegeLevel::Machine => true,
+                            PrivilegeLevel::Supervisor => mseccfg & MSECCFG_SSEED != 0,
+                            PrivilegeLevel::User => mseccfg & MSECCFG_USEED != 0,
+                            PrivilegeLevel::Hypervisor => false,
+                        }
+                })
+                .on_read(move |_, _| seed(source.lock().unwrap().poll())),
+        );
+    }
+}
+
+/// Encode the status of an entropy source as the value read from `seed`.
+fn seed(status: EntropyStatus) -> u64 {
+    match status {
+        EntropyStatus::Bist => 0b00 << 30,
+        EntropyStatus::Wait => 0b01 << 30,
+        EntropyStatus::Es16(entropy) => (0b10 << 30) | entropy as u64,
+        EntropyStatus::Dead => 0b11 << 30,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Zkr, MSECCFG, MSECCFGH};
+    use crate::rv32i::RV32I;
+    use crate::rv64i::RV64I;
+    use crate::zicsr::Zicsr;
+    use z2l_core::entropy::{EntropySource, EntropyStatus, SeededEntropySource};
+    use z2l_core::error::ProcessorException;
+    use z2l_core::extension::Extension;
+    use z2l_core::processor::hart::Hart;
+    use z2l_core::processor::PrivilegeLevel;
+
+    const READ_SEED: u32 = 0x0150_12f3; // csrrw x5, seed, x0
+    const READ_SEED_ONLY: u32 = 0x0150_22f3; // csrrs x5, seed, x0
+    const WRITE_MSECCFG: u32 = 0x7473_1073; // csrrw x0, mseccfg, x6
+
+    /// Create a hart with the provided base instruction set, Zicsr, and Zkr, using the provided
+    /// entropy source.
+    fn hart(base: &dyn Extension, source: impl EntropySource) -> Hart {
+        let mut hart = Hart::new();
+        base.register(&mut hart);
+        Zicsr.register(&mut hart);
+        Zkr::new(Box::new(source)).register(&mut hart);
+        hart
+    }
+
+    /// Execute a single instruction, returning the value of x5.
+    fn run(hart: &mut Hart, raw: u32) -> Result<i64, (ProcessorException, u64)> {
+        hart.cycle(Ok(raw), 0, &[])?;
+        hart.cycle(Ok(0x0000_0013), 0, &[])?;
+        Ok(hart.registers.get(&5).unwrap().load().unwrap())
+    }
+
+    #[test]
+    fn registers_csrs() {
+        let rv32 = hart(&RV32I, SeededEntropySource::new(0));
+        assert!(rv32.csrs.get(MSECCFG).is_some());
+        assert!(rv32.csrs.get(MSECCFGH).is_some());
+
+        let rv64 = hart(&RV64I, SeededEntropySource::new(0));
+        assert!(rv64.csrs.get(MSECCFG).is_some());
+        assert!(rv64.csrs.get(MSECCFGH).is_none());
+
+        // mseccfgh is removed if Zkr is registered before the base instruction set
+        let mut rv64 = Hart::new();
+        Zkr::new(Box::new(SeededEntropySource::new(0))).register(&mut rv64);
+        RV64I.register(&mut rv64);
+        assert!(rv64.csrs.get(MSECCFG).is_some());
+        assert!(rv64.csrs.get(MSECCFGH).is_none());
+    }
+
+    #[test]
+    fn deterministic_entropy() {
+        let mut expected = SeededEntropySource::new(42);
+        let mut rv32 = hart(&RV32I, SeededEntropySource::new(42));
+        let mut rv64 = hart(&RV64I, SeededEntropySource::new(42));
+
+        for _ in 0..8 {
+            let EntropyStatus::Es16(entropy) = expected.poll() else {
+                panic!("Expected entropy");
+            };
+            let value = 0x8000_0000 | entropy as i64;
+            assert_eq!(run(&mut rv32, READ_SEED), Ok(value as i32 as i64));
+            assert_eq!(run(&mut rv64, READ_SEED), Ok(value));
+        }
+    }
+
+    #[test]
+    fn status() {
+        let source = SeededEntropySource::new(0).with_self_test(1).with_wait(1);
+        let mut hart = hart(&RV64I, source);
+
+        let opst = |value: i64| value >> 30;
+        assert_eq!(run(&mut hart, READ_SEED).map(opst), Ok(0b00));
+        assert_eq!(run(&mut hart, READ_SEED).map(opst), Ok(0b01));
+        assert_eq!(run(&mut hart, READ_SEED).map(opst), Ok(0b10));
+        assert_eq!(run(&mut hart, READ_SEED).map(opst), Ok(0b01));
+    }
+
+    #[test]
+    fn read_only_access() {
+        let mut hart = hart(&RV32I, SeededEntropySource::new(0));
+        assert_eq!(
+            run(&mut hart, READ_SEED_ONLY),
+            Err((ProcessorException::IllegalInstruction, 0))
+        );
+    }
+
+    #[test]
+    fn mseccfg_gating() {
+        for (privilege, bit) in [
+            (PrivilegeLevel::User, 1 << 8),
+            (PrivilegeLevel::Supervisor, 1 << 9),
+        ] {
+            let mut hart = hart(&RV64I, SeededEntropySource::new(0));
+            hart.privilege = privilege;
+            assert_eq!(
+                run(&mut hart, READ_SEED),
+                Err((ProcessorException::IllegalInstruction, 0))
+            );
+
+            // Grant access from machine mode
+            hart.privilege = PrivilegeLevel::Machine;
+            hart.registers.get_mut(&6).unwrap().store(bit).unwrap();
+            run(&mut hart, WRITE_MSECCFG).unwrap();
+            assert_eq!(hart.csrs.get(MSECCFG).unwrap().value, bit as u64);
+
+            hart.privilege = privilege;
+            assert!(run(&mut hart, READ_SEED).is_ok());
+        }
+    }
+}
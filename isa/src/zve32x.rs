@@ -0,0 +1,469 @@
+//! The "Zve32x" standard extension for embedded vector processors with 32-bit integer elements.
+//!
+//! Zve32x is the smallest of the vector extension profiles: It supports elements of 8, 16, and 32
+//! bits (ELEN = 32), with integer arithmetic, mask, permutation, and reduction instructions, and
+//! unit-stride, strided, indexed, segment & whole register loads and stores. The vector register
+//! width (VLEN) is a parameter of the hart. The instructions themselves are described in the
+//! [`v`](crate::v) module.
+
+use crate::v::{self, AgnosticPolicy};
+use z2l_core::extension::Extension;
+use z2l_core::processor::hart::Hart;
+
+/// Width of the widest supported element, in bits.
+const ELEN: u32 = 32;
+
+/// An [`Extension`] defining the Zve32x standard extension.
+pub struct Zve32x {
+    vlen: usize,
+    policy: AgnosticPolicy,
+}
+
+impl Zve32x {
+    /// Create a new Zve32x extension, with vector registers of `vlen` bits.
+    ///
+    /// Agnostic elements are overwritten with all ones.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `vlen` is not a power of 2 between 32 and 65536.
+    pub fn new(vlen: usize) -> Self {
+        assert!(
+            vlen.is_power_of_two() && (ELEN as usize..=65536).contains(&vlen),
+            "VLEN must be a power of 2 between 32 and 65536"
+        );
+        Self {
+            vlen,
+            policy: AgnosticPolicy::default(),
+        }
+    }
+
+    /// Set how agnostic elements are written.
+    pub fn with_agnostic_policy(mut self, policy: AgnosticPolicy) -> Self {
+        self.policy = policy;
+        self
+    }
+}
+
+impl Extension for Zve32x {
+    fn code(&self) -> &'static str {
+        "Zve32x"
+    }
+
+    fn name(&self) -> &'static str {
+        "Standard Extension for Embedded Vector Processors with 32-bit Integer Elements"
+    }
+
+    fn register(&self, hart: &mut Hart) {
+        v::register(hart, ELEN, self.vlen, self.policy);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Zve32x;
+    use crate::m::M;
+    use crate::rv32i::RV32I;
+    use crate::rv64i::RV64I;
+    use crate::test_utils::{self, reg};
+    use crate::v::AgnosticPolicy;
+    use crate::zicsr::Zicsr;
+    use z2l_core::error::ProcessorException;
+    use z2l_core::extension::Extension;
+    use z2l_core::processor::{PrivilegeLevel, Processor};
+
+    /// Create a processor with the provided base & vector extensions, with a ROM at 0 containing
+    /// the provided program, and 0x100 bytes of RAM at 0x80000000 initialised with `data`.
+    fn processor(
+        base: Box<dyn Extension>,
+        vector: Zve32x,
+        program: &[u32],
+        data: &[u8],
+    ) -> Processor {
+        let extensions = vec![base, Box::new(M), Box::new(Zicsr), Box::new(vector)];
+        let processor = test_utils::processor(extensions, program);
+        processor
+            .mmu
+            .lock()
+            .unwrap()
+            .store_bytes(0x8000_0000, data)
+            .unwrap();
+        processor
+    }
+
+    /// Run the processor until it reaches the final `jal x0, 0` of its program.
+    fn run(processor: &mut Processor) -> Result<(), (ProcessorException, u64)> {
+        for _ in 0..200 {
+            processor.cycle()?;
+        }
+        Ok(())
+    }
+
+    /// Read `len` bytes of RAM, from offset `offset`.
+    fn ram(processor: &Processor, offset: usize, len: usize) -> Vec<u8> {
+        processor
+            .mmu
+            .lock()
+            .unwrap()
+            .load_bytes(0x8000_0000 + offset, len)
+            .unwrap()
+    }
+
+    fn words(bytes: &[u8]) -> Vec<u32> {
+        bytes
+            .chunks(4)
+            .map(|w| u32::from_le_bytes(w.try_into().unwrap()))
+            .collect()
+    }
+
+    #[test]
+    fn configuration() {
+        let program = [
+            0x00a0_0113, // addi x2, x0, 10
+            0x0d00_70d7, // vsetvli x1, x0, e32, m1, ta, ma
+            0x0011_71d7, // vsetvli x3, x2, e8, m2, tu, mu
+            0xccf1_f257, // vsetivli x4, 3, e16, mf2, ta, ma
+            0x0c80_7057, // vsetvli x0, x0, e16, m1, ta, ma
+            0xc200_22f3, // csrrs x5, vl, x0
+            0xc210_2373, // csrrs x6, vtype, x0
+            0xc220_23f3, // csrrs x7, vlenb, x0
+            0x0d71_7457, // vsetvli x8, x2, e32, mf2, ta, ma
+            0xc210_24f3, // csrrs x9, vtype, x0
+            0x0221_80d7, // vadd.vv v1, v2, v3
+        ];
+
+        for (base, vill) in [
+            (Box::new(RV32I) as Box<dyn Extension>, i32::MIN as i64),
+            (Box::new(RV64I), i64::MIN),
+        ] {
+            let mut processor = processor(base, Zve32x::new(128), &program, &[]);
+            assert_eq!(
+                run(&mut processor),
+                Err((ProcessorException::IllegalInstruction, 40))
+            );
+
+            assert_eq!(reg(&processor, 1), 4);
+            assert_eq!(reg(&processor, 3), 10);
+            assert_eq!(reg(&processor, 4), 3);
+            // Keeping vl is allowed, as the ratio of SEW to LMUL is unchanged
+            assert_eq!(reg(&processor, 5), 3);
+            assert_eq!(reg(&processor, 6), 0xc8);
+            assert_eq!(reg(&processor, 7), 16);
+            // SEW = 32 with LMUL = 1/2 is not supported with ELEN = 32
+            assert_eq!(reg(&processor, 8), 0);
+            assert_eq!(reg(&processor, 9), vill);
+        }
+    }
+
+    #[test]
+    fn unit_stride() {
+        let data = [1u32, 2, 3, 0xffff_ffff, 10, 20, 30, 40]
+            .iter()
+            .flat_map(|w| w.to_le_bytes())
+            .collect::<Vec<_>>();
+        let program = [
+            0x0010_0513, // addi x10, x0, 1
+            0x01f5_1513, // slli x10, x10, 31
+            0xcd02_7057, // vsetivli x0, 4, e32, m1, ta, ma
+            0x0205_6087, // vle32.v v1, (x10)
+            0x0105_0593, // addi x11, x10, 16
+            0x0205_e107, // vle32.v v2, (x11)
+            0x0211_01d7, // vadd.vv v3, v1, v2
+            0x0030_0613, // addi x12, x0, 3
+            0x9636_6257, // vmul.vx v4, v3, x12
+            0x0e40_32d7, // vrsub.vi v5, v4, 0
+            0x0205_0693, // addi x13, x10, 32
+            0x0206_e227, // vse32.v v4, (x13)
+            0x0305_0713, // addi x14, x10, 48
+            0x0207_62a7, // vse32.v v5, (x14)
+            0x0140_0793, // addi x15, x0, 20
+            0x0c17_f057, // vsetvli x0, x15, e8, m2, ta, ma
+            0x0205_0307, // vle8.v v6, (x10)
+            0x0260_b457, // vadd.vi v8, v6, 1
+            0x0405_0813, // addi x16, x10, 64
+            0x0208_0427, // vse8.v v8, (x16)
+            0x0000_006f, // jal x0, 0
+        ];
+
+        for base in [Box::new(RV32I) as Box<dyn Extension>, Box::new(RV64I)] {
+            let mut processor = processor(base, Zve32x::new(128), &program, &data);
+            run(&mut processor).unwrap();
+
+            let sums = [11u32, 22, 33, 39];
+            let products = sums.map(|s| s.wrapping_mul(3));
+            assert_eq!(words(&ram(&processor, 32, 16)), products);
+            assert_eq!(
+                words(&ram(&processor, 48, 16)),
+                products.map(u32::wrapping_neg)
+            );
+
+            // The byte elements span a group of 2 registers
+            let incremented = data[..20].iter().map(|b| b.wrapping_add(1));
+            assert_eq!(ram(&processor, 64, 20), incremented.collect::<Vec<_>>());
+            assert_eq!(ram(&processor, 84, 4), [0; 4]);
+        }
+    }
+
+    #[test]
+    fn strided_indexed_segment() {
+        let mut data = (0..32).collect::<Vec<u8>>();
+        data.extend([8, 4, 12, 0]);
+        let program = [
+            0x0010_0513, // addi x10, x0, 1
+            0x01f5_1513, // slli x10, x10, 31
+            0xcc82_7057, // vsetivli x0, 4, e16, m1, ta, ma
+            0x0060_0593, // addi x11, x0, 6
+            0x0ab5_5087, // vlse16.v v1, (x10), x11
+            0x0405_0613, // addi x12, x10, 64
+            0x0206_50a7, // vse16.v v1, (x12)
+            0xcc02_7057, // vsetivli x0, 4, e8, m1, ta, ma
+            0x0205_0693, // addi x13, x10, 32
+            0x0206_8107, // vle8.v v2, (x13)
+            0xcd02_7057, // vsetivli x0, 4, e32, m1, ta, ma
+            0x0625_0187, // vluxei8.v v3, (x10), v2
+            0x0505_0713, // addi x14, x10, 80
+            0x0207_61a7, // vse32.v v3, (x14)
+            0x0605_0793, // addi x15, x10, 96
+            0x0e27_81a7, // vsoxei8.v v3, (x15), v2
+            0xcc02_7057, // vsetivli x0, 4, e8, m1, ta, ma
+            0x2205_0207, // vlseg2e8.v v4, (x10)
+            0x0705_0813, // addi x16, x10, 112
+            0x0208_0227, // vse8.v v4, (x16)
+            0x0745_0893, // addi x17, x10, 116
+            0x0208_82a7, // vse8.v v5, (x17)
+            0x0000_006f, // jal x0, 0
+        ];
+
+        let mut processor = processor(Box::new(RV64I), Zve32x::new(128), &program, &data);
+        run(&mut processor).unwrap();
+
+        assert_eq!(ram(&processor, 64, 8), [0, 1, 6, 7, 12, 13, 18, 19]);
+        let gathered = [8, 4, 12, 0].map(|o| words(&data[o..o + 4])[0]);
+        assert_eq!(words(&ram(&processor, 80, 16)), gathered);
+        // Scattering the gathered words back to the same offsets restores the original order
+        assert_eq!(ram(&processor, 96, 16), &data[..16]);
+        assert_eq!(ram(&processor, 112, 8), [0, 2, 4, 6, 1, 3, 5, 7]);
+    }
+
+    #[test]
+    fn masking_policies() {
+        let program = [
+            0x0010_0513, // addi x10, x0, 1
+            0x01f5_1513, // slli x10, x10, 31
+            0xc008_7057, // vsetivli x0, 16, e8, m1, tu, mu
+            0x0550_0593, // addi x11, x0, 0x55
+            0x5e05_c257, // vmv.v.x v4, x11
+            0x5e05_c2d7, // vmv.v.x v5, x11
+            0x5208_a0d7, // vid.v v1
+            0x2610_b1d7, // vand.vi v3, v1, 1
+            0x6230_b057, // vmseq.vi v0, v3, 1
+            0xcc03_7057, // vsetivli x0, 6, e8, m1, ta, ma
+            0x0010_8257, // vadd.vv v4, v1, v1, v0.t
+            0xc003_7057, // vsetivli x0, 6, e8, m1, tu, mu
+            0x0010_82d7, // vadd.vv v5, v1, v1, v0.t
+            0xc008_7057, // vsetivli x0, 16, e8, m1, tu, mu
+            0x5c1f_b357, // vmerge.vim v6, v1, -1, v0
+            0x0205_0227, // vse8.v v4, (x10)
+            0x0105_0613, // addi x12, x10, 16
+            0x0206_02a7, // vse8.v v5, (x12)
+            0x0205_0693, // addi x13, x10, 32
+            0x0206_8327, // vse8.v v6, (x13)
+            0x0000_006f, // jal x0, 0
+        ];
+
+        let undisturbed = (0..16)
+            .map(|i| if i < 6 && i % 2 == 1 { i * 2 } else { 0x55 })
+            .collect::<Vec<u8>>();
+        for (policy, agnostic) in [
+            (AgnosticPolicy::Ones, 0xff),
+            (AgnosticPolicy::Undisturbed, 0x55),
+        ] {
+            let vector = Zve32x::new(128).with_agnostic_policy(policy);
+            let mut processor = processor(Box::new(RV64I), vector, &program, &[]);
+            run(&mut processor).unwrap();
+
+            let expected = undisturbed
+                .iter()
+                .map(|&b| if b == 0x55 { agnostic } else { b })
+                .collect::<Vec<_>>();
+            assert_eq!(ram(&processor, 0, 16), expected, "{:?}", policy);
+            assert_eq!(ram(&processor, 16, 16), undisturbed, "{:?}", policy);
+            let merged = (0..16).map(|i| if i % 2 == 1 { 0xff } else { i });
+            assert_eq!(ram(&processor, 32, 16), merged.collect::<Vec<u8>>());
+        }
+    }
+
+    #[test]
+    fn dot_product() {
+        let a: [i16; 10] = [1, -2, 3, 4, -5, 6, 7, 8, 9, -10];
+        let b: [i16; 10] = [300, 2, -3, 1000, 5, 6, -7, 8, 9, 32767];
+        let mut data = a.iter().flat_map(|x| x.to_le_bytes()).collect::<Vec<_>>();
+        data.resize(32, 0);
+        data.extend(b.iter().flat_map(|x| x.to_le_bytes()));
+
+        // Strip-mined loop, accumulating widened products in a group of 2 registers
+        let program = [
+            0x0010_0513, // addi x10, x0, 1
+            0x01f5_1513, // slli x10, x10, 31
+            0x0205_0593, // addi x11, x10, 32
+            0x00a0_0613, // addi x12, x0, 10
+            0x0d10_76d7, // vsetvli x13, x0, e32, m2, ta, ma
+            0x5e00_3257, // vmv.v.i v4, 0
+            0x0886_76d7, // loop: vsetvli x13, x12, e16, m1, tu, ma
+            0x0205_5087, // vle16.v v1, (x10)
+            0x0205_d107, // vle16.v v2, (x11)
+            0xf620_a257, // vwmacc.vv v4, v1, v2
+            0x40d6_0633, // sub x12, x12, x13
+            0x0016_9713, // slli x14, x13, 1
+            0x00e5_0533, // add x10, x10, x14
+            0x00e5_85b3, // add x11, x11, x14
+            0xfe06_10e3, // bne x12, x0, loop
+            0x0d10_76d7, // vsetvli x13, x0, e32, m2, ta, ma
+            0x4200_6457, // vmv.s.x v8, x0
+            0x0244_2457, // vredsum.vs v8, v4, v8
+            0x4280_27d7, // vmv.x.s x15, v8
+            0x0000_006f, // jal x0, 0
+        ];
+
+        for base in [Box::new(RV32I) as Box<dyn Extension>, Box::new(RV64I)] {
+            let mut processor = processor(base, Zve32x::new(64), &program, &data);
+            run(&mut processor).unwrap();
+
+            let expected = a.iter().zip(b).map(|(&a, b)| a as i64 * b as i64);
+            assert_eq!(reg(&processor, 15), expected.sum::<i64>());
+        }
+    }
+
+    #[test]
+    fn memory_faults() {
+        let program = [
+            0x0010_0513, // addi x10, x0, 1
+            0x01f5_1513, // slli x10, x10, 31
+            0x0200_0593, // addi x11, x0, 32
+            0x3055_9073, // csrrw x0, mtvec, x11
+            0xcd02_7057, // vsetivli x0, 4, e32, m1, ta, ma
+            0x5208_a0d7, // vid.v v1
+            0x0f85_0613, // addi x12, x10, 248
+            0x0206_6087, // vle32.v v1, (x12)
+            0x0205_60a7, // handler: vse32.v v1, (x10)
+            0x3410_26f3, // csrrs x13, mepc, x0
+            0x0080_2773, // csrrs x14, vstart, x0
+            0x03c0_0593, // addi x11, x0, 60
+            0x3055_9073, // csrrw x0, mtvec, x11
+            0x0210_b0d7, // vadd.vi v1, v1, 1
+            0x0206_60a7, // vse32.v v1, (x12)
+            0x3410_27f3, // handler: csrrs x15, mepc, x0
+            0x0000_006f, // jal x0, 0
+        ];
+
+        let mut processor = processor(Box::new(RV64I), Zve32x::new(128), &program, &[]);
+        run(&mut processor).unwrap();
+
+        // The third element of the load is outside RAM: No element is written
+        assert_eq!(reg(&processor, 13), 28);
+        assert_eq!(reg(&processor, 14), 0);
+        assert_eq!(words(&ram(&processor, 0, 16)), [0, 1, 2, 3]);
+
+        // The elements stored before the faulting element remain in memory
+        assert_eq!(reg(&processor, 15), 56);
+        assert_eq!(words(&ram(&processor, 248, 8)), [1, 2]);
+    }
+
+    #[test]
+    fn permutations() {
+        let program = [
+            0x0010_0513, // addi x10, x0, 1
+            0x01f5_1513, // slli x10, x10, 31
+            0xcc84_7057, // vsetivli x0, 8, e16, m1, ta, ma
+            0x5208_a0d7, // vid.v v1
+            0x0210_b0d7, // vadd.vi v1, v1, 1
+            0x0020_0593, // addi x11, x0, 2
+            0x3a15_c457, // vslideup.vx v8, v1, x11
+            0x3e11_b4d7, // vslidedown.vi v9, v1, 3
+            0x0640_0613, // addi x12, x0, 100
+            0x3a16_6557, // vslide1up.vx v10, v1, x12
+            0x3e16_65d7, // vslide1down.vx v11, v1, x12
+            0x5208_a357, // vid.v v6
+            0x0e63_b357, // vrsub.vi v6, v6, 7
+            0x3213_0657, // vrgather.vv v12, v1, v6
+            0x7a12_3057, // vmsgtu.vi v0, v1, 4
+            0x5e10_26d7, // vcompress.vm v13, v1, v0
+            0x4208_26d7, // vcpop.m x13, v0
+            0x4208_a757, // vfirst.m x14, v0
+            0x5208_2757, // viota.m v14, v0
+            0x5200_a7d7, // vmsbf.m v15, v0
+            0x1a16_2857, // vredmaxu.vs v16, v1, v12
+            0x4300_27d7, // vmv.x.s x15, v16
+            0xe285_0427, // vs8r.v v8, (x10)
+            0x2285_5807, // vl2re16.v v16, (x10)
+            0x9f00_b957, // vmv2r.v v18, v16
+            0x0805_0813, // addi x16, x10, 128
+            0x2288_0927, // vs2r.v v18, (x16)
+            0x0000_006f, // jal x0, 0
+        ];
+
+        let mut processor = processor(Box::new(RV64I), Zve32x::new(128), &program, &[]);
+        run(&mut processor).unwrap();
+
+        assert_eq!(reg(&processor, 13), 4);
+        assert_eq!(reg(&processor, 14), 4);
+        assert_eq!(reg(&processor, 15), 8);
+
+        let halfwords = |offset| {
+            ram(&processor, offset, 16)
+                .chunks(2)
+                .map(|h| u16::from_le_bytes([h[0], h[1]]))
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(halfwords(0), [0, 0, 1, 2, 3, 4, 5, 6]);
+        assert_eq!(halfwords(16), [4, 5, 6, 7, 8, 0, 0, 0]);
+        assert_eq!(halfwords(32), [100, 1, 2, 3, 4, 5, 6, 7]);
+        assert_eq!(halfwords(48), [2, 3, 4, 5, 6, 7, 8, 100]);
+        assert_eq!(halfwords(64), [8, 7, 6, 5, 4, 3, 2, 1]);
+        assert_eq!(halfwords(80), [5, 6, 7, 8, 0xffff, 0xffff, 0xffff, 0xffff]);
+        assert_eq!(halfwords(96), [0, 0, 0, 0, 0, 1, 2, 3]);
+        assert_eq!(ram(&processor, 112, 16), [&[0x0f], &[0; 15][..]].concat());
+
+        // Whole register loads, moves & stores copy the first 2 registers unchanged
+        assert_eq!(ram(&processor, 128, 32), ram(&processor, 0, 32));
+    }
+
+    #[test]
+    fn vlen() {
+        let program = [
+            0x0c30_70d7, // vsetvli x1, x0, e8, m8, ta, ma
+            0xc220_2173, // csrrs x2, vlenb, x0
+            0x0d60_71d7, // vsetvli x3, x0, e32, mf4, ta, ma
+            0x0000_006f, // jal x0, 0
+        ];
+
+        let mut processor = processor(Box::new(RV64I), Zve32x::new(256), &program, &[]);
+        run(&mut processor).unwrap();
+        assert_eq!(reg(&processor, 1), 256);
+        assert_eq!(reg(&processor, 2), 32);
+        // SEW = 32 with LMUL = 1/4 is not supported with ELEN = 32
+        assert_eq!(reg(&processor, 3), 0);
+    }
+
+    #[test]
+    fn vill_registration_order() {
+        let vector = Zve32x::new(128);
+        let orders: [(&[&dyn Extension], u64); 4] = [
+            (&[&RV32I, &vector], 1 << 31),
+            (&[&vector, &RV32I], 1 << 31),
+            (&[&RV64I, &vector], 1 << 63),
+            (&[&vector, &RV64I], 1 << 63),
+        ];
+        for (extensions, vill) in orders {
+            let mut hart = test_utils::hart(extensions);
+            let vtype = hart.csrs.read(0xc21, PrivilegeLevel::Machine).unwrap();
+            assert_eq!(vtype, vill);
+        }
+    }
+
+    #[test]
+    #[should_panic(expected = "VLEN must be a power of 2")]
+    fn invalid_vlen() {
+        Zve32x::new(48);
+    }
+}